@@ -4,7 +4,7 @@ use bitvec::vec::BitVec;
 use criterion::*;
 use ordered_float::OrderedFloat;
 use risinglight::array::{
-    ArrayFromDataExt, ArrayImpl, BoolArray, DecimalArray, F64Array, I32Array,
+    ArrayFromDataExt, ArrayImpl, BoolArray, DataChunk, DecimalArray, F64Array, I32Array,
 };
 use risinglight::parser::BinaryOperator;
 use risinglight::types::DataType;
@@ -112,6 +112,15 @@ fn filter(c: &mut Criterion) {
     });
 }
 
+fn hash(c: &mut Criterion) {
+    for_all_size(c, "hash(i32,i32)", |b, &size| {
+        let chunk: DataChunk = [make_i32_array(size), make_i32_array(size)]
+            .into_iter()
+            .collect();
+        b.iter(|| chunk.hash_rows())
+    });
+}
+
 fn make_bool_array(size: usize) -> ArrayImpl {
     let mask = make_valid_bitmap(size);
     let iter = (0..size as i32)
@@ -170,5 +179,5 @@ fn for_all_size(
     group.finish();
 }
 
-criterion_group!(benches, ops, agg, cast, filter);
+criterion_group!(benches, ops, agg, cast, filter, hash);
 criterion_main!(benches);
@@ -0,0 +1,91 @@
+/*
+ * Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+ *
+ * C API of RisingLight.
+ *
+ * Example:
+ *
+ *     risinglight_db *db;
+ *     risinglight_result *result;
+ *     if (risinglight_open(NULL, &db) != RISINGLIGHT_OK) { ... }
+ *     if (risinglight_run(db, "select 1, 'a'", &result) != RISINGLIGHT_OK) {
+ *         fprintf(stderr, "%s\n", risinglight_errmsg(db));
+ *     }
+ *     while (risinglight_fetch(result) == RISINGLIGHT_ROW) {
+ *         const char *value = risinglight_value(result, 0);
+ *         printf("%s\n", value ? value : "NULL");
+ *     }
+ *     risinglight_result_free(result);
+ *     risinglight_close(db);
+ */
+
+#ifndef RISINGLIGHT_H
+#define RISINGLIGHT_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Result codes. */
+#define RISINGLIGHT_OK 0        /* Successful result */
+#define RISINGLIGHT_ERROR 1     /* The SQL statement failed */
+#define RISINGLIGHT_MISUSE 2    /* The API is used incorrectly */
+#define RISINGLIGHT_INTERNAL 3  /* The database panicked */
+#define RISINGLIGHT_ROW 100     /* risinglight_fetch() has another row ready */
+#define RISINGLIGHT_DONE 101    /* risinglight_fetch() has finished */
+
+/* A database connection. */
+typedef struct RisingLightDb risinglight_db;
+
+/* The output of a SQL statement. */
+typedef struct RisingLightResult risinglight_result;
+
+/*
+ * Opens a database stored in `path`, or an in-memory database if `path` is NULL.
+ * The database must be closed by risinglight_close().
+ */
+int risinglight_open(const char *path, risinglight_db **db);
+
+/* Closes a database. Data is flushed to disk before closing. */
+int risinglight_close(risinglight_db *db);
+
+/*
+ * Runs SQL statements. The output of the last statement is written to `*result`, which must be
+ * freed by risinglight_result_free(). If `result` is NULL, the output is discarded.
+ */
+int risinglight_run(risinglight_db *db, const char *sql, risinglight_result **result);
+
+/*
+ * Returns the message of the last error on the database, or NULL if the last call succeeded.
+ * The string is valid until the next call on the database.
+ */
+const char *risinglight_errmsg(const risinglight_db *db);
+
+/* Returns the number of columns in the result. */
+size_t risinglight_column_count(const risinglight_result *result);
+
+/* Returns the name of the i-th column, or NULL if `i` is out of range. */
+const char *risinglight_column_name(const risinglight_result *result, size_t i);
+
+/* Returns the number of rows in the result. */
+size_t risinglight_row_count(const risinglight_result *result);
+
+/* Advances to the next row. Returns RISINGLIGHT_ROW, or RISINGLIGHT_DONE if there are no more rows. */
+int risinglight_fetch(risinglight_result *result);
+
+/*
+ * Returns the value of the i-th column in the current row as a string, or NULL if the value is
+ * NULL. The string is valid until the result is freed.
+ */
+const char *risinglight_value(const risinglight_result *result, size_t i);
+
+/* Frees a result. */
+void risinglight_result_free(risinglight_result *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RISINGLIGHT_H */
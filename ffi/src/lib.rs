@@ -0,0 +1,377 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! C API of RisingLight.
+//!
+//! The API is declared in `include/risinglight.h`. Functions return [`RISINGLIGHT_OK`] on success
+//! or an error code on failure. The message of the last error on a database can be retrieved by
+//! [`risinglight_errmsg`].
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+use risinglight::array::Chunk;
+use risinglight::storage::SecondaryStorageOptions;
+use risinglight::types::DataValue;
+use risinglight::Database;
+use tokio::runtime::Runtime;
+
+/// Successful result.
+pub const RISINGLIGHT_OK: c_int = 0;
+/// The SQL statement failed.
+pub const RISINGLIGHT_ERROR: c_int = 1;
+/// The API is used incorrectly, e.g. a required pointer is NULL or a string is not UTF-8.
+pub const RISINGLIGHT_MISUSE: c_int = 2;
+/// The database panicked.
+pub const RISINGLIGHT_INTERNAL: c_int = 3;
+/// [`risinglight_fetch`] has another row ready.
+pub const RISINGLIGHT_ROW: c_int = 100;
+/// [`risinglight_fetch`] has finished.
+pub const RISINGLIGHT_DONE: c_int = 101;
+
+/// A database connection.
+pub struct RisingLightDb {
+    runtime: Runtime,
+    database: Database,
+    /// The message of the last error.
+    errmsg: Option<CString>,
+}
+
+/// The output of a SQL statement.
+pub struct RisingLightResult {
+    names: Vec<CString>,
+    /// Values are formatted as strings. NULL values are `None`.
+    rows: Vec<Vec<Option<CString>>>,
+    /// The index of the next row to fetch.
+    next: usize,
+}
+
+/// Opens a database stored in `path`, or an in-memory database if `path` is NULL.
+///
+/// The database is written to `*db`, which must be closed by [`risinglight_close`].
+///
+/// # Safety
+///
+/// `path` must be NULL or a valid C string. `db` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_open(
+    path: *const c_char,
+    db: *mut *mut RisingLightDb,
+) -> c_int {
+    if db.is_null() {
+        return RISINGLIGHT_MISUSE;
+    }
+    *db = ptr::null_mut();
+    let path = if path.is_null() {
+        None
+    } else {
+        match CStr::from_ptr(path).to_str() {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => return RISINGLIGHT_MISUSE,
+        }
+    };
+    let Ok(runtime) = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    else {
+        return RISINGLIGHT_INTERNAL;
+    };
+    let database = catch_unwind(AssertUnwindSafe(|| match path {
+        Some(path) => {
+            let mut options = SecondaryStorageOptions::default_for_cli();
+            options.path = path;
+            runtime.block_on(Database::new_on_disk(options))
+        }
+        None => Database::new_in_memory(),
+    }));
+    let Ok(database) = database else {
+        return RISINGLIGHT_INTERNAL;
+    };
+    *db = Box::into_raw(Box::new(RisingLightDb {
+        runtime,
+        database,
+        errmsg: None,
+    }));
+    RISINGLIGHT_OK
+}
+
+/// Closes a database opened by [`risinglight_open`]. Data is flushed to disk before closing.
+///
+/// # Safety
+///
+/// `db` must be NULL or returned by [`risinglight_open`], and must not be used after closing.
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_close(db: *mut RisingLightDb) -> c_int {
+    if db.is_null() {
+        return RISINGLIGHT_OK;
+    }
+    let db = Box::from_raw(db);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        db.runtime.block_on(db.database.shutdown())
+    }));
+    match result {
+        Ok(Ok(())) => RISINGLIGHT_OK,
+        Ok(Err(_)) => RISINGLIGHT_ERROR,
+        Err(_) => RISINGLIGHT_INTERNAL,
+    }
+}
+
+/// Runs SQL statements. The output of the last statement is written to `*result`, which must be
+/// freed by [`risinglight_result_free`].
+///
+/// On failure, `*result` is set to NULL and the message can be retrieved by
+/// [`risinglight_errmsg`].
+///
+/// # Safety
+///
+/// `db` must be returned by [`risinglight_open`]. `sql` must be a valid C string. `result` must be
+/// NULL or a valid pointer. If `result` is NULL, the output is discarded.
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_run(
+    db: *mut RisingLightDb,
+    sql: *const c_char,
+    result: *mut *mut RisingLightResult,
+) -> c_int {
+    if db.is_null() || sql.is_null() {
+        return RISINGLIGHT_MISUSE;
+    }
+    let db = &mut *db;
+    if !result.is_null() {
+        *result = ptr::null_mut();
+    }
+    let Ok(sql) = CStr::from_ptr(sql).to_str() else {
+        db.set_errmsg("SQL is not a valid UTF-8 string");
+        return RISINGLIGHT_MISUSE;
+    };
+    let output = catch_unwind(AssertUnwindSafe(|| {
+        db.runtime.block_on(db.database.run(sql))
+    }));
+    let chunks = match output {
+        Ok(Ok(chunks)) => chunks,
+        Ok(Err(e)) => {
+            db.set_errmsg(&e.to_string());
+            return RISINGLIGHT_ERROR;
+        }
+        Err(_) => {
+            db.set_errmsg("internal error");
+            return RISINGLIGHT_INTERNAL;
+        }
+    };
+    db.errmsg = None;
+    if !result.is_null() {
+        let output = chunks
+            .last()
+            .map_or_else(RisingLightResult::empty, RisingLightResult::new);
+        *result = Box::into_raw(Box::new(output));
+    }
+    RISINGLIGHT_OK
+}
+
+/// Returns the message of the last error on the database, or NULL if the last call succeeded.
+///
+/// The string is valid until the next call on the database.
+///
+/// # Safety
+///
+/// `db` must be NULL or returned by [`risinglight_open`].
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_errmsg(db: *const RisingLightDb) -> *const c_char {
+    match db.as_ref().and_then(|db| db.errmsg.as_ref()) {
+        Some(msg) => msg.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Returns the number of columns in the result.
+///
+/// # Safety
+///
+/// `result` must be returned by [`risinglight_run`].
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_column_count(result: *const RisingLightResult) -> usize {
+    result.as_ref().map_or(0, |r| r.names.len())
+}
+
+/// Returns the name of the `i`-th column, or NULL if `i` is out of range.
+///
+/// The string is valid until the result is freed.
+///
+/// # Safety
+///
+/// `result` must be returned by [`risinglight_run`].
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_column_name(
+    result: *const RisingLightResult,
+    i: usize,
+) -> *const c_char {
+    match result.as_ref().and_then(|r| r.names.get(i)) {
+        Some(name) => name.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Returns the number of rows in the result.
+///
+/// # Safety
+///
+/// `result` must be returned by [`risinglight_run`].
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_row_count(result: *const RisingLightResult) -> usize {
+    result.as_ref().map_or(0, |r| r.rows.len())
+}
+
+/// Advances to the next row of the result.
+///
+/// Returns [`RISINGLIGHT_ROW`] if a row is ready to be read by [`risinglight_value`], or
+/// [`RISINGLIGHT_DONE`] if there are no more rows.
+///
+/// # Safety
+///
+/// `result` must be returned by [`risinglight_run`].
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_fetch(result: *mut RisingLightResult) -> c_int {
+    let Some(result) = result.as_mut() else {
+        return RISINGLIGHT_MISUSE;
+    };
+    if result.next >= result.rows.len() {
+        return RISINGLIGHT_DONE;
+    }
+    result.next += 1;
+    RISINGLIGHT_ROW
+}
+
+/// Returns the value of the `i`-th column in the current row as a string.
+///
+/// Returns NULL if the value is NULL, no row has been fetched, or `i` is out of range.
+/// The string is valid until the result is freed.
+///
+/// # Safety
+///
+/// `result` must be returned by [`risinglight_run`].
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_value(
+    result: *const RisingLightResult,
+    i: usize,
+) -> *const c_char {
+    let value = result.as_ref().and_then(|r| {
+        let row = r.rows.get(r.next.checked_sub(1)?)?;
+        row.get(i)?.as_ref()
+    });
+    match value {
+        Some(value) => value.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Frees a result returned by [`risinglight_run`].
+///
+/// # Safety
+///
+/// `result` must be NULL or returned by [`risinglight_run`], and must not be used after freeing.
+#[no_mangle]
+pub unsafe extern "C" fn risinglight_result_free(result: *mut RisingLightResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+impl RisingLightDb {
+    fn set_errmsg(&mut self, msg: &str) {
+        self.errmsg = Some(to_cstring(msg));
+    }
+}
+
+impl RisingLightResult {
+    fn new(chunk: &Chunk) -> Self {
+        let names = match (chunk.schema(), chunk.header()) {
+            (Some(schema), _) => schema.iter().map(|(name, _)| to_cstring(name)).collect(),
+            (None, Some(header)) => header.iter().map(|name| to_cstring(name)).collect(),
+            (None, None) => (0..chunk.data_chunks().first().map_or(0, |c| c.column_count()))
+                .map(|i| to_cstring(&format!("column{i}")))
+                .collect(),
+        };
+        let mut rows = vec![];
+        for data_chunk in chunk.data_chunks() {
+            for row in data_chunk.rows() {
+                rows.push(
+                    row.values()
+                        .map(|value| match value {
+                            DataValue::Null => None,
+                            DataValue::String(s) => Some(to_cstring(&s)),
+                            value => Some(to_cstring(&value.to_string())),
+                        })
+                        .collect(),
+                );
+            }
+        }
+        RisingLightResult {
+            names,
+            rows,
+            next: 0,
+        }
+    }
+
+    fn empty() -> Self {
+        RisingLightResult {
+            names: vec![],
+            rows: vec![],
+            next: 0,
+        }
+    }
+}
+
+/// Converts a string to a C string, removing NUL characters in it.
+fn to_cstring(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str> {
+        (!s.is_null()).then(|| CStr::from_ptr(s).to_str().unwrap())
+    }
+
+    #[test]
+    fn test_run_and_fetch() {
+        unsafe {
+            let mut db = ptr::null_mut();
+            assert_eq!(risinglight_open(ptr::null(), &mut db), RISINGLIGHT_OK);
+
+            let sql = c"create table t(a int, b string); insert into t values (1, 'x'), (2, null);";
+            assert_eq!(
+                risinglight_run(db, sql.as_ptr(), ptr::null_mut()),
+                RISINGLIGHT_OK
+            );
+
+            let mut result = ptr::null_mut();
+            let sql = c"select a, b from t order by a";
+            assert_eq!(
+                risinglight_run(db, sql.as_ptr(), &mut result),
+                RISINGLIGHT_OK
+            );
+            assert_eq!(risinglight_column_count(result), 2);
+            assert_eq!(to_str(risinglight_column_name(result, 0)), Some("a"));
+            assert_eq!(risinglight_row_count(result), 2);
+            assert_eq!(risinglight_fetch(result), RISINGLIGHT_ROW);
+            assert_eq!(to_str(risinglight_value(result, 0)), Some("1"));
+            assert_eq!(to_str(risinglight_value(result, 1)), Some("x"));
+            assert_eq!(risinglight_fetch(result), RISINGLIGHT_ROW);
+            assert_eq!(to_str(risinglight_value(result, 1)), None);
+            assert_eq!(risinglight_fetch(result), RISINGLIGHT_DONE);
+            risinglight_result_free(result);
+
+            let sql = c"select * from t2";
+            assert_eq!(
+                risinglight_run(db, sql.as_ptr(), &mut result),
+                RISINGLIGHT_ERROR
+            );
+            assert!(result.is_null());
+            assert!(to_str(risinglight_errmsg(db)).unwrap().contains("t2"));
+
+            assert_eq!(risinglight_close(db), RISINGLIGHT_OK);
+        }
+    }
+}
@@ -4,4 +4,7 @@ extern crate prost_build;
 
 fn main() {
     prost_build::compile_protos(&["src/proto/rowset.proto"], &["src/proto"]).unwrap();
+    tonic_build::configure()
+        .compile(&["src/proto/query.proto"], &["src/proto"])
+        .unwrap();
 }
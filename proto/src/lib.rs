@@ -5,3 +5,7 @@
 pub mod rowset {
     include!(concat!(env!("OUT_DIR"), "/risinglight.rowset.rs"));
 }
+
+pub mod query {
+    include!(concat!(env!("OUT_DIR"), "/risinglight.query.rs"));
+}
@@ -0,0 +1,523 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Conversion between RisingLight arrays and Apache Arrow arrays.
+
+use arrow::array::{Array as _, ArrayRef, AsArray, BooleanArray};
+use arrow::buffer::{BooleanBuffer, Buffer, NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow::datatypes::{
+    ArrowPrimitiveType, DataType as ArrowType, Date32Type, Decimal128Type, Field, Float64Type,
+    Int16Type, Int32Type, Int64Type, IntervalMonthDayNanoType, IntervalUnit, Schema, SchemaRef,
+    TimeUnit, TimestampMicrosecondType,
+};
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+
+use super::buffer::ValueBuffer;
+use super::*;
+use crate::types::{BlobRef, NativeType};
+
+/// The scale of decimals in Arrow if it is not specified.
+const DEFAULT_DECIMAL_SCALE: i8 = 10;
+
+/// The timezone of `TIMESTAMP WITH TIME ZONE` values in Arrow.
+const UTC: &str = "+00:00";
+
+impl DataType {
+    /// Returns the data type of arrays converted from Arrow arrays of the given type.
+    pub fn from_arrow(ty: &ArrowType) -> Result<Self, ConvertError> {
+        Ok(match ty {
+            ArrowType::Null => Self::Null,
+            ArrowType::Boolean => Self::Bool,
+            ArrowType::Int8 | ArrowType::Int16 | ArrowType::UInt8 => Self::Int16,
+            ArrowType::Int32 | ArrowType::UInt16 => Self::Int32,
+            ArrowType::Int64 | ArrowType::UInt32 => Self::Int64,
+            ArrowType::Float16 | ArrowType::Float32 | ArrowType::Float64 => Self::Float64,
+            ArrowType::Decimal128(p, s) if *s >= 0 => Self::Decimal(Some(*p), Some(*s as u8)),
+            ArrowType::Date32 | ArrowType::Date64 => Self::Date,
+            ArrowType::Timestamp(_, None) => Self::Timestamp,
+            ArrowType::Timestamp(_, Some(_)) => Self::TimestampTz,
+            ArrowType::Utf8 | ArrowType::LargeUtf8 => Self::String,
+            ArrowType::Binary | ArrowType::LargeBinary | ArrowType::FixedSizeBinary(_) => {
+                Self::Blob
+            }
+            ArrowType::Interval(IntervalUnit::MonthDayNano) => Self::Interval,
+            _ => return Err(ConvertError::FromArrow(format!("unsupported type {ty}"))),
+        })
+    }
+
+    /// Returns the type of Arrow arrays converted from arrays of this type.
+    pub fn to_arrow(&self) -> Result<ArrowType, ConvertError> {
+        Ok(match self {
+            Self::Null => ArrowType::Null,
+            Self::Bool => ArrowType::Boolean,
+            Self::Int16 => ArrowType::Int16,
+            Self::Int32 => ArrowType::Int32,
+            Self::Int64 => ArrowType::Int64,
+            Self::Float64 => ArrowType::Float64,
+            Self::Decimal(Some(p), s) => ArrowType::Decimal128(*p, s.unwrap_or(0) as i8),
+            Self::Decimal(None, s) => ArrowType::Decimal128(
+                arrow::datatypes::DECIMAL128_MAX_PRECISION,
+                s.map_or(DEFAULT_DECIMAL_SCALE, |s| s as i8),
+            ),
+            Self::Date => ArrowType::Date32,
+            Self::Timestamp => ArrowType::Timestamp(TimeUnit::Microsecond, None),
+            Self::TimestampTz => ArrowType::Timestamp(TimeUnit::Microsecond, Some(UTC.into())),
+            Self::Interval => ArrowType::Interval(IntervalUnit::MonthDayNano),
+            Self::String | Self::Json => ArrowType::Utf8,
+            Self::Blob => ArrowType::Binary,
+            Self::List(ty) => ArrowType::List(Arc::new(Field::new("item", ty.to_arrow()?, true))),
+            Self::Struct(_) => {
+                return Err(ConvertError::ToArrow(format!("unsupported type {self}")));
+            }
+        })
+    }
+}
+
+impl ArrayImpl {
+    /// Converts an Arrow array into an array of type [`DataType::from_arrow`].
+    pub fn from_arrow(array: &dyn arrow::array::Array) -> Result<Self, ConvertError> {
+        let ty = DataType::from_arrow(array.data_type())?;
+        // normalize the array to one arrow type for each data type
+        let normalized_type = match array.data_type() {
+            ArrowType::Int8 | ArrowType::UInt8 => ArrowType::Int16,
+            ArrowType::UInt16 => ArrowType::Int32,
+            ArrowType::UInt32 => ArrowType::Int64,
+            ArrowType::Float16 | ArrowType::Float32 => ArrowType::Float64,
+            ArrowType::Date64 => ArrowType::Date32,
+            ArrowType::Timestamp(_, tz) => ArrowType::Timestamp(TimeUnit::Microsecond, tz.clone()),
+            ArrowType::LargeUtf8 => ArrowType::Utf8,
+            ArrowType::LargeBinary | ArrowType::FixedSizeBinary(_) => ArrowType::Binary,
+            ty => ty.clone(),
+        };
+        let array = arrow::compute::cast(array, &normalized_type)
+            .map_err(|e| ConvertError::FromArrow(e.to_string()))?;
+
+        Ok(match ty {
+            DataType::Null => Self::new_null((0..array.len()).map(|_| None::<()>).collect()),
+            DataType::Bool => Self::new_bool(array.as_boolean().iter().collect()),
+            // SAFETY: the types have the same memory layout
+            DataType::Int16 => Self::new_int16(unsafe {
+                from_arrow_primitive::<_, Int16Type>(array.as_primitive())
+            }),
+            DataType::Int32 => Self::new_int32(unsafe {
+                from_arrow_primitive::<_, Int32Type>(array.as_primitive())
+            }),
+            DataType::Int64 => Self::new_int64(unsafe {
+                from_arrow_primitive::<_, Int64Type>(array.as_primitive())
+            }),
+            DataType::Float64 => Self::new_float64(unsafe {
+                from_arrow_primitive::<_, Float64Type>(array.as_primitive())
+            }),
+            DataType::Decimal(_, scale) => {
+                let scale = scale.unwrap_or(0) as u32;
+                Self::new_decimal(
+                    (array.as_primitive::<Decimal128Type>().iter())
+                        .map(|v| {
+                            v.map(|v| Decimal::try_from_i128_with_scale(v, scale))
+                                .transpose()
+                        })
+                        .collect::<Result<_, _>>()
+                        .map_err(|e| ConvertError::FromArrow(e.to_string()))?,
+                )
+            }
+            DataType::Date => Self::new_date(unsafe {
+                from_arrow_primitive::<_, Date32Type>(array.as_primitive())
+            }),
+            DataType::Timestamp => Self::new_timestamp(
+                (array.as_primitive::<TimestampMicrosecondType>().iter())
+                    .map(|v| v.map(Timestamp::from_unix_micros))
+                    .collect(),
+            ),
+            DataType::TimestampTz => Self::new_timestamp_tz(
+                (array.as_primitive::<TimestampMicrosecondType>().iter())
+                    .map(|v| v.map(TimestampTz::from_unix_micros))
+                    .collect(),
+            ),
+            DataType::String => Self::new_string(array.as_string::<i32>().iter().collect()),
+            DataType::Blob => {
+                let mut builder = BlobArrayBuilder::with_capacity(array.len());
+                for v in array.as_binary::<i32>().iter() {
+                    builder.push(v.map(BlobRef::new));
+                }
+                Self::new_blob(builder.finish())
+            }
+            DataType::Interval => Self::new_interval(
+                (array.as_primitive::<IntervalMonthDayNanoType>().iter())
+                    .map(|v| {
+                        v.map(|v| {
+                            let (months, days, nanos) = IntervalMonthDayNanoType::to_parts(v);
+                            Interval::new(months, days, (nanos / 1_000_000) as i32)
+                        })
+                    })
+                    .collect(),
+            ),
+            _ => unreachable!("unexpected type {ty}"),
+        })
+    }
+
+    /// Converts the array into an Arrow array of type [`DataType::to_arrow`].
+    ///
+    /// `ty` is the type of this array.
+    pub fn to_arrow(&self, ty: &DataType) -> Result<ArrayRef, ConvertError> {
+        use arrow::array as arrow_array;
+
+        Ok(match self {
+            Self::Null(a) => Arc::new(arrow_array::NullArray::new(a.len())),
+            Self::Bool(a) => Arc::new(arrow_array::BooleanArray::from_iter(
+                a.iter().map(|v| v.copied()),
+            )),
+            // SAFETY: the types have the same memory layout
+            Self::Int16(a) => Arc::new(unsafe { to_arrow_primitive::<_, Int16Type>(a) }),
+            Self::Int32(a) => Arc::new(unsafe { to_arrow_primitive::<_, Int32Type>(a) }),
+            Self::Int64(a) => Arc::new(unsafe { to_arrow_primitive::<_, Int64Type>(a) }),
+            Self::Float64(a) => Arc::new(unsafe { to_arrow_primitive::<_, Float64Type>(a) }),
+            Self::Decimal(a) => {
+                let ArrowType::Decimal128(precision, scale) = ty.to_arrow()? else {
+                    return Err(ConvertError::ToArrow(format!("{ty} is not decimal")));
+                };
+                let array = arrow_array::Decimal128Array::from_iter(a.iter().map(|v| {
+                    v.map(|d| {
+                        let mut d = *d;
+                        d.rescale(scale as u32);
+                        d.mantissa()
+                    })
+                }));
+                Arc::new(
+                    (array.with_precision_and_scale(precision, scale))
+                        .map_err(|e| ConvertError::ToArrow(e.to_string()))?,
+                )
+            }
+            Self::Date(a) => Arc::new(unsafe { to_arrow_primitive::<_, Date32Type>(a) }),
+            Self::Timestamp(a) => Arc::new(arrow_array::TimestampMicrosecondArray::from_iter(
+                a.iter().map(|v| v.map(|v| v.to_unix_micros())),
+            )),
+            Self::TimestampTz(a) => Arc::new(
+                arrow_array::TimestampMicrosecondArray::from_iter(
+                    a.iter().map(|v| v.map(|v| v.to_unix_micros())),
+                )
+                .with_timezone(UTC),
+            ),
+            Self::Interval(a) => Arc::new(arrow_array::IntervalMonthDayNanoArray::from_iter(
+                a.iter().map(|v| {
+                    v.map(|v| {
+                        IntervalMonthDayNanoType::make_value(
+                            v.num_months(),
+                            v.days(),
+                            v.num_milliseconds() as i64 * 1_000_000,
+                        )
+                    })
+                }),
+            )),
+            Self::String(a) => Arc::new(arrow_array::StringArray::from_iter(a.iter())),
+            Self::Blob(a) => Arc::new(arrow_array::BinaryArray::from_iter(a.iter())),
+            // JSON values are exported as text
+            Self::Json(a) => Arc::new(arrow_array::StringArray::from_iter(
+                a.iter().map(|v| v.map(|v| v.to_string())),
+            )),
+            Self::List(a) => {
+                let DataType::List(elem_type) = ty else {
+                    return Err(ConvertError::ToArrow(format!("{ty} is not list")));
+                };
+                // flatten elements of all lists into one array
+                let mut offsets = Vec::with_capacity(a.len() + 1);
+                let mut builder = ArrayBuilderImpl::with_capacity(a.len(), elem_type);
+                let mut len = 0;
+                offsets.push(0);
+                for list in a.iter() {
+                    if let Some(list) = list {
+                        for value in list.iter() {
+                            builder.push(value);
+                        }
+                        len += list.len();
+                    }
+                    offsets.push(len as i32);
+                }
+                let field = Field::new("item", elem_type.to_arrow()?, true);
+                let nulls = NullBuffer::from_iter(a.iter().map(|v| v.is_some()));
+                Arc::new(
+                    arrow_array::ListArray::try_new(
+                        Arc::new(field),
+                        OffsetBuffer::new(ScalarBuffer::from(offsets)),
+                        builder.finish().to_arrow(elem_type)?,
+                        Some(nulls),
+                    )
+                    .map_err(|e| ConvertError::ToArrow(e.to_string()))?,
+                )
+            }
+        })
+    }
+}
+
+impl ArrayImpl {
+    /// Filters the array with the Arrow kernel.
+    ///
+    /// Returns `None` if the values of the array can not be shared with Arrow.
+    pub(super) fn filter_by_arrow(&self, visibility: &[bool]) -> Option<Self> {
+        let predicate = BooleanArray::from(visibility.to_vec());
+        // SAFETY: the types have the same memory layout
+        Some(match self {
+            Self::Int16(a) => Self::new_int16(unsafe { filter::<_, Int16Type>(a, &predicate) }),
+            Self::Int32(a) => Self::new_int32(unsafe { filter::<_, Int32Type>(a, &predicate) }),
+            Self::Int64(a) => Self::new_int64(unsafe { filter::<_, Int64Type>(a, &predicate) }),
+            Self::Float64(a) => {
+                Self::new_float64(unsafe { filter::<_, Float64Type>(a, &predicate) })
+            }
+            Self::Date(a) => Self::new_date(unsafe { filter::<_, Date32Type>(a, &predicate) }),
+            _ => return None,
+        })
+    }
+}
+
+/// Converts a primitive array into an Arrow array. The values buffer is shared.
+///
+/// # Safety
+///
+/// `T` must have the same memory layout as `A::Native`.
+unsafe fn to_arrow_primitive<T: NativeType, A: ArrowPrimitiveType>(
+    array: &PrimitiveArray<T>,
+) -> arrow::array::PrimitiveArray<A> {
+    assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<A::Native>());
+    let values = ScalarBuffer::new(array.raw_buffer().to_arrow(), 0, array.len());
+    arrow::array::PrimitiveArray::new(values, to_null_buffer(array.get_valid_bitmap()))
+}
+
+/// Converts an Arrow array into a primitive array. The values buffer is shared.
+///
+/// # Safety
+///
+/// `T` must have the same memory layout as `A::Native`.
+unsafe fn from_arrow_primitive<T: NativeType, A: ArrowPrimitiveType>(
+    array: &arrow::array::PrimitiveArray<A>,
+) -> PrimitiveArray<T> {
+    assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<A::Native>());
+    let values = ValueBuffer::from_arrow(array.values().inner().clone());
+    PrimitiveArray::from_raw_parts(values, from_null_buffer(array.nulls(), array.len()))
+}
+
+/// Filters a primitive array with the Arrow kernel.
+///
+/// # Safety
+///
+/// `T` must have the same memory layout as `A::Native`.
+unsafe fn filter<T: NativeType, A: ArrowPrimitiveType>(
+    array: &PrimitiveArray<T>,
+    predicate: &BooleanArray,
+) -> PrimitiveArray<T> {
+    let array = to_arrow_primitive::<T, A>(array);
+    let filtered = arrow::compute::filter(&array, predicate).expect("failed to filter");
+    from_arrow_primitive::<T, A>(filtered.as_primitive())
+}
+
+/// Converts a validity bitmap into an Arrow null buffer. Returns `None` if there is no null.
+fn to_null_buffer(valid: &BitVec) -> Option<NullBuffer> {
+    if valid.all() {
+        return None;
+    }
+    // the bit order is the same as Arrow, as long as words are written in little endian
+    let bytes: Vec<u8> = (valid.as_raw_slice().iter())
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    let buffer = BooleanBuffer::new(Buffer::from_vec(bytes), 0, valid.len());
+    Some(NullBuffer::new(buffer))
+}
+
+/// Converts an Arrow null buffer into a validity bitmap.
+fn from_null_buffer(nulls: Option<&NullBuffer>, len: usize) -> BitVec {
+    let Some(nulls) = nulls else {
+        return BitVec::repeat(true, len);
+    };
+    if nulls.offset() % 8 != 0 {
+        return nulls.iter().collect();
+    }
+    const WORD_SIZE: usize = std::mem::size_of::<usize>();
+    let words = (nulls.validity()[nulls.offset() / 8..].chunks(WORD_SIZE))
+        .map(|bytes| {
+            let mut word = [0; WORD_SIZE];
+            word[..bytes.len()].copy_from_slice(bytes);
+            usize::from_le_bytes(word)
+        })
+        .collect();
+    let mut valid = BitVec::from_vec(words);
+    valid.truncate(len);
+    valid
+}
+
+impl DataChunk {
+    /// Converts the chunk into an Arrow record batch.
+    ///
+    /// `types` are the types of columns in `schema`.
+    pub fn to_record_batch(
+        &self,
+        schema: SchemaRef,
+        types: &[DataType],
+    ) -> Result<RecordBatch, ConvertError> {
+        let columns = (self.arrays().iter().zip(types))
+            .map(|(array, ty)| array.to_arrow(ty))
+            .collect::<Result<Vec<_>, ConvertError>>()?;
+        let options = RecordBatchOptions::new().with_row_count(Some(self.cardinality()));
+        RecordBatch::try_new_with_options(schema, columns, &options)
+            .map_err(|e| ConvertError::ToArrow(e.to_string()))
+    }
+}
+
+impl Chunk {
+    /// Returns the Arrow schema of the chunk.
+    ///
+    /// Returns an error if the chunk is not the output of a query.
+    pub fn arrow_schema(&self) -> Result<SchemaRef, ConvertError> {
+        let Some(schema) = self.schema() else {
+            return Err(ConvertError::ToArrow("unknown schema".into()));
+        };
+        let fields = (schema.iter())
+            .map(|(name, ty)| Ok(Field::new(name, ty.to_arrow()?, true)))
+            .collect::<Result<Vec<_>, ConvertError>>()?;
+        Ok(Arc::new(Schema::new(fields)))
+    }
+
+    /// Converts the chunk into Arrow record batches, one for each data chunk.
+    pub fn to_record_batches(&self) -> Result<Vec<RecordBatch>, ConvertError> {
+        let schema = self.arrow_schema()?;
+        let types: Vec<_> = (self.schema().unwrap().iter())
+            .map(|(_, ty)| ty.clone())
+            .collect();
+        (self.data_chunks().iter())
+            .map(|chunk| chunk.to_record_batch(schema.clone(), &types))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{ArrayRef, Date32Array, Int8Array, LargeStringArray, TimestampSecondArray};
+
+    use super::*;
+    use crate::types::List;
+
+    #[test]
+    fn test_from_arrow() {
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(Int8Array::from(vec![Some(1), None])),
+            Arc::new(LargeStringArray::from(vec![Some("a"), Some("b")])),
+            Arc::new(Date32Array::from(vec![None, Some(1)])),
+            Arc::new(TimestampSecondArray::from(vec![Some(0), Some(1)])),
+        ];
+        let actual: DataChunk = arrays
+            .iter()
+            .map(|a| ArrayImpl::from_arrow(a.as_ref()).unwrap())
+            .collect();
+        let expected: DataChunk = [
+            ArrayImpl::new_int16([Some(1), None].into_iter().collect()),
+            ArrayImpl::new_string(["a", "b"].iter().map(Some).collect()),
+            ArrayImpl::new_date([None, Some(Date::new(1))].into_iter().collect()),
+            ArrayImpl::new_timestamp(
+                [0, 1_000_000]
+                    .into_iter()
+                    .map(Timestamp::from_unix_micros)
+                    .collect(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_to_arrow() {
+        let arrays = [
+            (
+                DataType::Int32,
+                ArrayImpl::new_int32([Some(1), None].into_iter().collect()),
+            ),
+            (
+                DataType::TimestampTz,
+                ArrayImpl::new_timestamp_tz(
+                    [Some(TimestampTz::from_unix_micros(1)), None]
+                        .into_iter()
+                        .collect(),
+                ),
+            ),
+            (
+                DataType::Interval,
+                ArrayImpl::new_interval([Some(Interval::new(1, 2, 3)), None].into_iter().collect()),
+            ),
+        ];
+        for (ty, array) in arrays {
+            let arrow_array = array.to_arrow(&ty).unwrap();
+            assert_eq!(arrow_array.data_type(), &ty.to_arrow().unwrap());
+            assert_eq!(ArrayImpl::from_arrow(arrow_array.as_ref()).unwrap(), array);
+        }
+    }
+
+    #[test]
+    fn test_share_values_with_arrow() {
+        let array = ArrayImpl::new_int64([Some(1), None, Some(3)].into_iter().collect());
+        let arrow_array = array.to_arrow(&DataType::Int64).unwrap();
+        let ArrayImpl::Int64(a) = &array else {
+            unreachable!()
+        };
+        let values = arrow_array.as_primitive::<Int64Type>().values();
+        assert_eq!(values.as_ptr(), a.raw_data().as_ptr());
+
+        let converted = ArrayImpl::from_arrow(arrow_array.as_ref()).unwrap();
+        let ArrayImpl::Int64(b) = &converted else {
+            unreachable!()
+        };
+        assert_eq!(b.raw_data().as_ptr(), a.raw_data().as_ptr());
+        assert_eq!(converted, array);
+
+        let filtered = array.filter(&[false, true, true]);
+        let expected = ArrayImpl::new_int64([None, Some(3)].into_iter().collect());
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn test_decimal_and_list_to_arrow() {
+        let ty = DataType::Decimal(Some(10), Some(2));
+        let array =
+            ArrayImpl::new_decimal([Some("1.5".parse().unwrap()), None].into_iter().collect());
+        let arrow_array = array.to_arrow(&ty).unwrap();
+        let arrow_array = arrow_array.as_primitive::<Decimal128Type>();
+        assert_eq!(arrow_array.value(0), 150);
+        assert!(arrow_array.is_null(1));
+
+        let ty = DataType::List(Box::new(DataType::Int32));
+        let list: List = [1, 2].map(DataValue::Int32).into_iter().collect();
+        let array = ArrayImpl::new_list([Some(list), None].into_iter().collect());
+        let arrow_array = array.to_arrow(&ty).unwrap();
+        let arrow_array = arrow_array.as_list::<i32>();
+        assert_eq!(arrow_array.value_offsets(), &[0, 2, 2]);
+        assert!(arrow_array.is_null(1));
+        let values = arrow_array.values().as_primitive::<Int32Type>();
+        assert_eq!(values.values(), &[1, 2]);
+    }
+
+    #[test]
+    fn test_chunk_to_record_batches() {
+        let data_chunk: DataChunk = [
+            ArrayImpl::new_int32([1, 2].into_iter().map(Some).collect()),
+            ArrayImpl::new_string(["a", "b"].iter().map(Some).collect()),
+        ]
+        .into_iter()
+        .collect();
+        let mut chunk = Chunk::new(vec![data_chunk.clone(), data_chunk]);
+        assert!(chunk.to_record_batches().is_err());
+
+        chunk.set_schema(vec![
+            ("a".into(), DataType::Int32),
+            ("b".into(), DataType::String),
+        ]);
+        let batches = chunk.to_record_batches().unwrap();
+        assert_eq!(batches.len(), 2);
+        let schema = batches[0].schema();
+        assert_eq!(schema.field(0).name(), "a");
+        assert_eq!(schema.field(1).data_type(), &ArrowType::Utf8);
+        assert_eq!(batches[1].num_rows(), 2);
+    }
+
+    #[test]
+    fn test_unsupported_arrow_type() {
+        let ty = ArrowType::Time32(TimeUnit::Second);
+        assert!(DataType::from_arrow(&ty).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! The values buffer of primitive arrays.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::types::NativeType;
+
+/// An immutable buffer of values, stored in an Arrow [`Buffer`](arrow::buffer::Buffer).
+///
+/// Values are laid out contiguously as in a `[T]`, which is also the layout of the values buffer
+/// of Arrow primitive arrays, if `T` is a native type of Arrow or a transparent wrapper of one.
+/// So the buffer can be shared with Arrow arrays without copying.
+#[derive(Clone)]
+pub struct ValueBuffer<T: NativeType> {
+    buffer: arrow::buffer::Buffer,
+    /// The number of values. It can not be derived from the buffer if `T` is zero-sized.
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: NativeType> ValueBuffer<T> {
+    /// Creates a buffer from an Arrow buffer without copying.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must contain valid values of `T`.
+    pub unsafe fn from_arrow(buffer: arrow::buffer::Buffer) -> Self {
+        let size = std::mem::size_of::<T>();
+        assert!(
+            size != 0 && buffer.len() % size == 0,
+            "invalid buffer length"
+        );
+        assert!(
+            buffer.as_ptr().align_offset(std::mem::align_of::<T>()) == 0,
+            "unaligned buffer"
+        );
+        Self {
+            len: buffer.len() / size,
+            buffer,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the Arrow buffer of values.
+    pub fn to_arrow(&self) -> arrow::buffer::Buffer {
+        self.buffer.clone()
+    }
+}
+
+impl<T: NativeType> From<Vec<T>> for ValueBuffer<T> {
+    fn from(values: Vec<T>) -> Self {
+        let values = values.into_boxed_slice();
+        let len = values.len();
+        let ptr = NonNull::new(values.as_ptr() as *mut u8).unwrap();
+        // SAFETY: the allocation is owned by the buffer and never moved
+        let buffer = unsafe {
+            arrow::buffer::Buffer::from_custom_allocation(
+                ptr,
+                std::mem::size_of_val(&*values),
+                Arc::new(values),
+            )
+        };
+        Self {
+            buffer,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: NativeType> FromIterator<T> for ValueBuffer<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<T>>().into()
+    }
+}
+
+impl<T: NativeType> Deref for ValueBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: the buffer contains `len` aligned values of `T`
+        unsafe { std::slice::from_raw_parts(self.buffer.as_ptr().cast(), self.len) }
+    }
+}
+
+impl<T: NativeType> fmt::Debug for ValueBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.deref(), f)
+    }
+}
+
+impl<T: NativeType> PartialEq for ValueBuffer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
+impl<T: NativeType + Eq> Eq for ValueBuffer<T> {}
+
+impl<T: NativeType> PartialOrd for ValueBuffer<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.deref().partial_cmp(other.deref())
+    }
+}
+
+impl<T: NativeType + Ord> Ord for ValueBuffer<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+impl<T: NativeType + Hash> Hash for ValueBuffer<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+impl<T: NativeType + Serialize> Serialize for ValueBuffer<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.deref().serialize(serializer)
+    }
+}
+
+impl<'de, T: NativeType + Deserialize<'de>> Deserialize<'de> for ValueBuffer<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<T>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_with_arrow() {
+        let buffer = ValueBuffer::from(vec![1i32, 2, 3]);
+        let arrow_buffer = buffer.to_arrow();
+        assert_eq!(arrow_buffer.as_ptr(), buffer.as_ptr().cast());
+        assert_eq!(arrow_buffer.typed_data::<i32>(), &[1, 2, 3]);
+
+        let buffer = unsafe { ValueBuffer::<i32>::from_arrow(arrow_buffer) };
+        assert_eq!(&*buffer, &[1, 2, 3]);
+
+        let nulls: ValueBuffer<()> = std::iter::repeat(()).take(5).collect();
+        assert_eq!(nulls.len(), 5);
+    }
+}
@@ -9,7 +9,7 @@ use bitvec::vec::BitVec;
 use serde::{Deserialize, Serialize};
 
 use super::{Array, ArrayBuilder, ArrayEstimateExt, ArrayFromDataExt, ArrayValidExt};
-use crate::types::BlobRef;
+use crate::types::{BlobRef, JsonbRef};
 
 /// A collection of variable-length values.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -37,10 +37,18 @@ impl ValueRef for BlobRef {
     }
 }
 
+impl ValueRef for JsonbRef {
+    fn from_bytes(s: &[u8]) -> &Self {
+        JsonbRef::new(s)
+    }
+}
+
 pub type StringArray = BytesArray<str>;
 pub type BlobArray = BytesArray<BlobRef>;
+pub type JsonArray = BytesArray<JsonbRef>;
 pub type StringArrayBuilder = BytesArrayBuilder<str>;
 pub type BlobArrayBuilder = BytesArrayBuilder<BlobRef>;
+pub type JsonArrayBuilder = BytesArrayBuilder<JsonbRef>;
 
 impl<T: ValueRef + ?Sized> Clone for BytesArray<T> {
     fn clone(&self) -> Self {
@@ -1,6 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::ops::RangeBounds;
 use std::sync::Arc;
 
@@ -12,10 +13,17 @@ use crate::types::{DataValue, Row};
 /// A data chunk is a horizontal subset of a query result.
 ///
 /// Note: It's valid for a [`DataChunk`] to have 0 column, but non-zero cardinality.
+///
+/// A chunk may carry a visibility, so that a filter can mark rows as invisible instead of
+/// copying the visible ones into new arrays. Such chunks are only passed between executors aware
+/// of the visibility, and [`compact`](Self::compact)ed before any other use. The cardinality
+/// includes invisible rows.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DataChunk {
     arrays: Arc<[ArrayImpl]>,
     cardinality: usize,
+    /// Whether each row is visible. All rows are visible if `None`.
+    visibility: Option<Arc<[bool]>>,
 }
 
 impl FromIterator<ArrayImpl> for DataChunk {
@@ -29,6 +37,7 @@ impl FromIterator<ArrayImpl> for DataChunk {
         DataChunk {
             arrays,
             cardinality,
+            visibility: None,
         }
     }
 }
@@ -47,6 +56,7 @@ impl DataChunk {
                 .into_iter()
                 .collect(),
             cardinality: 1,
+            visibility: None,
         }
     }
 
@@ -55,6 +65,7 @@ impl DataChunk {
         DataChunk {
             arrays: Arc::new([]),
             cardinality,
+            visibility: None,
         }
     }
 
@@ -92,8 +103,38 @@ impl DataChunk {
         &self.arrays
     }
 
+    /// Returns the hash of each row.
+    ///
+    /// The hash function is fixed, so that equal rows from all chunks have the same hash.
+    pub fn hash_rows(&self) -> Vec<u64> {
+        #[cfg(feature = "simd")]
+        if let Some(hashes) = super::simd::hash(&self.arrays) {
+            return hashes;
+        }
+        let state = ahash::RandomState::with_seeds(1, 2, 3, 4);
+        (self.rows())
+            .map(|row| {
+                let mut hasher = state.build_hasher();
+                for value in row.values() {
+                    value.hash(&mut hasher);
+                }
+                hasher.finish()
+            })
+            .collect()
+    }
+
     /// Filter elements and create a new chunk.
+    ///
+    /// Invisible rows are also removed.
     pub fn filter(&self, visibility: &[bool]) -> Self {
+        let combined: Vec<bool>;
+        let visibility = match &self.visibility {
+            Some(vis) => {
+                combined = vis.iter().zip(visibility).map(|(a, b)| *a && *b).collect();
+                &combined
+            }
+            None => visibility,
+        };
         let arrays: Arc<[ArrayImpl]> = self.arrays.iter().map(|a| a.filter(visibility)).collect();
         DataChunk {
             cardinality: match arrays.first() {
@@ -101,9 +142,45 @@ impl DataChunk {
                 None => visibility.iter().filter(|b| **b).count(),
             },
             arrays,
+            visibility: None,
+        }
+    }
+
+    /// Returns whether each row is visible, or `None` if all rows are visible.
+    pub fn visibility(&self) -> Option<&[bool]> {
+        self.visibility.as_deref()
+    }
+
+    /// Marks rows as invisible where `visibility` is false, without copying the arrays.
+    pub fn with_visibility(mut self, visibility: &[bool]) -> Self {
+        assert_eq!(
+            visibility.len(),
+            self.cardinality,
+            "visibility length mismatch"
+        );
+        self.visibility = Some(match &self.visibility {
+            Some(vis) => vis.iter().zip(visibility).map(|(a, b)| *a && *b).collect(),
+            None => visibility.into(),
+        });
+        self
+    }
+
+    /// Returns the number of visible rows.
+    pub fn visible_cardinality(&self) -> usize {
+        match &self.visibility {
+            Some(vis) => vis.iter().filter(|b| **b).count(),
+            None => self.cardinality,
         }
     }
 
+    /// Removes invisible rows from the chunk.
+    pub fn compact(self) -> Self {
+        if let Some(vis) = &self.visibility {
+            return self.filter(vis);
+        }
+        self
+    }
+
     /// Return the number of columns.
     pub fn column_count(&self) -> usize {
         self.arrays.len()
@@ -126,6 +203,7 @@ impl DataChunk {
         DataChunk {
             arrays,
             cardinality: end - begin,
+            visibility: (self.visibility.as_ref()).map(|vis| vis[begin..end].into()),
         }
     }
 
@@ -182,6 +260,8 @@ impl fmt::Debug for DataChunk {
 pub struct Chunk {
     data_chunks: Vec<DataChunk>,
     header: Option<Vec<String>>,
+    /// Names and types of columns, if the chunk is the output of a query.
+    schema: Option<Vec<(String, DataType)>>,
 }
 
 impl Chunk {
@@ -190,6 +270,7 @@ impl Chunk {
         Chunk {
             data_chunks,
             header: None,
+            schema: None,
         }
     }
 
@@ -212,6 +293,16 @@ impl Chunk {
     pub fn set_header(&mut self, header: Vec<String>) {
         self.header = Some(header);
     }
+
+    /// Get names and types of columns.
+    pub fn schema(&self) -> Option<&[(String, DataType)]> {
+        self.schema.as_deref()
+    }
+
+    /// Set names and types of columns.
+    pub fn set_schema(&mut self, schema: Vec<(String, DataType)>) {
+        self.schema = Some(schema);
+    }
 }
 
 /// Print the chunk as a pretty table.
@@ -261,11 +352,13 @@ pub fn datachunk_to_sqllogictest_string(chunk: &Chunk) -> Vec<Vec<String>> {
                     DataValue::String(s) => s.to_string(),
                     DataValue::Blob(s) if s.is_empty() => "(empty)".to_string(),
                     DataValue::Blob(s) => s.to_string(),
+                    DataValue::Json(v) => v.to_string(),
                     DataValue::Decimal(v) => v.to_string(),
                     DataValue::Date(v) => v.to_string(),
                     DataValue::Timestamp(v) => v.to_string(),
                     DataValue::TimestampTz(v) => v.to_string(),
                     DataValue::Interval(v) => v.to_string(),
+                    DataValue::List(v) => v.to_string(),
                 };
                 row_vec.push(s);
             }
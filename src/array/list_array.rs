@@ -0,0 +1,189 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::borrow::Borrow;
+use std::mem;
+
+use bitvec::vec::BitVec;
+
+use super::{Array, ArrayBuilder, ArrayEstimateExt, ArrayFromDataExt, ArrayValidExt};
+use crate::types::{DataValue, ListRef};
+
+/// A collection of lists.
+///
+/// The elements of all lists are flattened into a single buffer,
+/// and the `offset` marks the range of each list.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ListArray {
+    offset: Box<[usize]>,
+    valid: BitVec,
+    values: Box<[DataValue]>,
+}
+
+impl Array for ListArray {
+    type Item = ListRef;
+    type Builder = ListArrayBuilder;
+
+    fn is_null(&self, idx: usize) -> bool {
+        !self.valid[idx]
+    }
+
+    fn get_raw(&self, idx: usize) -> &ListRef {
+        ListRef::new(&self.values[self.offset[idx]..self.offset[idx + 1]])
+    }
+
+    fn len(&self) -> usize {
+        self.valid.len()
+    }
+
+    fn filter(&self, p: &[bool]) -> Self {
+        assert_eq!(p.len(), self.len());
+        let mut builder = Self::Builder::with_capacity(self.len());
+        for (i, &v) in p.iter().enumerate() {
+            if v {
+                builder.push(self.get(i));
+            }
+        }
+        builder.finish()
+    }
+}
+
+impl ArrayValidExt for ListArray {
+    fn get_valid_bitmap(&self) -> &BitVec {
+        &self.valid
+    }
+    fn get_valid_bitmap_mut(&mut self) -> &mut BitVec {
+        &mut self.valid
+    }
+}
+
+impl ArrayEstimateExt for ListArray {
+    fn get_estimated_size(&self) -> usize {
+        self.values.len() * mem::size_of::<DataValue>()
+            + self.offset.len() * mem::size_of::<usize>()
+            + self.valid.len() / 8
+    }
+}
+
+impl ArrayFromDataExt for ListArray {
+    fn from_data(data_iter: impl Iterator<Item = impl Borrow<Self::Item>>, valid: BitVec) -> Self {
+        let mut values = Vec::with_capacity(valid.len());
+        let mut offset = Vec::with_capacity(valid.len() + 1);
+        offset.push(0);
+        for raw in data_iter {
+            let raw: &ListRef = raw.borrow();
+            values.extend_from_slice(raw);
+            offset.push(values.len());
+        }
+        Self {
+            valid,
+            values: values.into(),
+            offset: offset.into(),
+        }
+    }
+}
+
+/// A builder that uses `&ListRef` to build a [`ListArray`].
+pub struct ListArrayBuilder {
+    offset: Vec<usize>,
+    valid: BitVec,
+    values: Vec<DataValue>,
+}
+
+impl ArrayBuilder for ListArrayBuilder {
+    type Array = ListArray;
+
+    fn extend_from_raw_data(&mut self, raws: &[<<Self::Array as Array>::Item as ToOwned>::Owned]) {
+        for raw in raws {
+            self.values.extend_from_slice(raw);
+            self.offset.push(self.values.len());
+        }
+    }
+
+    fn extend_from_nulls(&mut self, count: usize) {
+        let len = self.values.len();
+        self.offset.extend((0..count).map(|_| len));
+    }
+
+    fn replace_bitmap(&mut self, valid: BitVec) {
+        let _ = mem::replace(&mut self.valid, valid);
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        let mut offset = Vec::with_capacity(capacity + 1);
+        offset.push(0);
+        Self {
+            offset,
+            values: Vec::with_capacity(capacity),
+            valid: BitVec::with_capacity(capacity),
+        }
+    }
+
+    fn reserve(&mut self, capacity: usize) {
+        self.offset.reserve(capacity + 1);
+        self.valid.reserve(capacity);
+        self.values.reserve(capacity);
+    }
+
+    fn push(&mut self, value: Option<&ListRef>) {
+        self.valid.push(value.is_some());
+        if let Some(x) = value {
+            self.values.extend_from_slice(x);
+        }
+        self.offset.push(self.values.len());
+    }
+
+    fn push_n(&mut self, n: usize, value: Option<&ListRef>) {
+        for _ in 0..n {
+            self.push(value);
+        }
+    }
+
+    fn append(&mut self, other: &ListArray) {
+        self.valid.extend_from_bitslice(&other.valid);
+        self.values.extend_from_slice(&other.values);
+        let start = *self.offset.last().unwrap();
+        for other_offset in &other.offset[1..] {
+            self.offset.push(*other_offset + start);
+        }
+    }
+
+    fn take(&mut self) -> ListArray {
+        ListArray {
+            valid: mem::take(&mut self.valid),
+            values: mem::take(&mut self.values).into(),
+            offset: mem::replace(&mut self.offset, vec![0]).into(),
+        }
+    }
+}
+
+// Enable `collect()` an array from iterator of `Option<&ListRef>` or `Option<List>`.
+impl<O: AsRef<ListRef>> FromIterator<Option<O>> for ListArray {
+    fn from_iter<I: IntoIterator<Item = Option<O>>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut builder = <Self as Array>::Builder::with_capacity(iter.size_hint().0);
+        for e in iter {
+            builder.push(e.as_ref().map(|e| e.as_ref()));
+        }
+        builder.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::List;
+
+    #[test]
+    fn test_list_array_builder() {
+        let list = List::from(vec![DataValue::Int32(1), DataValue::Int32(2)]);
+        let mut builder = ListArrayBuilder::with_capacity(3);
+        builder.push(Some(&*list));
+        builder.push(None);
+        builder.push(Some(ListRef::new(&[])));
+        let array = builder.finish();
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.get(0), Some(&*list));
+        assert_eq!(array.get(1), None);
+        assert_eq!(array.get(2).map(|l| l.len()), Some(0));
+    }
+}
@@ -11,18 +11,22 @@ use rust_decimal::prelude::FromStr;
 use rust_decimal::Decimal;
 
 use crate::types::{
-    Blob, ConvertError, DataType, DataValue, Date, Interval, Timestamp, TimestampTz, F32, F64,
+    Blob, ConvertError, DataType, DataValue, Date, Interval, Jsonb, Timestamp, TimestampTz, F32,
+    F64,
 };
 
+mod buffer;
 mod bytes_array;
 mod data_chunk;
 mod data_chunk_builder;
+mod list_array;
 pub mod ops;
 mod primitive_array;
 
 pub use self::bytes_array::*;
 pub use self::data_chunk::*;
 pub use self::data_chunk_builder::*;
+pub use self::list_array::*;
 pub use self::primitive_array::*;
 
 mod internal_ext;
@@ -33,6 +37,11 @@ mod shuffle_ext;
 
 pub use shuffle_ext::*;
 
+mod arrow_ext;
+
+#[cfg(feature = "simd")]
+pub mod simd;
+
 /// A trait over all array builders.
 ///
 /// `ArrayBuilder` is a trait over all builders. You could build an array with
@@ -199,11 +208,13 @@ pub enum ArrayImpl {
     Float64(Arc<F64Array>),
     String(Arc<StringArray>),
     Blob(Arc<BlobArray>),
+    Json(Arc<JsonArray>),
     Decimal(Arc<DecimalArray>),
     Date(Arc<DateArray>),
     Timestamp(Arc<TimestampArray>),
     TimestampTz(Arc<TimestampTzArray>),
     Interval(Arc<IntervalArray>),
+    List(Arc<ListArray>),
 }
 
 pub type NullArrayBuilder = PrimitiveArrayBuilder<()>;
@@ -230,11 +241,13 @@ pub enum ArrayBuilderImpl {
     Float64(F64ArrayBuilder),
     String(StringArrayBuilder),
     Blob(BlobArrayBuilder),
+    Json(JsonArrayBuilder),
     Decimal(DecimalArrayBuilder),
     Date(DateArrayBuilder),
     Timestamp(TimestampArrayBuilder),
     TimestampTz(TimestampTzArrayBuilder),
     Interval(IntervalArrayBuilder),
+    List(ListArrayBuilder),
 }
 
 /// `for_all_variants` includes all variants of our array types. If you added a new array
@@ -262,7 +275,9 @@ macro_rules! for_all_variants {
             { TimestampTz, TimestampTz, timestamp_tz, TimestampTzArray, TimestampTzArrayBuilder, TimestampTz, TimestampTz },
             { Interval, Interval, interval, IntervalArray, IntervalArrayBuilder, Interval, Interval },
             { String, str, string, StringArray, StringArrayBuilder, String, String },
-            { Blob, BlobRef, blob, BlobArray, BlobArrayBuilder, Blob, Blob }
+            { Blob, BlobRef, blob, BlobArray, BlobArrayBuilder, Blob, Blob },
+            { Json, JsonbRef, json, JsonArray, JsonArrayBuilder, Json, Json },
+            { List, ListRef, list, ListArray, ListArrayBuilder, List, List(_) }
         }
     };
 }
@@ -283,7 +298,9 @@ macro_rules! for_all_variants_without_null {
             { TimestampTz, TimestampTz, timestamp_tz, TimestampTzArray, TimestampTzArrayBuilder, TimestampTz, TimestampTz },
             { Interval, Interval, interval, IntervalArray, IntervalArrayBuilder, Interval, Interval },
             { String, str, string, StringArray, StringArrayBuilder, String, String },
-            { Blob, BlobRef, blob, BlobArray, BlobArrayBuilder, Blob, Blob }
+            { Blob, BlobRef, blob, BlobArray, BlobArrayBuilder, Blob, Blob },
+            { Json, JsonbRef, json, JsonArray, JsonArrayBuilder, Json, Json },
+            { List, ListRef, list, ListArray, ListArrayBuilder, List, List(_) }
         }
     };
 }
@@ -487,11 +504,13 @@ impl ArrayBuilderImpl {
             Self::Float64(a) if null => a.push(None),
             Self::String(a) if null => a.push(None),
             Self::Blob(a) if null => a.push(None),
+            Self::Json(a) if null => a.push(None),
             Self::Decimal(a) if null => a.push(None),
             Self::Date(a) if null => a.push(None),
             Self::Timestamp(a) if null => a.push(None),
             Self::TimestampTz(a) if null => a.push(None),
             Self::Interval(a) if null => a.push(None),
+            Self::List(a) if null => a.push(None),
             Self::Bool(a) => a.push(Some(
                 &s.parse::<bool>()
                     .map_err(|e| ConvertError::ParseBool(s.to_string(), e))?,
@@ -517,6 +536,10 @@ impl ArrayBuilderImpl {
                 &s.parse::<Blob>()
                     .map_err(|e| ConvertError::ParseBlob(s.to_string(), e))?,
             )),
+            Self::Json(a) => a
+                .push(Some(&s.parse::<Jsonb>().map_err(|e| {
+                    ConvertError::ParseJson(s.to_string(), e.to_string())
+                })?)),
             Self::Decimal(a) => a.push(Some(
                 &Decimal::from_str(s).map_err(|e| ConvertError::ParseDecimal(s.to_string(), e))?,
             )),
@@ -535,6 +558,7 @@ impl ArrayBuilderImpl {
                 &Interval::from_str(s)
                     .map_err(|e| ConvertError::ParseInterval(s.to_string(), e))?,
             )),
+            Self::List(_) => return Err(ConvertError::Cast(s.to_string(), "list")),
         }
         Ok(())
     }
@@ -599,6 +623,9 @@ macro_rules! impl_array {
 
             /// Filter the elements and return a new array.
             pub fn filter(&self, visibility: &[bool]) -> Self {
+                if let Some(array) = self.filter_by_arrow(visibility) {
+                    return array;
+                }
                 match self {
                     Self::Null(a) => Self::Null(a.filter(&visibility).into()),
                     $(
@@ -651,11 +678,13 @@ impl From<&DataValue> for ArrayImpl {
             &DataValue::Float64(v) => Self::new_float64([v].into_iter().collect()),
             DataValue::String(v) => Self::new_string([Some(v)].into_iter().collect()),
             DataValue::Blob(v) => Self::new_blob([Some(v)].into_iter().collect()),
+            DataValue::Json(v) => Self::new_json([Some(v)].into_iter().collect()),
             &DataValue::Decimal(v) => Self::new_decimal([v].into_iter().collect()),
             &DataValue::Date(v) => Self::new_date([v].into_iter().collect()),
             &DataValue::Timestamp(v) => Self::new_timestamp([v].into_iter().collect()),
             &DataValue::TimestampTz(v) => Self::new_timestamp_tz([v].into_iter().collect()),
             &DataValue::Interval(v) => Self::new_interval([v].into_iter().collect()),
+            DataValue::List(v) => Self::new_list([Some(v)].into_iter().collect()),
         }
     }
 }
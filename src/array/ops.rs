@@ -3,9 +3,13 @@
 //! Array operations.
 
 use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
 
+use chrono::{FixedOffset, Offset, Utc};
 use num_traits::ToPrimitive;
-use regex::Regex;
+use parking_lot::Mutex;
+use regex::{Regex, RegexBuilder};
 use rust_decimal::prelude::FromStr;
 use rust_decimal::Decimal;
 
@@ -13,8 +17,8 @@ use super::*;
 use crate::for_all_variants;
 use crate::parser::{BinaryOperator, UnaryOperator};
 use crate::types::{
-    Blob, ConvertError, DataType, DataValue, Date, DateTimeField, Interval, NativeType, Timestamp,
-    TimestampTz, F64,
+    Blob, ConvertError, DataType, DataValue, Date, DateTimeField, Interval, Jsonb, JsonbRef, List,
+    NativeType, Timestamp, TimestampTz, F64,
 };
 
 type A = ArrayImpl;
@@ -49,7 +53,7 @@ impl ArrayImpl {
 
 /// A macro to implement arithmetic operations.
 macro_rules! arith {
-    ($name:ident, $op:tt) => {
+    ($name:ident, $op:tt $(, $pat:pat => $expr:expr)*) => {
         pub fn $name(
             &self,
             other: &Self,
@@ -78,14 +82,15 @@ macro_rules! arith {
             (A::Int16(a), A::Decimal(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from(*a) $op *b)),
             (A::Int32(a), A::Decimal(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from(*a) $op *b)),
             (A::Int64(a), A::Decimal(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from(*a) $op *b)),
-            (A::Float64(a), A::Decimal(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from_f64_retain(a.0).unwrap() $op *b)),
+            (A::Float64(a), A::Decimal(b)) => A::new_decimal(try_binary_op(a.as_ref(), b.as_ref(), |a, b| float_to_decimal(*a).map(|a| a $op *b))?),
             (A::Decimal(a), A::Int16(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from(*b))),
             (A::Decimal(a), A::Int32(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from(*b))),
             (A::Decimal(a), A::Int64(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from(*b))),
-            (A::Decimal(a), A::Float64(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from_f64_retain(b.0).unwrap())),
+            (A::Decimal(a), A::Float64(b)) => A::new_decimal(try_binary_op(a.as_ref(), b.as_ref(), |a, b| float_to_decimal(*b).map(|b| *a $op b))?),
             (A::Decimal(a), A::Decimal(b)) => A::new_decimal(binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b)),
 
             (A::Date(a), A::Interval(b)) => A::new_date(binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op *b)),
+            $($pat => $expr,)*
 
             _ => return Err(ConvertError::NoBinaryOp(stringify!($name).into(), self.type_string(), other.type_string())),
         })
@@ -100,6 +105,10 @@ macro_rules! cmp {
             &self,
             other: &Self,
         ) -> Result {
+        #[cfg(feature = "simd")]
+        if let Some(array) = super::simd::$name(self, other) {
+            return Ok(A::new_bool(clear_null(array)));
+        }
         Ok(A::new_bool(clear_null(match (self, other) {
             (A::Bool(a), A::Bool(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b),
 
@@ -126,16 +135,19 @@ macro_rules! cmp {
             (A::Int16(a), A::Decimal(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from(*a) $op *b),
             (A::Int32(a), A::Decimal(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from(*a) $op *b),
             (A::Int64(a), A::Decimal(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from(*a) $op *b),
-            (A::Float64(a), A::Decimal(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| Decimal::from_f64_retain(a.0).unwrap() $op *b),
+            (A::Float64(a), A::Decimal(b)) => try_binary_op(a.as_ref(), b.as_ref(), |a, b| float_to_decimal(*a).map(|a| a $op *b))?,
             (A::Decimal(a), A::Int16(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from(*b)),
             (A::Decimal(a), A::Int32(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from(*b)),
             (A::Decimal(a), A::Int64(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from(*b)),
-            (A::Decimal(a), A::Float64(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| *a $op Decimal::from_f64_retain(b.0).unwrap()),
+            (A::Decimal(a), A::Float64(b)) => try_binary_op(a.as_ref(), b.as_ref(), |a, b| float_to_decimal(*b).map(|b| *a $op b))?,
             (A::Decimal(a), A::Decimal(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b),
 
             (A::String(a), A::String(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b),
 
             (A::Date(a), A::Date(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b),
+            (A::Timestamp(a), A::Timestamp(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b),
+            (A::TimestampTz(a), A::TimestampTz(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b),
+            (A::Interval(a), A::Interval(b)) => binary_op(a.as_ref(), b.as_ref(), |a, b| a $op b),
 
             _ => return Err(ConvertError::NoBinaryOp(stringify!($name).into(), self.type_string(), other.type_string())),
         })))
@@ -144,8 +156,12 @@ macro_rules! cmp {
 }
 
 impl ArrayImpl {
-    arith!(add, +);
-    arith!(sub, -);
+    arith!(add, +, (A::Timestamp(a), A::Interval(b)) => A::new_timestamp(
+        try_binary_op(a.as_ref(), b.as_ref(), |a, b| a.checked_add(*b).ok_or(ConvertError::TimestampOutOfRange))?
+    ));
+    arith!(sub, -, (A::Timestamp(a), A::Interval(b)) => A::new_timestamp(
+        try_binary_op(a.as_ref(), b.as_ref(), |a, b| a.checked_sub(*b).ok_or(ConvertError::TimestampOutOfRange))?
+    ));
     arith!(mul, *);
     arith!(unchecked_div, /);
     arith!(rem, %);
@@ -204,30 +220,45 @@ impl ArrayImpl {
         Ok(A::new_bool(clear_null(unary_op(a.as_ref(), |b| !b))))
     }
 
-    pub fn like(&self, pattern: &str) -> Result {
-        /// Converts a SQL LIKE pattern to a regex pattern.
-        fn like_to_regex(pattern: &str) -> String {
-            let mut regex = String::with_capacity(pattern.len());
-            regex.push('^');
-            for c in pattern.chars() {
-                match c {
-                    '%' => regex.push_str(".*"),
-                    '_' => regex.push('.'),
-                    c => regex.push(c),
-                }
-            }
-            regex.push('$');
-            regex
-        }
+    /// Matches strings against a LIKE pattern.
+    ///
+    /// `%` matches any sequence of characters, `_` matches any single character, and `\`
+    /// escapes the next character.
+    pub fn like(&self, pattern: &str, case_insensitive: bool) -> Result {
         let A::String(a) = self else {
             return Err(ConvertError::NoUnaryOp("like".into(), self.type_string()));
         };
-        let regex = Regex::new(&like_to_regex(pattern)).unwrap();
+        let regex = like_regex(pattern, case_insensitive)?;
         Ok(A::new_bool(clear_null(unary_op(a.as_ref(), |s| {
             regex.is_match(s)
         }))))
     }
 
+    /// Matches strings against the LIKE pattern in the same row.
+    pub fn like_array(&self, pattern: &Self, case_insensitive: bool) -> Result {
+        let (A::String(a), A::String(b)) = (self, pattern) else {
+            return Err(ConvertError::NoBinaryOp(
+                "like".into(),
+                self.type_string(),
+                pattern.type_string(),
+            ));
+        };
+        let mut builder = BoolArrayBuilder::with_capacity(a.len());
+        let mut last: Option<(&str, Arc<Regex>)> = None;
+        for (s, p) in a.iter().zip(b.iter()) {
+            let (Some(s), Some(p)) = (s, p) else {
+                builder.push(None);
+                continue;
+            };
+            if last.as_ref().map_or(true, |(last, _)| *last != p) {
+                last = Some((p, like_regex(p, case_insensitive)?));
+            }
+            let (_, regex) = last.as_ref().unwrap();
+            builder.push(Some(&regex.is_match(s)));
+        }
+        Ok(A::new_bool(builder.finish()))
+    }
+
     pub fn concat(&self, other: &Self) -> Result {
         let (A::String(a), A::String(b)) = (self, other) else {
             return Err(ConvertError::NoBinaryOp(
@@ -243,20 +274,46 @@ impl ArrayImpl {
     }
 
     pub fn extract(&self, field: &DateTimeField) -> Result {
+        use sqlparser::ast::DateTimeField as F;
+        let unsupported =
+            || ConvertError::NoUnaryOp(format!("extract({field} from)"), self.type_string());
         Ok(match self {
-            A::Date(a) => match &field.0 {
-                sqlparser::ast::DateTimeField::Year => {
-                    A::new_int32(unary_op(a.as_ref(), |d| d.year()))
-                }
-                sqlparser::ast::DateTimeField::Month => {
-                    A::new_int32(unary_op(a.as_ref(), |d| d.month()))
-                }
-                sqlparser::ast::DateTimeField::Day => {
-                    A::new_int32(unary_op(a.as_ref(), |d| d.day()))
-                }
-                f => todo!("extract {f} from date"),
-            },
-            A::Interval(_) => todo!("extract {field} from interval"),
+            A::Date(a) => A::new_int32(match &field.0 {
+                F::Year => unary_op(a.as_ref(), |d| d.year()),
+                F::Month => unary_op(a.as_ref(), |d| d.month()),
+                F::Day => unary_op(a.as_ref(), |d| d.day()),
+                _ => return Err(unsupported()),
+            }),
+            A::Timestamp(a) => A::new_int32(match &field.0 {
+                F::Year => try_unary_op(a.as_ref(), |t| {
+                    t.year().ok_or(ConvertError::TimestampOutOfRange)
+                })?,
+                F::Month => try_unary_op(a.as_ref(), |t| {
+                    t.month().ok_or(ConvertError::TimestampOutOfRange)
+                })?,
+                F::Day => try_unary_op(a.as_ref(), |t| {
+                    t.day().ok_or(ConvertError::TimestampOutOfRange)
+                })?,
+                F::Hour => try_unary_op(a.as_ref(), |t| {
+                    t.hour().ok_or(ConvertError::TimestampOutOfRange)
+                })?,
+                F::Minute => try_unary_op(a.as_ref(), |t| {
+                    t.minute().ok_or(ConvertError::TimestampOutOfRange)
+                })?,
+                F::Second => try_unary_op(a.as_ref(), |t| {
+                    t.second().ok_or(ConvertError::TimestampOutOfRange)
+                })?,
+                _ => return Err(unsupported()),
+            }),
+            A::Interval(a) => A::new_int32(match &field.0 {
+                F::Year => unary_op(a.as_ref(), |i| i.years()),
+                F::Month => unary_op(a.as_ref(), |i| i.months()),
+                F::Day => unary_op(a.as_ref(), |i| i.days()),
+                F::Hour => unary_op(a.as_ref(), |i| i.hours()),
+                F::Minute => unary_op(a.as_ref(), |i| i.minutes()),
+                F::Second => unary_op(a.as_ref(), |i| i.seconds()),
+                _ => return Err(unsupported()),
+            }),
             _ => {
                 return Err(ConvertError::NoUnaryOp(
                     "extract".into(),
@@ -267,6 +324,31 @@ impl ArrayImpl {
     }
 
     /// Select values from `true_array` or `false_array` according to the boolean value of `self`.
+    /// Merges two arrays into one, taking values from `true_array` at selected positions and
+    /// from `false_array` at others, in order.
+    ///
+    /// `true_array` has the length of selected positions, and `false_array` has the length of
+    /// others.
+    pub fn merge(selected: &[bool], true_array: &Self, false_array: &Self) -> Self {
+        // the branch of null type may be evaluated from a null constant
+        let mut builder = match true_array {
+            A::Null(_) => ArrayBuilderImpl::from_type_of_array(false_array),
+            _ => ArrayBuilderImpl::from_type_of_array(true_array),
+        };
+        builder.reserve(selected.len());
+        let (mut i, mut j) = (0, 0);
+        for &s in selected {
+            if s {
+                builder.push(&true_array.get(i));
+                i += 1;
+            } else {
+                builder.push(&false_array.get(j));
+                j += 1;
+            }
+        }
+        builder.finish()
+    }
+
     pub fn select(&self, true_array: &Self, false_array: &Self) -> Result {
         let A::Bool(s) = self else {
             return Err(ConvertError::NoUnaryOp("case".into(), self.type_string()));
@@ -291,6 +373,14 @@ impl ArrayImpl {
             (A::Interval(a), A::Interval(b)) => {
                 A::new_interval(select_op(s.as_ref(), a.as_ref(), b.as_ref()))
             }
+            // other types are selected value by value
+            (a, b) if std::mem::discriminant(a) == std::mem::discriminant(b) => {
+                let mut builder = ArrayBuilderImpl::from_type_of_array(a);
+                for (i, s) in s.iter().enumerate() {
+                    builder.push(&if s == Some(&true) { a.get(i) } else { b.get(i) });
+                }
+                builder.finish()
+            }
             _ => {
                 return Err(ConvertError::NoBinaryOp(
                     "case".into(),
@@ -334,6 +424,281 @@ impl ArrayImpl {
         )))
     }
 
+    /// Removes the longest prefix and suffix consisting of the characters.
+    pub fn btrim(&self, characters: &Self) -> Result {
+        self.trim_op("btrim", characters, |s, c| {
+            s.trim_matches(|x| c.contains(x))
+        })
+    }
+
+    /// Removes the longest prefix consisting of the characters.
+    pub fn ltrim(&self, characters: &Self) -> Result {
+        self.trim_op("ltrim", characters, |s, c| {
+            s.trim_start_matches(|x| c.contains(x))
+        })
+    }
+
+    /// Removes the longest suffix consisting of the characters.
+    pub fn rtrim(&self, characters: &Self) -> Result {
+        self.trim_op("rtrim", characters, |s, c| {
+            s.trim_end_matches(|x| c.contains(x))
+        })
+    }
+
+    fn trim_op(
+        &self,
+        name: &str,
+        characters: &Self,
+        f: impl for<'a> Fn(&'a str, &str) -> &'a str,
+    ) -> Result {
+        let (A::String(a), A::String(b)) = (self, characters) else {
+            return Err(ConvertError::NoBinaryOp(
+                name.into(),
+                self.type_string(),
+                characters.type_string(),
+            ));
+        };
+        Ok(A::new_string(binary_op(a.as_ref(), b.as_ref(), |s, c| {
+            f(s, c).to_string()
+        })))
+    }
+
+    /// Returns the 1-based character index of the first occurrence of `self` in `string`,
+    /// or 0 if not found.
+    pub fn position(&self, string: &Self) -> Result {
+        let (A::String(a), A::String(b)) = (self, string) else {
+            return Err(ConvertError::NoBinaryOp(
+                "position".into(),
+                self.type_string(),
+                string.type_string(),
+            ));
+        };
+        Ok(A::new_int32(binary_op(
+            a.as_ref(),
+            b.as_ref(),
+            |sub, s| match s.find(sub) {
+                Some(i) => s[..i].chars().count() as i32 + 1,
+                None => 0,
+            },
+        )))
+    }
+
+    /// Fills up the string on the left to the length, or truncates it if it is longer.
+    pub fn lpad(&self, length: &Self, fill: &Self) -> Result {
+        self.pad_op("lpad", length, fill, true)
+    }
+
+    /// Fills up the string on the right to the length, or truncates it if it is longer.
+    pub fn rpad(&self, length: &Self, fill: &Self) -> Result {
+        self.pad_op("rpad", length, fill, false)
+    }
+
+    fn pad_op(&self, name: &str, length: &Self, fill: &Self, left: bool) -> Result {
+        let (A::String(a), A::Int32(b), A::String(c)) = (self, length, fill) else {
+            return Err(ConvertError::NoTernaryOp(
+                name.into(),
+                self.type_string(),
+                length.type_string(),
+                fill.type_string(),
+            ));
+        };
+        Ok(A::new_string(ternary_op(
+            a.as_ref(),
+            b.as_ref(),
+            c.as_ref(),
+            |s, len, fill| {
+                let len = (*len).max(0) as usize;
+                let chars = s.chars().count();
+                if chars >= len || fill.is_empty() {
+                    return s.chars().take(len).collect::<String>();
+                }
+                let padding = fill.chars().cycle().take(len - chars);
+                if left {
+                    padding.chain(s.chars()).collect()
+                } else {
+                    s.chars().chain(padding).collect()
+                }
+            },
+        )))
+    }
+
+    /// Returns the captured substrings of the first match of the pattern, or the whole match if
+    /// the pattern has no capture group. Returns NULL if there is no match.
+    pub fn regexp_matches(&self, pattern: &Self) -> Result {
+        let (A::String(a), A::String(b)) = (self, pattern) else {
+            return Err(ConvertError::NoBinaryOp(
+                "regexp_matches".into(),
+                self.type_string(),
+                pattern.type_string(),
+            ));
+        };
+        let mut builder = ListArrayBuilder::with_capacity(a.len());
+        // the pattern is usually a constant, so only compile it when it changes
+        let mut regex: Option<Regex> = None;
+        for (s, p) in a.iter().zip(b.iter()) {
+            let (Some(s), Some(p)) = (s, p) else {
+                builder.push(None);
+                continue;
+            };
+            if regex.as_ref().map_or(true, |r| r.as_str() != p) {
+                let r = Regex::new(p).map_err(|e| ConvertError::ParseRegex(p.into(), e))?;
+                regex = Some(r);
+            }
+            let captures = regex.as_ref().unwrap().captures(s);
+            let list = captures.map(|c| -> List {
+                let to_value = |m: Option<regex::Match<'_>>| {
+                    m.map_or(DataValue::Null, |m| DataValue::String(m.as_str().into()))
+                };
+                match c.len() {
+                    1 => [to_value(c.get(0))].into_iter().collect(),
+                    _ => c.iter().skip(1).map(to_value).collect(),
+                }
+            });
+            builder.push(list.as_deref());
+        }
+        Ok(A::new_list(builder.finish()))
+    }
+
+    /// Rounds numbers to the number of digits after the decimal point.
+    ///
+    /// Halves are rounded away from zero. Negative digits round to the left of the point.
+    pub fn round(&self, digits: &Self) -> Result {
+        let A::Int32(d) = digits else {
+            return Err(ConvertError::NoBinaryOp(
+                "round".into(),
+                self.type_string(),
+                digits.type_string(),
+            ));
+        };
+        Ok(match self {
+            // integers are rounded as decimals
+            A::Int16(_) | A::Int32(_) | A::Int64(_) => {
+                let ty = match self {
+                    A::Int16(_) => DataType::Int16,
+                    A::Int32(_) => DataType::Int32,
+                    _ => DataType::Int64,
+                };
+                (self.cast(&DataType::Decimal(None, None))?)
+                    .round(digits)?
+                    .cast(&ty)?
+            }
+            A::Float64(a) => A::new_float64(binary_op(a.as_ref(), d.as_ref(), |v, d| {
+                let m = 10f64.powi(*d);
+                F64::from((v.0 * m).round() / m)
+            })),
+            A::Decimal(a) => A::new_decimal(binary_op(a.as_ref(), d.as_ref(), |v, d| {
+                let away = rust_decimal::RoundingStrategy::MidpointAwayFromZero;
+                if *d >= 0 {
+                    v.round_dp_with_strategy(*d as u32, away)
+                } else {
+                    let m = Decimal::from_i128_with_scale(10i128.pow(d.unsigned_abs().min(28)), 0);
+                    (v / m).round_dp_with_strategy(0, away) * m
+                }
+            })),
+            _ => {
+                return Err(ConvertError::NoBinaryOp(
+                    "round".into(),
+                    self.type_string(),
+                    digits.type_string(),
+                ))
+            }
+        })
+    }
+
+    /// Returns the nearest integers greater than or equal to numbers.
+    pub fn ceil(&self) -> Result {
+        Ok(match self {
+            A::Int16(_) | A::Int32(_) | A::Int64(_) => self.clone(),
+            A::Float64(a) => A::new_float64(unary_op(a.as_ref(), |v| F64::from(v.0.ceil()))),
+            A::Decimal(a) => A::new_decimal(unary_op(a.as_ref(), |v| v.ceil())),
+            _ => return Err(ConvertError::NoUnaryOp("ceil".into(), self.type_string())),
+        })
+    }
+
+    /// Returns the nearest integers less than or equal to numbers.
+    pub fn floor(&self) -> Result {
+        Ok(match self {
+            A::Int16(_) | A::Int32(_) | A::Int64(_) => self.clone(),
+            A::Float64(a) => A::new_float64(unary_op(a.as_ref(), |v| F64::from(v.0.floor()))),
+            A::Decimal(a) => A::new_decimal(unary_op(a.as_ref(), |v| v.floor())),
+            _ => return Err(ConvertError::NoUnaryOp("floor".into(), self.type_string())),
+        })
+    }
+
+    pub fn power(&self, exponent: &Self) -> Result {
+        let (Some(A::Float64(a)), Some(A::Float64(b))) = (self.to_float64(), exponent.to_float64())
+        else {
+            return Err(ConvertError::NoBinaryOp(
+                "power".into(),
+                self.type_string(),
+                exponent.type_string(),
+            ));
+        };
+        Ok(A::new_float64(binary_op(a.as_ref(), b.as_ref(), |a, b| {
+            F64::from(a.0.powf(b.0))
+        })))
+    }
+
+    /// Returns the natural logarithm of numbers.
+    pub fn ln(&self) -> Result {
+        self.log_op("ln", f64::ln)
+    }
+
+    /// Returns the base 10 logarithm of numbers.
+    pub fn log10(&self) -> Result {
+        self.log_op("log", f64::log10)
+    }
+
+    fn log_op(&self, name: &'static str, f: fn(f64) -> f64) -> Result {
+        let Some(A::Float64(a)) = self.to_float64() else {
+            return Err(ConvertError::NoUnaryOp(name.into(), self.type_string()));
+        };
+        Ok(A::new_float64(try_unary_op(a.as_ref(), |v| {
+            if v.0 <= 0.0 {
+                return Err(ConvertError::InvalidArgument(name, DataValue::Float64(*v)));
+            }
+            Ok(F64::from(f(v.0)))
+        })?))
+    }
+
+    /// Casts numbers to float64. Returns `None` if the array is not numeric.
+    fn to_float64(&self) -> Option<ArrayImpl> {
+        match self {
+            A::Int16(_) | A::Int32(_) | A::Int64(_) | A::Float64(_) | A::Decimal(_) => {
+                self.cast(&DataType::Float64).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the greater value of each row. NULL values are ignored.
+    pub fn greatest(&self, other: &Self) -> Result {
+        self.pick_op("greatest", other, std::cmp::Ordering::Greater)
+    }
+
+    /// Returns the less value of each row. NULL values are ignored.
+    pub fn least(&self, other: &Self) -> Result {
+        self.pick_op("least", other, std::cmp::Ordering::Less)
+    }
+
+    fn pick_op(&self, name: &str, other: &Self, order: std::cmp::Ordering) -> Result {
+        if std::mem::discriminant(self) != std::mem::discriminant(other) {
+            return Err(ConvertError::NoBinaryOp(
+                name.into(),
+                self.type_string(),
+                other.type_string(),
+            ));
+        }
+        assert_eq!(self.len(), other.len());
+        let mut builder = ArrayBuilderImpl::from_type_of_array(self);
+        for i in 0..self.len() {
+            let (a, b) = (self.get(i), other.get(i));
+            let pick_b = a.is_null() || (!b.is_null() && b.cmp(&a) == order);
+            builder.push(if pick_b { &b } else { &a });
+        }
+        Ok(builder.finish())
+    }
+
     /// Perform binary operation.
     pub fn binary_op(&self, op: &BinaryOperator, other: &ArrayImpl) -> Result {
         use BinaryOperator::*;
@@ -362,6 +727,12 @@ impl ArrayImpl {
 
     /// Cast the array to another type.
     pub fn cast(&self, data_type: &DataType) -> Result {
+        self.cast_in_time_zone(data_type, Utc.fix())
+    }
+
+    /// Cast the array to another type. Timestamps with time zone are converted from or to strings
+    /// in the time zone `tz`.
+    pub fn cast_in_time_zone(&self, data_type: &DataType, tz: FixedOffset) -> Result {
         type Type = DataType;
         Ok(match self {
             Self::Null(a) => {
@@ -380,15 +751,17 @@ impl ArrayImpl {
                 Type::String => {
                     Self::new_string(unary_op(a.as_ref(), |&b| if b { "true" } else { "false" }))
                 }
-                Type::Decimal(_, _) => {
-                    Self::new_decimal(unary_op(a.as_ref(), |&b| Decimal::from(b as u8)))
-                }
+                Type::Decimal(p, s) => Self::new_decimal(try_unary_op(a.as_ref(), |&b| {
+                    to_decimal(Decimal::from(b as u8), *p, *s)
+                })?),
                 Type::Null
                 | Type::Date
                 | Type::Timestamp
                 | Type::TimestampTz
                 | Type::Interval
                 | Type::Blob
+                | Type::Json
+                | Type::List(_)
                 | Type::Struct(_) => {
                     return Err(ConvertError::NoCast("BOOLEAN", data_type.clone()));
                 }
@@ -400,15 +773,17 @@ impl ArrayImpl {
                 Type::Int64 => Self::new_int64(unary_op(a.as_ref(), |&b| b as i64)),
                 Type::Float64 => Self::new_float64(unary_op(a.as_ref(), |&i| F64::from(i as f64))),
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
-                Type::Decimal(_, _) => {
-                    Self::new_decimal(unary_op(a.as_ref(), |&i| Decimal::from(i)))
-                }
+                Type::Decimal(p, s) => Self::new_decimal(try_unary_op(a.as_ref(), |&i| {
+                    to_decimal(Decimal::from(i), *p, *s)
+                })?),
                 Type::Null
                 | Type::Date
                 | Type::Timestamp
                 | Type::TimestampTz
                 | Type::Interval
                 | Type::Blob
+                | Type::Json
+                | Type::List(_)
                 | Type::Struct(_) => {
                     return Err(ConvertError::NoCast("SMALLINT", data_type.clone()));
                 }
@@ -423,15 +798,17 @@ impl ArrayImpl {
                 Type::Int64 => Self::new_int64(unary_op(a.as_ref(), |&b| b as i64)),
                 Type::Float64 => Self::new_float64(unary_op(a.as_ref(), |&i| F64::from(i as f64))),
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
-                Type::Decimal(_, _) => {
-                    Self::new_decimal(unary_op(a.as_ref(), |&i| Decimal::from(i)))
-                }
+                Type::Decimal(p, s) => Self::new_decimal(try_unary_op(a.as_ref(), |&i| {
+                    to_decimal(Decimal::from(i), *p, *s)
+                })?),
                 Type::Null
                 | Type::Date
                 | Type::Timestamp
                 | Type::TimestampTz
                 | Type::Interval
                 | Type::Blob
+                | Type::Json
+                | Type::List(_)
                 | Type::Struct(_) => {
                     return Err(ConvertError::NoCast("INT", data_type.clone()));
                 }
@@ -449,15 +826,17 @@ impl ArrayImpl {
                 Type::Int64 => Self::Int64(a.clone()),
                 Type::Float64 => Self::new_float64(unary_op(a.as_ref(), |&i| F64::from(i as f64))),
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
-                Type::Decimal(_, _) => {
-                    Self::new_decimal(unary_op(a.as_ref(), |&i| Decimal::from(i)))
-                }
+                Type::Decimal(p, s) => Self::new_decimal(try_unary_op(a.as_ref(), |&i| {
+                    to_decimal(Decimal::from(i), *p, *s)
+                })?),
                 Type::Null
                 | Type::Date
                 | Type::Timestamp
                 | Type::TimestampTz
                 | Type::Interval
                 | Type::Blob
+                | Type::Json
+                | Type::List(_)
                 | Type::Struct(_) => {
                     return Err(ConvertError::NoCast("BIGINT", data_type.clone()));
                 }
@@ -478,15 +857,17 @@ impl ArrayImpl {
                 })?),
                 Type::Float64 => Self::Float64(a.clone()),
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
-                Type::Decimal(_, _) => Self::new_decimal(unary_op(a.as_ref(), |&f| {
-                    Decimal::from_f64_retain(f.0).unwrap()
-                })),
+                Type::Decimal(p, s) => Self::new_decimal(try_unary_op(a.as_ref(), |&f| {
+                    to_decimal(float_to_decimal(f)?, *p, *s)
+                })?),
                 Type::Null
                 | Type::Date
                 | Type::Timestamp
                 | Type::TimestampTz
                 | Type::Interval
                 | Type::Blob
+                | Type::Json
+                | Type::List(_)
                 | Type::Struct(_) => {
                     return Err(ConvertError::NoCast("DOUBLE", data_type.clone()));
                 }
@@ -513,8 +894,10 @@ impl ArrayImpl {
                         .map_err(|e| ConvertError::ParseFloat(s.to_string(), e))
                 })?),
                 Type::String => Self::String(a.clone()),
-                Type::Decimal(_, _) => Self::new_decimal(try_unary_op(a.as_ref(), |s| {
-                    Decimal::from_str(s).map_err(|e| ConvertError::ParseDecimal(s.to_string(), e))
+                Type::Decimal(p, sc) => Self::new_decimal(try_unary_op(a.as_ref(), |s| {
+                    let d = Decimal::from_str(s)
+                        .map_err(|e| ConvertError::ParseDecimal(s.to_string(), e))?;
+                    to_decimal(d, *p, *sc)
                 })?),
                 Type::Date => Self::new_date(try_unary_op(a.as_ref(), |s| {
                     Date::from_str(s).map_err(|e| ConvertError::ParseDate(s.to_string(), e))
@@ -524,7 +907,7 @@ impl ArrayImpl {
                         .map_err(|e| ConvertError::ParseTimestamp(s.to_string(), e))
                 })?),
                 Type::TimestampTz => Self::new_timestamp_tz(try_unary_op(a.as_ref(), |s| {
-                    TimestampTz::from_str(s)
+                    TimestampTz::parse_in(s, tz)
                         .map_err(|e| ConvertError::ParseTimestampTz(s.to_string(), e))
                 })?),
                 Type::Interval => Self::new_interval(try_unary_op(a.as_ref(), |s| {
@@ -533,11 +916,20 @@ impl ArrayImpl {
                 Type::Blob => Self::new_blob(try_unary_op(a.as_ref(), |s| {
                     Blob::from_str(s).map_err(|e| ConvertError::ParseBlob(s.to_string(), e))
                 })?),
-                Type::Null | Type::Struct(_) => {
+                Type::Json => Self::new_json(try_unary_op(a.as_ref(), |s| {
+                    Jsonb::from_str(s)
+                        .map_err(|e| ConvertError::ParseJson(s.to_string(), e.to_string()))
+                })?),
+                Type::Null | Type::List(_) | Type::Struct(_) => {
                     return Err(ConvertError::NoCast("VARCHAR", data_type.clone()));
                 }
             },
             Self::Blob(_) => todo!("cast array"),
+            Self::Json(a) => match data_type {
+                Type::Json => self.clone(),
+                Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
+                _ => return Err(ConvertError::NoCast("JSON", data_type.clone())),
+            },
             Self::Decimal(a) => match data_type {
                 Type::Bool => Self::new_bool(unary_op(a.as_ref(), |&d| !d.is_zero())),
                 Type::Int16 => Self::new_int16(try_unary_op(a.as_ref(), |&d| {
@@ -558,30 +950,43 @@ impl ArrayImpl {
                         .ok_or(ConvertError::FromDecimalError(DataType::Float64, d))
                 })?),
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
-                Type::Decimal(_, _) => self.clone(),
+                Type::Decimal(None, None) => self.clone(),
+                Type::Decimal(p, s) => {
+                    Self::new_decimal(try_unary_op(a.as_ref(), |&d| to_decimal(d, *p, *s))?)
+                }
                 Type::Null
                 | Type::Blob
+                | Type::Json
                 | Type::Date
                 | Type::Timestamp
                 | Type::TimestampTz
                 | Type::Interval
+                | Type::List(_)
                 | Type::Struct(_) => {
                     return Err(ConvertError::NoCast("DOUBLE", data_type.clone()));
                 }
             },
             Self::Date(a) => match data_type {
                 Type::Date => self.clone(),
+                Type::Timestamp => {
+                    Self::new_timestamp(unary_op(a.as_ref(), |&d| Timestamp::from(d)))
+                }
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
                 _ => return Err(ConvertError::NoCast("DATE", data_type.clone())),
             },
             Self::Timestamp(a) => match data_type {
                 Type::Timestamp => self.clone(),
+                Type::Date => Self::new_date(try_unary_op(a.as_ref(), |t| {
+                    t.date().ok_or(ConvertError::TimestampOutOfRange)
+                })?),
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
                 _ => return Err(ConvertError::NoCast("TIMESTAMP", data_type.clone())),
             },
             Self::TimestampTz(a) => match data_type {
                 Type::TimestampTz => self.clone(),
-                Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
+                Type::String => Self::new_string(StringArray::from_iter_display(
+                    a.iter().map(|v| v.map(|v| v.display_in(tz))),
+                )),
                 _ => {
                     return Err(ConvertError::NoCast(
                         "TIMESTAMP WITH TIME ZONE",
@@ -594,11 +999,34 @@ impl ArrayImpl {
                 Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
                 _ => return Err(ConvertError::NoCast("INTERVAL", data_type.clone())),
             },
+            Self::List(a) => match data_type {
+                Type::List(ty) => {
+                    let mut builder = ListArrayBuilder::with_capacity(a.len());
+                    for list in a.iter() {
+                        let list = match list {
+                            Some(list) => Some(
+                                list.iter()
+                                    .map(|v| v.cast_in_time_zone(ty, tz))
+                                    .collect::<std::result::Result<List, _>>()?,
+                            ),
+                            None => None,
+                        };
+                        builder.push(list.as_deref());
+                    }
+                    Self::new_list(builder.finish())
+                }
+                Type::String => Self::new_string(StringArray::from_iter_display(a.iter())),
+                _ => return Err(ConvertError::NoCast("LIST", data_type.clone())),
+            },
         })
     }
 
     /// Returns the sum of values.
     pub fn sum(&self) -> DataValue {
+        #[cfg(feature = "simd")]
+        if let Some(sum) = super::simd::sum(self) {
+            return sum;
+        }
         match self {
             Self::Int16(a) => DataValue::Int16(a.raw_iter().sum()),
             Self::Int32(a) => DataValue::Int32(a.raw_iter().sum()),
@@ -624,6 +1052,81 @@ impl ArrayImpl {
         };
         Ok(A::new_string(unary_op(a.as_ref(), |s| s.replace(from, to))))
     }
+
+    /// Extracts an object field (by key) or an array element (by index) from JSON values.
+    ///
+    /// The result is a JSON value, or its text with strings unquoted if `as_text` is set.
+    /// Returns NULL if the field or element does not exist.
+    pub fn json_get(&self, path: &Self, as_text: bool) -> Result {
+        let op = if as_text { "->>" } else { "->" };
+        let (A::Json(a), A::String(_) | A::Int32(_)) = (self, path) else {
+            return Err(ConvertError::NoBinaryOp(
+                op.into(),
+                self.type_string(),
+                path.type_string(),
+            ));
+        };
+        let get = |i: usize| -> Option<&JsonbRef> {
+            let json = a.get(i)?;
+            match path {
+                A::String(key) => json.get_field(key.get(i)?),
+                A::Int32(index) => json.get_index(*index.get(i)? as i64),
+                _ => unreachable!(),
+            }
+        };
+        if !as_text {
+            return Ok(A::new_json((0..a.len()).map(get).collect()));
+        }
+        let mut builder = StringArrayBuilder::with_capacity(a.len());
+        for i in 0..a.len() {
+            let text = get(i)
+                .filter(|v| !v.is_null())
+                .map(|v| v.as_str().map_or_else(|| v.to_string(), |s| s.to_string()));
+            builder.push(text.as_deref());
+        }
+        Ok(A::new_string(builder.finish()))
+    }
+
+    /// Returns the number of elements in each list.
+    pub fn array_length(&self) -> Result {
+        let A::List(a) = self else {
+            return Err(ConvertError::NoUnaryOp(
+                "array_length".into(),
+                self.type_string(),
+            ));
+        };
+        Ok(A::new_int32(unary_op(a.as_ref(), |l| l.len() as i32)))
+    }
+
+    /// Returns the element at the 1-based `index` of each list, or NULL if out of range.
+    ///
+    /// The result array is built with the type of the first non-null element. The caller should
+    /// cast it to the element type in case there is none.
+    pub fn array_get(&self, index: &Self) -> Result {
+        let (A::List(a), A::Int32(index)) = (self, index) else {
+            return Err(ConvertError::NoBinaryOp(
+                "array_get".into(),
+                self.type_string(),
+                index.type_string(),
+            ));
+        };
+        let values = (a.iter().zip(index.iter()))
+            .map(|(list, i)| {
+                let i = usize::try_from(*i?).ok()?.checked_sub(1)?;
+                list?.get(i).cloned()
+            })
+            .map(|v| v.unwrap_or(DataValue::Null))
+            .collect::<Vec<_>>();
+        let ty = match values.iter().find(|v| !v.is_null()) {
+            Some(v) => v.data_type(),
+            None => DataType::Null,
+        };
+        let mut builder = ArrayBuilderImpl::with_capacity(values.len(), &ty);
+        for v in &values {
+            builder.push(v);
+        }
+        Ok(builder.finish())
+    }
 }
 
 /// Implement aggregation functions.
@@ -632,6 +1135,10 @@ macro_rules! impl_agg {
         impl ArrayImpl {
             /// Returns the minimum of values.
             pub fn min_(&self) -> DataValue {
+                #[cfg(feature = "simd")]
+                if let Some(min) = super::simd::min(self) {
+                    return min;
+                }
                 match self {
                     $(Self::$Abc(a) => a.nonnull_iter().min().into(),)*
                 }
@@ -639,6 +1146,10 @@ macro_rules! impl_agg {
 
             /// Returns the maximum of values.
             pub fn max_(&self) -> DataValue {
+                #[cfg(feature = "simd")]
+                if let Some(max) = super::simd::max(self) {
+                    return max;
+                }
                 match self {
                     $(Self::$Abc(a) => a.nonnull_iter().max().into(),)*
                 }
@@ -712,6 +1223,50 @@ fn safen_dividend(array: &ArrayImpl, valid: &BitVec) -> Option<ArrayImpl> {
     })
 }
 
+/// Compiled LIKE patterns, indexed by whether they are case insensitive.
+///
+/// Patterns are compiled once and shared by all chunks and queries.
+static LIKE_PATTERNS: LazyLock<[Mutex<HashMap<Box<str>, Arc<Regex>>>; 2]> =
+    LazyLock::new(Default::default);
+
+/// The maximum number of compiled LIKE patterns kept in [`LIKE_PATTERNS`].
+const MAX_LIKE_PATTERNS: usize = 1024;
+
+/// Returns the compiled regex of a LIKE pattern.
+fn like_regex(
+    pattern: &str,
+    case_insensitive: bool,
+) -> std::result::Result<Arc<Regex>, ConvertError> {
+    let mut patterns = LIKE_PATTERNS[case_insensitive as usize].lock();
+    if let Some(regex) = patterns.get(pattern) {
+        return Ok(regex.clone());
+    }
+    let mut expr = String::with_capacity(pattern.len() + 2);
+    expr.push('^');
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => expr.push_str(".*"),
+            '_' => expr.push('.'),
+            // a trailing escape character matches itself
+            '\\' => expr.push_str(&regex::escape(&chars.next().unwrap_or('\\').to_string())),
+            c => expr.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expr.push('$');
+    let compiled = RegexBuilder::new(&expr)
+        .case_insensitive(case_insensitive)
+        .dot_matches_new_line(true)
+        .build()
+        .map_err(|e| ConvertError::ParseRegex(pattern.into(), e))?;
+    let compiled = Arc::new(compiled);
+    if patterns.len() >= MAX_LIKE_PATTERNS {
+        patterns.clear();
+    }
+    patterns.insert(pattern.into(), compiled.clone());
+    Ok(compiled)
+}
+
 fn binary_op<A, B, O, F>(a: &A, b: &B, f: F) -> O
 where
     A: ArrayValidExt,
@@ -725,6 +1280,36 @@ where
     O::from_data(it, valid)
 }
 
+/// Converts a float to a decimal, failing on NaN and infinities.
+fn float_to_decimal(f: F64) -> std::result::Result<Decimal, ConvertError> {
+    Decimal::from_f64_retain(f.0).ok_or(ConvertError::ToDecimalError(DataValue::Float64(f)))
+}
+
+/// Converts a decimal to the given precision and scale.
+///
+/// The value is rounded to `scale` digits after the decimal point. As in the SQL standard,
+/// a missing scale defaults to 0 if the precision is specified.
+fn to_decimal(
+    mut d: Decimal,
+    precision: Option<u8>,
+    scale: Option<u8>,
+) -> std::result::Result<Decimal, ConvertError> {
+    let scale = scale.or(precision.map(|_| 0));
+    if let Some(scale) = scale {
+        d.rescale(scale as u32);
+    }
+    if let Some(precision) = precision
+        && let Some(max) = 10u128.checked_pow(precision as u32)
+        && d.mantissa().unsigned_abs() >= max
+    {
+        return Err(ConvertError::Overflow(
+            DataValue::Decimal(d),
+            DataType::Decimal(Some(precision), scale),
+        ));
+    }
+    Ok(d)
+}
+
 fn unary_op<A, O, F, V>(a: &A, f: F) -> O
 where
     A: ArrayValidExt,
@@ -753,18 +1338,40 @@ where
     Ok(builder.finish())
 }
 
+fn try_binary_op<A, B, O, F, V, E>(a: &A, b: &B, f: F) -> std::result::Result<O, E>
+where
+    A: Array,
+    B: Array,
+    O: Array,
+    V: Borrow<O::Item>,
+    F: Fn(&A::Item, &B::Item) -> std::result::Result<V, E>,
+{
+    assert_eq!(a.len(), b.len());
+    let mut builder = O::Builder::with_capacity(a.len());
+    for (a, b) in a.iter().zip(b.iter()) {
+        if let (Some(a), Some(b)) = (a, b) {
+            builder.push(Some(f(a, b)?.borrow()));
+        } else {
+            builder.push(None);
+        }
+    }
+    Ok(builder.finish())
+}
+
 fn select_op<A>(s: &BoolArray, a: &A, b: &A) -> A
 where
     A: ArrayValidExt + ArrayFromDataExt,
 {
     assert_eq!(a.len(), b.len());
+    // null conditions select the false branch
+    let selected: BitVec = s.iter().map(|s| s == Some(&true)).collect();
     let it = a
         .raw_iter()
         .zip(b.raw_iter())
-        .zip(s.raw_iter())
+        .zip(selected.iter())
         .map(|((a, b), s)| if *s { a } else { b });
-    let mut valid = s.get_valid_bitmap().and(a.get_valid_bitmap());
-    valid.or(&s.get_valid_bitmap().not_then_and(b.get_valid_bitmap()));
+    let mut valid = selected.and(a.get_valid_bitmap());
+    valid.or(&selected.not_then_and(b.get_valid_bitmap()));
     A::from_data(it, valid)
 }
 
@@ -8,15 +8,20 @@ use bitvec::vec::BitVec;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use super::buffer::ValueBuffer;
 use super::ops::BitVecExt;
 use super::{Array, ArrayBuilder, ArrayEstimateExt, ArrayFromDataExt, ArrayValidExt, BoolArray};
 use crate::types::{NativeType, F32, F64};
 
 /// A collection of primitive types, such as `i32`, `F32`.
+///
+/// The memory layout is compatible with Arrow: values are stored contiguously in a buffer which
+/// can be shared with Arrow arrays, and the validity bitmap has the same bit order as an Arrow
+/// bitmap.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct PrimitiveArray<T: NativeType> {
     valid: BitVec,
-    data: Box<[T]>,
+    data: ValueBuffer<T>,
 }
 
 // Enable `collect()` an array from iterator of `Option<T>`.
@@ -34,7 +39,7 @@ impl<T: NativeType> FromIterator<Option<T>> for PrimitiveArray<T> {
 // Enable `collect()` an array from iterator of `T`.
 impl<T: NativeType> FromIterator<T> for PrimitiveArray<T> {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        let data: Box<[T]> = iter.into_iter().collect();
+        let data: ValueBuffer<T> = iter.into_iter().collect();
         let size = data.len();
         Self {
             data,
@@ -45,7 +50,7 @@ impl<T: NativeType> FromIterator<T> for PrimitiveArray<T> {
 
 impl FromIterator<f32> for PrimitiveArray<F32> {
     fn from_iter<I: IntoIterator<Item = f32>>(iter: I) -> Self {
-        let data: Box<[F32]> = iter.into_iter().map(F32::from).collect();
+        let data: ValueBuffer<F32> = iter.into_iter().map(F32::from).collect();
         let size = data.len();
         Self {
             data,
@@ -56,7 +61,7 @@ impl FromIterator<f32> for PrimitiveArray<F32> {
 
 impl FromIterator<f64> for PrimitiveArray<F64> {
     fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Self {
-        let data: Box<[F64]> = iter.into_iter().map(F64::from).collect();
+        let data: ValueBuffer<F64> = iter.into_iter().map(F64::from).collect();
         let size = data.len();
         Self {
             data,
@@ -177,6 +182,24 @@ impl<T: NativeType> ArrayBuilder for PrimitiveArrayBuilder<T> {
     }
 }
 
+impl<T: NativeType> PrimitiveArray<T> {
+    /// Creates an array from raw data and the validity bitmap.
+    pub(super) fn from_raw_parts(data: ValueBuffer<T>, valid: BitVec) -> Self {
+        assert_eq!(data.len(), valid.len());
+        Self { valid, data }
+    }
+
+    /// Returns the buffer of raw data.
+    pub(super) fn raw_buffer(&self) -> &ValueBuffer<T> {
+        &self.data
+    }
+
+    /// Returns the raw data, including values of null slots.
+    pub fn raw_data(&self) -> &[T] {
+        &self.data
+    }
+}
+
 impl PrimitiveArray<bool> {
     /// Converts the raw bool array into a [`BitVec`].
     pub fn to_raw_bitvec(&self) -> BitVec {
@@ -192,9 +215,13 @@ impl PrimitiveArray<bool> {
 impl PrimitiveArray<Decimal> {
     /// Rescale the decimals.
     pub fn rescale(&mut self, scale: u8) {
-        for v in self.data.iter_mut() {
-            v.rescale(scale as u32);
-        }
+        self.data = (self.data.iter())
+            .map(|v| {
+                let mut v = *v;
+                v.rescale(scale as u32);
+                v
+            })
+            .collect();
     }
 }
 
@@ -204,9 +231,9 @@ pub fn clear_null(mut array: BoolArray) -> BoolArray {
         let chunk = std::simd::Mask::<i8, 64>::from_bitmask(bitmask as u64).to_array();
         valid.extend_from_slice(&chunk);
     }
-    for (d, v) in array.data.iter_mut().zip(valid) {
-        *d &= v;
-    }
+    array.data = (array.data.iter().zip(valid))
+        .map(|(d, v)| *d && v)
+        .collect();
     array
 }
 
@@ -0,0 +1,351 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! SIMD kernels over primitive arrays, enabled by the `simd` feature.
+//!
+//! Arrays are processed in chunks of 64 values, so that each chunk matches a word of the validity
+//! bitmap. The remaining values are processed by scalar code.
+//!
+//! Each kernel returns `None` if the arrays are not supported, and the caller falls back to the
+//! scalar implementation.
+
+use std::simd::prelude::*;
+use std::simd::{LaneCount, SimdElement, SupportedLaneCount};
+
+use bitvec::vec::BitVec;
+
+use super::ops::BitVecExt;
+use super::*;
+use crate::types::{DataValue, NativeType, F64};
+
+/// The number of values in a chunk.
+const LANES: usize = 64;
+
+/// A macro to implement comparison kernels.
+macro_rules! cmp {
+    ($name:ident, $simd:ident, $op:tt) => {
+        /// Compares two arrays with SIMD.
+        pub fn $name(a: &ArrayImpl, b: &ArrayImpl) -> Option<BoolArray> {
+            let (data, valid) = match (a, b) {
+                (ArrayImpl::Int16(a), ArrayImpl::Int16(b)) => (
+                    cmp(a.raw_data(), b.raw_data(), |a, b| Some(a.$simd(b).to_bitmask()), |a, b| a $op b),
+                    a.get_valid_bitmap().and(b.get_valid_bitmap()),
+                ),
+                (ArrayImpl::Int32(a), ArrayImpl::Int32(b)) => (
+                    cmp(a.raw_data(), b.raw_data(), |a, b| Some(a.$simd(b).to_bitmask()), |a, b| a $op b),
+                    a.get_valid_bitmap().and(b.get_valid_bitmap()),
+                ),
+                (ArrayImpl::Int64(a), ArrayImpl::Int64(b)) => (
+                    cmp(a.raw_data(), b.raw_data(), |a, b| Some(a.$simd(b).to_bitmask()), |a, b| a $op b),
+                    a.get_valid_bitmap().and(b.get_valid_bitmap()),
+                ),
+                // NaN is equal to itself and greater than any other value in `F64`,
+                // so chunks containing NaN are compared by scalar code
+                (ArrayImpl::Float64(a), ArrayImpl::Float64(b)) => (
+                    cmp(
+                        as_f64(a.raw_data()),
+                        as_f64(b.raw_data()),
+                        |a, b| (!(a.is_nan() | b.is_nan()).any()).then(|| a.$simd(b).to_bitmask()),
+                        |a, b| F64::from(*a) $op F64::from(*b),
+                    ),
+                    a.get_valid_bitmap().and(b.get_valid_bitmap()),
+                ),
+                _ => return None,
+            };
+            Some(BoolArray::from_raw_parts(data.into(), valid))
+        }
+    };
+}
+
+cmp!(eq, simd_eq, ==);
+cmp!(ne, simd_ne, !=);
+cmp!(gt, simd_gt, >);
+cmp!(lt, simd_lt, <);
+cmp!(ge, simd_ge, >=);
+cmp!(le, simd_le, <=);
+
+/// Compares two slices value by value.
+///
+/// `simd` returns the result of a chunk as a bitmask, or `None` if the chunk should be compared
+/// by `scalar`.
+fn cmp<T: SimdElement>(
+    a: &[T],
+    b: &[T],
+    simd: impl Fn(Simd<T, LANES>, Simd<T, LANES>) -> Option<u64>,
+    scalar: impl Fn(&T, &T) -> bool,
+) -> Vec<bool> {
+    assert_eq!(a.len(), b.len());
+    let mut output = Vec::with_capacity(a.len());
+    let (a_chunks, b_chunks) = (a.chunks_exact(LANES), b.chunks_exact(LANES));
+    let (a_remainder, b_remainder) = (a_chunks.remainder(), b_chunks.remainder());
+    for (a, b) in a_chunks.zip(b_chunks) {
+        match simd(Simd::from_slice(a), Simd::from_slice(b)) {
+            Some(bitmask) => {
+                output.extend_from_slice(&Mask::<i8, LANES>::from_bitmask(bitmask).to_array())
+            }
+            None => output.extend(a.iter().zip(b).map(|(a, b)| scalar(a, b))),
+        }
+    }
+    output.extend(
+        a_remainder
+            .iter()
+            .zip(b_remainder)
+            .map(|(a, b)| scalar(a, b)),
+    );
+    output
+}
+
+/// Returns the sum of non-null values.
+///
+/// Floating-point numbers are not supported, since adding them in a different order changes the
+/// result.
+pub fn sum(a: &ArrayImpl) -> Option<DataValue> {
+    Some(match a {
+        ArrayImpl::Int16(a) => DataValue::Int16(fold(
+            a.raw_data(),
+            a.get_valid_bitmap(),
+            0,
+            |a, b| a + b,
+            |a| a.reduce_sum(),
+            i16::wrapping_add,
+        )),
+        ArrayImpl::Int32(a) => DataValue::Int32(fold(
+            a.raw_data(),
+            a.get_valid_bitmap(),
+            0,
+            |a, b| a + b,
+            |a| a.reduce_sum(),
+            i32::wrapping_add,
+        )),
+        ArrayImpl::Int64(a) => DataValue::Int64(fold(
+            a.raw_data(),
+            a.get_valid_bitmap(),
+            0,
+            |a, b| a + b,
+            |a| a.reduce_sum(),
+            i64::wrapping_add,
+        )),
+        _ => return None,
+    })
+}
+
+/// A macro to implement minimum and maximum kernels.
+macro_rules! min_max {
+    (
+        $name:ident,
+        $simd:ident,
+        $reduce:ident,
+        $scalar:ident,
+        $int_identity:ident,
+        $float_identity:ident
+    ) => {
+        /// Returns the minimum or maximum of non-null values.
+        pub fn $name(a: &ArrayImpl) -> Option<DataValue> {
+            // leave empty results to scalar code
+            if a.get_valid_bitmap().not_any() {
+                return None;
+            }
+            Some(match a {
+                ArrayImpl::Int16(a) => DataValue::Int16(fold(
+                    a.raw_data(),
+                    a.get_valid_bitmap(),
+                    i16::$int_identity,
+                    |a, b| a.$simd(b),
+                    |a| a.$reduce(),
+                    i16::$scalar,
+                )),
+                ArrayImpl::Int32(a) => DataValue::Int32(fold(
+                    a.raw_data(),
+                    a.get_valid_bitmap(),
+                    i32::$int_identity,
+                    |a, b| a.$simd(b),
+                    |a| a.$reduce(),
+                    i32::$scalar,
+                )),
+                ArrayImpl::Int64(a) => DataValue::Int64(fold(
+                    a.raw_data(),
+                    a.get_valid_bitmap(),
+                    i64::$int_identity,
+                    |a, b| a.$simd(b),
+                    |a| a.$reduce(),
+                    i64::$scalar,
+                )),
+                // NaN is the greatest value in `F64`, but ignored by SIMD instructions
+                ArrayImpl::Float64(a) if !has_nan(as_f64(a.raw_data())) => {
+                    DataValue::Float64(F64::from(fold(
+                        as_f64(a.raw_data()),
+                        a.get_valid_bitmap(),
+                        f64::$float_identity,
+                        |a, b| a.$simd(b),
+                        |a| a.$reduce(),
+                        f64::$scalar,
+                    )))
+                }
+                _ => return None,
+            })
+        }
+    };
+}
+
+min_max!(min, simd_min, reduce_min, min, MAX, INFINITY);
+min_max!(max, simd_max, reduce_max, max, MIN, NEG_INFINITY);
+
+/// Folds non-null values of a slice. Null values are replaced by `identity`.
+fn fold<T: SimdElement>(
+    data: &[T],
+    valid: &BitVec,
+    identity: T,
+    simd: impl Fn(Simd<T, LANES>, Simd<T, LANES>) -> Simd<T, LANES>,
+    reduce: impl Fn(Simd<T, LANES>) -> T,
+    scalar: impl Fn(T, T) -> T,
+) -> T {
+    let chunks = data.chunks_exact(LANES);
+    let offset = data.len() - chunks.remainder().len();
+    let mut acc = Simd::splat(identity);
+    for (chunk, &word) in chunks.zip(valid.as_raw_slice()) {
+        let mask = Mask::<T::Mask, LANES>::from_bitmask(word as u64);
+        acc = simd(
+            acc,
+            mask.select(Simd::from_slice(chunk), Simd::splat(identity)),
+        );
+    }
+    (data[offset..].iter())
+        .zip(valid[offset..].iter().by_vals())
+        .filter(|(_, valid)| *valid)
+        .fold(reduce(acc), |acc, (v, _)| scalar(acc, *v))
+}
+
+/// The initial hash of rows.
+const HASH_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+/// The value hashed for nulls.
+const HASH_NULL: u64 = 0x8e4c_1b83_b0f9_2a67;
+
+/// Returns the hash of each row. Only integer keys are supported.
+///
+/// Integers of different widths have the same hash if they are equal.
+pub fn hash(keys: &[ArrayImpl]) -> Option<Vec<u64>> {
+    let supported = |key: &ArrayImpl| {
+        matches!(
+            key,
+            ArrayImpl::Int16(_) | ArrayImpl::Int32(_) | ArrayImpl::Int64(_)
+        )
+    };
+    if keys.is_empty() || !keys.iter().all(supported) {
+        return None;
+    }
+    let mut hashes = vec![HASH_SEED; keys[0].len()];
+    for key in keys {
+        match key {
+            ArrayImpl::Int16(a) => hash_column(&mut hashes, a, |v| v.cast()),
+            ArrayImpl::Int32(a) => hash_column(&mut hashes, a, |v| v.cast()),
+            ArrayImpl::Int64(a) => hash_column(&mut hashes, a, |v| v.cast()),
+            _ => unreachable!(),
+        }
+    }
+    Some(hashes)
+}
+
+/// Mixes values of a column into the hashes of rows.
+///
+/// Values are sign-extended by `widen`, so that equal integers of different widths have the same
+/// hash.
+fn hash_column<T>(
+    hashes: &mut [u64],
+    array: &PrimitiveArray<T>,
+    widen: impl Fn(Simd<T, LANES>) -> Simd<u64, LANES>,
+) where
+    T: NativeType + SimdElement + Into<i64>,
+{
+    let (data, valid) = (array.raw_data(), array.get_valid_bitmap());
+    assert_eq!(hashes.len(), data.len());
+    let chunks = data.chunks_exact(LANES);
+    let offset = data.len() - chunks.remainder().len();
+    let words = valid.as_raw_slice();
+    for ((chunk, hashes), &word) in chunks.zip(hashes.chunks_exact_mut(LANES)).zip(words) {
+        let values = widen(Simd::from_slice(chunk));
+        let values =
+            Mask::<i64, LANES>::from_bitmask(word as u64).select(values, Simd::splat(HASH_NULL));
+        mix(Simd::from_slice(hashes), values).copy_to_slice(hashes);
+    }
+    for ((v, hash), valid) in (data[offset..].iter())
+        .zip(&mut hashes[offset..])
+        .zip(valid[offset..].iter().by_vals())
+    {
+        let value = if valid {
+            Into::<i64>::into(*v) as u64
+        } else {
+            HASH_NULL
+        };
+        *hash = mix(Simd::<u64, 1>::splat(*hash), Simd::splat(value))[0];
+    }
+}
+
+/// Mixes a value into a hash.
+fn mix<const N: usize>(hash: Simd<u64, N>, value: Simd<u64, N>) -> Simd<u64, N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let hash = (hash ^ value) * Simd::splat(0x9e37_79b9_7f4a_7c15);
+    hash ^ (hash >> Simd::splat(32))
+}
+
+/// Returns true if any value is NaN.
+fn has_nan(data: &[f64]) -> bool {
+    let chunks = data.chunks_exact(LANES);
+    let remainder = chunks.remainder();
+    chunks
+        .map(Simd::<f64, LANES>::from_slice)
+        .any(|chunk| chunk.is_nan().any())
+        || remainder.iter().any(|v| v.is_nan())
+}
+
+/// Reinterprets a slice of [`F64`] as `f64`.
+fn as_f64(data: &[F64]) -> &[f64] {
+    // SAFETY: `OrderedFloat` is `repr(transparent)`
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), data.len()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmp() {
+        let a: I32Array = (0..200).map(|i| (i % 7 != 0).then_some(i)).collect();
+        let b: I32Array = (0..200).map(|i| Some(200 - i)).collect();
+        let result = gt(&a.clone().into(), &b.clone().into()).unwrap();
+        let expected = (a.iter().zip(b.iter())).map(|(a, b)| Some(a? > b?));
+        assert!(result.iter().map(|v| v.copied()).eq(expected));
+
+        let a: F64Array = [1.0, f64::NAN, 3.0].into_iter().cycle().take(200).collect();
+        let b: F64Array = [f64::NAN, 2.0, 3.0].into_iter().cycle().take(200).collect();
+        let result = ge(&a.into(), &b.into()).unwrap();
+        assert_eq!(&result.true_array()[..3], &[false, true, true]);
+        assert_eq!(&result.true_array()[192..195], &[false, true, true]);
+    }
+
+    #[test]
+    fn agg() {
+        let a: I64Array = (0..200).map(|i| (i % 3 != 0).then_some(i - 100)).collect();
+        let expected = a.nonnull_iter().sum::<i64>();
+        let a = ArrayImpl::from(a);
+        assert_eq!(sum(&a), Some(DataValue::Int64(expected)));
+        assert_eq!(min(&a), Some(DataValue::Int64(-99)));
+        assert_eq!(max(&a), Some(DataValue::Int64(99)));
+
+        let a: F64Array = (0..200).map(|i| i as f64).chain([f64::NAN]).collect();
+        assert_eq!(max(&a.into()), None);
+    }
+
+    #[test]
+    fn hash() {
+        let a: I32Array = (0..100).map(|i| (i % 5 != 0).then_some(i - 50)).collect();
+        let b: I64Array = (0..100).map(|i| (i % 5 != 0).then_some(i - 50)).collect();
+        let a = super::hash(&[a.into()]).unwrap();
+        let b = super::hash(&[b.into()]).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a[0], a[5]);
+        assert_ne!(a[1], a[2]);
+        let c: StringArray = ["a", "b"].into_iter().map(Some).collect();
+        assert_eq!(super::hash(&[c.into()]), None);
+    }
+}
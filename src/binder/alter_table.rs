@@ -0,0 +1,215 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::fmt;
+use std::str::FromStr;
+
+use pretty_xmlish::helper::delegate_fmt;
+use pretty_xmlish::Pretty;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::catalog::{ColumnCatalog, ColumnId, Dependent};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct AlterTable {
+    /// The table to alter, or `None` if it doesn't exist and `IF EXISTS` is given.
+    pub table_id: Option<TableRefId>,
+    pub operations: Vec<AlterTableOp>,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub enum AlterTableOp {
+    AddColumn(ColumnCatalog),
+    DropColumn(ColumnId),
+}
+
+impl fmt::Display for AlterTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explainer = Pretty::childless_record("AlterTable", self.pretty_table());
+        delegate_fmt(&explainer, f, String::with_capacity(1000))
+    }
+}
+
+impl AlterTable {
+    pub fn pretty_table<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
+        let ops = (self.operations.iter())
+            .map(|op| match op {
+                AlterTableOp::AddColumn(c) => {
+                    Pretty::simple_record("AddColumn", vec![], vec![c.desc().pretty()])
+                }
+                AlterTableOp::DropColumn(id) => {
+                    Pretty::childless_record("DropColumn", vec![("column_id", Pretty::display(id))])
+                }
+            })
+            .collect();
+        let table = match &self.table_id {
+            Some(table_id) => Pretty::display(table_id),
+            None => Pretty::display(&"none"),
+        };
+        vec![("table", table), ("operations", Pretty::Array(ops))]
+    }
+}
+
+impl FromStr for Box<AlterTable> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+impl Binder {
+    pub(super) fn bind_alter_table(
+        &mut self,
+        name: ObjectName,
+        if_exists: bool,
+        operations: Vec<AlterTableOperation>,
+    ) -> Result {
+        let name = self.table_name(&name)?;
+        let (schema_name, table_name) = split_name(&name)?;
+        let Some(table_id) = self.catalog.get_table_id_by_name(schema_name, table_name) else {
+            if if_exists {
+                let alter = AlterTable {
+                    table_id: None,
+                    operations: vec![],
+                };
+                return Ok(self.egraph.add(Node::AlterTable(Box::new(alter))));
+            }
+            return Err(BindError::InvalidTable(table_name.into()));
+        };
+        let table = self.catalog.get_table(&table_id).unwrap();
+        if table.is_system() || table.is_view() {
+            return Err(BindError::CanNotAlter);
+        }
+
+        // the columns after applying the previous operations
+        let mut columns: HashMap<String, ColumnCatalog> = (table.all_columns().into_values())
+            .map(|c| (c.name().to_string(), c))
+            .collect();
+        let mut next_column_id = table.next_column_id();
+        let indexes = self.catalog.get_table_indexes(&table_id);
+        let checks = self.bind_table_checks(table_id)?;
+        let checks = self.recexpr(checks);
+
+        let mut ops = vec![];
+        for op in operations {
+            match op {
+                AlterTableOperation::AddColumn {
+                    if_not_exists,
+                    column_def,
+                    column_position,
+                    ..
+                } => {
+                    if column_position.is_some() {
+                        return Err(BindError::Todo("column position".into()));
+                    }
+                    let mut column = self.bind_column_def(&column_def)?;
+                    if columns.contains_key(column.name()) {
+                        if if_not_exists {
+                            continue;
+                        }
+                        return Err(BindError::ColumnExists(column.name().into()));
+                    }
+                    if column.is_primary() {
+                        return Err(BindError::Todo("add primary key column".into()));
+                    }
+                    if (column_def.options.iter()).any(|opt| {
+                        matches!(
+                            opt.option,
+                            ColumnOption::Unique {
+                                is_primary: false,
+                                ..
+                            }
+                        )
+                    }) {
+                        return Err(BindError::Todo("add unique column".into()));
+                    }
+                    // existing rows are filled with the default value
+                    if !column.is_nullable() && column.default_value().is_null() {
+                        return Err(BindError::NotNullableColumn(column.name().into()));
+                    }
+                    column.set_id(next_column_id);
+                    next_column_id += 1;
+                    columns.insert(column.name().into(), column.clone());
+                    ops.push(AlterTableOp::AddColumn(column));
+                }
+                AlterTableOperation::DropColumn {
+                    column_name,
+                    if_exists,
+                    cascade,
+                } => {
+                    if cascade {
+                        return Err(BindError::Todo("cascade drop".into()));
+                    }
+                    let column_name = column_name.value.to_lowercase();
+                    let Some(column) = columns.get(&column_name) else {
+                        if if_exists {
+                            continue;
+                        }
+                        return Err(BindError::InvalidColumn(column_name));
+                    };
+                    if column.is_primary() || table.primary_keys().contains(&column.id()) {
+                        return Err(BindError::DropPrimaryKey(column_name));
+                    }
+                    if (table.unique_keys().iter()).any(|key| key.contains(&column.id())) {
+                        return Err(BindError::DropUniqueColumn(column_name));
+                    }
+                    if (table.foreign_keys().iter()).any(|fk| fk.column_ids.contains(&column.id()))
+                    {
+                        return Err(BindError::DropForeignKeyColumn(column_name));
+                    }
+                    if (checks.as_ref().iter())
+                        .any(|node| matches!(node, Node::Column(c) if c.column_id == column.id()))
+                    {
+                        return Err(BindError::DropCheckedColumn(column_name));
+                    }
+                    if let Some(index) =
+                        (indexes.iter()).find(|index| index.column_ids().contains(&column.id()))
+                    {
+                        return Err(BindError::DropIndexedColumn(
+                            column_name,
+                            index.name().into(),
+                        ));
+                    }
+                    let views = self.views_using_column(table_id, column.id());
+                    if !views.is_empty() {
+                        let views = self.describe_tables(&views);
+                        return Err(BindError::DropDependedObject(column_name, views));
+                    }
+                    if columns.len() == 1 {
+                        return Err(BindError::Todo("drop the last column".into()));
+                    }
+                    ops.push(AlterTableOp::DropColumn(column.id()));
+                    columns.remove(&column_name);
+                }
+                op => return Err(BindError::Todo(format!("alter table {op}"))),
+            }
+        }
+
+        let alter = self.egraph.add(Node::AlterTable(Box::new(AlterTable {
+            table_id: Some(table_id),
+            operations: ops,
+        })));
+        Ok(alter)
+    }
+
+    /// Returns the views whose queries refer to the column.
+    fn views_using_column(&self, table_id: TableRefId, column_id: ColumnId) -> Vec<TableRefId> {
+        let graph = self.catalog.dependency_graph();
+        (graph.dependents(table_id).iter())
+            .filter_map(|object| match object {
+                Dependent::Table(view_id) => Some(*view_id),
+                Dependent::Index(..) => None,
+            })
+            .filter(|view_id| {
+                let view = self.catalog.get_table(view_id).unwrap();
+                (view.query().unwrap().as_ref().iter()).any(|node| {
+                    matches!(node, Node::Column(c)
+                        if c.schema_id == table_id.schema_id
+                            && c.table_id == table_id.table_id
+                            && c.column_id == column_id)
+                })
+            })
+            .collect()
+    }
+}
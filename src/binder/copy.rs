@@ -25,7 +25,15 @@ pub enum FileFormat {
         escape: Option<char>,
         /// Whether or not the file has a header line.
         header: bool,
+        /// The string that represents a null value. Empty fields are always null when reading.
+        null: String,
     },
+    /// Apache Parquet. Columns are matched by position.
+    Parquet,
+    /// A JSON array of objects, one for each row. Only supported by `COPY TO`.
+    Json,
+    /// Newline-delimited JSON objects, one for each row. Only supported by `COPY TO`.
+    Ndjson,
 }
 
 impl std::fmt::Display for ExtSource {
@@ -55,12 +63,16 @@ impl Binder {
         target: CopyTarget,
         options: &[CopyOption],
     ) -> Result {
+        let format = FileFormat::from_options(options)?;
+        if !to && matches!(format, FileFormat::Json | FileFormat::Ndjson) {
+            return Err(BindError::Todo(format!("COPY FROM with format {format:?}")));
+        }
         let ext_source = self.egraph.add(Node::ExtSource(Box::new(ExtSource {
             path: match target {
                 CopyTarget::File { filename } => filename.into(),
                 t => todo!("unsupported copy target: {:?}", t),
             },
-            format: FileFormat::from_options(options),
+            format,
         })));
 
         let copy = if to {
@@ -80,7 +92,7 @@ impl Binder {
             self.egraph.add(Node::CopyTo([ext_source, query]))
         } else {
             // COPY <dest_table> FROM <source_file>
-            let (table, cols) = match source {
+            let (table, cols, checks) = match source {
                 CopySource::Table {
                     table_name,
                     columns,
@@ -92,14 +104,24 @@ impl Binder {
                         return Err(BindError::CopyTo("view".into()));
                     }
                     let cols = self.bind_table_columns(&table_name, &columns)?;
-                    (table, cols)
+                    let checks = self.bind_table_checks(self.node(table).as_table())?;
+                    (table, cols, checks)
                 }
                 CopySource::Query(_) => return Err(BindError::CopyTo("query".into())),
             };
             let types = self.type_(cols)?;
             let types = self.egraph.add(Node::Type(types));
             let copy = self.egraph.add(Node::CopyFrom([ext_source, types]));
-            self.egraph.add(Node::Insert([table, cols, copy]))
+            let returning = self.egraph.add(Node::List([].into()));
+            let on_conflict = self.egraph.add(Node::List([].into()));
+            self.egraph.add(Node::Insert([
+                table,
+                cols,
+                checks,
+                returning,
+                on_conflict,
+                copy,
+            ]))
         };
 
         Ok(copy)
@@ -108,28 +130,36 @@ impl Binder {
 
 impl FileFormat {
     /// Create from copy options.
-    pub fn from_options(options: &[CopyOption]) -> Self {
+    pub fn from_options(options: &[CopyOption]) -> Result<Self> {
         let mut delimiter = ',';
         let mut quote = '"';
         let mut escape = None;
         let mut header = false;
+        let mut null = String::new();
+        let mut format = "csv".to_string();
         for opt in options {
             match opt {
-                CopyOption::Format(fmt) => {
-                    assert_eq!(fmt.value.to_lowercase(), "csv", "only support CSV format")
-                }
+                CopyOption::Format(fmt) => format = fmt.value.to_lowercase(),
                 CopyOption::Delimiter(c) => delimiter = *c,
                 CopyOption::Header(b) => header = *b,
                 CopyOption::Quote(c) => quote = *c,
                 CopyOption::Escape(c) => escape = Some(*c),
-                o => panic!("unsupported copy option: {:?}", o),
+                CopyOption::Null(s) => null = s.clone(),
+                o => return Err(BindError::Todo(format!("COPY option {o}"))),
             }
         }
-        FileFormat::Csv {
-            delimiter,
-            quote,
-            escape,
-            header,
-        }
+        Ok(match format.as_str() {
+            "csv" => FileFormat::Csv {
+                delimiter,
+                quote,
+                escape,
+                header,
+                null,
+            },
+            "parquet" => FileFormat::Parquet,
+            "json" => FileFormat::Json,
+            "ndjson" => FileFormat::Ndjson,
+            f => return Err(BindError::Todo(format!("COPY format {f:?}"))),
+        })
     }
 }
@@ -45,20 +45,59 @@ impl CreateFunction {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct DropFunction {
+    /// Schema names and names of functions.
+    pub functions: Vec<(String, String)>,
+}
+
+impl fmt::Display for DropFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explainer = Pretty::childless_record("DropFunction", self.pretty_function());
+        delegate_fmt(&explainer, f, String::with_capacity(1000))
+    }
+}
+
+impl FromStr for Box<DropFunction> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+impl DropFunction {
+    pub fn pretty_function<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
+        let names = (self.functions.iter())
+            .map(|(schema_name, name)| Pretty::display(&format!("{schema_name}.{name}")))
+            .collect();
+        vec![("functions", Pretty::Array(names))]
+    }
+}
+
 impl Binder {
     pub(super) fn bind_create_function(
         &mut self,
+        or_replace: bool,
         name: ObjectName,
         args: Option<Vec<OperateFunctionArg>>,
         return_type: Option<DataType>,
         params: CreateFunctionBody,
     ) -> Result {
+        let name = self.new_object_name(&name)?;
         let Ok((schema_name, function_name)) = split_name(&name) else {
             return Err(BindError::BindFunctionError(
                 "failed to parse the input function name".to_string(),
             ));
         };
 
+        let Some(schema) = self.catalog.get_schema_by_name(schema_name) else {
+            return Err(BindError::InvalidSchema(schema_name.into()));
+        };
+        if !or_replace && schema.get_function_by_name(function_name).is_some() {
+            return Err(BindError::FunctionExists(function_name.into()));
+        }
+
         let schema_name = schema_name.to_string();
         let name = function_name.to_string();
 
@@ -114,4 +153,36 @@ impl Binder {
 
         Ok(f)
     }
+
+    pub(super) fn bind_drop_function(
+        &mut self,
+        if_exists: bool,
+        func_desc: Vec<DropFunctionDesc>,
+        option: Option<ReferentialAction>,
+    ) -> Result {
+        if option == Some(ReferentialAction::Cascade) {
+            return Err(BindError::Todo("cascade drop".into()));
+        }
+        let mut functions = Vec::with_capacity(func_desc.len());
+        for desc in func_desc {
+            // functions are identified by name only
+            let name = self.function_name(&desc.name)?;
+            let (schema_name, name) = split_name(&name)?;
+            if self
+                .catalog
+                .get_function_by_name(schema_name, name)
+                .is_none()
+            {
+                if if_exists {
+                    continue;
+                }
+                return Err(BindError::InvalidFunction(name.into()));
+            }
+            functions.push((schema_name.to_string(), name.to_string()));
+        }
+        let drop = self
+            .egraph
+            .add(Node::DropFunction(Box::new(DropFunction { functions })));
+        Ok(drop)
+    }
 }
@@ -0,0 +1,117 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::fmt;
+use std::str::FromStr;
+
+use pretty_xmlish::helper::delegate_fmt;
+use pretty_xmlish::Pretty;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::catalog::SchemaId;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct CreateSchema {
+    pub schema_name: String,
+    pub if_not_exists: bool,
+}
+
+impl fmt::Display for CreateSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explainer = Pretty::childless_record("CreateSchema", self.pretty_schema());
+        delegate_fmt(&explainer, f, String::with_capacity(1000))
+    }
+}
+
+impl FromStr for Box<CreateSchema> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+impl CreateSchema {
+    pub fn pretty_schema<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
+        vec![
+            ("name", Pretty::display(&self.schema_name)),
+            ("if_not_exists", Pretty::display(&self.if_not_exists)),
+        ]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct DropSchema {
+    pub schema_ids: Vec<SchemaId>,
+}
+
+impl fmt::Display for DropSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explainer = Pretty::childless_record("DropSchema", self.pretty_schema());
+        delegate_fmt(&explainer, f, String::with_capacity(1000))
+    }
+}
+
+impl FromStr for Box<DropSchema> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+impl DropSchema {
+    pub fn pretty_schema<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
+        let ids = self.schema_ids.iter().map(Pretty::display).collect();
+        vec![("schema_ids", Pretty::Array(ids))]
+    }
+}
+
+impl Binder {
+    pub(super) fn bind_create_schema(
+        &mut self,
+        schema_name: SchemaName,
+        if_not_exists: bool,
+    ) -> Result {
+        let SchemaName::Simple(name) = schema_name else {
+            return Err(BindError::Todo("schema authorization".into()));
+        };
+        let name = lower_case_name(&name);
+        let [schema_name] = name.0.as_slice() else {
+            return Err(BindError::InvalidTableName(name.0));
+        };
+        let schema_name = schema_name.value.clone();
+        if !if_not_exists && self.catalog.get_schema_by_name(&schema_name).is_some() {
+            return Err(BindError::SchemaExists(schema_name));
+        }
+        let create = self.egraph.add(Node::CreateSchema(Box::new(CreateSchema {
+            schema_name,
+            if_not_exists,
+        })));
+        Ok(create)
+    }
+
+    /// Binds `DROP SCHEMA`. Only empty schemas can be dropped.
+    pub(super) fn bind_drop_schema(&mut self, if_exists: bool, names: Vec<ObjectName>) -> Result {
+        let mut schema_ids = Vec::with_capacity(names.len());
+        for name in names {
+            let name = lower_case_name(&name);
+            let [schema_name] = name.0.as_slice() else {
+                return Err(BindError::InvalidTableName(name.0));
+            };
+            let schema_name = &schema_name.value;
+            let Some(schema) = self.catalog.get_schema_by_name(schema_name) else {
+                if if_exists {
+                    continue;
+                }
+                return Err(BindError::InvalidSchema(schema_name.clone()));
+            };
+            if !schema.all_tables().is_empty() || !schema.all_functions().is_empty() {
+                return Err(BindError::SchemaNotEmpty(schema_name.clone()));
+            }
+            schema_ids.push(schema.id());
+        }
+        let drop = (self.egraph).add(Node::DropSchema(Box::new(DropSchema { schema_ids })));
+        Ok(drop)
+    }
+}
@@ -9,7 +9,12 @@ use pretty_xmlish::Pretty;
 use serde::{Deserialize, Serialize};
 
 use super::*;
-use crate::catalog::{ColumnCatalog, ColumnDesc, ColumnId, SchemaId};
+use crate::array::ArrayImpl;
+use crate::catalog::{
+    ColumnCatalog, ColumnDesc, ColumnId, ColumnRefId, Compression, Constraints, ForeignKey,
+    SchemaId, ViewDefinition,
+};
+use crate::types::DataValue;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub struct CreateTable {
@@ -17,6 +22,12 @@ pub struct CreateTable {
     pub table_name: String,
     pub columns: Vec<ColumnCatalog>,
     pub ordered_pk_ids: Vec<ColumnId>,
+    pub unique_keys: Vec<Vec<ColumnId>>,
+    /// Expressions of `CHECK` constraints in SQL.
+    pub checks: Vec<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+    /// The definition of the view, if a view is created.
+    pub view: Option<ViewDefinition>,
 }
 
 impl fmt::Display for CreateTable {
@@ -27,15 +38,52 @@ impl fmt::Display for CreateTable {
 }
 
 impl CreateTable {
+    /// Returns the constraints of the table.
+    pub fn constraints(&self) -> Constraints {
+        Constraints {
+            primary_key: self.ordered_pk_ids.clone(),
+            unique_keys: self.unique_keys.clone(),
+            checks: self.checks.clone(),
+            foreign_keys: self.foreign_keys.clone(),
+        }
+    }
+
     pub fn pretty_table<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
         let cols = Pretty::Array(self.columns.iter().map(|c| c.desc().pretty()).collect());
         let ids = Pretty::Array(self.ordered_pk_ids.iter().map(Pretty::display).collect());
-        vec![
+        let mut fields = vec![
             ("schema_id", Pretty::display(&self.schema_id)),
             ("name", Pretty::display(&self.table_name)),
             ("columns", cols),
             ("ordered_ids", ids),
-        ]
+        ];
+        if !self.unique_keys.is_empty() {
+            let keys = (self.unique_keys.iter())
+                .map(|key| Pretty::Array(key.iter().map(Pretty::display).collect()))
+                .collect();
+            fields.push(("unique_keys", Pretty::Array(keys)));
+        }
+        if !self.checks.is_empty() {
+            let checks = self.checks.iter().map(Pretty::display).collect();
+            fields.push(("checks", Pretty::Array(checks)));
+        }
+        if !self.foreign_keys.is_empty() {
+            let ids = |ids: &[ColumnId]| Pretty::Array(ids.iter().map(Pretty::display).collect());
+            let foreign_keys = (self.foreign_keys.iter())
+                .map(|fk| {
+                    Pretty::childless_record(
+                        "ForeignKey",
+                        vec![
+                            ("columns", ids(&fk.column_ids)),
+                            ("ref_table", Pretty::display(&fk.ref_table_id)),
+                            ("ref_columns", ids(&fk.ref_column_ids)),
+                        ],
+                    )
+                })
+                .collect();
+            fields.push(("foreign_keys", Pretty::Array(foreign_keys)));
+        }
+        fields
     }
 }
 
@@ -47,14 +95,25 @@ impl FromStr for Box<CreateTable> {
     }
 }
 
+/// A table being created, which can be referenced by its own foreign keys.
+struct NewTable<'a> {
+    /// The name qualified with its schema.
+    name: &'a ObjectName,
+    schema_id: SchemaId,
+    columns: &'a [ColumnCatalog],
+    primary_key: &'a [ColumnId],
+    unique_keys: &'a [Vec<ColumnId>],
+}
+
 impl Binder {
     pub(super) fn bind_create_table(
         &mut self,
         name: ObjectName,
         columns: &[ColumnDef],
         constraints: &[TableConstraint],
+        options: &[SqlOption],
     ) -> Result {
-        let name = lower_case_name(&name);
+        let name = self.new_object_name(&lower_case_name(&name))?;
         let (schema_name, table_name) = split_name(&name)?;
         let schema = self
             .catalog
@@ -105,29 +164,394 @@ impl Binder {
                 .collect();
         }
 
+        let unique_keys = Binder::unique_keys(columns, constraints, &ordered_pk_ids)?;
+        let checks = Binder::checks(columns, constraints);
+
+        // (columns, referenced table, referenced columns, on delete, on update)
+        let mut foreign_key_defs = vec![];
+        for col_def in columns {
+            for option_def in &col_def.options {
+                if let ColumnOption::ForeignKey {
+                    foreign_table,
+                    referred_columns,
+                    on_delete,
+                    on_update,
+                    ..
+                } = &option_def.option
+                {
+                    foreign_key_defs.push((
+                        vec![col_def.name.clone()],
+                        foreign_table,
+                        referred_columns,
+                        on_delete,
+                        on_update,
+                    ));
+                }
+            }
+        }
+        for constraint in constraints {
+            if let TableConstraint::ForeignKey {
+                columns,
+                foreign_table,
+                referred_columns,
+                on_delete,
+                on_update,
+                ..
+            } = constraint
+            {
+                foreign_key_defs.push((
+                    columns.clone(),
+                    foreign_table,
+                    referred_columns,
+                    on_delete,
+                    on_update,
+                ));
+            }
+        }
+
         let mut columns: Vec<ColumnCatalog> = columns
             .iter()
             .enumerate()
-            .map(|(idx, col)| {
-                let mut col = ColumnCatalog::from(col);
+            .map(|(idx, col)| -> Result<ColumnCatalog> {
+                let mut col = self.bind_column_def(col)?;
                 col.set_id(idx as ColumnId);
-                col
+                Ok(col)
             })
-            .collect();
+            .try_collect()?;
 
         for &index in &ordered_pk_ids {
             columns[index as usize].set_nullable(false);
         }
+        for option in options {
+            Self::bind_table_option(&mut columns, option)?;
+        }
+
+        // the table does not exist yet, so columns are typed by `NULL` casts
+        let placeholders = (columns.iter())
+            .map(|column| {
+                let ty = self.egraph.add(Node::Type(column.data_type()));
+                let null = self.egraph.add(Node::null());
+                let id = self.egraph.add(Node::Cast([ty, null]));
+                (column.name().to_string(), id)
+            })
+            .collect_vec();
+        for check in &checks {
+            self.bind_check(table_name, check, &placeholders)?;
+        }
+
+        let table = NewTable {
+            name: &name,
+            schema_id: schema.id(),
+            columns: &columns,
+            primary_key: &ordered_pk_ids,
+            unique_keys: &unique_keys,
+        };
+        let foreign_keys = (foreign_key_defs.into_iter())
+            .map(|(names, ref_table, ref_names, on_delete, on_update)| {
+                self.bind_foreign_key(&table, &names, ref_table, ref_names, on_delete, on_update)
+            })
+            .try_collect()?;
 
         let create = self.egraph.add(Node::CreateTable(Box::new(CreateTable {
             schema_id: schema.id(),
             table_name: table_name.into(),
             columns,
             ordered_pk_ids,
+            unique_keys,
+            checks,
+            foreign_keys,
+            view: None,
         })));
         Ok(create)
     }
 
+    /// Binds `CREATE TABLE .. AS query`.
+    ///
+    /// Columns of the table are named after the output columns of the query and have the same
+    /// types.
+    pub(super) fn bind_create_table_as(
+        &mut self,
+        name: ObjectName,
+        query: Query,
+        options: &[SqlOption],
+    ) -> Result {
+        let name = self.new_object_name(&lower_case_name(&name))?;
+        let (schema_name, table_name) = split_name(&name)?;
+        let schema = self
+            .catalog
+            .get_schema_by_name(schema_name)
+            .ok_or_else(|| BindError::InvalidSchema(schema_name.into()))?;
+        if schema.get_table_by_name(table_name).is_some() {
+            return Err(BindError::TableExists(table_name.into()));
+        }
+
+        let (query, ctx) = self.bind_query(query)?;
+        let query_type = self.type_(query)?;
+        let mut columns: Vec<ColumnCatalog> = vec![];
+        for (idx, (id, ty)) in (self.schema(query).into_iter())
+            .zip(query_type.as_struct())
+            .enumerate()
+        {
+            let name = (self.output_name(id, &ctx)).ok_or(BindError::UnnamedColumn(idx + 1))?;
+            if columns.iter().any(|c| c.name() == name) {
+                return Err(BindError::ColumnExists(name));
+            }
+            if ty.is_null() {
+                return Err(BindError::Todo(format!("column {name:?} of type NULL")));
+            }
+            let desc = ColumnDesc::new(name, ty.clone(), true);
+            columns.push(ColumnCatalog::new(idx as ColumnId, desc));
+        }
+        for option in options {
+            Self::bind_table_option(&mut columns, option)?;
+        }
+
+        let table = self.egraph.add(Node::CreateTable(Box::new(CreateTable {
+            schema_id: schema.id(),
+            table_name: table_name.into(),
+            columns,
+            ordered_pk_ids: vec![],
+            unique_keys: vec![],
+            checks: vec![],
+            foreign_keys: vec![],
+            view: None,
+        })));
+        Ok(self.egraph.add(Node::CreateTableAs([table, query])))
+    }
+
+    /// Returns the name of an output column of a query, which is its alias or the name of the
+    /// column it refers to.
+    pub(super) fn output_name(&self, id: Id, ctx: &Context) -> Option<String> {
+        let alias = (ctx.output_aliases.iter())
+            .filter(|(_, alias_id)| **alias_id == id)
+            .map(|(name, _)| name)
+            .min();
+        if let Some(alias) = alias {
+            return Some(alias.clone());
+        }
+        match self.node(id) {
+            Node::Column(column) => self.catalog.get_column(column).map(|c| c.name().into()),
+            Node::Ref(id) => self.output_name(*id, ctx),
+            _ => None,
+        }
+    }
+
+    /// Binds an option in the `WITH` clause of `CREATE TABLE`.
+    ///
+    /// Supported options:
+    /// - `bloom_filter = 'c1, c2'`: build bloom filters on the columns.
+    /// - `compression = 'zstd'`: compress blocks of all columns with `zstd`, `lz4` or `none`.
+    /// - `compression = 'c1: zstd, c2: lz4'`: compress blocks of each column.
+    fn bind_table_option(columns: &mut [ColumnCatalog], option: &SqlOption) -> Result<()> {
+        let name = option.name.value.to_lowercase();
+        let value = match &option.value {
+            Expr::Value(Value::SingleQuotedString(s)) => s.as_str(),
+            _ => return Err(BindError::InvalidTableOption(option.to_string())),
+        };
+        match name.as_str() {
+            "bloom_filter" => {
+                for column_name in value.split(',').map(|s| s.trim().to_lowercase()) {
+                    let column = (columns.iter_mut())
+                        .find(|c| c.name() == column_name)
+                        .ok_or(BindError::InvalidColumn(column_name))?;
+                    column.set_bloom_filter(true);
+                }
+            }
+            "compression" => {
+                for item in value.split(',') {
+                    let (column_name, compression) = match item.split_once(':') {
+                        Some((column, compression)) => (Some(column.trim()), compression),
+                        None => (None, item),
+                    };
+                    let compression: Compression = (compression.trim().to_lowercase().parse())
+                        .map_err(|_| BindError::InvalidTableOption(option.to_string()))?;
+                    match column_name {
+                        Some(name) => {
+                            let name = name.to_lowercase();
+                            (columns.iter_mut())
+                                .find(|c| c.name() == name)
+                                .ok_or(BindError::InvalidColumn(name))?
+                                .set_compression(compression);
+                        }
+                        None => columns
+                            .iter_mut()
+                            .for_each(|c| c.set_compression(compression)),
+                    }
+                }
+            }
+            _ => return Err(BindError::InvalidTableOption(option.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Binds a `FOREIGN KEY` constraint on columns of the new table.
+    fn bind_foreign_key(
+        &self,
+        table: &NewTable<'_>,
+        names: &[Ident],
+        ref_table_name: &ObjectName,
+        ref_names: &[Ident],
+        on_delete: &Option<ReferentialAction>,
+        on_update: &Option<ReferentialAction>,
+    ) -> Result<ForeignKey> {
+        // rows can only be deleted when they are not referenced
+        for action in [on_delete, on_update].into_iter().flatten() {
+            if !matches!(
+                action,
+                ReferentialAction::Restrict | ReferentialAction::NoAction
+            ) {
+                return Err(BindError::Todo(format!("foreign key action {action}")));
+            }
+        }
+
+        let name = self.table_name(ref_table_name)?;
+        let (schema_name, table_name) = split_name(&name)?;
+        // the new table can reference itself, before it has an id
+        let (ref_table_id, ref_columns, primary_key, unique_keys) = if &name == table.name {
+            let id = TableRefId::new(table.schema_id, ForeignKey::SELF_TABLE_ID);
+            let (columns, primary_key) = (table.columns.to_vec(), table.primary_key.to_vec());
+            (id, columns, primary_key, table.unique_keys.to_vec())
+        } else {
+            let ref_table_id = (self.catalog)
+                .get_table_id_by_name(schema_name, table_name)
+                .ok_or_else(|| BindError::InvalidTable(table_name.into()))?;
+            let ref_table = self.catalog.get_table(&ref_table_id).unwrap();
+            if ref_table.is_system() || ref_table.is_view() {
+                return Err(BindError::InvalidForeignKey(format!(
+                    "referenced relation {table_name:?} is not a table"
+                )));
+            }
+            let columns = ref_table.all_columns().into_values().collect_vec();
+            let unique_keys = ref_table.unique_keys().to_vec();
+            (ref_table_id, columns, ref_table.primary_keys(), unique_keys)
+        };
+        let find_column = |columns: &[ColumnCatalog], name: &Ident| {
+            let name = name.value.to_lowercase();
+            (columns.iter())
+                .find(|c| c.name() == name)
+                .map(|c| c.id())
+                .ok_or(BindError::InvalidColumn(name))
+        };
+
+        let column_ids: Vec<ColumnId> = (names.iter())
+            .map(|name| find_column(table.columns, name))
+            .try_collect()?;
+        // the primary key is referenced by default
+        let ref_column_ids: Vec<ColumnId> = if ref_names.is_empty() {
+            primary_key.clone()
+        } else {
+            (ref_names.iter())
+                .map(|name| find_column(&ref_columns, name))
+                .try_collect()?
+        };
+
+        let matches_key = |key: &[ColumnId]| key.iter().sorted().eq(ref_column_ids.iter().sorted());
+        let is_unique = !ref_column_ids.is_empty()
+            && (matches_key(&primary_key) || unique_keys.iter().any(|key| matches_key(key)));
+        if !is_unique {
+            return Err(BindError::InvalidForeignKey(format!(
+                "no unique constraint matching given keys for referenced table {table_name:?}"
+            )));
+        }
+        if column_ids.len() != ref_column_ids.len() {
+            return Err(BindError::InvalidForeignKey(
+                "number of referencing and referenced columns disagree".into(),
+            ));
+        }
+        for (id, ref_id) in column_ids.iter().zip(&ref_column_ids) {
+            let column = table.columns.iter().find(|c| c.id() == *id).unwrap();
+            let ref_column = ref_columns.iter().find(|c| c.id() == *ref_id).unwrap();
+            if column.data_type() != ref_column.data_type() {
+                return Err(BindError::InvalidForeignKey(format!(
+                    "key columns {:?} and {:?} are of incompatible types",
+                    column.name(),
+                    ref_column.name()
+                )));
+            }
+        }
+
+        Ok(ForeignKey {
+            column_ids,
+            ref_table_id,
+            ref_column_ids,
+        })
+    }
+
+    /// Binds `CHECK` constraints of a table. Returns a list of conditions on its columns.
+    pub(super) fn bind_table_checks(&mut self, table_id: TableRefId) -> Result {
+        let table = self.catalog.get_table(&table_id).unwrap();
+        let columns = (table.all_columns().values())
+            .map(|column| {
+                let column_ref_id = ColumnRefId::from_table(table_id, 0, column.id());
+                let id = self.egraph.add(Node::Column(column_ref_id));
+                (column.name().to_string(), id)
+            })
+            .collect_vec();
+        let checks = (table.checks().iter())
+            .map(|check| self.bind_check(table.name(), check, &columns))
+            .try_collect()?;
+        Ok(self.egraph.add(Node::List(checks)))
+    }
+
+    /// Binds the expression of a `CHECK` constraint with the given columns in scope.
+    pub(super) fn bind_check(
+        &mut self,
+        table_name: &str,
+        check: &str,
+        columns: &[(String, Id)],
+    ) -> Result {
+        let expr = parse_expr(check).map_err(|_| BindError::InvalidCheck(check.into()))?;
+        self.contexts.push(Context::default());
+        for (name, id) in columns {
+            self.add_alias(name.clone(), table_name.into(), *id);
+        }
+        let id = self.bind_expr(expr);
+        self.contexts.pop();
+        let id = id?;
+
+        let has_subquery = (self.recexpr(id).as_ref().iter()).any(|e| matches!(e, Node::Proj(_)));
+        if has_subquery || !self.aggs(id).is_empty() || !self.overs(id).is_empty() {
+            return Err(BindError::InvalidCheck(check.into()));
+        }
+        if self.type_(id)? != crate::types::DataType::Bool {
+            return Err(BindError::InvalidCheck(check.into()));
+        }
+        Ok(id)
+    }
+
+    /// Binds a column definition, including its default value.
+    pub(super) fn bind_column_def(&mut self, cdef: &ColumnDef) -> Result<ColumnCatalog> {
+        let mut column = ColumnCatalog::from(cdef);
+        for opt in &cdef.options {
+            if let ColumnOption::Default(expr) = &opt.option {
+                let id = self.bind_expr(expr.clone())?;
+                let value = self.eval_constant(id).ok_or_else(|| {
+                    BindError::InvalidExpression(format!("default value must be constant: {expr}"))
+                })?;
+                let ty = column.data_type();
+                let value = value
+                    .cast(&ty)
+                    .map_err(|_| BindError::CastError(value.clone(), ty))?;
+                column.set_default(Some(value));
+            }
+        }
+        Ok(column)
+    }
+
+    /// Evaluates an expression consisting of constants and unary operators.
+    pub(super) fn eval_constant(&self, id: Id) -> Option<DataValue> {
+        let node = self.node(id);
+        if let Node::Constant(v) = node {
+            return Some(v.clone());
+        }
+        let (op, a) = node.unary_op()?;
+        let a = self.eval_constant(a)?;
+        if a.is_null() {
+            return Some(DataValue::Null);
+        }
+        Some(ArrayImpl::from(&a).unary_op(&op).ok()?.get(0))
+    }
+
     /// get primary keys' id in declared order。
     /// we use index in columns vector as column id
     fn ordered_pks_from_columns(columns: &[ColumnDef]) -> Vec<ColumnId> {
@@ -149,6 +573,68 @@ impl Binder {
         ordered_pks
     }
 
+    /// Returns the column ids of `UNIQUE` constraints from column options and table constraints.
+    ///
+    /// Constraints covered by the primary key or declared twice are only returned once.
+    fn unique_keys(
+        columns: &[ColumnDef],
+        constraints: &[TableConstraint],
+        ordered_pk_ids: &[ColumnId],
+    ) -> Result<Vec<Vec<ColumnId>>> {
+        let mut keys: Vec<Vec<ColumnId>> = vec![];
+        for (index, col_def) in columns.iter().enumerate() {
+            for option_def in &col_def.options {
+                if let ColumnOption::Unique {
+                    is_primary: false, ..
+                } = option_def.option
+                {
+                    keys.push(vec![index as ColumnId]);
+                }
+            }
+        }
+        for constraint in constraints {
+            if let TableConstraint::Unique { columns: names, .. } = constraint {
+                let key = (names.iter())
+                    .map(|name| {
+                        let name = name.value.to_lowercase();
+                        (columns.iter())
+                            .position(|c| c.name.value.eq_ignore_ascii_case(&name))
+                            .map(|index| index as ColumnId)
+                            .ok_or(BindError::InvalidColumn(name))
+                    })
+                    .try_collect()?;
+                keys.push(key);
+            }
+        }
+        let mut unique_keys: Vec<Vec<ColumnId>> = vec![];
+        for key in keys {
+            if key != ordered_pk_ids && !unique_keys.contains(&key) {
+                unique_keys.push(key);
+            }
+        }
+        Ok(unique_keys)
+    }
+
+    /// Returns the expressions of `CHECK` constraints from column options and table constraints.
+    fn checks(columns: &[ColumnDef], constraints: &[TableConstraint]) -> Vec<String> {
+        let column_checks = (columns.iter().flat_map(|c| &c.options)).filter_map(|option_def| {
+            match &option_def.option {
+                ColumnOption::Check(expr) => Some(expr),
+                _ => None,
+            }
+        });
+        let table_checks = constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                TableConstraint::Check { expr, .. } => Some(&**expr),
+                _ => None,
+            });
+        column_checks
+            .chain(table_checks)
+            .map(|expr| expr.to_string())
+            .collect()
+    }
+
     /// get the primary keys' name sorted by declaration order in "primary key(c1, c2..)" syntax.
     fn pks_name_from_constraints(constraints: &[TableConstraint]) -> Vec<String> {
         for constraint in constraints {
@@ -176,6 +662,10 @@ impl From<&ColumnDef> for ColumnCatalog {
                 ColumnOption::Null => is_nullable = true,
                 ColumnOption::NotNull => is_nullable = false,
                 ColumnOption::Unique { is_primary: p, .. } => is_primary = p,
+                // bound in `bind_column_def`
+                ColumnOption::Default(_) => {}
+                // bound in `bind_create_table`
+                ColumnOption::Check(_) | ColumnOption::ForeignKey { .. } => {}
                 _ => todo!("column options"),
             }
         }
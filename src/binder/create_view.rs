@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 
 use super::*;
-use crate::catalog::{ColumnCatalog, ColumnDesc, ColumnId};
+use crate::catalog::{ColumnCatalog, ColumnDesc, ColumnId, ViewDefinition};
 
 impl Binder {
     pub(super) fn bind_create_view(
@@ -10,7 +10,7 @@ impl Binder {
         columns: Vec<ViewColumnDef>,
         query: Query,
     ) -> Result {
-        let name = lower_case_name(&name);
+        let name = self.new_object_name(&lower_case_name(&name))?;
         let (schema_name, table_name) = split_name(&name)?;
         let schema = self
             .catalog
@@ -28,34 +28,79 @@ impl Binder {
             }
         }
 
-        let (query, _) = self.bind_query(query)?;
+        let definition = ViewDefinition {
+            query: query.to_string(),
+            search_path: self.search_path.clone(),
+        };
+        let (query, ctx) = self.bind_query(query)?;
         let query_type = self.type_(query)?;
         let output_types = query_type.as_struct();
 
-        // TODO: support inferring column names from query
-        if columns.len() != output_types.len() {
+        // columns are named after the output columns of the query if not given
+        let names = if columns.is_empty() {
+            let mut names: Vec<String> = vec![];
+            for (idx, id) in self.schema(query).into_iter().enumerate() {
+                let name = (self.output_name(id, &ctx)).ok_or(BindError::UnnamedColumn(idx + 1))?;
+                if names.contains(&name) {
+                    return Err(BindError::ColumnExists(name));
+                }
+                names.push(name);
+            }
+            names
+        } else if columns.len() == output_types.len() {
+            columns.into_iter().map(|c| c.name.value).collect()
+        } else {
             return Err(BindError::ViewAliasesMismatch);
-        }
+        };
 
-        let columns: Vec<ColumnCatalog> = columns
+        let columns: Vec<ColumnCatalog> = names
             .into_iter()
             .zip(output_types)
             .enumerate()
-            .map(|(idx, (column, ty))| {
-                ColumnCatalog::new(
-                    idx as ColumnId,
-                    ColumnDesc::new(column.name.value, ty.clone(), true),
-                )
+            .map(|(idx, (name, ty))| {
+                ColumnCatalog::new(idx as ColumnId, ColumnDesc::new(name, ty.clone(), true))
             })
             .collect();
 
+        // the view depends on the tables and views it refers to
+        let mut dependencies = std::mem::take(&mut self.views);
+        for node in self.recexpr(query).as_ref() {
+            if let Node::Table(table_id) = node {
+                dependencies.push(*table_id);
+            }
+        }
+        dependencies.sort();
+        dependencies.dedup();
+        let dependencies = (dependencies.into_iter())
+            .map(|id| self.egraph.add(Node::Table(id)))
+            .collect();
+        let dependencies = self.egraph.add(Node::List(dependencies));
+
         let table = self.egraph.add(Node::CreateTable(Box::new(CreateTable {
             schema_id: schema.id(),
             table_name: table_name.into(),
             columns,
             ordered_pk_ids: vec![],
+            unique_keys: vec![],
+            checks: vec![],
+            foreign_keys: vec![],
+            view: Some(definition),
         })));
-        let create_view = self.egraph.add(Node::CreateView([table, query]));
+        let create_view = self
+            .egraph
+            .add(Node::CreateView([table, dependencies, query]));
         Ok(create_view)
     }
+
+    /// Binds the query of a view from its definition.
+    pub fn bind_view_definition(&mut self, definition: &ViewDefinition) -> Result<RecExpr> {
+        let invalid = || BindError::InvalidView(definition.query.clone());
+        let stmts = parse(&definition.query).map_err(|_| invalid())?;
+        let [Statement::Query(query)] = &stmts[..] else {
+            return Err(invalid());
+        };
+        self.search_path = definition.search_path.clone();
+        let (query, _) = self.bind_query((**query).clone())?;
+        Ok(self.recexpr(query))
+    }
 }
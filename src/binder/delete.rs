@@ -3,7 +3,12 @@
 use super::*;
 
 impl Binder {
-    pub(super) fn bind_delete(&mut self, from: FromTable, selection: Option<Expr>) -> Result {
+    pub(super) fn bind_delete(
+        &mut self,
+        from: FromTable,
+        selection: Option<Expr>,
+        returning: Option<Vec<SelectItem>>,
+    ) -> Result {
         let from = match from {
             FromTable::WithFromKeyword(t) => t,
             FromTable::WithoutKeyword(t) => t,
@@ -21,6 +26,29 @@ impl Binder {
         let scan = self.bind_table_def(name, alias.clone(), true)?;
         let cond = self.bind_where(selection)?;
         let filter = self.egraph.add(Node::Filter([cond, scan]));
-        Ok(self.egraph.add(Node::Delete([table_id, filter])))
+        let returning = self.bind_returning(self.node(table_id).as_table(), returning)?;
+        Ok(self.egraph.add(Node::Delete([table_id, returning, filter])))
+    }
+
+    /// Binds `TRUNCATE TABLE`, which removes all rows of the table at once.
+    pub(super) fn bind_truncate(&mut self, name: &ObjectName) -> Result {
+        let (table_id, is_system, is_view) = self.bind_table_id(name)?;
+        if is_system || is_view {
+            return Err(BindError::CanNotDelete);
+        }
+        // rows referenced by other tables can not be removed without checking
+        let table_ref_id = self.node(table_id).as_table();
+        if let Some((referencing_id, _)) = (self.catalog.get_referencing_tables(table_ref_id))
+            .into_iter()
+            .find(|(id, _)| *id != table_ref_id)
+        {
+            let table = self.catalog.get_table(&table_ref_id).unwrap();
+            let referencing = self.catalog.get_table(&referencing_id).unwrap();
+            return Err(BindError::TruncateReferencedTable(
+                table.name().into(),
+                referencing.name().into(),
+            ));
+        }
+        Ok(self.egraph.add(Node::Truncate(table_id)))
     }
 }
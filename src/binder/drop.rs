@@ -1,6 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use super::*;
+use crate::catalog::Dependent;
 
 impl Binder {
     pub(super) fn bind_drop(
@@ -10,26 +11,84 @@ impl Binder {
         names: Vec<ObjectName>,
         cascade: bool,
     ) -> Result {
-        if !matches!(object_type, ObjectType::Table | ObjectType::View) {
+        if !matches!(
+            object_type,
+            ObjectType::Table | ObjectType::View | ObjectType::Index | ObjectType::Schema
+        ) {
             return Err(BindError::Todo(format!("drop {object_type:?}")));
         }
-        if cascade {
+        if cascade && object_type == ObjectType::Schema {
             return Err(BindError::Todo("cascade drop".into()));
         }
-        let mut table_ids = Vec::with_capacity(names.len());
+        if object_type == ObjectType::Index {
+            return self.bind_drop_index(if_exists, names);
+        }
+        if object_type == ObjectType::Schema {
+            return self.bind_drop_schema(if_exists, names);
+        }
+        let mut table_ref_ids = Vec::with_capacity(names.len());
         for name in names {
-            let name = lower_case_name(&name);
+            let name = self.table_name(&name)?;
             let (schema_name, table_name) = split_name(&name)?;
             let result = self.catalog.get_table_id_by_name(schema_name, table_name);
             if if_exists && result.is_none() {
                 continue;
             }
             let table_id = result.ok_or_else(|| BindError::InvalidTable(table_name.into()))?;
-            let id = self.egraph.add(Node::Table(table_id));
-            table_ids.push(id);
+            table_ref_ids.push(table_id);
+        }
+        // tables can be dropped together with the tables referencing them
+        for table_id in &table_ref_ids {
+            for (referencing_id, _) in self.catalog.get_referencing_tables(*table_id) {
+                if !table_ref_ids.contains(&referencing_id) {
+                    let table = self.catalog.get_table(table_id).unwrap();
+                    let referencing = self.catalog.get_table(&referencing_id).unwrap();
+                    return Err(BindError::DropReferencedTable(
+                        table.name().into(),
+                        referencing.name().into(),
+                    ));
+                }
+            }
         }
-        let list = self.egraph.add(Node::List(table_ids.into()));
+        // views depending on the dropped tables are dropped before them with `CASCADE`,
+        // otherwise the drop is refused. indexes are always dropped with their tables.
+        let graph = self.catalog.dependency_graph();
+        if !cascade {
+            for table_id in &table_ref_ids {
+                let dependents = (graph.drop_order(&[*table_id]).into_iter())
+                    .filter_map(|object| match object {
+                        Dependent::Table(id) if !table_ref_ids.contains(&id) => Some(id),
+                        _ => None,
+                    })
+                    .collect_vec();
+                if !dependents.is_empty() {
+                    let table = self.catalog.get_table(table_id).unwrap();
+                    return Err(BindError::DropDependedObject(
+                        table.name().into(),
+                        self.describe_tables(&dependents),
+                    ));
+                }
+            }
+        }
+        let table_ids = (graph.drop_order(&table_ref_ids).into_iter())
+            .filter_map(|object| match object {
+                Dependent::Table(id) => Some(self.egraph.add(Node::Table(id))),
+                Dependent::Index(..) => None,
+            })
+            .collect();
+        let list = self.egraph.add(Node::List(table_ids));
         let drop = self.egraph.add(Node::Drop(list));
         Ok(drop)
     }
+
+    /// Returns a description of tables and views for error messages, e.g. `view "v1", table "t"`.
+    pub(super) fn describe_tables(&self, tables: &[TableRefId]) -> String {
+        (tables.iter())
+            .map(|id| {
+                let table = self.catalog.get_table(id).unwrap();
+                let kind = if table.is_view() { "view" } else { "table" };
+                format!("{kind} {:?}", table.name())
+            })
+            .join(", ")
+    }
 }
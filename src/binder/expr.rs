@@ -6,24 +6,23 @@ use sqlparser::parser::Parser;
 
 use super::*;
 use crate::parser::{
-    self, BinaryOperator, DataType, DateTimeField, Expr, Function, FunctionArg, FunctionArgExpr,
-    UnaryOperator, Value,
+    self, ArrayAgg, BinaryOperator, DataType, DateTimeField, Expr, Function, FunctionArg,
+    FunctionArgExpr, JsonOperator, TrimWhereField, UnaryOperator, Value,
 };
-use crate::types::{DataValue, Interval};
+use crate::types::{DataValue, Interval, ParamIndex};
 
 impl Binder {
     /// Bind an expression.
     pub fn bind_expr(&mut self, expr: Expr) -> Result {
         let id = match expr {
             Expr::Value(v) => {
-                // This is okay since only sql udf relies on
-                // parameter-like (i.e., `$1`) values at present
-                // TODO: consider formally `bind_parameter` in the future
-                // e.g., lambda function support, etc.
+                // parameter-like (i.e., `$1`) values are arguments of sql udf,
+                // or parameters of prepared statements
                 if let Value::Placeholder(key) = v {
-                    self.udf_context
-                        .get_expr(&key)
-                        .map_or_else(|| Err(BindError::InvalidSQL), |&e| Ok(e))
+                    match self.udf_context.get_expr(&key) {
+                        Some(&e) => Ok(e),
+                        None => self.bind_param(&key),
+                    }
                 } else {
                     Ok(self.egraph.add(Node::Constant(v.into())))
                 }
@@ -47,8 +46,14 @@ impl Binder {
                 negated,
                 expr,
                 pattern,
-                ..
-            } => self.bind_like(*expr, *pattern, negated),
+                escape_char,
+            } => self.bind_like(*expr, *pattern, negated, escape_char, false),
+            Expr::ILike {
+                negated,
+                expr,
+                pattern,
+                escape_char,
+            } => self.bind_like(*expr, *pattern, negated, escape_char, true),
             Expr::Between {
                 expr,
                 negated,
@@ -57,12 +62,31 @@ impl Binder {
             } => self.bind_between(*expr, negated, *low, *high),
             Expr::Interval(interval) => self.bind_interval(interval),
             Expr::Extract { field, expr } => self.bind_extract(field, *expr),
+            Expr::JsonAccess {
+                left,
+                operator,
+                right,
+            } => self.bind_json_access(*left, operator, *right),
+            Expr::Array(array) => self.bind_array(array.elem),
+            Expr::ArrayAgg(agg) => self.bind_array_agg(agg),
+            Expr::ArrayIndex { obj, indexes } => self.bind_array_index(*obj, indexes),
             Expr::Substring {
                 expr,
                 substring_from,
                 substring_for,
                 ..
             } => self.bind_substring(*expr, substring_from, substring_for),
+            Expr::Trim {
+                expr,
+                trim_where,
+                trim_what,
+                trim_characters,
+            } => self.bind_trim(*expr, trim_where, trim_what, trim_characters),
+            Expr::Position { expr, r#in } => {
+                let substring = self.bind_expr(*expr)?;
+                let string = self.bind_expr(*r#in)?;
+                Ok(self.egraph.add(Node::Position([substring, string])))
+            }
             Expr::Case {
                 operand,
                 conditions,
@@ -121,6 +145,7 @@ impl Binder {
 
         let l = self.bind_expr(left)?;
         let r = self.bind_expr(right)?;
+        let (l, r) = (self.infer_param_type(l, r), self.infer_param_type(r, l));
         let node = match op {
             Plus => Node::Add([l, r]),
             Minus => Node::Sub([l, r]),
@@ -142,6 +167,49 @@ impl Binder {
         Ok(self.egraph.add(node))
     }
 
+    /// Binds an expression that must be a constant, e.g. a parameter of `EXECUTE`.
+    pub fn bind_constant(&mut self, expr: Expr) -> Result<DataValue> {
+        let id = self.bind_expr(expr.clone())?;
+        self.eval_constant(id)
+            .ok_or_else(|| BindError::InvalidExpression(format!("not a constant: {expr}")))
+    }
+
+    /// Binds a parameter `$n` of a prepared statement.
+    ///
+    /// The parameter is casted to its declared type if any. Otherwise its type is inferred from
+    /// the other operand of a binary operator, or unknown until execution.
+    fn bind_param(&mut self, key: &str) -> Result {
+        let Some(param_types) = &self.param_types else {
+            return Err(BindError::ParameterNotAllowed);
+        };
+        let index = (key.strip_prefix('$'))
+            .and_then(|n| n.parse::<u32>().ok())
+            .filter(|&n| n > 0)
+            .ok_or_else(|| BindError::InvalidParameter(key.into()))?;
+        let ty = param_types.get(index as usize - 1).cloned();
+        self.param_count = self.param_count.max(index as usize);
+        let param = self.egraph.add(Node::Param(ParamIndex(index)));
+        let Some(ty) = ty else {
+            return Ok(param);
+        };
+        let ty = self.egraph.add(Node::Type(ty));
+        Ok(self.egraph.add(Node::Cast([ty, param])))
+    }
+
+    /// Casts `expr` to the type of `other` if `expr` is a parameter of unknown type.
+    fn infer_param_type(&mut self, expr: Id, other: Id) -> Id {
+        if !matches!(self.node(expr), Node::Param(_)) {
+            return expr;
+        }
+        match self.type_(other) {
+            Ok(ty) if !ty.is_null() => {
+                let ty = self.egraph.add(Node::Type(ty));
+                self.egraph.add(Node::Cast([ty, expr]))
+            }
+            _ => expr,
+        }
+    }
+
     fn bind_unary_op(&mut self, op: UnaryOperator, expr: Expr) -> Result {
         use UnaryOperator::*;
         let expr = self.bind_expr(expr)?;
@@ -192,14 +260,84 @@ impl Binder {
                     .egraph
                     .add(Node::Constant(DataValue::Timestamp(timestamp))))
             }
+            DataType::JSON | DataType::JSONB => {
+                self.bind_cast(Expr::Value(Value::SingleQuotedString(value)), data_type)
+            }
             t => todo!("support typed string: {:?}", t),
         }
     }
 
-    fn bind_like(&mut self, expr: Expr, pattern: Expr, negated: bool) -> Result {
+    fn bind_json_access(&mut self, left: Expr, op: JsonOperator, right: Expr) -> Result {
+        let l = self.bind_expr(left)?;
+        let r = self.bind_expr(right)?;
+        let node = match op {
+            JsonOperator::Arrow => Node::JsonGet([l, r]),
+            JsonOperator::LongArrow => Node::JsonGetText([l, r]),
+            _ => return Err(BindError::Todo(format!("json operator: {op}"))),
+        };
+        Ok(self.egraph.add(node))
+    }
+
+    /// Binds an array constructor. Elements are casted to their common type.
+    fn bind_array(&mut self, elems: Vec<Expr>) -> Result {
+        let mut ids = vec![];
+        let mut ty = crate::types::DataType::Null;
+        for elem in elems {
+            let id = self.bind_expr(elem)?;
+            if let Some(union) = ty.union(&self.type_(id)?) {
+                ty = union;
+            }
+            ids.push(id);
+        }
+        for id in &mut ids {
+            if !ty.is_null() && self.type_(*id)? != ty {
+                let ty = self.egraph.add(Node::Type(ty.clone()));
+                *id = self.egraph.add(Node::Cast([ty, *id]));
+            }
+        }
+        let list = self.egraph.add(Node::List(ids.into()));
+        Ok(self.egraph.add(Node::Array(list)))
+    }
+
+    /// Binds subscripts `a[i][j]..`.
+    fn bind_array_index(&mut self, obj: Expr, indexes: Vec<Expr>) -> Result {
+        let mut id = self.bind_expr(obj)?;
+        for index in indexes {
+            let index = self.bind_expr(index)?;
+            id = self.egraph.add(Node::ArrayGet([id, index]));
+            // the result array has no type if all elements are null,
+            // so cast it to the element type explicitly.
+            let ty = self.type_(id)?;
+            let ty = self.egraph.add(Node::Type(ty));
+            id = self.egraph.add(Node::Cast([ty, id]));
+        }
+        Ok(id)
+    }
+
+    fn bind_like(
+        &mut self,
+        expr: Expr,
+        pattern: Expr,
+        negated: bool,
+        escape_char: Option<char>,
+        case_insensitive: bool,
+    ) -> Result {
         let expr = self.bind_expr(expr)?;
-        let pattern = self.bind_expr(pattern)?;
-        let like = self.egraph.add(Node::Like([expr, pattern]));
+        let mut pattern = self.bind_expr(pattern)?;
+        // patterns are escaped by backslash in execution
+        if let Some(escape) = escape_char
+            && escape != '\\'
+        {
+            let Node::Constant(DataValue::String(s)) = self.node(pattern) else {
+                return Err(BindError::Todo("ESCAPE with non-constant pattern".into()));
+            };
+            let s = escape_like_pattern(s, escape);
+            pattern = self.egraph.add(Node::Constant(DataValue::String(s.into())));
+        }
+        let like = match case_insensitive {
+            false => self.egraph.add(Node::Like([expr, pattern])),
+            true => self.egraph.add(Node::ILike([expr, pattern])),
+        };
         if negated {
             Ok(self.egraph.add(Node::Not(like)))
         } else {
@@ -224,16 +362,35 @@ impl Binder {
     fn bind_interval(&mut self, interval: parser::Interval) -> Result {
         let Expr::Value(Value::Number(v, _) | Value::SingleQuotedString(v)) = *interval.value
         else {
-            panic!("interval value must be number or string");
+            return Err(BindError::InvalidExpression(
+                "interval value must be number or string".into(),
+            ));
+        };
+        let cast_error = || {
+            BindError::CastError(
+                DataValue::String(v.clone().into()),
+                crate::types::DataType::Interval,
+            )
+        };
+        let value = match interval.leading_field {
+            // e.g. interval '1 day 2 hours'
+            None => v.parse().map_err(|_| cast_error())?,
+            Some(field) => {
+                let num: i32 = v.parse().map_err(|_| cast_error())?;
+                // the number in a smaller unit, e.g. months in `num` years
+                let scale = |unit: i32| num.checked_mul(unit).ok_or_else(cast_error);
+                match field {
+                    DateTimeField::Year => Interval::from_months(scale(12)?),
+                    DateTimeField::Month => Interval::from_months(num),
+                    DateTimeField::Day => Interval::from_days(num),
+                    DateTimeField::Hour => Interval::new(0, 0, scale(3600 * 1000)?),
+                    DateTimeField::Minute => Interval::new(0, 0, scale(60 * 1000)?),
+                    DateTimeField::Second => Interval::new(0, 0, scale(1000)?),
+                    f => return Err(BindError::Todo(format!("interval with leading field {f}"))),
+                }
+            }
         };
-        let num = v.parse().expect("interval value is not a number");
-        let value = DataValue::Interval(match interval.leading_field {
-            Some(DateTimeField::Day) => Interval::from_days(num),
-            Some(DateTimeField::Month) => Interval::from_months(num),
-            Some(DateTimeField::Year) => Interval::from_years(num),
-            f => todo!("Support interval with leading field: {f:?}"),
-        });
-        Ok(self.egraph.add(Node::Constant(value)))
+        Ok(self.egraph.add(Node::Constant(DataValue::Interval(value))))
     }
 
     fn bind_extract(&mut self, field: DateTimeField, expr: Expr) -> Result {
@@ -321,6 +478,35 @@ impl Binder {
         Ok(self.egraph.add(Node::Substring([expr, from, for_])))
     }
 
+    fn bind_trim(
+        &mut self,
+        expr: Expr,
+        trim_where: Option<TrimWhereField>,
+        trim_what: Option<Box<Expr>>,
+        trim_characters: Option<Vec<Expr>>,
+    ) -> Result {
+        let expr = self.bind_expr(expr)?;
+        let characters = match (trim_what, trim_characters) {
+            (Some(what), _) => self.bind_expr(*what)?,
+            (None, Some(mut characters)) if characters.len() == 1 => {
+                self.bind_expr(characters.remove(0))?
+            }
+            (None, Some(_)) => {
+                return Err(BindError::BindFunctionError(
+                    "trim requires at most 2 arguments".to_string(),
+                ))
+            }
+            (None, None) => self
+                .egraph
+                .add(Node::Constant(DataValue::String(" ".into()))),
+        };
+        Ok(self.egraph.add(match trim_where {
+            Some(TrimWhereField::Leading) => Node::Ltrim([expr, characters]),
+            Some(TrimWhereField::Trailing) => Node::Rtrim([expr, characters]),
+            Some(TrimWhereField::Both) | None => Node::Btrim([expr, characters]),
+        }))
+    }
+
     fn bind_function(&mut self, func: Function) -> Result {
         let mut args = vec![];
         for arg in func.args.clone() {
@@ -341,7 +527,9 @@ impl Binder {
         }
 
         let catalog = self.catalog();
-        let Ok((schema_name, function_name)) = split_name(&func.name) else {
+        // builtin functions are resolved even if no schema in the search path exists
+        let name = (self.function_name(&func.name)).unwrap_or_else(|_| func.name.clone());
+        let Ok((schema_name, function_name)) = split_name(&name) else {
             return Err(BindError::BindFunctionError(format!(
                 "failed to parse the function name {}",
                 func.name
@@ -403,13 +591,71 @@ impl Binder {
             return Ok(bind_result);
         }
 
-        let node = match func.name.to_string().to_lowercase().as_str() {
+        let name = func.name.to_string().to_lowercase();
+        // aggregations ignoring null values, which support DISTINCT and FILTER
+        let ignore_nulls = matches!(name.as_str(), "count" | "max" | "min" | "sum" | "avg");
+        if func.distinct && (!ignore_nulls || args.is_empty()) {
+            return Err(BindError::BindFunctionError(format!(
+                "DISTINCT is not supported in {}",
+                func.name
+            )));
+        }
+        if let Some(filter) = func.filter {
+            if !ignore_nulls {
+                return Err(BindError::BindFunctionError(format!(
+                    "FILTER is not supported in {}",
+                    func.name
+                )));
+            }
+            // `agg(x) FILTER (WHERE cond)` => `agg(CASE WHEN cond THEN x END)`
+            let cond = self.bind_expr(*filter)?;
+            if args.is_empty() {
+                // count(*) => count(1)
+                args.push(self.egraph.add(Node::Constant(DataValue::Int32(1))));
+            }
+            for arg in &mut args {
+                let null = self.egraph.add(Node::null());
+                let (then, else_) = self.implicit_type_cast(*arg, null)?;
+                *arg = self.egraph.add(Node::If([cond, then, else_]));
+            }
+        }
+
+        if !func.order_by.is_empty() && name != "string_agg" {
+            return Err(BindError::BindFunctionError(format!(
+                "ORDER BY is not supported in {}",
+                func.name
+            )));
+        }
+
+        let num_args = match name.as_str() {
+            "ceil" | "ceiling" | "floor" | "ln" => 1..=1,
+            "btrim" | "ltrim" | "rtrim" | "round" | "log" => 1..=2,
+            "strpos" | "regexp_matches" | "power" | "pow" | "nullif" => 2..=2,
+            "lpad" | "rpad" => 2..=3,
+            "coalesce" | "greatest" | "least" => 1..=usize::MAX,
+            _ => 0..=usize::MAX,
+        };
+        if !num_args.contains(&args.len()) {
+            return Err(BindError::BindFunctionError(format!(
+                "wrong number of arguments for function {}",
+                func.name
+            )));
+        }
+
+        let node = match name.as_str() {
             "count" if args.is_empty() => Node::RowCount,
             "count" if func.distinct => Node::CountDistinct(args[0]),
             "count" => Node::Count(args[0]),
+            // duplicate values don't change the minimum and maximum
             "max" => Node::Max(args[0]),
             "min" => Node::Min(args[0]),
+            "sum" if func.distinct => Node::SumDistinct(args[0]),
             "sum" => Node::Sum(args[0]),
+            "avg" if func.distinct => {
+                let sum = self.egraph.add(Node::SumDistinct(args[0]));
+                let count = self.egraph.add(Node::CountDistinct(args[0]));
+                Node::Div([sum, count])
+            }
             "avg" => {
                 let sum = self.egraph.add(Node::Sum(args[0]));
                 let count = self.egraph.add(Node::Count(args[0]));
@@ -417,8 +663,95 @@ impl Binder {
             }
             "first" => Node::First(args[0]),
             "last" => Node::Last(args[0]),
+            "string_agg" => {
+                let [expr, delimiter] = args[..] else {
+                    return Err(BindError::BindFunctionError(
+                        "string_agg requires 2 arguments".to_string(),
+                    ));
+                };
+                let orderby = self.bind_orderby(func.order_by)?;
+                Node::StringAgg([expr, delimiter, orderby])
+            }
             "replace" => Node::Replace([args[0], args[1], args[2]]),
+            "btrim" | "ltrim" | "rtrim" => {
+                let characters = match args.get(1) {
+                    Some(id) => *id,
+                    None => self
+                        .egraph
+                        .add(Node::Constant(DataValue::String(" ".into()))),
+                };
+                match name.as_str() {
+                    "btrim" => Node::Btrim([args[0], characters]),
+                    "ltrim" => Node::Ltrim([args[0], characters]),
+                    _ => Node::Rtrim([args[0], characters]),
+                }
+            }
+            "strpos" => Node::Position([args[1], args[0]]),
+            "lpad" | "rpad" => {
+                let fill = match args.get(2) {
+                    Some(id) => *id,
+                    None => self
+                        .egraph
+                        .add(Node::Constant(DataValue::String(" ".into()))),
+                };
+                match name.as_str() {
+                    "lpad" => Node::Lpad([args[0], args[1], fill]),
+                    _ => Node::Rpad([args[0], args[1], fill]),
+                }
+            }
+            "regexp_matches" => Node::RegexpMatches([args[0], args[1]]),
+            "round" => {
+                let digits = match args.get(1) {
+                    Some(id) => *id,
+                    None => self.egraph.add(Node::Constant(DataValue::Int32(0))),
+                };
+                Node::Round([args[0], digits])
+            }
+            "ceil" | "ceiling" => Node::Ceil(args[0]),
+            "floor" => Node::Floor(args[0]),
+            "power" | "pow" => Node::Power([args[0], args[1]]),
+            "ln" => Node::Ln(args[0]),
+            "log" if args.len() == 2 => {
+                // log(b, x) => ln(x) / ln(b)
+                let x = self.egraph.add(Node::Ln(args[1]));
+                let b = self.egraph.add(Node::Ln(args[0]));
+                Node::Div([x, b])
+            }
+            "log" => Node::Log(args[0]),
+            "coalesce" => {
+                // coalesce(a, b, ..) => if(isnull(a), coalesce(b, ..), a)
+                let (last, rest) = args.split_last().unwrap();
+                let mut id = *last;
+                for arg in rest.iter().rev() {
+                    let (arg, id1) = self.implicit_type_cast(*arg, id)?;
+                    let isnull = self.egraph.add(Node::IsNull(arg));
+                    id = self.egraph.add(Node::If([isnull, id1, arg]));
+                }
+                self.node(id).clone()
+            }
+            "nullif" => {
+                // nullif(a, b) => if(a = b, null, a)
+                let (a, b) = self.implicit_type_cast(args[0], args[1])?;
+                let eq = self.egraph.add(Node::Eq([a, b]));
+                let null = self.egraph.add(Node::null());
+                let (a, null) = self.implicit_type_cast(args[0], null)?;
+                Node::If([eq, null, a])
+            }
+            "greatest" | "least" => {
+                let mut id = args[0];
+                for arg in &args[1..] {
+                    let (a, b) = self.implicit_type_cast(id, *arg)?;
+                    id = self.egraph.add(match name.as_str() {
+                        "greatest" => Node::Greatest([a, b]),
+                        _ => Node::Least([a, b]),
+                    });
+                }
+                self.node(id).clone()
+            }
+            "array_length" => Node::ArrayLength(args[0]),
             "row_number" => Node::RowNumber,
+            "rank" => Node::Rank,
+            "dense_rank" => Node::DenseRank,
             name => todo!("Unsupported function: {}", name),
         };
         let mut id = self.egraph.add(node);
@@ -428,6 +761,15 @@ impl Binder {
         Ok(id)
     }
 
+    fn bind_array_agg(&mut self, agg: ArrayAgg) -> Result {
+        if agg.distinct || agg.limit.is_some() {
+            return Err(BindError::Todo("DISTINCT or LIMIT in array_agg".into()));
+        }
+        let expr = self.bind_expr(*agg.expr)?;
+        let orderby = self.bind_orderby(agg.order_by.unwrap_or_default())?;
+        Ok(self.egraph.add(Node::ArrayAgg([expr, orderby])))
+    }
+
     fn bind_window_function(&mut self, func: Id, window: WindowType) -> Result {
         let window = match window {
             WindowType::WindowSpec(window) => window,
@@ -487,3 +829,20 @@ impl From<Value> for DataValue {
         }
     }
 }
+
+/// Rewrites a LIKE pattern with a custom escape character to one escaped by backslash.
+fn escape_like_pattern(pattern: &str, escape: char) -> String {
+    let mut escaped = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == escape {
+            escaped.push('\\');
+            escaped.push(chars.next().unwrap_or(escape));
+        } else if c == '\\' {
+            escaped.push_str("\\\\");
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
@@ -0,0 +1,176 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::fmt;
+use std::str::FromStr;
+
+use pretty_xmlish::helper::delegate_fmt;
+use pretty_xmlish::Pretty;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::catalog::{ColumnId, IndexId, SchemaId};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct CreateIndex {
+    pub index_name: String,
+    pub table_id: TableRefId,
+    pub column_ids: Vec<ColumnId>,
+    /// Do nothing if an index with the same name exists.
+    pub if_not_exists: bool,
+}
+
+impl fmt::Display for CreateIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explainer = Pretty::childless_record("CreateIndex", self.pretty_index());
+        delegate_fmt(&explainer, f, String::with_capacity(1000))
+    }
+}
+
+impl CreateIndex {
+    pub fn pretty_index<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
+        let ids = Pretty::Array(self.column_ids.iter().map(Pretty::display).collect());
+        vec![
+            ("name", Pretty::display(&self.index_name)),
+            ("table", Pretty::display(&self.table_id)),
+            ("columns", ids),
+        ]
+    }
+}
+
+impl FromStr for Box<CreateIndex> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct DropIndex {
+    pub indexes: Vec<(SchemaId, IndexId)>,
+}
+
+impl fmt::Display for DropIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explainer = Pretty::childless_record("DropIndex", self.pretty_index());
+        delegate_fmt(&explainer, f, String::with_capacity(1000))
+    }
+}
+
+impl DropIndex {
+    pub fn pretty_index<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
+        let ids = (self.indexes.iter())
+            .map(|(schema_id, index_id)| Pretty::display(&format!("{schema_id}.{index_id}")))
+            .collect();
+        vec![("indexes", Pretty::Array(ids))]
+    }
+}
+
+impl FromStr for Box<DropIndex> {
+    type Err = ();
+
+    fn from_str(_s: &str) -> std::result::Result<Self, Self::Err> {
+        Err(())
+    }
+}
+
+impl Binder {
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn bind_create_index(
+        &mut self,
+        name: Option<ObjectName>,
+        table_name: ObjectName,
+        columns: Vec<OrderByExpr>,
+        unique: bool,
+        if_not_exists: bool,
+        using: Option<Ident>,
+        include: Vec<Ident>,
+        predicate: Option<Expr>,
+    ) -> Result {
+        if unique {
+            return Err(BindError::Todo("unique index".into()));
+        }
+        if using.is_some() || !include.is_empty() || predicate.is_some() {
+            return Err(BindError::Todo("index options".into()));
+        }
+        let table_name = self.table_name(&table_name)?;
+        let (schema_name, table_name) = split_name(&table_name)?;
+        let table_id = (self.catalog)
+            .get_table_id_by_name(schema_name, table_name)
+            .ok_or_else(|| BindError::InvalidTable(table_name.into()))?;
+        let table = self.catalog.get_table(&table_id).unwrap();
+        if table.is_system() || table.is_view() {
+            return Err(BindError::CanNotIndex);
+        }
+
+        let mut column_ids = vec![];
+        for column in &columns {
+            if column.asc == Some(false) || column.nulls_first.is_some() {
+                return Err(BindError::Todo("index order".into()));
+            }
+            let Expr::Identifier(ident) = &column.expr else {
+                return Err(BindError::Todo(format!(
+                    "index on expression {}",
+                    column.expr
+                )));
+            };
+            let column_name = ident.value.to_lowercase();
+            let column = (table.get_column_by_name(&column_name))
+                .ok_or_else(|| BindError::InvalidColumn(column_name.clone()))?;
+            if column_ids.contains(&column.id()) {
+                return Err(BindError::ColumnExists(column_name));
+            }
+            column_ids.push(column.id());
+        }
+
+        // the index is placed in the same schema as its table
+        let index_name = match name {
+            Some(name) => match lower_case_name(&name).0.as_slice() {
+                [index] => index.value.clone(),
+                _ => return Err(BindError::InvalidTableName(name.0)),
+            },
+            None => {
+                let mut name = table_name.to_string();
+                for column in &columns {
+                    name = format!("{name}_{}", column.expr.to_string().to_lowercase());
+                }
+                name + "_idx"
+            }
+        };
+        let schema = self.catalog.get_schema_by_id(table_id.schema_id).unwrap();
+        if !if_not_exists && schema.get_index_by_name(&index_name).is_some() {
+            return Err(BindError::IndexExists(index_name));
+        }
+
+        let create = self.egraph.add(Node::CreateIndex(Box::new(CreateIndex {
+            index_name,
+            table_id,
+            column_ids,
+            if_not_exists,
+        })));
+        Ok(create)
+    }
+
+    pub(super) fn bind_drop_index(&mut self, if_exists: bool, names: Vec<ObjectName>) -> Result {
+        let mut indexes = Vec::with_capacity(names.len());
+        for name in names {
+            let name = self.qualify_name(&lower_case_name(&name), |schema, name| {
+                schema.get_index_by_name(name).is_some()
+            })?;
+            let (schema_name, index_name) = split_name(&name)?;
+            let result = (self.catalog.get_schema_by_name(schema_name))
+                .and_then(|schema| Some((schema.id(), schema.get_index_by_name(index_name)?)));
+            let Some((schema_id, index)) = result else {
+                if if_exists {
+                    continue;
+                }
+                return Err(BindError::InvalidIndex(index_name.into()));
+            };
+            indexes.push((schema_id, index.id()));
+        }
+        let drop = self
+            .egraph
+            .add(Node::DropIndex(Box::new(DropIndex { indexes })));
+        Ok(drop)
+    }
+}
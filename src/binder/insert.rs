@@ -1,6 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use super::*;
+use crate::catalog::{ColumnId, ColumnRefId};
 use crate::parser::Query;
 
 impl Binder {
@@ -9,14 +10,130 @@ impl Binder {
         table_name: ObjectName,
         columns: Vec<Ident>,
         source: Box<Query>,
+        returning: Option<Vec<SelectItem>>,
+        on: Option<OnInsert>,
     ) -> Result {
         let (table, is_internal, is_view) = self.bind_table_id(&table_name)?;
         if is_internal || is_view {
             return Err(BindError::CanNotInsert);
         }
-        let cols = self.bind_table_columns(&table_name, &columns)?;
+        let mut cols = self.bind_table_columns(&table_name, &columns)?;
+        let table_id = self.node(table).as_table();
+        let checks = self.bind_table_checks(table_id)?;
+        let returning = self.bind_returning(table_id, returning)?;
+        let on_conflict = self.bind_on_conflict(table_id, on)?;
         let source = self.bind_query(*source)?.0;
-        let id = self.egraph.add(Node::Insert([table, cols, source]));
+
+        // validate the source against target columns
+        let types = self.type_(source)?.as_struct().to_vec();
+        let targets = self.node(cols).as_list().to_vec();
+        if types.len() > targets.len() || (!columns.is_empty() && types.len() < targets.len()) {
+            return Err(BindError::InsertColumnCountMismatch(
+                targets.len(),
+                types.len(),
+            ));
+        }
+        if types.len() < targets.len() {
+            // trailing columns are filled with default values
+            cols = self.egraph.add(Node::List(targets[..types.len()].into()));
+        }
+        for (&target, from) in targets.iter().zip(types) {
+            let to = self.type_(target)?;
+            if !from.can_assign_to(&to) {
+                return Err(TypeError::NoCast { from, to }.into());
+            }
+        }
+
+        let id = self.egraph.add(Node::Insert([
+            table,
+            cols,
+            checks,
+            returning,
+            on_conflict,
+            source,
+        ]));
         Ok(id)
     }
+
+    /// Binds the `ON CONFLICT` clause.
+    ///
+    /// Returns an [`OnConflict`](Node::OnConflict) node, or an empty list without the clause.
+    /// `SET` and `WHERE` of `DO UPDATE` refer to the existing row by the table name, and to the
+    /// row proposed for insertion by `excluded`, which is bound as the second occurrence of the
+    /// table.
+    fn bind_on_conflict(&mut self, table_id: TableRefId, on: Option<OnInsert>) -> Result {
+        let Some(on) = on else {
+            return Ok(self.egraph.add(Node::List([].into())));
+        };
+        let OnConflict {
+            conflict_target,
+            action,
+        } = match on {
+            OnInsert::OnConflict(on_conflict) => on_conflict,
+            on => return Err(BindError::Todo(format!("insert {on}"))),
+        };
+        let table = self.catalog.get_table(&table_id).unwrap();
+
+        // the conflict target must be a unique key
+        let key = match conflict_target {
+            Some(ConflictTarget::Columns(columns)) => {
+                let mut key = vec![];
+                for column in columns {
+                    let name = column.value.to_lowercase();
+                    let column = table
+                        .get_column_by_name(&name)
+                        .ok_or_else(|| BindError::InvalidColumn(name.clone()))?;
+                    key.push(column.id());
+                }
+                key
+            }
+            Some(ConflictTarget::OnConstraint(name)) => {
+                return Err(BindError::Todo(format!("on conflict on constraint {name}")));
+            }
+            None if matches!(action, OnConflictAction::DoNothing) => table.primary_keys(),
+            None => {
+                return Err(BindError::InvalidConflictTarget(
+                    "DO UPDATE requires a conflict target".into(),
+                ));
+            }
+        };
+        let is_key = |k: &[ColumnId]| {
+            !k.is_empty() && k.len() == key.len() && k.iter().all(|id| key.contains(id))
+        };
+        if !is_key(&table.primary_keys()) && !table.unique_keys().iter().any(|k| is_key(k)) {
+            return Err(BindError::InvalidConflictTarget(
+                "no unique constraint matches the columns".into(),
+            ));
+        }
+        let key = (key.into_iter())
+            .map(|id| {
+                let column_ref_id = ColumnRefId::from_table(table_id, 0, id);
+                self.egraph.add(Node::Column(column_ref_id))
+            })
+            .collect();
+        let key = self.egraph.add(Node::List(key));
+
+        let OnConflictAction::DoUpdate(DoUpdate {
+            assignments,
+            selection,
+        }) = action
+        else {
+            let values = self.egraph.add(Node::List([].into()));
+            let cond = self.egraph.add(Node::true_());
+            return Ok(self.egraph.add(Node::OnConflict([key, values, cond])));
+        };
+        self.contexts.push(Context::default());
+        for (occurrence, table_name) in [(0, table.name()), (1, "excluded")] {
+            for column in table.all_columns().values() {
+                let column_ref_id = ColumnRefId::from_table(table_id, occurrence, column.id());
+                let id = self.egraph.add(Node::Column(column_ref_id));
+                self.add_alias(column.name().into(), table_name.into(), id);
+            }
+        }
+        let values = self.bind_assignments(table_id, assignments);
+        let cond = self.bind_where(selection);
+        self.contexts.pop();
+        let values = self.egraph.add(Node::List(values?.into()));
+        Ok(self.egraph.add(Node::OnConflict([key, values, cond?])))
+    }
 }
@@ -10,23 +10,31 @@ use itertools::Itertools;
 
 use crate::array;
 use crate::catalog::function::FunctionCatalog;
-use crate::catalog::{RootCatalog, RootCatalogRef, TableRefId};
+use crate::catalog::{RootCatalog, RootCatalogRef, SchemaCatalog, TableRefId};
 use crate::parser::*;
 use crate::planner::{Expr as Node, RecExpr, TypeError, TypeSchemaAnalysis};
 
+mod alter_table;
 pub mod copy;
 mod create_function;
+mod create_schema;
 mod create_table;
 mod create_view;
 mod delete;
 mod drop;
 mod expr;
+mod index;
 mod insert;
 mod select;
 mod table;
+mod table_function;
+mod update;
 
+pub use self::alter_table::*;
 pub use self::create_function::*;
+pub use self::create_schema::*;
 pub use self::create_table::*;
+pub use self::index::*;
 
 pub type Result<T = Id> = std::result::Result<T, BindError>;
 
@@ -35,6 +43,12 @@ pub type Result<T = Id> = std::result::Result<T, BindError>;
 pub enum BindError {
     #[error("invalid schema {0:?}")]
     InvalidSchema(String),
+    #[error("schema {0:?} already exists")]
+    SchemaExists(String),
+    #[error("cannot drop schema {0:?} because it is not empty")]
+    SchemaNotEmpty(String),
+    #[error("no schema in the search path exists")]
+    NoSchemaSelected,
     #[error("invalid table {0:?}")]
     InvalidTable(String),
     #[error("invalid column {0:?}")]
@@ -43,6 +57,14 @@ pub enum BindError {
     TableExists(String),
     #[error("column {0:?} already exists")]
     ColumnExists(String),
+    #[error("invalid index {0:?}")]
+    InvalidIndex(String),
+    #[error("index {0:?} already exists")]
+    IndexExists(String),
+    #[error("invalid function {0:?}")]
+    InvalidFunction(String),
+    #[error("function {0:?} already exists")]
+    FunctionExists(String),
     #[error("duplicated alias {0:?}")]
     DuplicatedAlias(String),
     #[error("duplicate CTE name {0:?}")]
@@ -71,18 +93,26 @@ pub enum BindError {
     NestedAgg,
     #[error("WHERE clause cannot contain aggregates")]
     AggInWhere,
+    #[error("SET clause cannot contain aggregates")]
+    AggInSet,
     #[error("GROUP BY clause cannot contain aggregates")]
     AggInGroupBy,
     #[error("window function calls cannot be nested")]
     NestedWindow,
     #[error("WHERE clause cannot contain window functions")]
     WindowInWhere,
+    #[error("SET clause cannot contain window functions")]
+    WindowInSet,
     #[error("HAVING clause cannot contain window functions")]
     WindowInHaving,
     #[error("column {0:?} must appear in the GROUP BY clause or be used in an aggregate function")]
     ColumnNotInAgg(String),
     #[error("ORDER BY items must appear in the select list if DISTINCT is specified")]
     OrderKeyNotInDistinct,
+    #[error("SELECT DISTINCT ON expressions must match initial ORDER BY expressions")]
+    DistinctOnNotMatchOrderBy,
+    #[error("the combining JOIN type must be INNER or LEFT for a LATERAL reference")]
+    InvalidLateralJoin,
     #[error("{0:?} is not an aggregate function")]
     NotAgg(String),
     #[error("unsupported object name: {0:?}")]
@@ -95,10 +125,56 @@ pub enum BindError {
     CanNotInsert,
     #[error("can only delete from table")]
     CanNotDelete,
+    #[error("cannot truncate table {0:?} referenced by table {1:?}")]
+    TruncateReferencedTable(String, String),
+    #[error("can only update table")]
+    CanNotUpdate,
+    #[error("multiple assignments to column {0:?}")]
+    DuplicatedAssignment(String),
+    #[error("can only alter table")]
+    CanNotAlter,
+    #[error("cannot drop primary key column {0:?}")]
+    DropPrimaryKey(String),
+    #[error("cannot drop column {0:?} used by index {1:?}")]
+    DropIndexedColumn(String, String),
+    #[error("cannot drop column {0:?} used by a unique constraint")]
+    DropUniqueColumn(String),
+    #[error("cannot drop column {0:?} used by a check constraint")]
+    DropCheckedColumn(String),
+    #[error("invalid check constraint: {0}")]
+    InvalidCheck(String),
+    #[error("invalid view definition: {0}")]
+    InvalidView(String),
+    #[error("invalid AS OF clause: {0}")]
+    InvalidAsOf(String),
+    #[error("invalid foreign key: {0}")]
+    InvalidForeignKey(String),
+    #[error("cannot drop column {0:?} used by a foreign key")]
+    DropForeignKeyColumn(String),
+    #[error("cannot drop table {0:?} referenced by table {1:?}")]
+    DropReferencedTable(String, String),
+    #[error("cannot drop {0:?} because other objects depend on it: {1}")]
+    DropDependedObject(String, String),
+    #[error("can only create index on table")]
+    CanNotIndex,
+    #[error("invalid ON CONFLICT target: {0}")]
+    InvalidConflictTarget(String),
+    #[error("INSERT has {0} target columns but {1} expressions")]
+    InsertColumnCountMismatch(usize, usize),
+    #[error("column {0} of the query needs a name")]
+    UnnamedColumn(usize),
     #[error("VIEW aliases mismatch query result")]
     ViewAliasesMismatch,
     #[error("pragma does not exist: {0}")]
     NoPragma(String),
+    #[error("invalid table option: {0}")]
+    InvalidTableOption(String),
+    #[error("failed to read file {0:?}: {1}")]
+    ReadFile(String, String),
+    #[error("invalid parameter {0:?}")]
+    InvalidParameter(String),
+    #[error("parameters are only allowed in prepared statements")]
+    ParameterNotAllowed,
 }
 
 /// The binder resolves all expressions referring to schema objects such as
@@ -109,8 +185,16 @@ pub struct Binder {
     contexts: Vec<Context>,
     /// The number of occurrences of each table in the query.
     table_occurrences: HashMap<TableRefId, u32>,
+    /// The views inlined in the query.
+    views: Vec<TableRefId>,
     /// The context used in sql udf binding
     udf_context: UdfContext,
+    /// The declared types of parameters if binding a prepared statement.
+    param_types: Option<Vec<crate::types::DataType>>,
+    /// The max index of parameters `$n` in the statement.
+    param_count: usize,
+    /// Schemas to look up unqualified names in, in order.
+    search_path: Vec<String>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -230,11 +314,23 @@ impl UdfContext {
 
 pub fn bind_header(mut chunk: array::Chunk, stmt: &Statement) -> array::Chunk {
     let header_values = match stmt {
-        Statement::CreateTable { .. } => vec!["$create".to_string()],
-        Statement::Drop { .. } => vec!["$drop".to_string()],
-        Statement::Insert { .. } => vec!["$insert.row_counts".to_string()],
+        Statement::CreateTable { .. } | Statement::CreateIndex { .. } => {
+            vec!["$create".to_string()]
+        }
+        Statement::Drop { .. } | Statement::DropFunction { .. } => vec!["$drop".to_string()],
+        Statement::AlterTable { .. } => vec!["$alter".to_string()],
+        Statement::Truncate { .. } => vec!["$truncate".to_string()],
+        // statements with `RETURNING` output rows instead of row counts
+        Statement::Insert {
+            returning: None, ..
+        } => vec!["$insert.row_counts".to_string()],
         Statement::Explain { .. } => vec!["$explain".to_string()],
-        Statement::Delete { .. } => vec!["$delete.row_counts".to_string()],
+        Statement::Delete {
+            returning: None, ..
+        } => vec!["$delete.row_counts".to_string()],
+        Statement::Update {
+            returning: None, ..
+        } => vec!["$update.row_counts".to_string()],
         _ => Vec::new(),
     };
 
@@ -252,8 +348,8 @@ pub fn bind_header(mut chunk: array::Chunk, stmt: &Statement) -> array::Chunk {
 #[derive(Debug, Default)]
 struct Context {
     /// Defined CTEs.
-    /// cte_name -> (query_id, column_alias -> id)
-    ctes: HashMap<String, (Id, HashMap<String, Id>)>,
+    /// cte_name -> definition
+    ctes: HashMap<String, CteDef>,
     /// Table aliases that can be accessed from the current query.
     table_aliases: HashSet<String>,
     /// Column aliases that can be accessed from the current query.
@@ -264,6 +360,19 @@ struct Context {
     output_aliases: HashMap<String, Id>,
 }
 
+/// A CTE defined in the `WITH` clause.
+#[derive(Debug)]
+struct CteDef {
+    /// The bound query.
+    query: Id,
+    /// column_alias -> id
+    columns: HashMap<String, Id>,
+    /// Whether the CTE is the working table of a recursive CTE, which can only be referenced once.
+    working_table: bool,
+    /// Whether the bound query has been referenced.
+    referenced: bool,
+}
+
 impl Binder {
     /// Create a new binder.
     pub fn new(catalog: Arc<RootCatalog>) -> Self {
@@ -272,10 +381,38 @@ impl Binder {
             egraph: egg::EGraph::new(TypeSchemaAnalysis { catalog }),
             contexts: vec![Context::default()],
             table_occurrences: HashMap::new(),
+            views: vec![],
             udf_context: UdfContext::new(),
+            param_types: None,
+            param_count: 0,
+            search_path: vec![RootCatalog::DEFAULT_SCHEMA_NAME.into()],
         }
     }
 
+    /// Sets the schemas to look up unqualified names in.
+    pub fn set_search_path(&mut self, search_path: Vec<String>) {
+        self.search_path = search_path;
+    }
+
+    /// Create a new binder for a prepared statement, in which parameters `$n` are allowed.
+    ///
+    /// `param_types` are the declared types of the first parameters.
+    pub fn new_prepared(
+        catalog: Arc<RootCatalog>,
+        param_types: Vec<crate::types::DataType>,
+    ) -> Self {
+        Binder {
+            param_types: Some(param_types),
+            ..Self::new(catalog)
+        }
+    }
+
+    /// Returns the number of parameters in the bound statement, including declared ones.
+    pub fn param_count(&self) -> usize {
+        let declared = self.param_types.as_ref().map_or(0, |types| types.len());
+        self.param_count.max(declared)
+    }
+
     /// Bind a statement.
     pub fn bind(&mut self, stmt: Statement) -> Result<RecExpr> {
         let id = self.bind_stmt(stmt)?;
@@ -286,12 +423,30 @@ impl Binder {
 
     fn bind_stmt(&mut self, stmt: Statement) -> Result {
         match stmt {
+            Statement::CreateSchema {
+                schema_name,
+                if_not_exists,
+            } => self.bind_create_schema(schema_name, if_not_exists),
+            Statement::CreateTable {
+                name,
+                columns,
+                constraints,
+                with_options,
+                query: Some(query),
+                ..
+            } => {
+                if !columns.is_empty() || !constraints.is_empty() {
+                    return Err(BindError::Todo("CREATE TABLE AS with columns".into()));
+                }
+                self.bind_create_table_as(name, *query, &with_options)
+            }
             Statement::CreateTable {
                 name,
                 columns,
                 constraints,
+                with_options,
                 ..
-            } => self.bind_create_table(name, &columns, &constraints),
+            } => self.bind_create_table(name, &columns, &constraints, &with_options),
             Statement::CreateView {
                 name,
                 columns,
@@ -299,12 +454,51 @@ impl Binder {
                 ..
             } => self.bind_create_view(name, columns, *query),
             Statement::CreateFunction {
+                or_replace,
                 name,
                 args,
                 return_type,
                 params,
                 ..
-            } => self.bind_create_function(name, args, return_type, params),
+            } => self.bind_create_function(or_replace, name, args, return_type, params),
+            Statement::DropFunction {
+                if_exists,
+                func_desc,
+                option,
+            } => self.bind_drop_function(if_exists, func_desc, option),
+            Statement::CreateIndex {
+                name,
+                table_name,
+                using,
+                columns,
+                unique,
+                concurrently: false,
+                if_not_exists,
+                include,
+                nulls_distinct: None,
+                predicate,
+            } => self.bind_create_index(
+                name,
+                table_name,
+                columns,
+                unique,
+                if_not_exists,
+                using,
+                include,
+                predicate,
+            ),
+            Statement::AlterTable {
+                name,
+                if_exists,
+                only: false,
+                operations,
+                location: None,
+            } => self.bind_alter_table(name, if_exists, operations),
+            Statement::Truncate {
+                table_name,
+                partitions: None,
+                ..
+            } => self.bind_truncate(&table_name),
             Statement::Drop {
                 object_type,
                 if_exists,
@@ -316,11 +510,23 @@ impl Binder {
                 table_name,
                 columns,
                 source: Some(source),
+                returning,
+                on,
                 ..
-            } => self.bind_insert(table_name, columns, source),
+            } => self.bind_insert(table_name, columns, source, returning, on),
             Statement::Delete {
-                from, selection, ..
-            } => self.bind_delete(from, selection),
+                from,
+                selection,
+                returning,
+                ..
+            } => self.bind_delete(from, selection, returning),
+            Statement::Update {
+                table,
+                assignments,
+                from,
+                selection,
+                returning,
+            } => self.bind_update(table, assignments, from, selection, returning),
             Statement::Copy {
                 source,
                 to,
@@ -366,13 +572,9 @@ impl Binder {
     }
 
     /// Add a CTE to the current context.
-    fn add_cte(&mut self, table_name: &str, query: Id, columns: HashMap<String, Id>) -> Result<()> {
+    fn add_cte(&mut self, table_name: &str, def: CteDef) -> Result<()> {
         let context = self.contexts.last_mut().unwrap();
-        if context
-            .ctes
-            .insert(table_name.into(), (query, columns))
-            .is_some()
-        {
+        if context.ctes.insert(table_name.into(), def).is_some() {
             return Err(BindError::DuplicatedCteName(table_name.into()));
         }
         Ok(())
@@ -401,11 +603,11 @@ impl Binder {
     }
 
     /// Find an CTE.
-    fn find_cte(&self, cte_name: &str) -> Option<&(Id, HashMap<String, Id>)> {
+    fn find_cte(&mut self, cte_name: &str) -> Option<&mut CteDef> {
         self.contexts
-            .iter()
+            .iter_mut()
             .rev()
-            .find_map(|ctx| ctx.ctes.get(cte_name))
+            .find_map(|ctx| ctx.ctes.get_mut(cte_name))
     }
 
     fn type_(&self, id: Id) -> Result<crate::types::DataType> {
@@ -428,7 +630,6 @@ impl Binder {
         &self.egraph[id].nodes[0]
     }
 
-    #[allow(dead_code)]
     fn recexpr(&self, id: Id) -> RecExpr {
         self.node(id).build_recexpr(|id| self.node(id).clone())
     }
@@ -449,6 +650,48 @@ impl Binder {
         self.catalog.clone()
     }
 
+    /// Qualifies an unqualified name with a schema in the search path.
+    ///
+    /// The schema is the first one in the search path where `exists` returns true, or the first
+    /// existing one if the object exists in none of them, e.g. for creating a new object.
+    fn qualify_name(
+        &self,
+        name: &ObjectName,
+        exists: impl Fn(&SchemaCatalog, &str) -> bool,
+    ) -> Result<ObjectName> {
+        let [object] = name.0.as_slice() else {
+            return Ok(name.clone());
+        };
+        let schemas = (self.search_path.iter())
+            .filter_map(|name| self.catalog.get_schema_by_name(name))
+            .collect_vec();
+        let schema = (schemas.iter())
+            .find(|schema| exists(schema, &object.value))
+            .or(schemas.first())
+            .ok_or(BindError::NoSchemaSelected)?;
+        Ok(ObjectName(vec![Ident::new(schema.name()), object.clone()]))
+    }
+
+    /// Returns the name of an object to create, qualified with the first existing schema in the
+    /// search path.
+    fn new_object_name(&self, name: &ObjectName) -> Result<ObjectName> {
+        self.qualify_name(name, |_, _| false)
+    }
+
+    /// Returns the lower-cased name of a table or view, qualified with its schema.
+    fn table_name(&self, name: &ObjectName) -> Result<ObjectName> {
+        self.qualify_name(&lower_case_name(name), |schema, name| {
+            schema.get_table_by_name(name).is_some()
+        })
+    }
+
+    /// Returns the name of a function, qualified with its schema.
+    fn function_name(&self, name: &ObjectName) -> Result<ObjectName> {
+        self.qualify_name(name, |schema, name| {
+            schema.get_function_by_name(name).is_some()
+        })
+    }
+
     fn bind_explain(&mut self, query: Statement, analyze: bool) -> Result {
         let id = self.bind_stmt(query)?;
         let id = self.egraph.add(match analyze {
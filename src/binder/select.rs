@@ -1,7 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use super::*;
-use crate::parser::{Expr, Query, SelectItem, SetExpr};
+use crate::parser::{Expr, Query, SelectItem, SetExpr, SetOperator, SetQuantifier};
 
 impl Binder {
     /// Binds a query in a new sub-context.
@@ -15,11 +15,8 @@ impl Binder {
     /// Binds a query in the current context.
     pub(super) fn bind_query_internal(&mut self, query: Query) -> Result {
         if let Some(with) = query.with {
-            if with.recursive {
-                return Err(BindError::Todo("recursive CTE".into()));
-            }
             for cte in with.cte_tables {
-                self.bind_cte(cte)?;
+                self.bind_cte(cte, with.recursive)?;
             }
         }
         let child = match *query.body {
@@ -41,7 +38,29 @@ impl Binder {
     /// Binds a CTE definition: `alias AS query`.
     ///
     /// Returns a node of query and adds the CTE to the context.
-    fn bind_cte(&mut self, Cte { alias, query, .. }: Cte) -> Result {
+    fn bind_cte(&mut self, cte: Cte, recursive: bool) -> Result {
+        let table_alias = cte.alias.name.value.to_lowercase();
+        // a CTE in `WITH RECURSIVE` is recursive only if it is a union
+        let recursive = recursive && matches!(*cte.query.body, SetExpr::SetOperation { .. });
+        let (query, columns) = self.bind_cte_query(cte, recursive)?;
+        let def = CteDef {
+            query,
+            columns,
+            working_table: false,
+            referenced: false,
+        };
+        self.add_cte(&table_alias, def)?;
+        Ok(query)
+    }
+
+    /// Binds the query of a CTE.
+    ///
+    /// Returns a node of query and its columns.
+    fn bind_cte_query(&mut self, cte: Cte, recursive: bool) -> Result<(Id, HashMap<String, Id>)> {
+        if recursive {
+            return self.bind_recursive_cte_query(cte);
+        }
+        let Cte { alias, query, .. } = cte;
         let table_alias = alias.name.value.to_lowercase();
         let (query, ctx) = self.bind_query(*query)?;
         let mut columns = HashMap::new();
@@ -52,7 +71,7 @@ impl Binder {
             let actual_column_num = alias.columns.len();
             if actual_column_num != expected_column_num {
                 return Err(BindError::ColumnCountMismatch(
-                    table_alias.clone(),
+                    table_alias,
                     expected_column_num,
                     actual_column_num,
                 ));
@@ -70,8 +89,117 @@ impl Binder {
                 columns.insert(name, id);
             }
         }
-        self.add_cte(&table_alias, query, columns)?;
-        Ok(query)
+        Ok((query, columns))
+    }
+
+    /// Binds the query of a recursive CTE: `base UNION ALL recursive`.
+    ///
+    /// Returns a [`RecursiveUnion`](Node::RecursiveUnion) node and its columns.
+    ///
+    /// # Example
+    /// ```ignore
+    /// (recursive_union (list (cte_column base #0))
+    ///     base
+    ///     (proj (list (+ (cte_column base #0) 1))
+    ///         (working_table (list (cte_column base #0)))
+    ///     )
+    /// )
+    /// ```
+    fn bind_recursive_cte_query(
+        &mut self,
+        Cte { alias, query, .. }: Cte,
+    ) -> Result<(Id, HashMap<String, Id>)> {
+        let table_alias = alias.name.value.to_lowercase();
+        let SetExpr::SetOperation {
+            op: SetOperator::Union,
+            set_quantifier,
+            left,
+            right,
+        } = *query.body
+        else {
+            return Err(BindError::Todo("recursive CTE without UNION".into()));
+        };
+        if set_quantifier != SetQuantifier::All {
+            return Err(BindError::Todo("recursive CTE with UNION DISTINCT".into()));
+        }
+
+        // bind the non-recursive term
+        let (base, ctx) = self.bind_query(set_expr_to_query(left))?;
+        let base_schema = self.schema(base);
+        if !alias.columns.is_empty() && alias.columns.len() != base_schema.len() {
+            return Err(BindError::ColumnCountMismatch(
+                table_alias,
+                base_schema.len(),
+                alias.columns.len(),
+            ));
+        }
+        let mut cte_columns = vec![];
+        let mut columns = HashMap::new();
+        for (i, id) in base_schema.iter().enumerate() {
+            let index = self
+                .egraph
+                .add(Node::ColumnIndex(crate::types::ColumnIndex(i as _)));
+            let column = self.egraph.add(Node::CteColumn([base, index]));
+            if let Some(name) = alias.columns.get(i) {
+                // `with recursive t(a, b, ..)`
+                columns.insert(name.value.to_lowercase(), column);
+            }
+            cte_columns.push(column);
+        }
+        if alias.columns.is_empty() {
+            // `with recursive t`
+            for (name, id) in &ctx.output_aliases {
+                if let Some(i) = base_schema.iter().position(|x| x == id) {
+                    columns.insert(name.clone(), cte_columns[i]);
+                }
+            }
+        }
+        let cte_columns = self.egraph.add(Node::List(cte_columns.into()));
+
+        // bind the recursive term, where the CTE refers to the working table
+        let working_table = self.egraph.add(Node::WorkingTable(cte_columns));
+        self.contexts.push(Context::default());
+        let def = CteDef {
+            query: working_table,
+            columns: columns.clone(),
+            working_table: true,
+            referenced: false,
+        };
+        self.add_cte(&table_alias, def)?;
+        let ret = self.bind_query(set_expr_to_query(right));
+        self.contexts.pop();
+        let (mut recursive, _) = ret?;
+
+        // cast the recursive term to the types of the base term
+        let recursive_schema = self.schema(recursive);
+        if recursive_schema.len() != base_schema.len() {
+            return Err(BindError::ColumnCountMismatch(
+                table_alias,
+                base_schema.len(),
+                recursive_schema.len(),
+            ));
+        }
+        let mut casted = false;
+        let mut exprs = vec![];
+        for (&id, &base_id) in recursive_schema.iter().zip(&base_schema) {
+            let ty = self.type_(base_id)?;
+            if self.type_(id)? == ty {
+                exprs.push(id);
+                continue;
+            }
+            let ty = self.egraph.add(Node::Type(ty));
+            exprs.push(self.egraph.add(Node::Cast([ty, id])));
+            casted = true;
+        }
+        if casted {
+            let exprs = self.egraph.add(Node::List(exprs.into()));
+            recursive = self.egraph.add(Node::Proj([exprs, recursive]));
+        }
+
+        let query = self
+            .egraph
+            .add(Node::RecursiveUnion([cte_columns, base, recursive]));
+        Ok((query, columns))
     }
 
     fn bind_select(&mut self, select: Select, order_by: Vec<OrderByExpr>) -> Result {
@@ -85,6 +213,7 @@ impl Binder {
         };
         let having = self.bind_having(select.having)?;
         let orderby = self.bind_orderby(order_by)?;
+        let distinct_on = matches!(select.distinct, Some(Distinct::On(_)));
         let distinct = match select.distinct {
             None => self.egraph.add(Node::List([].into())),
             Some(Distinct::Distinct) => projection,
@@ -96,11 +225,15 @@ impl Binder {
         plan = self.egraph.add(Node::Filter([where_, plan]));
         let mut to_rewrite = [projection, distinct, having, orderby];
         plan = self.plan_agg(&mut to_rewrite, groupby, plan)?;
-        let [mut projection, distinct, mut having, orderby] = to_rewrite;
+        let [mut projection, distinct, mut having, mut orderby] = to_rewrite;
         self.plan_apply(&mut having, &mut plan);
         plan = self.egraph.add(Node::Filter([having, plan]));
+        self.plan_apply(&mut projection, &mut plan);
         plan = self.plan_window(projection, distinct, orderby, plan)?;
-        plan = self.plan_distinct(distinct, orderby, &mut projection, plan)?;
+        plan = match distinct_on {
+            true => self.plan_distinct_on(distinct, &mut orderby, &mut projection, plan)?,
+            false => self.plan_distinct(distinct, orderby, &mut projection, plan)?,
+        };
         plan = self.egraph.add(Node::Order([orderby, plan]));
         plan = self.egraph.add(Node::Proj([projection, plan]));
         Ok(plan)
@@ -186,15 +319,30 @@ impl Binder {
         let mut orderby = Vec::with_capacity(order_by.len());
         for e in order_by {
             let expr = self.bind_expr(e.expr)?;
-            let key = match e.asc {
-                Some(true) | None => expr,
-                Some(false) => self.egraph.add(Node::Desc(expr)),
+            let desc = e.asc == Some(false);
+            let mut key = match desc {
+                false => expr,
+                true => self.egraph.add(Node::Desc(expr)),
             };
+            // NULL is the smallest value, so only the opposite placement is marked
+            match e.nulls_first {
+                Some(true) if desc => key = self.egraph.add(Node::NullsFirst(key)),
+                Some(false) if !desc => key = self.egraph.add(Node::NullsLast(key)),
+                _ => {}
+            }
             orderby.push(key);
         }
         Ok(self.egraph.add(Node::List(orderby.into())))
     }
 
+    /// Returns the expression of an order key, e.g. `a` for `(nulls_first (desc a))`.
+    fn order_key_expr(&self, mut id: Id) -> Id {
+        while let Node::Desc(key) | Node::NullsFirst(key) | Node::NullsLast(key) = self.node(id) {
+            id = *key;
+        }
+        id
+    }
+
     /// Binds the VALUES clause. Returns a [`Values`](Node::Values) plan.
     fn bind_values(&mut self, values: Values) -> Result {
         let values = values.rows;
@@ -309,28 +457,85 @@ impl Binder {
         }
         // make sure all ORDER BY items are in DISTINCT list.
         for id in self.node(orderby).as_list() {
-            // id = key or (desc key)
-            let key = match self.node(*id) {
-                Node::Desc(id) => id,
-                _ => id,
-            };
-            if !distinct_on.contains(key) {
+            let key = self.order_key_expr(*id);
+            if !distinct_on.contains(&key) {
                 return Err(BindError::OrderKeyNotInDistinct);
             }
         }
-        // for all projection items that are not in DISTINCT list,
-        // wrap them with first() aggregation.
+        let aggs = self.wrap_first(&distinct_on, projection);
+        Ok(self.egraph.add(Node::HashAgg([distinct, aggs, plan])))
+    }
+
+    /// Generate a plan for DISTINCT ON, which keeps the first row of each group of rows
+    /// with equal `distinct` keys according to ORDER BY.
+    ///
+    /// The leftmost ORDER BY items must be the DISTINCT ON expressions, and the rest of them
+    /// decide which row comes first in each group. Rows are sorted by all ORDER BY items
+    /// before a [`SortAgg`](Node::SortAgg), then the `orderby` is truncated to the
+    /// DISTINCT ON expressions, as only one row is left in each group.
+    ///
+    /// Without ORDER BY, an arbitrary row of each group is returned as [`plan_distinct`] does.
+    ///
+    /// [`plan_distinct`]: Self::plan_distinct
+    ///
+    /// # Example
+    /// ```ignore
+    /// distinct=(list a)
+    /// orderby=(list (desc a) b)
+    /// projection=(list a c)
+    /// output=(sortagg (list a) (list (first c)) (order (list (desc a) b) plan))
+    /// orderby=(list (desc a))
+    /// ```
+    fn plan_distinct_on(
+        &mut self,
+        distinct: Id,
+        orderby: &mut Id,
+        projection: &mut Id,
+        plan: Id,
+    ) -> Result {
+        let order_keys = self.node(*orderby).as_list().to_vec();
+        if order_keys.is_empty() {
+            return self.plan_distinct(distinct, *orderby, projection, plan);
+        }
+        let distinct_on = self.node(distinct).as_list().to_vec();
+        let keys: Vec<Id> = (order_keys.iter())
+            .map(|id| self.order_key_expr(*id))
+            .collect();
+        // all DISTINCT ON expressions must come before other ORDER BY items,
+        // unless there are no other items
+        let prefix = keys.iter().take_while(|k| distinct_on.contains(k)).count();
+        if prefix < keys.len() && !distinct_on.iter().all(|k| keys[..prefix].contains(k)) {
+            return Err(BindError::DistinctOnNotMatchOrderBy);
+        }
+        // sort by DISTINCT ON expressions first, including those not in ORDER BY
+        let mut sort_keys = order_keys[..prefix].to_vec();
+        for id in &distinct_on {
+            if !keys[..prefix].contains(id) && !sort_keys.contains(id) {
+                sort_keys.push(*id);
+            }
+        }
+        sort_keys.extend_from_slice(&order_keys[prefix..]);
+        let sort_keys = self.egraph.add(Node::List(sort_keys.into()));
+        let plan = self.egraph.add(Node::Order([sort_keys, plan]));
+
+        let aggs = self.wrap_first(&distinct_on, projection);
+        *orderby = self.egraph.add(Node::List(order_keys[..prefix].into()));
+        Ok(self.egraph.add(Node::SortAgg([distinct, aggs, plan])))
+    }
+
+    /// Wraps all items in `projection` that are not in `keys` with a [`first`](Node::First)
+    /// aggregation. Returns the list of these aggregations.
+    fn wrap_first(&mut self, keys: &[Id], projection: &mut Id) -> Id {
         let mut aggs = vec![];
         let mut projs = self.node(*projection).as_list().to_vec();
         for id in &mut projs {
-            if !distinct_on.contains(id) {
+            if !keys.contains(id) {
                 *id = self.egraph.add(Node::First(*id));
                 aggs.push(*id);
             }
         }
-        let aggs = self.egraph.add(Node::List(aggs.into()));
         *projection = self.egraph.add(Node::List(projs.into()));
-        Ok(self.egraph.add(Node::HashAgg([distinct, aggs, plan])))
+        self.egraph.add(Node::List(aggs.into()))
     }
 
     /// Extracts all over nodes from `projection`, `distinct` and `orderby`.
@@ -375,3 +580,18 @@ impl Binder {
         *id = self.egraph.add(expr);
     }
 }
+
+/// Wraps a set expression as a query.
+fn set_expr_to_query(body: Box<SetExpr>) -> Query {
+    Query {
+        with: None,
+        body,
+        order_by: vec![],
+        limit: None,
+        limit_by: vec![],
+        offset: None,
+        fetch: None,
+        locks: vec![],
+        for_clause: None,
+    }
+}
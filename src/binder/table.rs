@@ -3,7 +3,8 @@
 use std::vec::Vec;
 
 use super::*;
-use crate::catalog::{ColumnRefId, RootCatalog};
+use crate::catalog::{ColumnRefId, TableCatalog};
+use crate::types::DataType;
 
 impl Binder {
     /// Binds the FROM clause. Returns a nested [`Join`](Node::Join) plan of tables.
@@ -12,14 +13,7 @@ impl Binder {
     pub(super) fn bind_from(&mut self, tables: Vec<TableWithJoins>) -> Result {
         let mut node = None;
         for table in tables {
-            let table_node = self.bind_table_with_joins(table)?;
-            node = Some(if let Some(node) = node {
-                let ty = self.egraph.add(Node::Inner);
-                let expr = self.egraph.add(Node::true_());
-                self.egraph.add(Node::Join([ty, expr, node, table_node]))
-            } else {
-                table_node
-            });
+            node = Some(self.bind_table_with_joins(table, node)?);
         }
         if let Some(node) = node {
             Ok(node)
@@ -42,16 +36,60 @@ impl Binder {
     ///     (scan $3 (list $3.1 $3.2) null)
     /// )
     /// ```
-    fn bind_table_with_joins(&mut self, tables: TableWithJoins) -> Result {
-        let mut node = self.bind_table_factor(tables.relation)?;
+    ///
+    /// If `left` is given, it is cross joined with the tables.
+    fn bind_table_with_joins(&mut self, tables: TableWithJoins, left: Option<Id>) -> Result {
+        let mut node = match left {
+            // `t, LATERAL x JOIN u` is bound as `(t, LATERAL x) JOIN u`, so that `x` can refer to
+            // `t`
+            Some(left) if is_lateral(&tables.relation) => {
+                let right = self.bind_table_factor(tables.relation)?;
+                let ty = self.egraph.add(Node::Inner);
+                let condition = self.egraph.add(Node::true_());
+                self.plan_lateral(ty, condition, left, right)?
+            }
+            Some(left) => {
+                let right = self.bind_table_with_joins(tables, None)?;
+                let ty = self.egraph.add(Node::Inner);
+                let condition = self.egraph.add(Node::true_());
+                return Ok(self.egraph.add(Node::Join([ty, condition, left, right])));
+            }
+            None => self.bind_table_factor(tables.relation)?,
+        };
         for join in tables.joins {
+            let lateral = is_lateral(&join.relation);
             let table = self.bind_table_factor(join.relation)?;
             let (ty, condition) = self.bind_join_op(join.join_operator)?;
-            node = self.egraph.add(Node::Join([ty, condition, node, table]));
+            node = match lateral {
+                true => self.plan_lateral(ty, condition, node, table)?,
+                false => self.egraph.add(Node::Join([ty, condition, node, table])),
+            };
         }
         Ok(node)
     }
 
+    /// Returns an [`Apply`](Node::Apply) plan joining a LATERAL item `right` to `left`.
+    ///
+    /// The `right` plan may refer to columns of `left`. Only inner and left outer joins are
+    /// allowed, and the apply will be rewritten to a join by the optimizer.
+    ///
+    /// # Example
+    /// - `t JOIN LATERAL x ON cond` => `(filter cond (apply inner t x))`
+    /// - `t LEFT JOIN LATERAL x ON cond` => `(apply left_outer t (filter cond x))`
+    fn plan_lateral(&mut self, ty: Id, condition: Id, left: Id, right: Id) -> Result {
+        match self.node(ty) {
+            Node::Inner => {
+                let apply = self.egraph.add(Node::Apply([ty, left, right]));
+                Ok(self.egraph.add(Node::Filter([condition, apply])))
+            }
+            Node::LeftOuter => {
+                let right = self.egraph.add(Node::Filter([condition, right]));
+                Ok(self.egraph.add(Node::Apply([ty, left, right])))
+            }
+            _ => Err(BindError::InvalidLateralJoin),
+        }
+    }
+
     /// Returns a `Scan` plan of table or a plan of subquery.
     ///
     /// # Example
@@ -59,7 +97,35 @@ impl Binder {
     /// - `bind_table_factor(select 1)` => `(values (1))`
     fn bind_table_factor(&mut self, table: TableFactor) -> Result {
         match table {
+            // 't AS OF <version>' is parsed as 't(as_of => <version>)'
+            TableFactor::Table {
+                name,
+                alias,
+                args: Some(args),
+                ..
+            } if matches!(&args[..], [FunctionArg::Named { name, .. }] if name.value == AS_OF_ARG) =>
+            {
+                let Some(FunctionArg::Named {
+                    arg: FunctionArgExpr::Expr(version),
+                    ..
+                }) = args.into_iter().next()
+                else {
+                    return Err(BindError::InvalidAsOf("expected an expression".into()));
+                };
+                self.bind_table_as_of(&name, version, alias)
+            }
+            TableFactor::Table {
+                name,
+                alias,
+                args: Some(args),
+                ..
+            } => self.bind_table_function(&name, args, alias),
             TableFactor::Table { name, alias, .. } => self.bind_table_def(&name, alias, false),
+            TableFactor::Derived {
+                subquery, alias, ..
+            } if matches!(*subquery.body, SetExpr::Values(_)) => {
+                self.bind_values_table(*subquery, alias)
+            }
             TableFactor::Derived {
                 subquery, alias, ..
             } => {
@@ -82,10 +148,144 @@ impl Binder {
                 }
                 Ok(id)
             }
+            TableFactor::Function {
+                name, args, alias, ..
+            } => self.bind_table_function(&name, args, alias),
+            TableFactor::UNNEST {
+                alias, array_exprs, ..
+            } => self.bind_unnest(array_exprs, alias),
             _ => panic!("bind table ref"),
         }
     }
 
+    /// Binds a VALUES query in the FROM clause as a table. Returns a
+    /// [`ValuesScan`](Node::ValuesScan) plan.
+    ///
+    /// Columns are named by the alias, or `column1`, `column2`, ... if not given. They refer to
+    /// the VALUES query, instead of the expressions in its first row.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // (VALUES (1, 'a'), (2, 'b')) AS t(x, y)
+    /// (values_scan
+    ///     (limit null 0 (values (list 1 'a') (list 2 'b')))
+    ///     (list (cte_column (limit ..) #0) (cte_column (limit ..) #1))
+    /// )
+    /// ```
+    fn bind_values_table(&mut self, query: Query, alias: Option<TableAlias>) -> Result {
+        let (query, _) = self.bind_query(query)?;
+        let (table_name, names) = match alias {
+            Some(alias) => (alias.name.value, alias.columns),
+            None => (String::new(), vec![]),
+        };
+        let len = self.schema(query).len();
+        if names.len() > len {
+            return Err(BindError::ColumnCountMismatch(table_name, len, names.len()));
+        }
+        let mut columns = vec![];
+        for i in 0..len {
+            let index = self
+                .egraph
+                .add(Node::ColumnIndex(crate::types::ColumnIndex(i as _)));
+            let column = self.egraph.add(Node::CteColumn([query, index]));
+            let name = match names.get(i) {
+                Some(name) => name.value.to_lowercase(),
+                None => format!("column{}", i + 1),
+            };
+            self.add_alias(name, table_name.clone(), column);
+            columns.push(column);
+        }
+        let columns = self.egraph.add(Node::List(columns.into()));
+        Ok(self.egraph.add(Node::ValuesScan([query, columns])))
+    }
+
+    /// Returns a `ScanAsOf` plan that reads a historical version of the table.
+    ///
+    /// The version is a constant integer for an epoch, or a timestamp. Strings are cast to
+    /// timestamps.
+    ///
+    /// # Example
+    /// - `bind_table_as_of(t, 1)` => `(scan_as_of $1 (list $1.1 $1.2) 1)`
+    fn bind_table_as_of(
+        &mut self,
+        name: &ObjectName,
+        version: Expr,
+        alias: Option<TableAlias>,
+    ) -> Result {
+        let mut version = self.bind_expr(version)?;
+        let mut ty = self.type_(version)?;
+        if ty == DataType::String {
+            ty = DataType::Timestamp;
+            let type_ = self.egraph.add(Node::Type(ty.clone()));
+            version = self.egraph.add(Node::Cast([type_, version]));
+        }
+        if !matches!(
+            ty,
+            DataType::Int16
+                | DataType::Int32
+                | DataType::Int64
+                | DataType::Timestamp
+                | DataType::TimestampTz
+        ) {
+            return Err(BindError::InvalidAsOf(format!(
+                "expected an epoch or a timestamp, found {ty}"
+            )));
+        }
+        let expr = self.recexpr(version);
+        if (expr.as_ref().iter()).any(|node| matches!(node, Node::Column(_) | Node::Ref(_))) {
+            return Err(BindError::InvalidAsOf(format!(
+                "expected a constant, found {expr}"
+            )));
+        }
+
+        let scan = self.bind_table_def(name, alias, false)?;
+        let Node::Scan([table, columns, _]) = self.node(scan).clone() else {
+            return Err(BindError::InvalidAsOf(format!("{name} is not a table")));
+        };
+        let table_id = self.node(table).as_table();
+        if self.catalog.get_table(&table_id).unwrap().is_system() {
+            return Err(BindError::InvalidAsOf(format!("{name} is a system table")));
+        }
+        Ok(self.egraph.add(Node::ScanAsOf([table, columns, version])))
+    }
+
+    /// Returns a `ProjectSet` plan that expands arrays into rows.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // unnest(array[1, 2])
+    /// (project_set
+    ///     (list (unnest (array (list 1 2))))
+    ///     (values (list 0))
+    /// )
+    /// ```
+    fn bind_unnest(&mut self, array_exprs: Vec<Expr>, alias: Option<TableAlias>) -> Result {
+        let mut unnests = vec![];
+        for expr in array_exprs {
+            let array = self.bind_expr(expr)?;
+            let unnest = self.egraph.add(Node::Unnest(array));
+            self.type_(unnest)?;
+            unnests.push(unnest);
+        }
+        let table_name = alias.as_ref().map_or("".into(), |a| a.name.value.clone());
+        let columns = alias.map_or(vec![], |a| a.columns);
+        for (i, id) in unnests.iter().enumerate() {
+            let name = match columns.get(i) {
+                Some(column) => column.value.to_lowercase(),
+                None if unnests.len() == 1 && !table_name.is_empty() => table_name.clone(),
+                None => "unnest".into(),
+            };
+            let id = self.wrap_ref(*id);
+            self.add_alias(name, table_name.clone(), id);
+        }
+
+        let zero = self.egraph.add(Node::zero());
+        let row = self.egraph.add(Node::List([zero].into()));
+        let values = self.egraph.add(Node::Values([row].into()));
+        let exprs = self.egraph.add(Node::List(unnests.into()));
+        Ok(self.egraph.add(Node::ProjectSet([exprs, values])))
+    }
+
     fn bind_join_op(&mut self, op: JoinOperator) -> Result<(Id, Id)> {
         use JoinOperator::*;
         match op {
@@ -150,7 +350,7 @@ impl Binder {
         with_rowid: bool,
     ) -> Result {
         let name = lower_case_name(name);
-        let (schema_name, table_name) = split_name(&name)?;
+        let (_, table_name) = split_name(&name)?;
 
         // check duplicated alias
         let table_alias = match &alias {
@@ -160,7 +360,27 @@ impl Binder {
         self.add_table_alias(table_alias)?;
 
         // find cte
-        if let Some((query, columns)) = self.find_cte(table_name).cloned() {
+        if let Some(cte) = self.find_cte(table_name) {
+            let (query, columns) = (cte.query, cte.columns.clone());
+            let (query, columns) = if !std::mem::replace(&mut cte.referenced, true) {
+                (query, columns)
+            } else if !cte.working_table {
+                // copy the bound query to distinguish columns of different references
+                let mut occurrences = HashMap::new();
+                let expr = self.recexpr(query);
+                let query = self.add_with_new_occurrences(&expr, &mut occurrences);
+                let columns: HashMap<_, _> = (columns.into_iter())
+                    .map(|(name, id)| {
+                        let expr = self.recexpr(id);
+                        (name, self.add_with_new_occurrences(&expr, &mut occurrences))
+                    })
+                    .collect();
+                (query, columns)
+            } else {
+                return Err(BindError::Todo(format!(
+                    "multiple references to recursive CTE \"{table_name}\""
+                )));
+            };
             // add column aliases
             for (column_name, id) in columns {
                 self.add_alias(column_name, table_alias.into(), id);
@@ -169,12 +389,17 @@ impl Binder {
         }
 
         // find table in catalog
+        let name = self.table_name(&name)?;
+        let (schema_name, _) = split_name(&name)?;
         let ref_id = self
             .catalog
             .get_table_id_by_name(schema_name, table_name)
             .ok_or_else(|| BindError::InvalidTable(table_name.into()))?;
 
         let table = self.catalog.get_table(&ref_id).unwrap();
+        if table.is_view() {
+            return self.bind_view(ref_id, &table, table_alias);
+        }
         let table_occurence = {
             let count = self.table_occurrences.entry(ref_id).or_default();
             std::mem::replace(count, *count + 1)
@@ -200,6 +425,55 @@ impl Binder {
         Ok(scan)
     }
 
+    /// Inlines the query of a view.
+    ///
+    /// Tables in the query are given new occurrences, so that multiple references to the same view
+    /// are distinguished from each other.
+    fn bind_view(&mut self, ref_id: TableRefId, view: &TableCatalog, view_alias: &str) -> Result {
+        self.views.push(ref_id);
+        let query = self.add_with_new_occurrences(view.query().unwrap(), &mut HashMap::new());
+
+        // add column aliases
+        for (column, id) in view.all_columns().values().zip(self.schema(query)) {
+            let id = self.wrap_ref(id);
+            self.add_alias(column.name().into(), view_alias.into(), id);
+        }
+        Ok(query)
+    }
+
+    /// Adds an expression where each table is given a new occurrence, so that its columns are
+    /// different from the columns of the original expression.
+    ///
+    /// `occurrences` maps each occurrence of tables in the original expression to the new one.
+    /// Share it between expressions that should refer to the same columns.
+    fn add_with_new_occurrences(
+        &mut self,
+        expr: &RecExpr,
+        occurrences: &mut HashMap<(TableRefId, u32), u32>,
+    ) -> Id {
+        let mut new_expr = RecExpr::default();
+        for node in expr.as_ref() {
+            let node = match node {
+                Node::Column(column) => {
+                    let table = TableRefId::new(column.schema_id, column.table_id);
+                    let occurrence = *occurrences
+                        .entry((table, column.table_occurrence))
+                        .or_insert_with(|| {
+                            let count = self.table_occurrences.entry(table).or_default();
+                            std::mem::replace(count, *count + 1)
+                        });
+                    Node::Column(ColumnRefId {
+                        table_occurrence: occurrence,
+                        ..*column
+                    })
+                }
+                node => node.clone(),
+            };
+            new_expr.add(node);
+        }
+        self.egraph.add_expr(&new_expr)
+    }
+
     /// Returns a list of given columns in the table.
     ///
     /// If `columns` is empty, returns all columns in the table.
@@ -214,7 +488,7 @@ impl Binder {
         table_name: &ObjectName,
         columns: &[Ident],
     ) -> Result {
-        let name = lower_case_name(table_name);
+        let name = self.table_name(table_name)?;
         let (schema_name, table_name) = split_name(&name)?;
 
         let table_ref_id = self
@@ -253,7 +527,7 @@ impl Binder {
     /// # Example
     /// - `bind_table_id(t)` => `$1`
     pub(super) fn bind_table_id(&mut self, table_name: &ObjectName) -> Result<(Id, bool, bool)> {
-        let name = lower_case_name(table_name);
+        let name = self.table_name(table_name)?;
         let (schema_name, table_name) = split_name(&name)?;
 
         let table_ref_id = self
@@ -262,12 +536,76 @@ impl Binder {
             .ok_or_else(|| BindError::InvalidTable(table_name.into()))?;
         let table = self.catalog.get_table(&table_ref_id).unwrap();
         let id = self.egraph.add(Node::Table(table_ref_id));
-        Ok((
-            id,
-            table_ref_id.schema_id == RootCatalog::SYSTEM_SCHEMA_ID,
-            table.is_view(),
-        ))
+        Ok((id, table.is_system(), table.is_view()))
+    }
+
+    /// Binds the `RETURNING` clause of a statement modifying the table.
+    ///
+    /// Returns a list of expressions on columns of the table, which is empty without the clause.
+    pub(super) fn bind_returning(
+        &mut self,
+        table_id: TableRefId,
+        returning: Option<Vec<SelectItem>>,
+    ) -> Result {
+        let Some(items) = returning else {
+            return Ok(self.egraph.add(Node::List([].into())));
+        };
+        let table = self.catalog.get_table(&table_id).unwrap();
+        let columns = (table.all_columns().values())
+            .map(|column| {
+                let column_ref_id = ColumnRefId::from_table(table_id, 0, column.id());
+                (
+                    column.name().to_string(),
+                    self.egraph.add(Node::Column(column_ref_id)),
+                )
+            })
+            .collect_vec();
+
+        self.contexts.push(Context::default());
+        for (name, id) in &columns {
+            self.add_alias(name.clone(), table.name().into(), *id);
+        }
+        let list = self.bind_returning_items(items, &columns);
+        self.contexts.pop();
+        Ok(self.egraph.add(Node::List(list?.into())))
     }
+
+    fn bind_returning_items(
+        &mut self,
+        items: Vec<SelectItem>,
+        columns: &[(String, Id)],
+    ) -> Result<Vec<Id>> {
+        let mut list = vec![];
+        for item in items {
+            let expr = match item {
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => expr,
+                SelectItem::Wildcard(_) => {
+                    list.extend(columns.iter().map(|(_, id)| *id));
+                    continue;
+                }
+                item => return Err(BindError::Todo(format!("returning {item}"))),
+            };
+            let sql = expr.to_string();
+            let id = self.bind_expr(expr)?;
+            if !self.aggs(id).is_empty() || !self.overs(id).is_empty() {
+                return Err(BindError::InvalidExpression(sql));
+            }
+            list.push(id);
+        }
+        Ok(list)
+    }
+}
+
+/// Returns true if the table factor can refer to columns of preceding FROM items.
+///
+/// As in Postgres, function calls are lateral without the `LATERAL` keyword.
+fn is_lateral(table: &TableFactor) -> bool {
+    matches!(
+        table,
+        TableFactor::Derived { lateral: true, .. }
+            | TableFactor::Function { .. }
+            | TableFactor::UNNEST { .. }
+    )
 }
 
 #[cfg(test)]
@@ -275,7 +613,7 @@ mod tests {
     use std::sync::Arc;
 
     use super::*;
-    use crate::catalog::{ColumnCatalog, ColumnDesc, RootCatalog};
+    use crate::catalog::{ColumnCatalog, ColumnDesc, Constraints, RootCatalog};
     use crate::parser::parse;
     use crate::types::DataType;
 
@@ -284,7 +622,7 @@ mod tests {
         let catalog = Arc::new(RootCatalog::new());
         let col_catalog = ColumnCatalog::new(0, ColumnDesc::new("a", DataType::Int32, false));
         catalog
-            .add_table(1, "t".into(), vec![col_catalog], vec![])
+            .add_table(1, "t".into(), vec![col_catalog], Constraints::default())
             .unwrap();
 
         let stmts = parse("select x.b from (select a as b from t) as x").unwrap();
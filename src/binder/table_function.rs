@@ -0,0 +1,385 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::fs::File;
+use std::path::Path;
+
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use super::copy::{ExtSource, FileFormat};
+use super::*;
+use crate::types::{ColumnIndex, DataType, DataValue, Date, Timestamp};
+
+/// The number of rows to read from a CSV file to infer types of columns.
+const CSV_INFER_ROWS: usize = 1000;
+
+impl Binder {
+    /// Binds a table function that reads a file, e.g. `read_csv('t.csv')`.
+    ///
+    /// The schema of the file is inferred when binding. Columns of the file refer to the
+    /// `copy_from` node that reads the file, in the same way as columns of a recursive CTE.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // read_csv('t.csv')
+    /// (file_scan path="t.csv"
+    ///     (list (cte_column (copy_from path="t.csv" struct(int, string)) #0)
+    ///           (cte_column (copy_from path="t.csv" struct(int, string)) #1))
+    /// )
+    /// ```
+    pub(super) fn bind_table_function(
+        &mut self,
+        name: &ObjectName,
+        args: Vec<FunctionArg>,
+        alias: Option<TableAlias>,
+    ) -> Result {
+        let func_name = name.to_string().to_lowercase();
+        if matches!(func_name.as_str(), "generate_series" | "range") {
+            return self.bind_generate_series(func_name, args, alias);
+        }
+        let mut path = None;
+        let mut options = HashMap::new();
+        for arg in args {
+            match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) if path.is_none() => {
+                    path = Some(expr)
+                }
+                FunctionArg::Named {
+                    name,
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                } => {
+                    options.insert(name.value.to_lowercase(), expr);
+                }
+                arg => return Err(BindError::InvalidExpression(arg.to_string())),
+            }
+        }
+        let Some(Expr::Value(Value::SingleQuotedString(path))) = path else {
+            return Err(BindError::BindFunctionError(format!(
+                "{func_name} expects a file path"
+            )));
+        };
+        let format = match func_name.as_str() {
+            "read_csv" => {
+                let mut delimiter = ',';
+                let mut quote = '"';
+                let mut escape = None;
+                let mut header = true;
+                let mut null = String::new();
+                for (name, value) in options.drain() {
+                    match (name.as_str(), value) {
+                        ("delimiter", Expr::Value(Value::SingleQuotedString(s)))
+                            if s.chars().count() == 1 =>
+                        {
+                            delimiter = s.chars().next().unwrap()
+                        }
+                        ("quote", Expr::Value(Value::SingleQuotedString(s)))
+                            if s.chars().count() == 1 =>
+                        {
+                            quote = s.chars().next().unwrap()
+                        }
+                        ("escape", Expr::Value(Value::SingleQuotedString(s)))
+                            if s.chars().count() == 1 =>
+                        {
+                            escape = s.chars().next()
+                        }
+                        ("header", Expr::Value(Value::Boolean(b))) => header = b,
+                        ("nullstr", Expr::Value(Value::SingleQuotedString(s))) => null = s,
+                        (_, value) => {
+                            return Err(BindError::BindFunctionError(format!(
+                                "invalid option of {func_name}: {name} => {value}"
+                            )))
+                        }
+                    }
+                }
+                FileFormat::Csv {
+                    delimiter,
+                    quote,
+                    escape,
+                    header,
+                    null,
+                }
+            }
+            "read_parquet" => FileFormat::Parquet,
+            _ => return Err(BindError::InvalidTable(func_name)),
+        };
+        if let Some((name, _)) = options.into_iter().next() {
+            return Err(BindError::BindFunctionError(format!(
+                "invalid option of {func_name}: {name}"
+            )));
+        }
+        let source = ExtSource {
+            path: path.into(),
+            format,
+        };
+        let columns = source
+            .infer_schema()
+            .map_err(|e| BindError::ReadFile(source.path.display().to_string(), e))?;
+
+        let table_name = alias.as_ref().map_or(func_name, |a| a.name.value.clone());
+        self.add_table_alias(&table_name)?;
+        let column_aliases = alias.map_or(vec![], |a| a.columns);
+
+        let source = self.egraph.add(Node::ExtSource(Box::new(source)));
+        let types = DataType::Struct(columns.iter().map(|(_, ty)| ty.clone()).collect());
+        let types = self.egraph.add(Node::Type(types));
+        let copy = self.egraph.add(Node::CopyFrom([source, types]));
+        let mut ids = vec![];
+        for (i, (name, _)) in columns.into_iter().enumerate() {
+            let index = self.egraph.add(Node::ColumnIndex(ColumnIndex(i as _)));
+            let id = self.egraph.add(Node::CteColumn([copy, index]));
+            let name = column_aliases
+                .get(i)
+                .map_or(name, |column| column.value.to_lowercase());
+            self.add_alias(name, table_name.clone(), id);
+            ids.push(id);
+        }
+        let columns = self.egraph.add(Node::List(ids.into()));
+        Ok(self.egraph.add(Node::FileScan([source, columns])))
+    }
+
+    /// Binds a table function that generates a series of values, e.g. `generate_series(1, 9, 2)`.
+    ///
+    /// Values are integers, or timestamps if the step is an interval. `range` is the same as
+    /// `generate_series`, except that the stop value is excluded.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // generate_series(1, 9, 2)
+    /// (generate_series
+    ///     (list 1 9 2 true)
+    ///     (list (cte_column (list 1 9 2 true) #0))
+    /// )
+    /// ```
+    fn bind_generate_series(
+        &mut self,
+        func_name: String,
+        args: Vec<FunctionArg>,
+        alias: Option<TableAlias>,
+    ) -> Result {
+        let mut exprs = vec![];
+        for arg in args {
+            let FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) = arg else {
+                return Err(BindError::InvalidExpression(arg.to_string()));
+            };
+            exprs.push(self.bind_expr(expr)?);
+        }
+        let (start, stop, step) = match exprs[..] {
+            [start, stop] => (start, stop, None),
+            [start, stop, step] => (start, stop, Some(step)),
+            _ => {
+                return Err(BindError::BindFunctionError(format!(
+                    "{func_name} expects 2 or 3 arguments"
+                )))
+            }
+        };
+        let (mut start, mut stop) = self.implicit_type_cast(start, stop)?;
+        let step = match self.type_(start)? {
+            DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+                let step = match step {
+                    Some(step) => step,
+                    None => self.egraph.add(Node::Constant(DataValue::Int32(1))),
+                };
+                let (new_start, step) = self.implicit_type_cast(start, step)?;
+                (start, stop) = self.implicit_type_cast(new_start, stop)?;
+                step
+            }
+            DataType::Date | DataType::Timestamp
+                if step.is_some_and(|step| matches!(self.type_(step), Ok(DataType::Interval))) =>
+            {
+                let ty = self.egraph.add(Node::Type(DataType::Timestamp));
+                start = self.egraph.add(Node::Cast([ty, start]));
+                stop = self.egraph.add(Node::Cast([ty, stop]));
+                step.unwrap()
+            }
+            ty => {
+                return Err(BindError::BindFunctionError(format!(
+                    "{func_name} is not supported for type {ty}"
+                )))
+            }
+        };
+        let ty = self.type_(start)?;
+        if !matches!(ty, DataType::Int16 | DataType::Int32 | DataType::Int64)
+            && ty != DataType::Timestamp
+        {
+            return Err(BindError::BindFunctionError(format!(
+                "{func_name} is not supported for type {ty}"
+            )));
+        }
+        let inclusive = self.egraph.add(Node::Constant(DataValue::Bool(
+            func_name == "generate_series",
+        )));
+        let args = self
+            .egraph
+            .add(Node::List([start, stop, step, inclusive].into()));
+
+        let table_name = alias
+            .as_ref()
+            .map_or(func_name.clone(), |a| a.name.value.clone());
+        self.add_table_alias(&table_name)?;
+        let name = match alias.and_then(|a| a.columns.into_iter().next()) {
+            Some(column) => column.value.to_lowercase(),
+            None => func_name,
+        };
+        let index = self.egraph.add(Node::ColumnIndex(ColumnIndex(0)));
+        let column = self.egraph.add(Node::CteColumn([args, index]));
+        self.add_alias(name, table_name, column);
+        let columns = self.egraph.add(Node::List([column].into()));
+        Ok(self.egraph.add(Node::GenerateSeries([args, columns])))
+    }
+}
+
+impl ExtSource {
+    /// Returns the names and types of columns in the file.
+    fn infer_schema(&self) -> std::result::Result<Vec<(String, DataType)>, String> {
+        let columns = match self.format {
+            FileFormat::Csv {
+                delimiter,
+                quote,
+                escape,
+                header,
+                ref null,
+            } => {
+                let reader = csv::ReaderBuilder::new()
+                    .delimiter(delimiter as u8)
+                    .quote(quote as u8)
+                    .escape(escape.map(|c| c as u8))
+                    .has_headers(header)
+                    .flexible(true)
+                    .from_path(&self.path)
+                    .map_err(|e| e.to_string())?;
+                infer_csv_schema(reader, header, null).map_err(|e| e.to_string())?
+            }
+            FileFormat::Parquet => infer_parquet_schema(&self.path)?,
+            FileFormat::Json | FileFormat::Ndjson => return Err("unsupported file format".into()),
+        };
+        if columns.is_empty() {
+            return Err("no column in file".into());
+        }
+        Ok(columns)
+    }
+}
+
+/// Infers the schema from the header and the first rows of a CSV file.
+///
+/// Columns without header are named `column{i}`. A column is an integer, float, boolean, date or
+/// timestamp column if all its values can be parsed as that type, otherwise a string column.
+fn infer_csv_schema(
+    mut reader: csv::Reader<File>,
+    header: bool,
+    null: &str,
+) -> std::result::Result<Vec<(String, DataType)>, csv::Error> {
+    let names = if header {
+        (reader.headers()?.iter())
+            .map(|name| name.trim().to_lowercase())
+            .collect_vec()
+    } else {
+        vec![]
+    };
+    // `None` if no value other than NULL has been seen
+    let mut types: Vec<Option<DataType>> = vec![None; names.len()];
+    for record in reader.records().take(CSV_INFER_ROWS) {
+        let record = record?;
+        if record.len() > types.len() {
+            types.resize(record.len(), None);
+        }
+        for (ty, value) in types.iter_mut().zip(record.iter()) {
+            if value.is_empty() || value == null {
+                continue;
+            }
+            let value_type = infer_csv_type(value);
+            *ty = Some(match ty.take() {
+                None => value_type,
+                Some(ty) => union_csv_type(ty, value_type),
+            });
+        }
+    }
+    // a delimiter at the end of lines doesn't start a new column
+    if types.last() == Some(&None) && names.get(types.len() - 1).map_or(true, |n| n.is_empty()) {
+        types.pop();
+    }
+    Ok((types.into_iter().enumerate())
+        .map(|(i, ty)| {
+            let name = match names.get(i) {
+                Some(name) if !name.is_empty() => name.clone(),
+                _ => format!("column{i}"),
+            };
+            (name, ty.unwrap_or(DataType::String))
+        })
+        .collect())
+}
+
+/// Returns the narrowest type that the value can be parsed as.
+fn infer_csv_type(value: &str) -> DataType {
+    if value.parse::<i32>().is_ok() {
+        DataType::Int32
+    } else if value.parse::<i64>().is_ok() {
+        DataType::Int64
+    } else if value.parse::<f64>().is_ok() {
+        DataType::Float64
+    } else if value.parse::<bool>().is_ok() {
+        DataType::Bool
+    } else if value.parse::<Date>().is_ok() {
+        DataType::Date
+    } else if value.parse::<Timestamp>().is_ok() {
+        DataType::Timestamp
+    } else {
+        DataType::String
+    }
+}
+
+/// Returns a type that values of both types can be parsed as.
+fn union_csv_type(a: DataType, b: DataType) -> DataType {
+    use DataType::*;
+    match (a, b) {
+        (a, b) if a == b => a,
+        (Int32 | Int64, Int32 | Int64) => Int64,
+        (Int32 | Int64 | Float64, Int32 | Int64 | Float64) => Float64,
+        (Date | Timestamp, Date | Timestamp) => Timestamp,
+        _ => String,
+    }
+}
+
+/// Returns the schema of a Parquet file.
+fn infer_parquet_schema(path: &Path) -> std::result::Result<Vec<(String, DataType)>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).map_err(|e| e.to_string())?;
+    (builder.schema().fields().iter())
+        .map(|field| {
+            let ty = DataType::from_arrow(field.data_type()).map_err(|e| e.to_string())?;
+            Ok((field.name().to_lowercase(), ty))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_infer_csv_schema() {
+        let csv = "a,b,c,d,\n1,1.5,x,2024-01-01,\n3000000000,,y,2024-01-01 00:00:00,\n";
+        let mut file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+        write!(file, "{}", csv).expect("failed to write file");
+        let source = ExtSource {
+            path: file.path().into(),
+            format: FileFormat::Csv {
+                delimiter: ',',
+                quote: '"',
+                escape: None,
+                header: true,
+                null: String::new(),
+            },
+        };
+        assert_eq!(
+            source.infer_schema().unwrap(),
+            vec![
+                ("a".into(), DataType::Int64),
+                ("b".into(), DataType::Float64),
+                ("c".into(), DataType::String),
+                ("d".into(), DataType::Timestamp),
+            ]
+        );
+    }
+}
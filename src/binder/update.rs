@@ -0,0 +1,89 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::*;
+use crate::catalog::ColumnRefId;
+
+impl Binder {
+    pub(super) fn bind_update(
+        &mut self,
+        table: TableWithJoins,
+        assignments: Vec<Assignment>,
+        from: Option<TableWithJoins>,
+        selection: Option<Expr>,
+        returning: Option<Vec<SelectItem>>,
+    ) -> Result {
+        if !table.joins.is_empty() || from.is_some() {
+            return Err(BindError::Todo(format!("update {table} with join")));
+        }
+        let TableFactor::Table { name, alias, .. } = &table.relation else {
+            return Err(BindError::Todo(format!("update {table}")));
+        };
+        let (table_id, is_system, is_view) = self.bind_table_id(name)?;
+        if is_system || is_view {
+            return Err(BindError::CanNotUpdate);
+        }
+        let table_ref_id = self.node(table_id).as_table();
+        let scan = self.bind_table_def(name, alias.clone(), true)?;
+        let cond = self.bind_where(selection)?;
+        let filter = self.egraph.add(Node::Filter([cond, scan]));
+
+        // the new row contains all columns of the table, followed by the handler of the old row
+        let mut list = self.bind_assignments(table_ref_id, assignments)?;
+        let rowid = ColumnRefId::from_table(table_ref_id, 0, u32::MAX);
+        list.push(self.egraph.add(Node::Column(rowid)));
+        let list = self.egraph.add(Node::List(list.into()));
+        let proj = self.egraph.add(Node::Proj([list, filter]));
+
+        let checks = self.bind_table_checks(table_ref_id)?;
+        let returning = self.bind_returning(table_ref_id, returning)?;
+        Ok(self
+            .egraph
+            .add(Node::Update([table_id, checks, returning, proj])))
+    }
+
+    /// Binds the `SET` clause. Returns the new values of all columns of the table.
+    ///
+    /// Columns not assigned keep their old values.
+    pub(super) fn bind_assignments(
+        &mut self,
+        table_ref_id: TableRefId,
+        assignments: Vec<Assignment>,
+    ) -> Result<Vec<Id>> {
+        let mut values = HashMap::new();
+        for assignment in assignments {
+            let column_name = match assignment.id.last() {
+                Some(ident) => ident.value.to_lowercase(),
+                None => return Err(BindError::InvalidSQL),
+            };
+            let value = self.bind_expr(assignment.value)?;
+            if !self.aggs(value).is_empty() {
+                return Err(BindError::AggInSet);
+            }
+            if !self.overs(value).is_empty() {
+                return Err(BindError::WindowInSet);
+            }
+            if values.insert(column_name.clone(), value).is_some() {
+                return Err(BindError::DuplicatedAssignment(column_name));
+            }
+        }
+
+        let table_catalog = self.catalog.get_table(&table_ref_id).unwrap();
+        let mut list = vec![];
+        for (column_id, column) in table_catalog.all_columns() {
+            let column_ref_id = ColumnRefId::from_table(table_ref_id, 0, column_id);
+            let new = match values.remove(column.name()) {
+                Some(value) if self.type_(value)? == column.data_type() => value,
+                Some(value) => {
+                    let ty = self.egraph.add(Node::Type(column.data_type()));
+                    self.egraph.add(Node::Cast([ty, value]))
+                }
+                None => self.egraph.add(Node::Column(column_ref_id)),
+            };
+            list.push(new);
+        }
+        if let Some(column_name) = values.into_keys().next() {
+            return Err(BindError::InvalidColumn(column_name));
+        }
+        Ok(list)
+    }
+}
@@ -4,7 +4,31 @@ use pretty_xmlish::Pretty;
 use serde::{Deserialize, Serialize};
 
 use super::ColumnId;
-use crate::types::DataType;
+use crate::types::{DataType, DataValue};
+
+/// The algorithm to compress blocks of a column.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Compression {
+    #[default]
+    None,
+    Zstd,
+    Lz4,
+}
+
+impl std::str::FromStr for Compression {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "zstd" => Ok(Self::Zstd),
+            "lz4" => Ok(Self::Lz4),
+            _ => Err(()),
+        }
+    }
+}
 
 /// A descriptor of a column.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -13,6 +37,15 @@ pub struct ColumnDesc {
     data_type: DataType,
     is_nullable: bool,
     is_primary: bool,
+    /// The value filled in when a row doesn't provide this column.
+    #[serde(default)]
+    default: Option<DataValue>,
+    /// Whether to build a bloom filter of the column in each rowset.
+    #[serde(default)]
+    bloom_filter: bool,
+    /// The algorithm to compress blocks of the column.
+    #[serde(default)]
+    compression: Compression,
 }
 
 impl ColumnDesc {
@@ -22,6 +55,9 @@ impl ColumnDesc {
             data_type: datatype,
             is_nullable,
             is_primary: false,
+            default: None,
+            bloom_filter: false,
+            compression: Compression::None,
         }
     }
 
@@ -45,6 +81,31 @@ impl ColumnDesc {
         &self.data_type
     }
 
+    pub fn set_default(&mut self, default: Option<DataValue>) {
+        self.default = default;
+    }
+
+    /// Returns the default value of the column, or `NULL` if not specified.
+    pub fn default_value(&self) -> DataValue {
+        self.default.clone().unwrap_or(DataValue::Null)
+    }
+
+    pub fn set_bloom_filter(&mut self, bloom_filter: bool) {
+        self.bloom_filter = bloom_filter;
+    }
+
+    pub fn has_bloom_filter(&self) -> bool {
+        self.bloom_filter
+    }
+
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -60,6 +121,15 @@ impl ColumnDesc {
         if self.is_nullable {
             fields.push(("nullable", Pretty::display(&self.is_nullable)));
         }
+        if let Some(default) = &self.default {
+            fields.push(("default", Pretty::display(default)));
+        }
+        if self.bloom_filter {
+            fields.push(("bloom_filter", Pretty::display(&self.bloom_filter)));
+        }
+        if self.compression != Compression::None {
+            fields.push(("compression", Pretty::debug(&self.compression)));
+        }
         Pretty::childless_record("Column", fields)
     }
 }
@@ -115,6 +185,30 @@ impl ColumnCatalog {
     pub fn is_nullable(&self) -> bool {
         self.desc.is_nullable()
     }
+
+    pub fn set_default(&mut self, default: Option<DataValue>) {
+        self.desc.set_default(default);
+    }
+
+    pub fn default_value(&self) -> DataValue {
+        self.desc.default_value()
+    }
+
+    pub fn set_bloom_filter(&mut self, bloom_filter: bool) {
+        self.desc.set_bloom_filter(bloom_filter);
+    }
+
+    pub fn has_bloom_filter(&self) -> bool {
+        self.desc.has_bloom_filter()
+    }
+
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.desc.set_compression(compression);
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.desc.compression()
+    }
 }
 
 /// Find the id of the sort key among column catalogs
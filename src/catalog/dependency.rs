@@ -0,0 +1,94 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+/// An object in the catalog that depends on a table or view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dependent {
+    /// A table or view.
+    Table(TableRefId),
+    /// An index on a table.
+    Index(SchemaId, IndexId),
+}
+
+/// The dependencies between objects in the catalog.
+///
+/// Views depend on the tables and views in their queries, and indexes depend on their tables.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    /// The objects depending directly on each table or view.
+    dependents: HashMap<TableRefId, Vec<Dependent>>,
+}
+
+impl DependencyGraph {
+    /// Adds an edge that `dependent` depends on `table`.
+    pub fn add(&mut self, table: TableRefId, dependent: Dependent) {
+        let dependents = self.dependents.entry(table).or_default();
+        if !dependents.contains(&dependent) {
+            dependents.push(dependent);
+            dependents.sort();
+        }
+    }
+
+    /// Returns the objects depending directly on the table or view.
+    pub fn dependents(&self, table: TableRefId) -> &[Dependent] {
+        self.dependents.get(&table).map_or(&[], |d| d.as_slice())
+    }
+
+    /// Returns the given tables and all objects depending on them directly or indirectly, in the
+    /// order to drop them: every object comes before the objects it depends on.
+    pub fn drop_order(&self, tables: &[TableRefId]) -> Vec<Dependent> {
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+        for table in tables {
+            self.visit(Dependent::Table(*table), &mut visited, &mut order);
+        }
+        order
+    }
+
+    /// Pushes the objects depending on the object, and then the object itself.
+    fn visit(
+        &self,
+        object: Dependent,
+        visited: &mut HashSet<Dependent>,
+        order: &mut Vec<Dependent>,
+    ) {
+        if !visited.insert(object) {
+            return;
+        }
+        if let Dependent::Table(table) = object {
+            for dependent in self.dependents(table) {
+                self.visit(*dependent, visited, order);
+            }
+        }
+        order.push(object);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_order() {
+        let t = TableRefId::new(1, 0);
+        let v1 = TableRefId::new(1, 1);
+        let v2 = TableRefId::new(1, 2);
+        let mut graph = DependencyGraph::default();
+        graph.add(t, Dependent::Index(1, 0));
+        graph.add(t, Dependent::Table(v1));
+        // v2 depends on both t and v1
+        graph.add(t, Dependent::Table(v2));
+        graph.add(v1, Dependent::Table(v2));
+
+        let order = graph.drop_order(&[t]);
+        assert_eq!(order.len(), 4);
+        assert_eq!(order.last(), Some(&Dependent::Table(t)));
+        let pos = |d| order.iter().position(|o| *o == d).unwrap();
+        assert!(pos(Dependent::Table(v2)) < pos(Dependent::Table(v1)));
+
+        assert_eq!(graph.drop_order(&[v2]), vec![Dependent::Table(v2)]);
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::*;
+
+/// The catalog of a secondary index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexCatalog {
+    id: IndexId,
+    name: String,
+    table_id: TableId,
+    /// The key columns of the index, in order.
+    column_ids: Vec<ColumnId>,
+}
+
+impl IndexCatalog {
+    pub fn new(id: IndexId, name: String, table_id: TableId, column_ids: Vec<ColumnId>) -> Self {
+        IndexCatalog {
+            id,
+            name,
+            table_id,
+            column_ids,
+        }
+    }
+
+    pub fn id(&self) -> IndexId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn table_id(&self) -> TableId {
+        self.table_id
+    }
+
+    pub fn column_ids(&self) -> &[ColumnId] {
+        &self.column_ids
+    }
+
+    /// Returns true if the index is keyed by the given column first.
+    pub fn is_leading_column(&self, column_id: ColumnId) -> bool {
+        self.column_ids.first() == Some(&column_id)
+    }
+}
@@ -6,20 +6,27 @@ use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 pub use self::column::*;
+pub use self::dependency::*;
+pub use self::index::*;
 pub use self::root::*;
 pub use self::schema::*;
+pub use self::statistics::*;
 pub use self::table::*;
 use crate::types::*;
 
 mod column;
+mod dependency;
 pub mod function;
+mod index;
 mod root;
 mod schema;
+mod statistics;
 mod table;
 
 pub type SchemaId = u32;
 pub type TableId = u32;
 pub type ColumnId = u32;
+pub type IndexId = u32;
 
 pub type RootCatalogRef = Arc<RootCatalog>;
 
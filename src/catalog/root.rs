@@ -1,16 +1,17 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use super::function::FunctionCatalog;
 use super::*;
 use crate::parser;
-use crate::planner::RecExpr;
+use crate::planner::{PlanCacheStats, RecExpr};
 
 /// The root of all catalogs.
 pub struct RootCatalog {
     inner: Mutex<Inner>,
+    plan_cache_stats: PlanCacheStats,
 }
 
 #[derive(Default)]
@@ -18,6 +19,12 @@ struct Inner {
     schema_idxs: HashMap<String, SchemaId>,
     schemas: HashMap<SchemaId, SchemaCatalog>,
     next_schema_id: SchemaId,
+    /// Bumped on every change of the catalog.
+    version: u64,
+    /// Statistics of tables collected by `ANALYZE`.
+    statistics: HashMap<TableRefId, TableStatistics>,
+    /// Bumped on every `ANALYZE`.
+    statistics_version: u64,
 }
 
 impl Default for RootCatalog {
@@ -29,13 +36,41 @@ impl Default for RootCatalog {
 impl RootCatalog {
     pub fn new() -> RootCatalog {
         let mut inner = Inner::default();
-        inner.add_system_schema();
+        let schema_id = inner.add_system_schema(Self::SYSTEM_SCHEMA_NAME, CREATE_SYSTEM_TABLE_SQL);
+        assert_eq!(schema_id, Self::SYSTEM_SCHEMA_ID);
         inner.add_schema(Self::DEFAULT_SCHEMA_NAME.into()).unwrap();
+        inner.add_system_schema(
+            Self::INFORMATION_SCHEMA_NAME,
+            CREATE_INFORMATION_SCHEMA_TABLE_SQL,
+        );
         RootCatalog {
             inner: Mutex::new(inner),
+            plan_cache_stats: PlanCacheStats::default(),
         }
     }
 
+    /// Returns the version of the catalog, which changes on every DDL.
+    pub fn version(&self) -> u64 {
+        self.inner.lock().unwrap().version
+    }
+
+    /// Returns the version of the statistics, which changes on every `ANALYZE`.
+    pub fn statistics_version(&self) -> u64 {
+        self.inner.lock().unwrap().statistics_version
+    }
+
+    /// Returns the statistics of the plan cache of the database.
+    pub fn plan_cache_stats(&self) -> &PlanCacheStats {
+        &self.plan_cache_stats
+    }
+
+    /// Locks the catalog for modification and bumps the version.
+    fn lock_for_update(&self) -> MutexGuard<'_, Inner> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.version += 1;
+        inner
+    }
+
     pub fn all_schemas(&self) -> HashMap<SchemaId, SchemaCatalog> {
         let inner = self.inner.lock().unwrap();
         inner.schemas.clone()
@@ -57,6 +92,18 @@ impl RootCatalog {
         inner.schemas.get(id).cloned()
     }
 
+    pub fn add_schema(&self, name: String) -> Result<SchemaId, CatalogError> {
+        let mut inner = self.lock_for_update();
+        inner.add_schema(name)
+    }
+
+    pub fn drop_schema(&self, schema_id: SchemaId) {
+        let mut inner = self.lock_for_update();
+        if let Some(schema) = inner.schemas.remove(&schema_id) {
+            inner.schema_idxs.remove(&schema.name());
+        }
+    }
+
     pub fn get_table(&self, table_ref_id: &TableRefId) -> Option<Arc<TableCatalog>> {
         let schema = self.get_schema_by_id(table_ref_id.schema_id)?;
         schema.get_table_by_id(table_ref_id.table_id)
@@ -79,11 +126,11 @@ impl RootCatalog {
         schema_id: SchemaId,
         name: String,
         columns: Vec<ColumnCatalog>,
-        ordered_pk_ids: Vec<ColumnId>,
+        constraints: Constraints,
     ) -> Result<TableId, CatalogError> {
-        let mut inner = self.inner.lock().unwrap();
+        let mut inner = self.lock_for_update();
         let schema = inner.schemas.get_mut(&schema_id).unwrap();
-        schema.add_table(name, columns, ordered_pk_ids)
+        schema.add_table(name, columns, constraints)
     }
 
     pub fn add_view(
@@ -92,18 +139,128 @@ impl RootCatalog {
         name: String,
         columns: Vec<ColumnCatalog>,
         query: RecExpr,
+        dependencies: Vec<TableRefId>,
     ) -> Result<TableId, CatalogError> {
-        let mut inner = self.inner.lock().unwrap();
+        let mut inner = self.lock_for_update();
         let schema = inner.schemas.get_mut(&schema_id).unwrap();
-        schema.add_view(name, columns, query)
+        schema.add_view(name, columns, query, dependencies)
+    }
+
+    pub fn add_column(
+        &self,
+        table_ref_id: TableRefId,
+        column: ColumnCatalog,
+    ) -> Result<ColumnId, CatalogError> {
+        let mut inner = self.lock_for_update();
+        let schema = inner.schemas.get_mut(&table_ref_id.schema_id).unwrap();
+        schema.add_column(table_ref_id.table_id, column)
+    }
+
+    pub fn drop_column(
+        &self,
+        table_ref_id: TableRefId,
+        column_id: ColumnId,
+    ) -> Result<(), CatalogError> {
+        let mut inner = self.lock_for_update();
+        if let Some(statistics) = inner.statistics.get_mut(&table_ref_id) {
+            statistics.columns.remove(&column_id);
+        }
+        let schema = inner.schemas.get_mut(&table_ref_id.schema_id).unwrap();
+        schema.drop_column(table_ref_id.table_id, column_id)
     }
 
     pub fn drop_table(&self, table_ref_id: TableRefId) {
-        let mut inner = self.inner.lock().unwrap();
+        let mut inner = self.lock_for_update();
+        inner.statistics.remove(&table_ref_id);
         let schema = inner.schemas.get_mut(&table_ref_id.schema_id).unwrap();
         schema.delete_table(table_ref_id.table_id);
     }
 
+    /// Sets the statistics of a table collected by `ANALYZE`.
+    pub fn set_statistics(&self, table_ref_id: TableRefId, statistics: TableStatistics) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.statistics_version += 1;
+        inner.statistics.insert(table_ref_id, statistics);
+    }
+
+    /// Returns the statistics of all analyzed tables.
+    pub fn all_statistics(&self) -> HashMap<TableRefId, TableStatistics> {
+        let inner = self.inner.lock().unwrap();
+        inner.statistics.clone()
+    }
+
+    pub fn add_index(
+        &self,
+        schema_id: SchemaId,
+        name: String,
+        table_id: TableId,
+        column_ids: Vec<ColumnId>,
+    ) -> Result<IndexId, CatalogError> {
+        let mut inner = self.lock_for_update();
+        let schema = inner.schemas.get_mut(&schema_id).unwrap();
+        schema.add_index(name, table_id, column_ids)
+    }
+
+    pub fn drop_index(&self, schema_id: SchemaId, index_id: IndexId) {
+        let mut inner = self.lock_for_update();
+        let schema = inner.schemas.get_mut(&schema_id).unwrap();
+        schema.delete_index(index_id);
+    }
+
+    pub fn get_index(&self, schema_id: SchemaId, index_id: IndexId) -> Option<Arc<IndexCatalog>> {
+        let inner = self.inner.lock().unwrap();
+        inner.schemas.get(&schema_id)?.get_index_by_id(index_id)
+    }
+
+    /// Returns all indexes on the table.
+    pub fn get_table_indexes(&self, table_ref_id: &TableRefId) -> Vec<Arc<IndexCatalog>> {
+        let inner = self.inner.lock().unwrap();
+        match inner.schemas.get(&table_ref_id.schema_id) {
+            Some(schema) => schema.get_indexes_of_table(table_ref_id.table_id),
+            None => vec![],
+        }
+    }
+
+    /// Returns all tables with a foreign key referencing the table, and their foreign keys.
+    pub fn get_referencing_tables(
+        &self,
+        table_ref_id: TableRefId,
+    ) -> Vec<(TableRefId, ForeignKey)> {
+        let inner = self.inner.lock().unwrap();
+        let mut referencing = vec![];
+        for (schema_id, schema) in &inner.schemas {
+            for (table_id, table) in schema.all_tables() {
+                for foreign_key in table.foreign_keys() {
+                    if foreign_key.ref_table_id == table_ref_id {
+                        referencing
+                            .push((TableRefId::new(*schema_id, table_id), foreign_key.clone()));
+                    }
+                }
+            }
+        }
+        referencing.sort();
+        referencing
+    }
+
+    /// Returns the dependencies between tables, views and indexes.
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let inner = self.inner.lock().unwrap();
+        let mut graph = DependencyGraph::default();
+        for (schema_id, schema) in &inner.schemas {
+            for (table_id, table) in schema.all_tables() {
+                let view = Dependent::Table(TableRefId::new(*schema_id, table_id));
+                for dependency in table.dependencies() {
+                    graph.add(*dependency, view);
+                }
+                for index in schema.get_indexes_of_table(table_id) {
+                    let table = TableRefId::new(*schema_id, table_id);
+                    graph.add(table, Dependent::Index(*schema_id, index.id()));
+                }
+            }
+        }
+        graph
+    }
+
     pub fn get_table_id_by_name(&self, schema_name: &str, table_name: &str) -> Option<TableRefId> {
         let schema = self.get_schema_by_name(schema_name)?;
         let table = schema.get_table_by_name(table_name)?;
@@ -135,14 +292,22 @@ impl RootCatalog {
         body: String,
     ) {
         let schema_idx = self.get_schema_id_by_name(&schema_name).unwrap();
-        let mut inner = self.inner.lock().unwrap();
+        let mut inner = self.lock_for_update();
         let schema = inner.schemas.get_mut(&schema_idx).unwrap();
         schema.create_function(name, arg_types, arg_names, return_type, language, body);
     }
 
+    pub fn drop_function(&self, schema_name: &str, name: &str) {
+        let schema_idx = self.get_schema_id_by_name(schema_name).unwrap();
+        let mut inner = self.lock_for_update();
+        let schema = inner.schemas.get_mut(&schema_idx).unwrap();
+        schema.drop_function(name);
+    }
+
     pub const DEFAULT_SCHEMA_NAME: &'static str = "postgres";
     pub const SYSTEM_SCHEMA_NAME: &'static str = "pg_catalog";
     pub const SYSTEM_SCHEMA_ID: TableId = 0;
+    pub const INFORMATION_SCHEMA_NAME: &'static str = "information_schema";
 }
 
 impl Inner {
@@ -158,20 +323,18 @@ impl Inner {
         Ok(schema_id)
     }
 
-    fn add_system_schema(&mut self) {
-        let schema_id = self
-            .add_schema(RootCatalog::SYSTEM_SCHEMA_NAME.into())
-            .unwrap();
+    /// Adds a schema of system tables defined by `sql`.
+    fn add_system_schema(&mut self, name: &str, sql: &str) -> SchemaId {
+        let schema_id = self.add_schema(name.into()).unwrap();
         let system_schema = self.schemas.get_mut(&schema_id).unwrap();
-        assert_eq!(schema_id, RootCatalog::SYSTEM_SCHEMA_ID);
 
-        let stmts = parser::parse(CREATE_SYSTEM_TABLE_SQL).unwrap();
+        let stmts = parser::parse(sql).unwrap();
         for stmt in stmts {
             let parser::Statement::CreateTable { name, columns, .. } = stmt else {
                 panic!("invalid system table sql: {stmt}");
             };
             system_schema
-                .add_table(
+                .add_system_table(
                     name.to_string(),
                     columns
                         .into_iter()
@@ -182,10 +345,10 @@ impl Inner {
                             column
                         })
                         .collect(),
-                    vec![],
                 )
                 .expect("failed to add system table");
         }
+        schema_id
     }
 }
 
@@ -222,6 +385,81 @@ const CREATE_SYSTEM_TABLE_SQL: &str = "
         n_row int,
         n_distinct int
     );
+    create table pg_stat_bloom_filter (
+        schema_name string not null,
+        table_name string not null,
+        n_checked int not null,
+        n_pruned int not null
+    );
+    create table pg_plan_cache (
+        n_entry int not null,
+        n_hit int not null,
+        n_miss int not null,
+        n_invalidated int not null
+    );
+    create table pg_stats (
+        schema_name string not null,
+        table_name string not null,
+        column_name string not null,
+        n_row int not null,
+        null_frac double not null,
+        n_distinct int not null,
+        min_value string,
+        max_value string,
+        histogram_bounds string
+    );
+    create table pg_functions (
+        schema_name string not null,
+        function_name string not null,
+        arg_types string not null,
+        return_type string not null,
+        language string not null,
+        body string not null
+    );
+    create table pg_namespace (
+        oid int not null,
+        nspname string not null
+    );
+    create table pg_class (
+        oid int not null,
+        relname string not null,
+        relnamespace int not null,
+        relkind string not null
+    );
+    create table pg_memory_usage (
+        id int not null,
+        parent_id int,
+        name string not null,
+        used_bytes bigint not null,
+        peak_bytes bigint not null,
+        limit_bytes bigint
+    );
+    create table pg_snapshots (
+        epoch bigint not null,
+        commit_time timestamp not null
+    );
+";
+
+const CREATE_INFORMATION_SCHEMA_TABLE_SQL: &str = "
+    create table schemata (
+        catalog_name string not null,
+        schema_name string not null
+    );
+    create table tables (
+        table_catalog string not null,
+        table_schema string not null,
+        table_name string not null,
+        table_type string not null
+    );
+    create table columns (
+        table_catalog string not null,
+        table_schema string not null,
+        table_name string not null,
+        column_name string not null,
+        ordinal_position int not null,
+        is_nullable string not null,
+        data_type string not null
+    );
 ";
 
 #[cfg(test)]
@@ -245,8 +483,21 @@ mod tests {
         assert_eq!(schema_catalog2.id(), 1);
         assert_eq!(schema_catalog2.name(), RootCatalog::DEFAULT_SCHEMA_NAME);
 
+        let table = catalog
+            .get_table_by_name("information_schema.tables")
+            .unwrap();
+        assert!(table.is_system());
+
         let col = ColumnCatalog::new(0, ColumnDesc::new("a", DataType::Int32, false));
-        let table_id = catalog.add_table(1, "t".into(), vec![col], vec![]).unwrap();
+        let table_id = catalog
+            .add_table(1, "t".into(), vec![col], Constraints::default())
+            .unwrap();
         assert_eq!(table_id, 0);
+
+        let schema_id = catalog.add_schema("s".into()).unwrap();
+        assert_eq!(schema_id, 3);
+        assert!(catalog.add_schema("s".into()).is_err());
+        catalog.drop_schema(schema_id);
+        assert!(catalog.get_schema_by_name("s").is_none());
     }
 }
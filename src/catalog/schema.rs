@@ -15,6 +15,9 @@ pub struct SchemaCatalog {
     table_idxs: HashMap<String, TableId>,
     tables: HashMap<TableId, Arc<TableCatalog>>,
     next_table_id: TableId,
+    index_idxs: HashMap<String, IndexId>,
+    indexes: HashMap<IndexId, Arc<IndexCatalog>>,
+    next_index_id: IndexId,
     /// Currently indexed by function name
     functions: HashMap<String, Arc<FunctionCatalog>>,
 }
@@ -27,6 +30,9 @@ impl SchemaCatalog {
             table_idxs: HashMap::new(),
             tables: HashMap::new(),
             next_table_id: 0,
+            index_idxs: HashMap::new(),
+            indexes: HashMap::new(),
+            next_index_id: 0,
             functions: HashMap::new(),
         }
     }
@@ -35,18 +41,23 @@ impl SchemaCatalog {
         &mut self,
         name: String,
         columns: Vec<ColumnCatalog>,
-        ordered_pk_ids: Vec<ColumnId>,
+        mut constraints: Constraints,
     ) -> Result<TableId, CatalogError> {
         if self.table_idxs.contains_key(&name) {
             return Err(CatalogError::Duplicated("table", name));
         }
         let table_id = self.next_table_id;
         self.next_table_id += 1;
+        for foreign_key in &mut constraints.foreign_keys {
+            if foreign_key.ref_table_id.table_id == ForeignKey::SELF_TABLE_ID {
+                foreign_key.ref_table_id.table_id = table_id;
+            }
+        }
         let table_catalog = Arc::new(TableCatalog::new(
             table_id,
             name.clone(),
             columns,
-            ordered_pk_ids,
+            constraints,
         ));
         self.table_idxs.insert(name, table_id);
         self.tables.insert(table_id, table_catalog);
@@ -58,6 +69,7 @@ impl SchemaCatalog {
         name: String,
         columns: Vec<ColumnCatalog>,
         query: RecExpr,
+        dependencies: Vec<TableRefId>,
     ) -> Result<TableId, CatalogError> {
         if self.table_idxs.contains_key(&name) {
             return Err(CatalogError::Duplicated("view", name));
@@ -69,15 +81,89 @@ impl SchemaCatalog {
             name.clone(),
             columns,
             query,
+            dependencies,
         ));
         self.table_idxs.insert(name, table_id);
         self.tables.insert(table_id, table_catalog);
         Ok(table_id)
     }
 
+    pub(super) fn add_system_table(
+        &mut self,
+        name: String,
+        columns: Vec<ColumnCatalog>,
+    ) -> Result<TableId, CatalogError> {
+        if self.table_idxs.contains_key(&name) {
+            return Err(CatalogError::Duplicated("table", name));
+        }
+        let table_id = self.next_table_id;
+        self.next_table_id += 1;
+        let table_catalog = Arc::new(TableCatalog::new_system(table_id, name.clone(), columns));
+        self.table_idxs.insert(name, table_id);
+        self.tables.insert(table_id, table_catalog);
+        Ok(table_id)
+    }
+
+    pub(super) fn add_column(
+        &mut self,
+        table_id: TableId,
+        column: ColumnCatalog,
+    ) -> Result<ColumnId, CatalogError> {
+        let table = self
+            .tables
+            .get_mut(&table_id)
+            .ok_or_else(|| CatalogError::NotFound("table", table_id.to_string()))?;
+        Arc::make_mut(table).alter_add_column(column)
+    }
+
+    pub(super) fn drop_column(
+        &mut self,
+        table_id: TableId,
+        column_id: ColumnId,
+    ) -> Result<(), CatalogError> {
+        let table = self
+            .tables
+            .get_mut(&table_id)
+            .ok_or_else(|| CatalogError::NotFound("table", table_id.to_string()))?;
+        Arc::make_mut(table).alter_drop_column(column_id)
+    }
+
     pub(super) fn delete_table(&mut self, id: TableId) {
         let catalog = self.tables.remove(&id).unwrap();
         self.table_idxs.remove(catalog.name()).unwrap();
+        for index_id in self.get_indexes_of_table(id).iter().map(|i| i.id()) {
+            self.delete_index(index_id);
+        }
+    }
+
+    pub(super) fn add_index(
+        &mut self,
+        name: String,
+        table_id: TableId,
+        column_ids: Vec<ColumnId>,
+    ) -> Result<IndexId, CatalogError> {
+        if self.index_idxs.contains_key(&name) {
+            return Err(CatalogError::Duplicated("index", name));
+        }
+        if !self.tables.contains_key(&table_id) {
+            return Err(CatalogError::NotFound("table", table_id.to_string()));
+        }
+        let index_id = self.next_index_id;
+        self.next_index_id += 1;
+        let index_catalog = Arc::new(IndexCatalog::new(
+            index_id,
+            name.clone(),
+            table_id,
+            column_ids,
+        ));
+        self.index_idxs.insert(name, index_id);
+        self.indexes.insert(index_id, index_catalog);
+        Ok(index_id)
+    }
+
+    pub(super) fn delete_index(&mut self, id: IndexId) {
+        let catalog = self.indexes.remove(&id).unwrap();
+        self.index_idxs.remove(catalog.name()).unwrap();
     }
 
     pub fn all_tables(&self) -> HashMap<TableId, Arc<TableCatalog>> {
@@ -99,6 +185,27 @@ impl SchemaCatalog {
             .cloned()
     }
 
+    pub fn get_index_by_id(&self, index_id: IndexId) -> Option<Arc<IndexCatalog>> {
+        self.indexes.get(&index_id).cloned()
+    }
+
+    pub fn get_index_by_name(&self, name: &str) -> Option<Arc<IndexCatalog>> {
+        self.index_idxs
+            .get(name)
+            .and_then(|id| self.indexes.get(id))
+            .cloned()
+    }
+
+    /// Returns all indexes on the table, ordered by index id.
+    pub fn get_indexes_of_table(&self, table_id: TableId) -> Vec<Arc<IndexCatalog>> {
+        let mut indexes: Vec<_> = (self.indexes.values())
+            .filter(|index| index.table_id() == table_id)
+            .cloned()
+            .collect();
+        indexes.sort_by_key(|index| index.id());
+        indexes
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
@@ -111,6 +218,10 @@ impl SchemaCatalog {
         self.functions.get(name).cloned()
     }
 
+    pub fn all_functions(&self) -> HashMap<String, Arc<FunctionCatalog>> {
+        self.functions.clone()
+    }
+
     pub fn create_function(
         &mut self,
         name: String,
@@ -132,6 +243,10 @@ impl SchemaCatalog {
             }),
         );
     }
+
+    pub(super) fn drop_function(&mut self, name: &str) {
+        self.functions.remove(name);
+    }
 }
 
 #[cfg(test)]
@@ -151,13 +266,28 @@ mod tests {
         assert_eq!(schema_catalog.name(), "test");
 
         let table_id = schema_catalog
-            .add_table("t".into(), col_catalogs, vec![])
+            .add_table("t".into(), col_catalogs, Constraints::default())
             .unwrap();
         assert_eq!(table_id, 0);
 
         let table_catalog = schema_catalog.get_table_by_id(0).unwrap();
         assert!(!table_catalog.contains_column("c"));
         assert!(table_catalog.contains_column("a"));
-        assert!(table_catalog.contains_column("b"))
+        assert!(table_catalog.contains_column("b"));
+
+        // index
+        let index_id = schema_catalog
+            .add_index("i".into(), table_id, vec![1])
+            .unwrap();
+        assert!(schema_catalog
+            .add_index("i".into(), table_id, vec![0])
+            .is_err());
+        let index = schema_catalog.get_index_by_name("i").unwrap();
+        assert_eq!(index.id(), index_id);
+        assert!(index.is_leading_column(1));
+        assert_eq!(schema_catalog.get_indexes_of_table(table_id).len(), 1);
+
+        schema_catalog.delete_table(table_id);
+        assert!(schema_catalog.get_index_by_name("i").is_none());
     }
 }
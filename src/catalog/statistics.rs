@@ -0,0 +1,223 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::collections::HashMap;
+
+use super::*;
+use crate::array::ArrayImpl;
+use crate::utils::hyperloglog::HyperLogLog;
+
+/// Statistics of a table collected by `ANALYZE`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableStatistics {
+    pub row_count: u64,
+    pub columns: HashMap<ColumnId, ColumnStatistics>,
+}
+
+/// Statistics of a column collected by `ANALYZE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStatistics {
+    /// The fraction of NULL values.
+    pub null_fraction: f64,
+    /// The minimum non-NULL value, or NULL if all values are NULL.
+    pub min: DataValue,
+    /// The maximum non-NULL value, or NULL if all values are NULL.
+    pub max: DataValue,
+    /// The estimated number of distinct non-NULL values.
+    pub distinct_count: u64,
+    /// Bounds of an equi-depth histogram of non-NULL values, in ascending order.
+    ///
+    /// Each pair of adjacent bounds is a bucket containing about the same number of values. The
+    /// first and last bounds are the minimum and maximum values. Empty if all values are NULL.
+    pub histogram: Vec<DataValue>,
+}
+
+/// The maximum number of buckets in a histogram.
+const NUM_BUCKETS: usize = 100;
+
+/// The maximum number of values sampled to build a histogram.
+const MAX_SAMPLES: usize = 30000;
+
+/// Collects statistics of a column from its values.
+#[derive(Default)]
+pub struct ColumnStatisticsBuilder {
+    rows: u64,
+    nulls: u64,
+    min: Option<DataValue>,
+    max: Option<DataValue>,
+    distinct: HyperLogLog,
+    /// Every `2^sample_shift`-th non-NULL value.
+    samples: Vec<DataValue>,
+    sample_shift: u32,
+}
+
+impl ColumnStatisticsBuilder {
+    /// Adds values of the column.
+    pub fn add_array(&mut self, array: &ArrayImpl) {
+        for i in 0..array.len() {
+            self.add(array.get(i));
+        }
+    }
+
+    fn add(&mut self, value: DataValue) {
+        self.rows += 1;
+        if value.is_null() {
+            self.nulls += 1;
+            return;
+        }
+        self.distinct.add(&value);
+        let index = self.rows - self.nulls - 1;
+        if index & ((1 << self.sample_shift) - 1) == 0 {
+            self.samples.push(value.clone());
+            if self.samples.len() == MAX_SAMPLES * 2 {
+                // halve the sampling rate
+                let samples = std::mem::take(&mut self.samples);
+                self.samples = samples.into_iter().step_by(2).collect();
+                self.sample_shift += 1;
+            }
+        }
+        if self.min.as_ref().map_or(true, |min| value < *min) {
+            self.min = Some(value.clone());
+        }
+        if self.max.as_ref().map_or(true, |max| value > *max) {
+            self.max = Some(value);
+        }
+    }
+
+    pub fn finish(mut self) -> ColumnStatistics {
+        self.samples.sort_unstable();
+        let mut histogram = match self.samples.len() {
+            n if n <= NUM_BUCKETS + 1 => self.samples,
+            n => (0..=NUM_BUCKETS)
+                .map(|i| self.samples[i * (n - 1) / NUM_BUCKETS].clone())
+                .collect(),
+        };
+        // the maximum value may not be sampled
+        if let (Some(last), Some(max)) = (histogram.last_mut(), &self.max) {
+            *last = max.clone();
+        }
+        ColumnStatistics {
+            null_fraction: match self.rows {
+                0 => 0.0,
+                rows => self.nulls as f64 / rows as f64,
+            },
+            min: self.min.unwrap_or(DataValue::Null),
+            max: self.max.unwrap_or(DataValue::Null),
+            distinct_count: self.distinct.estimate(),
+            histogram,
+        }
+    }
+}
+
+impl ColumnStatistics {
+    /// Returns the estimated fraction of rows whose value is less than the given value.
+    ///
+    /// Returns `None` if the value can not be compared with the column.
+    pub fn less_than_fraction(&self, value: &DataValue) -> Option<f64> {
+        let bounds = &self.histogram;
+        let Some(first) = bounds.first() else {
+            return Some(0.0);
+        };
+        let value = value.cast(&first.data_type()).ok()?;
+        if value.is_null() {
+            return None;
+        }
+        let i = bounds.partition_point(|bound| *bound < value);
+        let fraction = if i == 0 {
+            0.0
+        } else if i == bounds.len() {
+            1.0
+        } else {
+            // assume values are uniformly distributed in the bucket
+            let to_f64 = |value: &DataValue| match value.cast(&DataType::Float64) {
+                Ok(DataValue::Float64(v)) if value.data_type().is_number() => Some(v.0),
+                _ => None,
+            };
+            let within = match (to_f64(&bounds[i - 1]), to_f64(&bounds[i]), to_f64(&value)) {
+                (Some(low), Some(high), Some(v)) if high > low => (v - low) / (high - low),
+                _ => 0.5,
+            };
+            ((i - 1) as f64 + within) / (bounds.len() - 1) as f64
+        };
+        Some(fraction * (1.0 - self.null_fraction))
+    }
+
+    /// Returns the estimated fraction of rows whose value equals the given value.
+    ///
+    /// Values appearing multiple times in the histogram are frequent ones. Otherwise values are
+    /// assumed to be uniformly distributed. Returns `None` if the value can not be compared with
+    /// the column.
+    pub fn eq_fraction(&self, value: &DataValue) -> Option<f64> {
+        let bounds = &self.histogram;
+        let Some(first) = bounds.first() else {
+            return Some(0.0);
+        };
+        let value = value.cast(&first.data_type()).ok()?;
+        if value.is_null() {
+            return None;
+        }
+        let count = bounds.iter().filter(|bound| **bound == value).count();
+        let fraction = match count {
+            0 | 1 => 1.0 / self.distinct_count.max(1) as f64,
+            _ => count as f64 / bounds.len() as f64,
+        };
+        Some(fraction * (1.0 - self.null_fraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_statistics() {
+        let mut builder = ColumnStatisticsBuilder::default();
+        builder.add_array(&ArrayImpl::new_int32(
+            [Some(3), None, Some(1), Some(3)].into_iter().collect(),
+        ));
+        assert_eq!(
+            builder.finish(),
+            ColumnStatistics {
+                null_fraction: 0.25,
+                min: DataValue::Int32(1),
+                max: DataValue::Int32(3),
+                distinct_count: 2,
+                histogram: vec![
+                    DataValue::Int32(1),
+                    DataValue::Int32(3),
+                    DataValue::Int32(3)
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut builder = ColumnStatisticsBuilder::default();
+        // 0, 1, ..., 999 and 100000 values of 2000
+        builder.add_array(&ArrayImpl::new_int32((0..1000).map(Some).collect()));
+        builder.add_array(&ArrayImpl::new_int32(
+            std::iter::repeat(Some(2000)).take(100000).collect(),
+        ));
+        let stats = builder.finish();
+        assert_eq!(stats.histogram.len(), NUM_BUCKETS + 1);
+        assert_eq!(stats.histogram[0], DataValue::Int32(0));
+        assert_eq!(stats.histogram[NUM_BUCKETS], DataValue::Int32(2000));
+
+        let fraction = |f: Option<f64>| (f.unwrap() * 100.0).round();
+        assert_eq!(
+            fraction(stats.less_than_fraction(&DataValue::Int32(0))),
+            0.0
+        );
+        assert_eq!(
+            fraction(stats.less_than_fraction(&DataValue::Int32(2000))),
+            1.0
+        );
+        assert_eq!(
+            fraction(stats.less_than_fraction(&DataValue::Int32(3000))),
+            100.0
+        );
+        assert_eq!(fraction(stats.eq_fraction(&DataValue::Int32(2000))), 99.0);
+        assert_eq!(fraction(stats.eq_fraction(&DataValue::Int32(500))), 0.0);
+        assert_eq!(stats.less_than_fraction(&DataValue::Null), None);
+    }
+}
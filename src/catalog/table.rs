@@ -6,6 +6,7 @@ use super::*;
 use crate::planner::RecExpr;
 
 /// The catalog of a table.
+#[derive(Clone)]
 pub struct TableCatalog {
     id: TableId,
     name: String,
@@ -15,13 +16,59 @@ pub struct TableCatalog {
 
     kind: TableKind,
     next_column_id: ColumnId,
-    primary_key: Vec<ColumnId>,
+    constraints: Constraints,
+    /// Bumped every time the columns are altered.
+    version: u64,
+}
+
+/// Constraints of a table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Constraints {
+    /// Columns of the primary key in order.
+    pub primary_key: Vec<ColumnId>,
+    /// Column sets declared as `UNIQUE`, not including the primary key.
+    pub unique_keys: Vec<Vec<ColumnId>>,
+    /// Expressions of `CHECK` constraints in SQL.
+    pub checks: Vec<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// A `FOREIGN KEY` constraint.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ForeignKey {
+    /// The referencing columns of this table.
+    pub column_ids: Vec<ColumnId>,
+    pub ref_table_id: TableRefId,
+    /// The primary key or a unique key of the referenced table.
+    pub ref_column_ids: Vec<ColumnId>,
+}
+
+impl ForeignKey {
+    /// The referenced table id of foreign keys referencing the table being created, which is
+    /// replaced with the id of the table when it is added to the catalog.
+    pub const SELF_TABLE_ID: TableId = TableId::MAX;
+}
+
+/// The definition of a view, from which its query is bound again when the catalog is reloaded.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ViewDefinition {
+    /// The query in SQL.
+    pub query: String,
+    /// The search path to resolve names in the query.
+    pub search_path: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TableKind {
     Table,
-    View(RecExpr),
+    View {
+        /// The bound query, which is inlined where the view is referenced.
+        query: RecExpr,
+        /// Tables and views that the query depends on.
+        dependencies: Vec<TableRefId>,
+    },
+    /// A table generated from the catalog on read, such as `pg_catalog.pg_tables`.
+    System,
 }
 
 impl TableCatalog {
@@ -29,9 +76,9 @@ impl TableCatalog {
         id: TableId,
         name: String,
         columns: Vec<ColumnCatalog>,
-        primary_key: Vec<ColumnId>,
+        constraints: Constraints,
     ) -> TableCatalog {
-        Self::new_(id, name, columns, TableKind::Table, primary_key)
+        Self::new_(id, name, columns, TableKind::Table, constraints)
     }
 
     pub fn new_view(
@@ -39,8 +86,22 @@ impl TableCatalog {
         name: String,
         columns: Vec<ColumnCatalog>,
         query: RecExpr,
+        dependencies: Vec<TableRefId>,
     ) -> TableCatalog {
-        Self::new_(id, name, columns, TableKind::View(query), vec![])
+        Self::new_(
+            id,
+            name,
+            columns,
+            TableKind::View {
+                query,
+                dependencies,
+            },
+            Constraints::default(),
+        )
+    }
+
+    pub fn new_system(id: TableId, name: String, columns: Vec<ColumnCatalog>) -> TableCatalog {
+        Self::new_(id, name, columns, TableKind::System, Constraints::default())
     }
 
     fn new_(
@@ -48,7 +109,7 @@ impl TableCatalog {
         name: String,
         columns: Vec<ColumnCatalog>,
         kind: TableKind,
-        primary_key: Vec<ColumnId>,
+        constraints: Constraints,
     ) -> TableCatalog {
         let mut table_catalog = TableCatalog {
             id,
@@ -57,7 +118,8 @@ impl TableCatalog {
             columns: BTreeMap::new(),
             kind,
             next_column_id: 0,
-            primary_key,
+            constraints,
+            version: 0,
         };
         table_catalog
             .add_column(ColumnCatalog::new(
@@ -79,14 +141,49 @@ impl TableCatalog {
                 col_catalog.name().into(),
             ));
         }
-        self.next_column_id += 1;
         let id = col_catalog.id();
+        if id != u32::MAX {
+            self.next_column_id = self.next_column_id.max(id + 1);
+        }
         self.column_idxs
             .insert(col_catalog.name().to_string(), col_catalog.id());
         self.columns.insert(id, col_catalog);
         Ok(id)
     }
 
+    /// Adds a column to an existing table.
+    pub(super) fn alter_add_column(
+        &mut self,
+        col_catalog: ColumnCatalog,
+    ) -> Result<ColumnId, CatalogError> {
+        let id = self.add_column(col_catalog)?;
+        self.version += 1;
+        Ok(id)
+    }
+
+    /// Drops a column from an existing table.
+    pub(super) fn alter_drop_column(&mut self, id: ColumnId) -> Result<(), CatalogError> {
+        let col_catalog = self
+            .columns
+            .remove(&id)
+            .ok_or_else(|| CatalogError::NotFound("column", id.to_string()))?;
+        self.column_idxs.remove(col_catalog.name());
+        self.version += 1;
+        Ok(())
+    }
+
+    /// Returns the id that will be assigned to the next added column.
+    ///
+    /// Ids are never reused, so that data of a dropped column can not be mistaken for a new one.
+    pub fn next_column_id(&self) -> ColumnId {
+        self.next_column_id
+    }
+
+    /// Returns the version of the table schema.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
     pub fn contains_column(&self, name: &str) -> bool {
         self.column_idxs.contains_key(name)
     }
@@ -125,18 +222,42 @@ impl TableCatalog {
     }
 
     pub fn primary_keys(&self) -> Vec<ColumnId> {
-        self.primary_key.clone()
+        self.constraints.primary_key.clone()
+    }
+
+    pub fn unique_keys(&self) -> &[Vec<ColumnId>] {
+        &self.constraints.unique_keys
+    }
+
+    pub fn checks(&self) -> &[String] {
+        &self.constraints.checks
+    }
+
+    pub fn foreign_keys(&self) -> &[ForeignKey] {
+        &self.constraints.foreign_keys
     }
 
     pub fn is_view(&self) -> bool {
-        matches!(self.kind, TableKind::View(_))
+        matches!(self.kind, TableKind::View { .. })
+    }
+
+    pub fn is_system(&self) -> bool {
+        matches!(self.kind, TableKind::System)
     }
 
     /// Returns the query if it is a view.
     pub fn query(&self) -> Option<&RecExpr> {
         match &self.kind {
-            TableKind::Table => None,
-            TableKind::View(query) => Some(query),
+            TableKind::Table | TableKind::System => None,
+            TableKind::View { query, .. } => Some(query),
+        }
+    }
+
+    /// Returns the tables and views that the view depends on.
+    pub fn dependencies(&self) -> &[TableRefId] {
+        match &self.kind {
+            TableKind::Table | TableKind::System => &[],
+            TableKind::View { dependencies, .. } => dependencies,
         }
     }
 }
@@ -151,7 +272,7 @@ mod tests {
         let col1 = ColumnCatalog::new(1, ColumnDesc::new("b", DataType::Bool, false));
 
         let col_catalogs = vec![col0, col1];
-        let table_catalog = TableCatalog::new(0, "t".into(), col_catalogs, vec![]);
+        let table_catalog = TableCatalog::new(0, "t".into(), col_catalogs, Constraints::default());
 
         assert!(!table_catalog.contains_column("c"));
         assert!(table_catalog.contains_column("a"));
@@ -168,4 +289,27 @@ mod tests {
         assert_eq!(col1_catalog.name(), "b");
         assert_eq!(col1_catalog.data_type(), DataType::Bool);
     }
+
+    #[test]
+    fn test_alter_table_catalog() {
+        let col0 = ColumnCatalog::new(0, ColumnDesc::new("a", DataType::Int32, false));
+        let col1 = ColumnCatalog::new(1, ColumnDesc::new("b", DataType::Bool, false));
+        let mut table_catalog =
+            TableCatalog::new(0, "t".into(), vec![col0, col1], Constraints::default());
+        assert_eq!(table_catalog.version(), 0);
+        assert_eq!(table_catalog.next_column_id(), 2);
+
+        table_catalog.alter_drop_column(1).unwrap();
+        assert!(!table_catalog.contains_column("b"));
+        assert_eq!(table_catalog.version(), 1);
+        // column ids are not reused
+        assert_eq!(table_catalog.next_column_id(), 2);
+
+        let col2 = ColumnCatalog::new(2, ColumnDesc::new("b", DataType::Int64, true));
+        table_catalog.alter_add_column(col2).unwrap();
+        assert_eq!(table_catalog.get_column_id_by_name("b"), Some(2));
+        assert_eq!(table_catalog.version(), 2);
+        assert_eq!(table_catalog.next_column_id(), 3);
+        assert_eq!(table_catalog.all_columns().len(), 2);
+    }
 }
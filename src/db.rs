@@ -1,44 +1,108 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use chrono::FixedOffset;
 use futures::TryStreamExt;
+use itertools::Itertools;
 use minitrace::collector::SpanContext;
 use minitrace::Span;
 use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
 
-use crate::array::Chunk;
-use crate::binder::bind_header;
-use crate::catalog::{RootCatalog, RootCatalogRef, TableRefId};
-use crate::parser::{parse, ParserError, Statement};
-use crate::planner::Statistics;
+use crate::array::{ArrayImpl, Chunk, DataChunk};
+use crate::binder::{bind_header, Binder};
+use crate::catalog::{
+    ColumnRefId, ColumnStatisticsBuilder, RootCatalog, RootCatalogRef, TableRefId, TableStatistics,
+};
+use crate::executor::MemoryContext;
+use crate::parser::{parse, Expr as ParserExpr, Ident, ParserError, Statement, Value};
+use crate::planner::{Expr, Optimizer, PlanCache, RecExpr, Statistics};
 use crate::storage::{
-    InMemoryStorage, SecondaryStorage, SecondaryStorageOptions, Storage, StorageColumnRef,
-    StorageImpl, Table,
+    InMemoryStorage, ScanOptions, SecondaryStorage, SecondaryStorageOptions, Storage,
+    StorageColumnRef, StorageImpl, Table, Transaction, TransactionImpl, TxnIterator,
 };
+use crate::types::parse_time_zone;
+use crate::utils::metrics;
 
 /// The database instance.
 pub struct Database {
     catalog: RootCatalogRef,
     storage: StorageImpl,
+    /// The session of [`Database::run`].
+    session: Session,
+    /// Plans of recent queries.
+    plan_cache: PlanCache,
+    /// The root memory context, which has a child context for each running query.
+    memory: MemoryContext,
+}
+
+/// A client session, which has its own session variables, prepared statements and transaction.
+#[derive(Default)]
+pub struct Session {
     config: Mutex<Config>,
+    /// Prepared statements by name.
+    prepared: Mutex<HashMap<String, PreparedStatement>>,
+    /// The transaction started by `BEGIN`, if one is in progress.
+    transaction: Mutex<Option<TransactionImpl>>,
 }
 
-/// The configuration of the database.
+/// The maximum number of plans in the plan cache.
+const PLAN_CACHE_CAPACITY: u64 = 1024;
+
+/// A statement created by `PREPARE`.
+struct PreparedStatement {
+    /// The statement without `PREPARE`.
+    stmt: Statement,
+    /// The optimized plan with parameters.
+    plan: RecExpr,
+    /// The number of parameters.
+    param_count: usize,
+}
+
+/// The session variables. The default value is used for a variable if it is `None`.
 #[derive(Debug, Default)]
 struct Config {
     disable_optimizer: bool,
     mock_stat: Option<Statistics>,
+    max_recursive_iterations: Option<usize>,
+    parallelism: Option<usize>,
+    memory_limit: Option<usize>,
+    /// The maximum memory in bytes a query can use before it is aborted.
+    query_memory_limit: Option<usize>,
+    /// Schemas to look up unqualified names in.
+    search_path: Option<Vec<String>>,
+    /// The maximum number of rows returned by a query.
+    max_output_rows: Option<usize>,
+    /// The maximum number of malformed rows skipped by `COPY FROM`.
+    copy_max_errors: Option<usize>,
+    /// The time zone of timestamps with time zone in text.
+    time_zone: Option<FixedOffset>,
 }
 
+/// The names of session variables shown by `SHOW ALL`.
+const VARIABLES: [&str; 8] = [
+    "copy_max_errors",
+    "max_output_rows",
+    "max_recursive_iterations",
+    "memory_limit",
+    "parallelism",
+    "query_memory_limit",
+    "search_path",
+    "timezone",
+];
+
 impl Database {
     /// Create a new in-memory database instance.
     pub fn new_in_memory() -> Self {
         let storage = InMemoryStorage::new();
         Database {
+            plan_cache: PlanCache::new(storage.catalog().clone(), PLAN_CACHE_CAPACITY),
             catalog: storage.catalog().clone(),
             storage: StorageImpl::InMemoryStorage(Arc::new(storage)),
-            config: Default::default(),
+            session: Default::default(),
+            memory: MemoryContext::new_root("database"),
         }
     }
 
@@ -47,12 +111,19 @@ impl Database {
         let storage = Arc::new(SecondaryStorage::open(options).await.unwrap());
         storage.spawn_compactor().await;
         Database {
+            plan_cache: PlanCache::new(storage.catalog().clone(), PLAN_CACHE_CAPACITY),
             catalog: storage.catalog().clone(),
             storage: StorageImpl::SecondaryStorage(storage),
-            config: Default::default(),
+            session: Default::default(),
+            memory: MemoryContext::new_root("database"),
         }
     }
 
+    /// Returns the catalog of the database.
+    pub fn catalog(&self) -> &RootCatalogRef {
+        &self.catalog
+    }
+
     pub async fn shutdown(&self) -> Result<(), Error> {
         if let StorageImpl::SecondaryStorage(storage) = &self.storage {
             storage.shutdown().await?;
@@ -77,8 +148,15 @@ impl Database {
         })
     }
 
-    /// Run SQL queries and return the outputs.
+    /// Run SQL queries in the default session and return the outputs.
     pub async fn run(&self, sql: &str) -> Result<Vec<Chunk>, Error> {
+        self.run_in_session(&self.session, sql).await
+    }
+
+    /// Run SQL queries in a session and return the outputs.
+    ///
+    /// Session variables set and statements prepared by the queries only apply to the session.
+    pub async fn run_in_session(&self, session: &Session, sql: &str) -> Result<Vec<Chunk>, Error> {
         let _root = Span::root("run_sql", SpanContext::random());
 
         let sql = if let Some(cmd) = sql.trim().strip_prefix('\\') {
@@ -86,63 +164,280 @@ impl Database {
         } else {
             sql.to_string()
         };
+        if let Some(chunk) = self.handle_maintenance(session, &sql).await? {
+            return Ok(vec![chunk]);
+        }
 
-        let optimizer = crate::planner::Optimizer::new(
-            self.catalog.clone(),
-            self.get_storage_statistics().await?,
-            crate::planner::Config {
-                enable_range_filter_scan: self.storage.support_range_filter_scan(),
-                table_is_sorted_by_primary_key: self.storage.table_is_sorted_by_primary_key(),
-            },
-        );
+        let mut optimizer = self.optimizer(session).await?;
+
+        // skip parsing and planning if the plan of the query is cached
+        // plans in a transaction are not cached, since they are optimized for the transaction
+        let mut settings = session
+            .plan_settings()
+            .filter(|_| !session.in_transaction());
+        if let Some(settings) = &settings
+            && let Some((stmt, plan)) = self.plan_cache.get(&sql, settings).await
+        {
+            return Ok(vec![self.execute(session, &optimizer, &stmt, &plan).await?]);
+        }
+        let catalog_version = self.catalog.version();
+        let statistics_version = self.catalog.statistics_version();
 
         let stmts = parse(&sql)?;
+        let cacheable = stmts.len() == 1;
         let mut outputs: Vec<Chunk> = vec![];
         for stmt in stmts {
-            if self.handle_set(&stmt)? {
+            if self.handle_set(session, &stmt)? {
                 continue;
             }
-
-            let mut binder = crate::binder::Binder::new(self.catalog.clone());
-            let mut plan = binder.bind(stmt.clone())?;
-            if !self.config.lock().unwrap().disable_optimizer {
-                plan = optimizer.optimize(plan);
+            if let Some(chunk) = self.handle_show(session, &stmt)? {
+                outputs.push(chunk);
+                continue;
             }
-            let executor = match self.storage.clone() {
-                StorageImpl::InMemoryStorage(s) => {
-                    crate::executor::build(optimizer.clone(), s, &plan)
-                }
-                StorageImpl::SecondaryStorage(s) => {
-                    crate::executor::build(optimizer.clone(), s, &plan)
+            if self.handle_transaction(session, &stmt).await? {
+                optimizer = self.optimizer(session).await?;
+                settings = settings.filter(|_| !session.in_transaction());
+                continue;
+            }
+            if self.handle_prepare(session, &stmt, &optimizer)? {
+                continue;
+            }
+
+            let (stmt, plan) = match stmt {
+                Statement::Execute {
+                    name, parameters, ..
+                } => self.bind_execute(session, &name, parameters)?,
+                stmt => {
+                    let mut binder = Binder::new(self.catalog.clone());
+                    binder.set_search_path(session.search_path());
+                    let plan = binder.bind(stmt.clone())?;
+                    let plan = self.optimize(session, &optimizer, plan);
+                    if cacheable && let Some(settings) = &settings {
+                        (self.plan_cache)
+                            .insert(
+                                &sql,
+                                settings,
+                                catalog_version,
+                                statistics_version,
+                                &stmt,
+                                &plan,
+                            )
+                            .await;
+                    }
+                    (stmt, plan)
                 }
             };
-            let output = executor.try_collect().await?;
-            let mut chunk = Chunk::new(output);
-            chunk = bind_header(chunk, &stmt);
-            outputs.push(chunk);
+            outputs.push(self.execute(session, &optimizer, &stmt, &plan).await?);
         }
         Ok(outputs)
     }
 
-    async fn get_storage_statistics(&self) -> Result<Statistics, Error> {
-        if let Some(mock) = &self.config.lock().unwrap().mock_stat {
-            return Ok(mock.clone());
+    /// Creates an optimizer for the statements of the session.
+    async fn optimizer(&self, session: &Session) -> Result<Optimizer, Error> {
+        // rows written in a transaction are not sorted, and they are not filtered by the storage
+        let in_transaction = session.in_transaction();
+        let mut optimizer_config = crate::planner::Config {
+            enable_range_filter_scan: self.storage.support_range_filter_scan() && !in_transaction,
+            enable_index_scan: !in_transaction,
+            table_is_sorted_by_primary_key: self.storage.table_is_sorted_by_primary_key()
+                && !in_transaction,
+            ..Default::default()
+        };
+        {
+            let config = session.config.lock().unwrap();
+            if let Some(limit) = config.max_recursive_iterations {
+                optimizer_config.max_recursive_iterations = limit;
+            }
+            if let Some(parallelism) = config.parallelism {
+                optimizer_config.parallelism = parallelism;
+            }
+            optimizer_config.memory_limit = config.memory_limit;
+            if let Some(time_zone) = config.time_zone {
+                optimizer_config.time_zone = time_zone;
+            }
+            optimizer_config.copy_max_errors = config.copy_max_errors.unwrap_or_default();
         }
-        let mut stat = Statistics::default();
-        // only secondary storage supports statistics
-        let StorageImpl::SecondaryStorage(storage) = self.storage.clone() else {
-            return Ok(stat);
+        Ok(Optimizer::new(
+            self.catalog.clone(),
+            self.get_storage_statistics(session).await?,
+            optimizer_config,
+        ))
+    }
+
+    /// Executes the plan of a statement and returns the output.
+    ///
+    /// The execution of a query stops once `max_output_rows` rows are returned. Memory used by the
+    /// statement, including its output, is tracked in a memory context of the statement, and the
+    /// statement is aborted if it exceeds `query_memory_limit`.
+    async fn execute(
+        &self,
+        session: &Session,
+        optimizer: &Optimizer,
+        stmt: &Statement,
+        plan: &RecExpr,
+    ) -> Result<Chunk, Error> {
+        let schema = crate::executor::output_schema(&self.catalog, plan);
+        let (max_output_rows, query_memory_limit) = {
+            let config = session.config.lock().unwrap();
+            let max_output_rows = match stmt {
+                Statement::Query(_) => config.max_output_rows,
+                _ => None,
+            };
+            (max_output_rows, config.query_memory_limit)
         };
-        for schema in self.catalog.all_schemas().values() {
-            // skip internal schema
-            if schema.name() == RootCatalog::SYSTEM_SCHEMA_NAME {
-                continue;
+        let memory = self.memory.child(&stmt.to_string(), query_memory_limit);
+        let transaction = session.transaction.lock().unwrap().clone();
+        let mut executor = match (self.storage.clone(), transaction) {
+            (_, Some(TransactionImpl::InMemoryStorage(txn))) => {
+                crate::executor::build(optimizer.clone(), Arc::new(txn), plan, memory.clone())
             }
-            for table in schema.all_tables().values() {
-                if table.is_view() {
-                    continue;
+            (_, Some(TransactionImpl::SecondaryStorage(txn))) => {
+                crate::executor::build(optimizer.clone(), Arc::new(txn), plan, memory.clone())
+            }
+            (StorageImpl::InMemoryStorage(s), None) => {
+                crate::executor::build(optimizer.clone(), s, plan, memory.clone())
+            }
+            (StorageImpl::SecondaryStorage(s), None) => {
+                crate::executor::build(optimizer.clone(), s, plan, memory.clone())
+            }
+        };
+        metrics::QUERIES.inc();
+        let start = Instant::now();
+        let output = async {
+            let mut output = vec![];
+            let mut rows = 0;
+            while let Some(chunk) = executor.try_next().await? {
+                memory.alloc(chunk.estimated_size())?;
+                rows += chunk.cardinality();
+                if let Some(limit) = max_output_rows
+                    && rows >= limit
+                {
+                    output.push(chunk.slice(..chunk.cardinality() - (rows - limit)));
+                    break;
+                }
+                output.push(chunk);
+            }
+            Ok::<_, Error>(output)
+        }
+        .await;
+        metrics::QUERY_DURATION.observe(start.elapsed());
+        let output = output.inspect_err(|_| metrics::QUERY_ERRORS.inc())?;
+        let mut chunk = Chunk::new(output);
+        if let Some(schema) = schema {
+            chunk.set_schema(schema);
+        }
+        Ok(bind_header(chunk, stmt))
+    }
+
+    /// Optimizes the plan unless the optimizer is disabled.
+    fn optimize(&self, session: &Session, optimizer: &Optimizer, plan: RecExpr) -> RecExpr {
+        if session.config.lock().unwrap().disable_optimizer {
+            return plan;
+        }
+        optimizer.optimize(plan)
+    }
+
+    /// Handles `PREPARE` and `DEALLOCATE` statements. Returns true if the statement is handled.
+    ///
+    /// The plan of a prepared statement is optimized once when it is prepared, and reused by every
+    /// `EXECUTE`.
+    fn handle_prepare(
+        &self,
+        session: &Session,
+        stmt: &Statement,
+        optimizer: &Optimizer,
+    ) -> Result<bool, Error> {
+        match stmt {
+            Statement::Prepare {
+                name,
+                data_types,
+                statement,
+            } => {
+                let name = prepared_name(name);
+                let param_types = data_types.iter().map(Into::into).collect();
+                let mut binder = Binder::new_prepared(self.catalog.clone(), param_types);
+                binder.set_search_path(session.search_path());
+                let plan = binder.bind((**statement).clone())?;
+                let prepared = PreparedStatement {
+                    stmt: (**statement).clone(),
+                    plan: self.optimize(session, optimizer, plan),
+                    param_count: binder.param_count(),
+                };
+                let mut statements = session.prepared.lock().unwrap();
+                if statements.contains_key(&name) {
+                    return Err(Error::PreparedStatementExists(name));
+                }
+                statements.insert(name, prepared);
+            }
+            // `DEALLOCATE ALL` removes all prepared statements
+            Statement::Deallocate { name, .. }
+                if name.quote_style.is_none() && name.value.eq_ignore_ascii_case("all") =>
+            {
+                session.prepared.lock().unwrap().clear();
+            }
+            Statement::Deallocate { name, .. } => {
+                let name = prepared_name(name);
+                if session.prepared.lock().unwrap().remove(&name).is_none() {
+                    return Err(Error::NoPreparedStatement(name));
                 }
-                let table_id = TableRefId::new(schema.id(), table.id());
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Returns the statement and plan of `EXECUTE name(parameters)`.
+    ///
+    /// The parameters are evaluated and substituted into the prepared plan without re-planning.
+    fn bind_execute(
+        &self,
+        session: &Session,
+        name: &Ident,
+        parameters: Vec<ParserExpr>,
+    ) -> Result<(Statement, RecExpr), Error> {
+        let name = prepared_name(name);
+        let (stmt, plan, param_count) = {
+            let statements = session.prepared.lock().unwrap();
+            let prepared =
+                (statements.get(&name)).ok_or_else(|| Error::NoPreparedStatement(name.clone()))?;
+            (
+                prepared.stmt.clone(),
+                prepared.plan.clone(),
+                prepared.param_count,
+            )
+        };
+        if parameters.len() != param_count {
+            return Err(Error::ParameterCountMismatch {
+                name,
+                expected: param_count,
+                actual: parameters.len(),
+            });
+        }
+        let mut binder = Binder::new(self.catalog.clone());
+        let values = (parameters.into_iter())
+            .map(|expr| binder.bind_constant(expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        let nodes = (plan.as_ref().iter())
+            .map(|node| match node {
+                Expr::Param(i) => Expr::Constant(values[i.0 as usize - 1].clone()),
+                node => node.clone(),
+            })
+            .collect::<Vec<_>>();
+        Ok((stmt, RecExpr::from(nodes)))
+    }
+
+    async fn get_storage_statistics(&self, session: &Session) -> Result<Statistics, Error> {
+        if let Some(mock) = &session.config.lock().unwrap().mock_stat {
+            return Ok(mock.clone());
+        }
+        let mut stat = Statistics::default();
+        // only secondary storage supports block statistics
+        if let StorageImpl::SecondaryStorage(storage) = self.storage.clone() {
+            for table_id in self.user_tables() {
+                let column_ids = (self.catalog.get_table(&table_id).unwrap())
+                    .all_columns()
+                    .into_keys()
+                    .collect::<Vec<_>>();
                 let table = storage.get_table(table_id)?;
                 let txn = table.read().await?;
                 let values = txn.aggreagate_block_stat(&[(
@@ -150,36 +445,232 @@ impl Database {
                     StorageColumnRef::Idx(0),
                 )]);
                 stat.add_row_count(table_id, values[0].as_usize().unwrap().unwrap() as u32);
+
+                // distinct values of each column for join ordering
+                let stats = (0..column_ids.len())
+                    .map(|idx| {
+                        (
+                            BlockStatisticsType::DistinctValue,
+                            StorageColumnRef::Idx(idx as u32),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let values = txn.aggreagate_block_stat(&stats);
+                for (column_id, value) in column_ids.into_iter().zip(values) {
+                    if let Ok(Some(count)) = value.as_usize() {
+                        let column_id = ColumnRefId::from_table(table_id, 0, column_id);
+                        stat.add_distinct_values(column_id, count as u32);
+                    }
+                }
+            }
+        }
+        // statistics collected by `ANALYZE` are more accurate than block statistics, but row
+        // counts may be stale
+        for (table_id, table_stat) in self.catalog.all_statistics() {
+            if stat.get_row_count(table_id).is_none() {
+                stat.add_row_count(table_id, table_stat.row_count as u32);
+            }
+            for (column_id, column_stat) in table_stat.columns {
+                let column_id = ColumnRefId::from_table(table_id, 0, column_id);
+                stat.add_distinct_values(column_id, column_stat.distinct_count as u32);
+                stat.add_column_statistics(column_id, column_stat);
             }
         }
         Ok(stat)
     }
 
-    /// Mock the row count of a table for planner test.
-    fn handle_set(&self, stmt: &Statement) -> Result<bool, Error> {
-        if let Statement::Pragma { name, .. } = stmt {
-            match name.to_string().as_str() {
-                "enable_optimizer" => {
-                    self.config.lock().unwrap().disable_optimizer = false;
-                    return Ok(true);
+    /// Handles `VACUUM [table]`, `CHECKPOINT` and `ANALYZE [[TABLE] table]` statements, which are
+    /// not supported by the parser.
+    ///
+    /// `CHECKPOINT` writes the write-ahead log to disk. `VACUUM` also merges RowSets and removes
+    /// deleted rows, and returns the number of bytes reclaimed and RowSets merged. Both are no-ops
+    /// on the in-memory storage. `ANALYZE` scans tables and stores their statistics in the catalog
+    /// for the optimizer.
+    async fn handle_maintenance(
+        &self,
+        session: &Session,
+        sql: &str,
+    ) -> Result<Option<Chunk>, Error> {
+        let sql = sql.trim().trim_end_matches(';').to_lowercase();
+        let tokens = sql.split_whitespace().collect::<Vec<_>>();
+        let table_id = match tokens.as_slice() {
+            ["checkpoint"] => {
+                if let StorageImpl::SecondaryStorage(storage) = &self.storage {
+                    storage.checkpoint().await?;
                 }
-                "disable_optimizer" => {
-                    self.config.lock().unwrap().disable_optimizer = true;
-                    return Ok(true);
+                return Ok(Some(Chunk::new(vec![])));
+            }
+            ["analyze"] => {
+                for table_id in self.user_tables() {
+                    self.analyze(table_id).await?;
                 }
-                name => {
-                    return Err(crate::binder::BindError::NoPragma(name.into()).into());
+                return Ok(Some(Chunk::new(vec![])));
+            }
+            ["analyze", name] | ["analyze", "table", name] => {
+                self.analyze(self.get_table_id(session, name)?).await?;
+                return Ok(Some(Chunk::new(vec![])));
+            }
+            ["vacuum"] => None,
+            ["vacuum", name] => Some(self.get_table_id(session, name)?),
+            _ => return Ok(None),
+        };
+        let (bytes_reclaimed, rowsets_merged) = match &self.storage {
+            StorageImpl::InMemoryStorage(_) => (0, 0),
+            StorageImpl::SecondaryStorage(storage) => {
+                let stats = storage.vacuum(table_id).await?;
+                (stats.bytes_reclaimed, stats.rowsets_merged)
+            }
+        };
+        let output: DataChunk = [
+            ArrayImpl::new_int64([bytes_reclaimed as i64].into_iter().collect()),
+            ArrayImpl::new_int32([rowsets_merged as i32].into_iter().collect()),
+        ]
+        .into_iter()
+        .collect();
+        let mut chunk = Chunk::new(vec![output]);
+        chunk.set_header(vec!["bytes_reclaimed".into(), "rowsets_merged".into()]);
+        Ok(Some(chunk))
+    }
+
+    /// Returns the id of a table given by `[schema.]table`.
+    ///
+    /// An unqualified table is looked up in the schemas of the search path.
+    fn get_table_id(&self, session: &Session, name: &str) -> Result<TableRefId, Error> {
+        let table_id = match name.split_once('.') {
+            Some((schema_name, table_name)) => {
+                (self.catalog).get_table_id_by_name(schema_name, table_name)
+            }
+            None => (session.search_path().iter())
+                .find_map(|schema_name| self.catalog.get_table_id_by_name(schema_name, name)),
+        };
+        let table_id =
+            table_id.ok_or_else(|| crate::binder::BindError::InvalidTable(name.to_string()))?;
+        Ok(table_id)
+    }
+
+    /// Returns the ids of all tables that are not views or system tables.
+    fn user_tables(&self) -> Vec<TableRefId> {
+        let mut table_ids = vec![];
+        for schema in self.catalog.all_schemas().values() {
+            for table in schema.all_tables().values() {
+                if !table.is_view() && !table.is_system() {
+                    table_ids.push(TableRefId::new(schema.id(), table.id()));
                 }
             }
         }
-        let Statement::SetVariable {
-            variable, value, ..
-        } = stmt
-        else {
-            return Ok(false);
+        table_ids
+    }
+
+    /// Collects statistics of a table and stores them in the catalog.
+    async fn analyze(&self, table_id: TableRefId) -> Result<(), Error> {
+        let stats = match &self.storage {
+            StorageImpl::InMemoryStorage(storage) => {
+                collect_statistics(&**storage, table_id).await?
+            }
+            StorageImpl::SecondaryStorage(storage) => {
+                collect_statistics(&**storage, table_id).await?
+            }
         };
+        self.catalog.set_statistics(table_id, stats);
+        Ok(())
+    }
+
+    /// Handles transaction control statements. Returns true if the statement is handled.
+    ///
+    /// Each session has its own transaction. Writes of statements in the transaction are kept in
+    /// the transaction, so that they are only visible to later statements of the session until
+    /// `COMMIT`. Since the catalog can not be restored, DDL statements are rejected in a
+    /// transaction.
+    async fn handle_transaction(&self, session: &Session, stmt: &Statement) -> Result<bool, Error> {
+        match stmt {
+            Statement::StartTransaction { .. } => {
+                let mut transaction = session.transaction.lock().unwrap();
+                if transaction.is_some() {
+                    return Err(Error::TransactionInProgress);
+                }
+                *transaction = Some(self.storage.begin());
+            }
+            Statement::Commit { .. } => {
+                let transaction = session.transaction.lock().unwrap().take();
+                transaction.ok_or(Error::NoTransaction)?.commit().await?;
+            }
+            Statement::Rollback {
+                savepoint: None, ..
+            } => {
+                let transaction = session.transaction.lock().unwrap().take();
+                transaction.ok_or(Error::NoTransaction)?.rollback().await?;
+            }
+            Statement::CreateTable { .. }
+            | Statement::CreateView { .. }
+            | Statement::CreateIndex { .. }
+            | Statement::CreateFunction { .. }
+            | Statement::CreateSchema { .. }
+            | Statement::AlterTable { .. }
+            | Statement::Drop { .. }
+            | Statement::DropFunction { .. }
+            | Statement::Truncate { .. }
+                if session.in_transaction() =>
+            {
+                return Err(Error::DdlInTransaction);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Handle `PRAGMA` and `SET` statements. Returns true if the statement is handled.
+    fn handle_set(&self, session: &Session, stmt: &Statement) -> Result<bool, Error> {
+        let (variable, value) = match stmt {
+            Statement::Pragma { name, .. } => {
+                let disable = match name.to_string().as_str() {
+                    "enable_optimizer" => false,
+                    "disable_optimizer" => true,
+                    name => return Err(crate::binder::BindError::NoPragma(name.into()).into()),
+                };
+                session.config.lock().unwrap().disable_optimizer = disable;
+                return Ok(true);
+            }
+            // `SET TIME ZONE <value>`
+            Statement::SetTimeZone { value, .. } => {
+                session.set_variable("timezone", std::slice::from_ref(value))?;
+                return Ok(true);
+            }
+            Statement::SetVariable {
+                variable, value, ..
+            } => (variable, value),
+            _ => return Ok(false),
+        };
+        if let [name] = variable.0.as_slice()
+            && session.set_variable(&name.value.to_lowercase(), value)?
+        {
+            return Ok(true);
+        }
+        // `SET mock_distinct_<table>.<column> = <n>`
+        if let [table, column] = variable.0.as_slice()
+            && let Some(table_name) = table.value.strip_prefix("mock_distinct_")
+        {
+            let count = value[0]
+                .to_string()
+                .parse::<u32>()
+                .map_err(|_| Error::Internal("invalid count".into()))?;
+            let table_id = self
+                .catalog
+                .get_table_id_by_name("postgres", table_name)
+                .ok_or_else(|| Error::Internal("table not found".into()))?;
+            let column_id = (self.catalog.get_table(&table_id))
+                .and_then(|table| table.get_column_id_by_name(&column.value))
+                .ok_or_else(|| Error::Internal("column not found".into()))?;
+            session
+                .config
+                .lock()
+                .unwrap()
+                .mock_stat
+                .get_or_insert_with(Default::default)
+                .add_distinct_values(ColumnRefId::from_table(table_id, 0, column_id), count);
+            return Ok(true);
+        }
         let Some(table_name) = variable.0[0].value.strip_prefix("mock_rowcount_") else {
-            return Ok(false);
+            return Err(Error::UnknownVariable(variable.to_string()));
         };
         let count = value[0]
             .to_string()
@@ -189,7 +680,8 @@ impl Database {
             .catalog
             .get_table_id_by_name("postgres", table_name)
             .ok_or_else(|| Error::Internal("table not found".into()))?;
-        self.config
+        session
+            .config
             .lock()
             .unwrap()
             .mock_stat
@@ -197,6 +689,167 @@ impl Database {
             .add_row_count(table_id, count);
         Ok(true)
     }
+
+    /// Handles `SHOW <variable>` and `SHOW ALL`. Returns the values of variables if the statement
+    /// is handled.
+    fn handle_show(&self, session: &Session, stmt: &Statement) -> Result<Option<Chunk>, Error> {
+        let Statement::ShowVariable { variable } = stmt else {
+            return Ok(None);
+        };
+        let name = variable
+            .iter()
+            .map(|ident| ident.value.to_lowercase())
+            .join(" ");
+        let (names, header) = match name.as_str() {
+            "all" => (VARIABLES.to_vec(), vec!["name".into(), "setting".into()]),
+            "time zone" => (vec!["timezone"], vec!["timezone".into()]),
+            name => (vec![name], vec![name.into()]),
+        };
+        let settings = (names.iter())
+            .map(|name| {
+                (session.get_variable(name)).ok_or_else(|| Error::UnknownVariable(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut arrays = vec![];
+        if name == "all" {
+            arrays.push(ArrayImpl::new_string(
+                names.iter().map(|s| Some(*s)).collect(),
+            ));
+        }
+        arrays.push(ArrayImpl::new_string(
+            settings.iter().map(|s| Some(s.as_str())).collect(),
+        ));
+        let mut chunk = Chunk::new(vec![arrays.into_iter().collect()]);
+        chunk.set_header(header);
+        Ok(Some(chunk))
+    }
+}
+
+impl Session {
+    /// Returns true if a transaction is in progress.
+    fn in_transaction(&self) -> bool {
+        self.transaction.lock().unwrap().is_some()
+    }
+
+    /// Sets a session variable. Returns false if the variable does not exist.
+    fn set_variable(&self, name: &str, value: &[ParserExpr]) -> Result<bool, Error> {
+        let invalid = || Error::InvalidValue(name.into(), value.iter().join(", "));
+        // `SET <variable> = default` resets the variable
+        let is_default = matches!(value, [ParserExpr::Identifier(ident)]
+            if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("default"));
+        // a number, where `0` means no limit for limits
+        let number = || match value {
+            [ParserExpr::Value(Value::Number(n, _))] => n.parse::<usize>().map_err(|_| invalid()),
+            _ => Err(invalid()),
+        };
+        let mut config = self.config.lock().unwrap();
+        match name {
+            "max_recursive_iterations" if is_default => config.max_recursive_iterations = None,
+            "max_recursive_iterations" => config.max_recursive_iterations = Some(number()?),
+            "parallelism" if is_default => config.parallelism = None,
+            "parallelism" => match number()? {
+                0 => return Err(invalid()),
+                n => config.parallelism = Some(n),
+            },
+            // `SET memory_limit = <bytes>`, or `0` for no limit
+            "memory_limit" if is_default => config.memory_limit = None,
+            "memory_limit" => config.memory_limit = Some(number()?).filter(|&n| n != 0),
+            // `SET query_memory_limit = <bytes>`, or `0` for no limit
+            "query_memory_limit" if is_default => config.query_memory_limit = None,
+            "query_memory_limit" => config.query_memory_limit = Some(number()?).filter(|&n| n != 0),
+            // `SET max_output_rows = <n>`, or `0` for no limit
+            "max_output_rows" if is_default => config.max_output_rows = None,
+            "max_output_rows" => config.max_output_rows = Some(number()?).filter(|&n| n != 0),
+            // `SET copy_max_errors = <n>`, or `0` to abort on the first malformed row
+            "copy_max_errors" if is_default => config.copy_max_errors = None,
+            "copy_max_errors" => config.copy_max_errors = Some(number()?).filter(|&n| n != 0),
+            // `SET search_path = <schema>[, <schema>...]`
+            "search_path" if is_default => config.search_path = None,
+            "search_path" => {
+                let search_path = (value.iter())
+                    .map(|expr| match expr {
+                        ParserExpr::Identifier(ident) if ident.quote_style.is_none() => {
+                            Ok(ident.value.to_lowercase())
+                        }
+                        ParserExpr::Identifier(ident) => Ok(ident.value.clone()),
+                        ParserExpr::Value(Value::SingleQuotedString(s)) => Ok(s.clone()),
+                        _ => Err(invalid()),
+                    })
+                    .collect::<Result<_, _>>()?;
+                config.search_path = Some(search_path);
+            }
+            "timezone" if is_default => config.time_zone = None,
+            "timezone" => {
+                let tz = match value {
+                    [ParserExpr::Value(Value::SingleQuotedString(s))] => parse_time_zone(s),
+                    [ParserExpr::Value(Value::Number(n, _))] => parse_time_zone(n),
+                    [ParserExpr::UnaryOp { op, expr }] => parse_time_zone(&format!("{op}{expr}")),
+                    [ParserExpr::Identifier(ident)] => parse_time_zone(&ident.value),
+                    _ => None,
+                };
+                config.time_zone = Some(tz.ok_or_else(invalid)?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Returns the value of a session variable in text.
+    fn get_variable(&self, name: &str) -> Option<String> {
+        let config = self.config.lock().unwrap();
+        let default = crate::planner::Config::default();
+        let limit = |limit: Option<usize>| limit.map_or("0".into(), |n| n.to_string());
+        Some(match name {
+            "max_recursive_iterations" => config
+                .max_recursive_iterations
+                .unwrap_or(default.max_recursive_iterations)
+                .to_string(),
+            "parallelism" => config
+                .parallelism
+                .unwrap_or(default.parallelism)
+                .to_string(),
+            "memory_limit" => limit(config.memory_limit),
+            "query_memory_limit" => limit(config.query_memory_limit),
+            "max_output_rows" => limit(config.max_output_rows),
+            "copy_max_errors" => limit(config.copy_max_errors),
+            "search_path" => (config.search_path.clone())
+                .unwrap_or_else(|| vec![RootCatalog::DEFAULT_SCHEMA_NAME.into()])
+                .join(", "),
+            "timezone" => config.time_zone.unwrap_or(default.time_zone).to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Returns the time zone of timestamps with time zone in text.
+    pub fn time_zone(&self) -> FixedOffset {
+        let time_zone = self.config.lock().unwrap().time_zone;
+        time_zone.unwrap_or_else(|| crate::planner::Config::default().time_zone)
+    }
+
+    /// Returns the schemas to look up unqualified names in.
+    fn search_path(&self) -> Vec<String> {
+        let search_path = self.config.lock().unwrap().search_path.clone();
+        search_path.unwrap_or_else(|| vec![RootCatalog::DEFAULT_SCHEMA_NAME.into()])
+    }
+
+    /// Returns the session variables that plans depend on in text, so that plans are only cached
+    /// for sessions with the same settings. Returns `None` if plans should not be cached.
+    fn plan_settings(&self) -> Option<String> {
+        let config = self.config.lock().unwrap();
+        if config.mock_stat.is_some() {
+            return None;
+        }
+        // constants are folded in the time zone, e.g. casts from strings to timestamps
+        Some(format!(
+            "{:?} {:?} {:?} {:?} {:?} {:?}",
+            config.disable_optimizer,
+            config.max_recursive_iterations,
+            config.parallelism,
+            config.memory_limit,
+            config.search_path,
+            config.time_zone,
+        ))
+    }
 }
 
 /// The error type of database operations.
@@ -227,6 +880,64 @@ pub enum Error {
         #[backtrace]
         crate::storage::TracedStorageError,
     ),
+    #[error("there is already a transaction in progress")]
+    TransactionInProgress,
+    #[error("there is no transaction in progress")]
+    NoTransaction,
+    #[error("DDL statements are not supported in a transaction")]
+    DdlInTransaction,
+    #[error("prepared statement {0:?} already exists")]
+    PreparedStatementExists(String),
+    #[error("prepared statement {0:?} does not exist")]
+    NoPreparedStatement(String),
+    #[error("prepared statement {name:?} expects {expected} parameters, but {actual} are given")]
+    ParameterCountMismatch {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("unrecognized configuration parameter {0:?}")]
+    UnknownVariable(String),
+    #[error("invalid value for parameter {0:?}: {1}")]
+    InvalidValue(String, String),
     #[error("Internal error: {0}")]
     Internal(String),
 }
+
+/// Returns the name of a prepared statement. Unquoted names are case-insensitive.
+fn prepared_name(name: &Ident) -> String {
+    match name.quote_style {
+        Some(_) => name.value.clone(),
+        None => name.value.to_lowercase(),
+    }
+}
+
+/// Scans all rows of a table and collects its statistics.
+async fn collect_statistics(
+    storage: &impl Storage,
+    table_id: TableRefId,
+) -> Result<TableStatistics, Error> {
+    let table = storage.get_table(table_id)?;
+    let columns = table.columns()?;
+    let col_idx = (0..columns.len() as u32)
+        .map(StorageColumnRef::Idx)
+        .collect::<Vec<_>>();
+    let mut builders = (columns.iter())
+        .map(|_| ColumnStatisticsBuilder::default())
+        .collect::<Vec<_>>();
+    let mut row_count = 0;
+    let txn = table.read().await?;
+    let mut it = txn.scan(&col_idx, ScanOptions::default()).await?;
+    while let Some(chunk) = it.next_batch(None).await? {
+        row_count += chunk.cardinality() as u64;
+        for (builder, array) in builders.iter_mut().zip(chunk.arrays()) {
+            builder.add_array(array);
+        }
+    }
+    Ok(TableStatistics {
+        row_count,
+        columns: (columns.iter().map(|c| c.id()))
+            .zip(builders.into_iter().map(|b| b.finish()))
+            .collect(),
+    })
+}
@@ -0,0 +1,33 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::binder::{AlterTable, AlterTableOp};
+use crate::storage::Storage;
+
+/// The executor of `alter table` statement.
+pub struct AlterTableExecutor<S: Storage> {
+    pub table: Box<AlterTable>,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> AlterTableExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        // nothing to do if the table doesn't exist
+        if let Some(table_id) = self.table.table_id {
+            for op in &self.table.operations {
+                match op {
+                    AlterTableOp::AddColumn(column) => {
+                        self.storage.add_column(table_id, column).await?;
+                    }
+                    AlterTableOp::DropColumn(column_id) => {
+                        self.storage.drop_column(table_id, *column_id).await?;
+                    }
+                }
+            }
+        }
+        yield DataChunk::single(1);
+    }
+}
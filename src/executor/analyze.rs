@@ -26,9 +26,18 @@ impl AnalyzeExecutor {
 
         // explain the plan
         let get_metadata = |id| {
+            let (Some(rows), Some(chunks), Some(time)) = (
+                self.metrics.get_rows(id),
+                self.metrics.get_chunks(id),
+                self.metrics.get_time(id),
+            ) else {
+                // the node is not built by this query, e.g. the recursive term of recursive CTE
+                return vec![];
+            };
             vec![
-                ("rows", self.metrics.get_rows(id).to_string()),
-                ("time", format!("{:?}", self.metrics.get_time(id))),
+                ("rows", rows.to_string()),
+                ("chunks", chunks.to_string()),
+                ("time", format!("{time:?}")),
             ]
         };
         let explain_obj = Explain::of(&self.plan)
@@ -54,23 +63,34 @@ impl AnalyzeExecutor {
 pub struct Metrics {
     spans: HashMap<Id, TimeSpan>,
     rows: HashMap<Id, Counter>,
+    chunks: HashMap<Id, Counter>,
 }
 
 impl Metrics {
-    /// Register metrics for a node.
-    pub fn register(&mut self, id: Id, span: TimeSpan, rows: Counter) {
-        self.spans.insert(id, span);
-        self.rows.insert(id, rows);
+    /// Register metrics for a node and returns its time span, row counter and chunk counter.
+    ///
+    /// Metrics are shared if the node is registered multiple times, e.g. for each partition.
+    pub fn register(&mut self, id: Id) -> (TimeSpan, Counter, Counter) {
+        (
+            self.spans.entry(id).or_default().clone(),
+            self.rows.entry(id).or_default().clone(),
+            self.chunks.entry(id).or_default().clone(),
+        )
     }
 
     /// Get the running time for a node.
-    pub fn get_time(&self, id: Id) -> Duration {
-        self.spans.get(&id).map(|span| span.busy_time()).unwrap()
+    pub fn get_time(&self, id: Id) -> Option<Duration> {
+        self.spans.get(&id).map(|span| span.busy_time())
     }
 
     /// Get the number of rows produced by a node.
-    pub fn get_rows(&self, id: Id) -> u64 {
-        self.rows.get(&id).map(|rows| rows.get()).unwrap()
+    pub fn get_rows(&self, id: Id) -> Option<u64> {
+        self.rows.get(&id).map(|rows| rows.get())
+    }
+
+    /// Get the number of chunks produced by a node.
+    pub fn get_chunks(&self, id: Id) -> Option<u64> {
+        self.chunks.get(&id).map(|chunks| chunks.get())
     }
 }
 
@@ -0,0 +1,463 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Checks of table constraints on rows written by `insert` and `update`.
+
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Bound;
+use std::sync::Arc;
+
+use super::*;
+use crate::array::DataChunk;
+use crate::catalog::{ColumnCatalog, ColumnId, ForeignKey, IndexId, TableRefId};
+use crate::storage::{
+    KeyRange, ScanOptions, Storage, StorageColumnRef, Table, TracedStorageError, Transaction,
+    TxnIterator,
+};
+use crate::types::{DataValue, Row};
+
+/// Constraints of a table.
+#[derive(Default)]
+pub struct TableConstraints {
+    /// Column sets whose values must be unique, including the primary key.
+    pub unique_keys: Vec<Vec<ColumnId>>,
+    /// `CHECK` constraints in SQL, and their conditions on columns of the table.
+    pub checks: Vec<(String, RecExpr)>,
+    /// Foreign keys, with an index to look up keys in the referenced table if there is one.
+    pub foreign_keys: Vec<(ForeignKey, Option<IndexId>)>,
+}
+
+/// A foreign key referencing a table.
+pub struct Referencing {
+    pub table_id: TableRefId,
+    /// The name of the referencing table for error messages.
+    pub table_name: String,
+    pub foreign_key: ForeignKey,
+    /// An index to look up keys in the referencing table, if there is one.
+    pub index: Option<IndexId>,
+}
+
+/// Checks rows to be written to a table against its constraints.
+///
+/// The rows must contain all columns of the table in order.
+pub struct ConstraintChecker<'a, S: Storage> {
+    storage: &'a S,
+    columns: &'a [ColumnCatalog],
+    /// Check conditions on the positions of columns.
+    checks: Vec<(&'a str, RecExpr)>,
+    unique: UniqueChecker,
+    foreign_keys: Vec<ForeignKeyChecker<'a>>,
+    excluded: &'a HashSet<DataValue>,
+}
+
+impl<'a, S: Storage> ConstraintChecker<'a, S> {
+    /// Collects existing unique keys of the table in the transaction.
+    ///
+    /// Rows in `excluded` are about to be removed by the transaction, so their keys are ignored.
+    /// The transaction must be an [`update`](Table::update) one if the table has unique keys, so
+    /// that no key is added by other writers until it ends.
+    pub async fn new(
+        storage: &'a S,
+        table: &S::Table,
+        txn: &S::Transaction,
+        columns: &'a [ColumnCatalog],
+        constraints: &'a TableConstraints,
+        excluded: &'a HashSet<DataValue>,
+    ) -> Result<Self> {
+        let checks = (constraints.checks.iter())
+            .map(|(sql, cond)| (sql.as_str(), resolve_table_columns(cond, columns)))
+            .collect();
+        let foreign_keys = (constraints.foreign_keys.iter())
+            .map(|(foreign_key, index)| {
+                ForeignKeyChecker::new(table.table_id(), columns, foreign_key, *index)
+            })
+            .try_collect()?;
+        Ok(ConstraintChecker {
+            storage,
+            columns,
+            checks,
+            unique: UniqueChecker::new(columns, &constraints.unique_keys, txn, excluded).await?,
+            foreign_keys,
+            excluded,
+        })
+    }
+
+    /// Checks rows to be written, and remembers their keys.
+    pub fn check(&mut self, chunk: &DataChunk) -> Result<()> {
+        for (column, array) in self.columns.iter().zip(chunk.arrays()) {
+            if !column.is_nullable() && array.iter().any(|v| v.is_null()) {
+                return Err(ExecutorError::not_nullable(column.name()));
+            }
+        }
+        for (sql, cond) in &self.checks {
+            let result = Evaluator::new(cond).eval(chunk)?;
+            if result.iter().any(|v| v == DataValue::Bool(false)) {
+                return Err(ExecutorError::check_violation(sql));
+            }
+        }
+        self.unique.check(chunk)?;
+        for foreign_key in &mut self.foreign_keys {
+            foreign_key.add(chunk);
+        }
+        Ok(())
+    }
+
+    /// Checks that the rows checked reference existing rows, after all rows are checked.
+    pub async fn finish(self, txn: &S::Transaction) -> Result<()> {
+        for foreign_key in self.foreign_keys {
+            foreign_key.check(self.storage, txn, self.excluded).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that no two rows in a table have the same value on a unique key.
+///
+/// Keys containing NULL are never considered duplicates.
+struct UniqueChecker {
+    keys: Vec<UniqueKey>,
+}
+
+struct UniqueKey {
+    /// Positions of the key columns in the table.
+    positions: Vec<usize>,
+    /// Names of the key columns for error messages.
+    names: Vec<String>,
+    /// Known values of the key.
+    values: HashSet<Row>,
+}
+
+impl UniqueChecker {
+    async fn new(
+        columns: &[ColumnCatalog],
+        unique_keys: &[Vec<ColumnId>],
+        txn: &impl Transaction,
+        excluded: &HashSet<DataValue>,
+    ) -> Result<Self> {
+        let positions: Vec<_> = (unique_keys.iter())
+            .map(|key| key_positions(columns, key))
+            .try_collect()?;
+        let values = scan_keys(txn, &positions, excluded).await?;
+        let keys = (positions.into_iter().zip(values))
+            .map(|(positions, values)| UniqueKey {
+                names: (positions.iter())
+                    .map(|i| columns[*i].name().to_string())
+                    .collect(),
+                positions,
+                values,
+            })
+            .collect();
+        Ok(UniqueChecker { keys })
+    }
+
+    fn check(&mut self, chunk: &DataChunk) -> Result<()> {
+        for key in &mut self.keys {
+            for row in chunk.rows() {
+                let value = row.get_by_indexes(&key.positions);
+                if value.iter().any(|v| v.is_null()) {
+                    continue;
+                }
+                if key.values.contains(&value) {
+                    return Err(ExecutorError::unique_violation(
+                        key.names.join(", "),
+                        value.iter().join(", "),
+                    ));
+                }
+                key.values.insert(value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that keys of rows to write exist in the table they reference.
+///
+/// Keys containing NULL do not reference any row.
+struct ForeignKeyChecker<'a> {
+    foreign_key: &'a ForeignKey,
+    index: Option<IndexId>,
+    /// Positions of the key columns in the table.
+    positions: Vec<usize>,
+    /// Names of the key columns for error messages.
+    names: Vec<String>,
+    /// Positions of the referenced columns in the table, if the table references itself.
+    self_positions: Option<Vec<usize>>,
+    /// Keys of the rows to write.
+    keys: BTreeSet<Row>,
+    /// Keys that the rows to write have on the referenced columns, if the table references
+    /// itself.
+    written: HashSet<Row>,
+}
+
+impl<'a> ForeignKeyChecker<'a> {
+    fn new(
+        table_id: TableRefId,
+        columns: &[ColumnCatalog],
+        foreign_key: &'a ForeignKey,
+        index: Option<IndexId>,
+    ) -> Result<Self> {
+        let positions = key_positions(columns, &foreign_key.column_ids)?;
+        Ok(ForeignKeyChecker {
+            foreign_key,
+            index,
+            names: (positions.iter())
+                .map(|i| columns[*i].name().to_string())
+                .collect(),
+            positions,
+            self_positions: (foreign_key.ref_table_id == table_id)
+                .then(|| key_positions(columns, &foreign_key.ref_column_ids))
+                .transpose()?,
+            keys: BTreeSet::new(),
+            written: HashSet::new(),
+        })
+    }
+
+    fn add(&mut self, chunk: &DataChunk) {
+        for row in chunk.rows() {
+            let key = row.get_by_indexes(&self.positions);
+            if !key.iter().any(|v| v.is_null()) {
+                self.keys.insert(key);
+            }
+            if let Some(positions) = &self.self_positions {
+                self.written.insert(row.get_by_indexes(positions));
+            }
+        }
+    }
+
+    /// Looks up the keys in the referenced table. `txn` and `excluded` are the transaction and
+    /// the rows it removes, if the table references itself.
+    async fn check<S: Storage>(
+        self,
+        storage: &S,
+        txn: &S::Transaction,
+        excluded: &HashSet<DataValue>,
+    ) -> Result<()> {
+        // rows can reference the rows written with them
+        let keys: BTreeSet<Row> = (self.keys.into_iter())
+            .filter(|key| !self.written.contains(key))
+            .collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let ref_table = storage.get_table(self.foreign_key.ref_table_id)?;
+        let ref_positions = key_positions(&ref_table.columns()?, &self.foreign_key.ref_column_ids)?;
+        let found = if self.self_positions.is_some() {
+            find_keys(
+                storage,
+                &ref_table,
+                txn,
+                self.index,
+                &ref_positions,
+                &keys,
+                excluded,
+            )
+            .await?
+        } else {
+            let ref_txn = ref_table.read().await?;
+            let excluded = &HashSet::new();
+            (find_keys(
+                storage,
+                &ref_table,
+                &ref_txn,
+                self.index,
+                &ref_positions,
+                &keys,
+                excluded,
+            ))
+            .await?
+        };
+        if let Some(key) = keys.iter().find(|key| !found.contains(*key)) {
+            return Err(ExecutorError::foreign_key_violation(
+                self.names.join(", "),
+                key.iter().join(", "),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Fails if any row removed from the table is referenced by a foreign key (`RESTRICT`).
+///
+/// `removed` are the row handlers of the removed rows in the transaction. Referenced columns are
+/// unique, so a key is still referenced only if no `written` row has the same key.
+pub async fn check_references<S: Storage>(
+    storage: &S,
+    table: &S::Table,
+    txn: &S::Transaction,
+    referencing: &[Referencing],
+    removed: &HashSet<DataValue>,
+    written: &[DataChunk],
+) -> Result<()> {
+    if removed.is_empty() || referencing.is_empty() {
+        return Ok(());
+    }
+    let columns = table.columns()?;
+    let rows: Arc<[i64]> = (removed.iter())
+        .map(|v| match v {
+            DataValue::Int64(handler) => *handler,
+            _ => panic!("invalid row handler"),
+        })
+        .sorted()
+        .collect();
+
+    for referencing in referencing {
+        let foreign_key = &referencing.foreign_key;
+        let positions = key_positions(&columns, &foreign_key.ref_column_ids)?;
+        let column_refs = (positions.iter())
+            .map(|i| StorageColumnRef::Idx(*i as u32))
+            .collect_vec();
+        let mut removed_keys = BTreeSet::new();
+        let options = ScanOptions::default().with_rows(rows.clone());
+        let mut iter = txn.scan(&column_refs, options).await?;
+        while let Some(chunk) = iter.next_batch(None).await? {
+            for row in chunk.rows() {
+                let key = row.to_owned();
+                if !key.iter().any(|v| v.is_null()) {
+                    removed_keys.insert(key);
+                }
+            }
+        }
+        for chunk in written {
+            for row in chunk.rows() {
+                removed_keys.remove(&row.get_by_indexes(&positions));
+            }
+        }
+        if removed_keys.is_empty() {
+            continue;
+        }
+
+        let index = referencing.index;
+        let found = if referencing.table_id == table.table_id() {
+            // rows can be removed with the rows referencing them
+            let key = key_positions(&columns, &foreign_key.column_ids)?;
+            find_keys(storage, table, txn, index, &key, &removed_keys, removed).await?
+        } else {
+            let table = storage.get_table(referencing.table_id)?;
+            let key = key_positions(&table.columns()?, &foreign_key.column_ids)?;
+            let txn = table.read().await?;
+            find_keys(
+                storage,
+                &table,
+                &txn,
+                index,
+                &key,
+                &removed_keys,
+                &HashSet::new(),
+            )
+            .await?
+        };
+        if let Some(value) = removed_keys.iter().find(|key| found.contains(*key)) {
+            let names = (positions.iter()).map(|i| columns[*i].name()).join(", ");
+            return Err(ExecutorError::still_referenced(
+                names,
+                value.iter().join(", "),
+                referencing.table_name.clone(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the positions of key columns in `columns`.
+pub(super) fn key_positions(columns: &[ColumnCatalog], key: &[ColumnId]) -> Result<Vec<usize>> {
+    (key.iter())
+        .map(|id| {
+            (columns.iter())
+                .position(|c| c.id() == *id)
+                .ok_or_else(|| TracedStorageError::not_found("column", id).into())
+        })
+        .try_collect()
+}
+
+/// Returns the values of each key in the rows of the transaction, except the `excluded` ones.
+///
+/// A key is given by the positions of its columns. Only the key columns are scanned. Values
+/// containing NULL are skipped.
+async fn scan_keys(
+    txn: &impl Transaction,
+    keys: &[Vec<usize>],
+    excluded: &HashSet<DataValue>,
+) -> Result<Vec<HashSet<Row>>> {
+    let mut values = vec![HashSet::new(); keys.len()];
+    if keys.is_empty() {
+        return Ok(values);
+    }
+    let scanned = keys.iter().flatten().copied().unique().collect_vec();
+    // the row handler comes first
+    let column_refs = std::iter::once(StorageColumnRef::RowHandler)
+        .chain(scanned.iter().map(|i| StorageColumnRef::Idx(*i as u32)))
+        .collect_vec();
+    let keys = (keys.iter())
+        .map(|key| {
+            (key.iter())
+                .map(|i| scanned.iter().position(|j| i == j).unwrap() + 1)
+                .collect_vec()
+        })
+        .collect_vec();
+    let mut iter = txn.scan(&column_refs, ScanOptions::default()).await?;
+    while let Some(chunk) = iter.next_batch(None).await? {
+        for row in chunk.rows() {
+            if excluded.contains(&row.get(0)) {
+                continue;
+            }
+            for (positions, values) in keys.iter().zip(&mut values) {
+                let value = row.get_by_indexes(positions);
+                if !value.iter().any(|v| v.is_null()) {
+                    values.insert(value);
+                }
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Returns the `keys` that rows of a table have, except the `excluded` rows.
+///
+/// `positions` are the positions of the key columns in the table. Rows are found by looking up the
+/// leading key column in the index if there is one, otherwise by scanning the key columns in the
+/// transaction until all keys are found.
+async fn find_keys<S: Storage>(
+    storage: &S,
+    table: &S::Table,
+    txn: &S::Transaction,
+    index: Option<IndexId>,
+    positions: &[usize],
+    keys: &BTreeSet<Row>,
+    excluded: &HashSet<DataValue>,
+) -> Result<HashSet<Row>> {
+    // the row handler comes first
+    let column_refs = std::iter::once(StorageColumnRef::RowHandler)
+        .chain(positions.iter().map(|i| StorageColumnRef::Idx(*i as u32)))
+        .collect_vec();
+    let matched = |chunk: &DataChunk| {
+        (chunk.rows())
+            .filter(|row| !excluded.contains(&row.get(0)))
+            .map(|row| row.values().skip(1).collect_vec())
+            .filter(|key| keys.contains(key))
+            .collect_vec()
+    };
+    let mut found = HashSet::new();
+    let Some(index_id) = index else {
+        let mut iter = txn.scan(&column_refs, ScanOptions::default()).await?;
+        while found.len() < keys.len() {
+            let Some(chunk) = iter.next_batch(None).await? else {
+                break;
+            };
+            found.extend(matched(&chunk));
+        }
+        return Ok(found);
+    };
+    let index = storage.get_index(table.table_id().schema_id, index_id)?;
+    for leading in keys.iter().map(|key| &key[0]).dedup() {
+        let range = KeyRange {
+            start: Bound::Included(leading.clone()),
+            end: Bound::Included(leading.clone()),
+        };
+        let (txn, handlers) = index.lookup(table, &range).await?;
+        let options = ScanOptions::default().with_rows(handlers.into());
+        let mut iter = txn.scan(&column_refs, options).await?;
+        while let Some(chunk) = iter.next_batch(None).await? {
+            found.extend(matched(&chunk));
+        }
+    }
+    Ok(found)
+}
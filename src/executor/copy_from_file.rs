@@ -1,30 +1,96 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::sync::Mutex;
 
 use indicatif::{ProgressBar, ProgressStyle};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use tokio::sync::mpsc::Sender;
 
 use super::*;
-use crate::array::{ArrayImpl, DataChunkBuilder};
+use crate::array::{ArrayBuilderImpl, ArrayImpl, DataChunkBuilder};
 use crate::binder::copy::{ExtSource, FileFormat};
-use crate::types::DataType;
+use crate::types::{DataType, DataValue};
 
 /// The executor of loading file data.
 pub struct CopyFromFileExecutor {
     pub source: ExtSource,
     pub types: Vec<DataType>,
+    /// Skips malformed rows of CSV files instead of aborting the load if set.
+    pub tolerance: Option<ErrorTolerance>,
+}
+
+/// The malformed rows skipped by a CSV load.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorTolerance {
+    /// The maximum number of rows to skip. The load is aborted on more malformed rows.
+    max_errors: usize,
+    /// The line numbers and errors of skipped rows.
+    rejected: Arc<Mutex<Vec<(u64, String)>>>,
+}
+
+impl ErrorTolerance {
+    pub fn new(max_errors: usize) -> Self {
+        Self {
+            max_errors,
+            rejected: Default::default(),
+        }
+    }
+
+    /// Skips a malformed row, or returns the error if there are too many.
+    fn reject(&self, line: u64, error: Error) -> Result<()> {
+        let mut rejected = self.rejected.lock().unwrap();
+        if rejected.len() >= self.max_errors {
+            return Err(error);
+        }
+        rejected.push((line, error.to_string()));
+        Ok(())
+    }
+
+    /// Returns a summary of the load inserted by `insert`: the number of loaded rows, the number
+    /// of rejected rows, and the errors of rejected rows.
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn summarize(self, insert: BoxedExecutor) {
+        let mut loaded = 0;
+        #[for_await]
+        for chunk in insert {
+            let chunk = chunk?;
+            if let DataValue::Int32(n) = chunk.array_at(0).get(0) {
+                loaded += n;
+            }
+        }
+        let rejected = std::mem::take(&mut *self.rejected.lock().unwrap());
+        let errors = (!rejected.is_empty()).then(|| {
+            (rejected.iter())
+                .map(|(line, error)| format!("line {line}: {error}"))
+                .join("; ")
+        });
+        yield [
+            ArrayImpl::new_int32([loaded].into_iter().collect()),
+            ArrayImpl::new_int32([rejected.len() as i32].into_iter().collect()),
+            ArrayImpl::new_string([errors].into_iter().collect()),
+        ]
+        .into_iter()
+        .collect();
+    }
 }
 
 /// When the source file size is above the limit, we show a progress bar on the screen.
 const IMPORT_PROGRESS_BAR_LIMIT: u64 = 1024 * 1024;
 
+/// The number of rows in each data chunk read from Parquet files.
+const PARQUET_BATCH_SIZE: usize = 64 * 1024;
+
+/// The minimum size of byte ranges that CSV files are split into for parallel parsing.
+const CSV_RANGE_SIZE: u64 = 32 * 1024 * 1024;
+
 impl CopyFromFileExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self) {
         let types = self.types.clone();
-        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        // buffer a chunk for each parsing thread, so that parsing is pipelined with insertion
+        let (tx, mut rx) = tokio::sync::mpsc::channel(parallelism());
         // # Cancellation
         // When this stream is dropped, the `rx` is dropped, the spawned task will fail to send to
         // `tx`, then the task will finish.
@@ -45,60 +111,134 @@ impl CopyFromFileExecutor {
 
     /// Read records from file using blocking IO.
     ///
-    /// The read data chunks will be sent through `tx`.
+    /// The read data chunks will be sent through `tx`. Large CSV files are split into byte
+    /// ranges parsed by multiple threads, so rows of different ranges may be interleaved.
     fn read_file_blocking(self, tx: Sender<DataChunk>) -> Result<()> {
-        let file = File::open(self.source.path)?;
+        let mut file = File::open(&self.source.path)?;
         let file_size = file.metadata()?.len();
-        let mut buf_reader = BufReader::new(file);
-        let mut reader = match self.source.format {
+        let (header, quote, escape) = match self.source.format {
             FileFormat::Csv {
-                delimiter,
+                header,
                 quote,
                 escape,
-                header,
-            } => csv::ReaderBuilder::new()
-                .delimiter(delimiter as u8)
-                .quote(quote as u8)
-                .escape(escape.map(|c| c as u8))
-                .has_headers(header)
-                .from_reader(&mut buf_reader),
+                ..
+            } => (header, quote as u8, escape.map(|c| c as u8)),
+            FileFormat::Parquet => return self.read_parquet_blocking(file, file_size, tx),
+            FileFormat::Json | FileFormat::Ndjson => panic!("reading JSON files is not supported"),
         };
 
-        let bar = if file_size < IMPORT_PROGRESS_BAR_LIMIT {
-            // disable progress bar if file size is < 1MB
-            ProgressBar::hidden()
+        let bar = progress_bar(file_size, file_size, "{bytes}/{total_bytes}");
+        // files are not split when skipping malformed rows, so that line numbers can be reported
+        let num_ranges = match self.tolerance {
+            Some(_) => 1,
+            None => (file_size / CSV_RANGE_SIZE).clamp(1, parallelism() as u64),
+        };
+        let ranges = split_records(&file, file_size, num_ranges, quote, escape)?;
+        if ranges.len() <= 1 {
+            file.rewind()?;
+            self.read_csv_blocking(file, header, &tx, &bar)?;
         } else {
-            let bar = ProgressBar::new(file_size);
-            bar.set_style(
-                ProgressStyle::default_bar()
-                    .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes}")
-                    .unwrap()
-                    .progress_chars("=>-"),
-            );
-            bar
+            let (this, tx, bar) = (&self, &tx, &bar);
+            std::thread::scope(|s| {
+                let handles = (ranges.into_iter())
+                    .map(|(start, end)| {
+                        s.spawn(move || -> Result<()> {
+                            let mut file = File::open(&this.source.path)?;
+                            file.seek(SeekFrom::Start(start))?;
+                            let header = header && start == 0;
+                            this.read_csv_blocking(file.take(end - start), header, tx, bar)
+                        })
+                    })
+                    .collect_vec();
+                (handles.into_iter())
+                    .map(|handle| handle.join().unwrap())
+                    .collect::<Result<()>>()
+            })?;
+        }
+        bar.finish();
+        Ok(())
+    }
+
+    /// Read records from CSV data using blocking IO, and send data chunks through `tx`.
+    fn read_csv_blocking(
+        &self,
+        data: impl Read,
+        header: bool,
+        tx: &Sender<DataChunk>,
+        bar: &ProgressBar,
+    ) -> Result<()> {
+        let FileFormat::Csv {
+            delimiter,
+            quote,
+            escape,
+            ref null,
+            ..
+        } = self.source.format
+        else {
+            panic!("not a CSV file");
         };
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter as u8)
+            .quote(quote as u8)
+            .escape(escape.map(|c| c as u8))
+            .has_headers(header)
+            .from_reader(data);
 
         let column_count = self.types.len();
 
         // create chunk builder
         let mut chunk_builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
         let mut size_count = 0;
+        // builders to check whether rows can be converted before pushing them
+        let mut row_builders = (self.types.iter())
+            .map(|ty| ArrayBuilderImpl::with_capacity(1, ty))
+            .collect_vec();
 
         for record in reader.records() {
             // read records and push raw str rows into data chunk builder
-            let record = record?;
+            let record = match (record, &self.tolerance) {
+                (Ok(record), _) => record,
+                (Err(e), Some(tolerance)) if !e.is_io_error() => {
+                    let line = e.position().map_or(0, |p| p.line());
+                    tolerance.reject(line, e.into())?;
+                    continue;
+                }
+                (Err(e), _) => return Err(e.into()),
+            };
+            let line = record.position().map_or(0, |p| p.line());
 
             if !(record.len() == column_count
                 || record.len() == column_count + 1 && record.get(column_count) == Some(""))
             {
-                return Err(Error::length_mismatch(column_count, record.len()));
+                let error = Error::length_mismatch(column_count, record.len());
+                match &self.tolerance {
+                    Some(tolerance) => {
+                        tolerance.reject(line, error)?;
+                        continue;
+                    }
+                    None => return Err(error),
+                }
             }
 
             size_count += record.as_slice().as_bytes().len();
 
+            // fields equal to the null string are pushed as empty strings, which are null
+            let fields = record.iter().map(|s| if s == null { "" } else { s });
+            if let Some(tolerance) = &self.tolerance {
+                let res = (row_builders.iter_mut().zip(fields.clone()))
+                    .try_for_each(|(builder, s)| builder.push_str(s));
+                for builder in &mut row_builders {
+                    builder.take();
+                }
+                if let Err(e) = res {
+                    tolerance.reject(line, e.into())?;
+                    continue;
+                }
+            }
+
             // push a raw str row and send it if necessary
-            if let Some(chunk) = chunk_builder.push_str_row(record.iter())? {
-                bar.set_position(size_count as u64);
+            if let Some(chunk) = chunk_builder.push_str_row(fields)? {
+                bar.inc(std::mem::take(&mut size_count) as u64);
                 tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
             }
         }
@@ -106,11 +246,129 @@ impl CopyFromFileExecutor {
         if let Some(chunk) = chunk_builder.take() {
             tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
         }
+        bar.inc(size_count as u64);
+        Ok(())
+    }
+
+    /// Read record batches from a Parquet file using blocking IO.
+    ///
+    /// Columns of the file are matched with the table by position, and casted to the types of
+    /// the table if necessary.
+    fn read_parquet_blocking(
+        self,
+        file: File,
+        file_size: u64,
+        tx: Sender<DataChunk>,
+    ) -> Result<()> {
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(file)?.with_batch_size(PARQUET_BATCH_SIZE);
+        let fields = builder.schema().fields();
+        if fields.len() != self.types.len() {
+            return Err(Error::length_mismatch(self.types.len(), fields.len()));
+        }
+        // whether each column should be casted to the type of table
+        let need_cast = (fields.iter().zip(&self.types))
+            .map(
+                |(field, ty)| match DataType::from_arrow(field.data_type())? {
+                    DataType::Decimal(_, _) => Ok(!matches!(ty, DataType::Decimal(_, _))),
+                    source => Ok(source != *ty),
+                },
+            )
+            .collect::<Result<Vec<bool>>>()?;
+
+        let num_rows = builder.metadata().file_metadata().num_rows() as u64;
+        let bar = progress_bar(file_size, num_rows, "{pos}/{len} rows");
+
+        for batch in builder.build()? {
+            let batch = batch?;
+            if batch.num_rows() == 0 {
+                continue;
+            }
+            let chunk = (batch.columns().iter().zip(&self.types).zip(&need_cast))
+                .map(|((array, ty), &need_cast)| {
+                    let array = ArrayImpl::from_arrow(array.as_ref())?;
+                    Ok(if need_cast { array.cast(ty)? } else { array })
+                })
+                .collect::<Result<DataChunk>>()?;
+            bar.inc(batch.num_rows() as u64);
+            tx.blocking_send(chunk).map_err(|_| Error::aborted())?;
+        }
         bar.finish();
         Ok(())
     }
 }
 
+/// Splits a CSV file into at most `num_ranges` byte ranges at record boundaries.
+///
+/// Quoted fields may contain line breaks, so the file is scanned from the start to find the line
+/// breaks out of quotes.
+fn split_records(
+    file: &File,
+    file_size: u64,
+    num_ranges: u64,
+    quote: u8,
+    escape: Option<u8>,
+) -> Result<Vec<(u64, u64)>> {
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(0))?;
+    let mut bounds = vec![0];
+    // the next range to find the start of
+    let mut next = 1;
+    let (mut pos, mut in_quotes, mut escaped) = (0, false, false);
+    while next < num_ranges {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let len = buf.len();
+        for &b in buf {
+            pos += 1;
+            if escaped {
+                escaped = false;
+            } else if in_quotes && Some(b) == escape && b != quote {
+                escaped = true;
+            } else if b == quote {
+                // a doubled quote in a quoted field toggles twice
+                in_quotes = !in_quotes;
+            } else if b == b'\n' && !in_quotes {
+                // a range starts after the first line break at or after its offset
+                while next < num_ranges && pos >= file_size * next / num_ranges {
+                    bounds.push(pos);
+                    next += 1;
+                }
+            }
+        }
+        reader.consume(len);
+    }
+    bounds.push(file_size);
+    Ok((bounds.into_iter().tuple_windows())
+        .filter(|(start, end)| start < end)
+        .collect())
+}
+
+/// Returns the number of threads to parse a file.
+fn parallelism() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Returns a progress bar of `len` if the file is large enough.
+fn progress_bar(file_size: u64, len: u64, progress: &str) -> ProgressBar {
+    if file_size < IMPORT_PROGRESS_BAR_LIMIT {
+        // disable progress bar if file size is < 1MB
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(len);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template(&format!(
+                "[{{elapsed_precise}}] {{bar:40.cyan/blue}} {progress}"
+            ))
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    bar
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Write;
@@ -134,9 +392,11 @@ mod tests {
                     quote: '"',
                     escape: None,
                     header: false,
+                    null: String::new(),
                 },
             },
             types: vec![DataType::Int32, DataType::Float64, DataType::String],
+            tolerance: None,
         };
         let actual = executor.execute().next().await.unwrap().unwrap();
 
@@ -149,4 +409,111 @@ mod tests {
         .collect();
         assert_eq!(actual, expected);
     }
+
+    #[tokio::test]
+    async fn read_csv_skip_malformed_rows() {
+        let csv = "1,one\nx,two\n3,null\n4\n";
+
+        let mut file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+        write!(file, "{}", csv).expect("failed to write file");
+
+        let tolerance = ErrorTolerance::new(2);
+        let executor = CopyFromFileExecutor {
+            source: ExtSource {
+                path: file.path().into(),
+                format: FileFormat::Csv {
+                    delimiter: ',',
+                    quote: '"',
+                    escape: None,
+                    header: false,
+                    null: "null".into(),
+                },
+            },
+            types: vec![DataType::Int32, DataType::String],
+            tolerance: Some(tolerance.clone()),
+        };
+        let actual = executor.execute().next().await.unwrap().unwrap();
+
+        let expected: DataChunk = [
+            ArrayImpl::new_int32([1, 3].into_iter().collect()),
+            ArrayImpl::new_string([Some("one"), None].into_iter().collect()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(actual, expected);
+        let lines = (tolerance.rejected.lock().unwrap().iter())
+            .map(|(line, _)| *line)
+            .collect_vec();
+        assert_eq!(lines, vec![2, 4]);
+    }
+
+    #[test]
+    fn split_records_of_file() {
+        let mut file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+        write!(file, "1,one\n22,two\n333,three\n").expect("failed to write file");
+        let file = file.reopen().unwrap();
+        let split = |num_ranges| split_records(&file, 23, num_ranges, b'"', None).unwrap();
+
+        assert_eq!(split(1), vec![(0, 23)]);
+        assert_eq!(split(2), vec![(0, 13), (13, 23)]);
+        // ranges starting in the same line are merged
+        assert_eq!(split(3), vec![(0, 13), (13, 23)]);
+        assert_eq!(split(23), vec![(0, 6), (6, 13), (13, 23)]);
+    }
+
+    #[test]
+    fn split_records_with_quoted_line_breaks() {
+        let mut file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+        write!(file, "1,\"a\nb\"\n2,\"c\"\"\nd\"\n3,\"e\\\"\nf\"\n").expect("failed to write file");
+        let file = file.reopen().unwrap();
+        let size = file.metadata().unwrap().len();
+
+        // quotes are escaped by doubling or by the escape character
+        let ranges = split_records(&file, size, size, b'"', Some(b'\\')).unwrap();
+        assert_eq!(ranges, vec![(0, 8), (8, 18), (18, size)]);
+    }
+
+    #[tokio::test]
+    async fn read_parquet() {
+        use arrow::array::{ArrayRef, Float32Array, Int64Array, StringArray};
+        use arrow::record_batch::RecordBatch;
+        use parquet::arrow::ArrowWriter;
+
+        let batch = RecordBatch::try_from_iter([
+            ("a", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+            (
+                "b",
+                Arc::new(Float32Array::from(vec![Some(1.5), None])) as ArrayRef,
+            ),
+            (
+                "c",
+                Arc::new(StringArray::from(vec!["one", "two"])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+        let mut writer =
+            ArrowWriter::try_new(file.reopen().unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let executor = CopyFromFileExecutor {
+            source: ExtSource {
+                path: file.path().into(),
+                format: FileFormat::Parquet,
+            },
+            types: vec![DataType::Int32, DataType::Float64, DataType::String],
+            tolerance: None,
+        };
+        let actual = executor.execute().next().await.unwrap().unwrap();
+
+        let expected: DataChunk = [
+            ArrayImpl::new_int32([1, 2].into_iter().collect()),
+            ArrayImpl::new_float64([Some(1.5.into()), None].into_iter().collect()),
+            ArrayImpl::new_string(["one", "two"].iter().map(Some).collect()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(actual, expected);
+    }
 }
@@ -1,16 +1,27 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::{BufWriter, Write};
 
+use arrow::datatypes::{Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
 use tokio::sync::mpsc;
 
 use super::*;
+use crate::array::ArrayImpl;
 use crate::binder::copy::{ExtSource, FileFormat};
+use crate::types::{DataType, DataValue};
 
 /// The executor of saving data to file.
 pub struct CopyToFileExecutor {
     pub source: ExtSource,
+    /// Names of the output columns.
+    pub names: Vec<String>,
+    /// Types of the output columns.
+    pub types: Vec<DataType>,
 }
 
 impl CopyToFileExecutor {
@@ -20,9 +31,7 @@ impl CopyToFileExecutor {
         // # Cancellation
         // When this stream is dropped, the `sender` is dropped, the `recver` will return
         // `None` in the spawned task, then the task will finish.
-        let writer = tokio::task::spawn_blocking(move || {
-            Self::write_file_blocking(self.source.path, self.source.format, recver)
-        });
+        let writer = tokio::task::spawn_blocking(move || self.write_file_blocking(recver));
         #[for_await]
         for batch in child {
             let res = sender.send(batch?).await;
@@ -36,24 +45,27 @@ impl CopyToFileExecutor {
         yield DataChunk::single(rows as _);
     }
 
-    fn write_file_blocking(
-        path: PathBuf,
-        format: FileFormat,
-        mut recver: mpsc::Receiver<DataChunk>,
-    ) -> Result<usize> {
-        let file = File::create(path)?;
-        let mut writer = match format {
+    fn write_file_blocking(self, mut recver: mpsc::Receiver<DataChunk>) -> Result<usize> {
+        let file = File::create(&self.source.path)?;
+        let (mut writer, null) = match self.source.format {
             FileFormat::Csv {
                 delimiter,
                 quote,
                 escape,
                 header,
-            } => csv::WriterBuilder::new()
-                .delimiter(delimiter as u8)
-                .quote(quote as u8)
-                .escape(escape.unwrap_or(quote) as u8)
-                .has_headers(header)
-                .from_writer(file),
+                null,
+            } => {
+                let writer = csv::WriterBuilder::new()
+                    .delimiter(delimiter as u8)
+                    .quote(quote as u8)
+                    .escape(escape.unwrap_or(quote) as u8)
+                    .has_headers(header)
+                    .from_writer(file);
+                (writer, null)
+            }
+            FileFormat::Parquet => return self.write_parquet_blocking(file, recver),
+            FileFormat::Json => return self.write_json_blocking(file, recver, false),
+            FileFormat::Ndjson => return self.write_json_blocking(file, recver, true),
         };
 
         let mut rows = 0;
@@ -61,7 +73,11 @@ impl CopyToFileExecutor {
         while let Some(chunk) = recver.blocking_recv() {
             for i in 0..chunk.cardinality() {
                 // TODO(wrj): avoid dynamic memory allocation (String)
-                let row = chunk.arrays().iter().map(|a| a.get_to_string(i));
+                let row = chunk.arrays().iter().map(|a| match a.get(i) {
+                    DataValue::Null => null.clone(),
+                    DataValue::String(s) => s.into(),
+                    v => v.to_string(),
+                });
                 writer.write_record(row)?;
             }
             writer.flush()?;
@@ -72,12 +88,123 @@ impl CopyToFileExecutor {
 
         Ok(rows)
     }
+
+    /// Write data chunks into a Parquet file. Each data chunk becomes a record batch.
+    fn write_parquet_blocking(
+        self,
+        file: File,
+        mut recver: mpsc::Receiver<DataChunk>,
+    ) -> Result<usize> {
+        // the parquet writer doesn't support intervals of months, days and nanoseconds,
+        // so intervals are written as strings.
+        let types = (self.types.iter())
+            .map(|ty| match ty {
+                DataType::Interval => DataType::String,
+                ty => ty.clone(),
+            })
+            .collect_vec();
+        let fields = (self.names.iter().zip(&types))
+            .map(|(name, ty)| Ok(Field::new(name, ty.to_arrow()?, true)))
+            .collect::<Result<Vec<_>>>()?;
+        let schema = Arc::new(Schema::new(fields));
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+
+        let mut rows = 0;
+        while let Some(chunk) = recver.blocking_recv() {
+            let columns = (chunk.arrays().iter().zip(&types))
+                .map(|(array, ty)| match array {
+                    ArrayImpl::Interval(_) => Ok(array.cast(ty)?.to_arrow(ty)?),
+                    _ => Ok(array.to_arrow(ty)?),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+            rows += chunk.cardinality();
+        }
+        writer.close()?;
+        Ok(rows)
+    }
+
+    /// Write data chunks into a JSON file, as an array of objects or newline-delimited objects
+    /// with the output columns as keys.
+    fn write_json_blocking(
+        self,
+        file: File,
+        mut recver: mpsc::Receiver<DataChunk>,
+        newline_delimited: bool,
+    ) -> Result<usize> {
+        let mut writer = BufWriter::new(file);
+        // keys are escaped only once
+        let keys = (self.names.iter())
+            .map(serde_json::to_string)
+            .collect::<serde_json::Result<Vec<_>>>()?;
+
+        let mut rows = 0;
+        if !newline_delimited {
+            writer.write_all(b"[")?;
+        }
+        while let Some(chunk) = recver.blocking_recv() {
+            for i in 0..chunk.cardinality() {
+                if !newline_delimited {
+                    writer.write_all(if rows == 0 { b"\n" } else { b",\n" as &[u8] })?;
+                }
+                writer.write_all(b"{")?;
+                for (j, (key, array)) in keys.iter().zip(chunk.arrays()).enumerate() {
+                    if j != 0 {
+                        writer.write_all(b",")?;
+                    }
+                    write!(writer, "{key}:")?;
+                    write_json_value(&mut writer, &array.get(i))?;
+                }
+                writer.write_all(b"}")?;
+                if newline_delimited {
+                    writer.write_all(b"\n")?;
+                }
+                rows += 1;
+            }
+        }
+        if !newline_delimited {
+            writer.write_all(if rows == 0 { b"]\n" } else { b"\n]\n" as &[u8] })?;
+        }
+        writer.flush()?;
+        Ok(rows)
+    }
+}
+
+/// Writes a value in JSON. Numbers, booleans and JSON values are written as they are, lists as
+/// arrays, and other values, including infinite and NaN floats, as strings.
+fn write_json_value(writer: &mut impl Write, value: &DataValue) -> Result<()> {
+    match value {
+        DataValue::Null => writer.write_all(b"null")?,
+        DataValue::Bool(v) => write!(writer, "{v}")?,
+        DataValue::Int16(v) => write!(writer, "{v}")?,
+        DataValue::Int32(v) => write!(writer, "{v}")?,
+        DataValue::Int64(v) => write!(writer, "{v}")?,
+        DataValue::Decimal(v) => write!(writer, "{v}")?,
+        DataValue::Float64(v) if v.is_finite() => write!(writer, "{v}")?,
+        DataValue::String(s) => serde_json::to_writer(&mut *writer, s)?,
+        DataValue::Json(v) => write!(writer, "{v}")?,
+        DataValue::List(list) => {
+            writer.write_all(b"[")?;
+            for (i, v) in list.iter().enumerate() {
+                if i != 0 {
+                    writer.write_all(b",")?;
+                }
+                write_json_value(writer, v)?;
+            }
+            writer.write_all(b"]")?;
+        }
+        v => serde_json::to_writer(&mut *writer, &v.to_string())?,
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::array::ArrayImpl;
+    use crate::types::List;
 
     #[tokio::test]
     async fn write_csv() {
@@ -91,14 +218,17 @@ mod tests {
                     quote: '"',
                     escape: None,
                     header: false,
+                    null: String::new(),
                 },
             },
+            names: vec!["a".into(), "b".into(), "c".into()],
+            types: vec![DataType::Int32, DataType::Float64, DataType::String],
         };
         let child = async_stream::try_stream! {
             yield [
-                ArrayImpl::new_int32([1, 2].into_iter().collect()),
-                ArrayImpl::new_float64([1.5, 2.5].into_iter().collect()),
-                ArrayImpl::new_string(["one", "two"].iter().map(Some).collect()),
+                ArrayImpl::new_int32([1, 2, 3].into_iter().collect()),
+                ArrayImpl::new_float64([Some(1.5.into()), Some(2.5.into()), None].into_iter().collect()),
+                ArrayImpl::new_string([Some("one"), Some("two"), None].into_iter().collect()),
             ]
             .into_iter()
             .collect();
@@ -107,7 +237,98 @@ mod tests {
         executor.execute(child).next().await.unwrap().unwrap();
 
         let actual = std::fs::read_to_string(file.path()).unwrap();
-        let expected = "1,1.5,one\n2,2.5,two\n";
+        let expected = "1,1.5,one\n2,2.5,two\n3,,\n";
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn write_json() {
+        let file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+
+        for (format, expected) in [
+            (
+                FileFormat::Json,
+                "[\n{\"a\":1,\"b\":\"x\\\"y\",\"c\":[1.5,null]},\n{\"a\":null,\"b\":null,\"c\":null}\n]\n",
+            ),
+            (
+                FileFormat::Ndjson,
+                "{\"a\":1,\"b\":\"x\\\"y\",\"c\":[1.5,null]}\n{\"a\":null,\"b\":null,\"c\":null}\n",
+            ),
+        ] {
+            let executor = CopyToFileExecutor {
+                source: ExtSource {
+                    path: file.path().into(),
+                    format,
+                },
+                names: vec!["a".into(), "b".into(), "c".into()],
+                types: vec![
+                    DataType::Int32,
+                    DataType::String,
+                    DataType::List(Box::new(DataType::Float64)),
+                ],
+            };
+            let child = async_stream::try_stream! {
+                yield [
+                    ArrayImpl::new_int32([Some(1), None].into_iter().collect()),
+                    ArrayImpl::new_string([Some("x\"y"), None].into_iter().collect()),
+                    ArrayImpl::new_list(
+                        [
+                            Some(List::from(vec![
+                                DataValue::Float64(1.5.into()),
+                                DataValue::Null,
+                            ])),
+                            None,
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                ]
+                .into_iter()
+                .collect();
+            }
+            .boxed();
+            executor.execute(child).next().await.unwrap().unwrap();
+
+            let actual = std::fs::read_to_string(file.path()).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn write_parquet() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let file = tempfile::NamedTempFile::new().expect("failed to create temp file");
+
+        let executor = CopyToFileExecutor {
+            source: ExtSource {
+                path: file.path().into(),
+                format: FileFormat::Parquet,
+            },
+            names: vec!["a".into(), "b".into()],
+            types: vec![DataType::Int32, DataType::String],
+        };
+        let chunk: DataChunk = [
+            ArrayImpl::new_int32([Some(1), None].into_iter().collect()),
+            ArrayImpl::new_string([Some("one"), None].into_iter().collect()),
+        ]
+        .into_iter()
+        .collect();
+        let expected = chunk.clone();
+        let child = async_stream::try_stream! { yield chunk; }.boxed();
+        let rows = executor.execute(child).next().await.unwrap().unwrap();
+        assert_eq!(rows, DataChunk::single(2));
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file.reopen().unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches = reader.map(|b| b.unwrap()).collect_vec();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].schema().field(0).name(), "a");
+        let actual: DataChunk = (batches[0].columns().iter())
+            .map(|a| ArrayImpl::from_arrow(a.as_ref()).unwrap())
+            .collect();
         assert_eq!(actual, expected);
     }
 }
@@ -0,0 +1,33 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::binder::CreateIndex;
+use crate::storage::Storage;
+
+/// The executor of `create index` statement.
+pub struct CreateIndexExecutor<S: Storage> {
+    pub index: Box<CreateIndex>,
+    pub catalog: RootCatalogRef,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> CreateIndexExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        let exists = (self.catalog.get_schema_by_id(self.index.table_id.schema_id))
+            .and_then(|schema| schema.get_index_by_name(&self.index.index_name))
+            .is_some();
+        if !(exists && self.index.if_not_exists) {
+            self.storage
+                .create_index(
+                    &self.index.index_name,
+                    self.index.table_id,
+                    &self.index.column_ids,
+                )
+                .await?;
+        }
+        yield DataChunk::single(1);
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::binder::CreateSchema;
+use crate::catalog::RootCatalogRef;
+use crate::storage::Storage;
+
+/// The executor of `create schema` statement.
+pub struct CreateSchemaExecutor<S: Storage> {
+    pub schema: Box<CreateSchema>,
+    pub catalog: RootCatalogRef,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> CreateSchemaExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        let exists = (self.catalog)
+            .get_schema_by_name(&self.schema.schema_name)
+            .is_some();
+        if !(exists && self.schema.if_not_exists) {
+            self.storage.create_schema(&self.schema.schema_name).await?;
+        }
+        yield DataChunk::single(1);
+    }
+}
@@ -20,7 +20,7 @@ impl<S: Storage> CreateTableExecutor<S> {
                 self.table.schema_id,
                 &self.table.table_name,
                 &self.table.columns,
-                &self.table.ordered_pk_ids,
+                &self.table.constraints(),
             )
             .await?;
 
@@ -0,0 +1,57 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::binder::CreateTable;
+use crate::catalog::{RootCatalogRef, TableRefId};
+use crate::storage::{Storage, Table, Transaction};
+
+/// The executor of `create table .. as` statement.
+///
+/// Creates the table and inserts the output of the query into it. The table is dropped if the
+/// query fails.
+pub struct CreateTableAsExecutor<S: Storage> {
+    pub table: Box<CreateTable>,
+    pub catalog: RootCatalogRef,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> CreateTableAsExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, child: BoxedExecutor) {
+        self.storage
+            .create_table(
+                self.table.schema_id,
+                &self.table.table_name,
+                &self.table.columns,
+                &self.table.constraints(),
+            )
+            .await?;
+        let schema = self.catalog.get_schema_by_id(self.table.schema_id).unwrap();
+        let table_id = schema.get_table_id_by_name(&self.table.table_name).unwrap();
+        let table_id = TableRefId::new(self.table.schema_id, table_id);
+
+        match self.insert(table_id, child).await {
+            Ok(cnt) => yield DataChunk::single(cnt as i32),
+            Err(e) => {
+                self.storage.drop_table(table_id).await?;
+                return Err(e);
+            }
+        }
+    }
+
+    /// Inserts all rows from the child into the table in one transaction.
+    async fn insert(&self, table_id: TableRefId, mut child: BoxedExecutor) -> Result<usize> {
+        let table = self.storage.get_table(table_id)?;
+        let mut txn = table.write().await?;
+        let mut cnt = 0;
+        while let Some(chunk) = child.next().await {
+            let chunk = chunk?;
+            cnt += chunk.cardinality();
+            txn.append(chunk).await?;
+        }
+        txn.commit().await?;
+        Ok(cnt)
+    }
+}
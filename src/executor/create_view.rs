@@ -1,25 +1,34 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::sync::Arc;
+
 use super::*;
 use crate::binder::CreateTable;
-use crate::catalog::RootCatalogRef;
+use crate::catalog::TableRefId;
+use crate::storage::Storage;
 
 /// The executor of `create view` statement.
-pub struct CreateViewExecutor {
+pub struct CreateViewExecutor<S: Storage> {
     pub table: Box<CreateTable>,
+    pub dependencies: Vec<TableRefId>,
     pub query: RecExpr,
-    pub catalog: RootCatalogRef,
+    pub storage: Arc<S>,
 }
 
-impl CreateViewExecutor {
+impl<S: Storage> CreateViewExecutor<S> {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self) {
-        self.catalog.add_view(
-            self.table.schema_id,
-            self.table.table_name,
-            self.table.columns,
-            self.query,
-        )?;
+        let definition = self.table.view.as_ref().expect("no view definition");
+        self.storage
+            .create_view(
+                self.table.schema_id,
+                &self.table.table_name,
+                &self.table.columns,
+                definition,
+                &self.query,
+                &self.dependencies,
+            )
+            .await?;
 
         yield DataChunk::single(1);
     }
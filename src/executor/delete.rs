@@ -1,7 +1,9 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
+use super::constraint::{check_references, Referencing};
 use super::*;
 use crate::array::DataChunk;
 use crate::catalog::TableRefId;
@@ -10,8 +12,12 @@ use crate::storage::{RowHandler, Storage, Table, Transaction};
 /// The executor of `delete` statement.
 ///
 /// The last column of the input data chunk should be `_row_id_`.
+/// Returns the number of deleted rows, or the `returning` expressions on them.
 pub struct DeleteExecutor<S: Storage> {
     pub table_id: TableRefId,
+    /// Foreign keys referencing the table.
+    pub referencing: Vec<Referencing>,
+    pub returning: Option<RecExpr>,
     pub storage: Arc<S>,
 }
 
@@ -20,7 +26,9 @@ impl<S: Storage> DeleteExecutor<S> {
     pub async fn execute(self, child: BoxedExecutor) {
         let table = self.storage.get_table(self.table_id)?;
         let mut txn = table.update().await?;
+        let mut deleted = HashSet::new();
         let mut cnt = 0;
+        let mut outputs = vec![];
         #[for_await]
         for chunk in child {
             let chunk = chunk?;
@@ -31,11 +39,32 @@ impl<S: Storage> DeleteExecutor<S> {
                     row_handler_idx,
                 );
                 txn.delete(&row_handler).await?;
+                if !self.referencing.is_empty() {
+                    deleted.insert(row_handlers.get(row_handler_idx));
+                }
             }
             cnt += chunk.cardinality();
+            if let Some(returning) = &self.returning {
+                outputs.push(Evaluator::new(returning).eval_list(&chunk)?);
+            }
         }
+        check_references(
+            &*self.storage,
+            &table,
+            &txn,
+            &self.referencing,
+            &deleted,
+            &[],
+        )
+        .await?;
         txn.commit().await?;
 
-        yield DataChunk::single(cnt as i32);
+        if self.returning.is_some() {
+            for chunk in outputs {
+                yield chunk;
+            }
+        } else {
+            yield DataChunk::single(cnt as i32);
+        }
     }
 }
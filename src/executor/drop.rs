@@ -18,7 +18,7 @@ impl<S: Storage> DropExecutor<S> {
     pub async fn execute(self) {
         for table in self.tables {
             if self.catalog.get_table(&table).unwrap().is_view() {
-                self.catalog.drop_table(table);
+                self.storage.drop_view(table).await?;
             } else {
                 self.storage.drop_table(table).await?;
             }
@@ -0,0 +1,21 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::*;
+use crate::binder::DropFunction;
+use crate::catalog::RootCatalogRef;
+
+/// The executor of `drop function` statement.
+pub struct DropFunctionExecutor {
+    pub f: Box<DropFunction>,
+    pub catalog: RootCatalogRef,
+}
+
+impl DropFunctionExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        for (schema_name, name) in &self.f.functions {
+            self.catalog.drop_function(schema_name, name);
+        }
+        yield DataChunk::single(1);
+    }
+}
@@ -0,0 +1,23 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::binder::DropIndex;
+use crate::storage::Storage;
+
+/// The executor of `drop index` statement.
+pub struct DropIndexExecutor<S: Storage> {
+    pub index: Box<DropIndex>,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> DropIndexExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        for (schema_id, index_id) in &self.index.indexes {
+            self.storage.drop_index(*schema_id, *index_id).await?;
+        }
+        yield DataChunk::single(1);
+    }
+}
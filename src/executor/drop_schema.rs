@@ -0,0 +1,23 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::binder::DropSchema;
+use crate::storage::Storage;
+
+/// The executor of `drop schema` statement.
+pub struct DropSchemaExecutor<S: Storage> {
+    pub schema: Box<DropSchema>,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> DropSchemaExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        for schema_id in &self.schema.schema_ids {
+            self.storage.drop_schema(*schema_id).await?;
+        }
+        yield DataChunk::single(1);
+    }
+}
@@ -32,14 +32,46 @@ enum Inner {
     Io(#[from] std::io::Error),
     #[error("csv error: {0}")]
     Csv(#[from] csv::Error),
+    #[error("parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error("arrow error: {0}")]
+    Arrow(#[from] arrow::error::ArrowError),
+    #[error("serde error: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error("bincode error: {0}")]
+    Bincode(#[from] bincode::Error),
     #[error("tuple length mismatch: expected {expected} but got {actual}")]
     LengthMismatch { expected: usize, actual: usize },
     #[error("exceed char/varchar length limit: item length {length} > char/varchar width {width}")]
     ExceedLengthLimit { length: u64, width: u64 },
-    #[error("value can not be null")]
-    NotNullable,
+    #[error("null value in column {0:?} violates not-null constraint")]
+    NotNullable(String),
+    #[error("new row violates check constraint: {0}")]
+    CheckViolation(String),
+    #[error(
+        "duplicate key value violates unique constraint: ({columns})=({values}) already exists"
+    )]
+    UniqueViolation { columns: String, values: String },
+    #[error("insert or update violates foreign key constraint: ({columns})=({values}) is not present in the referenced table")]
+    ForeignKeyViolation { columns: String, values: String },
+    #[error("update or delete violates foreign key constraint: ({columns})=({values}) is still referenced from table {table:?}")]
+    StillReferenced {
+        columns: String,
+        values: String,
+        table: String,
+    },
+    #[error("ON CONFLICT DO UPDATE cannot affect a row a second time: ({columns})=({values}) is proposed more than once")]
+    ConflictTwice { columns: String, values: String },
+    #[error("recursive query exceeds the iteration limit of {0}")]
+    RecursionLimit(usize),
+    #[error("out of memory: {context:?} exceeds the memory limit of {limit} bytes")]
+    OutOfMemory { context: String, limit: usize },
     #[error("abort")]
     Aborted,
+    #[error("this correlated subquery is not supported")]
+    CorrelatedSubquery,
+    #[error("invalid index scan: {0}")]
+    InvalidIndexScan(&'static str),
 }
 
 impl From<Inner> for Error {
@@ -78,17 +110,77 @@ impl From<csv::Error> for Error {
     }
 }
 
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        Inner::from(e).into()
+    }
+}
+
+impl From<arrow::error::ArrowError> for Error {
+    fn from(e: arrow::error::ArrowError) -> Self {
+        Inner::from(e).into()
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Inner::from(e).into()
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Inner::from(e).into()
+    }
+}
+
 impl Error {
     pub fn length_mismatch(expected: usize, actual: usize) -> Self {
         Inner::LengthMismatch { expected, actual }.into()
     }
-    pub fn not_nullable() -> Self {
-        Inner::NotNullable.into()
+    pub fn not_nullable(column: &str) -> Self {
+        Inner::NotNullable(column.into()).into()
+    }
+    pub fn check_violation(check: &str) -> Self {
+        Inner::CheckViolation(check.into()).into()
     }
     pub fn exceed_length_limit(length: u64, width: u64) -> Self {
         Inner::ExceedLengthLimit { length, width }.into()
     }
+    pub fn unique_violation(columns: String, values: String) -> Self {
+        Inner::UniqueViolation { columns, values }.into()
+    }
+    pub fn foreign_key_violation(columns: String, values: String) -> Self {
+        Inner::ForeignKeyViolation { columns, values }.into()
+    }
+    pub fn still_referenced(columns: String, values: String, table: String) -> Self {
+        Inner::StillReferenced {
+            columns,
+            values,
+            table,
+        }
+        .into()
+    }
+    pub fn conflict_twice(columns: String, values: String) -> Self {
+        Inner::ConflictTwice { columns, values }.into()
+    }
     pub fn aborted() -> Self {
         Inner::Aborted.into()
     }
+    pub fn recursion_limit(limit: usize) -> Self {
+        Inner::RecursionLimit(limit).into()
+    }
+    pub fn out_of_memory(context: &str, limit: usize) -> Self {
+        Inner::OutOfMemory {
+            context: context.into(),
+            limit,
+        }
+        .into()
+    }
+    pub fn correlated_subquery() -> Self {
+        Inner::CorrelatedSubquery.into()
+    }
+    pub fn invalid_index_scan(reason: &'static str) -> Self {
+        Inner::InvalidIndexScan(reason).into()
+    }
 }
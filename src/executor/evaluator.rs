@@ -2,19 +2,50 @@
 
 //! Apply expressions on data chunks.
 
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
+use chrono::FixedOffset;
 use egg::{Id, Language};
+use itertools::Itertools;
 
 use crate::array::*;
 use crate::planner::{Expr, RecExpr};
-use crate::types::{ConvertError, DataValue};
+use crate::types::{ConvertError, DataValue, List};
 
 /// A wrapper over [`RecExpr`] to evaluate it on [`DataChunk`]s.
+///
+/// Common subexpressions share a node in a [`RecExpr`] built from an e-graph. They are evaluated
+/// only once on a chunk.
 pub struct Evaluator<'a> {
     expr: &'a RecExpr,
     id: Id,
+    /// Results of common subexpressions on the chunk being evaluated.
+    shared: Option<&'a SharedResults>,
+}
+
+/// Results of nodes used more than once in an expression, on a chunk.
+struct SharedResults {
+    /// Whether the node of each id is used more than once.
+    shared: Vec<bool>,
+    results: RefCell<HashMap<Id, ArrayImpl>>,
+}
+
+impl SharedResults {
+    fn new(expr: &RecExpr) -> Self {
+        let mut uses = vec![0u8; expr.as_ref().len()];
+        for node in expr.as_ref() {
+            for child in node.children() {
+                uses[usize::from(*child)] = uses[usize::from(*child)].saturating_add(1);
+            }
+        }
+        SharedResults {
+            shared: uses.into_iter().map(|n| n > 1).collect(),
+            results: RefCell::new(HashMap::new()),
+        }
+    }
 }
 
 impl fmt::Display for Evaluator<'_> {
@@ -30,10 +61,11 @@ impl<'a> Evaluator<'a> {
         Self {
             expr,
             id: Id::from(expr.as_ref().len() - 1),
+            shared: None,
         }
     }
 
-    fn node(&self) -> &Expr {
+    pub fn node(&self) -> &Expr {
         &self.expr[self.id]
     }
 
@@ -41,11 +73,29 @@ impl<'a> Evaluator<'a> {
         Self {
             expr: self.expr,
             id,
+            shared: self.shared,
+        }
+    }
+
+    /// Returns the evaluator of a node to be evaluated on another chunk.
+    fn next_on_other_chunk(&self, id: Id) -> Self {
+        Self {
+            expr: self.expr,
+            id,
+            shared: None,
         }
     }
 
     /// Evaluate a list of expressions.
     pub fn eval_list(&self, chunk: &DataChunk) -> Result<DataChunk, ConvertError> {
+        if self.shared.is_none() {
+            let shared = SharedResults::new(self.expr);
+            let this = Evaluator {
+                shared: Some(&shared),
+                ..*self
+            };
+            return this.eval_list(chunk);
+        }
         let list = self.node().as_list();
         if list.is_empty() {
             return Ok(DataChunk::no_column(chunk.cardinality()));
@@ -53,8 +103,96 @@ impl<'a> Evaluator<'a> {
         list.iter().map(|id| self.next(*id).eval(chunk)).collect()
     }
 
+    /// Evaluates the expression on a chunk that may have invisible rows.
+    ///
+    /// Simple expressions, e.g. comparisons of columns, are evaluated on all rows, so that the
+    /// chunk doesn't have to be compacted. If they fail, possibly on an invisible row, the chunk is
+    /// compacted and evaluated again. Other expressions, e.g. arithmetic which may overflow on
+    /// invisible rows, are evaluated on the compacted chunk. Returns the chunk evaluated on and the
+    /// result.
+    pub fn eval_visible(&self, chunk: DataChunk) -> Result<(DataChunk, ArrayImpl), ConvertError> {
+        let chunk = self.compact_unless_simple(chunk);
+        match self.eval(&chunk) {
+            Ok(array) => Ok((chunk, array)),
+            Err(_) if chunk.visibility().is_some() => {
+                let chunk = chunk.compact();
+                let array = self.eval(&chunk)?;
+                Ok((chunk, array))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Evaluates a list of expressions on a chunk that may have invisible rows.
+    ///
+    /// The result has the same visibility as the chunk. See [`eval_visible`](Self::eval_visible).
+    pub fn eval_list_visible(&self, chunk: DataChunk) -> Result<DataChunk, ConvertError> {
+        let chunk = self.compact_unless_simple(chunk);
+        let output = match self.eval_list(&chunk) {
+            Ok(output) => output,
+            Err(_) if chunk.visibility().is_some() => return self.eval_list(&chunk.compact()),
+            Err(e) => return Err(e),
+        };
+        Ok(match chunk.visibility() {
+            Some(vis) => output.with_visibility(vis),
+            None => output,
+        })
+    }
+
+    /// Compacts the chunk unless the expression is simple enough to be evaluated on invisible
+    /// rows, i.e. it can't panic on any value.
+    fn compact_unless_simple(&self, chunk: DataChunk) -> DataChunk {
+        use Expr::*;
+        let simple = self.expr.as_ref().iter().all(|node| {
+            matches!(
+                node,
+                ColumnIndex(_)
+                    | Constant(_)
+                    | List(_)
+                    | Eq(_)
+                    | NotEq(_)
+                    | Gt(_)
+                    | Lt(_)
+                    | GtEq(_)
+                    | LtEq(_)
+                    | And(_)
+                    | Or(_)
+                    | Xor(_)
+                    | Not(_)
+                    | IsNull(_)
+            )
+        });
+        if simple {
+            chunk
+        } else {
+            chunk.compact()
+        }
+    }
+
     /// Evaluate the given expression as an array.
     pub fn eval(&self, chunk: &DataChunk) -> Result<ArrayImpl, ConvertError> {
+        let Some(shared) = self.shared else {
+            let shared = SharedResults::new(self.expr);
+            let this = Evaluator {
+                shared: Some(&shared),
+                ..*self
+            };
+            return this.eval(chunk);
+        };
+        let id = usize::from(self.id);
+        if !shared.shared[id] || self.node().children().is_empty() {
+            return self.eval_node(chunk);
+        }
+        if let Some(array) = shared.results.borrow().get(&self.id) {
+            return Ok(array.clone());
+        }
+        let array = self.eval_node(chunk)?;
+        (shared.results.borrow_mut()).insert(self.id, array.clone());
+        Ok(array)
+    }
+
+    /// Evaluate the node as an array.
+    fn eval_node(&self, chunk: &DataChunk) -> Result<ArrayImpl, ConvertError> {
         use Expr::*;
         match self.node() {
             ColumnIndex(idx) => Ok(chunk.array_at(idx.0 as _).clone()),
@@ -68,19 +206,37 @@ impl<'a> Evaluator<'a> {
                 let array = self.next(*a).eval(chunk)?;
                 array.cast(self.next(*ty).node().as_type())
             }
+            CastTz([ty, a, tz]) => {
+                let array = self.next(*a).eval(chunk)?;
+                let Constant(DataValue::Int32(secs)) = self.next(*tz).node() else {
+                    panic!("time zone should be a constant");
+                };
+                let tz = FixedOffset::east_opt(*secs).expect("invalid time zone");
+                array.cast_in_time_zone(self.next(*ty).node().as_type(), tz)
+            }
             IsNull(a) => {
                 let array = self.next(*a).eval(chunk)?;
                 Ok(ArrayImpl::new_bool(
                     array.get_valid_bitmap().iter().map(|v| !v).collect(),
                 ))
             }
-            Like([a, b]) => match self.next(*b).node() {
-                Expr::Constant(DataValue::String(pattern)) => {
-                    let a = self.next(*a).eval(chunk)?;
-                    a.like(pattern)
+            Like([a, b]) | ILike([a, b]) => {
+                let case_insensitive = matches!(self.node(), ILike(_));
+                let a = self.next(*a).eval(chunk)?;
+                match self.next(*b).node() {
+                    // compile the pattern once for all rows
+                    Expr::Constant(DataValue::String(pattern)) => a.like(pattern, case_insensitive),
+                    _ => {
+                        let b = self.next(*b).eval(chunk)?;
+                        a.like_array(&b, case_insensitive)
+                    }
                 }
-                _ => panic!("like pattern must be a string constant"),
-            },
+            }
+            JsonGet([a, b]) | JsonGetText([a, b]) => {
+                let a = self.next(*a).eval(chunk)?;
+                let b = self.next(*b).eval(chunk)?;
+                a.json_get(&b, matches!(self.node(), JsonGetText(_)))
+            }
             Extract([field, a]) => {
                 let a = self.next(*a).eval(chunk)?;
                 let Expr::Field(field) = &self.expr[*field] else {
@@ -94,11 +250,61 @@ impl<'a> Evaluator<'a> {
                 let length = self.next(*length).eval(chunk)?;
                 str.substring(&start, &length)
             }
+            Lpad([str, length, fill]) | Rpad([str, length, fill]) => {
+                let str = self.next(*str).eval(chunk)?;
+                let length = self.next(*length).eval(chunk)?;
+                let fill = self.next(*fill).eval(chunk)?;
+                match self.node() {
+                    Lpad(_) => str.lpad(&length, &fill),
+                    _ => str.rpad(&length, &fill),
+                }
+            }
+            Btrim([a, b])
+            | Ltrim([a, b])
+            | Rtrim([a, b])
+            | Position([a, b])
+            | RegexpMatches([a, b])
+            | Round([a, b])
+            | Power([a, b])
+            | Greatest([a, b])
+            | Least([a, b]) => {
+                let a = self.next(*a).eval(chunk)?;
+                let b = self.next(*b).eval(chunk)?;
+                match self.node() {
+                    Btrim(_) => a.btrim(&b),
+                    Ltrim(_) => a.ltrim(&b),
+                    Rtrim(_) => a.rtrim(&b),
+                    Position(_) => a.position(&b),
+                    RegexpMatches(_) => a.regexp_matches(&b),
+                    Round(_) => a.round(&b),
+                    Power(_) => a.power(&b),
+                    Greatest(_) => a.greatest(&b),
+                    _ => a.least(&b),
+                }
+            }
+            Ceil(a) => self.next(*a).eval(chunk)?.ceil(),
+            Floor(a) => self.next(*a).eval(chunk)?.floor(),
+            Ln(a) => self.next(*a).eval(chunk)?.ln(),
+            Log(a) => self.next(*a).eval(chunk)?.log10(),
             If([cond, then, else_]) => {
                 let cond = self.next(*cond).eval(chunk)?;
-                let then = self.next(*then).eval(chunk)?;
-                let else_ = self.next(*else_).eval(chunk)?;
-                cond.select(&then, &else_)
+                let ArrayImpl::Bool(cond) = cond else {
+                    return Err(ConvertError::NoUnaryOp("case".into(), cond.type_string()));
+                };
+                // null conditions select the else branch
+                let selected = cond.iter().map(|s| s == Some(&true)).collect_vec();
+                // each branch is only evaluated on rows selecting it, so that errors in the
+                // other branch are not raised, e.g. `CASE WHEN b <> 0 THEN a / b END`
+                if !selected.contains(&false) {
+                    return self.next(*then).eval(chunk);
+                }
+                if !selected.contains(&true) {
+                    return self.next(*else_).eval(chunk);
+                }
+                let then = (self.next_on_other_chunk(*then)).eval(&chunk.filter(&selected))?;
+                let unselected = selected.iter().map(|s| !s).collect_vec();
+                let else_ = (self.next_on_other_chunk(*else_)).eval(&chunk.filter(&unselected))?;
+                Ok(ArrayImpl::merge(&selected, &then, &else_))
             }
             In([expr, list]) => {
                 let expr = self.next(*expr).eval(chunk)?;
@@ -110,14 +316,49 @@ impl<'a> Evaluator<'a> {
                 }
                 Ok(in_)
             }
-            Desc(a) | Ref(a) => self.next(*a).eval(chunk),
+            Desc(a) | NullsFirst(a) | NullsLast(a) | Ref(a) => self.next(*a).eval(chunk),
             // for aggs, evaluate its children
-            RowCount => Ok(ArrayImpl::new_null(
+            Over([f, _, _]) => self.next(*f).eval(chunk),
+            RowCount | RowNumber | Rank | DenseRank => Ok(ArrayImpl::new_null(
                 (0..chunk.cardinality()).map(|_| ()).collect(),
             )),
-            Count(a) | Sum(a) | Min(a) | Max(a) | First(a) | Last(a) | CountDistinct(a) => {
-                self.next(*a).eval(chunk)
+            Count(a) | Sum(a) | Min(a) | Max(a) | First(a) | Last(a) | CountDistinct(a)
+            | SumDistinct(a) => self.next(*a).eval(chunk),
+            StringAgg([_, _, orderby]) | ArrayAgg([_, orderby]) => {
+                // arguments and order keys of each row are packed into a list
+                let args = &self.node().children()[..self.node().len() - 1];
+                let mut arrays: Vec<ArrayImpl> = (args.iter())
+                    .map(|id| self.next(*id).eval(chunk))
+                    .try_collect()?;
+                arrays.extend(
+                    self.next(*orderby)
+                        .eval_list(chunk)?
+                        .arrays()
+                        .iter()
+                        .cloned(),
+                );
+                Ok(ArrayImpl::new_list(
+                    (0..chunk.cardinality())
+                        .map(|i| Some(arrays.iter().map(|a| a.get(i)).collect::<List>()))
+                        .collect(),
+                ))
+            }
+            Array(list) => {
+                let elems = self.next(*list).eval_list(chunk)?;
+                Ok(ArrayImpl::new_list(
+                    (0..chunk.cardinality())
+                        .map(|i| Some(elems.arrays().iter().map(|a| a.get(i)).collect::<List>()))
+                        .collect(),
+                ))
             }
+            ArrayGet([a, i]) => {
+                let a = self.next(*a).eval(chunk)?;
+                let i = self.next(*i).eval(chunk)?;
+                a.array_get(&i)
+            }
+            ArrayLength(a) => self.next(*a).eval(chunk)?.array_length(),
+            // lists are expanded by the project set executor
+            Unnest(a) => self.next(*a).eval(chunk),
             Replace([a, from, to]) => {
                 let a = self.next(*a).eval(chunk)?;
                 let from = self.next(*from);
@@ -147,6 +388,19 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    /// Returns the evaluators of the elements in a list.
+    pub fn list(&self) -> impl Iterator<Item = Self> + '_ {
+        (self.node().as_list().iter()).map(|id| self.next(*id))
+    }
+
+    /// Returns the window function, partition keys and order keys of an over node.
+    pub fn over(&self) -> (Self, Self, Self) {
+        let Expr::Over([f, partitionby, orderby]) = self.node() else {
+            panic!("not an over node: {self}");
+        };
+        (self.next(*f), self.next(*partitionby), self.next(*orderby))
+    }
+
     /// Returns the initial aggregation states.
     pub fn init_agg_states<B: FromIterator<AggState>>(&self) -> B {
         (self.node().as_list().iter())
@@ -155,12 +409,15 @@ impl<'a> Evaluator<'a> {
     }
 
     /// Returns the initial aggregation state.
-    fn init_agg_state(&self) -> AggState {
+    pub fn init_agg_state(&self) -> AggState {
         use Expr::*;
         match self.node() {
             Over([window, _, _]) => self.next(*window).init_agg_state(),
-            CountDistinct(_) => AggState::DistinctValue(HashSet::default()),
-            RowCount | RowNumber | Count(_) => AggState::Value(DataValue::Int32(0)),
+            CountDistinct(_) | SumDistinct(_) => AggState::DistinctValue(HashSet::default()),
+            StringAgg(_) | ArrayAgg(_) => AggState::Rows(vec![]),
+            RowCount | RowNumber | Rank | DenseRank | Count(_) => {
+                AggState::Value(DataValue::Int32(0))
+            }
             Sum(_) | Min(_) | Max(_) | First(_) | Last(_) => AggState::Value(DataValue::Null),
             t => panic!("not aggregation: {t}"),
         }
@@ -181,16 +438,24 @@ impl<'a> Evaluator<'a> {
     }
 
     /// Append a list of values to a list of agg states.
+    ///
+    /// Returns the estimated number of bytes of values kept in variable-length states.
     pub fn agg_list_append(
         &self,
         states: &mut [AggState],
         values: impl Iterator<Item = DataValue>,
-    ) {
+    ) -> usize {
         let list = self.node().as_list();
+        let mut size = 0;
         for ((state, id), value) in states.iter_mut().zip(list).zip(values) {
+            let (len, value_size) = (state.len(), value.estimated_size());
             let s = std::mem::take(state);
             *state = self.next(*id).agg_append(s, value);
+            if state.len() > len {
+                size += value_size;
+            }
         }
+        size
     }
 
     /// Consume a list of agg states and return their results.
@@ -198,15 +463,72 @@ impl<'a> Evaluator<'a> {
         &self,
         states: impl IntoIterator<Item = AggState>,
     ) -> impl Iterator<Item = DataValue> {
-        states.into_iter().map(|s| s.into_result())
+        let list = self.node().as_list();
+        (states.into_iter().zip(list))
+            .map(|(state, id)| self.next(*id).agg_result(&state))
+            .collect_vec()
+            .into_iter()
     }
 
-    /// Get the results of a list of agg states.
-    pub fn agg_list_get_result(
-        &self,
-        states: impl IntoIterator<Item = &'a AggState> + 'a,
-    ) -> impl Iterator<Item = DataValue> + 'a {
-        states.into_iter().map(|s| s.result())
+    /// Returns the result of an agg state.
+    pub fn agg_result(&self, state: &AggState) -> DataValue {
+        use Expr::*;
+        match state {
+            AggState::Value(v) => v.clone(),
+            AggState::DistinctValue(values) => match self.node() {
+                Over([window, _, _]) => self.next(*window).agg_result(state),
+                SumDistinct(_) => {
+                    (values.iter()).fold(DataValue::Null, |sum, v| sum.add(v.clone()))
+                }
+                _ => DataValue::Int32(values.len() as _),
+            },
+            AggState::Rows(rows) => match self.node() {
+                Over([window, _, _]) => self.next(*window).agg_result(state),
+                StringAgg([_, _, orderby]) => {
+                    // null values are ignored, and each value is preceded by its delimiter
+                    let mut result: Option<String> = None;
+                    for row in self.next(*orderby).sort_rows(rows, 2) {
+                        let DataValue::String(value) = &row[0] else {
+                            continue;
+                        };
+                        match &mut result {
+                            None => result = Some(value.to_string()),
+                            Some(result) => {
+                                if let DataValue::String(delimiter) = &row[1] {
+                                    result.push_str(delimiter);
+                                }
+                                result.push_str(value);
+                            }
+                        }
+                    }
+                    result.map_or(DataValue::Null, |s| DataValue::String(s.into()))
+                }
+                ArrayAgg(_) if rows.is_empty() => DataValue::Null,
+                ArrayAgg([_, orderby]) => DataValue::List(
+                    (self.next(*orderby).sort_rows(rows, 1).into_iter())
+                        .map(|row| row[0].clone())
+                        .collect(),
+                ),
+                t => panic!("not aggregation: {t}"),
+            },
+        }
+    }
+
+    /// Sorts packed rows of an aggregation by the order keys starting from `offset`.
+    fn sort_rows<'b>(&self, rows: &'b [List], offset: usize) -> Vec<&'b List> {
+        let orders = self.orders();
+        let mut sorted = rows.iter().collect_vec();
+        // the sort is stable, so rows without order keys are kept in input order
+        sorted.sort_by(|row1, row2| {
+            for ((v1, v2), order) in row1[offset..].iter().zip(&row2[offset..]).zip(&orders) {
+                match order.cmp(v1, v2) {
+                    Ordering::Equal => continue,
+                    o => return o,
+                }
+            }
+            Ordering::Equal
+        });
+        sorted
     }
 
     /// Evaluate the aggregation.
@@ -215,6 +537,8 @@ impl<'a> Evaluator<'a> {
             fn add(self, other: Self) -> Self {
                 if self.is_null() {
                     other
+                } else if other.is_null() {
+                    self
                 } else {
                     self + other
                 }
@@ -240,27 +564,35 @@ impl<'a> Evaluator<'a> {
                 t => panic!("not aggregation: {t}"),
             }),
             AggState::DistinctValue(mut values) => match self.node() {
-                CountDistinct(a) => {
+                CountDistinct(a) | SumDistinct(a) => {
                     let array = self.next(*a).eval(chunk)?;
-                    for value in array.iter() {
-                        values.insert(value);
-                    }
+                    // null values are ignored
+                    values.extend(array.iter().filter(|v| !v.is_null()));
                     AggState::DistinctValue(values)
                 }
                 t => panic!("invalid aggregation: {t}"),
             },
+            AggState::Rows(mut rows) => {
+                for value in self.eval(chunk)?.iter() {
+                    let DataValue::List(row) = value else {
+                        panic!("invalid aggregation: {self}");
+                    };
+                    rows.push(row);
+                }
+                AggState::Rows(rows)
+            }
         })
     }
 
     /// Append a value to agg state.
-    fn agg_append(&self, state: AggState, value: DataValue) -> AggState {
+    pub fn agg_append(&self, state: AggState, value: DataValue) -> AggState {
         use Expr::*;
         if let Over([window, _, _]) = self.node() {
             return self.next(*window).agg_append(state, value);
         }
         match state {
             AggState::Value(state) => AggState::Value(match self.node() {
-                RowCount | RowNumber => state.add(DataValue::Int32(1)),
+                RowCount | RowNumber | Rank | DenseRank => state.add(DataValue::Int32(1)),
                 Count(_) => state.add(DataValue::Int32(!value.is_null() as _)),
                 Sum(_) => state.add(value),
                 Min(_) => state.min(value),
@@ -270,47 +602,85 @@ impl<'a> Evaluator<'a> {
                 t => panic!("not aggregation: {t}"),
             }),
             AggState::DistinctValue(mut values) => {
-                values.insert(value);
+                if !value.is_null() {
+                    values.insert(value);
+                }
                 AggState::DistinctValue(values)
             }
+            AggState::Rows(mut rows) => {
+                let DataValue::List(row) = value else {
+                    panic!("invalid aggregation: {self}");
+                };
+                rows.push(row);
+                AggState::Rows(rows)
+            }
         }
     }
 
-    /// Returns a list of bools for order keys.
-    ///
-    /// The bool is false if the order is ascending, true if the order is descending.
-    pub fn orders(&self) -> Vec<bool> {
+    /// Returns the sort orders of order keys.
+    pub fn orders(&self) -> Vec<SortOrder> {
         (self.node().as_list().iter())
-            .map(|id| matches!(self.next(*id).node(), Expr::Desc(_)))
+            .map(|id| {
+                let (key, nulls_first) = match self.next(*id).node() {
+                    Expr::NullsFirst(key) => (self.next(*key), Some(true)),
+                    Expr::NullsLast(key) => (self.next(*key), Some(false)),
+                    _ => (self.next(*id), None),
+                };
+                let desc = matches!(key.node(), Expr::Desc(_));
+                SortOrder {
+                    desc,
+                    // NULL is the smallest value by default
+                    nulls_first: nulls_first.unwrap_or(!desc),
+                }
+            })
             .collect()
     }
 }
 
+/// The sort order of an order key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortOrder {
+    pub desc: bool,
+    pub nulls_first: bool,
+}
+
+impl SortOrder {
+    /// Compares two values in this order.
+    pub fn cmp(&self, v1: &DataValue, v2: &DataValue) -> Ordering {
+        match (v1.is_null(), v2.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if self.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if self.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if self.desc => v1.cmp(v2).reverse(),
+            (false, false) => v1.cmp(v2),
+        }
+    }
+}
+
 /// The aggregate state.
 #[derive(Debug, PartialEq, Eq)]
 pub enum AggState {
     Value(DataValue),
     DistinctValue(HashSet<DataValue>),
-}
-
-impl Default for AggState {
-    fn default() -> Self {
-        AggState::Value(DataValue::Null)
-    }
+    /// Arguments and order keys of all rows, for aggregations depending on the order of values.
+    Rows(Vec<List>),
 }
 
 impl AggState {
-    fn into_result(self) -> DataValue {
+    /// Returns the number of values kept in the state.
+    fn len(&self) -> usize {
         match self {
-            AggState::Value(v) => v,
-            AggState::DistinctValue(v) => DataValue::Int32(v.len() as _),
+            AggState::Value(_) => 0,
+            AggState::DistinctValue(values) => values.len(),
+            AggState::Rows(rows) => rows.len(),
         }
     }
+}
 
-    fn result(&self) -> DataValue {
-        match self {
-            AggState::Value(v) => v.clone(),
-            AggState::DistinctValue(v) => DataValue::Int32(v.len() as _),
-        }
+impl Default for AggState {
+    fn default() -> Self {
+        AggState::Value(DataValue::Null)
     }
 }
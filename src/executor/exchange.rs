@@ -0,0 +1,127 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use futures::channel::mpsc;
+use futures::SinkExt;
+
+use super::*;
+use crate::array::DataChunk;
+
+/// The number of chunks buffered for each output partition.
+const BUFFER_SIZE: usize = 16;
+
+/// How rows are distributed to output partitions.
+pub enum Distribution {
+    /// All rows are sent to one partition.
+    Single,
+    /// Chunks are sent to each partition in turn.
+    Random,
+    /// Each row is sent to the partition given by the hash of its keys.
+    Hash(RecExpr),
+}
+
+/// The executor of exchange operation.
+///
+/// Each input partition is consumed by a separate task, which redistributes its rows to output
+/// partitions.
+pub struct ExchangeExecutor {
+    pub dist: Distribution,
+    /// The number of output partitions. Ignored if all rows are sent to one partition.
+    pub num_partitions: usize,
+}
+
+impl ExchangeExecutor {
+    /// Returns the output stream of each partition.
+    pub fn execute(self, inputs: Vec<BoxedExecutor>) -> Vec<BoxedExecutor> {
+        let num_partitions = match self.dist {
+            Distribution::Single => 1,
+            _ => self.num_partitions,
+        };
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..num_partitions)
+            .map(|_| mpsc::channel(BUFFER_SIZE))
+            .unzip();
+        let dist = Arc::new(self.dist);
+        let tasks = (inputs.into_iter().enumerate())
+            .map(|(i, input)| {
+                let task = produce(input, senders.clone(), dist.clone(), i);
+                AbortOnDropHandle(tokio::spawn(task))
+            })
+            .collect_vec();
+        // tasks are aborted when all output streams are dropped
+        let tasks = Arc::new(tasks);
+        (receivers.into_iter())
+            .map(|receiver| consume(receiver, tasks.clone()))
+            .collect()
+    }
+}
+
+/// Sends rows of an input partition to output partitions.
+async fn produce(
+    mut input: BoxedExecutor,
+    mut outputs: Vec<mpsc::Sender<Result<DataChunk>>>,
+    dist: Arc<Distribution>,
+    index: usize,
+) {
+    // start from different partitions, so that small inputs are also distributed
+    let mut next = index;
+    while let Some(chunk) = input.next().await {
+        match chunk.and_then(|chunk| split(&dist, chunk, outputs.len(), &mut next)) {
+            Ok(chunks) => {
+                for (output, chunk) in outputs.iter_mut().zip(chunks) {
+                    if let Some(chunk) = chunk {
+                        // the partition may be dropped, e.g. by limit
+                        _ = output.send(Ok(chunk)).await;
+                    }
+                }
+            }
+            Err(e) => {
+                // the error is returned by one of the partitions
+                if let Some(output) = outputs.iter_mut().find(|output| !output.is_closed()) {
+                    _ = output.send(Err(e)).await;
+                }
+                return;
+            }
+        }
+        if outputs.iter().all(|output| output.is_closed()) {
+            return;
+        }
+    }
+}
+
+/// Splits a chunk into chunks of each output partition.
+fn split(
+    dist: &Distribution,
+    chunk: DataChunk,
+    num_partitions: usize,
+    next: &mut usize,
+) -> Result<Vec<Option<DataChunk>>> {
+    let mut chunks = (0..num_partitions).map(|_| None).collect_vec();
+    match dist {
+        Distribution::Single => chunks[0] = Some(chunk),
+        Distribution::Random => {
+            chunks[*next % num_partitions] = Some(chunk);
+            *next += 1;
+        }
+        Distribution::Hash(keys) => {
+            let keys = Evaluator::new(keys).eval_list(&chunk)?;
+            let partitions = (keys.hash_rows().into_iter())
+                .map(|hash| hash as usize % num_partitions)
+                .collect_vec();
+            for (i, output) in chunks.iter_mut().enumerate() {
+                let visibility = partitions.iter().map(|p| *p == i).collect_vec();
+                if visibility.contains(&true) {
+                    *output = Some(chunk.filter(&visibility));
+                }
+            }
+        }
+    }
+    Ok(chunks)
+}
+
+/// Receives chunks of an output partition.
+#[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+async fn consume(receiver: mpsc::Receiver<Result<DataChunk>>, _tasks: Arc<Vec<AbortOnDropHandle>>) {
+    #[for_await]
+    for chunk in receiver {
+        yield chunk?;
+    }
+}
@@ -4,6 +4,9 @@ use super::*;
 use crate::array::{ArrayImpl, DataChunk};
 
 /// The executor of a filter operation.
+///
+/// Rows not satisfying the condition are marked invisible in the output chunks, instead of being
+/// copied out.
 pub struct FilterExecutor {
     pub condition: RecExpr,
 }
@@ -13,12 +16,11 @@ impl FilterExecutor {
     pub async fn execute(self, child: BoxedExecutor) {
         #[for_await]
         for batch in child {
-            let batch = batch?;
-            let vis = match Evaluator::new(&self.condition).eval(&batch)? {
-                ArrayImpl::Bool(a) => a,
+            let (batch, vis) = match Evaluator::new(&self.condition).eval_visible(batch?)? {
+                (batch, ArrayImpl::Bool(a)) => (batch, a),
                 _ => panic!("filters can only accept bool array"),
             };
-            yield batch.filter(vis.true_array());
+            yield batch.with_visibility(vis.true_array());
         }
     }
 }
@@ -0,0 +1,67 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::cmp::Ordering;
+
+use super::*;
+use crate::array::{ArrayBuilderImpl, DataChunk};
+use crate::types::{ConvertError, DataType, DataValue};
+
+/// The executor of `generate_series` and `range` table functions.
+///
+/// Values are generated lazily, one chunk at a time.
+pub struct GenerateSeriesExecutor {
+    /// The list of start, stop, step and whether stop is included.
+    pub args: RecExpr,
+    pub ty: DataType,
+}
+
+impl GenerateSeriesExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        let args = Evaluator::new(&self.args).eval_list(&DataChunk::single(0))?;
+        let [start, stop, step, inclusive] = [0, 1, 2, 3].map(|i| args.array_at(i).get(0));
+        if step.is_zero() {
+            return Err(ConvertError::InvalidArgument("generate_series", step).into());
+        }
+        let ascending = step.is_positive();
+        let inclusive = inclusive == DataValue::Bool(true);
+
+        let mut builder = ArrayBuilderImpl::with_capacity(PROCESSING_WINDOW_SIZE, &self.ty);
+        let mut len = 0;
+        // no value is generated if any argument is null
+        let mut value = (!start.is_null() && !stop.is_null() && !step.is_null()).then_some(start);
+        while let Some(v) = value {
+            let in_range = match v.partial_cmp(&stop) {
+                Some(Ordering::Less) => ascending,
+                Some(Ordering::Greater) => !ascending,
+                _ => inclusive,
+            };
+            if !in_range {
+                break;
+            }
+            builder.push(&v);
+            len += 1;
+            if len == PROCESSING_WINDOW_SIZE {
+                yield DataChunk::from_iter([builder.take()]);
+                len = 0;
+            }
+            value = next_value(v, &step);
+        }
+        if len != 0 {
+            yield DataChunk::from_iter([builder.take()]);
+        }
+    }
+}
+
+/// Returns the next value in the series, or `None` if overflow.
+fn next_value(value: DataValue, step: &DataValue) -> Option<DataValue> {
+    match (value, step) {
+        (DataValue::Int16(v), DataValue::Int16(s)) => v.checked_add(*s).map(DataValue::Int16),
+        (DataValue::Int32(v), DataValue::Int32(s)) => v.checked_add(*s).map(DataValue::Int32),
+        (DataValue::Int64(v), DataValue::Int64(s)) => v.checked_add(*s).map(DataValue::Int64),
+        (DataValue::Timestamp(v), DataValue::Interval(s)) => {
+            v.checked_add(*s).map(DataValue::Timestamp)
+        }
+        (value, step) => panic!("invalid series: {value} + {step}"),
+    }
+}
@@ -1,5 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::hash::BuildHasher;
+
 use ahash::{HashMap, HashMapExt};
 use iter_chunks::IterChunks;
 use smallvec::SmallVec;
@@ -9,19 +11,30 @@ use crate::array::DataChunkBuilder;
 use crate::types::DataValue;
 
 /// The executor of hash aggregation.
+///
+/// If groups exceed the memory budget, rows of new groups are spilled to temporary files,
+/// partitioned by the hash of their keys. Each partition is aggregated after the groups in memory
+/// are output, and may be spilled again.
 pub struct HashAggExecutor {
     pub keys: RecExpr,
     pub aggs: RecExpr,
     pub types: Vec<DataType>,
+    pub budget: MemoryBudget,
 }
 
 pub type GroupKeys = SmallVec<[DataValue; 4]>;
 pub type AggValue = SmallVec<[AggState; 4]>;
 
+/// The number of partitions that rows are spilled into.
+const NUM_SPILL_PARTITIONS: usize = 16;
+
+/// A spilled row with its group keys and aggregation arguments.
+type SpilledRow = (GroupKeys, Vec<DataValue>);
+
 impl HashAggExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
-        let mut states = HashMap::<GroupKeys, AggValue>::new();
+        let mut table = HashTable::new(&self.aggs, &self.budget, 0);
 
         #[for_await]
         for chunk in child {
@@ -31,25 +44,105 @@ impl HashAggExecutor {
 
             for i in 0..chunk.cardinality() {
                 let keys = keys_chunk.row(i).values().collect();
-                let states = states
-                    .entry(keys)
-                    .or_insert_with(|| Evaluator::new(&self.aggs).init_agg_states());
-                Evaluator::new(&self.aggs).agg_list_append(states, args_chunk.row(i).values());
+                table.push(keys, args_chunk.row(i).values())?;
             }
         }
 
-        let mut batches = IterChunks::chunks(states.into_iter(), PROCESSING_WINDOW_SIZE);
-        while let Some(batch) = batches.next() {
-            let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
-            for (key, states) in batch {
-                let agg_results = Evaluator::new(&self.aggs).agg_list_take_result(states);
-                if let Some(chunk) = builder.push_row(key.into_iter().chain(agg_results)) {
+        // spilled partitions to be aggregated, with the level of their hash tables
+        let mut partitions = vec![];
+        loop {
+            let (states, spilled) = table.finish();
+            partitions.extend(spilled.into_iter().map(|p| (p, table.level + 1)));
+
+            let mut batches = IterChunks::chunks(states.into_iter(), PROCESSING_WINDOW_SIZE);
+            while let Some(batch) = batches.next() {
+                let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
+                for (key, states) in batch {
+                    let agg_results = Evaluator::new(&self.aggs).agg_list_take_result(states);
+                    if let Some(chunk) = builder.push_row(key.into_iter().chain(agg_results)) {
+                        yield chunk;
+                    }
+                }
+                if let Some(chunk) = builder.take() {
                     yield chunk;
                 }
             }
-            if let Some(chunk) = builder.take() {
-                yield chunk;
+
+            let Some((partition, level)) = partitions.pop() else {
+                break;
+            };
+            table = HashTable::new(&self.aggs, &self.budget, level);
+            for row in partition.finish()? {
+                let (keys, args) = row?;
+                table.push(keys, args.into_iter())?;
             }
         }
     }
 }
+
+/// A hash table of aggregation states, which spills rows of new groups when exceeding the memory
+/// budget.
+///
+/// Keys of groups and values kept in variable-length states are counted. Groups in memory keep
+/// growing after the budget is exceeded.
+struct HashTable<'a> {
+    aggs: &'a RecExpr,
+    states: HashMap<GroupKeys, AggValue>,
+    reservation: MemoryReservation,
+    /// The level of nested spilling. Rows are partitioned by a different hash on each level.
+    level: usize,
+    /// Spilled partitions. Empty if the budget is not exceeded.
+    spilled: Vec<SpillWriter<SpilledRow>>,
+}
+
+impl<'a> HashTable<'a> {
+    fn new(aggs: &'a RecExpr, budget: &MemoryBudget, level: usize) -> Self {
+        HashTable {
+            aggs,
+            states: HashMap::new(),
+            reservation: MemoryReservation::new(budget, "hash agg"),
+            level,
+            spilled: vec![],
+        }
+    }
+
+    /// Appends a row to its group, or spills it if its group is not in memory.
+    fn push(&mut self, keys: GroupKeys, args: impl Iterator<Item = DataValue>) -> Result<()> {
+        let evaluator = Evaluator::new(self.aggs);
+        if let Some(states) = self.states.get_mut(&keys) {
+            let size = evaluator.agg_list_append(states, args);
+            return self.grow(size);
+        }
+        if !self.spilled.is_empty() {
+            let hasher = ahash::RandomState::with_seeds(self.level as u64, 1, 2, 3);
+            let partition = hasher.hash_one(&keys) as usize % NUM_SPILL_PARTITIONS;
+            return self.spilled[partition].write(&(keys, args.collect()));
+        }
+        let mut size = std::mem::size_of::<(GroupKeys, AggValue)>()
+            + keys.iter().map(|v| v.estimated_size()).sum::<usize>();
+        let mut states: AggValue = evaluator.init_agg_states();
+        size += evaluator.agg_list_append(&mut states, args);
+        self.states.insert(keys, states);
+        self.grow(size)
+    }
+
+    /// Reserves memory for groups in memory, and starts spilling if the budget is exceeded.
+    fn grow(&mut self, size: usize) -> Result<()> {
+        if !self.reservation.grow(size)? && self.spilled.is_empty() {
+            self.spilled = (0..NUM_SPILL_PARTITIONS)
+                .map(|_| SpillWriter::new())
+                .try_collect()?;
+        }
+        Ok(())
+    }
+
+    /// Takes the states of groups in memory and non-empty spilled partitions.
+    fn finish(&mut self) -> (HashMap<GroupKeys, AggValue>, Vec<SpillWriter<SpilledRow>>) {
+        let states = std::mem::take(&mut self.states);
+        let spilled = std::mem::take(&mut self.spilled);
+        (
+            states,
+            spilled.into_iter().filter(|p| !p.is_empty()).collect(),
+        )
+    }
+}
@@ -11,11 +11,14 @@ use crate::array::{ArrayBuilderImpl, ArrayImpl, DataChunk, DataChunkBuilder, Row
 use crate::types::{DataType, DataValue, Row};
 
 /// The executor for hash join
+///
+/// Rows of the hash table are counted in the memory budget.
 pub struct HashJoinExecutor<const T: JoinType> {
     pub left_keys: RecExpr,
     pub right_keys: RecExpr,
     pub left_types: Vec<DataType>,
     pub right_types: Vec<DataType>,
+    pub budget: MemoryBudget,
 }
 
 /// Join types for generating join code during the compilation.
@@ -39,13 +42,25 @@ impl<const T: JoinType> HashJoinExecutor<T> {
             matched: bool,
         }
         let mut hash_map: HashMap<JoinKeys, LeftKeyInfo> = HashMap::new();
+        // rows with null keys never match
+        let mut null_key_rows: Vec<Row> = vec![];
+        let mut reservation = MemoryReservation::new(&self.budget, "hash join");
         #[for_await]
         for chunk in left {
             let chunk = chunk?;
             let keys_chunk = Evaluator::new(&self.left_keys).eval_list(&chunk)?;
             for (row, keys) in chunk.rows().zip(keys_chunk.rows()) {
-                let keys = keys.values().collect();
-                hash_map.entry(keys).or_default().rows.push(row.to_owned());
+                let keys: JoinKeys = keys.values().collect();
+                let row = row.to_owned();
+                if has_null(&keys) {
+                    if T == JoinType::LeftOuter || T == JoinType::FullOuter {
+                        reservation.grow(values_size(&row))?;
+                        null_key_rows.push(row);
+                    }
+                    continue;
+                }
+                reservation.grow(values_size(&keys) + values_size(&row))?;
+                hash_map.entry(keys).or_default().rows.push(row);
             }
             tokio::task::consume_budget().await;
         }
@@ -59,7 +74,13 @@ impl<const T: JoinType> HashJoinExecutor<T> {
             let chunk = chunk?;
             let keys_chunk = Evaluator::new(&self.right_keys).eval_list(&chunk)?;
             for (right_row, keys) in chunk.rows().zip(keys_chunk.rows()) {
-                if let Some(left_rows) = hash_map.get_mut(&keys.values().collect::<JoinKeys>()) {
+                let keys: JoinKeys = keys.values().collect();
+                let left_rows = if has_null(&keys) {
+                    None
+                } else {
+                    hash_map.get_mut(&keys)
+                };
+                if let Some(left_rows) = left_rows {
                     left_rows.matched = true;
                     for left_row in &left_rows.rows {
                         let values = left_row.iter().cloned().chain(right_row.values());
@@ -81,11 +102,12 @@ impl<const T: JoinType> HashJoinExecutor<T> {
 
         // append rows for left outer join
         if T == JoinType::LeftOuter || T == JoinType::FullOuter {
-            for (_, rows) in hash_map {
-                if rows.matched {
-                    continue;
-                }
-                for row in rows.rows {
+            let unmatched = (hash_map.into_values())
+                .filter(|rows| !rows.matched)
+                .map(|rows| rows.rows.into_vec())
+                .chain([null_key_rows]);
+            for rows in unmatched {
+                for row in rows {
                     // append row: (left, NULL)
                     let values =
                         (row.into_iter()).chain(self.right_types.iter().map(|_| DataValue::Null));
@@ -109,19 +131,28 @@ pub struct HashSemiJoinExecutor {
     pub right_keys: RecExpr,
     pub left_types: Vec<DataType>,
     pub anti: bool,
+    pub budget: MemoryBudget,
 }
 
 impl HashSemiJoinExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, left: BoxedExecutor, right: BoxedExecutor) {
         let mut key_set: HashSet<JoinKeys> = HashSet::new();
+        let mut reservation = MemoryReservation::new(&self.budget, "hash join");
         // build
         #[for_await]
         for chunk in right {
             let chunk = chunk?;
             let keys_chunk = Evaluator::new(&self.right_keys).eval_list(&chunk)?;
             for row in keys_chunk.rows() {
-                key_set.insert(row.values().collect());
+                let keys: JoinKeys = row.values().collect();
+                // null never equals to anything
+                if !has_null(&keys) {
+                    let size = values_size(&keys);
+                    if key_set.insert(keys) {
+                        reservation.grow(size)?;
+                    }
+                }
             }
             tokio::task::consume_budget().await;
         }
@@ -132,7 +163,10 @@ impl HashSemiJoinExecutor {
             let keys_chunk = Evaluator::new(&self.left_keys).eval_list(&chunk)?;
             let exists = keys_chunk
                 .rows()
-                .map(|key| key_set.contains(&key.values().collect::<JoinKeys>()) ^ self.anti)
+                .map(|key| {
+                    let keys: JoinKeys = key.values().collect();
+                    (!has_null(&keys) && key_set.contains(&keys)) ^ self.anti
+                })
                 .collect::<Vec<bool>>();
             yield chunk.filter(&exists);
         }
@@ -147,20 +181,29 @@ pub struct HashSemiJoinExecutor2 {
     pub left_types: Vec<DataType>,
     pub right_types: Vec<DataType>,
     pub anti: bool,
+    pub budget: MemoryBudget,
 }
 
 impl HashSemiJoinExecutor2 {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, left: BoxedExecutor, right: BoxedExecutor) {
         let mut key_set: HashMap<JoinKeys, DataChunkBuilder> = HashMap::new();
+        let mut reservation = MemoryReservation::new(&self.budget, "hash join");
         // build
         #[for_await]
         for chunk in right {
             let chunk = chunk?;
             let keys_chunk = Evaluator::new(&self.right_keys).eval_list(&chunk)?;
             for (key, row) in keys_chunk.rows().zip(chunk.rows()) {
+                let keys: JoinKeys = key.values().collect();
+                if has_null(&keys) {
+                    continue;
+                }
+                reservation.grow(
+                    values_size(&keys) + row.values().map(|v| v.estimated_size()).sum::<usize>(),
+                )?;
                 let chunk = key_set
-                    .entry(key.values().collect())
+                    .entry(keys)
                     .or_insert_with(|| DataChunkBuilder::unbounded(&self.right_types))
                     .push_row(row.values());
                 assert!(chunk.is_none());
@@ -178,7 +221,10 @@ impl HashSemiJoinExecutor2 {
             let keys_chunk = Evaluator::new(&self.left_keys).eval_list(&chunk)?;
             let mut exists = Vec::with_capacity(chunk.cardinality());
             for (key, lrow) in keys_chunk.rows().zip(chunk.rows()) {
-                let b = if let Some(rchunk) = key_set.get(&key.values().collect::<JoinKeys>()) {
+                let keys: JoinKeys = key.values().collect();
+                let b = if has_null(&keys) {
+                    false
+                } else if let Some(rchunk) = key_set.get(&keys) {
                     let lchunk = self.left_row_to_chunk(&lrow, rchunk.cardinality());
                     let join_chunk = lchunk.row_concat(rchunk.clone());
                     let ArrayImpl::Bool(a) = Evaluator::new(&self.condition).eval(&join_chunk)?
@@ -208,3 +254,15 @@ impl HashSemiJoinExecutor2 {
             .collect()
     }
 }
+
+/// Returns the estimated number of bytes used by the values.
+fn values_size(values: &[DataValue]) -> usize {
+    values.iter().map(|v| v.estimated_size()).sum()
+}
+
+/// Returns true if any of the join keys is null.
+///
+/// A null key never matches any other key, including another null.
+pub fn has_null(keys: &[DataValue]) -> bool {
+    keys.iter().any(|v| v.is_null())
+}
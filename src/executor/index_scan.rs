@@ -0,0 +1,57 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::array::DataChunk;
+use crate::catalog::{ColumnRefId, IndexId, TableRefId};
+use crate::storage::{
+    KeyRange, ScanOptions, Storage, StorageColumnRef, Table, TracedStorageError, Transaction,
+    TxnIterator,
+};
+
+/// The executor of index scan operation.
+pub struct IndexScanExecutor<S: Storage> {
+    pub table_id: TableRefId,
+    pub index_id: IndexId,
+    pub columns: Vec<ColumnRefId>,
+    /// The range of the leading key column.
+    pub range: KeyRange,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> IndexScanExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        let table = self.storage.get_table(self.table_id)?;
+        let index = (self.storage).get_index(self.table_id.schema_id, self.index_id)?;
+        let table_columns = table.columns()?;
+
+        let mut col_idx: Vec<_> = self
+            .columns
+            .iter()
+            .map(|x| match x.column_id {
+                u32::MAX => Ok(StorageColumnRef::RowHandler),
+                id => (table_columns.iter())
+                    .position(|c| c.id() == id)
+                    .map(|idx| StorageColumnRef::Idx(idx as u32))
+                    .ok_or_else(|| TracedStorageError::not_found("column", id)),
+            })
+            .try_collect()?;
+        if self.columns.is_empty() {
+            col_idx.push(StorageColumnRef::RowHandler);
+        }
+
+        // find the handlers of matched rows, then read only these rows
+        let (txn, handlers) = index.lookup(&table, &self.range).await?;
+        let mut it = txn
+            .scan(&col_idx, ScanOptions::default().with_rows(handlers.into()))
+            .await?;
+        while let Some(mut x) = it.next_batch(None).await? {
+            if self.columns.is_empty() {
+                x = DataChunk::no_column(x.cardinality());
+            }
+            yield x;
+        }
+    }
+}
@@ -1,20 +1,47 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+use chrono::FixedOffset;
+
+use super::constraint::{
+    check_references, key_positions, ConstraintChecker, Referencing, TableConstraints,
+};
 use super::*;
-use crate::array::DataChunk;
-use crate::catalog::{ColumnId, TableRefId};
-use crate::storage::{Storage, Table, Transaction};
-use crate::types::ColumnIndex;
+use crate::array::{ArrayImpl, DataChunk, DataChunkBuilder};
+use crate::catalog::{ColumnCatalog, ColumnId, TableRefId};
+use crate::storage::{
+    RowHandler, ScanOptions, Storage, StorageColumnRef, Table, Transaction, TxnIterator,
+};
+use crate::types::{ColumnIndex, DataValue, Row};
 
 /// The executor of `insert` statement.
 pub struct InsertExecutor<S: Storage> {
     pub table_id: TableRefId,
     pub column_ids: Vec<ColumnId>,
+    pub constraints: TableConstraints,
+    /// Expressions on columns of the table to return for each inserted or updated row.
+    pub returning: Option<RecExpr>,
+    /// The `ON CONFLICT` clause.
+    pub upsert: Option<Upsert>,
+    /// Foreign keys referencing the table.
+    pub referencing: Vec<Referencing>,
+    /// The time zone to convert strings to timestamps with time zone in.
+    pub time_zone: FixedOffset,
     pub storage: Arc<S>,
 }
 
+/// The action on rows whose key already exists in the table.
+pub struct Upsert {
+    /// The unique key to detect conflicts.
+    pub key: Vec<ColumnId>,
+    /// The new values of all columns and the condition to update the existing row, or `None` to
+    /// do nothing. Columns of the first occurrence of the table refer to the existing row, and
+    /// of the second occurrence to the row proposed for insertion.
+    pub update: Option<(RecExpr, RecExpr)>,
+}
+
 impl<S: Storage> InsertExecutor<S> {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
@@ -23,42 +50,236 @@ impl<S: Storage> InsertExecutor<S> {
 
         // construct an expression
         let mut expr = RecExpr::default();
+        let time_zone = expr.add(Expr::Constant(DataValue::Int32(
+            self.time_zone.local_minus_utc(),
+        )));
         let list = columns
             .iter()
             .map(|col| {
                 let val = expr.add(
                     match self.column_ids.iter().position(|&id| id == col.id()) {
                         Some(index) => Expr::ColumnIndex(ColumnIndex(index as _)),
-                        None => Expr::null(),
+                        None => Expr::Constant(col.default_value()),
                     },
                 );
                 let ty = expr.add(Expr::Type(col.data_type()));
-                expr.add(Expr::Cast([ty, val]))
+                expr.add(Expr::CastTz([ty, val, time_zone]))
             })
             .collect();
         expr.add(Expr::List(list));
 
-        let mut txn = table.write().await?;
+        let returning = (self.returning.as_ref()).map(|expr| resolve_table_columns(expr, &columns));
+
         let mut cnt = 0;
-        #[for_await]
-        for chunk in child {
-            let chunk = Evaluator::new(&expr).eval_list(&chunk?)?;
-            cnt += chunk.cardinality();
-            txn.append(chunk).await?;
+        let mut outputs = vec![];
+        if let Some(upsert) = &self.upsert {
+            // conflicting rows are replaced by deleting the old ones in the same transaction
+            let mut txn = table.update().await?;
+            let mut chunks = vec![];
+            #[for_await]
+            for chunk in child {
+                chunks.push(Evaluator::new(&expr).eval_list(&chunk?)?);
+            }
+            let (chunks, deleted) = upsert.apply(&mut txn, &columns, chunks).await?;
+
+            let mut checker = ConstraintChecker::new(
+                &*self.storage,
+                &table,
+                &txn,
+                &columns,
+                &self.constraints,
+                &deleted,
+            )
+            .await?;
+            for chunk in &chunks {
+                checker.check(chunk)?;
+            }
+            checker.finish(&txn).await?;
+            check_references(
+                &*self.storage,
+                &table,
+                &txn,
+                &self.referencing,
+                &deleted,
+                &chunks,
+            )
+            .await?;
+
+            for chunk in chunks {
+                cnt += chunk.cardinality();
+                if let Some(returning) = &returning {
+                    outputs.push(Evaluator::new(returning).eval_list(&chunk)?);
+                }
+                txn.append(chunk).await?;
+            }
+            txn.commit().await?;
+        } else {
+            // unique keys are checked and appended while blocking other writers that check them
+            let mut txn = if self.constraints.unique_keys.is_empty() {
+                table.write().await?
+            } else {
+                table.update().await?
+            };
+            let excluded = HashSet::new();
+            let mut checker = ConstraintChecker::new(
+                &*self.storage,
+                &table,
+                &txn,
+                &columns,
+                &self.constraints,
+                &excluded,
+            )
+            .await?;
+            #[for_await]
+            for chunk in child {
+                let chunk = Evaluator::new(&expr).eval_list(&chunk?)?;
+                checker.check(&chunk)?;
+                cnt += chunk.cardinality();
+                if let Some(returning) = &returning {
+                    outputs.push(Evaluator::new(returning).eval_list(&chunk)?);
+                }
+                txn.append(chunk).await?;
+            }
+            checker.finish(&txn).await?;
+            txn.commit().await?;
+        }
+
+        if returning.is_some() {
+            for chunk in outputs {
+                yield chunk;
+            }
+        } else {
+            yield DataChunk::single(cnt as i32);
+        }
+    }
+}
+
+impl Upsert {
+    /// Resolves conflicts of rows to insert with existing rows of the table.
+    ///
+    /// Existing rows to update are deleted from the transaction. Returns the rows to write,
+    /// including the updated ones, and the handlers of deleted rows.
+    async fn apply<T: Transaction>(
+        &self,
+        txn: &mut T,
+        columns: &[ColumnCatalog],
+        chunks: Vec<DataChunk>,
+    ) -> Result<(Vec<DataChunk>, HashSet<DataValue>)> {
+        let positions = key_positions(columns, &self.key)?;
+        let keys = (chunks.iter())
+            .flat_map(|chunk| chunk.rows().map(|row| row.get_by_indexes(&positions)))
+            .filter(|key| !key.iter().any(|v| v.is_null()))
+            .collect();
+        let mut existing = lookup_rows(txn, columns.len(), &positions, &keys).await?;
+
+        // each existing row in conflict is followed by the row proposed for insertion
+        let types = columns.iter().map(|c| c.data_type()).collect_vec();
+        let mut conflicts = DataChunkBuilder::unbounded(types.iter().chain(&types));
+        let mut handlers = vec![];
+        let mut proposed = HashSet::new();
+        let mut outputs = vec![];
+        for chunk in chunks {
+            let mut visibility = vec![true; chunk.cardinality()];
+            for (row, visible) in chunk.rows().zip(&mut visibility) {
+                let key = row.get_by_indexes(&positions);
+                if key.iter().any(|v| v.is_null()) {
+                    continue;
+                }
+                if proposed.contains(&key) {
+                    if self.update.is_some() {
+                        let names = positions.iter().map(|i| columns[*i].name()).join(", ");
+                        return Err(ExecutorError::conflict_twice(names, key.iter().join(", ")));
+                    }
+                    *visible = false;
+                    continue;
+                }
+                if let Some((handler, old)) = existing.remove(&key) {
+                    *visible = false;
+                    if self.update.is_some() {
+                        handlers.push(handler);
+                        let _ = conflicts.push_row(old.into_iter().chain(row.values()));
+                    }
+                }
+                proposed.insert(key);
+            }
+            outputs.push(chunk.filter(&visibility));
         }
-        txn.commit().await?;
 
-        yield DataChunk::single(cnt as i32);
+        let mut deleted = HashSet::new();
+        let (Some((values, cond)), Some(conflicts)) = (&self.update, conflicts.take()) else {
+            return Ok((outputs, deleted));
+        };
+        let values = resolve_upsert_columns(values, columns);
+        let cond = resolve_upsert_columns(cond, columns);
+        let visibility = (Evaluator::new(&cond).eval(&conflicts)?.iter())
+            .map(|v| v == DataValue::Bool(true))
+            .collect_vec();
+        for (handler, visible) in handlers.iter().zip(&visibility) {
+            if *visible {
+                txn.delete(&T::RowHandlerType::from_column(handler, 0))
+                    .await?;
+                deleted.insert(handler.get(0));
+            }
+        }
+        let updated = Evaluator::new(&values).eval_list(&conflicts.filter(&visibility))?;
+        outputs.push(updated);
+        Ok((outputs, deleted))
     }
 }
 
+/// Returns the handler and values of existing rows with the given keys.
+///
+/// A key is given by the positions of its columns.
+async fn lookup_rows(
+    txn: &impl Transaction,
+    num_columns: usize,
+    positions: &[usize],
+    keys: &HashSet<Row>,
+) -> Result<HashMap<Row, (ArrayImpl, Row)>> {
+    let mut rows = HashMap::new();
+    if keys.is_empty() {
+        return Ok(rows);
+    }
+    // the row handler comes first
+    let column_refs = std::iter::once(StorageColumnRef::RowHandler)
+        .chain((0..num_columns).map(|i| StorageColumnRef::Idx(i as u32)))
+        .collect_vec();
+    let mut iter = txn.scan(&column_refs, ScanOptions::default()).await?;
+    while let Some(chunk) = iter.next_batch(None).await? {
+        for (i, row) in chunk.rows().enumerate() {
+            let key = positions.iter().map(|p| row.get(p + 1)).collect_vec();
+            if keys.contains(&key) {
+                let handler = chunk.array_at(0).slice(i..=i);
+                rows.insert(key, (handler, row.values().skip(1).collect()));
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Resolves columns of the existing row and the excluded row to their indexes in the chunk of
+/// both rows.
+fn resolve_upsert_columns(expr: &RecExpr, columns: &[ColumnCatalog]) -> RecExpr {
+    let nodes = (expr.as_ref().iter())
+        .map(|node| match node {
+            Expr::Column(c) => {
+                let index = columns.iter().position(|col| col.id() == c.column_id);
+                let offset = c.table_occurrence as usize * columns.len();
+                Expr::ColumnIndex(ColumnIndex((offset + index.unwrap()) as _))
+            }
+            node => node.clone(),
+        })
+        .collect_vec();
+    RecExpr::from(nodes)
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
     use super::*;
     use crate::array::ArrayImpl;
-    use crate::catalog::{ColumnCatalog, ColumnDesc, TableRefId};
+    use crate::catalog::{ColumnCatalog, ColumnDesc, Constraints, TableRefId};
     use crate::storage::{InMemoryStorage, StorageImpl};
     use crate::types::DataType;
 
@@ -68,6 +289,11 @@ mod tests {
         let executor = InsertExecutor {
             table_id: TableRefId::new(1, 0),
             column_ids: vec![0, 1],
+            constraints: TableConstraints::default(),
+            returning: None,
+            upsert: None,
+            referencing: vec![],
+            time_zone: FixedOffset::east_opt(0).unwrap(),
             storage: storage.as_in_memory_storage(),
         };
         let source = async_stream::try_stream! {
@@ -93,7 +319,7 @@ mod tests {
                     ColumnCatalog::new(0, ColumnDesc::new("v1", DataType::Int32, false)),
                     ColumnCatalog::new(1, ColumnDesc::new("v2", DataType::Int32, false)),
                 ],
-                &[],
+                &Constraints::default(),
             )
             .await
             .unwrap();
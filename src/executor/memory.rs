@@ -0,0 +1,190 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Tracking of memory used by queries.
+//!
+//! Memory contexts form a tree: the root context of the database has a child for each running
+//! query, which in turn has a child for each executor holding data in memory, e.g. hash tables and
+//! sort buffers. Memory allocated in a context is also counted in all its ancestors.
+
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use super::*;
+
+/// A node in the tree of memory contexts.
+///
+/// Memory allocated in a context and not freed is released when the context is dropped.
+#[derive(Debug, Clone)]
+pub struct MemoryContext {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    id: u32,
+    name: String,
+    /// The maximum memory in bytes, including memory of descendants. Unlimited if `None`.
+    limit: Option<usize>,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    parent: Option<MemoryContext>,
+    children: Mutex<Vec<Weak<Inner>>>,
+}
+
+/// The memory usage of a context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub id: u32,
+    pub parent_id: Option<u32>,
+    pub name: String,
+    pub used: usize,
+    pub peak: usize,
+    pub limit: Option<usize>,
+}
+
+impl MemoryContext {
+    /// Creates a root context.
+    pub fn new_root(name: &str) -> Self {
+        Self::new(name, None, None)
+    }
+
+    /// Creates a child context with an optional limit.
+    pub fn child(&self, name: &str, limit: Option<usize>) -> Self {
+        let child = Self::new(name, limit, Some(self.clone()));
+        let mut children = self.inner.children.lock().unwrap();
+        children.retain(|c| c.strong_count() > 0);
+        children.push(Arc::downgrade(&child.inner));
+        child
+    }
+
+    fn new(name: &str, limit: Option<usize>, parent: Option<MemoryContext>) -> Self {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+        MemoryContext {
+            inner: Arc::new(Inner {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                name: name.into(),
+                limit,
+                used: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                parent,
+                children: Mutex::new(vec![]),
+            }),
+        }
+    }
+
+    /// Allocates memory in the context.
+    ///
+    /// Returns an out-of-memory error without allocating if the context or any of its ancestors
+    /// would exceed its limit.
+    pub fn alloc(&self, size: usize) -> Result<()> {
+        let mut allocated: Vec<&Inner> = vec![];
+        let mut ctx = Some(self);
+        while let Some(MemoryContext { inner }) = ctx {
+            let used = inner.used.fetch_add(size, Ordering::Relaxed) + size;
+            allocated.push(inner);
+            if let Some(limit) = inner.limit
+                && used > limit
+            {
+                for inner in allocated {
+                    inner.used.fetch_sub(size, Ordering::Relaxed);
+                }
+                return Err(ExecutorError::out_of_memory(&inner.name, limit));
+            }
+            inner.peak.fetch_max(used, Ordering::Relaxed);
+            ctx = inner.parent.as_ref();
+        }
+        Ok(())
+    }
+
+    /// Frees memory allocated in the context before.
+    pub fn free(&self, size: usize) {
+        self.inner.free(size);
+    }
+
+    /// Returns the memory in bytes used by the context and its descendants.
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> &MemoryContext {
+        match &self.inner.parent {
+            Some(parent) => parent.root(),
+            None => self,
+        }
+    }
+
+    /// Returns the usage of the context and all its descendants in pre-order.
+    pub fn usages(&self) -> Vec<MemoryUsage> {
+        let mut usages = vec![];
+        self.inner.collect_usages(&mut usages);
+        usages
+    }
+}
+
+impl Inner {
+    fn free(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+        if let Some(parent) = &self.parent {
+            parent.free(size);
+        }
+    }
+
+    fn collect_usages(&self, usages: &mut Vec<MemoryUsage>) {
+        usages.push(MemoryUsage {
+            id: self.id,
+            parent_id: self.parent.as_ref().map(|p| p.inner.id),
+            name: self.name.clone(),
+            used: self.used.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+            limit: self.limit,
+        });
+        let children = self.children.lock().unwrap().clone();
+        for child in children.iter().filter_map(|c| c.upgrade()) {
+            child.collect_usages(usages);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // release the memory still counted in ancestors
+        let used = *self.used.get_mut();
+        if used > 0
+            && let Some(parent) = &self.parent
+        {
+            parent.free(used);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_context() {
+        let root = MemoryContext::new_root("root");
+        let query = root.child("query", Some(100));
+        let executor = query.child("executor", None);
+        executor.alloc(60).unwrap();
+        assert_eq!(root.used(), 60);
+        assert!(executor.alloc(50).is_err());
+        assert_eq!(query.used(), 60);
+
+        let usages = root.usages();
+        assert_eq!(usages.len(), 3);
+        assert_eq!(usages[2].name, "executor");
+        assert_eq!(usages[2].parent_id, Some(usages[1].id));
+        assert_eq!((usages[1].used, usages[1].limit), (60, Some(100)));
+
+        executor.free(20);
+        assert_eq!(
+            (query.used(), query.inner.peak.load(Ordering::Relaxed)),
+            (40, 60)
+        );
+        drop(executor);
+        assert_eq!(root.used(), 0);
+        assert_eq!(root.usages().len(), 2);
+    }
+}
@@ -28,7 +28,8 @@ impl<const T: JoinType> MergeJoinExecutor<T> {
         loop {
             match (&left_group, &right_group) {
                 // cross join if left key == right key
-                (Some((lkey, lchunk)), Some((rkey, rchunk))) if lkey == rkey => {
+                // null keys never match, they are handled as unmatched rows below
+                (Some((lkey, lchunk)), Some((rkey, rchunk))) if lkey == rkey && !has_null(lkey) => {
                     for left_row in lchunk {
                         for right_row in rchunk {
                             let values = left_row.iter().chain(right_row.iter()).cloned();
@@ -42,7 +43,8 @@ impl<const T: JoinType> MergeJoinExecutor<T> {
                 }
                 // left join if left key < right key or right is finished
                 (Some((lkey, lchunk)), _)
-                    if right_group.as_ref().map_or(true, |(rkey, _)| lkey < rkey) =>
+                    if has_null(lkey)
+                        || right_group.as_ref().map_or(true, |(rkey, _)| lkey < rkey) =>
                 {
                     if T == JoinType::LeftOuter || T == JoinType::FullOuter {
                         for left_row in lchunk {
@@ -57,7 +59,8 @@ impl<const T: JoinType> MergeJoinExecutor<T> {
                 }
                 // right join if left key > right key or left is finished
                 (_, Some((rkey, rchunk)))
-                    if left_group.as_ref().map_or(true, |(lkey, _)| lkey > rkey) =>
+                    if has_null(rkey)
+                        || left_group.as_ref().map_or(true, |(lkey, _)| lkey > rkey) =>
                 {
                     if T == JoinType::RightOuter || T == JoinType::FullOuter {
                         for right_row in rchunk {
@@ -23,69 +23,108 @@ use itertools::Itertools;
 use tracing::Instrument;
 
 // use minitrace::prelude::*;
+use self::alter_table::*;
 use self::analyze::*;
+use self::constraint::{Referencing, TableConstraints};
 use self::copy_from_file::*;
 use self::copy_to_file::*;
 use self::create_function::*;
+use self::create_index::*;
+use self::create_schema::*;
 use self::create_table::*;
+use self::create_table_as::*;
 use self::create_view::*;
 use self::delete::*;
 use self::drop::*;
+use self::drop_function::*;
+use self::drop_index::*;
+use self::drop_schema::*;
 pub use self::error::Error as ExecutorError;
 use self::error::*;
 use self::evaluator::*;
+use self::exchange::*;
 use self::explain::*;
 use self::filter::*;
+use self::generate_series::*;
 use self::hash_agg::*;
 use self::hash_join::*;
+use self::index_scan::*;
 use self::insert::*;
 use self::limit::*;
+pub use self::memory::MemoryContext;
 use self::merge_join::*;
 use self::nested_loop_join::*;
 use self::order::*;
 // #[allow(unused_imports)]
 // use self::perfect_hash_agg::*;
+use self::project_set::*;
 use self::projection::*;
+use self::recursive_union::*;
 use self::simple_agg::*;
 use self::sort_agg::*;
+use self::spill::*;
 use self::system_table_scan::*;
 use self::table_scan::*;
+use self::tee::*;
 use self::top_n::TopNExecutor;
+use self::truncate::*;
+use self::update::*;
 use self::values::*;
 use self::window::*;
 use crate::array::DataChunk;
-use crate::catalog::{RootCatalog, RootCatalogRef, TableRefId};
+use crate::catalog::{
+    ColumnCatalog, ColumnId, ColumnRefId, IndexId, RootCatalog, RootCatalogRef, TableRefId,
+};
 use crate::planner::{Expr, ExprAnalysis, Optimizer, RecExpr, TypeSchemaAnalysis};
-use crate::storage::Storage;
-use crate::types::{ColumnIndex, DataType};
+use crate::storage::{KeyRange, Storage};
+use crate::types::{ColumnIndex, DataType, DataValue};
+use crate::utils::metrics;
 use crate::utils::timed::{FutureExt as _, Span as TimeSpan};
 
+mod alter_table;
 mod analyze;
+mod constraint;
 mod copy_from_file;
 mod copy_to_file;
 mod create_function;
+mod create_index;
+mod create_schema;
 mod create_table;
+mod create_table_as;
 mod create_view;
 mod delete;
 mod drop;
+mod drop_function;
+mod drop_index;
+mod drop_schema;
 mod evaluator;
+mod exchange;
 mod explain;
 mod filter;
+mod generate_series;
 mod hash_agg;
 mod hash_join;
+mod index_scan;
 mod insert;
 mod limit;
+mod memory;
 mod nested_loop_join;
 mod order;
 mod system_table_scan;
 // mod perfect_hash_agg;
 mod error;
 mod merge_join;
+mod project_set;
 mod projection;
+mod recursive_union;
 mod simple_agg;
 mod sort_agg;
+mod spill;
 mod table_scan;
+mod tee;
 mod top_n;
+mod truncate;
+mod update;
 mod values;
 mod window;
 
@@ -100,8 +139,46 @@ const PROCESSING_WINDOW_SIZE: usize = 1024;
 /// and produces a stream to its parent.
 pub type BoxedExecutor = BoxStream<'static, Result<DataChunk>>;
 
-pub fn build(optimizer: Optimizer, storage: Arc<impl Storage>, plan: &RecExpr) -> BoxedExecutor {
-    Builder::new(optimizer, storage, plan).build()
+pub fn build(
+    optimizer: Optimizer,
+    storage: Arc<impl Storage>,
+    plan: &RecExpr,
+    memory: MemoryContext,
+) -> BoxedExecutor {
+    Builder::new(optimizer, storage, plan, memory).build()
+}
+
+/// Returns the names and types of output columns of a plan.
+///
+/// Returns `None` if the plan doesn't output rows, e.g. `CREATE TABLE`.
+pub fn output_schema(catalog: &RootCatalogRef, plan: &RecExpr) -> Option<Vec<(String, DataType)>> {
+    let mut egraph = egg::EGraph::new(TypeSchemaAnalysis {
+        catalog: catalog.clone(),
+    });
+    let root = egraph.add_expr(plan);
+    let Ok(DataType::Struct(types)) = &egraph[root].data.type_ else {
+        return None;
+    };
+    let schema = &egraph[root].data.schema;
+    if schema.len() != types.len() {
+        return None;
+    }
+    let names = schema
+        .iter()
+        .map(|&id| column_name(catalog, &egraph[id].nodes[0]));
+    Some(names.zip(types.iter().cloned()).collect())
+}
+
+/// Returns the name of an output column.
+///
+/// Columns of tables are named by the column names, and others are named `?column?`.
+fn column_name(catalog: &RootCatalog, expr: &Expr) -> String {
+    match expr {
+        Expr::Column(cid) => catalog
+            .get_column(cid)
+            .map_or("?column?".into(), |c| c.name().to_string()),
+        _ => "?column?".into(),
+    }
 }
 
 /// The builder of executor.
@@ -110,39 +187,53 @@ struct Builder<S: Storage> {
     optimizer: Optimizer,
     egraph: egg::EGraph<Expr, TypeSchemaAnalysis>,
     root: Id,
-    /// For scans on views, we prebuild their executors and store them here.
-    /// Multiple scans on the same view will share the same executor.
-    views: HashMap<TableRefId, StreamSubscriber>,
+    /// The input of the working table when building the recursive term of a recursive CTE.
+    working_table: Vec<DataChunk>,
+    /// Ranges of columns in the filters on scans, used to skip blocks out of the ranges.
+    zone_filters: HashMap<Id, Vec<(ColumnRefId, KeyRange)>>,
+    /// The partition being built and the number of partitions, if nodes are built for each
+    /// partition.
+    partition: (usize, usize),
+    /// The subscriber of each output partition of exchanges.
+    exchanges: HashMap<Id, Vec<StreamSubscriber>>,
+    /// The number of parents of each node in the plan.
+    refs: HashMap<Id, usize>,
+    /// Outputs of subplans with multiple parents not taken yet, for each partition.
+    shared: HashMap<(Id, (usize, usize)), Vec<BoxedExecutor>>,
+    /// The memory budget shared by executors of the query.
+    budget: MemoryBudget,
     metrics: Metrics,
+    /// The constant node of the time zone of the session in seconds east of UTC.
+    time_zone: Id,
 }
 
 impl<S: Storage> Builder<S> {
     /// Create a new executor builder.
-    fn new(optimizer: Optimizer, storage: Arc<S>, plan: &RecExpr) -> Self {
+    ///
+    /// Memory used by executors is tracked in the given memory context of the query.
+    fn new(optimizer: Optimizer, storage: Arc<S>, plan: &RecExpr, memory: MemoryContext) -> Self {
         let mut egraph = egg::EGraph::new(TypeSchemaAnalysis {
             catalog: optimizer.catalog().clone(),
         });
         let root = egraph.add_expr(plan);
-
-        // recursively build for all views
-        let mut views = HashMap::new();
-        for node in plan.as_ref() {
-            if let Expr::Table(tid) = node
-                && let Some(query) = optimizer.catalog().get_table(tid).unwrap().query()
-            {
-                let builder = Self::new(optimizer.clone(), storage.clone(), query);
-                let subscriber = builder.build_subscriber();
-                views.insert(*tid, subscriber);
-            }
-        }
-
+        let budget = MemoryBudget::new(optimizer.config().memory_limit, memory);
+        let refs = count_refs(&egraph, root);
+        let time_zone = optimizer.config().time_zone.local_minus_utc();
+        let time_zone = egraph.add(Expr::Constant(DataValue::Int32(time_zone)));
         Builder {
             storage,
             optimizer,
             egraph,
             root,
-            views,
+            working_table: vec![],
+            zone_filters: HashMap::new(),
+            partition: (0, 1),
+            exchanges: HashMap::new(),
+            refs,
+            shared: HashMap::new(),
+            budget,
             metrics: Metrics::default(),
+            time_zone,
         }
     }
 
@@ -152,9 +243,20 @@ impl<S: Storage> Builder<S> {
         &self.egraph[id].nodes[0]
     }
 
+    /// Returns the node to execute from id.
+    ///
+    /// Casts are done in the time zone of the session, in which timestamps with time zone are
+    /// converted from or to strings.
+    fn exec_node(&self, id: Id) -> Expr {
+        match self.node(id) {
+            &Expr::Cast([ty, a]) => Expr::CastTz([ty, a, self.time_zone]),
+            node => node.clone(),
+        }
+    }
+
     /// Extract a `RecExpr` from id.
     fn recexpr(&self, id: Id) -> RecExpr {
-        self.node(id).build_recexpr(|id| self.node(id).clone())
+        self.exec_node(id).build_recexpr(|id| self.exec_node(id))
     }
 
     /// Returns the output types of a plan node.
@@ -163,6 +265,13 @@ impl<S: Storage> Builder<S> {
         ty.as_struct()
     }
 
+    /// Returns the names of output columns of a plan node.
+    fn plan_names(&self, id: Id) -> Vec<String> {
+        (self.egraph[id].data.schema.iter())
+            .map(|&expr| column_name(self.catalog(), self.node(expr)))
+            .collect()
+    }
+
     /// Resolve the column index of `expr` in `plan`.
     fn resolve_column_index(&self, expr: Id, plan: Id) -> RecExpr {
         let schema = &self.egraph[plan].data.schema;
@@ -179,35 +288,117 @@ impl<S: Storage> Builder<S> {
 
     /// Resolve the column index of `expr` in `schema`.
     fn resolve_column_index_on_schema(&self, expr: Id, schema: &[Id]) -> RecExpr {
-        self.node(expr).build_recexpr(|id| {
+        self.exec_node(expr).build_recexpr(|id| {
             if let Some(idx) = schema.iter().position(|x| *x == id) {
                 return Expr::ColumnIndex(ColumnIndex(idx as _));
             }
             match self.node(id) {
                 Expr::Column(c) => panic!("column {c} not found from input"),
-                e => e.clone(),
+                _ => self.exec_node(id),
             }
         })
     }
 
+    /// Returns the ranges of columns in the conjunctions of a condition.
+    fn column_ranges(&self, cond: Id) -> Vec<(ColumnRefId, KeyRange)> {
+        if let Expr::And([a, b]) = self.node(cond) {
+            let mut ranges = self.column_ranges(*a);
+            ranges.extend(self.column_ranges(*b));
+            return ranges;
+        }
+        let mut egraph = egg::EGraph::new(ExprAnalysis::default());
+        let root = egraph.add_expr(&self.recexpr(cond));
+        egraph[root].data.range.clone().into_iter().collect()
+    }
+
     /// Returns the catalog.
     fn catalog(&self) -> &RootCatalogRef {
         self.optimizer.catalog()
     }
 
+    /// Returns the constraints of a table with the given bound `CHECK` conditions.
+    fn table_constraints(&self, table_id: TableRefId, checks: Id) -> TableConstraints {
+        let table = self.catalog().get_table(&table_id).unwrap();
+        let primary_key = Some(table.primary_keys()).filter(|pk| !pk.is_empty());
+        let conds = (self.node(checks).as_list().iter()).map(|id| self.recexpr(*id));
+        TableConstraints {
+            unique_keys: (primary_key.into_iter())
+                .chain(table.unique_keys().iter().cloned())
+                .collect(),
+            checks: table.checks().iter().cloned().zip(conds).collect(),
+            foreign_keys: (table.foreign_keys().iter())
+                .map(|fk| {
+                    let index = self.key_index(fk.ref_table_id, fk.ref_column_ids[0]);
+                    (fk.clone(), index)
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns foreign keys referencing a table.
+    fn referencing_tables(&self, table_id: TableRefId) -> Vec<Referencing> {
+        (self.catalog().get_referencing_tables(table_id).into_iter())
+            .map(|(id, foreign_key)| Referencing {
+                table_id: id,
+                table_name: self.catalog().get_table(&id).unwrap().name().to_string(),
+                index: self.key_index(id, foreign_key.column_ids[0]),
+                foreign_key,
+            })
+            .collect()
+    }
+
+    /// Returns an index of a table to look up keys by their leading column.
+    ///
+    /// Indexes only contain committed rows, so they are not used if index scans are disabled,
+    /// e.g. in transactions.
+    fn key_index(&self, table_id: TableRefId, column_id: ColumnId) -> Option<IndexId> {
+        if !self.optimizer.config().enable_index_scan {
+            return None;
+        }
+        (self.catalog().get_table_indexes(&table_id).into_iter())
+            .find(|index| index.is_leading_column(column_id))
+            .map(|index| index.id())
+    }
+
     /// Builds the executor.
     fn build(mut self) -> BoxedExecutor {
         self.build_id(self.root)
     }
 
-    /// Builds the executor and returns its subscriber.
-    fn build_subscriber(mut self) -> StreamSubscriber {
-        self.build_id_subscriber(self.root)
+    /// Builds the executor for the given id. Invisible rows are removed from its output.
+    fn build_id(&mut self, id: Id) -> BoxedExecutor {
+        let stream = self.build_id_with_visibility(id);
+        match self.node(id) {
+            // executors that may output chunks with visibility
+            Expr::Filter(_) | Expr::Proj(_) => stream.map(|c| c.map(DataChunk::compact)).boxed(),
+            _ => stream,
+        }
     }
 
-    /// Builds the executor for the given id.
-    fn build_id(&mut self, id: Id) -> BoxedExecutor {
-        self.build_id_subscriber(id).subscribe()
+    /// Builds the executor for the given id, whose output chunks may have invisible rows.
+    ///
+    /// Identical subplans share a node in the e-graph. They are built once, and their output is
+    /// replicated to all parents.
+    fn build_id_with_visibility(&mut self, id: Id) -> BoxedExecutor {
+        let refs = self.refs.get(&id).copied().unwrap_or(1);
+        if refs <= 1 || matches!(self.node(id), Expr::Exchange(_)) {
+            return self.build_id_subscriber(id).subscribe();
+        }
+        let key = (id, self.partition);
+        if let Some(streams) = self.shared.get_mut(&key) {
+            return match streams.pop() {
+                Some(stream) => stream,
+                None => self.build_id_subscriber(id).subscribe(),
+            };
+        }
+        let stream = self.build_id_subscriber(id).subscribe();
+        let mut streams = TeeExecutor {
+            memory: self.budget.context().child("tee", None),
+        }
+        .execute(stream, refs);
+        let stream = streams.pop().unwrap();
+        self.shared.insert(key, streams);
+        stream
     }
 
     /// Builds the executor for the given id and returns its subscriber.
@@ -239,44 +430,76 @@ impl<S: Storage> Builder<S> {
                     }
                 };
 
-                if let Some(subscriber) = self.views.get(&table_id) {
-                    // scan a view
-                    assert!(
-                        filter.is_none(),
-                        "range filter is not supported in view scan"
-                    );
-
-                    // resolve column index
-                    // child schema: [$v.0, $v.1, ...]
-                    let mut projs = RecExpr::default();
-                    let lists = columns
-                        .iter()
-                        .map(|c| {
-                            projs.add(ColumnIndex(crate::types::ColumnIndex(c.column_id as _)))
-                        })
-                        .collect();
-                    projs.add(List(lists));
-
-                    ProjectionExecutor { projs }.execute(subscriber.subscribe())
-                } else if table_id.schema_id == RootCatalog::SYSTEM_SCHEMA_ID {
+                if (self.catalog().get_table(&table_id)).is_some_and(|t| t.is_system()) {
                     SystemTableScan {
                         catalog: self.catalog().clone(),
                         storage: self.storage.clone(),
                         table_id,
                         columns,
+                        memory: self.budget.context().clone(),
                     }
                     .execute()
                 } else {
+                    // only ranges of the scanned columns can be used to skip blocks
+                    let zone_filters = (self.zone_filters.get(&id).cloned().unwrap_or_default())
+                        .into_iter()
+                        .filter(|(column, _)| columns.contains(column))
+                        .collect();
                     TableScanExecutor {
                         table_id,
                         columns,
                         filter,
+                        zone_filters,
+                        partition: self.partition,
+                        as_of: None,
                         storage: self.storage.clone(),
                     }
                     .execute()
                 }
             }
 
+            ScanAsOf([table, list, version]) => TableScanExecutor {
+                table_id: self.node(table).as_table(),
+                columns: (self.node(list).as_list().iter())
+                    .map(|id| self.node(*id).as_column())
+                    .collect_vec(),
+                filter: None,
+                zone_filters: vec![],
+                partition: self.partition,
+                as_of: Some(self.recexpr(version)),
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            IndexScan([table, list, cond]) => {
+                let table_id = self.node(table).as_table();
+                let columns = (self.node(list).as_list().iter())
+                    .map(|id| self.node(*id).as_column())
+                    .collect_vec();
+                // analyze the range of the leading index column
+                let mut egraph = egg::EGraph::new(ExprAnalysis::default());
+                let root = egraph.add_expr(&self.recexpr(cond));
+                let executor = (|| -> Result<_> {
+                    let (column, range) = (egraph[root].data.range.clone()).ok_or_else(|| {
+                        ExecutorError::invalid_index_scan("condition is not a range")
+                    })?;
+                    let index = (self.catalog().get_table_indexes(&table_id).into_iter())
+                        .find(|index| index.is_leading_column(column.column_id))
+                        .ok_or_else(|| ExecutorError::invalid_index_scan("index not found"))?;
+                    Ok(IndexScanExecutor {
+                        table_id,
+                        index_id: index.id(),
+                        columns,
+                        range,
+                        storage: self.storage.clone(),
+                    })
+                })();
+                match executor {
+                    Ok(executor) => executor.execute(),
+                    Err(error) => futures::stream::once(async { Err(error) }).boxed(),
+                }
+            }
+
             Values(rows) => ValuesExecutor {
                 column_types: self.plan_types(id).to_vec(),
                 values: {
@@ -294,16 +517,40 @@ impl<S: Storage> Builder<S> {
             Proj([projs, child]) => ProjectionExecutor {
                 projs: self.resolve_column_index(projs, child),
             }
-            .execute(self.build_id(child)),
+            .execute(self.build_id_with_visibility(child)),
 
-            Filter([cond, child]) => FilterExecutor {
-                condition: self.resolve_column_index(cond, child),
+            ProjectSet([exprs, child]) => ProjectSetExecutor {
+                exprs: self.resolve_column_index(exprs, child),
+                types: self.plan_types(id).to_vec(),
             }
             .execute(self.build_id(child)),
 
+            Filter([cond, child]) => {
+                // the scan may be partitioned by an exchange
+                let scan = match self.node(child) {
+                    Exchange([_, scan]) => *scan,
+                    _ => child,
+                };
+                // a scan shared with other parents can not skip rows out of the filter
+                let shared = [child, scan]
+                    .iter()
+                    .any(|id| self.refs.get(id).is_some_and(|n| *n > 1));
+                if let Scan(_) = self.node(scan)
+                    && !shared
+                {
+                    let ranges = self.column_ranges(cond);
+                    self.zone_filters.insert(scan, ranges);
+                }
+                FilterExecutor {
+                    condition: self.resolve_column_index(cond, child),
+                }
+                .execute(self.build_id_with_visibility(child))
+            }
+
             Order([order_keys, child]) => OrderExecutor {
                 order_keys: self.resolve_column_index(order_keys, child),
                 types: self.plan_types(id).to_vec(),
+                budget: self.budget.clone(),
             }
             .execute(self.build_id(child)),
 
@@ -356,8 +603,9 @@ impl<S: Storage> Builder<S> {
                 t => panic!("invalid join type: {t:?}"),
             },
 
+            // apply should be rewritten to join by optimizer
             Apply(_) => {
-                panic!("Apply is not supported in executor. It should be rewritten to join by optimizer.")
+                futures::stream::once(async { Err(ExecutorError::correlated_subquery()) }).boxed()
             }
 
             Agg([aggs, child]) => SimpleAggExecutor {
@@ -370,6 +618,7 @@ impl<S: Storage> Builder<S> {
                 keys: self.resolve_column_index(keys, child),
                 aggs: self.resolve_column_index(aggs, child),
                 types: self.plan_types(id).to_vec(),
+                budget: self.budget.clone(),
             }
             .execute(self.build_id(child)),
 
@@ -386,16 +635,67 @@ impl<S: Storage> Builder<S> {
             }
             .execute(self.build_id(child)),
 
+            RecursiveUnion([_, base, recursive]) => {
+                let optimizer = self.optimizer.clone();
+                let storage = self.storage.clone();
+                let memory = self.budget.context().clone();
+                let recursive = self.recexpr(recursive);
+                RecursiveUnionExecutor {
+                    build_recursive: Box::new(move |working_table| {
+                        let mut builder = Builder::new(
+                            optimizer.clone(),
+                            storage.clone(),
+                            &recursive,
+                            memory.clone(),
+                        );
+                        builder.working_table = working_table;
+                        builder.build()
+                    }),
+                    max_iterations: self.optimizer.config().max_recursive_iterations,
+                }
+                .execute(self.build_id(base))
+            }
+
+            WorkingTable(_) => {
+                let chunks = std::mem::take(&mut self.working_table);
+                futures::stream::iter(chunks.into_iter().map(Ok)).boxed()
+            }
+
+            FileScan([src, _]) => CopyFromFileExecutor {
+                source: self.node(src).as_ext_source(),
+                types: self.plan_types(id).to_vec(),
+                tolerance: None,
+            }
+            .execute(),
+
+            ValuesScan([child, _]) => self.build_id(child),
+
+            GenerateSeries([args, _]) => GenerateSeriesExecutor {
+                args: self.recexpr(args),
+                ty: self.plan_types(id)[0].clone(),
+            }
+            .execute(),
+
             CreateTable(table) => CreateTableExecutor {
                 table,
                 storage: self.storage.clone(),
             }
             .execute(),
 
-            CreateView([table, query]) => CreateViewExecutor {
+            CreateTableAs([table, child]) => CreateTableAsExecutor {
                 table: self.node(table).as_create_table(),
-                query: self.recexpr(query),
                 catalog: self.catalog().clone(),
+                storage: self.storage.clone(),
+            }
+            .execute(self.build_id(child)),
+
+            CreateView([table, dependencies, query]) => CreateViewExecutor {
+                table: self.node(table).as_create_table(),
+                dependencies: (self.node(dependencies).as_list().iter())
+                    .map(|id| self.node(*id).as_table())
+                    .collect(),
+                query: self.recexpr(query),
+                storage: self.storage.clone(),
             }
             .execute(),
 
@@ -405,6 +705,50 @@ impl<S: Storage> Builder<S> {
             }
             .execute(),
 
+            DropFunction(f) => DropFunctionExecutor {
+                f,
+                catalog: self.optimizer.catalog().clone(),
+            }
+            .execute(),
+
+            CreateSchema(schema) => CreateSchemaExecutor {
+                schema,
+                catalog: self.optimizer.catalog().clone(),
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            DropSchema(schema) => DropSchemaExecutor {
+                schema,
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            AlterTable(table) => AlterTableExecutor {
+                table,
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            CreateIndex(index) => CreateIndexExecutor {
+                index,
+                catalog: self.catalog().clone(),
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            DropIndex(index) => DropIndexExecutor {
+                index,
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
+            Truncate(table) => TruncateExecutor {
+                table_id: self.node(table).as_table(),
+                storage: self.storage.clone(),
+            }
+            .execute(),
+
             Drop(tables) => DropExecutor {
                 tables: (self.node(tables).as_list().iter())
                     .map(|id| self.node(*id).as_table())
@@ -414,29 +758,92 @@ impl<S: Storage> Builder<S> {
             }
             .execute(),
 
-            Insert([table, cols, child]) => InsertExecutor {
-                table_id: self.node(table).as_table(),
-                column_ids: (self.node(cols).as_list().iter())
-                    .map(|id| self.node(*id).as_column().column_id)
-                    .collect(),
-                storage: self.storage.clone(),
+            Insert([table, cols, checks, returning, on_conflict, child]) => {
+                let table_id = self.node(table).as_table();
+                let upsert = match self.node(on_conflict).clone() {
+                    OnConflict([key, values, cond]) => Some(Upsert {
+                        key: (self.node(key).as_list().iter())
+                            .map(|id| self.node(*id).as_column().column_id)
+                            .collect(),
+                        update: (!self.node(values).as_list().is_empty())
+                            .then(|| (self.recexpr(values), self.recexpr(cond))),
+                    }),
+                    _ => None,
+                };
+                // `COPY FROM` skipping malformed rows returns a summary of the load
+                let max_errors = self.optimizer.config().copy_max_errors;
+                let tolerance = match self.node(child) {
+                    CopyFrom(_) if max_errors > 0 => Some(ErrorTolerance::new(max_errors)),
+                    _ => None,
+                };
+                let child_stream = match (self.node(child).clone(), &tolerance) {
+                    (CopyFrom([src, types]), Some(tolerance)) => CopyFromFileExecutor {
+                        source: self.node(src).as_ext_source(),
+                        types: self.node(types).as_type().as_struct().to_vec(),
+                        tolerance: Some(tolerance.clone()),
+                    }
+                    .execute(),
+                    _ => self.build_id(child),
+                };
+                let insert = InsertExecutor {
+                    table_id,
+                    column_ids: (self.node(cols).as_list().iter())
+                        .map(|id| self.node(*id).as_column().column_id)
+                        .collect(),
+                    constraints: self.table_constraints(table_id, checks),
+                    returning: (!self.node(returning).as_list().is_empty())
+                        .then(|| self.recexpr(returning)),
+                    upsert,
+                    referencing: self.referencing_tables(table_id),
+                    time_zone: self.optimizer.config().time_zone,
+                    storage: self.storage.clone(),
+                }
+                .execute(child_stream);
+                match tolerance {
+                    Some(tolerance) => tolerance.summarize(insert),
+                    None => insert,
+                }
             }
-            .execute(self.build_id(child)),
 
-            Delete([table, child]) => DeleteExecutor {
-                table_id: self.node(table).as_table(),
-                storage: self.storage.clone(),
+            Delete([table, returning, child]) => {
+                let table_id = self.node(table).as_table();
+                DeleteExecutor {
+                    table_id,
+                    referencing: self.referencing_tables(table_id),
+                    returning: (!self.node(returning).as_list().is_empty())
+                        .then(|| self.resolve_column_index(returning, child)),
+                    storage: self.storage.clone(),
+                }
+                .execute(self.build_id(child))
+            }
+
+            Update([table, checks, returning, child]) => {
+                let table_id = self.node(table).as_table();
+                // the child returns new values of all columns in the order of their ids
+                let table = self.catalog().get_table(&table_id).unwrap();
+                UpdateExecutor {
+                    table_id,
+                    column_ids: table.all_columns().into_keys().collect(),
+                    constraints: self.table_constraints(table_id, checks),
+                    referencing: self.referencing_tables(table_id),
+                    returning: (!self.node(returning).as_list().is_empty())
+                        .then(|| self.recexpr(returning)),
+                    storage: self.storage.clone(),
+                }
+                .execute(self.build_id(child))
             }
-            .execute(self.build_id(child)),
 
             CopyFrom([src, types]) => CopyFromFileExecutor {
                 source: self.node(src).as_ext_source(),
                 types: self.node(types).as_type().as_struct().to_vec(),
+                tolerance: None,
             }
             .execute(),
 
             CopyTo([src, child]) => CopyToFileExecutor {
                 source: self.node(src).as_ext_source(),
+                names: self.plan_names(child),
+                types: self.plan_types(child).to_vec(),
             }
             .execute(self.build_id(child)),
 
@@ -458,11 +865,75 @@ impl<S: Storage> Builder<S> {
 
             Empty(_) => futures::stream::empty().boxed(),
 
+            Exchange([dist, child]) => {
+                if !self.exchanges.contains_key(&id) {
+                    let streams = self.build_exchange(dist, child);
+                    let subscribers = (streams.into_iter())
+                        .map(|stream| self.spawn(id, stream))
+                        .collect();
+                    self.exchanges.insert(id, subscribers);
+                }
+                // an exchange to a single partition is built outside of partitions
+                let partitions = &self.exchanges[&id];
+                return partitions[self.partition.0 % partitions.len()].clone();
+            }
+
             node => panic!("not a plan: {node:?}"),
         };
         self.spawn(id, stream)
     }
 
+    /// Builds the output partitions of an exchange.
+    fn build_exchange(&mut self, dist: Id, child: Id) -> Vec<BoxedExecutor> {
+        let num_partitions = self.optimizer.config().parallelism;
+        if let (Expr::Random, Expr::Scan([table, _, _])) = (self.node(dist), self.node(child)) {
+            let table_id = self.node(*table).as_table();
+            if !(self.catalog().get_table(&table_id)).is_some_and(|t| t.is_system()) {
+                // tables are partitioned by the storage
+                return self.build_partitions(child, num_partitions);
+            }
+        }
+        let dist = match self.node(dist).clone() {
+            Expr::Single => Distribution::Single,
+            Expr::Random => Distribution::Random,
+            Expr::Hash(keys) => Distribution::Hash(self.resolve_column_index(keys, child)),
+            node => panic!("not a distribution: {node}"),
+        };
+        let inputs = self.build_partitions(child, self.num_partitions(child));
+        ExchangeExecutor {
+            dist,
+            num_partitions,
+        }
+        .execute(inputs)
+    }
+
+    /// Builds the executor of each partition.
+    fn build_partitions(&mut self, id: Id, num_partitions: usize) -> Vec<BoxedExecutor> {
+        let partition = self.partition;
+        let streams = (0..num_partitions)
+            .map(|i| {
+                self.partition = (i, num_partitions);
+                self.build_id(id)
+            })
+            .collect();
+        self.partition = partition;
+        streams
+    }
+
+    /// Returns the number of output partitions of a plan.
+    fn num_partitions(&self, id: Id) -> usize {
+        match self.node(id) {
+            Expr::Exchange([dist, _]) => match self.node(*dist) {
+                Expr::Single => 1,
+                _ => self.optimizer.config().parallelism,
+            },
+            node => (node.children().iter())
+                .map(|child| self.num_partitions(*child))
+                .max()
+                .unwrap_or(1),
+        }
+    }
+
     fn build_hashjoin<const T: JoinType>(&mut self, args: [Id; 6]) -> BoxedExecutor {
         let [_, cond, lkeys, rkeys, left, right] = args;
         assert_eq!(self.node(cond), &Expr::true_());
@@ -471,6 +942,7 @@ impl<S: Storage> Builder<S> {
             right_keys: self.resolve_column_index(rkeys, right),
             left_types: self.plan_types(left).to_vec(),
             right_types: self.plan_types(right).to_vec(),
+            budget: self.budget.clone(),
         }
         .execute(self.build_id(left), self.build_id(right))
     }
@@ -483,6 +955,7 @@ impl<S: Storage> Builder<S> {
                 right_keys: self.resolve_column_index(rkeys, right),
                 left_types: self.plan_types(left).to_vec(),
                 anti,
+                budget: self.budget.clone(),
             }
             .execute(self.build_id(left), self.build_id(right))
         } else {
@@ -493,6 +966,7 @@ impl<S: Storage> Builder<S> {
                 left_types: self.plan_types(left).to_vec(),
                 right_types: self.plan_types(right).to_vec(),
                 anti,
+                budget: self.budget.clone(),
             }
             .execute(self.build_id(left), self.build_id(right))
         }
@@ -513,20 +987,21 @@ impl<S: Storage> Builder<S> {
     /// Spawn a new task to execute the given stream.
     fn spawn(&mut self, id: Id, mut stream: BoxedExecutor) -> StreamSubscriber {
         let name = self.node(id).to_string();
-        let span = TimeSpan::default();
-        let output_row_counter = Counter::default();
-
-        self.metrics
-            .register(id, span.clone(), output_row_counter.clone());
+        // partitions of a node share the metrics
+        let (span, output_row_counter, output_chunk_counter) = self.metrics.register(id);
 
         let (tx, rx) = async_broadcast::broadcast(16);
+        metrics::EXECUTORS.inc();
         let handle = tokio::task::Builder::default()
             .name(&format!("{id}.{name}"))
             .spawn(
                 async move {
                     while let Some(item) = stream.next().await {
                         if let Ok(chunk) = &item {
-                            output_row_counter.inc(chunk.cardinality() as _);
+                            output_row_counter.inc(chunk.visible_cardinality() as _);
+                            output_chunk_counter.inc(1);
+                            metrics::EXECUTOR_ROWS.inc_by(chunk.visible_cardinality() as _);
+                            metrics::EXECUTOR_CHUNKS.inc();
                         }
                         if tx.broadcast(item).await.is_err() {
                             // all receivers are dropped, stop the task.
@@ -546,9 +1021,38 @@ impl<S: Storage> Builder<S> {
     }
 }
 
+/// Resolves columns of a table in `expr` to their positions in `columns`.
+fn resolve_table_columns(expr: &RecExpr, columns: &[ColumnCatalog]) -> RecExpr {
+    let nodes = (expr.as_ref().iter())
+        .map(|node| match node {
+            Expr::Column(c) => {
+                let index = columns.iter().position(|col| col.id() == c.column_id);
+                Expr::ColumnIndex(ColumnIndex(index.unwrap() as _))
+            }
+            node => node.clone(),
+        })
+        .collect_vec();
+    RecExpr::from(nodes)
+}
+
+/// Counts the parents of each node in the plan rooted at `root`.
+fn count_refs(egraph: &egg::EGraph<Expr, TypeSchemaAnalysis>, root: Id) -> HashMap<Id, usize> {
+    let mut refs = HashMap::new();
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        let count = refs.entry(id).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            stack.extend_from_slice(egraph[id].nodes[0].children());
+        }
+    }
+    refs
+}
+
 /// A subscriber of an executor's output stream.
 ///
 /// New streams can be created by calling `subscribe`.
+#[derive(Clone)]
 struct StreamSubscriber {
     rx: async_broadcast::InactiveReceiver<Result<DataChunk>>,
     handle: Arc<AbortOnDropHandle>,
@@ -22,9 +22,6 @@ pub struct NestedLoopJoinExecutor {
 impl NestedLoopJoinExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, left_child: BoxedExecutor, right_child: BoxedExecutor) {
-        if !matches!(self.op, Expr::Inner | Expr::LeftOuter) {
-            todo!("unsupported join type: {:?}", self.op);
-        }
         let left_chunks = left_child.try_collect::<Vec<DataChunk>>().await?;
 
         let left_rows = || left_chunks.iter().flat_map(|chunk| chunk.rows());
@@ -33,6 +30,8 @@ impl NestedLoopJoinExecutor {
         let mut builder = DataChunkBuilder::new(data_types, PROCESSING_WINDOW_SIZE);
         let mut filter_builder = BoolArrayBuilder::with_capacity(PROCESSING_WINDOW_SIZE);
 
+        // right rows are kept to find the unmatched ones for right outer join
+        let mut right_chunks = vec![];
         let mut right_row_num = 0;
         // inner join: left x right
         #[for_await]
@@ -54,6 +53,9 @@ impl NestedLoopJoinExecutor {
                 }
             }
             right_row_num += right_chunk.cardinality();
+            if matches!(self.op, Expr::RightOuter | Expr::FullOuter) {
+                right_chunks.push(right_chunk);
+            }
         }
 
         // take rest of data
@@ -67,10 +69,11 @@ impl NestedLoopJoinExecutor {
         }
         let filter = filter_builder.take();
 
+        let left_row_num = left_rows().count();
+
         // append rows for left outer join
-        if matches!(self.op, Expr::LeftOuter) {
+        if matches!(self.op, Expr::LeftOuter | Expr::FullOuter) {
             // we need to pick row of left_row which unmatched rows
-            let left_row_num = left_rows().count();
             for (mut i, left_row) in left_rows().enumerate() {
                 let mut matched = false;
                 for _ in 0..right_row_num {
@@ -93,6 +96,26 @@ impl NestedLoopJoinExecutor {
             }
         }
 
+        // append rows for right outer join
+        if matches!(self.op, Expr::RightOuter | Expr::FullOuter) {
+            let right_rows = right_chunks.iter().flat_map(|chunk| chunk.rows());
+            for (j, right_row) in right_rows.enumerate() {
+                // the results of the j-th right row are `filter[j * left_row_num..]`
+                let matched = (j * left_row_num..(j + 1) * left_row_num)
+                    .any(|i| matches!(filter.get(i), Some(true)));
+                if matched {
+                    continue;
+                }
+                // append row: (NULL, right)
+                let values =
+                    (self.left_types.iter().map(|_| DataValue::Null)).chain(right_row.values());
+                if let Some(chunk) = builder.push_row(values) {
+                    yield chunk;
+                }
+                tokio::task::consume_budget().await;
+            }
+        }
+
         if let Some(chunk) = builder.take() {
             yield chunk;
         }
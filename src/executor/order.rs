@@ -2,43 +2,87 @@
 
 use std::cmp::Ordering;
 
+use binary_heap_plus::BinaryHeap;
+
 use super::*;
 use crate::array::{DataChunk, DataChunkBuilder, RowRef};
-use crate::types::DataType;
+use crate::types::{DataType, Row};
 
 /// The executor of an order operation.
+///
+/// If the input exceeds the memory budget, sorted runs of rows are spilled to temporary files,
+/// and merged after all input is consumed.
 pub struct OrderExecutor {
     /// A list of expressions to order by.
     ///
     /// e.g. `(list (+ #0 #1) (desc #0))`
     pub order_keys: RecExpr,
     pub types: Vec<DataType>,
+    pub budget: MemoryBudget,
 }
 
 impl OrderExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
+        let orders = Evaluator::new(&self.order_keys).orders();
+
         // evaluate order keys and append the original rows
         // chunks = keys || child
         let mut chunks = vec![];
+        let mut reservation = MemoryReservation::new(&self.budget, "order");
+        let mut runs = vec![];
         #[for_await]
         for chunk in child {
             let chunk = chunk?;
             let order_key_chunk = Evaluator::new(&self.order_keys).eval_list(&chunk)?;
-            chunks.push(order_key_chunk.row_concat(chunk));
+            let chunk = order_key_chunk.row_concat(chunk);
+            let size = chunk.estimated_size();
+            chunks.push(chunk);
+            if !reservation.grow(size)? {
+                runs.push(spill_run(&chunks, &orders)?);
+                chunks.clear();
+                reservation.clear();
+            }
         }
 
-        // sort the rows by keys
-        let mut rows = gen_row_array(&chunks);
-        let orders = Evaluator::new(&self.order_keys).orders();
-        rows.sort_unstable_by(|row1, row2| cmp(row1, row2, &orders));
-
-        // build chunk by the new order
         let order_keys_len = self.order_keys.as_ref().last().unwrap().as_list().len();
         let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
-        for row in rows {
-            if let Some(chunk) = builder.push_row(row.values().skip(order_keys_len)) {
-                yield chunk;
+        if runs.is_empty() {
+            // sort the rows by keys
+            let mut rows = gen_row_array(&chunks);
+            rows.sort_unstable_by(|row1, row2| cmp(row1, row2, &orders));
+
+            // build chunk by the new order
+            for row in rows {
+                if let Some(chunk) = builder.push_row(row.values().skip(order_keys_len)) {
+                    yield chunk;
+                }
+            }
+        } else {
+            if !chunks.is_empty() {
+                runs.push(spill_run(&chunks, &orders)?);
+                chunks.clear();
+                reservation.clear();
+            }
+
+            // merge sorted runs with a min-heap of the next row in each run
+            let mut runs: Vec<_> = runs.into_iter().map(|run| run.finish()).try_collect()?;
+            let mut heap = BinaryHeap::with_capacity_by(
+                runs.len(),
+                |(row1, _): &(Row, usize), (row2, _): &(Row, usize)| cmp_rows(row2, row1, &orders),
+            );
+            for (i, run) in runs.iter_mut().enumerate() {
+                if let Some(row) = run.next() {
+                    heap.push((row?, i));
+                }
+            }
+            while let Some((row, i)) = heap.pop() {
+                if let Some(next) = runs[i].next() {
+                    heap.push((next?, i));
+                }
+                if let Some(chunk) = builder.push_row(row.into_iter().skip(order_keys_len)) {
+                    yield chunk;
+                }
             }
         }
         if let Some(chunk) = builder.take() {
@@ -47,14 +91,33 @@ impl OrderExecutor {
     }
 }
 
+/// Sorts rows in the chunks and spills them to a temporary file.
+fn spill_run(chunks: &[DataChunk], orders: &[SortOrder]) -> Result<SpillWriter<Row>> {
+    let mut rows = gen_row_array(chunks);
+    rows.sort_unstable_by(|row1, row2| cmp(row1, row2, orders));
+    let mut run = SpillWriter::new()?;
+    for row in rows {
+        run.write(&row.to_owned())?;
+    }
+    Ok(run)
+}
+
 /// Compare two rows by orders.
-///
-/// The order is `false` for ascending and `true` for descending.
-fn cmp(row1: &RowRef, row2: &RowRef, orders: &[bool]) -> Ordering {
-    for ((v1, v2), desc) in row1.values().zip(row2.values()).zip(orders) {
-        match v1.cmp(&v2) {
+pub fn cmp(row1: &RowRef, row2: &RowRef, orders: &[SortOrder]) -> Ordering {
+    for ((v1, v2), order) in row1.values().zip(row2.values()).zip(orders) {
+        match order.cmp(&v1, &v2) {
+            Ordering::Equal => continue,
+            o => return o,
+        }
+    }
+    Ordering::Equal
+}
+
+/// Compare two owned rows by orders.
+fn cmp_rows(row1: &Row, row2: &Row, orders: &[SortOrder]) -> Ordering {
+    for ((v1, v2), order) in row1.iter().zip(row2.iter()).zip(orders) {
+        match order.cmp(v1, v2) {
             Ordering::Equal => continue,
-            o if *desc => return o.reverse(),
             o => return o,
         }
     }
@@ -62,6 +125,6 @@ fn cmp(row1: &RowRef, row2: &RowRef, orders: &[bool]) -> Ordering {
 }
 
 /// Generate an array of rows for the chunks.
-fn gen_row_array(chunks: &[DataChunk]) -> Vec<RowRef<'_>> {
+pub fn gen_row_array(chunks: &[DataChunk]) -> Vec<RowRef<'_>> {
     chunks.iter().flat_map(|chunk| chunk.rows()).collect()
 }
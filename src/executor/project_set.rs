@@ -0,0 +1,65 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::*;
+use crate::array::{ArrayBuilderImpl, DataChunk};
+use crate::types::DataValue;
+
+/// The executor of project set operation.
+///
+/// Each `unnest` expression expands a list into multiple rows.
+/// Other expressions are repeated for each expanded row.
+pub struct ProjectSetExecutor {
+    /// A list of expressions.
+    ///
+    /// e.g. `(list (unnest #0) #1)`
+    pub exprs: RecExpr,
+    /// The output types.
+    pub types: Vec<DataType>,
+}
+
+impl ProjectSetExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, child: BoxedExecutor) {
+        let evaluator = Evaluator::new(&self.exprs);
+        let is_unnest = (evaluator.list())
+            .map(|e| matches!(e.node(), Expr::Unnest(_)))
+            .collect_vec();
+
+        #[for_await]
+        for batch in child {
+            let batch = batch?;
+            // for `unnest`, the array contains the lists to be expanded
+            let chunk = evaluator.eval_list(&batch)?;
+            let mut builders = (self.types.iter())
+                .map(|ty| ArrayBuilderImpl::with_capacity(batch.cardinality(), ty))
+                .collect_vec();
+            for row in 0..batch.cardinality() {
+                let values = chunk.arrays().iter().map(|a| a.get(row)).collect_vec();
+                // the number of output rows is the length of the longest list
+                let len = (values.iter().zip(&is_unnest))
+                    .filter(|(_, unnest)| **unnest)
+                    .map(|(v, _)| match v {
+                        DataValue::List(list) => list.len(),
+                        _ => 0,
+                    })
+                    .max()
+                    .unwrap_or(1);
+                for i in 0..len {
+                    for ((builder, value), unnest) in
+                        builders.iter_mut().zip(&values).zip(&is_unnest)
+                    {
+                        match value {
+                            // shorter lists are padded with nulls
+                            DataValue::List(list) if *unnest => {
+                                builder.push(list.get(i).unwrap_or(&DataValue::Null))
+                            }
+                            _ if *unnest => builder.push(&DataValue::Null),
+                            value => builder.push(value),
+                        }
+                    }
+                }
+            }
+            yield builders.into_iter().collect();
+        }
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use super::*;
+
+/// The executor of a recursive CTE.
+///
+/// It outputs the rows of the base term, then repeatedly runs the recursive term on the rows
+/// produced by the last iteration until no more rows are produced.
+pub struct RecursiveUnionExecutor {
+    /// Builds the executor of the recursive term on the given working table.
+    pub build_recursive: Box<dyn Fn(Vec<DataChunk>) -> BoxedExecutor + Send + Sync>,
+    /// The maximum number of iterations.
+    pub max_iterations: usize,
+}
+
+impl RecursiveUnionExecutor {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, base: BoxedExecutor) {
+        let mut working_table = vec![];
+        #[for_await]
+        for chunk in base {
+            let chunk = chunk?;
+            working_table.push(chunk.clone());
+            yield chunk;
+        }
+
+        let mut iterations = 0;
+        while working_table.iter().any(|chunk| chunk.cardinality() != 0) {
+            if iterations == self.max_iterations {
+                return Err(ExecutorError::recursion_limit(self.max_iterations));
+            }
+            iterations += 1;
+
+            let recursive = (self.build_recursive)(std::mem::take(&mut working_table));
+            #[for_await]
+            for chunk in recursive {
+                let chunk = chunk?;
+                working_table.push(chunk.clone());
+                yield chunk;
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Temporary files for executors spilling data to disk when exceeding the memory limit.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::*;
+
+/// The memory budget of a query, shared by executors holding data in memory.
+///
+/// Executors that can spill to disk start spilling when the budget is exceeded.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    /// The maximum memory in bytes. Unlimited if `None`.
+    limit: Option<usize>,
+    used: Arc<AtomicUsize>,
+    /// The memory context of the query.
+    context: MemoryContext,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: Option<usize>, context: MemoryContext) -> Self {
+        MemoryBudget {
+            limit,
+            used: Arc::new(AtomicUsize::new(0)),
+            context,
+        }
+    }
+
+    /// Returns the memory context of the query.
+    pub fn context(&self) -> &MemoryContext {
+        &self.context
+    }
+
+    /// Reserves memory. Returns `false` if the budget is exceeded after the reservation.
+    pub fn reserve(&self, size: usize) -> bool {
+        let used = self.used.fetch_add(size, Ordering::Relaxed) + size;
+        self.limit.map_or(true, |limit| used <= limit)
+    }
+
+    /// Releases memory reserved before.
+    pub fn release(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+/// Memory reserved from a budget by an executor, which is released when dropped.
+///
+/// The memory is tracked in a memory context of the executor under the context of the query.
+pub struct MemoryReservation {
+    budget: MemoryBudget,
+    context: MemoryContext,
+    size: usize,
+}
+
+impl MemoryReservation {
+    pub fn new(budget: &MemoryBudget, name: &str) -> Self {
+        MemoryReservation {
+            budget: budget.clone(),
+            context: budget.context.child(name, None),
+            size: 0,
+        }
+    }
+
+    /// Reserves more memory. Returns `false` if the budget is exceeded after the reservation.
+    ///
+    /// Returns an out-of-memory error if the memory limit of the query is exceeded.
+    pub fn grow(&mut self, size: usize) -> Result<bool> {
+        self.context.alloc(size)?;
+        self.size += size;
+        Ok(self.budget.reserve(size))
+    }
+
+    /// Releases all memory reserved.
+    pub fn clear(&mut self) {
+        self.context.free(self.size);
+        self.budget.release(self.size);
+        self.size = 0;
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// A writer of items to an anonymous temporary file.
+///
+/// The file is removed when it is closed, including when the query is cancelled and the
+/// executor is dropped.
+pub struct SpillWriter<T> {
+    writer: BufWriter<File>,
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> SpillWriter<T> {
+    /// Creates a temporary file.
+    pub fn new() -> Result<Self> {
+        metrics::SPILL_FILES.inc();
+        Ok(SpillWriter {
+            writer: BufWriter::new(tempfile::tempfile()?),
+            len: 0,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Appends an item to the file.
+    pub fn write(&mut self, item: &T) -> Result<()> {
+        bincode::serialize_into(&mut self.writer, item)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns `true` if no item is written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Finishes writing and returns an iterator over the items in the file.
+    pub fn finish(mut self) -> Result<impl Iterator<Item = Result<T>>> {
+        self.writer.flush()?;
+        let mut file = self.writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(file);
+        Ok((0..self.len)
+            .map(move |_| bincode::deserialize_from(&mut reader).map_err(ExecutorError::from)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DataValue;
+
+    #[test]
+    fn test_memory_budget() {
+        let context = MemoryContext::new_root("query");
+        let budget = MemoryBudget::new(Some(100), context.clone());
+        assert!(budget.reserve(60));
+        assert!(!budget.clone().reserve(60));
+        budget.release(60);
+        assert!(budget.reserve(40));
+        assert!(!budget.reserve(1));
+        budget.release(101);
+
+        let mut reservation = MemoryReservation::new(&budget, "executor");
+        assert!(reservation.grow(100).unwrap());
+        assert_eq!(context.used(), 100);
+        drop(reservation);
+        assert_eq!(context.used(), 0);
+        assert!(budget.reserve(100));
+    }
+
+    #[test]
+    fn test_spill() {
+        let rows = vec![
+            vec![DataValue::Int32(1), DataValue::String("a".into())],
+            vec![DataValue::Null, DataValue::String("b\n".into())],
+            vec![
+                DataValue::Float64(f64::NAN.into()),
+                DataValue::Float64(f64::INFINITY.into()),
+            ],
+            vec![
+                DataValue::Decimal("1.50".parse().unwrap()),
+                DataValue::Date("2024-01-01".parse().unwrap()),
+            ],
+        ];
+        let mut writer = SpillWriter::new().unwrap();
+        for row in &rows {
+            writer.write(row).unwrap();
+        }
+        assert!(!writer.is_empty());
+        let items = writer.finish().unwrap();
+        assert_eq!(items.map(|row| row.unwrap()).collect_vec(), rows);
+    }
+}
@@ -4,8 +4,11 @@ use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
 
 use super::*;
 use crate::array::*;
-use crate::catalog::{ColumnRefId, RootCatalogRef, TableRefId};
+use crate::catalog::{
+    ColumnRefId, RootCatalog, RootCatalogRef, SchemaCatalog, TableCatalog, TableRefId,
+};
 use crate::storage::{Storage, StorageColumnRef, Table};
+use crate::types::{DataValue, Timestamp};
 
 /// Scan a system table.
 pub struct SystemTableScan<S: Storage> {
@@ -13,6 +16,8 @@ pub struct SystemTableScan<S: Storage> {
     pub storage: Arc<S>,
     pub table_id: TableRefId,
     pub columns: Vec<ColumnRefId>,
+    /// The memory context of the query.
+    pub memory: MemoryContext,
 }
 
 impl<S: Storage> SystemTableScan<S> {
@@ -24,11 +29,23 @@ impl<S: Storage> SystemTableScan<S> {
             .expect("table not found");
         assert_eq!(self.columns.len(), table.all_columns().len());
 
+        // names of system tables are unique across `pg_catalog` and `information_schema`
         yield match table.name() {
             "contributors" => contributors(),
             "pg_tables" => pg_tables(self.catalog),
             "pg_attribute" => pg_attribute(self.catalog),
             "pg_stat" => pg_stat(self.catalog, &*self.storage).await?,
+            "pg_stat_bloom_filter" => pg_stat_bloom_filter(self.catalog, &*self.storage)?,
+            "pg_plan_cache" => pg_plan_cache(self.catalog),
+            "pg_stats" => pg_stats(self.catalog),
+            "pg_functions" => pg_functions(self.catalog),
+            "pg_namespace" => pg_namespace(self.catalog),
+            "pg_class" => pg_class(self.catalog),
+            "pg_memory_usage" => pg_memory_usage(&self.memory),
+            "pg_snapshots" => pg_snapshots(&*self.storage),
+            "schemata" => information_schema_schemata(self.catalog),
+            "tables" => information_schema_tables(self.catalog),
+            "columns" => information_schema_columns(self.catalog),
             name => panic!("unknown system table: {:?}", name),
         };
     }
@@ -184,22 +201,22 @@ async fn pg_stat(catalog: RootCatalogRef, storage: &impl Storage) -> Result<Data
 
     if let Some(storage) = storage.as_disk() {
         for (sid, schema) in catalog.all_schemas() {
-            if sid == RootCatalog::SYSTEM_SCHEMA_ID {
-                continue;
-            }
             for (tid, table) in schema.all_tables() {
-                if table.is_view() {
+                if table.is_view() || table.is_system() {
                     continue;
                 }
                 let stable = storage.get_table(TableRefId::new(sid, tid))?;
 
-                for (cid, column) in table.all_columns() {
+                for (idx, column) in table.all_columns().into_values().enumerate() {
                     let txn = stable.read().await?;
                     let values = txn.aggreagate_block_stat(&[
-                        (BlockStatisticsType::RowCount, StorageColumnRef::Idx(cid)),
+                        (
+                            BlockStatisticsType::RowCount,
+                            StorageColumnRef::Idx(idx as u32),
+                        ),
                         (
                             BlockStatisticsType::DistinctValue,
-                            StorageColumnRef::Idx(cid),
+                            StorageColumnRef::Idx(idx as u32),
                         ),
                     ]);
                     let row = values[0].as_usize().unwrap().unwrap() as i32;
@@ -225,3 +242,323 @@ async fn pg_stat(catalog: RootCatalogRef, storage: &impl Storage) -> Result<Data
         n_distinct.into(),
     ]))
 }
+
+/// Returns `pg_stat_bloom_filter` table.
+fn pg_stat_bloom_filter(catalog: RootCatalogRef, storage: &impl Storage) -> Result<DataChunk> {
+    let mut schema_name = StringArrayBuilder::new();
+    let mut table_name = StringArrayBuilder::new();
+    let mut n_checked = I32ArrayBuilder::new();
+    let mut n_pruned = I32ArrayBuilder::new();
+
+    if let Some(storage) = storage.as_disk() {
+        for (sid, schema) in catalog.all_schemas() {
+            for (tid, table) in schema.all_tables() {
+                if table.is_view() || table.is_system() {
+                    continue;
+                }
+                let stable = storage.get_table(TableRefId::new(sid, tid))?;
+                let (checked, pruned) = stable.bloom_filter_stats();
+
+                schema_name.push(Some(&schema.name()));
+                table_name.push(Some(table.name()));
+                n_checked.push(Some(&(checked as i32)));
+                n_pruned.push(Some(&(pruned as i32)));
+            }
+        }
+    }
+    Ok(DataChunk::from_iter([
+        ArrayBuilderImpl::from(schema_name),
+        table_name.into(),
+        n_checked.into(),
+        n_pruned.into(),
+    ]))
+}
+
+/// Returns `pg_plan_cache` table.
+fn pg_plan_cache(catalog: RootCatalogRef) -> DataChunk {
+    let (entries, hits, misses, invalidations) = catalog.plan_cache_stats().get();
+    [entries, hits, misses, invalidations]
+        .into_iter()
+        .map(|n| ArrayImpl::new_int32([n as i32].into_iter().collect()))
+        .collect()
+}
+
+/// Returns `pg_stats` table, the statistics collected by `ANALYZE`.
+fn pg_stats(catalog: RootCatalogRef) -> DataChunk {
+    let mut schema_name = StringArrayBuilder::new();
+    let mut table_name = StringArrayBuilder::new();
+    let mut column_name = StringArrayBuilder::new();
+    let mut n_row = I32ArrayBuilder::new();
+    let mut null_frac = F64ArrayBuilder::new();
+    let mut n_distinct = I32ArrayBuilder::new();
+    let mut min_value = StringArrayBuilder::new();
+    let mut max_value = StringArrayBuilder::new();
+    let mut histogram_bounds = StringArrayBuilder::new();
+
+    let value_to_string = |value: &DataValue| match value {
+        DataValue::Null => None,
+        DataValue::String(s) => Some(s.to_string()),
+        value => Some(value.to_string()),
+    };
+    for (table_id, stats) in catalog
+        .all_statistics()
+        .into_iter()
+        .sorted_by_key(|(id, _)| *id)
+    {
+        let (Some(schema), Some(table)) = (
+            catalog.get_schema_by_id(table_id.schema_id),
+            catalog.get_table(&table_id),
+        ) else {
+            continue;
+        };
+        for (column_id, column) in table.all_columns() {
+            let Some(column_stats) = stats.columns.get(&column_id) else {
+                continue;
+            };
+            schema_name.push(Some(&schema.name()));
+            table_name.push(Some(table.name()));
+            column_name.push(Some(column.name()));
+            n_row.push(Some(&(stats.row_count as i32)));
+            null_frac.push(Some(&column_stats.null_fraction.into()));
+            n_distinct.push(Some(&(column_stats.distinct_count as i32)));
+            min_value.push(value_to_string(&column_stats.min).as_deref());
+            max_value.push(value_to_string(&column_stats.max).as_deref());
+            let bounds = (column_stats.histogram.iter())
+                .filter_map(value_to_string)
+                .join(",");
+            histogram_bounds.push(Some(&format!("{{{bounds}}}")));
+        }
+    }
+    DataChunk::from_iter([
+        ArrayBuilderImpl::from(schema_name),
+        table_name.into(),
+        column_name.into(),
+        n_row.into(),
+        null_frac.into(),
+        n_distinct.into(),
+        min_value.into(),
+        max_value.into(),
+        histogram_bounds.into(),
+    ])
+}
+
+/// Returns `pg_functions` table, the functions created by `CREATE FUNCTION`.
+fn pg_functions(catalog: RootCatalogRef) -> DataChunk {
+    let mut schema_name = StringArrayBuilder::new();
+    let mut function_name = StringArrayBuilder::new();
+    let mut arg_types = StringArrayBuilder::new();
+    let mut return_type = StringArrayBuilder::new();
+    let mut language = StringArrayBuilder::new();
+    let mut body = StringArrayBuilder::new();
+
+    for (_, schema) in catalog
+        .all_schemas()
+        .into_iter()
+        .sorted_by_key(|(id, _)| *id)
+    {
+        for (_, function) in schema
+            .all_functions()
+            .into_iter()
+            .sorted_by(|a, b| a.0.cmp(&b.0))
+        {
+            let types = (function.arg_types.iter())
+                .map(|ty| ty.to_string().to_ascii_lowercase())
+                .join(",");
+            schema_name.push(Some(&schema.name()));
+            function_name.push(Some(&function.name));
+            arg_types.push(Some(&types));
+            return_type.push(Some(&function.return_type.to_string().to_ascii_lowercase()));
+            language.push(Some(&function.language));
+            body.push(Some(&function.body));
+        }
+    }
+    DataChunk::from_iter([
+        ArrayBuilderImpl::from(schema_name),
+        function_name.into(),
+        arg_types.into(),
+        return_type.into(),
+        language.into(),
+        body.into(),
+    ])
+}
+
+/// Returns all schemas and their tables, ordered by ids.
+fn sorted_tables(catalog: &RootCatalogRef) -> Vec<(SchemaCatalog, Arc<TableCatalog>)> {
+    let mut tables = vec![];
+    for (_, schema) in catalog
+        .all_schemas()
+        .into_iter()
+        .sorted_by_key(|(id, _)| *id)
+    {
+        for (_, table) in schema.all_tables().into_iter().sorted_by_key(|(id, _)| *id) {
+            tables.push((schema.clone(), table));
+        }
+    }
+    tables
+}
+
+/// Returns the kind of a table as in `pg_class.relkind`.
+fn relkind(table: &TableCatalog) -> &'static str {
+    if table.is_view() {
+        "v"
+    } else {
+        "r"
+    }
+}
+
+/// Returns `pg_namespace` table.
+fn pg_namespace(catalog: RootCatalogRef) -> DataChunk {
+    let mut oid = I32ArrayBuilder::new();
+    let mut nspname = StringArrayBuilder::new();
+
+    for (id, schema) in catalog
+        .all_schemas()
+        .into_iter()
+        .sorted_by_key(|(id, _)| *id)
+    {
+        oid.push(Some(&(id as i32)));
+        nspname.push(Some(&schema.name()));
+    }
+    DataChunk::from_iter([ArrayBuilderImpl::from(oid), nspname.into()])
+}
+
+/// Returns `pg_class` table.
+///
+/// The oid of a table is composed of its schema id in the high 16 bits and table id in the low.
+fn pg_class(catalog: RootCatalogRef) -> DataChunk {
+    let mut oid = I32ArrayBuilder::new();
+    let mut relname = StringArrayBuilder::new();
+    let mut relnamespace = I32ArrayBuilder::new();
+    let mut relkind_ = StringArrayBuilder::new();
+
+    for (schema, table) in sorted_tables(&catalog) {
+        oid.push(Some(&(((schema.id() << 16) | table.id()) as i32)));
+        relname.push(Some(table.name()));
+        relnamespace.push(Some(&(schema.id() as i32)));
+        relkind_.push(Some(relkind(&table)));
+    }
+    DataChunk::from_iter([
+        ArrayBuilderImpl::from(oid),
+        relname.into(),
+        relnamespace.into(),
+        relkind_.into(),
+    ])
+}
+
+/// Returns `pg_memory_usage` table, the memory used by the database, running queries and their
+/// executors.
+fn pg_memory_usage(memory: &MemoryContext) -> DataChunk {
+    let mut id = I32ArrayBuilder::new();
+    let mut parent_id = I32ArrayBuilder::new();
+    let mut name = StringArrayBuilder::new();
+    let mut used_bytes = I64ArrayBuilder::new();
+    let mut peak_bytes = I64ArrayBuilder::new();
+    let mut limit_bytes = I64ArrayBuilder::new();
+
+    for usage in memory.root().usages() {
+        id.push(Some(&(usage.id as i32)));
+        parent_id.push(usage.parent_id.map(|id| id as i32).as_ref());
+        name.push(Some(&usage.name));
+        used_bytes.push(Some(&(usage.used as i64)));
+        peak_bytes.push(Some(&(usage.peak as i64)));
+        limit_bytes.push(usage.limit.map(|n| n as i64).as_ref());
+    }
+    DataChunk::from_iter([
+        ArrayBuilderImpl::from(id),
+        parent_id.into(),
+        name.into(),
+        used_bytes.into(),
+        peak_bytes.into(),
+        limit_bytes.into(),
+    ])
+}
+
+/// Returns `pg_snapshots` table, the snapshots that can be read by time travel queries.
+fn pg_snapshots(storage: &impl Storage) -> DataChunk {
+    let mut epoch = I64ArrayBuilder::new();
+    let mut commit_time = TimestampArrayBuilder::new();
+
+    if let Some(storage) = storage.as_disk() {
+        for (e, time) in storage.snapshots() {
+            epoch.push(Some(&(e as i64)));
+            commit_time.push(Some(&Timestamp::from_unix_micros(time)));
+        }
+    }
+    DataChunk::from_iter([ArrayBuilderImpl::from(epoch), commit_time.into()])
+}
+
+/// Returns `information_schema.schemata` table.
+fn information_schema_schemata(catalog: RootCatalogRef) -> DataChunk {
+    let mut catalog_name = StringArrayBuilder::new();
+    let mut schema_name = StringArrayBuilder::new();
+
+    for (_, schema) in catalog
+        .all_schemas()
+        .into_iter()
+        .sorted_by_key(|(id, _)| *id)
+    {
+        catalog_name.push(Some(RootCatalog::DEFAULT_SCHEMA_NAME));
+        schema_name.push(Some(&schema.name()));
+    }
+    DataChunk::from_iter([ArrayBuilderImpl::from(catalog_name), schema_name.into()])
+}
+
+/// Returns `information_schema.tables` table.
+fn information_schema_tables(catalog: RootCatalogRef) -> DataChunk {
+    let mut table_catalog = StringArrayBuilder::new();
+    let mut table_schema = StringArrayBuilder::new();
+    let mut table_name = StringArrayBuilder::new();
+    let mut table_type = StringArrayBuilder::new();
+
+    for (schema, table) in sorted_tables(&catalog) {
+        let ty = if table.is_view() {
+            "VIEW"
+        } else if table.is_system() {
+            "SYSTEM TABLE"
+        } else {
+            "BASE TABLE"
+        };
+        table_catalog.push(Some(RootCatalog::DEFAULT_SCHEMA_NAME));
+        table_schema.push(Some(&schema.name()));
+        table_name.push(Some(table.name()));
+        table_type.push(Some(ty));
+    }
+    DataChunk::from_iter([
+        ArrayBuilderImpl::from(table_catalog),
+        table_schema.into(),
+        table_name.into(),
+        table_type.into(),
+    ])
+}
+
+/// Returns `information_schema.columns` table.
+fn information_schema_columns(catalog: RootCatalogRef) -> DataChunk {
+    let mut table_catalog = StringArrayBuilder::new();
+    let mut table_schema = StringArrayBuilder::new();
+    let mut table_name = StringArrayBuilder::new();
+    let mut column_name = StringArrayBuilder::new();
+    let mut ordinal_position = I32ArrayBuilder::new();
+    let mut is_nullable = StringArrayBuilder::new();
+    let mut data_type = StringArrayBuilder::new();
+
+    for (schema, table) in sorted_tables(&catalog) {
+        for (position, column) in table.all_columns().values().enumerate() {
+            table_catalog.push(Some(RootCatalog::DEFAULT_SCHEMA_NAME));
+            table_schema.push(Some(&schema.name()));
+            table_name.push(Some(table.name()));
+            column_name.push(Some(column.name()));
+            ordinal_position.push(Some(&(position as i32 + 1)));
+            is_nullable.push(Some(if column.is_nullable() { "YES" } else { "NO" }));
+            data_type.push(Some(&column.data_type().to_string().to_ascii_lowercase()));
+        }
+    }
+    DataChunk::from_iter([
+        ArrayBuilderImpl::from(table_catalog),
+        table_schema.into(),
+        table_name.into(),
+        column_name.into(),
+        ordinal_position.into(),
+        is_nullable.into(),
+        data_type.into(),
+    ])
+}
@@ -6,14 +6,25 @@ use super::*;
 use crate::array::DataChunk;
 use crate::catalog::{ColumnRefId, TableRefId};
 use crate::storage::{
-    KeyRange, ScanOptions, Storage, StorageColumnRef, Table, Transaction, TxnIterator,
+    AsOf, KeyRange, ScanOptions, Storage, StorageColumnRef, Table, TracedStorageError, Transaction,
+    TxnIterator,
 };
+use crate::types::{ConvertError, DataValue};
+
+/// The number of blocks read ahead of the current block of each column in scans.
+const PREFETCH_BLOCKS: usize = 4;
 
 /// The executor of table scan operation.
 pub struct TableScanExecutor<S: Storage> {
     pub table_id: TableRefId,
     pub columns: Vec<ColumnRefId>,
     pub filter: Option<KeyRange>,
+    /// Ranges of columns in the filter above the scan. Rows out of the ranges may be skipped.
+    pub zone_filters: Vec<(ColumnRefId, KeyRange)>,
+    /// The index of the partition to scan and the number of partitions.
+    pub partition: (usize, usize),
+    /// The epoch or timestamp of the version to read. The latest version is read if not set.
+    pub as_of: Option<RecExpr>,
     pub storage: Arc<S>,
 }
 
@@ -21,27 +32,59 @@ impl<S: Storage> TableScanExecutor<S> {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self) {
         let table = self.storage.get_table(self.table_id)?;
+        let table_columns = table.columns()?;
 
-        let mut col_idx = self
+        let mut col_idx: Vec<_> = self
             .columns
             .iter()
             .map(|x| match x.column_id {
-                u32::MAX => StorageColumnRef::RowHandler,
-                id => StorageColumnRef::Idx(id), // convert column id -> storage column idx
+                u32::MAX => Ok(StorageColumnRef::RowHandler),
+                // convert column id -> storage column idx
+                id => (table_columns.iter())
+                    .position(|c| c.id() == id)
+                    .map(|idx| StorageColumnRef::Idx(idx as u32))
+                    .ok_or_else(|| TracedStorageError::not_found("column", id)),
+            })
+            .try_collect()?;
+
+        let zone_filters = (self.zone_filters.into_iter())
+            .filter_map(|(column, range)| {
+                let idx = table_columns
+                    .iter()
+                    .position(|c| c.id() == column.column_id)?;
+                Some((idx as u32, range))
             })
-            .collect_vec();
+            .collect();
 
         // TODO: append row handler?
         if self.columns.is_empty() {
             col_idx.push(StorageColumnRef::RowHandler);
         }
 
-        let txn = table.read().await?;
+        let txn = match &self.as_of {
+            Some(version) => {
+                let version = Evaluator::new(version).eval(&DataChunk::single(0))?.get(0);
+                let as_of = match version {
+                    DataValue::Int16(v) if v >= 0 => AsOf::Epoch(v as u64),
+                    DataValue::Int32(v) if v >= 0 => AsOf::Epoch(v as u64),
+                    DataValue::Int64(v) if v >= 0 => AsOf::Epoch(v as u64),
+                    DataValue::Timestamp(v) => AsOf::Timestamp(v.to_unix_micros()),
+                    DataValue::TimestampTz(v) => AsOf::Timestamp(v.to_unix_micros()),
+                    v => return Err(ConvertError::InvalidArgument("AS OF", v).into()),
+                };
+                table.read_as_of(as_of).await?
+            }
+            None => table.read().await?,
+        };
 
         let mut it = txn
             .scan(
                 &col_idx,
-                ScanOptions::default().with_filter_opt(self.filter),
+                ScanOptions::default()
+                    .with_filter_opt(self.filter)
+                    .with_zone_filters(zone_filters)
+                    .with_partition(self.partition.0, self.partition.1)
+                    .with_prefetch(PREFETCH_BLOCKS),
             )
             .await?;
 
@@ -0,0 +1,73 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use tokio::sync::mpsc;
+
+use super::*;
+
+/// The executor that replicates the output of a subplan to multiple consumers.
+///
+/// Chunks are buffered for each consumer without bound, so that consumers can read at different
+/// paces, e.g. a hash join reading both sides from the same subplan. Buffered chunks are counted
+/// in the memory context.
+pub struct TeeExecutor {
+    pub memory: MemoryContext,
+}
+
+/// A chunk buffered for consumers. Its memory is freed when all consumers have read it.
+struct Buffered {
+    item: Result<DataChunk>,
+    size: usize,
+    memory: MemoryContext,
+}
+
+impl Drop for Buffered {
+    fn drop(&mut self) {
+        self.memory.free(self.size);
+    }
+}
+
+impl TeeExecutor {
+    /// Returns `n` output streams of the child.
+    pub fn execute(self, mut child: BoxedExecutor, n: usize) -> Vec<BoxedExecutor> {
+        let (txs, rxs): (Vec<_>, Vec<_>) = (0..n).map(|_| mpsc::unbounded_channel()).unzip();
+        let memory = self.memory;
+        let handle = tokio::spawn(async move {
+            while let Some(mut item) = child.next().await {
+                let mut size = item.as_ref().map_or(0, |chunk| chunk.estimated_size());
+                if let Err(e) = memory.alloc(size) {
+                    item = Err(e);
+                    size = 0;
+                }
+                let failed = item.is_err();
+                let buffered = Arc::new(Buffered {
+                    item,
+                    size,
+                    memory: memory.clone(),
+                });
+                let mut sent = false;
+                for tx in &txs {
+                    sent |= tx.send(buffered.clone()).is_ok();
+                }
+                if !sent || failed {
+                    // all consumers are dropped, or the error is the end of the stream
+                    return;
+                }
+            }
+        });
+        let handle = Arc::new(AbortOnDropHandle(handle));
+        (rxs.into_iter())
+            .map(|rx| Self::consume(rx, handle.clone()))
+            .collect()
+    }
+
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    async fn consume(
+        mut rx: mpsc::UnboundedReceiver<Arc<Buffered>>,
+        handle: Arc<AbortOnDropHandle>,
+    ) {
+        while let Some(buffered) = rx.recv().await {
+            yield buffered.item.clone()?;
+        }
+        drop(handle);
+    }
+}
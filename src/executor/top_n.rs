@@ -5,10 +5,12 @@ use std::cmp::Ordering;
 use binary_heap_plus::BinaryHeap;
 
 use super::*;
-use crate::array::{DataChunk, DataChunkBuilder};
+use crate::array::{DataChunk, DataChunkBuilder, RowRef};
 use crate::types::{DataType, Row};
 
 /// The executor of a Top N operation.
+///
+/// It keeps the first `offset + limit` rows in a bounded max-heap instead of sorting all rows.
 pub struct TopNExecutor {
     pub offset: usize,
     pub limit: usize,
@@ -23,21 +25,31 @@ impl TopNExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
         // initialize heap
-        let heap_size = self.offset + self.limit;
+        let heap_size = match self.limit {
+            0 => 0,
+            limit => self.offset.saturating_add(limit),
+        };
         let orders = Evaluator::new(&self.order_keys).orders();
-        let mut heap =
-            BinaryHeap::with_capacity_by(heap_size, |row1, row2| cmp(row1, row2, &orders));
+        let mut heap = BinaryHeap::with_capacity_by(
+            heap_size.min(PROCESSING_WINDOW_SIZE),
+            |row1: &Row, row2: &Row| cmp(row1, row2, &orders),
+        );
 
         // evaluate order keys and append the original rows
         // chunks = keys || child
         #[for_await]
         for chunk in child {
+            if heap_size == 0 {
+                break;
+            }
             let chunk = chunk?;
             let order_key_chunk = Evaluator::new(&self.order_keys).eval_list(&chunk)?;
             for row in order_key_chunk.row_concat(chunk).rows() {
-                heap.push(row.to_owned());
-                if heap.len() > heap_size {
-                    heap.pop();
+                if heap.len() < heap_size {
+                    heap.push(row.to_owned());
+                } else if cmp_ref(&row, heap.peek().unwrap(), &orders) == Ordering::Less {
+                    // replace the last row without copying rows that are not in the top N
+                    *heap.peek_mut().unwrap() = row.to_owned();
                 }
             }
         }
@@ -62,13 +74,21 @@ impl TopNExecutor {
 }
 
 /// Compare two rows by orders.
-///
-/// The order is `false` for ascending and `true` for descending.
-fn cmp(row1: &Row, row2: &Row, orders: &[bool]) -> Ordering {
-    for ((v1, v2), desc) in row1.iter().zip(row2.iter()).zip(orders) {
-        match v1.cmp(v2) {
+fn cmp(row1: &Row, row2: &Row, orders: &[SortOrder]) -> Ordering {
+    for ((v1, v2), order) in row1.iter().zip(row2.iter()).zip(orders) {
+        match order.cmp(v1, v2) {
+            Ordering::Equal => continue,
+            o => return o,
+        }
+    }
+    Ordering::Equal
+}
+
+/// Compare a row in a chunk with an owned row by orders.
+fn cmp_ref(row1: &RowRef<'_>, row2: &Row, orders: &[SortOrder]) -> Ordering {
+    for ((v1, v2), order) in row1.values().zip(row2.iter()).zip(orders) {
+        match order.cmp(&v1, v2) {
             Ordering::Equal => continue,
-            o if *desc => return o.reverse(),
             o => return o,
         }
     }
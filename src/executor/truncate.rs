@@ -0,0 +1,21 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use super::*;
+use crate::catalog::TableRefId;
+use crate::storage::Storage;
+
+/// The executor of `truncate` statement.
+pub struct TruncateExecutor<S: Storage> {
+    pub table_id: TableRefId,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> TruncateExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self) {
+        self.storage.truncate_table(self.table_id).await?;
+        yield DataChunk::single(1);
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::constraint::{check_references, ConstraintChecker, Referencing, TableConstraints};
+use super::*;
+use crate::array::DataChunk;
+use crate::catalog::{ColumnId, TableRefId};
+use crate::storage::{RowHandler, Storage, Table, Transaction};
+use crate::types::ColumnIndex;
+
+/// The executor of `update` statement.
+///
+/// Each row of the input data chunk contains the new values of `column_ids`, followed by
+/// `_row_id_` of the row to replace. Returns the number of updated rows, or the `returning`
+/// expressions on the new rows.
+pub struct UpdateExecutor<S: Storage> {
+    pub table_id: TableRefId,
+    pub column_ids: Vec<ColumnId>,
+    pub constraints: TableConstraints,
+    /// Foreign keys referencing the table.
+    pub referencing: Vec<Referencing>,
+    /// Expressions on columns of the table.
+    pub returning: Option<RecExpr>,
+    pub storage: Arc<S>,
+}
+
+impl<S: Storage> UpdateExecutor<S> {
+    #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
+    pub async fn execute(self, child: BoxedExecutor) {
+        let table = self.storage.get_table(self.table_id)?;
+        let columns = table.columns()?;
+
+        // reorder the new values as columns in the table
+        let mut expr = RecExpr::default();
+        let list = columns
+            .iter()
+            .map(|col| {
+                let index = self.column_ids.iter().position(|&id| id == col.id());
+                expr.add(Expr::ColumnIndex(ColumnIndex(index.unwrap() as _)))
+            })
+            .collect();
+        expr.add(Expr::List(list));
+
+        // an update is a delete followed by an insert in the same transaction.
+        // all old rows are deleted first, so that new rows can reuse their keys.
+        let mut txn = table.update().await?;
+        let mut deleted = HashSet::new();
+        let mut chunks = vec![];
+        #[for_await]
+        for chunk in child {
+            let chunk = chunk?;
+            let row_handlers = chunk.array_at(chunk.column_count() - 1);
+            for row_handler_idx in 0..row_handlers.len() {
+                let row_handler = <S::Transaction as Transaction>::RowHandlerType::from_column(
+                    row_handlers,
+                    row_handler_idx,
+                );
+                txn.delete(&row_handler).await?;
+                deleted.insert(row_handlers.get(row_handler_idx));
+            }
+            chunks.push(Evaluator::new(&expr).eval_list(&chunk)?);
+        }
+
+        let mut checker = ConstraintChecker::new(
+            &*self.storage,
+            &table,
+            &txn,
+            &columns,
+            &self.constraints,
+            &deleted,
+        )
+        .await?;
+        for chunk in &chunks {
+            checker.check(chunk)?;
+        }
+        checker.finish(&txn).await?;
+        check_references(
+            &*self.storage,
+            &table,
+            &txn,
+            &self.referencing,
+            &deleted,
+            &chunks,
+        )
+        .await?;
+
+        let returning = (self.returning.as_ref()).map(|expr| resolve_table_columns(expr, &columns));
+        let mut cnt = 0;
+        let mut outputs = vec![];
+        for chunk in chunks {
+            cnt += chunk.cardinality();
+            if let Some(returning) = &returning {
+                outputs.push(Evaluator::new(returning).eval_list(&chunk)?);
+            }
+            txn.append(chunk).await?;
+        }
+        txn.commit().await?;
+
+        if returning.is_some() {
+            for chunk in outputs {
+                yield chunk;
+            }
+        } else {
+            yield DataChunk::single(cnt as i32);
+        }
+    }
+}
@@ -1,7 +1,10 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::cmp::Ordering;
+
 use super::*;
 use crate::array::DataChunkBuilder;
+use crate::types::DataValue;
 
 /// The executor of window functions.
 pub struct WindowExecutor {
@@ -16,18 +19,88 @@ pub struct WindowExecutor {
 impl WindowExecutor {
     #[try_stream(boxed, ok = DataChunk, error = ExecutorError)]
     pub async fn execute(self, child: BoxedExecutor) {
-        let mut states = Evaluator::new(&self.exprs).init_agg_states::<Vec<_>>();
-
+        // a partition may span multiple chunks, so collect all input first
+        let mut chunks = vec![];
         #[for_await]
         for chunk in child {
-            let chunk = chunk?;
+            chunks.push(chunk?);
+        }
+
+        let exprs = Evaluator::new(&self.exprs);
+        let mut args_chunks = vec![];
+        for chunk in &chunks {
+            args_chunks.push(exprs.eval_list(chunk)?);
+        }
+        let args = gen_row_array(&args_chunks);
+
+        // results[i][j] is the value of the j-th window function on the i-th row
+        let mut results = vec![vec![DataValue::Null; self.types.len()]; args.len()];
+        for (j, over) in exprs.list().enumerate() {
+            let (func, partition_keys, order_keys) = over.over();
+            let mut partition_chunks = vec![];
+            let mut order_chunks = vec![];
+            for chunk in &chunks {
+                partition_chunks.push(partition_keys.eval_list(chunk)?);
+                order_chunks.push(order_keys.eval_list(chunk)?);
+            }
+            let partitions = gen_row_array(&partition_chunks);
+            let orders = gen_row_array(&order_chunks);
+            let key_orders = order_keys.orders();
+
+            // the sort is stable, so rows without order keys are visited in input order
+            let mut indices = (0..args.len()).collect_vec();
+            indices.sort_by(|&a, &b| {
+                (partitions[a].values().cmp(partitions[b].values()))
+                    .then_with(|| cmp(&orders[a], &orders[b], &key_orders))
+            });
+            let same_partition =
+                |a: usize, b: usize| partitions[a].values().eq(partitions[b].values());
+            // without order keys, each row is a peer group of its own
+            let is_peer = |a: usize, b: usize| {
+                !key_orders.is_empty()
+                    && cmp(&orders[a], &orders[b], &key_orders) == Ordering::Equal
+            };
+
+            let mut state = AggState::default();
+            let (mut rows_before, mut groups_before) = (0, 0);
+            let mut start = 0;
+            while start < indices.len() {
+                let first = indices[start];
+                if start == 0 || !same_partition(indices[start - 1], first) {
+                    state = func.init_agg_state();
+                    (rows_before, groups_before) = (0, 0);
+                }
+                let end = (start + 1..indices.len())
+                    .find(|&k| !same_partition(first, indices[k]) || !is_peer(first, indices[k]))
+                    .unwrap_or(indices.len());
+                let group = &indices[start..end];
+                // peers share the same aggregation result
+                for &i in group {
+                    state = func.agg_append(state, args[i].get(j));
+                }
+                for (k, &i) in group.iter().enumerate() {
+                    results[i][j] = match func.node() {
+                        Expr::RowNumber => DataValue::Int32((rows_before + k + 1) as _),
+                        Expr::Rank => DataValue::Int32((rows_before + 1) as _),
+                        Expr::DenseRank => DataValue::Int32((groups_before + 1) as _),
+                        _ => func.agg_result(&state),
+                    };
+                }
+                rows_before += group.len();
+                groups_before += 1;
+                start = end;
+            }
+        }
+
+        let mut results = results.into_iter();
+        for chunk in chunks {
             let mut builder = DataChunkBuilder::new(&self.types, chunk.cardinality() + 1);
-            for i in 0..chunk.cardinality() {
-                Evaluator::new(&self.exprs).agg_list_append(&mut states, chunk.row(i).values());
-                let results = Evaluator::new(&self.exprs).agg_list_get_result(&states);
-                _ = builder.push_row(results);
+            for values in results.by_ref().take(chunk.cardinality()) {
+                _ = builder.push_row(values);
             }
-            let window_chunk = builder.take().unwrap();
+            let Some(window_chunk) = builder.take() else {
+                continue;
+            };
             yield chunk.row_concat(window_chunk);
         }
     }
@@ -54,11 +54,11 @@ pub mod types;
 pub mod utils;
 
 #[cfg(feature = "python")]
-use python::open;
+use python::{open, PythonDatabase};
 #[cfg(feature = "jemalloc")]
 use tikv_jemallocator::Jemalloc;
 
-pub use self::db::{Database, Error};
+pub use self::db::{Database, Error, Session};
 
 /// Jemalloc can significantly improve performance compared to the default system allocator.
 #[cfg(feature = "jemalloc")]
@@ -73,6 +73,7 @@ use pyo3::{prelude::*, wrap_pyfunction};
 #[cfg(feature = "python")]
 #[pymodule]
 fn risinglight(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PythonDatabase>()?;
     m.add_function(wrap_pyfunction!(open, m)?)?;
     Ok(())
 }
@@ -11,12 +11,15 @@ use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use humantime::format_duration;
 use itertools::Itertools;
-use risinglight::array::{datachunk_to_sqllogictest_string, Chunk};
-use risinglight::server::run_server;
+use risinglight::array::{datachunk_to_sqllogictest_string, ArrayBuilderImpl, Chunk};
+use risinglight::server::{
+    run_flight_server, run_grpc_server, run_http_server, run_metrics_server, run_server,
+};
 use risinglight::storage::SecondaryStorageOptions;
+use risinglight::types::{DataType, DataValue};
 use risinglight::utils::time::RoundingDuration;
 use risinglight::Database;
 use rustyline::error::ReadlineError;
@@ -43,10 +46,8 @@ struct Args {
     memory: bool,
 
     /// Control the output format
-    /// - `text`: plain text
-    /// - `human`: human readable format
-    #[clap(long)]
-    output_format: Option<String>,
+    #[clap(long, value_enum, default_value_t = OutputFormat::Human)]
+    output_format: OutputFormat,
 
     /// Whether to use minitrace
     #[clap(long)]
@@ -57,55 +58,157 @@ struct Args {
     tokio_console: bool,
 
     /// Start the postgres server instead of the interactive shell.
-    #[clap(long)]
+    #[clap(long, alias = "serve")]
     server: bool,
+    /// Start an Arrow Flight server instead of the interactive shell.
+    #[clap(long)]
+    flight: bool,
+    /// Start a gRPC query service instead of the interactive shell.
+    #[clap(long)]
+    grpc: bool,
+    /// Start an HTTP server accepting queries at `POST /query` instead of the interactive shell.
+    #[clap(long)]
+    http: bool,
     /// The host to bind to.
     /// Defaults to localhost.
-    /// Ignored if --server is not set.
+    /// Ignored if none of --server, --flight, --grpc and --http is set.
     #[clap(long)]
     host: Option<String>,
     /// The port to listen on.
-    /// Default to 5432.
-    /// Ignored if `--server` is not specified.
+    /// Default to 5432, 50051 for `--flight` and `--grpc`, or 8080 for `--http`.
+    /// Ignored if none of `--server`, `--flight`, `--grpc` and `--http` is specified.
     #[clap(long)]
     port: Option<u16>,
+    /// Serve metrics in the Prometheus text format at `/metrics` on this port.
+    /// Binds to the same host as `--host`.
+    #[clap(long)]
+    metrics_port: Option<u16>,
+
+    /// Store all blocks without compression, ignoring the compression options of tables.
+    /// Ignored if `--memory` is set.
+    #[clap(long)]
+    disable_compression: bool,
+
+    /// Open the database at an older version of the manifest for debugging.
+    /// Changes made in this mode are not persisted.
+    /// Ignored if `--memory` is set.
+    #[clap(long)]
+    manifest_version: Option<usize>,
+
+    /// Keep replaced snapshots for this many seconds, so that they can be read by time travel
+    /// queries (`SELECT .. FROM t AS OF ..`).
+    /// Ignored if `--memory` is set.
+    #[clap(long, default_value_t = 0)]
+    snapshot_retention: u64,
 }
 
-// human-readable message
-fn print_chunk(chunk: &Chunk, output_format: &Option<String>) {
-    let output_format = output_format.as_ref().map(|x| x.as_str());
-    match output_format {
-        Some("human") | None => match chunk.header() {
-            Some(header) => match header[0].as_str() {
-                "$insert.row_counts" => {
-                    println!(
-                        "{} rows inserted",
-                        chunk.get_first_data_chunk().array_at(0).get_to_string(0)
-                    )
-                }
-                "$delete.row_counts" => {
-                    println!(
-                        "{} rows deleted",
-                        chunk.get_first_data_chunk().array_at(0).get_to_string(0)
-                    )
-                }
-                "$create" => println!("created"),
-                "$drop" => println!("dropped"),
-                "$explain" => println!(
-                    "{}",
-                    chunk.get_first_data_chunk().array_at(0).get_to_string(0)
-                ),
-                _ => println!("{}", chunk),
-            },
-            None => println!("{}", chunk),
-        },
-        Some("text") => println!(
+/// The format of query results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human readable tables.
+    #[value(alias = "table")]
+    Human,
+    /// Values separated by commas, one row per line.
+    Text,
+    /// CSV with a header line.
+    Csv,
+    /// An array of JSON objects, one row per line.
+    Json,
+}
+
+/// Returns the message of a chunk that reports the status of a statement rather than rows.
+fn status_message(chunk: &Chunk) -> Option<String> {
+    let first_value = || chunk.get_first_data_chunk().array_at(0).get_to_string(0);
+    Some(match chunk.header()?[0].as_str() {
+        "$insert.row_counts" => format!("{} rows inserted", first_value()),
+        "$delete.row_counts" => format!("{} rows deleted", first_value()),
+        "$update.row_counts" => format!("{} rows updated", first_value()),
+        "$create" => "created".into(),
+        "$drop" => "dropped".into(),
+        "$truncate" => "truncated".into(),
+        "$explain" => first_value(),
+        _ => return None,
+    })
+}
+
+/// Returns the names of columns, or `column{i}` if unknown.
+fn column_names(chunk: &Chunk) -> Vec<String> {
+    if let Some(schema) = chunk.schema() {
+        return schema.iter().map(|(name, _)| name.clone()).collect();
+    }
+    let count = chunk.data_chunks().first().map_or(0, |c| c.column_count());
+    (0..count).map(|i| format!("column{i}")).collect()
+}
+
+fn print_chunk(chunk: &Chunk, output_format: OutputFormat) {
+    if output_format == OutputFormat::Text {
+        println!(
             "{}",
             datachunk_to_sqllogictest_string(chunk)
                 .iter()
                 .format_with("\n", |row, f| f(&row.iter().format(","))),
-        ),
-        Some(format) => panic!("unsupported output format: {}", format),
+        );
+        return;
+    }
+    if let Some(message) = status_message(chunk) {
+        println!("{message}");
+        return;
+    }
+    match output_format {
+        OutputFormat::Human if chunk.header().is_none() && chunk.schema().is_some() => {
+            let mut chunk = chunk.clone();
+            chunk.set_header(column_names(&chunk));
+            println!("{chunk}");
+        }
+        OutputFormat::Human | OutputFormat::Text => println!("{chunk}"),
+        OutputFormat::Csv => print_csv(chunk),
+        OutputFormat::Json => print_json(chunk),
+    }
+}
+
+fn print_csv(chunk: &Chunk) {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    if chunk.schema().is_some() {
+        writer.write_record(column_names(chunk)).unwrap();
+    }
+    for data_chunk in chunk.data_chunks() {
+        for i in 0..data_chunk.cardinality() {
+            let record = data_chunk.arrays().iter().map(|a| match a.get(i) {
+                DataValue::Null => String::new(),
+                _ => a.get_to_string(i),
+            });
+            writer.write_record(record).unwrap();
+        }
+    }
+    writer.flush().unwrap();
+}
+
+fn print_json(chunk: &Chunk) {
+    let names = column_names(chunk);
+    let rows = chunk
+        .data_chunks()
+        .iter()
+        .flat_map(|c| c.rows())
+        .map(|row| {
+            let object: serde_json::Map<_, _> = (names.iter().cloned())
+                .zip(row.values().map(json_value))
+                .collect();
+            serde_json::Value::Object(object).to_string()
+        });
+    println!("[{}]", rows.format(",\n"));
+}
+
+fn json_value(value: DataValue) -> serde_json::Value {
+    match value {
+        DataValue::Null => serde_json::Value::Null,
+        DataValue::Bool(v) => v.into(),
+        DataValue::Int16(v) => v.into(),
+        DataValue::Int32(v) => v.into(),
+        DataValue::Int64(v) => v.into(),
+        DataValue::Float64(v) => v.0.into(),
+        DataValue::String(v) => v.to_string().into(),
+        DataValue::Json(v) => v.to_value(),
+        value => value.to_string().into(),
     }
 }
 
@@ -123,32 +226,46 @@ fn print_execution_time(start_time: Instant) {
     }
 }
 
-async fn run_query_in_background(db: Arc<Database>, sql: String, output_format: Option<String>) {
-    let start_time = Instant::now();
-
-    select! {
-        _ = signal::ctrl_c() => {
-            // we simply drop the future `task` to cancel the query.
-            println!("Interrupted");
-        }
-        ret = db.run(&sql) => {
-            match ret {
-                Ok(chunks) => {
-                    for chunk in chunks {
-                        print_chunk(&chunk, &output_format);
+/// Returns whether the SQL ends with a `;` outside of string literals and comments.
+fn is_complete_sql(sql: &str) -> bool {
+    let mut chars = sql.chars().peekable();
+    let mut complete = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                complete = false;
+                // a quote in a string literal is escaped by doubling it
+                while let Some(d) = chars.next() {
+                    if d == c && chars.next_if_eq(&c).is_none() {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.next_if_eq(&'-').is_some() => {
+                while chars.next_if(|&d| d != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                let mut closed = false;
+                while let Some(d) = chars.next() {
+                    if d == '*' && chars.next_if_eq(&'/').is_some() {
+                        closed = true;
+                        break;
                     }
-                    print_execution_time(start_time);
                 }
-                Err(err) => println!("{}", err),
+                if !closed {
+                    return false;
+                }
             }
+            ';' => complete = true,
+            c if c.is_whitespace() => {}
+            _ => complete = false,
         }
     }
+    complete
 }
 
-/// Read line by line from STDIN until a line ending with `;`.
-///
-/// Note that `;` in string literals will also be treated as a terminator
-/// as long as it is at the end of a line.
+/// Read line by line from STDIN until a line ending with `;` outside of string literals and
+/// comments.
 fn read_sql(rl: &mut DefaultEditor) -> Result<String, ReadlineError> {
     let mut sql = String::new();
     loop {
@@ -164,7 +281,7 @@ fn read_sql(rl: &mut DefaultEditor) -> Result<String, ReadlineError> {
         }
 
         sql.push_str(line.as_str());
-        if line.ends_with(';') {
+        if is_complete_sql(&sql) {
             return Ok(sql);
         } else {
             sql.push('\n');
@@ -172,8 +289,147 @@ fn read_sql(rl: &mut DefaultEditor) -> Result<String, ReadlineError> {
     }
 }
 
+const SHELL_HELP: &str = "\
+\\dt              list tables
+\\d <table>       describe a table
+\\timing [on|off] toggle printing the execution time of queries
+\\format [format] show or set the output format: human (table), text, csv or json
+\\q               quit
+\\?               show this help";
+
+/// The interactive shell.
+struct Shell {
+    db: Arc<Database>,
+    output_format: OutputFormat,
+    /// Whether to print the execution time of queries.
+    timing: bool,
+}
+
+impl Shell {
+    /// Runs a shell command starting with `\`. Returns `false` if the shell should exit.
+    async fn run_command(&mut self, command: &str) -> bool {
+        let tokens = command.split_whitespace().collect_vec();
+        match tokens.as_slice() {
+            ["\\q"] => return false,
+            ["\\?"] => println!("{SHELL_HELP}"),
+            ["\\dt"] | ["\\d"] => print_chunk(&self.list_tables(), self.output_format),
+            ["\\d", table] => match self.describe_table(table) {
+                Some(chunk) => print_chunk(&chunk, self.output_format),
+                None => println!("table {table:?} not found"),
+            },
+            ["\\timing"] => self.timing = !self.timing,
+            ["\\timing", "on"] => self.timing = true,
+            ["\\timing", "off"] => self.timing = false,
+            ["\\format"] => {
+                let format = self.output_format.to_possible_value().unwrap();
+                println!("{}", format.get_name());
+            }
+            ["\\format", format] => match OutputFormat::from_str(format, true) {
+                Ok(format) => self.output_format = format,
+                Err(e) => println!("{e}"),
+            },
+            ["\\timing" | "\\format", ..] => println!("invalid arguments. see \\? for help"),
+            // other commands are handled by the database
+            _ => self.run_sql(command.to_string()).await,
+        }
+        true
+    }
+
+    /// Returns the tables and views, except for system tables.
+    fn list_tables(&self) -> Chunk {
+        let mut tables = vec![];
+        for schema in self.db.catalog().all_schemas().into_values() {
+            for table in schema.all_tables().into_values() {
+                if !table.is_system() {
+                    tables.push((schema.name(), table));
+                }
+            }
+        }
+        tables.sort_by_key(|(schema, table)| (schema.clone(), table.id()));
+        let rows = tables.into_iter().map(|(schema, table)| {
+            let kind = if table.is_view() { "view" } else { "table" };
+            [
+                DataValue::String(schema.into()),
+                DataValue::Int32(table.id() as i32),
+                DataValue::String(table.name().into()),
+                DataValue::String(kind.into()),
+            ]
+        });
+        let schema = vec![
+            ("schema".into(), DataType::String),
+            ("id".into(), DataType::Int32),
+            ("name".into(), DataType::String),
+            ("type".into(), DataType::String),
+        ];
+        rows_to_chunk(schema, rows)
+    }
+
+    /// Returns the columns of a table, or `None` if the table doesn't exist.
+    fn describe_table(&self, name: &str) -> Option<Chunk> {
+        let table = self.db.catalog().get_table_by_name(name)?;
+        let rows = table.all_columns().into_values().map(|column| {
+            [
+                DataValue::String(column.name().into()),
+                DataValue::String(column.data_type().to_string().to_ascii_lowercase().into()),
+                DataValue::Bool(!column.is_nullable()),
+                DataValue::Bool(column.is_primary()),
+            ]
+        });
+        let schema = vec![
+            ("column".into(), DataType::String),
+            ("type".into(), DataType::String),
+            ("not null".into(), DataType::Bool),
+            ("primary key".into(), DataType::Bool),
+        ];
+        Some(rows_to_chunk(schema, rows))
+    }
+
+    async fn run_sql(&self, sql: String) {
+        let start_time = Instant::now();
+
+        select! {
+            _ = signal::ctrl_c() => {
+                // we simply drop the future `task` to cancel the query.
+                println!("Interrupted");
+            }
+            ret = self.db.run(&sql) => {
+                match ret {
+                    Ok(chunks) => {
+                        for chunk in chunks {
+                            print_chunk(&chunk, self.output_format);
+                        }
+                        if self.timing {
+                            print_execution_time(start_time);
+                        }
+                    }
+                    Err(err) => println!("{}", err),
+                }
+            }
+        }
+    }
+}
+
+/// Builds a chunk of rows with the given column names and types.
+fn rows_to_chunk<const N: usize>(
+    schema: Vec<(String, DataType)>,
+    rows: impl Iterator<Item = [DataValue; N]>,
+) -> Chunk {
+    let mut builders = schema
+        .iter()
+        .map(|(_, ty)| ArrayBuilderImpl::new(ty))
+        .collect_vec();
+    for row in rows {
+        for (builder, value) in builders.iter_mut().zip(&row) {
+            builder.push(value);
+        }
+    }
+    let mut chunk = Chunk::new(vec![builders.into_iter().collect()]);
+    chunk.set_schema(schema);
+    chunk
+}
+
 /// Run RisingLight interactive mode
-async fn interactive(db: Database, output_format: Option<String>) -> Result<()> {
+async fn interactive(db: Database, output_format: OutputFormat) -> Result<()> {
     let mut rl = DefaultEditor::new()?;
     let history_path = dirs::cache_dir().map(|p| {
         let cache_dir = p.join("risinglight");
@@ -191,15 +447,28 @@ async fn interactive(db: Database, output_format: Option<String>) -> Result<()>
         }
     }
 
-    let db = Arc::new(db);
+    let mut shell = Shell {
+        db: Arc::new(db),
+        output_format,
+        timing: true,
+    };
 
     loop {
         let read_sql = read_sql(&mut rl);
         match read_sql {
             Ok(sql) => {
-                if !sql.trim().is_empty() {
-                    rl.add_history_entry(sql.as_str())?;
-                    run_query_in_background(db.clone(), sql, output_format.clone()).await;
+                let sql = sql.trim();
+                if sql.is_empty() {
+                    continue;
+                }
+                rl.add_history_entry(sql)?;
+                if sql.starts_with('\\') {
+                    if !shell.run_command(sql).await {
+                        println!("Exited");
+                        break;
+                    }
+                } else {
+                    shell.run_sql(sql.to_string()).await;
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -226,7 +495,7 @@ async fn interactive(db: Database, output_format: Option<String>) -> Result<()>
 }
 
 /// Run a SQL file in RisingLight
-async fn run_sql(db: Database, path: &str, output_format: Option<String>) -> Result<()> {
+async fn run_sql(db: Database, path: &str, output_format: OutputFormat) -> Result<()> {
     let lines = std::fs::read_to_string(path)?;
 
     info!("{}", lines);
@@ -234,7 +503,7 @@ async fn run_sql(db: Database, path: &str, output_format: Option<String>) -> Res
     let chunks = db.run(&lines).await?;
 
     for chunk in chunks {
-        print_chunk(&chunk, &output_format);
+        print_chunk(&chunk, output_format);
     }
 
     Ok(())
@@ -243,7 +512,7 @@ async fn run_sql(db: Database, path: &str, output_format: Option<String>) -> Res
 /// Wrapper for sqllogictest
 struct DatabaseWrapper {
     db: Database,
-    output_format: Option<String>,
+    output_format: OutputFormat,
 }
 
 #[async_trait]
@@ -269,7 +538,7 @@ impl sqllogictest::AsyncDB for &DatabaseWrapper {
         let chunks = self.db.run(sql).await?;
 
         for chunk in &chunks {
-            print_chunk(chunk, &self.output_format);
+            print_chunk(chunk, self.output_format);
         }
 
         if chunks.is_empty() || chunks.iter().all(|c| c.data_chunks().is_empty()) {
@@ -292,7 +561,7 @@ impl sqllogictest::AsyncDB for &DatabaseWrapper {
 }
 
 /// Run a sqllogictest file in RisingLight
-async fn run_sqllogictest(db: Database, path: &str, output_format: Option<String>) -> Result<()> {
+async fn run_sqllogictest(db: Database, path: &str, output_format: OutputFormat) -> Result<()> {
     let db = DatabaseWrapper { db, output_format };
     let mut tester = sqllogictest::Runner::new(|| async { Ok(&db) });
     let path = path.to_string();
@@ -326,6 +595,10 @@ async fn main() -> Result<()> {
         minitrace::set_reporter(ConsoleReporter, Config::default());
     }
 
+    if let Some(port) = args.metrics_port {
+        tokio::spawn(run_metrics_server(args.host.clone(), port));
+    }
+
     let db = if args.memory {
         info!("using memory engine");
         Database::new_in_memory()
@@ -335,6 +608,9 @@ async fn main() -> Result<()> {
         if let Some(path) = args.storage_path {
             options.path = PathBuf::new().join(path);
         }
+        options.disable_compression = args.disable_compression;
+        options.manifest_version = args.manifest_version;
+        options.snapshot_retention = Duration::from_secs(args.snapshot_retention);
         Database::new_on_disk(options).await
     };
 
@@ -349,6 +625,12 @@ async fn main() -> Result<()> {
         }
     } else if args.server {
         run_server(args.host, args.port, db).await;
+    } else if args.flight {
+        run_flight_server(args.host, args.port, db).await;
+    } else if args.grpc {
+        run_grpc_server(args.host, args.port, db).await;
+    } else if args.http {
+        run_http_server(args.host, args.port, db).await;
     } else {
         interactive(db, args.output_format).await?;
     }
@@ -356,3 +638,20 @@ async fn main() -> Result<()> {
     minitrace::flush();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_complete_sql() {
+        assert!(is_complete_sql("select 1;"));
+        assert!(is_complete_sql("select 1;  -- comment"));
+        assert!(is_complete_sql("select ';' /* ; */;"));
+        assert!(!is_complete_sql("select 1"));
+        assert!(!is_complete_sql("select 1 -- ;"));
+        assert!(!is_complete_sql("select 'a;"));
+        assert!(!is_complete_sql("select 'it''s;"));
+        assert!(!is_complete_sql("select 1; /* ;"));
+    }
+}
@@ -5,11 +5,124 @@
 
 pub use sqlparser::ast::*;
 use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::Keyword;
 use sqlparser::parser::Parser;
 pub use sqlparser::parser::ParserError;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace, Word};
 
 /// Parse the SQL string into a list of ASTs.
 pub fn parse(sql: &str) -> Result<Vec<Statement>, ParserError> {
     let dialect = PostgreSqlDialect {};
-    Parser::parse_sql(&dialect, sql)
+    let tokens = Tokenizer::new(&dialect, sql).tokenize()?;
+    match rewrite_as_of(&tokens) {
+        Some(tokens) => Parser::new(&dialect).with_tokens(tokens).parse_statements(),
+        // keep the locations of tokens in errors
+        None => Parser::parse_sql(&dialect, sql),
+    }
+}
+
+/// Parse the SQL string into an expression.
+pub fn parse_expr(sql: &str) -> Result<Expr, ParserError> {
+    let dialect = PostgreSqlDialect {};
+    Parser::new(&dialect).try_with_sql(sql)?.parse_expr()
+}
+
+/// The name of the argument of a table for time travel queries.
+pub const AS_OF_ARG: &str = "as_of";
+
+/// Rewrites time travel queries `t AS OF <expr>` into `t(as_of => <expr>)`, since `AS OF` is not
+/// supported by the Postgres dialect. Returns `None` if there is nothing to rewrite.
+///
+/// The expression ends before a comma, a closing parenthesis, or a keyword starting a clause. So
+/// an alias of the table must follow `AS`, e.g. `t AS OF 1 AS t1`.
+fn rewrite_as_of(tokens: &[Token]) -> Option<Vec<Token>> {
+    let is_keyword =
+        |token: &Token, keyword| matches!(token, Token::Word(w) if w.keyword == keyword);
+    let next = |i: usize| (i..tokens.len()).find(|&i| !matches!(tokens[i], Token::Whitespace(_)));
+
+    let mut output: Vec<Token> = vec![];
+    let mut rewritten = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        // `AS OF` after a table name
+        let as_of = is_keyword(&tokens[i], Keyword::AS)
+            && (output.iter().rev())
+                .find(|token| !matches!(token, Token::Whitespace(_)))
+                .is_some_and(|token| matches!(token, Token::Word(_)))
+            && next(i + 1).is_some_and(|j| is_keyword(&tokens[j], Keyword::OF));
+        if !as_of {
+            output.push(tokens[i].clone());
+            i += 1;
+            continue;
+        }
+        rewritten = true;
+        output.extend([
+            Token::LParen,
+            Token::make_word(AS_OF_ARG, None),
+            Token::RArrow,
+        ]);
+        i = next(i + 1).unwrap() + 1;
+        let mut depth = 0;
+        while let Some(token) = tokens.get(i) {
+            match token {
+                Token::LParen | Token::LBracket => depth += 1,
+                Token::RParen | Token::RBracket if depth == 0 => break,
+                Token::RParen | Token::RBracket => depth -= 1,
+                Token::Comma | Token::SemiColon if depth == 0 => break,
+                Token::Word(Word { keyword, .. }) if depth == 0 && ENDS_AS_OF.contains(keyword) => {
+                    break
+                }
+                _ => {}
+            }
+            output.push(token.clone());
+            i += 1;
+        }
+        output.push(Token::RParen);
+        output.push(Token::Whitespace(Whitespace::Space));
+    }
+    rewritten.then_some(output)
+}
+
+/// Keywords ending the expression of `AS OF`.
+const ENDS_AS_OF: &[Keyword] = &[
+    Keyword::AS,
+    Keyword::CROSS,
+    Keyword::EXCEPT,
+    Keyword::FETCH,
+    Keyword::FOR,
+    Keyword::FULL,
+    Keyword::GROUP,
+    Keyword::HAVING,
+    Keyword::INNER,
+    Keyword::INTERSECT,
+    Keyword::JOIN,
+    Keyword::LEFT,
+    Keyword::LIMIT,
+    Keyword::NATURAL,
+    Keyword::OFFSET,
+    Keyword::ON,
+    Keyword::ORDER,
+    Keyword::RETURNING,
+    Keyword::RIGHT,
+    Keyword::UNION,
+    Keyword::USING,
+    Keyword::WHERE,
+    Keyword::WINDOW,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn as_of() {
+        let sql = "select * from t as of 3 as t1 join s as of timestamp '2024-01-01' on t1.a = s.a";
+        let expected = "SELECT * FROM t(as_of => 3) AS t1 JOIN s(as_of => TIMESTAMP '2024-01-01') ON t1.a = s.a";
+        assert_eq!(parse(sql).unwrap()[0].to_string(), expected);
+
+        // the expression may contain parentheses and commas
+        let sql = "select * from t as of (1 + abs(-2)), s";
+        let expected = "SELECT * FROM t(as_of => (1 + abs(-2))), s";
+        assert_eq!(parse(sql).unwrap()[0].to_string(), expected);
+    }
 }
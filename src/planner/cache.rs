@@ -0,0 +1,208 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use moka::future::Cache;
+use moka::policy::EvictionPolicy;
+
+use super::{Expr, RecExpr};
+use crate::catalog::RootCatalogRef;
+use crate::parser::Statement;
+
+/// An LRU cache of optimized plans, keyed by normalized SQL, the session variables and the version
+/// of statistics the plans depend on.
+///
+/// A plan is only valid for the version of the catalog it was planned with. Entries planned with
+/// an older version are dropped on lookup. Entries planned with older statistics are never looked
+/// up again and are eventually evicted.
+pub struct PlanCache {
+    catalog: RootCatalogRef,
+    cache: Cache<String, Arc<CachedPlan>>,
+}
+
+struct CachedPlan {
+    stmt: Statement,
+    plan: RecExpr,
+    catalog_version: u64,
+}
+
+/// Statistics of the plan cache, shown in `pg_catalog.pg_plan_cache`.
+#[derive(Debug, Default)]
+pub struct PlanCacheStats {
+    entries: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl PlanCache {
+    /// Creates a plan cache holding at most `capacity` plans.
+    pub fn new(catalog: RootCatalogRef, capacity: u64) -> Self {
+        let cache = Cache::builder()
+            .max_capacity(capacity)
+            .eviction_policy(EvictionPolicy::lru())
+            .build();
+        PlanCache { catalog, cache }
+    }
+
+    /// Returns the statement and plan of the SQL if it is cached and still valid.
+    ///
+    /// `settings` describes the session variables the plan depends on. Plans are only shared by
+    /// sessions with the same settings.
+    pub async fn get(&self, sql: &str, settings: &str) -> Option<(Statement, RecExpr)> {
+        let key = cache_key(
+            settings,
+            self.catalog.statistics_version(),
+            &normalize_sql(sql)?,
+        );
+        let stats = self.catalog.plan_cache_stats();
+        let Some(entry) = self.cache.get(&key).await else {
+            stats.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        if entry.catalog_version != self.catalog.version() {
+            self.cache.invalidate(&key).await;
+            self.update_entry_count().await;
+            stats.invalidations.fetch_add(1, Ordering::Relaxed);
+            stats.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        stats.hits.fetch_add(1, Ordering::Relaxed);
+        Some((entry.stmt.clone(), entry.plan.clone()))
+    }
+
+    /// Caches the plan of the SQL, which was planned with the given versions of the catalog and
+    /// statistics.
+    ///
+    /// Only queries and DML statements are cached. Plans reading external files are not cached,
+    /// because the schema of the file is inferred when planning.
+    pub async fn insert(
+        &self,
+        sql: &str,
+        settings: &str,
+        catalog_version: u64,
+        statistics_version: u64,
+        stmt: &Statement,
+        plan: &RecExpr,
+    ) {
+        let Some(key) = normalize_sql(sql) else {
+            return;
+        };
+        let key = cache_key(settings, statistics_version, &key);
+        if !matches!(
+            stmt,
+            Statement::Query(_)
+                | Statement::Insert { .. }
+                | Statement::Update { .. }
+                | Statement::Delete { .. }
+        ) || plan
+            .as_ref()
+            .iter()
+            .any(|e| matches!(e, Expr::ExtSource(_)))
+        {
+            return;
+        }
+        let entry = CachedPlan {
+            stmt: stmt.clone(),
+            plan: plan.clone(),
+            catalog_version,
+        };
+        self.cache.insert(key, Arc::new(entry)).await;
+        self.update_entry_count().await;
+    }
+
+    /// Removes all cached plans, e.g. when the optimizer is reconfigured.
+    pub async fn clear(&self) {
+        self.cache.invalidate_all();
+        self.update_entry_count().await;
+    }
+
+    async fn update_entry_count(&self) {
+        self.cache.run_pending_tasks().await;
+        let stats = self.catalog.plan_cache_stats();
+        stats
+            .entries
+            .store(self.cache.entry_count(), Ordering::Relaxed);
+    }
+}
+
+impl PlanCacheStats {
+    /// Returns the number of entries, hits, misses and invalidated entries.
+    pub fn get(&self) -> (u64, u64, u64, u64) {
+        (
+            self.entries.load(Ordering::Relaxed),
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+            self.invalidations.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Returns the key of the plan cache.
+fn cache_key(settings: &str, statistics_version: u64, sql: &str) -> String {
+    format!("{settings}\n{statistics_version}\n{sql}")
+}
+
+/// Normalizes a SQL string into the key of the plan cache.
+///
+/// Whitespace outside of quotes is collapsed, unquoted text is lowercased, and trailing
+/// semicolons are removed. Returns `None` if the SQL contains comments, escapes or dollar signs,
+/// which can't be normalized without tokenizing.
+fn normalize_sql(sql: &str) -> Option<String> {
+    let mut key = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut chars = sql
+        .trim()
+        .trim_end_matches([';', ' ', '\t', '\n', '\r'])
+        .chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '\\' | '$') => return None,
+            (Some(q), c) => {
+                key.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            (None, '\'' | '"') => {
+                key.push(c);
+                quote = Some(c);
+            }
+            (None, '-') if chars.clone().next() == Some('-') => return None,
+            (None, '/') if chars.clone().next() == Some('*') => return None,
+            (None, c) if c.is_whitespace() => {
+                if !key.ends_with(' ') {
+                    key.push(' ');
+                }
+            }
+            (None, c) => key.extend(c.to_lowercase()),
+        }
+    }
+    if quote.is_some() || key.is_empty() {
+        return None;
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_sql() {
+        assert_eq!(
+            normalize_sql("SELECT  *\n FROM t WHERE v = 'A  b';").as_deref(),
+            Some("select * from t where v = 'A  b'")
+        );
+        assert_eq!(
+            normalize_sql("select \"V\" from t").as_deref(),
+            Some("select \"V\" from t")
+        );
+        assert_eq!(normalize_sql("select 1 -- comment"), None);
+        assert_eq!(normalize_sql("select /* comment */ 1"), None);
+        assert_eq!(normalize_sql("select $1"), None);
+        assert_eq!(normalize_sql("select 'a"), None);
+        assert_eq!(normalize_sql(";"), None);
+    }
+}
@@ -31,16 +31,23 @@ impl egg::CostFunction<Expr> for CostFn<'_> {
 
         let c = match enode {
             // plan nodes
-            Scan(_) | Values(_) => build(),
+            Scan(_) | ScanAsOf(_) | Values(_) | FileScan(_) | GenerateSeries(_) => build(),
+            // the index is in memory and a lookup takes log(n). matched rows are read at random,
+            // which costs more than reading them in a scan.
+            IndexScan([t, _, _]) => {
+                (rules::rows::table_rows(self.egraph, t) + 1.0).log2() + build() * 4.0
+            }
+            ValuesScan([c, _]) => costs(c),
             Order([_, c]) => nlogn(rows(c)) + build() + costs(c),
             Filter([exprs, c]) => costs(exprs) * rows(c) + build() + costs(c),
             Proj([exprs, c]) | Window([exprs, c]) => costs(exprs) * rows(c) + costs(c),
+            ProjectSet([exprs, c]) => costs(exprs) * rows(c) + build() + costs(c),
             Agg([exprs, c]) => costs(exprs) * rows(c) + build() + costs(c),
             HashAgg([keys, aggs, c]) => {
                 (hash(rows(id)) + costs(keys) + costs(aggs)) * rows(c) + build() + costs(c)
             }
             SortAgg([keys, aggs, c]) => (costs(keys) + costs(aggs)) * rows(c) + build() + costs(c),
-            Limit([_, _, c]) => build() + costs(c),
+            Limit([_, _, c]) | Exchange([_, c]) => build() + costs(c),
             TopN([_, _, _, c]) => (rows(id) + 1.0).log2() * rows(c) + build() + costs(c),
             Join([_, cond, l, r]) => {
                 costs(cond) * rows(l) * rows(r) + build() + costs(l) + costs(r)
@@ -66,12 +73,17 @@ impl egg::CostFunction<Expr> for CostFn<'_> {
                     + costs(r)
             }
             Apply([_, l, r]) => build() + costs(l) + rows(l) * costs(r),
-            Insert([_, _, c]) | CopyTo([_, c]) => rows(c) * cols(c) + costs(c),
+            Insert([_, _, _, _, _, c])
+            | Update([_, _, _, c])
+            | CopyTo([_, c])
+            | CreateTableAs([_, c]) => rows(c) * cols(c) + costs(c),
             Empty(_) => 0.0,
             Max1Row(c) => costs(c),
             // expressions
-            Column(_) | Ref(_) => 0.01, // column reference is almost free
+            Column(_) | Ref(_) | CteColumn(_) => 0.01, // column reference is almost free
             List(_) => enode.fold(0.01, |sum, id| sum + costs(&id)), // list is almost free
+            // pattern matching is much more expensive than comparisons
+            Like(_) | ILike(_) => enode.fold(1.0, |sum, id| sum + costs(&id)),
             // each operator has a cost of 0.1
             _ => enode.fold(0.1, |sum, id| sum + costs(&id)),
         };
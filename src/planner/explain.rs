@@ -124,18 +124,23 @@ impl<'a> Explain<'a> {
                 }
             }
             ColumnIndex(i) => Pretty::display(i),
+            Param(i) => format!("${}", i.0).into(),
 
             // TODO: use object
             ExtSource(src) => format!("path={:?}, format={}", src.path, src.format).into(),
             Symbol(s) => Pretty::display(s),
             Ref(e) => Pretty::fieldless_record("ref", vec![self.expr(e).pretty()]),
+            CteColumn([_, index]) => {
+                Pretty::fieldless_record("cte_column", vec![self.expr(index).pretty()])
+            }
             List(list) => Pretty::Array(list.iter().map(|e| self.expr(e).pretty()).collect()),
 
             // binary operations
             Add([a, b]) | Sub([a, b]) | Mul([a, b]) | Div([a, b]) | Mod([a, b])
             | StringConcat([a, b]) | Gt([a, b]) | Lt([a, b]) | GtEq([a, b]) | LtEq([a, b])
             | Eq([a, b]) | NotEq([a, b]) | And([a, b]) | Or([a, b]) | Xor([a, b])
-            | Like([a, b]) => Pretty::childless_record(
+            | Like([a, b]) | ILike([a, b]) | JsonGet([a, b]) | JsonGetText([a, b])
+            | ArrayGet([a, b]) => Pretty::childless_record(
                 enode.to_string(),
                 vec![
                     ("lhs", self.expr(a).pretty()),
@@ -144,7 +149,8 @@ impl<'a> Explain<'a> {
             ),
 
             // unary operations
-            Neg(a) | Not(a) | IsNull(a) => {
+            Neg(a) | Not(a) | IsNull(a) | ArrayLength(a) | Unnest(a) | Ceil(a) | Floor(a)
+            | Ln(a) | Log(a) => {
                 let name = enode.to_string();
                 let v = vec![self.expr(a).pretty()];
                 Pretty::fieldless_record(name, v)
@@ -168,6 +174,28 @@ impl<'a> Explain<'a> {
                 ],
             ),
             Field(field) => Pretty::display(field),
+            Btrim([a, b])
+            | Ltrim([a, b])
+            | Rtrim([a, b])
+            | Position([a, b])
+            | RegexpMatches([a, b])
+            | Round([a, b])
+            | Power([a, b])
+            | Greatest([a, b])
+            | Least([a, b]) => {
+                let name = enode.to_string();
+                let v = vec![self.expr(a).pretty(), self.expr(b).pretty()];
+                Pretty::fieldless_record(name, v)
+            }
+            Lpad([a, b, c]) | Rpad([a, b, c]) => {
+                let name = enode.to_string();
+                let v = vec![
+                    self.expr(a).pretty(),
+                    self.expr(b).pretty(),
+                    self.expr(c).pretty(),
+                ];
+                Pretty::fieldless_record(name, v)
+            }
             Replace([a, b, c]) => Pretty::childless_record(
                 "Replace",
                 vec![
@@ -185,14 +213,26 @@ impl<'a> Explain<'a> {
                 ],
             ),
 
+            Array(list) => Pretty::fieldless_record("array", vec![self.expr(list).pretty()]),
+
             // aggregations
-            RowCount | RowNumber => enode.to_string().into(),
+            RowCount | RowNumber | Rank | DenseRank => enode.to_string().into(),
             Max(a) | Min(a) | Sum(a) | Avg(a) | Count(a) | First(a) | Last(a)
-            | CountDistinct(a) => {
+            | CountDistinct(a) | SumDistinct(a) => {
                 let name = enode.to_string();
                 let v = vec![self.expr(a).pretty()];
                 Pretty::fieldless_record(name, v)
             }
+            StringAgg([a, delimiter, orderby]) => Pretty::simple_record(
+                "string_agg",
+                vec![("order_by", self.expr(orderby).pretty())],
+                vec![self.expr(a).pretty(), self.expr(delimiter).pretty()],
+            ),
+            ArrayAgg([a, orderby]) => Pretty::simple_record(
+                "array_agg",
+                vec![("order_by", self.expr(orderby).pretty())],
+                vec![self.expr(a).pretty()],
+            ),
             Over([f, orderby, partitionby]) => Pretty::simple_record(
                 "Over",
                 vec![
@@ -216,6 +256,14 @@ impl<'a> Explain<'a> {
                 vec![("type", self.expr(b).pretty())],
                 vec![self.expr(a).pretty()],
             ),
+            CastTz([a, b, tz]) => Pretty::simple_record(
+                "Cast",
+                vec![
+                    ("type", self.expr(b).pretty()),
+                    ("time_zone", self.expr(tz).pretty()),
+                ],
+                vec![self.expr(a).pretty()],
+            ),
 
             Scan([table, list, filter]) => Pretty::childless_record(
                 "Scan",
@@ -225,6 +273,22 @@ impl<'a> Explain<'a> {
                     ("filter", self.expr(filter).pretty()),
                 ]),
             ),
+            IndexScan([table, list, cond]) => Pretty::childless_record(
+                "IndexScan",
+                with_meta(vec![
+                    ("table", self.expr(table).pretty()),
+                    ("list", self.expr(list).pretty()),
+                    ("cond", self.expr(cond).pretty()),
+                ]),
+            ),
+            ScanAsOf([table, list, version]) => Pretty::childless_record(
+                "ScanAsOf",
+                with_meta(vec![
+                    ("table", self.expr(table).pretty()),
+                    ("list", self.expr(list).pretty()),
+                    ("version", self.expr(version).pretty()),
+                ]),
+            ),
             Values(values) => Pretty::simple_record(
                 "Values",
                 with_meta(vec![("rows", Pretty::display(&values.len()))]),
@@ -235,6 +299,11 @@ impl<'a> Explain<'a> {
                 with_meta(vec![("exprs", self.expr(exprs).pretty())]),
                 vec![self.child(child).pretty()],
             ),
+            ProjectSet([exprs, child]) => Pretty::simple_record(
+                "ProjectSet",
+                with_meta(vec![("exprs", self.expr(exprs).pretty())]),
+                vec![self.child(child).pretty()],
+            ),
             Filter([cond, child]) => Pretty::simple_record(
                 "Filter",
                 with_meta(vec![("cond", self.expr(cond).pretty())]),
@@ -249,6 +318,14 @@ impl<'a> Explain<'a> {
                 let v = vec![self.expr(a).pretty()];
                 Pretty::fieldless_record("desc", v)
             }
+            NullsFirst(a) => {
+                let v = vec![self.expr(a).pretty()];
+                Pretty::fieldless_record("nulls_first", v)
+            }
+            NullsLast(a) => {
+                let v = vec![self.expr(a).pretty()];
+                Pretty::fieldless_record("nulls_last", v)
+            }
             Limit([limit, offset, child]) => Pretty::simple_record(
                 "Limit",
                 with_meta(vec![
@@ -322,34 +399,135 @@ impl<'a> Explain<'a> {
                 with_meta(vec![("windows", self.expr(windows).pretty())]),
                 vec![self.child(child).pretty()],
             ),
+            RecursiveUnion([columns, base, recursive]) => Pretty::simple_record(
+                "RecursiveUnion",
+                with_meta(vec![("columns", self.expr(columns).pretty())]),
+                vec![self.child(base).pretty(), self.child(recursive).pretty()],
+            ),
+            WorkingTable(columns) => Pretty::childless_record(
+                "WorkingTable",
+                with_meta(vec![("columns", self.expr(columns).pretty())]),
+            ),
+            FileScan([src, columns]) => Pretty::childless_record(
+                "FileScan",
+                with_meta(vec![
+                    ("src", self.expr(src).pretty()),
+                    ("columns", self.expr(columns).pretty()),
+                ]),
+            ),
+            GenerateSeries([args, columns]) => Pretty::childless_record(
+                "GenerateSeries",
+                with_meta(vec![
+                    ("args", self.expr(args).pretty()),
+                    ("columns", self.expr(columns).pretty()),
+                ]),
+            ),
+            ValuesScan([child, columns]) => Pretty::simple_record(
+                "ValuesScan",
+                with_meta(vec![("columns", self.expr(columns).pretty())]),
+                vec![self.child(child).pretty()],
+            ),
+            Exchange([dist, child]) => Pretty::simple_record(
+                "Exchange",
+                with_meta(vec![("dist", self.expr(dist).pretty())]),
+                vec![self.child(child).pretty()],
+            ),
+            Single | Random => Pretty::display(enode),
+            Hash(keys) => Pretty::fieldless_record("hash", vec![self.expr(keys).pretty()]),
             CreateTable(t) => {
                 let fields = with_meta(t.pretty_table());
                 Pretty::childless_record("CreateTable", fields)
             }
-            CreateView([table, query]) => Pretty::simple_record(
+            CreateView([table, dependencies, query]) => Pretty::simple_record(
                 "CreateView",
-                with_meta(vec![("table", self.expr(table).pretty())]),
+                with_meta(vec![
+                    ("table", self.expr(table).pretty()),
+                    ("dependencies", self.expr(dependencies).pretty()),
+                ]),
                 vec![self.expr(query).pretty()],
             ),
+            CreateTableAs([table, child]) => Pretty::simple_record(
+                "CreateTableAs",
+                with_meta(vec![("table", self.expr(table).pretty())]),
+                vec![self.child(child).pretty()],
+            ),
             CreateFunction(f) => {
                 let v = f.pretty_function();
                 Pretty::childless_record("CreateFunction", v)
             }
+            DropFunction(f) => {
+                let fields = with_meta(f.pretty_function());
+                Pretty::childless_record("DropFunction", fields)
+            }
+            CreateSchema(s) => {
+                let fields = with_meta(s.pretty_schema());
+                Pretty::childless_record("CreateSchema", fields)
+            }
+            DropSchema(s) => {
+                let fields = with_meta(s.pretty_schema());
+                Pretty::childless_record("DropSchema", fields)
+            }
+            AlterTable(t) => {
+                let fields = with_meta(t.pretty_table());
+                Pretty::childless_record("AlterTable", fields)
+            }
+            CreateIndex(i) => {
+                let fields = with_meta(i.pretty_index());
+                Pretty::childless_record("CreateIndex", fields)
+            }
+            DropIndex(i) => {
+                let fields = with_meta(i.pretty_index());
+                Pretty::childless_record("DropIndex", fields)
+            }
             Drop(tables) => {
                 let fields = with_meta(vec![("objects", self.expr(tables).pretty())]);
                 Pretty::childless_record("Drop", fields)
             }
-            Insert([table, cols, child]) => Pretty::simple_record(
-                "Insert",
-                with_meta(vec![
+            Truncate(table) => {
+                let fields = with_meta(vec![("table", self.expr(table).pretty())]);
+                Pretty::childless_record("Truncate", fields)
+            }
+            Insert([table, cols, checks, returning, on_conflict, child]) => {
+                let mut fields = vec![
                     ("table", self.expr(table).pretty()),
                     ("cols", self.expr(cols).pretty()),
+                    ("checks", self.expr(checks).pretty()),
+                    ("returning", self.expr(returning).pretty()),
+                ];
+                if let OnConflict(_) = &self.expr[*on_conflict] {
+                    fields.push(("on_conflict", self.expr(on_conflict).pretty()));
+                }
+                Pretty::simple_record(
+                    "Insert",
+                    with_meta(fields),
+                    vec![self.child(child).pretty()],
+                )
+            }
+            OnConflict([key, values, cond]) => {
+                let mut fields = vec![("key", self.expr(key).pretty())];
+                if !self.expr[*values].as_list().is_empty() {
+                    fields.push(("values", self.expr(values).pretty()));
+                    if !self.is_true(cond) {
+                        fields.push(("cond", self.expr(cond).pretty()));
+                    }
+                }
+                Pretty::childless_record("OnConflict", fields)
+            }
+            Delete([table, returning, child]) => Pretty::simple_record(
+                "Delete",
+                with_meta(vec![
+                    ("table", self.expr(table).pretty()),
+                    ("returning", self.expr(returning).pretty()),
                 ]),
                 vec![self.child(child).pretty()],
             ),
-            Delete([table, child]) => Pretty::simple_record(
-                "Delete",
-                with_meta(vec![("table", self.expr(table).pretty())]),
+            Update([table, checks, returning, child]) => Pretty::simple_record(
+                "Update",
+                with_meta(vec![
+                    ("table", self.expr(table).pretty()),
+                    ("checks", self.expr(checks).pretty()),
+                    ("returning", self.expr(returning).pretty()),
+                ]),
                 vec![self.child(child).pretty()],
             ),
             CopyFrom([src, _]) => Pretty::childless_record(
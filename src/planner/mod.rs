@@ -3,16 +3,22 @@
 use egg::{define_language, Id, Symbol};
 
 use crate::binder::copy::ExtSource;
-use crate::binder::{CreateFunction, CreateTable};
+use crate::binder::{
+    AlterTable, CreateFunction, CreateIndex, CreateSchema, CreateTable, DropFunction, DropIndex,
+    DropSchema,
+};
 use crate::catalog::{ColumnRefId, TableRefId};
 use crate::parser::{BinaryOperator, UnaryOperator};
-use crate::types::{ColumnIndex, DataType, DataValue, DateTimeField};
+use crate::types::{ColumnIndex, DataType, DataValue, DateTimeField, ParamIndex};
 
+mod cache;
 mod cost;
 mod explain;
 mod optimizer;
+mod parallel;
 mod rules;
 
+pub use cache::{PlanCache, PlanCacheStats};
 pub use explain::Explain;
 pub use optimizer::{Config, Optimizer};
 pub use rules::{ExprAnalysis, Statistics, TypeError, TypeSchemaAnalysis};
@@ -31,12 +37,16 @@ define_language! {
         Column(ColumnRefId),            // $1.2, $2.1, ...
         Table(TableRefId),              // $1, $2, ...
         ColumnIndex(ColumnIndex),       // #0, #1, ...
+        Param(ParamIndex),              // ?1, ?2, ...
+                                            // a parameter of prepared statement
 
         // utilities
         "ref" = Ref(Id),                // (ref expr)
                                             // refer the expr as a column
                                             // it can also prevent optimization
         "list" = List(Box<[Id]>),       // (list ...)
+        "cte_column" = CteColumn([Id; 2]),  // (cte_column base #index)
+                                            // a column of recursive CTE
 
         // binary operations
         "+" = Add([Id; 2]),
@@ -55,6 +65,9 @@ define_language! {
         "or" = Or([Id; 2]),
         "xor" = Xor([Id; 2]),
         "like" = Like([Id; 2]),
+        "ilike" = ILike([Id; 2]),
+        "->" = JsonGet([Id; 2]),                // (-> json key|index)
+        "->>" = JsonGetText([Id; 2]),           // (->> json key|index)
 
         // unary operations
         "-" = Neg(Id),
@@ -68,6 +81,29 @@ define_language! {
             Field(DateTimeField),
         "replace" = Replace([Id; 3]),           // (replace expr pattern replacement)
         "substring" = Substring([Id; 3]),       // (substring expr start length)
+        "btrim" = Btrim([Id; 2]),               // (btrim expr characters)
+        "ltrim" = Ltrim([Id; 2]),               // (ltrim expr characters)
+        "rtrim" = Rtrim([Id; 2]),               // (rtrim expr characters)
+        "position" = Position([Id; 2]),         // (position substring expr)
+        "lpad" = Lpad([Id; 3]),                 // (lpad expr length fill)
+        "rpad" = Rpad([Id; 3]),                 // (rpad expr length fill)
+        "regexp_matches" = RegexpMatches([Id; 2]),  // (regexp_matches expr pattern)
+        "round" = Round([Id; 2]),               // (round expr digits)
+        "ceil" = Ceil(Id),
+        "floor" = Floor(Id),
+        "power" = Power([Id; 2]),               // (power base exponent)
+        "ln" = Ln(Id),
+        "log" = Log(Id),                        // (log expr)
+                                                    // base 10 logarithm
+        "greatest" = Greatest([Id; 2]),
+        "least" = Least([Id; 2]),
+
+        // array functions
+        "array" = Array(Id),                    // (array [expr..])
+        "array_get" = ArrayGet([Id; 2]),        // (array_get array index)
+        "array_length" = ArrayLength(Id),       // (array_length array)
+        "unnest" = Unnest(Id),                  // (unnest array)
+                                                    // only allowed in `project_set`
 
         // aggregations
         "max" = Max(Id),
@@ -76,28 +112,43 @@ define_language! {
         "avg" = Avg(Id),
         "count" = Count(Id),
         "count-distinct" = CountDistinct(Id),
+        "sum-distinct" = SumDistinct(Id),
         "rowcount" = RowCount,
         "first" = First(Id),
         "last" = Last(Id),
+        "string_agg" = StringAgg([Id; 3]),      // (string_agg expr delimiter [order_key..])
+        "array_agg" = ArrayAgg([Id; 2]),        // (array_agg expr [order_key..])
         // window functions
         "over" = Over([Id; 3]),                 // (over window_function [partition_key..] [order_key..])
         // TODO: support frame clause
             // "range" = Range([Id; 2]),               // (range start end)
         "row_number" = RowNumber,
+        "rank" = Rank,
+        "dense_rank" = DenseRank,
 
         // subquery related
         "exists" = Exists(Id),                  // (exists plan)
         "in" = In([Id; 2]),                     // (in expr plan)
 
         "cast" = Cast([Id; 2]),                 // (cast type expr)
+        "cast_tz" = CastTz([Id; 3]),            // (cast_tz type expr time_zone)
+                                                    // cast in the time zone given by seconds east of UTC
+                                                    // only built for executors
 
         // plans
         "scan" = Scan([Id; 3]),                 // (scan table [column..] filter)
+        "index_scan" = IndexScan([Id; 3]),      // (index_scan table [column..] cond)
+                                                    // `cond` is a range on the leading column of an index
+        "scan_as_of" = ScanAsOf([Id; 3]),       // (scan_as_of table [column..] version)
+                                                    // read the table at an epoch or a timestamp
         "values" = Values(Box<[Id]>),           // (values [expr..]..)
         "proj" = Proj([Id; 2]),                 // (proj [expr..] child)
         "filter" = Filter([Id; 2]),             // (filter expr child)
         "order" = Order([Id; 2]),               // (order [order_key..] child)
             "desc" = Desc(Id),                      // (desc key)
+            "nulls_first" = NullsFirst(Id),         // (nulls_first (desc key))
+            "nulls_last" = NullsLast(Id),           // (nulls_last key)
+                                                    // NULL is the smallest value by default
         "limit" = Limit([Id; 3]),               // (limit limit offset child)
         "topn" = TopN([Id; 4]),                 // (topn limit offset [order_key..] child)
         "join" = Join([Id; 4]),                 // (join join_type cond left right)
@@ -118,12 +169,45 @@ define_language! {
                                                     // child must be ordered by keys
         "window" = Window([Id; 2]),             // (window [over..] child)
                                                     // output = child || exprs
+        "project_set" = ProjectSet([Id; 2]),    // (project_set [expr..] child)
+                                                    // expand each `unnest` into multiple rows
+        "recursive_union" = RecursiveUnion([Id; 3]),    // (recursive_union [column..] base recursive)
+                                                        // run `recursive` on the working table
+                                                        // until it produces no rows
+        "working_table" = WorkingTable(Id),     // (working_table [column..])
+                                                    // output of the last iteration
+        "file_scan" = FileScan([Id; 2]),        // (file_scan source [column..])
+                                                    // read all columns of an external file
+        "generate_series" = GenerateSeries([Id; 2]),    // (generate_series [start stop step inclusive] [column])
+                                                        // generate values from start to stop by step
+        "values_scan" = ValuesScan([Id; 2]),    // (values_scan child [column..])
+                                                    // output rows of a VALUES query as a table
+        "exchange" = Exchange([Id; 2]),         // (exchange dist child)
+                                                    // redistribute rows of child into partitions
+            "single" = Single,                      // all rows in one partition
+            "random" = Random,                      // rows distributed in any way
+            "hash" = Hash(Id),                      // (hash [key..])
+                                                    // rows distributed by the hash of keys
         CreateTable(Box<CreateTable>),
-        "create_view" = CreateView([Id; 2]),    // (create_view create_table child)
+        "create_view" = CreateView([Id; 3]),    // (create_view create_table [table..] child)
+        "create_table_as" = CreateTableAs([Id; 2]), // (create_table_as create_table child)
         CreateFunction(CreateFunction),
+        DropFunction(Box<DropFunction>),
+        CreateSchema(Box<CreateSchema>),
+        DropSchema(Box<DropSchema>),
         "drop" = Drop(Id),                      // (drop [table..])
-        "insert" = Insert([Id; 3]),             // (insert table [column..] child)
-        "delete" = Delete([Id; 2]),             // (delete table child)
+        "truncate" = Truncate(Id),              // (truncate table)
+        AlterTable(Box<AlterTable>),
+        CreateIndex(Box<CreateIndex>),
+        DropIndex(Box<DropIndex>),
+        "insert" = Insert([Id; 6]),             // (insert table [column..] [check..] [returning..] on_conflict child)
+                                                    // on_conflict is an empty list without the clause
+        "on_conflict" = OnConflict([Id; 3]),    // (on_conflict [key..] [value..] cond)
+                                                    // values of all columns on the existing and excluded
+                                                    // rows, or an empty list to do nothing
+        "delete" = Delete([Id; 3]),             // (delete table [returning..] child)
+        "update" = Update([Id; 4]),             // (update table [check..] [returning..] child)
+                                                    // child returns new rows and old row ids
         "copy_from" = CopyFrom([Id; 2]),        // (copy_from dest types)
         "copy_to" = CopyTo([Id; 2]),            // (copy_to dest child)
             ExtSource(Box<ExtSource>),
@@ -247,14 +331,17 @@ impl Expr {
                 | Avg(_)
                 | Count(_)
                 | CountDistinct(_)
+                | SumDistinct(_)
                 | First(_)
                 | Last(_)
+                | StringAgg(_)
+                | ArrayAgg(_)
         )
     }
 
     pub const fn is_window_function(&self) -> bool {
         use Expr::*;
-        matches!(self, RowNumber) || self.is_aggregate_function()
+        matches!(self, RowNumber | Rank | DenseRank) || self.is_aggregate_function()
     }
 }
 
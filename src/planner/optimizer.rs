@@ -2,6 +2,7 @@
 
 use std::sync::LazyLock;
 
+use chrono::{FixedOffset, Offset, Utc};
 use egg::CostFunction;
 
 use super::*;
@@ -14,10 +15,40 @@ pub struct Optimizer {
 }
 
 /// Optimizer configurations.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Config {
     pub enable_range_filter_scan: bool,
+    /// Whether scans can be replaced by index scans. Indexes only contain committed rows.
+    pub enable_index_scan: bool,
     pub table_is_sorted_by_primary_key: bool,
+    /// The maximum number of iterations of a recursive CTE.
+    pub max_recursive_iterations: usize,
+    /// The number of partitions processed in parallel. Plans are not parallelized if it is 1.
+    pub parallelism: usize,
+    /// The memory budget of a query in bytes. Hash aggregations and sorts spill to disk when
+    /// exceeding it. Unlimited if `None`.
+    pub memory_limit: Option<usize>,
+    /// The maximum number of malformed rows skipped by `COPY FROM`. Loads are aborted on the
+    /// first malformed row if it is 0.
+    pub copy_max_errors: usize,
+    /// The time zone of the session. Timestamps with time zone are converted from or to strings
+    /// in it.
+    pub time_zone: FixedOffset,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enable_range_filter_scan: false,
+            enable_index_scan: false,
+            table_is_sorted_by_primary_key: false,
+            max_recursive_iterations: 1000,
+            parallelism: 1,
+            memory_limit: None,
+            copy_max_errors: 0,
+            time_zone: Utc.fix(),
+        }
+    }
 }
 
 impl Optimizer {
@@ -34,6 +65,10 @@ impl Optimizer {
 
     /// Optimize the given expression.
     pub fn optimize(&self, mut expr: RecExpr) -> RecExpr {
+        // the query of a view is stored as bound, and optimized where the view is inlined
+        if let Some(Expr::CreateView(_)) = expr.as_ref().last() {
+            return expr;
+        }
         let mut cost = f32::MAX;
 
         // define extra rules for some configurations
@@ -41,6 +76,9 @@ impl Optimizer {
         if self.analysis.config.enable_range_filter_scan {
             extra_rules.append(&mut rules::range::filter_scan_rule());
         }
+        if self.analysis.config.enable_index_scan {
+            extra_rules.append(&mut rules::range::index_scan_rules());
+        }
 
         // 1. pushdown apply
         self.optimize_stage(&mut expr, &mut cost, STAGE1_RULES.iter(), 2, 6);
@@ -49,6 +87,10 @@ impl Optimizer {
         self.optimize_stage(&mut expr, &mut cost, rules, 4, 6);
         // 3. join reorder and hashjoin
         self.optimize_stage(&mut expr, &mut cost, STAGE3_RULES.iter(), 3, 8);
+        // 4. insert exchanges to process partitions in parallel
+        if self.analysis.config.parallelism > 1 {
+            expr = parallel::to_parallel_plan(&self.analysis, &expr);
+        }
         expr
     }
 
@@ -108,6 +150,11 @@ impl Optimizer {
     pub fn catalog(&self) -> &RootCatalogRef {
         &self.analysis.catalog
     }
+
+    /// Returns the configurations.
+    pub fn config(&self) -> &Config {
+        &self.analysis.config
+    }
 }
 
 /// Stage1 rules in the optimizer.
@@ -0,0 +1,262 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Converts plans into parallel plans.
+//!
+//! In a parallel plan, a plan node may run on multiple partitions of its input at the same time.
+//! The distribution of rows in partitions is described by [`Distribution`]. Table scans are split
+//! into partitions, and `exchange` nodes are inserted where a node requires a different
+//! distribution from what its child provides.
+
+use std::collections::HashMap;
+
+use super::*;
+
+/// Describes how rows are distributed in partitions.
+#[derive(Debug, Clone, PartialEq)]
+enum Distribution {
+    /// All rows are in one partition.
+    Single,
+    /// Rows are distributed in any way.
+    Random,
+    /// Rows with the same keys are in the same partition.
+    Hash(Vec<Id>),
+}
+
+/// Returns the parallel plan of an optimized plan.
+pub fn to_parallel_plan(analysis: &ExprAnalysis, plan: &RecExpr) -> RecExpr {
+    let mut egraph = EGraph::new(analysis.clone());
+    let mut classes: Vec<Id> = vec![];
+    for node in plan.as_ref() {
+        let node = node.clone().map_children(|id| classes[usize::from(id)]);
+        classes.push(egraph.add(node));
+    }
+    let mut canonical = HashMap::new();
+    for (i, class) in classes.iter().enumerate() {
+        canonical.entry(*class).or_insert(Id::from(i));
+    }
+    let mut converter = ToParallel {
+        input: plan,
+        egraph: &egraph,
+        classes,
+        canonical,
+        output: RecExpr::default(),
+    };
+    let root = Id::from(plan.as_ref().len() - 1);
+    let (id, dist) = converter.visit(root, true);
+    converter.exchange(id, &dist, &Distribution::Single);
+    converter.output
+}
+
+struct ToParallel<'a> {
+    input: &'a RecExpr,
+    /// The e-graph containing the input plan, used to get the analysis data of nodes.
+    egraph: &'a EGraph,
+    /// The e-class of each node in the input plan.
+    classes: Vec<Id>,
+    /// The first node in the input plan of each e-class.
+    canonical: HashMap<Id, Id>,
+    output: RecExpr,
+}
+
+impl ToParallel<'_> {
+    /// Converts a plan and returns the converted plan with the distribution of its output.
+    ///
+    /// If `keep_order` is true, the order of output rows must be kept.
+    fn visit(&mut self, id: Id, keep_order: bool) -> (Id, Distribution) {
+        use Expr::*;
+        let node = self.input[id].clone();
+        match node {
+            Scan([table, _, _]) => {
+                let table_id = self.input[table].as_table();
+                let catalog = &self.egraph.analysis.catalog;
+                // system tables are not stored in the storage
+                let partitioned = (catalog.get_table(&table_id))
+                    .map_or(true, |table| !table.is_system())
+                    // rows may be ordered by primary key
+                    && !(keep_order && !self.data(id).orderby.is_empty());
+                let scan = self.copy(id);
+                if !partitioned {
+                    return (scan, Distribution::Single);
+                }
+                let scan = self.exchange(scan, &Distribution::Single, &Distribution::Random);
+                (scan, Distribution::Random)
+            }
+            Proj([_, child]) | Filter([_, child]) | ProjectSet([_, child]) => {
+                let (new_child, dist) = self.visit(child, keep_order);
+                // keys may not be in the output
+                let schema = &self.data(id).schema;
+                let dist = match dist {
+                    Distribution::Hash(keys)
+                        if !keys.iter().all(|key| schema.contains(&self.class(*key))) =>
+                    {
+                        Distribution::Random
+                    }
+                    dist => dist,
+                };
+                (self.add(&node, &[(child, new_child)]), dist)
+            }
+            HashAgg([keys, _, child]) => {
+                let (new_child, dist) = self.visit(child, false);
+                if dist == Distribution::Single {
+                    return (self.add(&node, &[(child, new_child)]), dist);
+                }
+                let required = Distribution::Hash(self.keys(keys));
+                let new_child = self.exchange(new_child, &dist, &required);
+                (self.add(&node, &[(child, new_child)]), required)
+            }
+            HashJoin([op, _, lkeys, rkeys, left, right]) => {
+                let (new_left, ldist) = self.visit(left, false);
+                let (new_right, rdist) = self.visit(right, false);
+                if ldist == Distribution::Single && rdist == Distribution::Single {
+                    let children = [(left, new_left), (right, new_right)];
+                    return (self.add(&node, &children), Distribution::Single);
+                }
+                // partition both sides by join keys, so that matched rows are in the same partition
+                let lhash = Distribution::Hash(self.keys(lkeys));
+                let rhash = Distribution::Hash(self.keys(rkeys));
+                let new_left = self.exchange(new_left, &ldist, &lhash);
+                let new_right = self.exchange(new_right, &rdist, &rhash);
+                let children = [(left, new_left), (right, new_right)];
+                let dist = match self.input[op] {
+                    // left keys are null in rows only from the right side
+                    RightOuter | FullOuter => Distribution::Random,
+                    _ => lhash,
+                };
+                (self.add(&node, &children), dist)
+            }
+            // plans that sort or don't care about the order of input
+            Order([_, child])
+            | TopN([_, _, _, child])
+            | Agg([_, child])
+            | Insert([_, _, _, _, _, child])
+            | CreateTableAs([_, child])
+            | Delete([_, _, child])
+            | Update([_, _, _, child]) => self.gather(&node, &[child], false),
+            Join([_, _, left, right]) => self.gather(&node, &[left, right], false),
+            // plans that keep the order of input
+            Limit([_, _, child]) | Window([_, child]) | CopyTo([_, child]) | Explain(child)
+            | Analyze(child) => self.gather(&node, &[child], keep_order),
+            // plans that require ordered input
+            SortAgg([_, _, child]) => self.gather(&node, &[child], true),
+            MergeJoin([_, _, _, _, left, right]) => self.gather(&node, &[left, right], true),
+            // other plans are not parallelized
+            _ => (self.copy(id), Distribution::Single),
+        }
+    }
+
+    /// Converts a plan whose children must be in a single partition.
+    fn gather(&mut self, node: &Expr, children: &[Id], keep_order: bool) -> (Id, Distribution) {
+        let mut converted = vec![];
+        for &child in children {
+            let (new_child, dist) = self.visit(child, keep_order);
+            let new_child = self.exchange(new_child, &dist, &Distribution::Single);
+            converted.push((child, new_child));
+        }
+        (self.add(node, &converted), Distribution::Single)
+    }
+
+    /// Inserts an exchange over the plan if its distribution is not the required one.
+    fn exchange(&mut self, id: Id, dist: &Distribution, required: &Distribution) -> Id {
+        if dist == required {
+            return id;
+        }
+        let dist = match required {
+            Distribution::Single => self.output.add(Expr::Single),
+            Distribution::Random => self.output.add(Expr::Random),
+            Distribution::Hash(keys) => {
+                let keys = keys.iter().map(|key| self.copy(*key)).collect();
+                let list = self.output.add(Expr::List(keys));
+                self.output.add(Expr::Hash(list))
+            }
+        };
+        self.output.add(Expr::Exchange([dist, id]))
+    }
+
+    /// Adds a node to the output plan. Its children are replaced by the converted ones if given,
+    /// or copied otherwise.
+    fn add(&mut self, node: &Expr, converted: &[(Id, Id)]) -> Id {
+        let node = node.clone().map_children(|id| {
+            match converted.iter().find(|(child, _)| *child == id) {
+                Some((_, new_child)) => *new_child,
+                None => self.copy(id),
+            }
+        });
+        self.output.add(node)
+    }
+
+    /// Copies an expression or a plan to the output plan without change.
+    fn copy(&mut self, id: Id) -> Id {
+        let node = self.input[id].clone().map_children(|id| self.copy(id));
+        self.output.add(node)
+    }
+
+    /// Returns the keys in a list. Equal keys are represented by the same id.
+    fn keys(&self, list: Id) -> Vec<Id> {
+        (self.input[list].as_list().iter())
+            .map(|id| self.canonical[&self.class(*id)])
+            .collect()
+    }
+
+    /// Returns the e-class of a node in the input plan.
+    fn class(&self, id: Id) -> Id {
+        self.classes[usize::from(id)]
+    }
+
+    /// Returns the analysis data of a node in the input plan.
+    fn data(&self, id: Id) -> &rules::Data {
+        &self.egraph[self.class(id)].data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // tables are in schema 1, since schema 0 is the system schema
+
+    fn to_parallel(plan: &str) -> String {
+        let plan = plan.parse().unwrap();
+        to_parallel_plan(&ExprAnalysis::default(), &plan).to_string()
+    }
+
+    fn pretty(plan: &str) -> String {
+        plan.parse::<RecExpr>().unwrap().to_string()
+    }
+
+    #[test]
+    fn hash_agg() {
+        assert_eq!(
+            to_parallel(
+                "(hashagg (list $1.1.1) (list (count $1.1.2))
+                    (scan $1.1 (list $1.1.1 $1.1.2) true))"
+            ),
+            pretty(
+                "(exchange single
+                    (hashagg (list $1.1.1) (list (count $1.1.2))
+                        (exchange (hash (list $1.1.1))
+                            (exchange random (scan $1.1 (list $1.1.1 $1.1.2) true)))))"
+            )
+        );
+    }
+
+    #[test]
+    fn hash_join() {
+        assert_eq!(
+            to_parallel(
+                "(order (list $1.1.1)
+                    (hashjoin inner true (list $1.1.1) (list $1.2.1)
+                        (scan $1.1 (list $1.1.1) true)
+                        (scan $1.2 (list $1.2.1) true)))"
+            ),
+            pretty(
+                "(order (list $1.1.1)
+                    (exchange single
+                        (hashjoin inner true (list $1.1.1) (list $1.2.1)
+                            (exchange (hash (list $1.1.1))
+                                (exchange random (scan $1.1 (list $1.1.1) true)))
+                            (exchange (hash (list $1.2.1))
+                                (exchange random (scan $1.2 (list $1.2.1) true))))))"
+            )
+        );
+    }
+}
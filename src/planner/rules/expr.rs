@@ -2,6 +2,8 @@
 
 //! Expression simplification rules and constant folding.
 
+use chrono::FixedOffset;
+
 use super::*;
 use crate::array::ArrayImpl;
 use crate::types::DataValue;
@@ -98,6 +100,10 @@ pub fn rules() -> Vec<Rewrite> { vec![
 
     rw!("avg";       "(avg ?a)" => "(/ (sum ?a) (count ?a))"),
 
+    // convert LIKE with a constant prefix into range predicates,
+    // so that they can be used to prune data by zone maps and indexes.
+    rw!("like-prefix"; "(like ?a ?pattern)" => { like_to_range() }),
+
     // Extract Common Predicate
     // example:
     //            OR
@@ -153,15 +159,22 @@ pub fn eval_constant(egraph: &EGraph, enode: &Expr) -> ConstValue {
         Some(array_a.unary_op(&op).ok()?.get(0))
     } else if let &IsNull(a) = enode {
         Some(DataValue::Bool(x(a)?.is_null()))
-    } else if let &Cast([ty, a]) = enode {
+    } else if let &Cast([ty, a]) | &CastTz([ty, a, _]) = enode {
         let a = x(a)?;
         let ty = egraph[ty].nodes[0].as_type();
         // don't eval cast if data type can not be kept
         if a.is_null() && !ty.is_null() || ty.is_parametric_decimal() {
             return None;
         }
+        let tz = match enode {
+            CastTz([_, _, tz]) => match x(*tz)? {
+                DataValue::Int32(secs) => FixedOffset::east_opt(*secs)?,
+                _ => return None,
+            },
+            _ => egraph.analysis.config.time_zone,
+        };
         // TODO: handle cast error
-        a.cast(ty).ok()
+        a.cast_in_time_zone(ty, tz).ok()
     } else if let &Max(a) | &Min(a) | &Avg(a) | &First(a) | &Last(a) = enode {
         x(a).cloned()
     } else {
@@ -179,6 +192,96 @@ pub fn union_constant(egraph: &mut EGraph, id: Id) {
     }
 }
 
+/// Returns an applier that converts `?a LIKE 'literal'` into `?a = 'literal'`,
+/// and `?a LIKE 'prefix%'` into `?a >= 'prefix' AND ?a < 'prefiy'`.
+fn like_to_range() -> impl Applier<Expr, ExprAnalysis> {
+    struct LikeToRange {
+        eq: Pattern,
+        range: Pattern,
+        ge: Pattern,
+        pattern: Var,
+        low: Var,
+        high: Var,
+    }
+    impl Applier<Expr, ExprAnalysis> for LikeToRange {
+        fn apply_one(
+            &self,
+            egraph: &mut EGraph,
+            eclass: Id,
+            subst: &Subst,
+            searcher_ast: Option<&PatternAst<Expr>>,
+            rule_name: Symbol,
+        ) -> Vec<Id> {
+            let Some(DataValue::String(pattern)) = &egraph[subst[self.pattern]].data.constant
+            else {
+                return vec![];
+            };
+            let Some((prefix, exact)) = like_prefix(pattern) else {
+                return vec![];
+            };
+            let mut subst = subst.clone();
+            let next = next_string(&prefix);
+            subst.insert(
+                self.low,
+                egraph.add(Expr::Constant(DataValue::String(prefix.into()))),
+            );
+            let pattern = if exact {
+                &self.eq
+            } else if let Some(next) = next {
+                subst.insert(
+                    self.high,
+                    egraph.add(Expr::Constant(DataValue::String(next.into()))),
+                );
+                &self.range
+            } else {
+                &self.ge
+            };
+            pattern.apply_one(egraph, eclass, &subst, searcher_ast, rule_name)
+        }
+    }
+    LikeToRange {
+        eq: pattern("(= ?a ?low)"),
+        range: pattern("(and (>= ?a ?low) (< ?a ?high))"),
+        ge: pattern("(>= ?a ?low)"),
+        pattern: var("?pattern"),
+        low: var("?low"),
+        high: var("?high"),
+    }
+}
+
+/// Returns the literal prefix of a LIKE pattern, and whether the pattern has no wildcard.
+///
+/// Returns `None` if the pattern is not in the form of `literal` or `prefix%`.
+fn like_prefix(pattern: &str) -> Option<(String, bool)> {
+    let mut prefix = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => prefix.push(chars.next().unwrap_or('\\')),
+            '%' if !prefix.is_empty() && chars.all(|c| c == '%') => return Some((prefix, false)),
+            '%' | '_' => return None,
+            c => prefix.push(c),
+        }
+    }
+    Some((prefix, true))
+}
+
+/// Returns the smallest string greater than all strings starting with `prefix`.
+fn next_string(prefix: &str) -> Option<String> {
+    let mut s = prefix.to_string();
+    while let Some(c) = s.pop() {
+        let next = match c {
+            '\u{D7FF}' => Some('\u{E000}'),
+            c => char::from_u32(c as u32 + 1),
+        };
+        if let Some(next) = next {
+            s.push(next);
+            return Some(s);
+        }
+    }
+    None
+}
+
 /// Returns true if the expression is a non-zero constant.
 fn is_not_zero(var: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
     value_is(var, |v| !v.is_zero())
@@ -313,6 +416,18 @@ mod tests {
         "(and (< a 2) (<= a 1))" => "(<= a 1)",
     }
 
+    egg::test_fn! {
+        like_prefix,
+        rules(),
+        "(like a 'abc%')" => "(and (>= a 'abc') (< a 'abd'))",
+    }
+
+    egg::test_fn! {
+        like_literal,
+        rules(),
+        "(like a 'a\\%c')" => "(= a 'a%c')",
+    }
+
     egg::test_fn! {
         constant_gt_lt_conflict,
         rules(),
@@ -208,3 +208,12 @@ fn var(s: &str) -> Var {
 fn pattern(s: &str) -> Pattern {
     s.parse().expect("invalid pattern")
 }
+
+/// Returns true if the join type `ty` is one of `types`.
+///
+/// This is a helper function for submodules.
+fn is_join_type(ty: &str, types: &[Expr]) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let ty = var(ty);
+    let types = types.to_vec();
+    move |egraph, _, subst| egraph[subst[ty]].nodes.iter().any(|e| types.contains(e))
+}
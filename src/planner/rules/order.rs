@@ -48,15 +48,18 @@ pub fn order_rules() -> Vec<Rewrite> { vec![
         if is_orderby("?keys", "?child")
     ),
     rw!("merge-join";
-        "(hashjoin ?type ?cond ?lkey ?rkey ?left ?right)" =>
-        "(mergejoin ?type ?cond ?lkey ?rkey ?left ?right)"
+        "(hashjoin ?type true ?lkey ?rkey ?left ?right)" =>
+        "(mergejoin ?type true ?lkey ?rkey ?left ?right)"
         if is_orderby("?lkey", "?left")
         if is_orderby("?rkey", "?right")
+        // semi and anti join are not supported by merge join
+        if is_join_type("?type", &[Expr::Inner, Expr::LeftOuter, Expr::RightOuter, Expr::FullOuter])
     ),
+    // also turns SELECT DISTINCT into a streaming deduplication if the input is sorted
     rw!("sort-agg";
         "(hashagg ?keys ?aggs ?child)" =>
         "(sortagg ?keys ?aggs ?child)"
-        if is_orderby("?keys", "?child")
+        if is_grouped_by("?keys", "?child")
     ),
 ]}
 
@@ -70,3 +73,35 @@ fn is_orderby(keys: &str, plan: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> boo
         plan_keys.starts_with(keys)
     }
 }
+
+/// Returns true if rows with the same keys are adjacent in the plan.
+///
+/// That is, the plan is ordered by the keys in any order and direction.
+fn is_grouped_by(keys: &str, plan: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let keys = var(keys);
+    let plan = var(plan);
+    move |egraph, _, subst| {
+        let keys: Vec<Id> = (egraph[subst[keys]].data.orderby.iter())
+            .map(|id| egraph.find(*id))
+            .collect();
+        let plan_keys = &egraph[subst[plan]].data.orderby;
+        if plan_keys.len() < keys.len() {
+            return false;
+        }
+        // strip directions from order keys, e.g. `(nulls_first (desc a))` => `a`
+        let strip = |mut id: Id| loop {
+            let key = egraph[id].iter().find_map(|e| match e {
+                Expr::Desc(key) | Expr::NullsFirst(key) | Expr::NullsLast(key) => Some(*key),
+                _ => None,
+            });
+            match key {
+                Some(key) => id = key,
+                None => return egraph.find(id),
+            }
+        };
+        let prefix: Vec<Id> = (plan_keys[..keys.len()].iter())
+            .map(|id| strip(*id))
+            .collect();
+        keys.iter().all(|k| prefix.contains(k)) && prefix.iter().all(|k| keys.contains(k))
+    }
+}
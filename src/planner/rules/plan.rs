@@ -7,6 +7,7 @@ use itertools::Itertools;
 use super::schema::schema_is_eq;
 use super::*;
 use crate::planner::ExprExt;
+use crate::types::DataValue;
 
 /// Returns the rules that always improve the plan.
 pub fn always_better_rules() -> Vec<Rewrite> {
@@ -27,9 +28,11 @@ fn cancel_rules() -> Vec<Rewrite> { vec![
 
 #[rustfmt::skip]
 fn merge_rules() -> Vec<Rewrite> { vec![
+    // only when the number of rows is limited, so that the heap of topn is bounded
     rw!("limit-order-topn";
         "(limit ?limit ?offset (order ?keys ?child))" =>
         "(topn ?limit ?offset ?keys ?child)"
+        if is_not_null("?limit")
     ),
     rw!("filter-merge";
         "(filter ?cond1 (filter ?cond2 ?child))" =>
@@ -56,6 +59,11 @@ pub fn predicate_pushdown_rules() -> Vec<Rewrite> { vec![
         "(hashagg ?keys ?aggs (filter ?cond ?child))"
         if not_depend_on("?cond", "?aggs")
     ),
+    rw!("pushdown-filter-sortagg";
+        "(filter ?cond (sortagg ?keys ?aggs ?child))" =>
+        "(sortagg ?keys ?aggs (filter ?cond ?child))"
+        if not_depend_on("?cond", "?aggs")
+    ),
     rw!("pushdown-filter-inner-join";
         "(filter ?cond (join inner ?on ?left ?right))" =>
         "(join inner (and ?on ?cond) ?left ?right)"
@@ -74,25 +82,56 @@ pub fn predicate_pushdown_rules() -> Vec<Rewrite> { vec![
         "(join left_outer ?on (filter ?cond ?left) ?right)"
         if not_depend_on("?cond", "?right")
     ),
+    rw!("pushdown-filter-right-outer-join";
+        "(filter ?cond (join right_outer ?on ?left ?right))" =>
+        "(join right_outer ?on ?left (filter ?cond ?right))"
+        if not_depend_on("?cond", "?left")
+    ),
+    // rows padded with nulls by an outer join are removed by a filter rejecting nulls
+    rw!("left-outer-join-to-inner-join";
+        "(filter ?cond (join left_outer ?on ?left ?right))" =>
+        "(filter ?cond (join inner ?on ?left ?right))"
+        if null_reject("?right", "?cond")
+    ),
+    rw!("right-outer-join-to-inner-join";
+        "(filter ?cond (join right_outer ?on ?left ?right))" =>
+        "(filter ?cond (join inner ?on ?left ?right))"
+        if null_reject("?left", "?cond")
+    ),
+    rw!("full-outer-join-to-left-outer-join";
+        "(filter ?cond (join full_outer ?on ?left ?right))" =>
+        "(filter ?cond (join left_outer ?on ?left ?right))"
+        if null_reject("?left", "?cond")
+    ),
+    rw!("full-outer-join-to-right-outer-join";
+        "(filter ?cond (join full_outer ?on ?left ?right))" =>
+        "(filter ?cond (join right_outer ?on ?left ?right))"
+        if null_reject("?right", "?cond")
+    ),
+    // a condition on one side can only be pushed down if the join does not preserve its rows
     rw!("pushdown-join-condition-left";
         "(join ?type (and ?cond1 ?cond2) ?left ?right)" =>
         "(join ?type ?cond2 (filter ?cond1 ?left) ?right)"
         if not_depend_on("?cond1", "?right")
+        if is_join_type("?type", &[Expr::Inner, Expr::Semi, Expr::RightOuter])
     ),
     rw!("pushdown-join-condition-left-1";
         "(join ?type ?cond1 ?left ?right)" =>
         "(join ?type true (filter ?cond1 ?left) ?right)"
         if not_depend_on("?cond1", "?right")
+        if is_join_type("?type", &[Expr::Inner, Expr::Semi, Expr::RightOuter])
     ),
     rw!("pushdown-join-condition-right";
         "(join ?type (and ?cond1 ?cond2) ?left ?right)" =>
         "(join ?type ?cond2 ?left (filter ?cond1 ?right))"
         if not_depend_on("?cond1", "?left")
+        if is_join_type("?type", &[Expr::Inner, Expr::Semi, Expr::Anti, Expr::LeftOuter])
     ),
     rw!("pushdown-join-condition-right-1";
         "(join ?type ?cond1 ?left ?right)" =>
         "(join ?type true ?left (filter ?cond1 ?right))"
         if not_depend_on("?cond1", "?left")
+        if is_join_type("?type", &[Expr::Inner, Expr::Semi, Expr::Anti, Expr::LeftOuter])
     ),
     rw!("pushdown-filter-apply-left";
         "(filter ?cond (apply ?type ?left ?right))" =>
@@ -137,8 +176,185 @@ pub fn join_reorder_rules() -> Vec<Rewrite> { vec![
         "(proj ?proj (hashjoin inner ?cond ?lkeys ?rkeys ?left ?right))" =>
         "(proj ?proj (hashjoin inner ?cond ?rkeys ?lkeys ?right ?left))"
     ),
+    // rotations and swaps can only reach a few orders of a multi-way join in limited iterations,
+    // so a greedy order by estimated rows is added as a shortcut.
+    rw!("inner-join-greedy-reorder";
+        "(proj ?proj (join inner ?cond ?left ?right))" =>
+        { greedy_join_reorder() }
+    ),
 ]}
 
+/// The maximum depth of a join tree to be flattened by the greedy join reorder.
+const MAX_JOIN_DEPTH: usize = 16;
+
+/// Returns an applier that reorders a multi-way inner join under a projection greedily.
+///
+/// The join tree is flattened into relations and conditions. Starting from the relation with the
+/// fewest rows, it repeatedly joins the relation that produces the fewest rows, preferring those
+/// connected by a condition, and builds a left-deep tree. The cost function decides whether the
+/// new tree is better than the others.
+fn greedy_join_reorder() -> impl Applier<Expr, ExprAnalysis> {
+    struct GreedyJoinReorder {
+        proj: Var,
+        cond: Var,
+        left: Var,
+        right: Var,
+    }
+    impl Applier<Expr, ExprAnalysis> for GreedyJoinReorder {
+        fn apply_one(
+            &self,
+            egraph: &mut EGraph,
+            eclass: Id,
+            subst: &Subst,
+            _searcher_ast: Option<&PatternAst<Expr>>,
+            _rule_name: Symbol,
+        ) -> Vec<Id> {
+            let mut relations = vec![];
+            let mut conditions = vec![];
+            flatten_condition(egraph, subst[self.cond], &mut vec![], &mut conditions);
+            flatten_inner_join(egraph, subst[self.left], 1, &mut relations, &mut conditions);
+            flatten_inner_join(
+                egraph,
+                subst[self.right],
+                1,
+                &mut relations,
+                &mut conditions,
+            );
+            if relations.len() < 3 {
+                // 2-way joins are handled by the swap rule
+                return vec![];
+            }
+            conditions.sort();
+            conditions.dedup();
+
+            let rows = |egraph: &EGraph, id: Id| egraph[id].data.rows;
+            let first = (0..relations.len())
+                .min_by(|&a, &b| rows(egraph, relations[a]).total_cmp(&rows(egraph, relations[b])))
+                .unwrap();
+            let mut tree = relations.swap_remove(first);
+            let mut columns: HashSet<Expr> = produced(egraph, tree).collect();
+            while !relations.is_empty() {
+                // (is cross join, rows, index of relation, conditions)
+                let mut best: Option<(bool, f32, usize, Vec<Id>)> = None;
+                for (i, &relation) in relations.iter().enumerate() {
+                    let mut new_columns = columns.clone();
+                    new_columns.extend(produced(egraph, relation));
+                    let conds = (conditions.iter().copied())
+                        .filter(|c| egraph[*c].data.columns.is_subset(&new_columns))
+                        .collect_vec();
+                    let new_rows = (conds.iter())
+                        .fold(rows(egraph, tree) * rows(egraph, relation), |r, c| {
+                            r * egraph[*c].data.rows
+                        });
+                    let key = (conds.is_empty(), new_rows);
+                    if best.as_ref().map_or(true, |(c, r, ..)| key < (*c, *r)) {
+                        best = Some((key.0, key.1, i, conds));
+                    }
+                }
+                let (_, _, i, conds) = best.unwrap();
+                let relation = relations.swap_remove(i);
+                columns.extend(produced(egraph, relation));
+                conditions.retain(|c| !conds.contains(c));
+                let cond = and_all(egraph, &conds);
+                let inner = egraph.add(Expr::Inner);
+                tree = egraph.add(Expr::Join([inner, cond, tree, relation]));
+            }
+            if !conditions.is_empty() {
+                // conditions not on columns of the relations, e.g. on outer columns
+                let cond = and_all(egraph, &conditions);
+                tree = egraph.add(Expr::Filter([cond, tree]));
+            }
+            let id = egraph.add(Expr::Proj([subst[self.proj], tree]));
+            if egraph.union(eclass, id) {
+                vec![eclass]
+            } else {
+                vec![]
+            }
+        }
+    }
+    GreedyJoinReorder {
+        proj: var("?proj"),
+        cond: var("?cond"),
+        left: var("?left"),
+        right: var("?right"),
+    }
+}
+
+/// Collects the relations and conditions of a tree of inner joins.
+///
+/// Projections between joins are skipped since they only prune columns.
+fn flatten_inner_join(
+    egraph: &EGraph,
+    id: Id,
+    depth: usize,
+    relations: &mut Vec<Id>,
+    conditions: &mut Vec<Id>,
+) {
+    let is_inner_join = |id: Id| {
+        egraph[id].iter().any(|e| match e {
+            Expr::Join([ty, ..]) => egraph[*ty].nodes.contains(&Expr::Inner),
+            _ => false,
+        })
+    };
+    let is_column =
+        |id: &Id| (egraph[*id].iter()).any(|e| matches!(e, Expr::Column(_) | Expr::Ref(_)));
+    if depth < MAX_JOIN_DEPTH {
+        for node in egraph[id].iter() {
+            match *node {
+                Expr::Join([ty, cond, left, right])
+                    if egraph[ty].nodes.contains(&Expr::Inner) && left != id && right != id =>
+                {
+                    flatten_condition(egraph, cond, &mut vec![], conditions);
+                    flatten_inner_join(egraph, left, depth + 1, relations, conditions);
+                    flatten_inner_join(egraph, right, depth + 1, relations, conditions);
+                    return;
+                }
+                Expr::Proj([exprs, child])
+                    if child != id
+                        && is_inner_join(child)
+                        && egraph[exprs].as_list().iter().all(is_column) =>
+                {
+                    flatten_inner_join(egraph, child, depth + 1, relations, conditions);
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+    relations.push(id);
+}
+
+/// Splits a condition into conjuncts.
+///
+/// `visiting` is the stack of conditions being split, used to avoid cycles in the e-graph.
+fn flatten_condition(egraph: &EGraph, id: Id, visiting: &mut Vec<Id>, conditions: &mut Vec<Id>) {
+    if egraph[id].data.constant == Some(DataValue::Bool(true)) {
+        return;
+    }
+    visiting.push(id);
+    let and = egraph[id].iter().find_map(|e| match *e {
+        Expr::And([a, b]) if !visiting.contains(&a) && !visiting.contains(&b) => Some((a, b)),
+        _ => None,
+    });
+    match and {
+        Some((a, b)) => {
+            flatten_condition(egraph, a, visiting, conditions);
+            flatten_condition(egraph, b, visiting, conditions);
+        }
+        None => conditions.push(id),
+    }
+    visiting.pop();
+}
+
+/// Returns the conjunction of conditions, or `true` if there is no condition.
+fn and_all(egraph: &mut EGraph, conditions: &[Id]) -> Id {
+    let mut iter = conditions.iter().copied();
+    let Some(first) = iter.next() else {
+        return egraph.add(Expr::Constant(DataValue::Bool(true)));
+    };
+    iter.fold(first, |a, b| egraph.add(Expr::And([a, b])))
+}
+
 #[rustfmt::skip]
 pub fn hash_join_rules() -> Vec<Rewrite> { vec![
     rw!("hash-join-on-one-eq";
@@ -225,6 +441,30 @@ pub fn subquery_rules() -> Vec<Rewrite> { vec![
         // if null_reject("?right", "?cond")
         if depend_on("?cond", "?right")
     ),
+    // scalar aggregation always returns exactly one row,
+    // so left outer apply is equivalent to inner apply.
+    // the inner apply is then decorrelated by `pushdown-apply-scalar-agg`, which groups the rows by
+    // the left columns and aggregates null-padded rows for left rows without a match.
+    // so it requires that left rows are distinct and no aggregation counts the null-padded rows.
+    rw!("left-outer-apply-agg-to-inner-apply";
+        "(apply left_outer ?left (agg ?aggs ?right))" =>
+        "(apply inner ?left (agg ?aggs ?right))"
+        if no_count("?aggs")
+        if has_key("?left")
+    ),
+    rw!("left-outer-apply-proj-agg-to-inner-apply";
+        "(apply left_outer ?left (proj ?exprs (agg ?aggs ?right)))" =>
+        "(apply inner ?left (proj ?exprs (agg ?aggs ?right)))"
+        if no_count("?aggs")
+        if has_key("?left")
+    ),
+    // number left rows with duplicates, so that they are distinct
+    rw!("left-outer-apply-agg-number-left";
+        "(apply left_outer ?left ?right)" =>
+        { number_left() }
+        if is_scalar_agg("?right")
+        if not_has_key("?left")
+    ),
     // Orthogonal Optimization of Subqueries and Aggregation
     // https://citeseerx.ist.psu.edu/viewdoc/download?doi=10.1.1.563.8492&rep=rep1&type=pdf
     // Figure 4 Rule (1)
@@ -249,6 +489,17 @@ pub fn subquery_rules() -> Vec<Rewrite> { vec![
         "(apply inner ?left (proj ?keys ?right))" =>
         { extract_key("(proj ?new_keys (apply inner ?left ?right))") }
     ),
+    rw!("pushdown-left-outer-apply-proj";
+        "(apply left_outer ?left (proj ?keys ?right))" =>
+        { extract_key("(proj ?new_keys (apply left_outer ?left ?right))") }
+        // the projection must be null on the null-padded rows
+        if all_null_if("?keys", "?right")
+    ),
+    rw!("pushdown-apply-project-set";
+        "(apply inner ?left (project_set ?keys ?right))" =>
+        // ?new_keys = ?left || ?keys
+        { extract_key("(project_set ?new_keys (apply inner ?left ?right))") }
+    ),
     rw!("pushdown-semi-apply-proj";
         "(apply semi ?left (proj ?proj ?right))" =>
         "(apply semi ?left ?right)"
@@ -350,6 +601,56 @@ fn extract_key(pattern_str: &str) -> impl Applier<Expr, ExprAnalysis> {
     }
 }
 
+/// Returns an applier that numbers the rows of `?left` with `row_number`, and removes the number
+/// from the output of the apply.
+///
+/// ```text
+/// (apply left_outer ?left ?right)
+/// =>
+/// (proj (list ?left.schema.. ?right.schema..)
+///     (apply left_outer (window (list (over row_number list list)) ?left) ?right))
+/// ```
+fn number_left() -> impl Applier<Expr, ExprAnalysis> {
+    struct NumberLeft {
+        left: Var,
+        right: Var,
+    }
+    impl Applier<Expr, ExprAnalysis> for NumberLeft {
+        fn apply_one(
+            &self,
+            egraph: &mut EGraph,
+            eclass: Id,
+            subst: &Subst,
+            _searcher_ast: Option<&PatternAst<Expr>>,
+            _rule_name: Symbol,
+        ) -> Vec<Id> {
+            let (left, right) = (subst[self.left], subst[self.right]);
+            let schema = (egraph[left].data.schema.iter())
+                .chain(&egraph[right].data.schema)
+                .copied()
+                .collect();
+            let row_number = egraph.add(Expr::RowNumber);
+            let empty = egraph.add(Expr::List([].into()));
+            let over = egraph.add(Expr::Over([row_number, empty, empty]));
+            let overs = egraph.add(Expr::List([over].into()));
+            let numbered = egraph.add(Expr::Window([overs, left]));
+            let ty = egraph.add(Expr::LeftOuter);
+            let apply = egraph.add(Expr::Apply([ty, numbered, right]));
+            let schema = egraph.add(Expr::List(schema));
+            let id = egraph.add(Expr::Proj([schema, apply]));
+            if egraph.union(eclass, id) {
+                vec![eclass]
+            } else {
+                vec![]
+            }
+        }
+    }
+    NumberLeft {
+        left: var("?left"),
+        right: var("?right"),
+    }
+}
+
 /// Pushdown projections and prune unused columns.
 #[rustfmt::skip]
 pub fn projection_pushdown_rules() -> Vec<Rewrite> { vec![
@@ -384,6 +685,10 @@ pub fn projection_pushdown_rules() -> Vec<Rewrite> { vec![
         "(hashagg ?keys ?aggs ?child)" =>
         { apply_proj("(hashagg [?keys] [?aggs] ?child)") }
     ),
+    rw!("pushdown-proj-sortagg";
+        "(sortagg ?keys ?aggs ?child)" =>
+        { apply_proj("(sortagg [?keys] [?aggs] ?child)") }
+    ),
     rw!("pushdown-proj-join";
         "(proj ?exprs (join ?type ?on ?left ?right))" =>
         { apply_proj("(proj [?exprs] (join ?type [?on] ?left ?right))") }
@@ -396,6 +701,14 @@ pub fn projection_pushdown_rules() -> Vec<Rewrite> { vec![
         "(proj ?exprs (scan ?table ?columns ?filter))" =>
         { column_prune("(proj ?exprs (scan ?table ?columns ?filter))") }
     ),
+    rw!("pushdown-proj-index-scan";
+        "(proj ?exprs (index_scan ?table ?columns ?filter))" =>
+        { column_prune("(proj ?exprs (index_scan ?table ?columns ?filter))") }
+    ),
+    rw!("pushdown-proj-scan-as-of";
+        "(proj ?exprs (scan_as_of ?table ?columns ?filter))" =>
+        { column_prune("(proj ?exprs (scan_as_of ?table ?columns ?filter))") }
+    ),
 ]}
 
 /// Returns true if the columns used in `expr` is disjoint from columns produced by `plan`.
@@ -431,6 +744,56 @@ fn all_depend_on(expr: &str, plan: &str) -> impl Fn(&mut EGraph, Id, &Subst) ->
     }
 }
 
+/// Returns true if `expr` is null or false when all columns produced by `plan` are null.
+fn null_reject(plan: &str, expr: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let plan = var(plan);
+    let expr = var(expr);
+    move |egraph, _, subst| {
+        let produced = produced(egraph, subst[plan]).collect();
+        rejects_null(egraph, subst[expr], &produced, 8)
+    }
+}
+
+/// Returns true if all expressions in the list are null when all columns produced by `plan` are
+/// null.
+fn all_null_if(exprs: &str, plan: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let exprs = var(exprs);
+    let plan = var(plan);
+    move |egraph, _, subst| {
+        let produced = produced(egraph, subst[plan]).collect();
+        (egraph[subst[exprs]].as_list().iter()).all(|id| is_null_if(egraph, *id, &produced, 8))
+    }
+}
+
+/// Returns true if the condition is null or false when all `columns` are null.
+///
+/// Only looks `depth` levels into the expression, as an e-graph may contain cycles.
+fn rejects_null(egraph: &EGraph, id: Id, columns: &ColumnSet, depth: usize) -> bool {
+    use Expr::*;
+    let rejects = |id: &Id| rejects_null(egraph, *id, columns, depth - 1);
+    depth > 0
+        && egraph[id].iter().any(|node| match node {
+            And([a, b]) => rejects(a) || rejects(b),
+            Or([a, b]) => rejects(a) && rejects(b),
+            _ => is_null_if(egraph, id, columns, depth),
+        })
+}
+
+/// Returns true if the expression is null when all `columns` are null.
+fn is_null_if(egraph: &EGraph, id: Id, columns: &ColumnSet, depth: usize) -> bool {
+    use Expr::*;
+    let is_null = |id: &Id| is_null_if(egraph, *id, columns, depth - 1);
+    depth > 0
+        && egraph[id].iter().any(|node| match node {
+            Column(_) | Ref(_) => columns.contains(node),
+            Add([a, b]) | Sub([a, b]) | Mul([a, b]) | Div([a, b]) | Mod([a, b])
+            | StringConcat([a, b]) | Gt([a, b]) | Lt([a, b]) | GtEq([a, b]) | LtEq([a, b])
+            | Eq([a, b]) | NotEq([a, b]) | Like([a, b]) | ILike([a, b]) => is_null(a) || is_null(b),
+            Neg(a) | Not(a) | Cast([_, a]) => is_null(a),
+            _ => false,
+        })
+}
+
 /// Returns the columns produced by the plan.
 fn produced(egraph: &EGraph, plan: Id) -> impl Iterator<Item = Expr> + '_ {
     (egraph[plan].data.schema.iter()).map(|id| {
@@ -442,6 +805,98 @@ fn produced(egraph: &EGraph, plan: Id) -> impl Iterator<Item = Expr> + '_ {
     })
 }
 
+/// Returns true if no aggregation in the list is a count, which counts null-padded rows.
+fn no_count(aggs: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let aggs = var(aggs);
+    move |egraph, _, subst| {
+        (egraph[subst[aggs]].as_list().iter()).all(|id| {
+            !(egraph[*id].iter())
+                .any(|e| matches!(e, Expr::Count(_) | Expr::CountDistinct(_) | Expr::RowCount))
+        })
+    }
+}
+
+/// Returns true if the plan `var1` is a scalar aggregation, with an optional projection.
+fn is_scalar_agg(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let var1 = var(var1);
+    move |egraph, _, subst| {
+        let is_agg = |id: &Id| egraph[*id].iter().any(|e| matches!(e, Expr::Agg(_)));
+        egraph[subst[var1]].iter().any(|e| match e {
+            Expr::Agg(_) => true,
+            Expr::Proj([_, child]) => is_agg(child),
+            _ => false,
+        })
+    }
+}
+
+/// Returns true if the rows of the plan `var1` are distinct.
+fn has_key(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let var1 = var(var1);
+    move |egraph, _, subst| unique_key(egraph, subst[var1], 8).is_some()
+}
+
+/// Returns true if the rows of the plan `var1` may not be distinct.
+fn not_has_key(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let var1 = var(var1);
+    move |egraph, _, subst| unique_key(egraph, subst[var1], 8).is_none()
+}
+
+/// Returns the expressions in the schema of the plan that are distinct on all rows, or `None` if
+/// no such key is known.
+///
+/// Only looks `depth` levels into the plan, as an e-graph may contain cycles.
+fn unique_key(egraph: &EGraph, id: Id, depth: usize) -> Option<Vec<Id>> {
+    use Expr::*;
+    if depth == 0 {
+        return None;
+    }
+    let schema = &egraph[id].data.schema;
+    egraph[id].iter().find_map(|node| {
+        let key = match node {
+            Scan([table, columns, _])
+            | IndexScan([table, columns, _])
+            | ScanAsOf([table, columns, _]) => {
+                let Table(table_id) = egraph[*table].nodes[0] else {
+                    return None;
+                };
+                let primary_keys = egraph.analysis.catalog.get_table(&table_id)?.primary_keys();
+                if primary_keys.is_empty() {
+                    return None;
+                }
+                let columns = egraph[*columns].as_list();
+                (primary_keys.iter())
+                    .map(|column_id| {
+                        columns.iter().copied().find(|id| {
+                            matches!(egraph[*id].nodes[0], Column(c) if c.column_id == *column_id)
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?
+            }
+            // a row number over all rows
+            Window([overs, child]) => (egraph[*overs].as_list().iter().copied())
+                .find(|id| {
+                    egraph[*id].iter().any(|e| {
+                        matches!(e, Over([f, partition, _])
+                            if egraph[*f].nodes.contains(&RowNumber)
+                                && egraph[*partition].as_list().is_empty())
+                    })
+                })
+                .map(|id| vec![id])
+                .or_else(|| unique_key(egraph, *child, depth - 1))?,
+            Proj([_, child])
+            | Filter([_, child])
+            | Order([_, child])
+            | Limit([_, _, child])
+            | TopN([_, _, _, child]) => unique_key(egraph, *child, depth - 1)?,
+            Agg(_) => vec![],
+            HashAgg([keys, _, _]) | SortAgg([keys, _, _]) => egraph[*keys].as_list().to_vec(),
+            _ => return None,
+        };
+        // the key must be in the output
+        key.iter().all(|id| schema.contains(id)).then_some(key)
+    })
+}
+
 /// Returns true if the node `var1` is not a list.
 fn is_not_list(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
     let var1 = var(var1);
@@ -453,6 +908,12 @@ fn is_not_list(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
     }
 }
 
+/// Returns true if the expression `var1` is not null.
+fn is_not_null(var1: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let var1 = var(var1);
+    move |egraph, _, subst| !egraph[subst[var1]].nodes.contains(&Expr::null())
+}
+
 /// The data type of column analysis.
 ///
 /// It is the set of columns used in the expression or plan.
@@ -464,7 +925,7 @@ pub fn analyze_columns(egraph: &EGraph, enode: &Expr) -> ColumnSet {
     use Expr::*;
     let columns = |i: &Id| &egraph[*i].data.columns;
     match enode {
-        Column(_) | Ref(_) => [enode.clone()].into_iter().collect(),
+        Column(_) | Ref(_) | CteColumn(_) => [enode.clone()].into_iter().collect(),
         // others: merge from all children
         _ => (enode.children().iter())
             .flat_map(|id| columns(id).iter().cloned())
@@ -624,6 +1085,24 @@ mod tests {
         "
     }
 
+    #[test]
+    fn offset_order_is_not_topn() {
+        // SELECT name FROM student ORDER BY name OFFSET 10
+        let expr: RecExpr = "
+        (limit null 10
+            (order (list $1.2)
+                (scan $1 (list $1.2) null)
+            )
+        )"
+        .parse()
+        .unwrap();
+        let runner = egg::Runner::<_, _, ()>::new(ExprAnalysis::default())
+            .with_expr(&expr)
+            .run(&rules());
+        let root = runner.egraph.find(runner.roots[0]);
+        assert!(!(runner.egraph[root].nodes.iter()).any(|e| matches!(e, Expr::TopN(_))));
+    }
+
     egg::test_fn! {
         predicate_pushdown,
         rules(),
@@ -646,6 +1125,49 @@ mod tests {
         ))"
     }
 
+    egg::test_fn! {
+        outer_join_to_inner_join,
+        rules(),
+        // SELECT * FROM t1 LEFT JOIN t2 ON t1.id = t2.id WHERE t2.a > 1
+        "
+        (filter (> $2.2 1)
+        (join left_outer (= $1.1 $2.1)
+            (scan $1 (list $1.1 $1.2) null)
+            (scan $2 (list $2.1 $2.2) null)
+        ))" => "
+        (hashjoin inner true (list $1.1) (list $2.1)
+            (scan $1 (list $1.1 $1.2) null)
+            (filter (> $2.2 1)
+                (scan $2 (list $2.1 $2.2) null)
+            )
+        )"
+    }
+
+    #[test]
+    fn outer_join_is_kept_without_null_rejection() {
+        // SELECT * FROM t1 LEFT JOIN t2 ON t1.id = t2.id WHERE t2.a IS NULL OR t1.a > 1
+        let expr: RecExpr = "
+        (filter (or (isnull $2.2) (> $1.2 1))
+        (join left_outer (= $1.1 $2.1)
+            (scan $1 (list $1.1 $1.2) null)
+            (scan $2 (list $2.1 $2.2) null)
+        ))"
+        .parse()
+        .unwrap();
+        let runner = egg::Runner::<_, _, ()>::new(ExprAnalysis::default())
+            .with_expr(&expr)
+            .run(&rules());
+        let egraph = &runner.egraph;
+        let joins = (egraph.classes().flat_map(|c| c.iter()))
+            .filter_map(|e| match e {
+                Expr::Join([op, ..]) | Expr::HashJoin([op, ..]) => Some(&egraph[*op].nodes[0]),
+                _ => None,
+            })
+            .collect_vec();
+        assert!(!joins.is_empty());
+        assert!(joins.iter().all(|op| matches!(op, Expr::LeftOuter)));
+    }
+
     egg::test_fn! {
         join_reorder,
         rules(),
@@ -669,6 +1191,34 @@ mod tests {
         )"
     }
 
+    egg::test_fn! {
+        greedy_join_reorder,
+        join_reorder_rules(),
+        // SELECT t1.b, t2.b, t3.b FROM t1, t2, t3
+        // WHERE t1.a = t2.a AND t2.a = t3.a AND t3.b = 1
+        "
+        (proj (list $1.2 $2.2 $3.2)
+        (join inner (= $2.1 $3.1)
+            (join inner (= $1.1 $2.1)
+                (scan $1 (list $1.1 $1.2) null)
+                (scan $2 (list $2.1 $2.2) null)
+            )
+            (filter (= $3.2 1)
+                (scan $3 (list $3.1 $3.2) null)
+            )
+        ))" => "
+        (proj (list $1.2 $2.2 $3.2)
+        (join inner (= $1.1 $2.1)
+            (join inner (= $2.1 $3.1)
+                (filter (= $3.2 1)
+                    (scan $3 (list $3.1 $3.2) null)
+                )
+                (scan $2 (list $2.1 $2.2) null)
+            )
+            (scan $1 (list $1.1 $1.2) null)
+        ))"
+    }
+
     egg::test_fn! {
         hash_join,
         rules(),
@@ -99,6 +99,50 @@ pub fn filter_scan_rule() -> Vec<Rewrite> { vec![
     ),
 ]}
 
+#[rustfmt::skip]
+pub fn index_scan_rules() -> Vec<Rewrite> { vec![
+    // turn range condition on an indexed column into index scan
+    rw!("filter-index-scan";
+        "(filter ?cond (scan ?table ?columns true))" =>
+        "(index_scan ?table ?columns ?cond)"
+        if is_index_range("?table", "?columns", "?cond")
+    ),
+    rw!("filter-index-scan-1";
+        "(filter (and ?cond1 ?cond2) (scan ?table ?columns true))" =>
+        "(filter ?cond2 (index_scan ?table ?columns ?cond1))"
+        if is_index_range("?table", "?columns", "?cond1")
+    ),
+]}
+
+/// Returns true if the expression is a range on the leading column of an index on the table.
+///
+/// Row ids can not be read from an index, so scans on them are not rewritten.
+fn is_index_range(
+    table: &str,
+    columns: &str,
+    cond: &str,
+) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
+    let table = var(table);
+    let columns = var(columns);
+    let cond = var(cond);
+    move |egraph, _, subst| {
+        let Some((column, _)) = &egraph[subst[cond]].data.range else {
+            return false;
+        };
+        let table_id = egraph[subst[table]].nodes[0].as_table();
+        if column.table() != table_id {
+            return false;
+        }
+        let read_rowid = (egraph[subst[columns]].as_list().iter())
+            .any(|id| egraph[*id].as_column().column_id == u32::MAX);
+        if read_rowid {
+            return false;
+        }
+        (egraph.analysis.catalog.get_table_indexes(&table_id).iter())
+            .any(|index| index.is_leading_column(column.column_id))
+    }
+}
+
 /// Returns true if the expression is a primary key range.
 fn is_primary_key_range(expr: &str) -> impl Fn(&mut EGraph, Id, &Subst) -> bool {
     let var = var(expr);
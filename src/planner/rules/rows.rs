@@ -3,12 +3,20 @@
 use std::collections::HashMap;
 
 use super::*;
-use crate::catalog::{ColumnRefId, TableRefId};
+use crate::catalog::{ColumnRefId, ColumnStatistics, TableRefId};
 use crate::types::DataValue;
 
 /// The data type of row number analysis.
 pub type Rows = f32;
 
+/// Returns the estimated rows of the table.
+pub fn table_rows(egraph: &EGraph, tid: &Id) -> Rows {
+    let table_id = egraph[*tid].nodes[0].as_table();
+    (egraph.analysis.stat)
+        .get_row_count(table_id)
+        .unwrap_or(DEFAULT_ROW_COUNT) as f32
+}
+
 /// Returns the estimated rows for plans, or selectivity for expressions.
 pub fn analyze_rows(egraph: &EGraph, enode: &Expr) -> Rows {
     use Expr::*;
@@ -24,15 +32,29 @@ pub fn analyze_rows(egraph: &EGraph, enode: &Expr) -> Rows {
     match enode {
         // for plan nodes, the result represents estimated rows
         Values(v) => v.len() as f32,
-        Scan([tid, _, _]) => {
-            let table_id = egraph[*tid].nodes[0].as_table();
-            egraph
-                .analysis
-                .stat
-                .get_row_count(table_id)
-                .unwrap_or(DEFAULT_ROW_COUNT) as f32
+        FileScan(_) => DEFAULT_ROW_COUNT as f32,
+        ValuesScan([c, _]) => x(c),
+        GenerateSeries([args, _]) => {
+            let int = |id: &Id| match egraph[*id].data.constant {
+                Some(DataValue::Int16(v)) => Some(v as i64),
+                Some(DataValue::Int32(v)) => Some(v as i64),
+                Some(DataValue::Int64(v)) => Some(v),
+                _ => None,
+            };
+            let values: Vec<_> = egraph[*args].as_list().iter().map(int).collect();
+            match values[..] {
+                [Some(start), Some(stop), Some(step), _] if step != 0 => {
+                    ((stop - start) as f32 / step as f32).floor().max(-1.0) + 1.0
+                }
+                _ => DEFAULT_ROW_COUNT as f32,
+            }
         }
-        Proj([_, c]) | Order([_, c]) | Window([_, c]) => x(c),
+        Scan([tid, _, _]) | ScanAsOf([tid, _, _]) => table_rows(egraph, tid),
+        IndexScan([tid, _, cond]) => table_rows(egraph, tid) * x(cond),
+        Proj([_, c]) | Order([_, c]) | Window([_, c]) | Exchange([_, c]) => x(c),
+        // TODO: consider the length of arrays
+        ProjectSet([_, c]) => x(c) * 10.0,
+        RecursiveUnion([_, base, _]) => x(base),
         Agg(_) => 1.0,
         HashAgg([keys, _, c]) | SortAgg([keys, _, c]) => {
             // TODO: consider distinct values of group keys
@@ -48,6 +70,14 @@ pub fn analyze_rows(egraph: &EGraph, enode: &Expr) -> Rows {
             if let Semi | Anti = egraph[*t].nodes[0] {
                 return x(l) * x(on) * 0.5f32.powi(list_len(lkey) as i32);
             }
+            // each pair of keys matches 1 / max(ndv(lkey), ndv(rkey)) of the cross product
+            let keys = (egraph[*lkey].as_list().iter()).zip(egraph[*rkey].as_list());
+            let selectivity = keys
+                .map(|(l, r)| eq_selectivity(egraph, l, r))
+                .product::<Option<f32>>();
+            if let Some(selectivity) = selectivity {
+                return x(l) * x(r) * x(on) * selectivity;
+            }
             let contains_primary_key = |list: &Id| {
                 let catalog = &egraph.analysis.catalog;
                 egraph[*list].as_list().iter().any(|cid| {
@@ -85,7 +115,12 @@ pub fn analyze_rows(egraph: &EGraph, enode: &Expr) -> Rows {
         Or([a, b]) => x(a) + x(b) - x(a) * x(b), // TODO: consider dependency
         Xor([a, b]) => x(a) + x(b) - 2.0 * x(a) * x(b),
         Not(a) => 1.0 - x(a),
-        Gt(_) | Lt(_) | GtEq(_) | LtEq(_) | Eq(_) | NotEq(_) | Like(_) => 0.5,
+        Eq([a, b]) => eq_selectivity(egraph, a, b).unwrap_or(0.5),
+        NotEq([a, b]) => eq_selectivity(egraph, a, b).map_or(0.5, |s| 1.0 - s),
+        Lt([a, b]) | LtEq([a, b]) => range_selectivity(egraph, a, b).unwrap_or(0.5),
+        Gt([a, b]) | GtEq([a, b]) => range_selectivity(egraph, b, a).unwrap_or(0.5),
+        Like(_) | ILike(_) => 0.5,
+        IsNull(a) => column_statistics(egraph, a).map_or(1.0, |s| s.null_fraction as f32),
         In([_, b]) => 1.0 / x(b),
         Exists(_) => 0.5,
 
@@ -95,11 +130,60 @@ pub fn analyze_rows(egraph: &EGraph, enode: &Expr) -> Rows {
 
 const DEFAULT_ROW_COUNT: u32 = 1000;
 
+/// Returns the selectivity of `a = b` estimated from the histogram if one side is a constant, or
+/// from the number of distinct values otherwise.
+///
+/// Returns `None` if neither side is a column with statistics.
+fn eq_selectivity(egraph: &EGraph, a: &Id, b: &Id) -> Option<f32> {
+    let constant = |id: &Id| egraph[*id].data.constant.as_ref();
+    if let Some(fraction) = (column_statistics(egraph, a).zip(constant(b)))
+        .or_else(|| column_statistics(egraph, b).zip(constant(a)))
+        .and_then(|(stats, value)| stats.eq_fraction(value))
+    {
+        return Some(fraction as f32);
+    }
+    let distinct_values = |id: &Id| {
+        egraph[*id].nodes.iter().find_map(|e| match e {
+            Expr::Column(cid) => egraph.analysis.stat.get_distinct_values(*cid),
+            _ => None,
+        })
+    };
+    let ndv = match (distinct_values(a), distinct_values(b)) {
+        (Some(a), Some(b)) => a.max(b),
+        (Some(n), None) | (None, Some(n)) => n,
+        (None, None) => return None,
+    };
+    Some(1.0 / ndv.max(1) as f32)
+}
+
+/// Returns the statistics of the column collected by `ANALYZE`.
+fn column_statistics<'a>(egraph: &'a EGraph, id: &Id) -> Option<&'a ColumnStatistics> {
+    egraph[*id].nodes.iter().find_map(|e| match e {
+        Expr::Column(cid) => egraph.analysis.stat.get_column_statistics(*cid),
+        _ => None,
+    })
+}
+
+/// Returns the selectivity of `a < b` estimated from the histogram, where one side is a column
+/// and the other side is a constant.
+fn range_selectivity(egraph: &EGraph, a: &Id, b: &Id) -> Option<f32> {
+    let constant = |id: &Id| egraph[*id].data.constant.as_ref();
+    let fraction = if let (Some(stats), Some(value)) = (column_statistics(egraph, a), constant(b)) {
+        stats.less_than_fraction(value)?
+    } else {
+        let stats = column_statistics(egraph, b)?;
+        let not_null = 1.0 - stats.null_fraction;
+        (not_null - stats.less_than_fraction(constant(a)?)?).max(0.0)
+    };
+    Some(fraction as f32)
+}
+
 /// Statistic from storage for row estimation.
 #[derive(Debug, Clone, Default)]
 pub struct Statistics {
     row_counts: HashMap<TableRefId, u32>,
     distinct_values: HashMap<ColumnRefId, u32>,
+    columns: HashMap<ColumnRefId, ColumnStatistics>,
 }
 
 impl Statistics {
@@ -120,4 +204,14 @@ impl Statistics {
         column_id.table_occurrence = 0;
         self.distinct_values.get(&column_id).copied()
     }
+
+    pub fn add_column_statistics(&mut self, mut column_id: ColumnRefId, stats: ColumnStatistics) {
+        column_id.table_occurrence = 0;
+        self.columns.insert(column_id, stats);
+    }
+
+    pub fn get_column_statistics(&self, mut column_id: ColumnRefId) -> Option<&ColumnStatistics> {
+        column_id.table_occurrence = 0;
+        self.columns.get(&column_id)
+    }
 }
@@ -17,7 +17,8 @@ pub fn analyze_schema(
     let concat = |v1: Vec<Id>, v2: Vec<Id>| v1.into_iter().chain(v2).collect();
     match enode {
         // equal to child
-        Filter([_, c]) | Order([_, c]) | Limit([_, _, c]) | TopN([_, _, _, c]) | Empty(c) => x(c),
+        Filter([_, c]) | Order([_, c]) | Limit([_, _, c]) | TopN([_, _, _, c])
+        | Exchange([_, c]) | Empty(c) => x(c),
 
         // concat 2 children
         Join([t, _, l, r])
@@ -32,10 +33,17 @@ pub fn analyze_schema(
         List(ids) => ids.to_vec(),
 
         // plans that change schema
-        Scan([_, columns, _]) => x(columns),
+        Scan([_, columns, _]) | IndexScan([_, columns, _]) | ScanAsOf([_, columns, _]) => {
+            x(columns)
+        }
         Values(vs) => x(&vs[0]),
-        Proj([exprs, _]) | Agg([exprs, _]) => x(exprs),
+        Proj([exprs, _]) | Agg([exprs, _]) | ProjectSet([exprs, _]) => x(exprs),
         Window([exprs, child]) => concat(x(child), x(exprs)),
+        RecursiveUnion([columns, _, _])
+        | WorkingTable(columns)
+        | FileScan([_, columns])
+        | GenerateSeries([_, columns])
+        | ValuesScan([_, columns]) => x(columns),
         HashAgg([keys, aggs, _]) | SortAgg([keys, aggs, _]) => concat(x(keys), x(aggs)),
 
         // not plan node
@@ -38,11 +38,19 @@ pub fn analyze_type(
             .get_column(col)
             .ok_or_else(|| TypeError::Unavailable(enode.to_string()))?
             .data_type()),
+        // the type of a parameter is unknown until it is substituted by a value
+        Param(_) => Ok(DataType::Null),
         Ref(a) => x(a),
+        CteColumn([base, index]) => {
+            let ColumnIndex(crate::types::ColumnIndex(i)) = node0(index) else {
+                panic!("not a column index");
+            };
+            Ok(x(base)?.as_struct()[i as usize].clone())
+        }
         List(list) => Ok(DataType::Struct(list.iter().map(x).try_collect()?)),
 
         // cast
-        Cast([ty, a]) => merge(enode, [x(ty)?, x(a)?], |[ty, _]| Some(ty)),
+        Cast([ty, a]) | CastTz([ty, a, _]) => merge(enode, [x(ty)?, x(a)?], |[ty, _]| Some(ty)),
 
         // number ops
         Neg(a) => check(enode, x(a)?, |a| a.is_number()),
@@ -64,6 +72,7 @@ pub fn analyze_type(
                     },
                     (a, b) if a.is_number() && b.is_number() => Some(b),
                     (DataType::Date, DataType::Interval) => Some(DataType::Date),
+                    (DataType::Timestamp, DataType::Interval) => Some(DataType::Timestamp),
                     _ => None,
                 }
             })
@@ -73,10 +82,20 @@ pub fn analyze_type(
         StringConcat([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
             (a == DataType::String && b == DataType::String).then_some(DataType::String)
         }),
-        Like([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
+        Like([a, b]) | ILike([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
             (a == DataType::String && b == DataType::String).then_some(DataType::Bool)
         }),
 
+        // json ops
+        JsonGet([a, b]) | JsonGetText([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
+            (a == DataType::Json && matches!(b, DataType::String | DataType::Int32)).then_some(
+                match enode {
+                    JsonGet(_) => DataType::Json,
+                    _ => DataType::String,
+                },
+            )
+        }),
+
         // bool ops
         Not(a) => check(enode, x(a)?, |a| a == &DataType::Bool),
         Gt([a, b]) | Lt([a, b]) | GtEq([a, b]) | LtEq([a, b]) | Eq([a, b]) | NotEq([a, b]) => {
@@ -116,7 +135,8 @@ pub fn analyze_type(
 
         // functions
         Extract([_, a]) => merge(enode, [x(a)?], |[a]| {
-            matches!(a, DataType::Date | DataType::Interval).then_some(DataType::Int32)
+            matches!(a, DataType::Date | DataType::Timestamp | DataType::Interval)
+                .then_some(DataType::Int32)
         }),
         Substring([str, start, len]) => {
             merge(enode, [x(str)?, x(start)?, x(len)?], |[str, start, len]| {
@@ -124,15 +144,75 @@ pub fn analyze_type(
                     .then_some(DataType::String)
             })
         }
+        Btrim([a, b]) | Ltrim([a, b]) | Rtrim([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
+            (a == DataType::String && b == DataType::String).then_some(DataType::String)
+        }),
+        Position([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
+            (a == DataType::String && b == DataType::String).then_some(DataType::Int32)
+        }),
+        Lpad([str, len, fill]) | Rpad([str, len, fill]) => {
+            merge(enode, [x(str)?, x(len)?, x(fill)?], |[str, len, fill]| {
+                (str == DataType::String && len == DataType::Int32 && fill == DataType::String)
+                    .then_some(DataType::String)
+            })
+        }
+        RegexpMatches([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
+            (a == DataType::String && b == DataType::String)
+                .then_some(DataType::List(Box::new(DataType::String)))
+        }),
+
+        // math functions
+        Round([a, digits]) => merge(enode, [x(a)?, x(digits)?], |[a, digits]| {
+            (a.is_number() && digits == DataType::Int32).then(|| rounded(a))
+        }),
+        Ceil(a) | Floor(a) => merge(enode, [x(a)?], |[a]| a.is_number().then(|| rounded(a))),
+        Power([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| {
+            (a.is_number() && b.is_number()).then_some(DataType::Float64)
+        }),
+        Ln(a) | Log(a) => merge(enode, [x(a)?], |[a]| {
+            a.is_number().then_some(DataType::Float64)
+        }),
+        Greatest([a, b]) | Least([a, b]) => merge(enode, [x(a)?, x(b)?], |[a, b]| a.union(&b)),
+
+        // array functions
+        Array(list) => {
+            let mut type_ = DataType::Null;
+            for ty in x(list)?.as_struct() {
+                type_ = type_.union(ty).ok_or_else(|| TypeError::NoCast {
+                    from: ty.clone(),
+                    to: type_.clone(),
+                })?;
+            }
+            Ok(DataType::List(Box::new(type_)))
+        }
+        ArrayGet([a, i]) => merge(enode, [x(a)?, x(i)?], |[a, i]| match (a, i) {
+            (DataType::List(ty), DataType::Int32) => Some(*ty),
+            _ => None,
+        }),
+        ArrayLength(a) => merge(enode, [x(a)?], |[a]| {
+            matches!(a, DataType::List(_)).then_some(DataType::Int32)
+        }),
+        Unnest(a) => merge(enode, [x(a)?], |[a]| match a {
+            DataType::List(ty) => Some(*ty),
+            _ => None,
+        }),
 
         // number agg
         Max(a) | Min(a) => x(a),
-        Sum(a) => check(enode, x(a)?, |a| a.is_number()),
+        Sum(a) | SumDistinct(a) => check(enode, x(a)?, |a| a.is_number()),
         Avg(a) => check(enode, x(a)?, |a| a.is_number()),
 
         // agg
-        RowCount | RowNumber | Count(_) | CountDistinct(_) => Ok(DataType::Int32),
+        RowCount | RowNumber | Rank | DenseRank | Count(_) | CountDistinct(_) => {
+            Ok(DataType::Int32)
+        }
         First(a) | Last(a) => x(a),
+        StringAgg([a, delimiter, _]) => merge(enode, [x(a)?, x(delimiter)?], |[a, delimiter]| {
+            (matches!(a, DataType::String | DataType::Null)
+                && matches!(delimiter, DataType::String | DataType::Null))
+            .then_some(DataType::String)
+        }),
+        ArrayAgg([a, _]) => Ok(DataType::List(Box::new(x(a)?))),
         Over([f, _, _]) => x(f),
 
         // scalar functions
@@ -142,7 +222,8 @@ pub fn analyze_type(
         }),
 
         // equal to child
-        Filter([_, c]) | Order([_, c]) | Limit([_, _, c]) | TopN([_, _, _, c]) | Empty(c) => x(c),
+        Filter([_, c]) | Order([_, c]) | Limit([_, _, c]) | TopN([_, _, _, c])
+        | Exchange([_, c]) | Empty(c) => x(c),
 
         // concat 2 children
         Join([t, _, l, r]) | HashJoin([t, _, _, _, l, r]) | MergeJoin([t, _, _, _, l, r]) => {
@@ -153,7 +234,9 @@ pub fn analyze_type(
         }
 
         // plans that change schema
-        Scan([_, columns, _]) => x(columns),
+        Scan([_, columns, _]) | IndexScan([_, columns, _]) | ScanAsOf([_, columns, _]) => {
+            x(columns)
+        }
         Values(rows) => {
             if rows.is_empty() {
                 return Ok(DataType::Null);
@@ -168,8 +251,14 @@ pub fn analyze_type(
             }
             Ok(type_)
         }
-        Proj([exprs, _]) | Agg([exprs, _]) => x(exprs),
+        Proj([exprs, _]) | Agg([exprs, _]) | ProjectSet([exprs, _]) => x(exprs),
         Window([exprs, c]) => concat_struct(x(c)?, x(exprs)?),
+        RecursiveUnion([columns, _, _])
+        | WorkingTable(columns)
+        | FileScan([_, columns])
+        | GenerateSeries([_, columns])
+        | ValuesScan([_, columns]) => x(columns),
+        CopyFrom([_, types]) => x(types),
         HashAgg([keys, aggs, _]) | SortAgg([keys, aggs, _]) => concat_struct(x(keys)?, x(aggs)?),
         Max1Row(c) => Ok(x(c)?.as_struct()[0].clone()),
 
@@ -178,6 +267,14 @@ pub fn analyze_type(
     }
 }
 
+/// Returns the type of a number after rounding. The scale of decimals may change.
+fn rounded(ty: DataType) -> DataType {
+    match ty {
+        DataType::Decimal(_, _) => DataType::Decimal(None, None),
+        ty => ty,
+    }
+}
+
 fn check(enode: &Expr, a: DataType, check: impl FnOnce(&DataType) -> bool) -> Type {
     if check(&a) {
         Ok(a)
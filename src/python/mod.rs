@@ -2,54 +2,101 @@
 
 use std::path::PathBuf;
 
+use arrow::ipc::writer::StreamWriter;
+use pyo3::conversion::ToPyObject;
+use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
 use tokio::runtime::Runtime;
 
+use crate::array::Chunk;
 use crate::storage::SecondaryStorageOptions;
+use crate::types::DataValue;
 use crate::Database;
-#[pyclass]
+
+/// A database embedded in Python.
+///
+/// ```python
+/// import risinglight
+/// db = risinglight.Database()
+/// db.run("select 1 + 1")
+/// ```
+#[pyclass(name = "Database")]
 pub struct PythonDatabase {
     runtime: Runtime,
     database: Database,
 }
-use pyo3::exceptions::PyException;
 
-use crate::array::Chunk;
 #[pymethods]
 impl PythonDatabase {
-    pub fn query(&self, py: Python<'_>, sql: String) -> PyResult<Vec<Vec<PyObject>>> {
-        let result = self
-            .runtime
-            .block_on(async { self.database.run(&sql).await });
-        match result {
-            Ok(chunks) => {
-                let mut rows = vec![];
-                for chunk in chunks {
-                    let mut table = datachunk_to_python_list(py, &chunk);
-                    rows.append(&mut table);
-                }
-                Ok(rows)
+    /// Opens a database stored in `path`, or an in-memory database if `path` is not given.
+    #[new]
+    #[pyo3(signature = (path=None))]
+    fn new(path: Option<String>) -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let database = match path {
+            Some(path) => {
+                let mut options = SecondaryStorageOptions::default_for_cli();
+                options.path = PathBuf::new().join(path);
+                runtime.block_on(Database::new_on_disk(options))
             }
-            Err(e) => Err(PyException::new_err(e.to_string())),
+            None => Database::new_in_memory(),
+        };
+        Ok(PythonDatabase { runtime, database })
+    }
+
+    /// Runs SQL statements and returns the rows of all outputs.
+    pub fn run(&self, py: Python<'_>, sql: String) -> PyResult<Vec<Vec<PyObject>>> {
+        let chunks = self.run_chunks(py, &sql)?;
+        let mut rows = vec![];
+        for chunk in chunks {
+            rows.append(&mut datachunk_to_python_list(py, &chunk));
         }
+        Ok(rows)
+    }
+
+    /// Same as [`run`](Self::run). Kept for compatibility.
+    pub fn query(&self, py: Python<'_>, sql: String) -> PyResult<Vec<Vec<PyObject>>> {
+        self.run(py, sql)
+    }
+
+    /// Runs SQL statements and returns the output of the last statement as a `pyarrow.Table`.
+    pub fn run_arrow(&self, py: Python<'_>, sql: String) -> PyResult<PyObject> {
+        let mut chunks = self.run_chunks(py, &sql)?;
+        let chunk = match chunks.pop() {
+            Some(chunk) if chunk.schema().is_some() => chunk,
+            // statements like `CREATE TABLE` output nothing
+            _ => {
+                let mut chunk = Chunk::new(vec![]);
+                chunk.set_schema(vec![]);
+                chunk
+            }
+        };
+        let data = chunk_to_arrow_ipc(&chunk).map_err(|e| PyException::new_err(e.to_string()))?;
+        let table = py
+            .import_bound("pyarrow.ipc")?
+            .call_method1("open_stream", (PyBytes::new_bound(py, &data),))?
+            .call_method0("read_all")?;
+        Ok(table.unbind())
+    }
+}
+
+impl PythonDatabase {
+    /// Runs SQL statements without holding the GIL.
+    fn run_chunks(&self, py: Python<'_>, sql: &str) -> PyResult<Vec<Chunk>> {
+        py.allow_threads(|| self.runtime.block_on(self.database.run(sql)))
+            .map_err(|e| PyException::new_err(e.to_string()))
     }
 }
-use pyo3::conversion::ToPyObject;
 
 /// Open a database for user, user can specify the path of database file
 #[pyfunction]
 pub fn open(path: String) -> PyResult<PythonDatabase> {
-    let runtime = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()?;
-    let mut options = SecondaryStorageOptions::default_for_cli();
-    options.path = PathBuf::new().join(path);
-
-    let database = runtime.block_on(async move { Database::new_on_disk(options).await });
-    Ok(PythonDatabase { runtime, database })
+    PythonDatabase::new(Some(path))
 }
 
-use crate::types::DataValue;
 /// Convert datachunk into Python List
 pub fn datachunk_to_python_list(py: Python, chunk: &Chunk) -> Vec<Vec<PyObject>> {
     let mut output = vec![];
@@ -59,7 +106,7 @@ pub fn datachunk_to_python_list(py: Python, chunk: &Chunk) -> Vec<Vec<PyObject>>
 
             for array in data_chunk.arrays() {
                 let s = match array.get(row) {
-                    DataValue::Null => "null".to_object(py),
+                    DataValue::Null => py.None(),
                     DataValue::Bool(v) => v.to_object(py),
                     DataValue::Int16(v) => v.to_object(py),
                     DataValue::Int32(v) => v.to_object(py),
@@ -67,11 +114,13 @@ pub fn datachunk_to_python_list(py: Python, chunk: &Chunk) -> Vec<Vec<PyObject>>
                     DataValue::Float64(v) => v.to_object(py),
                     DataValue::String(s) => s.to_object(py),
                     DataValue::Blob(s) => s.to_string().to_object(py),
+                    DataValue::Json(v) => v.to_string().to_object(py),
                     DataValue::Decimal(v) => v.to_string().to_object(py),
                     DataValue::Date(v) => v.to_string().to_object(py),
                     DataValue::Timestamp(v) => v.to_string().to_object(py),
                     DataValue::TimestampTz(v) => v.to_string().to_object(py),
                     DataValue::Interval(v) => v.to_string().to_object(py),
+                    DataValue::List(v) => v.to_string().to_object(py),
                 };
                 row_vec.push(s);
             }
@@ -80,3 +129,12 @@ pub fn datachunk_to_python_list(py: Python, chunk: &Chunk) -> Vec<Vec<PyObject>>
     }
     output
 }
+
+/// Encodes the chunk in the Arrow IPC streaming format.
+fn chunk_to_arrow_ipc(chunk: &Chunk) -> anyhow::Result<Vec<u8>> {
+    let mut writer = StreamWriter::try_new(vec![], &chunk.arrow_schema()?)?;
+    for batch in chunk.to_record_batches()? {
+        writer.write(&batch)?;
+    }
+    Ok(writer.into_inner()?)
+}
@@ -0,0 +1,160 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Arrow Flight server.
+//!
+//! A client sends a SQL query as the ticket of `DoGet`, and receives the results of the last
+//! statement as Arrow record batches. For example, in Python:
+//!
+//! ```python
+//! import pyarrow.flight as flight
+//!
+//! client = flight.connect("grpc://127.0.0.1:50051")
+//! df = client.do_get(flight.Ticket("select * from t")).read_pandas()
+//! ```
+
+use std::sync::Arc;
+
+use arrow::datatypes::Schema;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
+};
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::info;
+
+use crate::Database;
+
+pub async fn run_flight_server(host: Option<String>, port: Option<u16>, db: Database) {
+    let addr = format!(
+        "{}:{}",
+        host.unwrap_or_else(|| "127.0.0.1".to_string()),
+        port.unwrap_or(50051)
+    );
+    let service = FlightServiceServer::new(FlightServer { db: Arc::new(db) });
+    info!("Listening on: {}", addr);
+    Server::builder()
+        .add_service(service)
+        .serve(addr.parse().expect("invalid address"))
+        .await
+        .unwrap();
+}
+
+struct FlightServer {
+    db: Arc<Database>,
+}
+
+#[tonic::async_trait]
+impl FlightService for FlightServer {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("handshake"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("list_flights"))
+    }
+
+    /// Returns an endpoint whose ticket is the query in the command of the descriptor.
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        let endpoint = FlightEndpoint::new().with_ticket(Ticket::new(descriptor.cmd.clone()));
+        let info = FlightInfo::new()
+            .with_descriptor(descriptor)
+            .with_endpoint(endpoint);
+        Ok(Response::new(info))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("poll_flight_info"))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("get_schema"))
+    }
+
+    /// Runs the query in the ticket and returns the results of the last statement.
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let ticket = request.into_inner().ticket;
+        let query = std::str::from_utf8(&ticket)
+            .map_err(|_| Status::invalid_argument("ticket is not a valid UTF-8 query"))?;
+        info!("query:{query:?}");
+        let chunks =
+            (self.db.run(query).await).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        // statements like `CREATE TABLE` output nothing
+        let (schema, batches) = match chunks.last() {
+            Some(chunk) if chunk.schema().is_some() => {
+                let schema = chunk.arrow_schema();
+                let batches = chunk.to_record_batches();
+                (
+                    schema.map_err(|e| Status::internal(e.to_string()))?,
+                    batches.map_err(|e| Status::internal(e.to_string()))?,
+                )
+            }
+            _ => (Arc::new(Schema::empty()), vec![]),
+        };
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(stream::iter(batches.into_iter().map(Ok)))
+            .map_err(Status::from);
+        Ok(Response::new(stream.boxed()))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("do_put"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("do_action"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("list_actions"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("do_exchange"))
+    }
+}
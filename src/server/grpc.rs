@@ -0,0 +1,175 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! gRPC query service.
+//!
+//! `QueryService.Execute` runs SQL statements in a new session and streams the results of the
+//! last statement: a schema, followed by a message for each data chunk, encoded as protobuf rows
+//! or Arrow IPC streams. The service is defined in `proto/src/proto/query.proto`.
+
+use std::sync::Arc;
+
+use arrow::ipc::writer::StreamWriter;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use risinglight_proto::query::execute_request::Format;
+use risinglight_proto::query::execute_response::Payload;
+use risinglight_proto::query::query_service_server::{QueryService, QueryServiceServer};
+use risinglight_proto::query::{self as proto, ExecuteRequest, ExecuteResponse};
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::array::{Chunk, DataChunk};
+use crate::types::DataValue;
+use crate::{Database, Session};
+
+pub async fn run_grpc_server(host: Option<String>, port: Option<u16>, db: Database) {
+    let addr = format!(
+        "{}:{}",
+        host.unwrap_or_else(|| "127.0.0.1".to_string()),
+        port.unwrap_or(50051)
+    );
+    let service = QueryServiceServer::new(QueryServer { db: Arc::new(db) });
+    info!("Listening on: {}", addr);
+    Server::builder()
+        .add_service(service)
+        .serve(addr.parse().expect("invalid address"))
+        .await
+        .unwrap();
+}
+
+struct QueryServer {
+    db: Arc<Database>,
+}
+
+#[tonic::async_trait]
+impl QueryService for QueryServer {
+    type ExecuteStream = BoxStream<'static, Result<ExecuteResponse, Status>>;
+
+    async fn execute(
+        &self,
+        request: Request<ExecuteRequest>,
+    ) -> Result<Response<Self::ExecuteStream>, Status> {
+        let request = request.into_inner();
+        info!("query:{:?}", request.sql);
+        let session = Session::default();
+        let chunks = (self.db.run_in_session(&session, &request.sql).await)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        // statements like `CREATE TABLE` output nothing
+        let Some(chunk) = chunks.last().filter(|chunk| chunk.schema().is_some()) else {
+            return Ok(Response::new(stream::empty().boxed()));
+        };
+        let payloads = match request.format() {
+            Format::Protobuf => encode_protobuf(chunk),
+            Format::Arrow => encode_arrow(chunk).map_err(|e| Status::internal(e.to_string()))?,
+        };
+        let responses = (payloads.into_iter()).map(|payload| {
+            Ok(ExecuteResponse {
+                payload: Some(payload),
+            })
+        });
+        Ok(Response::new(stream::iter(responses).boxed()))
+    }
+}
+
+/// Encodes the schema and data chunks of the chunk in protobuf.
+fn encode_protobuf(chunk: &Chunk) -> Vec<Payload> {
+    let mut payloads = vec![Payload::Schema(encode_schema(chunk))];
+    for data_chunk in chunk.data_chunks() {
+        payloads.push(Payload::Chunk(encode_data_chunk(data_chunk)));
+    }
+    payloads
+}
+
+/// Encodes the schema of the chunk, and its data chunks as Arrow IPC streams.
+fn encode_arrow(chunk: &Chunk) -> anyhow::Result<Vec<Payload>> {
+    let mut payloads = vec![Payload::Schema(encode_schema(chunk))];
+    for batch in chunk.to_record_batches()? {
+        let mut writer = StreamWriter::try_new(vec![], &batch.schema())?;
+        writer.write(&batch)?;
+        payloads.push(Payload::Arrow(writer.into_inner()?));
+    }
+    Ok(payloads)
+}
+
+fn encode_schema(chunk: &Chunk) -> proto::Schema {
+    let columns = (chunk.schema().unwrap().iter())
+        .map(|(name, ty)| proto::schema::Column {
+            name: name.clone(),
+            data_type: ty.to_string(),
+        })
+        .collect();
+    proto::Schema { columns }
+}
+
+fn encode_data_chunk(chunk: &DataChunk) -> proto::DataChunk {
+    let rows = (0..chunk.cardinality())
+        .map(|i| proto::data_chunk::Row {
+            values: (chunk.arrays().iter())
+                .map(|array| encode_value(array.get(i)))
+                .collect(),
+        })
+        .collect();
+    proto::DataChunk { rows }
+}
+
+fn encode_value(value: DataValue) -> proto::Value {
+    use proto::value::Value;
+    let value = match value {
+        DataValue::Null => Value::Null(true),
+        DataValue::Bool(v) => Value::Bool(v),
+        DataValue::Int16(v) => Value::Int(v.into()),
+        DataValue::Int32(v) => Value::Int(v.into()),
+        DataValue::Int64(v) => Value::Int(v),
+        DataValue::Float64(v) => Value::Float(v.into_inner()),
+        DataValue::String(v) => Value::Text(v.into()),
+        DataValue::Blob(v) => Value::Blob(v.to_vec()),
+        v => Value::Text(v.to_string()),
+    };
+    proto::Value { value: Some(value) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::ArrayImpl;
+    use crate::types::DataType;
+
+    #[test]
+    fn test_encode() {
+        let data_chunk: DataChunk = [
+            ArrayImpl::new_int32([Some(1), None].into_iter().collect()),
+            ArrayImpl::new_string([Some("a"), Some("b")].into_iter().collect()),
+        ]
+        .into_iter()
+        .collect();
+        let mut chunk = Chunk::new(vec![data_chunk]);
+        chunk.set_schema(vec![
+            ("x".into(), DataType::Int32),
+            ("y".into(), DataType::String),
+        ]);
+
+        let payloads = encode_protobuf(&chunk);
+        let [Payload::Schema(schema), Payload::Chunk(data_chunk)] = &payloads[..] else {
+            panic!("unexpected payloads: {payloads:?}");
+        };
+        assert_eq!(schema.columns[1].name, "y");
+        assert_eq!(
+            data_chunk.rows[1].values[0].value,
+            Some(proto::value::Value::Null(true))
+        );
+        assert_eq!(
+            data_chunk.rows[0].values[1].value,
+            Some(proto::value::Value::Text("a".into()))
+        );
+
+        let payloads = encode_arrow(&chunk).unwrap();
+        let [Payload::Schema(_), Payload::Arrow(data)] = &payloads[..] else {
+            panic!("unexpected payloads: {payloads:?}");
+        };
+        let reader = arrow::ipc::reader::StreamReader::try_new(&data[..], None).unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(batches[0].num_rows(), 2);
+    }
+}
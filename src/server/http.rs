@@ -0,0 +1,301 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! An HTTP endpoint for running queries.
+//!
+//! `POST /query` takes SQL statements as the request body, runs them in a new session and returns
+//! the output of the last statement as JSON:
+//!
+//! ```json
+//! {"columns":[{"name":"a","type":"INT"}],"rows":[[1],[null]]}
+//! ```
+//!
+//! Errors are returned as `{"error":{"code":"...","message":"..."}}` with a 4xx or 5xx status.
+//! Request bodies larger than 16 MiB are rejected with `413 Payload Too Large`, and request lines
+//! or headers larger than 8 KiB each or 64 KiB in total with `431 Request Header Fields Too Large`.
+
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::array::{Chunk, DataChunk};
+use crate::types::DataValue;
+use crate::{Database, Error, Session};
+
+/// The maximum size of SQL in a request body.
+const MAX_BODY_SIZE: usize = 16 << 20;
+/// The maximum size of the request line or a header line.
+const MAX_LINE_SIZE: usize = 8 << 10;
+/// The maximum size of the request line and headers.
+const MAX_HEAD_SIZE: usize = 64 << 10;
+
+/// Serves `POST /query`.
+pub async fn run_http_server(host: Option<String>, port: Option<u16>, db: Database) {
+    let db = Arc::new(db);
+    let addr = format!(
+        "{}:{}",
+        host.unwrap_or_else(|| "127.0.0.1".to_string()),
+        port.unwrap_or(8080)
+    );
+    let listener = TcpListener::bind(&addr).await.unwrap();
+    info!("Listening on: http://{}/query", addr);
+    loop {
+        let (socket, _) = listener.accept().await.unwrap();
+        let db = db.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(socket, &db).await {
+                warn!("failed to serve query: {e}");
+            }
+        });
+    }
+}
+
+async fn handle_request(
+    socket: impl AsyncRead + AsyncWrite + Unpin,
+    db: &Database,
+) -> std::io::Result<()> {
+    let mut socket = BufReader::new(socket);
+    let mut request_line = String::new();
+    let mut head_size = read_line(&mut socket, &mut request_line).await?;
+    let mut content_length = 0;
+    let mut line = String::new();
+    loop {
+        let size = read_line(&mut socket, &mut line).await?;
+        head_size += size;
+        if head_size > MAX_HEAD_SIZE || size.max(request_line.len()) > MAX_LINE_SIZE {
+            let message = "request line or headers are too large";
+            let socket = socket.get_mut();
+            return write_error(
+                socket,
+                "431 Request Header Fields Too Large",
+                "header_too_large",
+                message,
+            )
+            .await;
+        }
+        if size <= 2 {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+        line.clear();
+    }
+    if content_length > MAX_BODY_SIZE {
+        let message = format!("request body exceeds {MAX_BODY_SIZE} bytes");
+        let socket = socket.get_mut();
+        return write_error(
+            socket,
+            "413 Payload Too Large",
+            "payload_too_large",
+            &message,
+        )
+        .await;
+    }
+    let mut body = vec![0; content_length];
+    socket.read_exact(&mut body).await?;
+    let socket = socket.get_mut();
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    match (method, path) {
+        ("POST", "/query") => {}
+        (_, "/query") => {
+            return write_error(socket, "405 Method Not Allowed", "method_not_allowed", "").await
+        }
+        _ => return write_error(socket, "404 Not Found", "not_found", "").await,
+    }
+    let Ok(sql) = String::from_utf8(body) else {
+        let message = "SQL is not valid UTF-8";
+        return write_error(socket, "400 Bad Request", "invalid_request", message).await;
+    };
+
+    info!("query:{:?}", sql);
+    let chunk = match db.run_in_session(&Session::default(), &sql).await {
+        Ok(mut chunks) => chunks.pop(),
+        Err(e) => {
+            let (status, code) = error_code(&e);
+            return write_error(socket, status, code, &e.to_string()).await;
+        }
+    };
+    let columns = chunk.as_ref().map_or(json!([]), encode_columns);
+    let data_chunks = chunk.as_ref().map_or(&[][..], |c| c.data_chunks());
+    let rows: Vec<Value> = data_chunks.iter().flat_map(encode_rows).collect();
+    let body = json!({ "columns": columns, "rows": rows }).to_string();
+    write_response(socket, "200 OK", &body).await
+}
+
+/// Reads a line of the request head into `line`, but no more than `MAX_LINE_SIZE + 1` bytes.
+/// Returns the number of bytes read.
+async fn read_line(
+    socket: &mut (impl AsyncBufRead + Unpin),
+    line: &mut String,
+) -> std::io::Result<usize> {
+    (socket.take(MAX_LINE_SIZE as u64 + 1))
+        .read_line(line)
+        .await
+}
+
+async fn write_response(
+    socket: &mut (impl AsyncWrite + Unpin),
+    status: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
+
+async fn write_error(
+    socket: &mut (impl AsyncWrite + Unpin),
+    status: &str,
+    code: &str,
+    message: &str,
+) -> std::io::Result<()> {
+    let body = json!({ "error": { "code": code, "message": message } }).to_string();
+    write_response(socket, status, &body).await
+}
+
+/// Returns the HTTP status and the error code of an error.
+fn error_code(error: &Error) -> (&'static str, &'static str) {
+    match error {
+        Error::Parse(_) => ("400 Bad Request", "parse_error"),
+        Error::Bind(_) => ("400 Bad Request", "bind_error"),
+        Error::Execute(_) => ("500 Internal Server Error", "execute_error"),
+        Error::Storage(_) => ("500 Internal Server Error", "storage_error"),
+        Error::TransactionInProgress | Error::NoTransaction | Error::DdlInTransaction => {
+            ("400 Bad Request", "transaction_error")
+        }
+        Error::PreparedStatementExists(_)
+        | Error::NoPreparedStatement(_)
+        | Error::ParameterCountMismatch { .. } => ("400 Bad Request", "prepared_statement_error"),
+        Error::UnknownVariable(_) | Error::InvalidValue(..) => {
+            ("400 Bad Request", "variable_error")
+        }
+        Error::Internal(_) => ("500 Internal Server Error", "internal_error"),
+    }
+}
+
+/// Returns the names and types of columns, or `column{i}` with unknown types if the chunk is not
+/// the output of a query.
+fn encode_columns(chunk: &Chunk) -> Value {
+    if let Some(schema) = chunk.schema() {
+        return (schema.iter())
+            .map(|(name, ty)| json!({ "name": name, "type": ty.to_string() }))
+            .collect();
+    }
+    let count = chunk.data_chunks().first().map_or(0, |c| c.column_count());
+    (0..count)
+        .map(|i| json!({ "name": format!("column{i}"), "type": null }))
+        .collect()
+}
+
+/// Returns each row of the data chunk as an array of values.
+fn encode_rows(chunk: &DataChunk) -> impl Iterator<Item = Value> + '_ {
+    chunk
+        .rows()
+        .map(|row| Value::Array(row.values().map(json_value).collect()))
+}
+
+fn json_value(value: DataValue) -> Value {
+    match value {
+        DataValue::Null => Value::Null,
+        DataValue::Bool(v) => v.into(),
+        DataValue::Int16(v) => v.into(),
+        DataValue::Int32(v) => v.into(),
+        DataValue::Int64(v) => v.into(),
+        DataValue::Float64(v) => v.0.into(),
+        DataValue::String(v) => v.to_string().into(),
+        DataValue::Json(v) => v.to_value(),
+        DataValue::List(list) => list.iter().cloned().map(json_value).collect(),
+        value => value.to_string().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn request(db: &Database, request: &str) -> String {
+        let (mut client, server) = tokio::io::duplex(1 << 20);
+        client.write_all(request.as_bytes()).await.unwrap();
+        handle_request(server, db).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    fn post(target: &str, sql: &str) -> String {
+        format!(
+            "POST {target} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{sql}",
+            sql.len()
+        )
+    }
+
+    #[tokio::test]
+    async fn query() {
+        let db = Database::new_in_memory();
+        db.run("create table t (a int, b string); insert into t values (1, 'x'), (null, 'y');")
+            .await
+            .unwrap();
+
+        let response = request(&db, &post("/query", "select * from t order by b")).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with(
+            r#"{"columns":[{"name":"a","type":"INT"},{"name":"b","type":"STRING"}],"rows":[[1,"x"],[null,"y"]]}"#
+        ), "{response}");
+
+        let response = request(&db, &post("/query", "select * from no_such_table")).await;
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            "{response}"
+        );
+        assert!(response.contains(r#""code":"bind_error""#), "{response}");
+
+        let response = request(
+            &db,
+            &format!(
+                "POST /query HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                MAX_BODY_SIZE + 1
+            ),
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"),
+            "{response}"
+        );
+        assert!(
+            response.contains(r#""code":"payload_too_large""#),
+            "{response}"
+        );
+
+        let header = format!("X-Padding: {}\r\n", "x".repeat(MAX_LINE_SIZE));
+        let response = request(&db, &format!("GET /query HTTP/1.1\r\n{header}\r\n")).await;
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+            "{response}"
+        );
+        let headers = "X-Padding: x\r\n".repeat(MAX_HEAD_SIZE / 14);
+        let response = request(&db, &format!("GET /query HTTP/1.1\r\n{headers}\r\n")).await;
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+            "{response}"
+        );
+
+        let response = request(&db, "GET /query HTTP/1.1\r\n\r\n").await;
+        assert!(
+            response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+            "{response}"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! An HTTP endpoint serving metrics to Prometheus.
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+use crate::utils::metrics;
+
+/// Serves `GET /metrics` in the Prometheus text format.
+pub async fn run_metrics_server(host: Option<String>, port: u16) {
+    let addr = format!(
+        "{}:{}",
+        host.unwrap_or_else(|| "127.0.0.1".to_string()),
+        port
+    );
+    let listener = TcpListener::bind(&addr).await.unwrap();
+    info!("Serving metrics on: http://{}/metrics", addr);
+    loop {
+        let (socket, _) = listener.accept().await.unwrap();
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(socket).await {
+                warn!("failed to serve metrics: {e}");
+            }
+        });
+    }
+}
+
+async fn handle_request(socket: TcpStream) -> std::io::Result<()> {
+    let mut socket = BufReader::new(socket);
+    let mut request_line = String::new();
+    socket.read_line(&mut request_line).await?;
+    // skip headers
+    let mut line = String::new();
+    while socket.read_line(&mut line).await? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics::gather()),
+        _ => ("404 Not Found", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    socket.get_mut().write_all(response.as_bytes()).await?;
+    socket.get_mut().shutdown().await
+}
@@ -1,5 +1,9 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+mod flight;
+mod grpc;
+mod http;
+mod metrics;
 mod processor;
 
 use std::sync::Arc;
@@ -11,11 +15,15 @@ use pgwire::tokio::process_socket;
 use tokio::net::TcpListener;
 use tracing::info;
 
+pub use self::flight::run_flight_server;
+pub use self::grpc::run_grpc_server;
+pub use self::http::run_http_server;
+pub use self::metrics::run_metrics_server;
 use crate::server::processor::Processor;
 use crate::Database;
 
 pub async fn run_server(host: Option<String>, port: Option<u16>, db: Database) {
-    let processor = Arc::new(Processor::new(db));
+    let db = Arc::new(db);
     let authenticator = Arc::new(NoopStartupHandler);
     let addr = format!(
         "{}:{}",
@@ -27,7 +35,8 @@ pub async fn run_server(host: Option<String>, port: Option<u16>, db: Database) {
     loop {
         let incoming_socket = listener.accept().await.unwrap();
         let authenticator_ref = authenticator.clone();
-        let processor_ref = processor.clone();
+        // each connection has its own session
+        let processor_ref = Arc::new(Processor::new(db.clone()));
         let placeholder = Arc::new(StatelessMakeHandler::new(Arc::new(
             PlaceholderExtendedQueryHandler,
         )));
@@ -36,7 +45,7 @@ pub async fn run_server(host: Option<String>, port: Option<u16>, db: Database) {
                 incoming_socket.0,
                 None,
                 authenticator_ref,
-                processor_ref.clone(),
+                processor_ref,
                 placeholder.make(),
             )
             .await
@@ -3,6 +3,7 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::FixedOffset;
 use futures::stream;
 use pgwire::api::query::SimpleQueryHandler;
 use pgwire::api::results::{DataRowEncoder, FieldFormat, FieldInfo, QueryResponse, Response, Tag};
@@ -10,15 +11,23 @@ use pgwire::api::{ClientInfo, Type};
 use pgwire::error::{PgWireError, PgWireResult};
 use tracing::info;
 
-use crate::Database;
+use crate::array::{ArrayImpl, Chunk};
+use crate::types::{DataType, DataValue};
+use crate::{Database, Session};
 
+/// The handler of queries from a client connection.
 pub struct Processor {
-    db: Database,
+    db: Arc<Database>,
+    /// The session of the connection.
+    session: Session,
 }
 
 impl Processor {
-    pub fn new(db: Database) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            session: Session::default(),
+        }
     }
 }
 
@@ -35,42 +44,137 @@ impl SimpleQueryHandler for Processor {
         info!("query:{query:?}");
         let chunks = self
             .db
-            .run(query)
+            .run_in_session(&self.session, query)
             .await
             .map_err(|e| PgWireError::ApiError(Box::new(e)))?;
 
-        if !query.to_uppercase().starts_with("SELECT") {
-            return Ok(vec![Response::Execution(Tag::new("OK"))]);
-        }
-        let mut results = Vec::new();
-        let mut headers = None;
+        let mut responses = Vec::with_capacity(chunks.len());
         for chunk in chunks {
-            for data_chunk in chunk.data_chunks() {
-                for i in 0..data_chunk.cardinality() {
-                    let headers = headers.get_or_insert_with(|| {
-                        Arc::new(vec![
-                            FieldInfo::new(
-                                "++".into(),
-                                None,
-                                None,
-                                Type::CHAR,
-                                FieldFormat::Text
-                            );
-                            data_chunk.arrays().len()
-                        ])
-                    });
-                    let mut encoder = DataRowEncoder::new(headers.clone());
-                    data_chunk.arrays().iter().for_each(|a| {
-                        let field = a.get_to_string(i);
-                        encoder.encode_field(&field).unwrap();
-                    });
-                    results.push(encoder.finish());
+            let response = match chunk.header().map(|h| h[0].as_str()) {
+                Some("$insert.row_counts") => {
+                    Response::Execution(Tag::new("INSERT").with_oid(0).with_rows(row_count(&chunk)))
+                }
+                Some("$delete.row_counts") => {
+                    Response::Execution(Tag::new("DELETE").with_rows(row_count(&chunk)))
                 }
+                Some("$update.row_counts") => {
+                    Response::Execution(Tag::new("UPDATE").with_rows(row_count(&chunk)))
+                }
+                Some("$create") => Response::Execution(Tag::new("CREATE")),
+                Some("$drop") => Response::Execution(Tag::new("DROP")),
+                Some("$alter") => Response::Execution(Tag::new("ALTER TABLE")),
+                Some("$truncate") => Response::Execution(Tag::new("TRUNCATE TABLE")),
+                _ => Response::Query(query_response(&chunk, self.session.time_zone())?),
+            };
+            responses.push(response);
+        }
+        if responses.is_empty() {
+            // statements like `SET` and `PRAGMA` produce no output
+            responses.push(Response::Execution(Tag::new("OK")));
+        }
+        Ok(responses)
+    }
+}
+
+/// Encodes the rows of a chunk into a query response. Timestamps with time zone are shown in the
+/// time zone `tz`.
+fn query_response<'a>(chunk: &Chunk, tz: FixedOffset) -> PgWireResult<QueryResponse<'a>> {
+    let fields = Arc::new(field_infos(chunk));
+    let mut rows = Vec::new();
+    for data_chunk in chunk.data_chunks() {
+        for i in 0..data_chunk.cardinality() {
+            let mut encoder = DataRowEncoder::new(fields.clone());
+            for array in data_chunk.arrays() {
+                encoder.encode_field(&text_value(array, i, tz))?;
             }
+            rows.push(encoder.finish());
         }
-        Ok(vec![Response::Query(QueryResponse::new(
-            headers.expect("fixme: db should return schema even if no output data"),
-            stream::iter(results.into_iter()),
-        ))])
     }
+    Ok(QueryResponse::new(fields, stream::iter(rows)))
+}
+
+/// Returns the row description of a chunk.
+///
+/// Columns are described by the schema of query outputs, so that the description is also
+/// available for empty results. Otherwise, columns are reported as `?column?` like postgres does
+/// for unnamed expressions, with types of the arrays in the first data chunk.
+fn field_infos(chunk: &Chunk) -> Vec<FieldInfo> {
+    let field = |name: &str, ty| FieldInfo::new(name.into(), None, None, ty, FieldFormat::Text);
+    if let Some(schema) = chunk.schema() {
+        return (schema.iter())
+            .map(|(name, ty)| field(name, pg_type(ty)))
+            .collect();
+    }
+    // the plan is returned in a column named `QUERY PLAN` like postgres does
+    let name = match chunk.header() {
+        Some([header, ..]) if header == "$explain" => "QUERY PLAN",
+        _ => "?column?",
+    };
+    let Some(data_chunk) = chunk.data_chunks().first() else {
+        return vec![];
+    };
+    (data_chunk.arrays().iter())
+        .map(|array| field(name, pg_type_of_array(array)))
+        .collect()
+}
+
+/// Returns the postgres type of a data type.
+fn pg_type(ty: &DataType) -> Type {
+    match ty {
+        DataType::Null => Type::UNKNOWN,
+        DataType::Bool => Type::BOOL,
+        DataType::Int16 => Type::INT2,
+        DataType::Int32 => Type::INT4,
+        DataType::Int64 => Type::INT8,
+        DataType::Float64 => Type::FLOAT8,
+        DataType::Decimal(..) => Type::NUMERIC,
+        DataType::Date => Type::DATE,
+        DataType::Timestamp => Type::TIMESTAMP,
+        DataType::TimestampTz => Type::TIMESTAMPTZ,
+        DataType::Interval => Type::INTERVAL,
+        DataType::String => Type::VARCHAR,
+        DataType::Blob => Type::BYTEA,
+        DataType::Json => Type::JSON,
+        // lists and structs are sent in text format
+        DataType::List(_) | DataType::Struct(_) => Type::VARCHAR,
+    }
+}
+
+/// Returns the postgres type of an array.
+fn pg_type_of_array(array: &ArrayImpl) -> Type {
+    match array {
+        ArrayImpl::Null(_) => Type::UNKNOWN,
+        ArrayImpl::Bool(_) => Type::BOOL,
+        ArrayImpl::Int16(_) => Type::INT2,
+        ArrayImpl::Int32(_) => Type::INT4,
+        ArrayImpl::Int64(_) => Type::INT8,
+        ArrayImpl::Float64(_) => Type::FLOAT8,
+        ArrayImpl::Decimal(_) => Type::NUMERIC,
+        ArrayImpl::Date(_) => Type::DATE,
+        ArrayImpl::Timestamp(_) => Type::TIMESTAMP,
+        ArrayImpl::TimestampTz(_) => Type::TIMESTAMPTZ,
+        ArrayImpl::Interval(_) => Type::INTERVAL,
+        ArrayImpl::String(_) => Type::VARCHAR,
+        ArrayImpl::Blob(_) => Type::BYTEA,
+        ArrayImpl::Json(_) => Type::JSON,
+        // lists are sent in text format
+        ArrayImpl::List(_) => Type::VARCHAR,
+    }
+}
+
+/// Returns the value at `idx` in postgres text format. `None` for null.
+fn text_value(array: &ArrayImpl, idx: usize, tz: FixedOffset) -> Option<String> {
+    match array.get(idx) {
+        DataValue::Null => None,
+        DataValue::Bool(b) => Some(if b { "t" } else { "f" }.into()),
+        DataValue::TimestampTz(v) => Some(v.display_in(tz).to_string()),
+        _ => Some(array.get_to_string(idx)),
+    }
+}
+
+/// Returns the affected row count of a DML statement.
+fn row_count(chunk: &Chunk) -> usize {
+    (chunk.data_chunks().iter())
+        .map(|c| c.array_at(0).get(0).as_usize().unwrap().unwrap_or(0))
+        .sum()
 }
@@ -19,6 +19,10 @@ pub enum StorageError {
     Duplicated(&'static str, String),
     #[error("invalid column id: {0}")]
     InvalidColumn(ColumnId),
+    #[error("transaction conflict: {0}")]
+    Conflict(String),
+    #[error("{0} not supported by this storage")]
+    Unsupported(&'static str),
     #[error("IO error: {0}")]
     Io(#[from] Box<std::io::Error>),
     #[error("JSON decode error: {0}")]
@@ -98,6 +102,10 @@ impl TracedStorageError {
         StorageError::NotFound(ty, item.to_string()).into()
     }
 
+    pub fn conflict(message: impl ToString) -> Self {
+        StorageError::Conflict(message.to_string()).into()
+    }
+
     pub fn decode(message: impl ToString) -> Self {
         StorageError::Decode(message.to_string()).into()
     }
@@ -0,0 +1,294 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Secondary indexes shared by all storage engines.
+//!
+//! An index maps the keys of rows to their row handlers, ordered by key. The entries are kept in
+//! memory: they are built from the table on the first lookup, and then maintained by the storage
+//! engine, which calls [`TableIndex::insert`] and [`TableIndex::delete`] after committing writes
+//! to the table. Rows found by a lookup are read by [`ScanOptions::with_rows`].
+//!
+//! Handlers of rows change when the rows are moved, e.g. by compactions or truncations. The
+//! entries are dropped then, and rebuilt by the next lookup.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use itertools::Itertools;
+
+use super::{
+    KeyRange, ScanOptions, StorageColumnRef, StorageResult, Table, TracedStorageError, Transaction,
+    TxnIterator,
+};
+use crate::array::DataChunk;
+use crate::catalog::{ColumnCatalog, ColumnId};
+use crate::types::{DataValue, Row};
+
+/// An in-memory ordered index on a table.
+pub struct TableIndex {
+    /// Key columns of the index.
+    column_ids: Vec<ColumnId>,
+    /// Bumped before and after rows of the table are moved to new handlers, so it is odd while
+    /// the rows are being moved.
+    moves: AtomicU64,
+    /// The entries, or `None` if they are not built.
+    entries: Mutex<Option<IndexEntries>>,
+}
+
+#[derive(Default)]
+struct IndexEntries {
+    /// Handlers of rows ordered by key. Rows with NULL in the leading key column never match a
+    /// range, so they are not indexed.
+    handlers: BTreeMap<Row, BTreeSet<i64>>,
+    /// Keys of the indexed rows.
+    keys: HashMap<i64, Row>,
+}
+
+impl IndexEntries {
+    /// Adds the rows of a chunk with the key columns followed by the row handler column.
+    fn insert(&mut self, chunk: &DataChunk) {
+        let handler_idx = chunk.column_count() - 1;
+        for row in chunk.rows() {
+            let DataValue::Int64(handler) = row.get(handler_idx) else {
+                panic!("invalid row handler");
+            };
+            let key = (0..handler_idx).map(|i| row.get(i)).collect_vec();
+            if key[0].is_null() {
+                continue;
+            }
+            self.handlers
+                .entry(key.clone())
+                .or_default()
+                .insert(handler);
+            self.keys.insert(handler, key);
+        }
+    }
+
+    fn delete(&mut self, handler: i64) {
+        let Some(key) = self.keys.remove(&handler) else {
+            return;
+        };
+        if let Some(handlers) = self.handlers.get_mut(&key) {
+            handlers.remove(&handler);
+            if handlers.is_empty() {
+                self.handlers.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the sorted handlers of rows whose leading key is in `range`.
+    fn lookup(&self, range: &KeyRange) -> Vec<i64> {
+        let lower = match &range.start {
+            Bound::Included(v) | Bound::Excluded(v) => Bound::Included(vec![v.clone()]),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let mut handlers = (self.handlers.range((lower, Bound::Unbounded)))
+            .skip_while(|(key, _)| matches!(&range.start, Bound::Excluded(v) if &key[0] == v))
+            .take_while(|(key, _)| range.contains(&key[0]))
+            .flat_map(|(_, handlers)| handlers.iter().copied())
+            .collect_vec();
+        handlers.sort_unstable();
+        handlers
+    }
+}
+
+impl TableIndex {
+    pub fn new(column_ids: &[ColumnId]) -> Self {
+        TableIndex {
+            column_ids: column_ids.to_vec(),
+            moves: AtomicU64::new(0),
+            entries: Mutex::new(None),
+        }
+    }
+
+    /// Returns the key columns of the index.
+    pub fn column_ids(&self) -> &[ColumnId] {
+        &self.column_ids
+    }
+
+    /// Returns the positions of the key columns in `columns` of the table.
+    pub fn key_positions(&self, columns: &[ColumnCatalog]) -> StorageResult<Vec<usize>> {
+        (self.column_ids.iter())
+            .map(|id| {
+                (columns.iter())
+                    .position(|c| c.id() == *id)
+                    .ok_or_else(|| TracedStorageError::not_found("column", id))
+            })
+            .try_collect()
+    }
+
+    /// Adds committed rows to the index. The chunk contains the key columns followed by the row
+    /// handler column.
+    pub fn insert(&self, chunk: &DataChunk) {
+        if let Some(entries) = &mut *self.entries.lock().unwrap() {
+            entries.insert(chunk);
+        }
+    }
+
+    /// Removes committed deletions from the index.
+    pub fn delete(&self, handlers: impl IntoIterator<Item = i64>) {
+        if let Some(entries) = &mut *self.entries.lock().unwrap() {
+            for handler in handlers {
+                entries.delete(handler);
+            }
+        }
+    }
+
+    /// Drops the entries, which are rebuilt by the next lookup.
+    pub fn reset(&self) {
+        *self.entries.lock().unwrap() = None;
+    }
+
+    /// Marks that rows of the table are being moved to new handlers. Lookups wait until
+    /// [`end_move`](Self::end_move) is called.
+    pub fn begin_move(&self) {
+        self.moves.fetch_add(1, Ordering::SeqCst);
+        *self.entries.lock().unwrap() = None;
+    }
+
+    /// Marks that rows of the table have been moved. The entries are rebuilt by the next lookup.
+    pub fn end_move(&self) {
+        *self.entries.lock().unwrap() = None;
+        self.moves.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Finds rows whose leading key is in `range`.
+    ///
+    /// Returns a read-only txn on the table and the sorted handlers of the rows in the txn.
+    pub async fn lookup<T: Table>(
+        &self,
+        table: &T,
+        range: &KeyRange,
+    ) -> StorageResult<(T::Transaction, Vec<i64>)> {
+        loop {
+            let moves = self.moves.load(Ordering::SeqCst);
+            if moves % 2 == 1 {
+                tokio::task::yield_now().await;
+                continue;
+            }
+            let version = table.data_version();
+            let txn = table.read().await?;
+            let cached = (self.entries.lock().unwrap().as_ref()).map(|e| e.lookup(range));
+            let handlers = match cached {
+                Some(handlers) => handlers,
+                None => self.build(table, &txn, version, moves, range).await?,
+            };
+            // handlers are valid in the txn if no row has been moved since it started
+            if self.moves.load(Ordering::SeqCst) == moves {
+                return Ok((txn, handlers));
+            }
+            txn.abort().await?;
+        }
+    }
+
+    /// Builds the entries from the txn, and returns the handlers of rows in `range`.
+    ///
+    /// `version` and `moves` are read before the txn is started. The entries are kept only if the
+    /// table has not been modified since, otherwise they may miss writes committed after the txn
+    /// started.
+    async fn build<T: Table>(
+        &self,
+        table: &T,
+        txn: &T::Transaction,
+        version: u64,
+        moves: u64,
+        range: &KeyRange,
+    ) -> StorageResult<Vec<i64>> {
+        let column_refs = (self.key_positions(&table.columns()?)?.into_iter())
+            .map(|idx| StorageColumnRef::Idx(idx as u32))
+            .chain([StorageColumnRef::RowHandler])
+            .collect_vec();
+        let mut iter = txn.scan(&column_refs, ScanOptions::default()).await?;
+        let mut entries = IndexEntries::default();
+        while let Some(chunk) = iter.next_batch(None).await? {
+            entries.insert(&chunk);
+        }
+        let handlers = entries.lookup(range);
+
+        let mut cache = self.entries.lock().unwrap();
+        if table.data_version() == version && self.moves.load(Ordering::SeqCst) == moves {
+            *cache = Some(entries);
+        }
+        Ok(handlers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::array::ArrayImpl;
+    use crate::catalog::{ColumnDesc, Constraints, TableRefId};
+    use crate::storage::memory::InMemoryRowHandler;
+    use crate::storage::{InMemoryStorage, Storage};
+    use crate::types::DataType;
+
+    #[tokio::test]
+    async fn test_index_lookup() {
+        let storage = InMemoryStorage::new();
+        let columns = vec![
+            ColumnCatalog::new(0, ColumnDesc::new("a", DataType::Int32, false)),
+            ColumnCatalog::new(1, ColumnDesc::new("b", DataType::Int32, true)),
+        ];
+        let table_id = TableRefId::new(1, 0);
+        storage
+            .create_table(1, "t", &columns, &Constraints::default())
+            .await
+            .unwrap();
+        storage.create_index("t_b", table_id, &[1]).await.unwrap();
+        let table = storage.get_table(table_id).unwrap();
+        let index_id = storage.catalog().get_table_indexes(&table_id)[0].id();
+        let index = storage.get_index(1, index_id).unwrap();
+        let range = KeyRange {
+            start: Bound::Excluded(DataValue::Int32(1)),
+            end: Bound::Included(DataValue::Int32(3)),
+        };
+        let append = |a: Vec<i32>, b: Vec<i32>| {
+            let table = table.clone();
+            async move {
+                let mut txn = table.write().await.unwrap();
+                txn.append(
+                    [
+                        ArrayImpl::new_int32(a.into_iter().collect()),
+                        ArrayImpl::new_int32(b.into_iter().collect()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )
+                .await
+                .unwrap();
+                txn.commit().await.unwrap();
+            }
+        };
+        let lookup = || async {
+            let (txn, handlers) = index.lookup(&table, &range).await.unwrap();
+            let rows: Arc<[i64]> = handlers.into();
+            let mut iter = txn
+                .scan(
+                    &[StorageColumnRef::Idx(0)],
+                    ScanOptions::default().with_rows(rows),
+                )
+                .await
+                .unwrap();
+            let mut values = vec![];
+            while let Some(chunk) = iter.next_batch(None).await.unwrap() {
+                values.extend(chunk.rows().map(|row| row.get(0)));
+            }
+            values
+        };
+
+        append((0..5).collect(), vec![3, 2, 1, 2, 5]).await;
+        // the entries are built by the first lookup
+        assert_eq!(lookup().await, [0, 1, 3].map(DataValue::Int32).to_vec());
+
+        // and maintained by later writes
+        append(vec![5], vec![2]).await;
+        assert_eq!(lookup().await, [0, 1, 3, 5].map(DataValue::Int32).to_vec());
+        let mut txn = table.update().await.unwrap();
+        txn.delete(&InMemoryRowHandler(1)).await.unwrap();
+        txn.commit().await.unwrap();
+        assert_eq!(lookup().await, [0, 3, 5].map(DataValue::Int32).to_vec());
+    }
+}
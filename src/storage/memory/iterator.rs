@@ -17,6 +17,10 @@ pub struct InMemoryTxnIterator {
     chunks: Arc<Vec<DataChunk>>,
     deleted_rows: Arc<HashSet<usize>>,
     col_idx: Vec<StorageColumnRef>,
+    /// Only chunks whose index modulo `partition.1` equals `partition.0` are returned.
+    partition: (usize, usize),
+    /// The sorted positions of rows to return. All rows are returned if `None`.
+    rows: Option<Arc<[i64]>>,
     cnt: usize,
     row_cnt: usize,
 }
@@ -26,10 +30,14 @@ impl InMemoryTxnIterator {
         chunks: Arc<Vec<DataChunk>>,
         deleted_rows: Arc<HashSet<usize>>,
         col_idx: &[StorageColumnRef],
+        partition: (usize, usize),
+        rows: Option<Arc<[i64]>>,
     ) -> Self {
         Self {
             chunks,
             col_idx: col_idx.to_vec(),
+            partition,
+            rows,
             cnt: 0,
             row_cnt: 0,
             deleted_rows,
@@ -40,6 +48,12 @@ impl InMemoryTxnIterator {
         &mut self,
         _expected_size: Option<usize>,
     ) -> StorageResult<Option<DataChunk>> {
+        // skip chunks of other partitions
+        let (index, count) = self.partition;
+        while self.cnt < self.chunks.len() && self.cnt % count != index {
+            self.row_cnt += self.chunks[self.cnt].cardinality();
+            self.cnt += 1;
+        }
         if self.cnt >= self.chunks.len() {
             Ok(None)
         } else {
@@ -48,7 +62,11 @@ impl InMemoryTxnIterator {
             let batch_range = self.row_cnt..(selected_chunk.cardinality() + self.row_cnt);
             let visibility = batch_range
                 .clone()
-                .map(|x| !self.deleted_rows.contains(&x))
+                .map(|x| {
+                    !self.deleted_rows.contains(&x)
+                        && (self.rows.as_ref())
+                            .map_or(true, |rows| rows.binary_search(&(x as i64)).is_ok())
+                })
                 .collect::<BitVec>();
 
             let chunk = if self.col_idx.is_empty() {
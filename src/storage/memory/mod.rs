@@ -20,8 +20,12 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use super::{Storage, StorageError, StorageResult, TracedStorageError};
-use crate::catalog::{ColumnCatalog, ColumnId, RootCatalog, RootCatalogRef, SchemaId, TableRefId};
+use super::{Storage, StorageError, StorageResult, TableIndex, TracedStorageError};
+use crate::catalog::{
+    ColumnCatalog, ColumnId, Constraints, IndexId, RootCatalog, RootCatalogRef, SchemaId,
+    TableRefId, ViewDefinition,
+};
+use crate::planner::RecExpr;
 
 mod table;
 pub use table::InMemoryTable;
@@ -39,6 +43,7 @@ pub use row_handler::InMemoryRowHandler;
 pub struct InMemoryStorage {
     catalog: RootCatalogRef,
     tables: Mutex<HashMap<TableRefId, InMemoryTable>>,
+    indexes: Mutex<HashMap<(SchemaId, IndexId), Arc<TableIndex>>>,
 }
 
 impl Default for InMemoryStorage {
@@ -52,6 +57,7 @@ impl InMemoryStorage {
         InMemoryStorage {
             catalog: Arc::new(RootCatalog::new()),
             tables: Mutex::new(HashMap::new()),
+            indexes: Mutex::new(HashMap::new()),
         }
     }
 
@@ -69,7 +75,7 @@ impl Storage for InMemoryStorage {
         schema_id: SchemaId,
         table_name: &str,
         column_descs: &[ColumnCatalog],
-        ordered_pk_ids: &[ColumnId],
+        constraints: &Constraints,
     ) -> StorageResult<()> {
         let schema = self
             .catalog
@@ -84,7 +90,7 @@ impl Storage for InMemoryStorage {
                 schema_id,
                 table_name.into(),
                 column_descs.to_vec(),
-                ordered_pk_ids.to_vec(),
+                constraints.clone(),
             )
             .map_err(|_| StorageError::Duplicated("table", table_name.into()))?;
 
@@ -114,6 +120,138 @@ impl Storage for InMemoryStorage {
             .unwrap()
             .remove(&table_id)
             .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
+        let mut indexes = self.indexes.lock().unwrap();
+        for index in self.catalog.get_table_indexes(&table_id) {
+            indexes.remove(&(table_id.schema_id, index.id()));
+        }
+        self.catalog.drop_table(table_id);
+        Ok(())
+    }
+
+    async fn truncate_table(&self, table_id: TableRefId) -> StorageResult<()> {
+        let table = self.get_table(table_id)?;
+        table.inner.write().unwrap().truncate();
+        Ok(())
+    }
+
+    async fn add_column(&self, table_id: TableRefId, column: &ColumnCatalog) -> StorageResult<()> {
+        let mut tables = self.tables.lock().unwrap();
+        let table = tables
+            .get_mut(&table_id)
+            .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
+        self.catalog
+            .add_column(table_id, column.clone())
+            .map_err(|_| TracedStorageError::duplicated("column", column.name()))?;
+        table.inner.write().unwrap().add_column(column);
+        let mut columns = table.columns.to_vec();
+        columns.push(column.clone());
+        table.columns = columns.into();
+        Ok(())
+    }
+
+    async fn drop_column(&self, table_id: TableRefId, column_id: ColumnId) -> StorageResult<()> {
+        let mut tables = self.tables.lock().unwrap();
+        let table = tables
+            .get_mut(&table_id)
+            .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
+        let idx = (table.columns.iter())
+            .position(|col| col.id() == column_id)
+            .ok_or_else(|| TracedStorageError::not_found("column", column_id))?;
+        self.catalog
+            .drop_column(table_id, column_id)
+            .map_err(|_| TracedStorageError::not_found("column", column_id))?;
+        table.inner.write().unwrap().drop_column(idx);
+        let mut columns = table.columns.to_vec();
+        columns.remove(idx);
+        table.columns = columns.into();
+        Ok(())
+    }
+
+    async fn create_index(
+        &self,
+        index_name: &str,
+        table_id: TableRefId,
+        column_ids: &[ColumnId],
+    ) -> StorageResult<()> {
+        let table = self.get_table(table_id)?;
+        let index_id = self
+            .catalog
+            .add_index(
+                table_id.schema_id,
+                index_name.into(),
+                table_id.table_id,
+                column_ids.to_vec(),
+            )
+            .map_err(|_| TracedStorageError::duplicated("index", index_name))?;
+        let index = Arc::new(TableIndex::new(column_ids));
+        let key_positions = index.key_positions(&table.columns)?;
+        (table.inner.write().unwrap()).add_index(index.clone(), key_positions);
+        (self.indexes.lock().unwrap()).insert((table_id.schema_id, index_id), index);
+        Ok(())
+    }
+
+    async fn drop_index(&self, schema_id: SchemaId, index_id: IndexId) -> StorageResult<()> {
+        let index = self
+            .indexes
+            .lock()
+            .unwrap()
+            .remove(&(schema_id, index_id))
+            .ok_or_else(|| TracedStorageError::not_found("index", index_id))?;
+        if let Some(catalog) = self.catalog.get_index(schema_id, index_id) {
+            let table_id = TableRefId::new(schema_id, catalog.table_id());
+            if let Ok(table) = self.get_table(table_id) {
+                table.inner.write().unwrap().remove_index(&index);
+            }
+        }
+        self.catalog.drop_index(schema_id, index_id);
+        Ok(())
+    }
+
+    fn get_index(&self, schema_id: SchemaId, index_id: IndexId) -> StorageResult<Arc<TableIndex>> {
+        let index = self
+            .indexes
+            .lock()
+            .unwrap()
+            .get(&(schema_id, index_id))
+            .ok_or_else(|| TracedStorageError::not_found("index", index_id))?
+            .clone();
+        Ok(index)
+    }
+
+    async fn create_schema(&self, schema_name: &str) -> StorageResult<()> {
+        self.catalog
+            .add_schema(schema_name.into())
+            .map_err(|_| TracedStorageError::duplicated("schema", schema_name))?;
+        Ok(())
+    }
+
+    async fn drop_schema(&self, schema_id: SchemaId) -> StorageResult<()> {
+        self.catalog.drop_schema(schema_id);
+        Ok(())
+    }
+
+    async fn create_view(
+        &self,
+        schema_id: SchemaId,
+        view_name: &str,
+        column_descs: &[ColumnCatalog],
+        _definition: &ViewDefinition,
+        query: &RecExpr,
+        dependencies: &[TableRefId],
+    ) -> StorageResult<()> {
+        self.catalog
+            .add_view(
+                schema_id,
+                view_name.into(),
+                column_descs.to_vec(),
+                query.clone(),
+                dependencies.to_vec(),
+            )
+            .map_err(|_| TracedStorageError::duplicated("view", view_name))?;
+        Ok(())
+    }
+
+    async fn drop_view(&self, table_id: TableRefId) -> StorageResult<()> {
         self.catalog.drop_table(table_id);
         Ok(())
     }
@@ -3,6 +3,7 @@
 use crate::array::{Array, ArrayImpl};
 use crate::storage::RowHandler;
 
+#[derive(Clone, Copy)]
 pub struct InMemoryRowHandler(pub u64);
 
 impl RowHandler for InMemoryRowHandler {
@@ -5,9 +5,9 @@ use std::sync::{Arc, RwLock};
 use std::vec::Vec;
 
 use super::*;
-use crate::array::DataChunk;
+use crate::array::{ArrayBuilderImpl, ArrayImpl, DataChunk};
 use crate::catalog::TableRefId;
-use crate::storage::Table;
+use crate::storage::{AsOf, Table, TableIndex};
 
 /// A table in in-memory engine. This struct can be freely cloned, as it
 /// only serves as a reference to a table.
@@ -17,11 +17,19 @@ pub struct InMemoryTable {
     pub(super) columns: Arc<[ColumnCatalog]>,
     pub(super) inner: InMemoryTableInnerRef,
     pub(super) ordered_pk_ids: Vec<ColumnId>,
+    /// Held by `update` txns until they end.
+    pub(super) update_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 pub(super) struct InMemoryTableInner {
     chunks: Vec<DataChunk>,
+    /// The number of rows in all chunks.
+    row_count: usize,
     deleted_rows: HashSet<usize>,
+    /// Bumped on every modification.
+    version: u64,
+    /// Indexes on the table, with the positions of their key columns.
+    indexes: Vec<(Arc<TableIndex>, Vec<usize>)>,
 }
 
 pub(super) type InMemoryTableInnerRef = Arc<RwLock<InMemoryTableInner>>;
@@ -30,20 +38,90 @@ impl InMemoryTableInner {
     pub fn new() -> Self {
         Self {
             chunks: vec![],
+            row_count: 0,
             deleted_rows: HashSet::new(),
+            version: 0,
+            indexes: vec![],
         }
     }
 
     pub fn append(&mut self, chunk: DataChunk) -> Result<(), StorageError> {
+        let positions = self.row_count..self.row_count + chunk.cardinality();
+        self.row_count = positions.end;
+        for (index, key_positions) in &self.indexes {
+            let handlers = ArrayImpl::new_int64(positions.clone().map(|pos| pos as i64).collect());
+            let keys: DataChunk = (key_positions.iter())
+                .map(|i| chunk.array_at(*i).clone())
+                .chain([handlers])
+                .collect();
+            index.insert(&keys);
+        }
         self.chunks.push(chunk);
+        self.version += 1;
         Ok(())
     }
 
     pub fn delete(&mut self, row_id: usize) -> Result<(), StorageError> {
         self.deleted_rows.insert(row_id);
+        for (index, _) in &self.indexes {
+            index.delete([row_id as i64]);
+        }
+        self.version += 1;
         Ok(())
     }
 
+    /// Adds an index on the columns at `key_positions`.
+    pub fn add_index(&mut self, index: Arc<TableIndex>, key_positions: Vec<usize>) {
+        self.indexes.push((index, key_positions));
+    }
+
+    /// Removes an index.
+    pub fn remove_index(&mut self, index: &Arc<TableIndex>) {
+        self.indexes.retain(|(i, _)| !Arc::ptr_eq(i, index));
+    }
+
+    /// Appends a column filled with its default value to all chunks.
+    pub fn add_column(&mut self, column: &ColumnCatalog) {
+        for chunk in &mut self.chunks {
+            let mut builder =
+                ArrayBuilderImpl::with_capacity(chunk.cardinality(), &column.data_type());
+            builder.push_n(chunk.cardinality(), &column.default_value());
+            *chunk = (chunk.arrays().iter().cloned())
+                .chain([builder.finish()])
+                .collect();
+        }
+        self.version += 1;
+    }
+
+    /// Removes the column at `idx` from all chunks.
+    pub fn drop_column(&mut self, idx: usize) {
+        for chunk in &mut self.chunks {
+            *chunk = (chunk.arrays().iter().enumerate())
+                .filter(|(i, _)| *i != idx)
+                .map(|(_, array)| array.clone())
+                .collect();
+        }
+        // indexed columns can not be dropped, so only the positions after it are shifted
+        for (_, key_positions) in &mut self.indexes {
+            for pos in key_positions.iter_mut().filter(|pos| **pos > idx) {
+                *pos -= 1;
+            }
+        }
+        self.version += 1;
+    }
+
+    /// Removes all rows. Positions of rows appended later start from 0 again.
+    pub fn truncate(&mut self) {
+        self.indexes
+            .iter()
+            .for_each(|(index, _)| index.begin_move());
+        self.chunks.clear();
+        self.row_count = 0;
+        self.deleted_rows.clear();
+        self.version += 1;
+        self.indexes.iter().for_each(|(index, _)| index.end_move());
+    }
+
     pub fn get_all_chunks(&self) -> Vec<DataChunk> {
         self.chunks.clone()
     }
@@ -60,6 +138,7 @@ impl InMemoryTable {
             columns: columns.into(),
             inner: Arc::new(RwLock::new(InMemoryTableInner::new())),
             ordered_pk_ids: Vec::new(),
+            update_lock: Arc::new(tokio::sync::Mutex::new(())),
         }
     }
 }
@@ -76,18 +155,31 @@ impl Table for InMemoryTable {
     }
 
     async fn write(&self) -> StorageResult<InMemoryTransaction> {
-        InMemoryTransaction::start(self)
+        InMemoryTransaction::start(self, None)
     }
 
     async fn read(&self) -> StorageResult<InMemoryTransaction> {
-        InMemoryTransaction::start(self)
+        InMemoryTransaction::start(self, None)
+    }
+
+    async fn read_as_of(&self, _as_of: AsOf) -> StorageResult<InMemoryTransaction> {
+        Err(StorageError::Unsupported("time travel").into())
     }
 
     async fn update(&self) -> StorageResult<InMemoryTransaction> {
-        InMemoryTransaction::start(self)
+        let lock = self.update_lock.clone().lock_owned().await;
+        InMemoryTransaction::start(self, Some(lock))
+    }
+
+    async fn read_pinned(&self) -> StorageResult<InMemoryTransaction> {
+        InMemoryTransaction::start(self, None)
     }
 
     fn ordered_pk_ids(&self) -> Vec<ColumnId> {
         self.ordered_pk_ids.clone()
     }
+
+    fn data_version(&self) -> u64 {
+        self.inner.read().unwrap().version
+    }
 }
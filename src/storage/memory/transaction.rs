@@ -4,6 +4,7 @@ use std::collections::HashSet;
 use std::sync::Arc;
 
 use itertools::Itertools;
+use tokio::sync::OwnedMutexGuard;
 
 use super::table::InMemoryTableInnerRef;
 use super::{InMemoryRowHandler, InMemoryTable, InMemoryTxnIterator};
@@ -35,10 +36,18 @@ pub struct InMemoryTransaction {
 
     /// Ordered primary key indexes in `column_infos`
     ordered_pk_idx: Vec<usize>,
+
+    /// The update lock of the table, if this is an update txn.
+    _update_lock: Option<OwnedMutexGuard<()>>,
 }
 
 impl InMemoryTransaction {
-    pub(super) fn start(table: &InMemoryTable) -> StorageResult<Self> {
+    /// Start a transaction. The snapshot is taken after `update_lock` is acquired, so that no
+    /// other update txn commits after the snapshot until this txn ends.
+    pub(super) fn start(
+        table: &InMemoryTable,
+        update_lock: Option<OwnedMutexGuard<()>>,
+    ) -> StorageResult<Self> {
         let inner = table.inner.read().unwrap();
         let ordered_pk_idx = table
             .ordered_pk_ids()
@@ -59,6 +68,7 @@ impl InMemoryTransaction {
             snapshot: Arc::new(inner.get_all_chunks()),
             deleted_rows: Arc::new(inner.get_all_deleted_rows()),
             ordered_pk_idx,
+            _update_lock: update_lock,
         })
     }
 }
@@ -136,6 +146,8 @@ impl Transaction for InMemoryTransaction {
             snapshot,
             self.deleted_rows.clone(),
             col_idx,
+            opts.partition.unwrap_or((0, 1)),
+            opts.rows,
         ))
     }
 
@@ -162,6 +174,14 @@ impl Transaction for InMemoryTransaction {
         Ok(())
     }
 
+    async fn commit_all(txns: Vec<Self>) -> StorageResult<()> {
+        // commits of in-memory tables never fail
+        for txn in txns {
+            txn.commit().await?;
+        }
+        Ok(())
+    }
+
     async fn abort(mut self) -> StorageResult<()> {
         self.finished = true;
         Ok(())
@@ -6,7 +6,15 @@ mod memory;
 pub use memory::InMemoryStorage;
 
 mod secondary;
-pub use secondary::{SecondaryStorage, StorageOptions as SecondaryStorageOptions};
+pub use secondary::{
+    BlockCachePolicy, SecondaryStorage, StorageOptions as SecondaryStorageOptions,
+};
+
+mod index;
+pub use index::TableIndex;
+
+mod session;
+pub use session::*;
 
 mod error;
 pub use error::{StorageError, StorageResult, TracedStorageError};
@@ -21,8 +29,11 @@ pub use chunk::*;
 use enum_dispatch::enum_dispatch;
 
 use crate::array::{ArrayImpl, DataChunk};
-use crate::catalog::{ColumnCatalog, ColumnId, SchemaId, TableRefId};
-use crate::types::DataValue;
+use crate::catalog::{
+    ColumnCatalog, ColumnId, Constraints, IndexId, SchemaId, TableRefId, ViewDefinition,
+};
+use crate::planner::RecExpr;
+use crate::types::{DataValue, Timestamp};
 
 #[enum_dispatch(StorageDispatch)]
 #[derive(Clone)]
@@ -60,6 +71,43 @@ impl StorageImpl {
             Self::InMemoryStorage(_) => false,
         }
     }
+
+    /// Begins a transaction of a session.
+    pub fn begin(&self) -> TransactionImpl {
+        match self {
+            Self::InMemoryStorage(s) => {
+                TransactionImpl::InMemoryStorage(SessionTransaction::begin(s.clone()))
+            }
+            Self::SecondaryStorage(s) => {
+                TransactionImpl::SecondaryStorage(SessionTransaction::begin(s.clone()))
+            }
+        }
+    }
+}
+
+/// A transaction of a session on [`StorageImpl`].
+#[derive(Clone)]
+pub enum TransactionImpl {
+    InMemoryStorage(SessionTransaction<InMemoryStorage>),
+    SecondaryStorage(SessionTransaction<SecondaryStorage>),
+}
+
+impl TransactionImpl {
+    /// Applies the writes of the transaction to the storage.
+    pub async fn commit(&self) -> StorageResult<()> {
+        match self {
+            Self::InMemoryStorage(txn) => txn.commit().await,
+            Self::SecondaryStorage(txn) => txn.commit().await,
+        }
+    }
+
+    /// Discards the writes of the transaction.
+    pub async fn rollback(&self) -> StorageResult<()> {
+        match self {
+            Self::InMemoryStorage(txn) => txn.rollback().await,
+            Self::SecondaryStorage(txn) => txn.rollback().await,
+        }
+    }
 }
 
 /// Represents a storage engine.
@@ -75,13 +123,71 @@ pub trait Storage: Sync + Send + 'static {
         schema_id: SchemaId,
         table_name: &str,
         column_descs: &[ColumnCatalog],
-        ordered_pk_ids: &[ColumnId],
+        constraints: &Constraints,
     ) -> impl Future<Output = StorageResult<()>> + Send;
 
     fn get_table(&self, table_id: TableRefId) -> StorageResult<Self::Table>;
 
     fn drop_table(&self, table_id: TableRefId) -> impl Future<Output = StorageResult<()>> + Send;
 
+    /// Remove all rows of the table. The definition and indexes of the table are kept.
+    fn truncate_table(
+        &self,
+        table_id: TableRefId,
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Add a column to the table. Existing rows read the default value of the column.
+    fn add_column(
+        &self,
+        table_id: TableRefId,
+        column: &ColumnCatalog,
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Drop a column from the table.
+    fn drop_column(
+        &self,
+        table_id: TableRefId,
+        column_id: ColumnId,
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Create an index on the columns of the table.
+    fn create_index(
+        &self,
+        index_name: &str,
+        table_id: TableRefId,
+        column_ids: &[ColumnId],
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Drop an index.
+    fn drop_index(
+        &self,
+        schema_id: SchemaId,
+        index_id: IndexId,
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
+    fn get_index(&self, schema_id: SchemaId, index_id: IndexId) -> StorageResult<Arc<TableIndex>>;
+
+    /// Create a schema.
+    fn create_schema(&self, schema_name: &str) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Drop an empty schema.
+    fn drop_schema(&self, schema_id: SchemaId) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Create a view with the bound query. The query is bound from the definition again when the
+    /// catalog is reloaded.
+    fn create_view(
+        &self,
+        schema_id: SchemaId,
+        view_name: &str,
+        column_descs: &[ColumnCatalog],
+        definition: &ViewDefinition,
+        query: &RecExpr,
+        dependencies: &[TableRefId],
+    ) -> impl Future<Output = StorageResult<()>> + Send;
+
+    /// Drop a view.
+    fn drop_view(&self, table_id: TableRefId) -> impl Future<Output = StorageResult<()>> + Send;
+
     // XXX: remove this
     fn as_disk(&self) -> Option<&SecondaryStorage>;
 }
@@ -101,14 +207,46 @@ pub trait Table: Sync + Send + Clone + 'static {
     /// Begin a read-only txn
     fn read(&self) -> impl Future<Output = StorageResult<Self::Transaction>> + Send + '_;
 
-    /// Begin a txn that might delete or update rows
+    /// Begin a read-only txn on a historical version of the table
+    fn read_as_of(
+        &self,
+        as_of: AsOf,
+    ) -> impl Future<Output = StorageResult<Self::Transaction>> + Send + '_;
+
+    /// Begin a txn that might delete or update rows. It blocks other `update` txns on the table
+    /// until it ends.
     fn update(&self) -> impl Future<Output = StorageResult<Self::Transaction>> + Send + '_;
 
+    /// Begin a read-only txn, whose rows can be deleted by an [`update`](Self::update) txn started
+    /// later. Unlike `update`, it does not block other txns.
+    fn read_pinned(&self) -> impl Future<Output = StorageResult<Self::Transaction>> + Send + '_;
+
     /// Get table id
     fn table_id(&self) -> TableRefId;
 
     /// Get primary key
     fn ordered_pk_ids(&self) -> Vec<ColumnId>;
+
+    /// Get a number that changes whenever rows or columns of the table are modified.
+    fn data_version(&self) -> u64;
+}
+
+/// A historical version of the storage read by time travel queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsOf {
+    /// The version committed at the epoch.
+    Epoch(u64),
+    /// The latest version committed at or before the time, in microseconds since the Unix epoch.
+    Timestamp(i64),
+}
+
+impl std::fmt::Display for AsOf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Epoch(epoch) => write!(f, "epoch {epoch}"),
+            Self::Timestamp(micros) => write!(f, "{}", Timestamp::from_unix_micros(*micros)),
+        }
+    }
 }
 
 /// Reference to a column.
@@ -117,14 +255,13 @@ pub enum StorageColumnRef {
     /// A runtime column which contains necessary information to locate a row
     /// **only valid in the current transaction**.
     RowHandler,
-    /// User column index. Note that this index is NOT the `ColumnId` in catalog. It is the storage
-    /// column id, which is the same as the position of a column in the column catalog passed to a
-    /// RowSet.
+    /// User column index. Note that this index is NOT the `ColumnId` in catalog. It is the
+    /// position of a column in the current columns of the table.
     Idx(u32),
 }
 
 /// A temporary reference to a row in table.
-pub trait RowHandler: Sync + Send + 'static {
+pub trait RowHandler: Sync + Send + Clone + 'static {
     fn from_column(column: &ArrayImpl, idx: usize) -> Self;
 }
 
@@ -158,6 +295,11 @@ pub trait Transaction: Sync + Send + 'static {
     /// Commit a transaction.
     fn commit(self) -> impl Future<Output = StorageResult<()>> + Send;
 
+    /// Commit transactions on different tables atomically.
+    fn commit_all(txns: Vec<Self>) -> impl Future<Output = StorageResult<()>> + Send
+    where
+        Self: Sized;
+
     /// Abort a transaction.
     fn abort(self) -> impl Future<Output = StorageResult<()>> + Send;
 }
@@ -168,6 +310,14 @@ pub struct ScanOptions {
     is_sorted: bool,
     reversed: bool,
     filter: Option<KeyRange>,
+    /// Ranges of columns, given by their indexes in the table.
+    zone_filters: Vec<(u32, KeyRange)>,
+    /// The index of the partition to scan and the number of partitions.
+    partition: Option<(usize, usize)>,
+    /// The number of blocks to read ahead of the current block of each column.
+    prefetch_blocks: usize,
+    /// Handlers of the rows to read, in ascending order. All rows are read if `None`.
+    rows: Option<Arc<[i64]>>,
 }
 
 impl ScanOptions {
@@ -177,10 +327,39 @@ impl ScanOptions {
         self
     }
 
+    /// Skip rows whose values of a column are out of its range. Blocks whose values are all out of
+    /// the range are skipped if the storage records their minimum and maximum values, and other
+    /// columns of skipped rows may not be decoded. Rows out of the range may still be returned.
+    pub fn with_zone_filters(mut self, zone_filters: Vec<(u32, KeyRange)>) -> Self {
+        self.zone_filters = zone_filters;
+        self
+    }
+
+    /// Scan one of `count` disjoint partitions of the table. Partitions are split by the unit of
+    /// storage, e.g. RowSets, so their sizes may vary.
+    pub fn with_partition(mut self, index: usize, count: usize) -> Self {
+        self.partition = Some((index, count));
+        self
+    }
+
+    /// Read the next `blocks` blocks in background while decoding the current block, if the
+    /// storage reads data in blocks. No blocks are prefetched by default.
+    pub fn with_prefetch(mut self, blocks: usize) -> Self {
+        self.prefetch_blocks = blocks;
+        self
+    }
+
     pub fn with_sorted(mut self, sorted: bool) -> Self {
         self.is_sorted = sorted;
         self
     }
+
+    /// Only read the rows of the given handlers, which are sorted in ascending order. Handlers of
+    /// rows not in the transaction are ignored.
+    pub fn with_rows(mut self, rows: Arc<[i64]>) -> Self {
+        self.rows = Some(rows);
+        self
+    }
 }
 
 /// A range of keys.
@@ -207,6 +386,33 @@ pub struct KeyRange {
     pub end: Bound<DataValue>,
 }
 
+impl KeyRange {
+    /// Returns true if some values between `min` and `max` may be in the range.
+    ///
+    /// Bounds of a different type than `min` and `max` can not be compared, so they are ignored.
+    pub fn overlaps(&self, min: &DataValue, max: &DataValue) -> bool {
+        let comparable = |v: &DataValue| std::mem::discriminant(v) == std::mem::discriminant(min);
+        let after_start = match &self.start {
+            Bound::Included(start) if comparable(start) => max >= start,
+            Bound::Excluded(start) if comparable(start) => max > start,
+            _ => true,
+        };
+        let before_end = match &self.end {
+            Bound::Included(end) if comparable(end) => min <= end,
+            Bound::Excluded(end) if comparable(end) => min < end,
+            _ => true,
+        };
+        after_start && before_end
+    }
+
+    /// Returns true if the value may be in the range.
+    ///
+    /// Bounds of a different type than the value are ignored, so nulls are always in the range.
+    pub fn may_contain(&self, value: &DataValue) -> bool {
+        self.overlaps(value, value)
+    }
+}
+
 impl RangeBounds<DataValue> for KeyRange {
     fn start_bound(&self) -> Bound<&DataValue> {
         match &self.start {
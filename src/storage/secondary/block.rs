@@ -7,6 +7,7 @@
 mod blob_block_builder;
 mod blob_block_iterator;
 mod char_block_builder;
+mod compressed_block;
 mod dict_block_builder;
 mod dict_block_iterator;
 mod fake_block_iterator;
@@ -21,6 +22,7 @@ use bitvec::prelude::{BitVec, Lsb0};
 pub use blob_block_builder::*;
 pub use blob_block_iterator::*;
 pub use char_block_builder::*;
+pub use compressed_block::*;
 pub use fake_block_iterator::*;
 pub use nullable_block_builder::*;
 pub use primitive_block_builder::*;
@@ -36,10 +38,12 @@ pub use rle_block_iterator::*;
 mod block_index_builder;
 pub use block_index_builder::*;
 use bytes::{Buf, BufMut, Bytes};
+use moka::future::Cache;
+use moka::policy::EvictionPolicy;
 use risinglight_proto::rowset::block_checksum::ChecksumType;
 use risinglight_proto::rowset::block_index::BlockType;
 
-use super::StorageResult;
+use super::{BlockCachePolicy, StorageResult};
 use crate::array::Array;
 use crate::storage::TracedStorageError;
 
@@ -55,6 +59,9 @@ pub type Block = Bytes;
 /// |    data     | block_type | cksum_type | cksum  |
 /// |  variable   |    4B      |     4B     |   8B   |
 /// ```
+///
+/// If the column is compressed, data is compressed by [`compress_block`] after the block is
+/// finished, and `block_type` becomes the type of the compression.
 pub trait BlockBuilder<A: Array> {
     /// Append one data into the block, or default/null value if item is None
     fn append(&mut self, item: Option<&A::Item>);
@@ -115,6 +122,19 @@ pub trait NonNullableBlockIterator<A: Array> {
     ) -> usize;
 }
 
+/// Creates a block cache holding at most `capacity` bytes of blocks.
+pub fn new_block_cache(capacity: usize, policy: BlockCachePolicy) -> Cache<BlockCacheKey, Block> {
+    let policy = match policy {
+        BlockCachePolicy::Lru => EvictionPolicy::lru(),
+        BlockCachePolicy::TinyLfu => EvictionPolicy::tiny_lfu(),
+    };
+    Cache::builder()
+        .max_capacity(capacity as u64)
+        .weigher(|_, block: &Block| block.len().try_into().unwrap_or(u32::MAX))
+        .eviction_policy(policy)
+        .build()
+}
+
 /// A key in block cache contains `rowset_id`, `column_id` and `block_id`.
 ///
 /// TODO: support per-table self-increment RowSet Id. Currently, all tables share one RowSet ID
@@ -176,3 +196,22 @@ impl BlockMeta {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_block_cache_capacity() {
+        let cache = new_block_cache(100, BlockCachePolicy::Lru);
+        for block_id in 0..3 {
+            let key = BlockCacheKey::default().block(block_id);
+            cache.insert(key, Bytes::from(vec![0; 40])).await;
+        }
+        cache.run_pending_tasks().await;
+        assert_eq!(cache.weighted_size(), 80);
+        // the least recently used block is evicted
+        let key = BlockCacheKey::default().block(0);
+        assert!(cache.get(&key).await.is_none());
+    }
+}
@@ -3,7 +3,7 @@
 use risinglight_proto::rowset::block_index::BlockType;
 use risinglight_proto::rowset::{BlockIndex, BlockStatistics};
 
-use super::{BlockMeta, BLOCK_META_NON_CHECKSUM_SIZE, BLOCK_META_SIZE};
+use super::{compress_block, BlockMeta, BLOCK_META_NON_CHECKSUM_SIZE, BLOCK_META_SIZE};
 use crate::storage::secondary::{build_checksum, ColumnBuilderOptions};
 
 /// Builds the block index.
@@ -22,6 +22,9 @@ pub struct BlockIndexBuilder {
 
     /// Builder options
     options: ColumnBuilderOptions,
+
+    /// Version of the value encoding recorded in each index
+    encoding_version: u32,
 }
 
 impl BlockIndexBuilder {
@@ -32,9 +35,16 @@ impl BlockIndexBuilder {
             indexes: vec![],
             block_header: vec![],
             options,
+            encoding_version: 0,
         }
     }
 
+    /// Records the version of the value encoding in the index of each block.
+    pub fn with_encoding_version(mut self, encoding_version: u32) -> Self {
+        self.encoding_version = encoding_version;
+        self
+    }
+
     /// Record information of a block and produce a new index entry.
     pub fn finish_block(
         &mut self,
@@ -44,6 +54,14 @@ impl BlockIndexBuilder {
         stats: Vec<BlockStatistics>,
         first_key: Option<Vec<u8>>,
     ) {
+        let mut block_type = block_type;
+        if let Some((compressed_type, compressed)) =
+            compress_block(self.options.compression, block_type, block_data)
+        {
+            block_type = compressed_type;
+            *block_data = compressed;
+        }
+
         self.indexes.push(BlockIndex {
             offset: column_data.len() as u64,
             length: block_data.len() as u64 + BLOCK_META_SIZE as u64,
@@ -53,6 +71,7 @@ impl BlockIndexBuilder {
             is_first_key_null: first_key.is_none(),
             first_key: first_key.unwrap_or_default(),
             stats,
+            encoding_version: self.encoding_version,
         });
 
         // the new block will begin at the current row count
@@ -0,0 +1,85 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use bytes::{Buf, BufMut};
+use risinglight_proto::rowset::block_index::BlockType;
+
+use crate::catalog::Compression;
+use crate::storage::{StorageResult, TracedStorageError};
+
+/// Compresses an encoded block. The layout is
+///
+/// ```plain
+/// | compressed data | block_type of data (4B) |
+/// ```
+///
+/// The type of the compressed block is `ZstdCompress` or `Lz4Compress`. Returns `None` if the
+/// block is not compressed, or compression does not make it smaller.
+pub fn compress_block(
+    compression: Compression,
+    block_type: BlockType,
+    data: &[u8],
+) -> Option<(BlockType, Vec<u8>)> {
+    let (compressed_type, mut compressed) = match compression {
+        Compression::None => return None,
+        Compression::Zstd => (BlockType::ZstdCompress, zstd::bulk::compress(data, 0).ok()?),
+        Compression::Lz4 => (
+            BlockType::Lz4Compress,
+            lz4_flex::compress_prepend_size(data),
+        ),
+    };
+    if compressed.len() + 4 >= data.len() {
+        return None;
+    }
+    compressed.put_i32(block_type.into());
+    Some((compressed_type, compressed))
+}
+
+/// Decompresses a block built by [`compress_block`]. Returns the type and data of the block
+/// before compression.
+pub fn decompress_block(block_type: BlockType, data: &[u8]) -> StorageResult<(BlockType, Vec<u8>)> {
+    if data.len() < 4 {
+        return Err(TracedStorageError::decode("compressed block is too small"));
+    }
+    let (compressed, mut inner_type) = data.split_at(data.len() - 4);
+    let inner_type = BlockType::try_from(inner_type.get_i32())
+        .map_err(|_| TracedStorageError::decode("expected valid block type"))?;
+    let data = match block_type {
+        BlockType::ZstdCompress => zstd::stream::decode_all(compressed)
+            .map_err(|e| TracedStorageError::decode(format!("zstd: {e}")))?,
+        BlockType::Lz4Compress => lz4_flex::decompress_size_prepended(compressed)
+            .map_err(|e| TracedStorageError::decode(format!("lz4: {e}")))?,
+        _ => return Err(TracedStorageError::decode("expected compressed block")),
+    };
+    Ok((inner_type, data))
+}
+
+/// Returns true if the block is built by [`compress_block`].
+pub fn is_compressed_block(block_type: BlockType) -> bool {
+    matches!(block_type, BlockType::ZstdCompress | BlockType::Lz4Compress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_block() {
+        let data = [1u8, 2, 3, 4].repeat(1000);
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            let (block_type, compressed) =
+                compress_block(compression, BlockType::RunLength, &data).unwrap();
+            assert!(is_compressed_block(block_type));
+            assert!(compressed.len() < data.len());
+            let (inner_type, decompressed) = decompress_block(block_type, &compressed).unwrap();
+            assert_eq!(inner_type, BlockType::RunLength);
+            assert_eq!(decompressed, data);
+        }
+    }
+
+    #[test]
+    fn test_incompressible_block() {
+        let data = [1u8, 2, 3];
+        assert!(compress_block(Compression::Zstd, BlockType::Plain, &data).is_none());
+        assert!(compress_block(Compression::None, BlockType::Plain, &data).is_none());
+    }
+}
@@ -85,7 +85,10 @@ where
             block_stat_type: BlockStatisticsType::DistinctValue as i32,
             body: distinct_count.to_le_bytes().to_vec(),
         };
-        vec![distinct_stat]
+        // runs of keys are the same as runs of values
+        let run_stat = (self.rle_builder.get_statistics().into_iter())
+            .filter(|stat| stat.block_stat_type() == BlockStatisticsType::RunCount);
+        std::iter::once(distinct_stat).chain(run_stat).collect()
     }
 
     fn should_finish(&self, next_item: &Option<&A::Item>) -> bool {
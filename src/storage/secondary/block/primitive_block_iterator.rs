@@ -17,6 +17,9 @@ pub struct PlainPrimitiveBlockIterator<T: PrimitiveFixedWidthEncode> {
     /// Indicates the beginning row of the next batch
     next_row: usize,
 
+    /// Version of the value encoding
+    version: u32,
+
     _phantom: PhantomData<T>,
 }
 
@@ -26,9 +29,16 @@ impl<T: PrimitiveFixedWidthEncode> PlainPrimitiveBlockIterator<T> {
             block,
             row_count,
             next_row: 0,
+            version: T::VERSION,
             _phantom: PhantomData,
         }
     }
+
+    /// Decodes values in the given version of encoding.
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
 }
 
 impl<T: PrimitiveFixedWidthEncode> NonNullableBlockIterator<T::ArrayType>
@@ -46,7 +56,7 @@ impl<T: PrimitiveFixedWidthEncode> NonNullableBlockIterator<T::ArrayType>
         // TODO(chi): error handling on corrupted block
 
         let mut cnt = 0;
-        let mut buffer = &self.block[self.next_row * T::WIDTH..];
+        let mut buffer = &self.block[self.next_row * T::width(self.version)..];
 
         loop {
             if let Some(expected_size) = expected_size {
@@ -60,7 +70,7 @@ impl<T: PrimitiveFixedWidthEncode> NonNullableBlockIterator<T::ArrayType>
                 break;
             }
 
-            builder.push(Some(&T::decode(&mut buffer)));
+            builder.push(Some(&T::decode_version(&mut buffer, self.version)));
             cnt += 1;
             self.next_row += 1;
         }
@@ -89,12 +99,13 @@ impl<T: PrimitiveFixedWidthEncode> BlockIterator<T::ArrayType> for PlainPrimitiv
 
 #[cfg(test)]
 mod tests {
-    use bytes::Bytes;
+    use bytes::{BufMut, Bytes};
 
     use super::PlainPrimitiveBlockIterator;
-    use crate::array::{ArrayBuilder, ArrayToVecExt, I32ArrayBuilder};
+    use crate::array::{ArrayBuilder, ArrayToVecExt, I32ArrayBuilder, IntervalArrayBuilder};
     use crate::storage::secondary::block::{BlockBuilder, PlainPrimitiveBlockBuilder};
     use crate::storage::secondary::BlockIterator;
+    use crate::types::Interval;
 
     #[test]
     fn test_scan_i32() {
@@ -122,4 +133,24 @@ mod tests {
         let mut builder = I32ArrayBuilder::new();
         assert_eq!(scanner.next_batch(None, &mut builder), 0);
     }
+
+    #[test]
+    fn test_scan_legacy_interval() {
+        // version 0 of interval only encodes months and days
+        let mut data = vec![];
+        for (months, days) in [(1, 2), (3, 4)] {
+            data.put_i32(months);
+            data.put_i32(days);
+        }
+
+        let mut scanner =
+            PlainPrimitiveBlockIterator::<Interval>::new(Bytes::from(data), 2).with_version(0);
+
+        let mut builder = IntervalArrayBuilder::new();
+        assert_eq!(scanner.next_batch(None, &mut builder), 2);
+        assert_eq!(
+            builder.finish().to_vec(),
+            vec![Some(Interval::from_md(1, 2)), Some(Interval::from_md(3, 4))]
+        );
+    }
 }
@@ -0,0 +1,148 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Bloom filters of columns in a rowset.
+//!
+//! A bloom filter is stored in `{column_id}.bloom` of the rowset directory, in the format of:
+//!
+//! ```plain
+//! | num_hashes (u32) | bits (u64) | bits (u64) | ... |
+//! ```
+
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::AtomicU64;
+
+use bytes::{Buf, BufMut};
+
+use crate::storage::{StorageResult, TracedStorageError};
+use crate::types::DataValue;
+
+/// Number of bits for each distinct value, which gives a false positive rate of about 1%.
+const BITS_PER_KEY: usize = 10;
+
+/// Number of hash functions. `BITS_PER_KEY * ln(2)` is optimal.
+const NUM_HASHES: u32 = 7;
+
+/// A bloom filter of the values in a column.
+pub struct BloomFilter {
+    num_hashes: u32,
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Decode a bloom filter from the content built by [`BloomFilterBuilder`].
+    pub fn from_bytes(mut data: &[u8]) -> StorageResult<Self> {
+        if data.len() < 4 || (data.len() - 4) % 8 != 0 {
+            return Err(TracedStorageError::decode("invalid bloom filter"));
+        }
+        let num_hashes = data.get_u32_le();
+        let mut bits = Vec::with_capacity(data.len() / 8);
+        while data.has_remaining() {
+            bits.push(data.get_u64_le());
+        }
+        Ok(Self { num_hashes, bits })
+    }
+
+    /// Returns `false` if the value is definitely not in the column.
+    pub fn may_contain(&self, value: &DataValue) -> bool {
+        if value.is_null() || self.bits.is_empty() {
+            return false;
+        }
+        let num_bits = self.bits.len() as u64 * 64;
+        bit_positions(value, self.num_hashes, num_bits)
+            .all(|pos| self.bits[(pos / 64) as usize] & (1 << (pos % 64)) != 0)
+    }
+}
+
+/// Builds a [`BloomFilter`] of the values appended.
+///
+/// NULL values are never added.
+#[derive(Default)]
+pub struct BloomFilterBuilder {
+    values: Vec<DataValue>,
+}
+
+impl BloomFilterBuilder {
+    pub fn add(&mut self, value: DataValue) {
+        if !value.is_null() {
+            self.values.push(value);
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.values.sort_unstable();
+        self.values.dedup();
+
+        let num_words = (self.values.len() * BITS_PER_KEY).div_ceil(64);
+        let mut bits = vec![0u64; num_words];
+        let num_bits = num_words as u64 * 64;
+        for value in &self.values {
+            for pos in bit_positions(value, NUM_HASHES, num_bits) {
+                bits[(pos / 64) as usize] |= 1 << (pos % 64);
+            }
+        }
+
+        let mut data = Vec::with_capacity(4 + num_words * 8);
+        data.put_u32_le(NUM_HASHES);
+        for word in bits {
+            data.put_u64_le(word);
+        }
+        data
+    }
+}
+
+/// Statistics of bloom filter checks on a table.
+#[derive(Default)]
+pub struct BloomFilterStats {
+    /// Number of rowsets whose bloom filters are checked.
+    pub checked: AtomicU64,
+    /// Number of rowsets skipped by bloom filters.
+    pub pruned: AtomicU64,
+}
+
+/// Returns the positions of bits for a value, using double hashing.
+///
+/// The hashes must be stable across versions, so we use crc32 instead of the default hasher.
+fn bit_positions(value: &DataValue, num_hashes: u32, num_bits: u64) -> impl Iterator<Item = u64> {
+    let hash = |seed: u32| {
+        let mut hasher = crc32fast::Hasher::new_with_initial(seed);
+        value.hash(&mut hasher);
+        hasher.finish()
+    };
+    let h1 = hash(0);
+    let h2 = hash(0x9e37_79b9) | 1;
+    (0..num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut builder = BloomFilterBuilder::default();
+        for i in 0..1000 {
+            builder.add(DataValue::Int32(i * 2));
+        }
+        builder.add(DataValue::Null);
+        let filter = BloomFilter::from_bytes(&builder.finish()).unwrap();
+
+        for i in 0..1000 {
+            assert!(filter.may_contain(&DataValue::Int32(i * 2)));
+        }
+        let false_positives = (0..1000)
+            .filter(|i| filter.may_contain(&DataValue::Int32(i * 2 + 1)))
+            .count();
+        assert!(
+            false_positives < 50,
+            "too many false positives: {false_positives}"
+        );
+        assert!(!filter.may_contain(&DataValue::Null));
+    }
+
+    #[test]
+    fn test_empty_bloom_filter() {
+        let filter = BloomFilter::from_bytes(&BloomFilterBuilder::default().finish()).unwrap();
+        assert!(!filter.may_contain(&DataValue::Int32(1)));
+        assert!(BloomFilter::from_bytes(&[1, 2, 3]).is_err());
+    }
+}
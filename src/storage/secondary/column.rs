@@ -13,6 +13,7 @@ mod char_column_builder;
 mod column_builder;
 mod column_iterator;
 mod concrete_column_iterator;
+mod default_column_iterator;
 mod primitive_column_builder;
 mod primitive_column_factory;
 mod row_handler_column_iterator;
@@ -24,6 +25,7 @@ pub use blob_column_factory::*;
 pub use column_builder::*;
 pub use column_iterator::*;
 pub use concrete_column_iterator::*;
+pub use default_column_iterator::*;
 pub use primitive_column_builder::*;
 pub use primitive_column_factory::*;
 use risinglight_proto::rowset::BlockIndex;
@@ -37,10 +39,14 @@ pub use char_column_factory::*;
 use moka::future::Cache;
 
 use super::block::BLOCK_META_CHECKSUM_SIZE;
-use super::{Block, BlockCacheKey, BlockMeta, ColumnIndex, BLOCK_META_SIZE};
+use super::{
+    decompress_block, is_compressed_block, Block, BlockCacheKey, BlockMeta, ColumnIndex,
+    BLOCK_META_SIZE,
+};
 use crate::array::Array;
 use crate::storage::secondary::verify_checksum;
 use crate::storage::{StorageResult, TracedStorageError};
+use crate::utils::metrics;
 
 /// Builds a column. [`ColumnBuilder`] will automatically chunk [`Array`] into
 /// blocks, calls `BlockBuilder` to generate a block, and builds index for a
@@ -118,6 +124,17 @@ pub struct Column {
     file: ColumnReadableFile,
     block_cache: Cache<BlockCacheKey, Block>,
     base_block_key: BlockCacheKey,
+    /// The number of blocks to fetch ahead of the current block by iterators.
+    prefetch_blocks: usize,
+}
+
+/// A block being fetched into the block cache in background, which is cancelled when dropped.
+pub struct PrefetchHandle(tokio::task::JoinHandle<()>);
+
+impl Drop for PrefetchHandle {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 impl Column {
@@ -132,9 +149,30 @@ impl Column {
             file,
             block_cache,
             base_block_key,
+            prefetch_blocks: 0,
         }
     }
 
+    /// Makes iterators of the column fetch `n` blocks ahead of the current block in background,
+    /// so that reading blocks overlaps with decoding.
+    pub fn with_prefetch(mut self, n: usize) -> Self {
+        self.prefetch_blocks = n;
+        self
+    }
+
+    pub fn prefetch_blocks(&self) -> usize {
+        self.prefetch_blocks
+    }
+
+    /// Starts fetching a block into the block cache in background.
+    pub fn prefetch(&self, block_id: u32) -> PrefetchHandle {
+        let column = self.clone();
+        PrefetchHandle(tokio::spawn(async move {
+            // errors are returned when the block is read by the iterator
+            _ = column.get_block(block_id).await;
+        }))
+    }
+
     pub fn index(&self) -> &ColumnIndex {
         &self.index
     }
@@ -145,20 +183,18 @@ impl Column {
     }
 
     pub async fn get_block(&self, block_id: u32) -> StorageResult<(BlockMeta, Block)> {
-        // It is possible that there will be multiple futures accessing
-        // one block not in cache concurrently, which might cause avalanche
-        // in cache. For now, we don't handle it.
+        // Concurrent accesses to one block not in cache, e.g. from prefetching, are coalesced
+        // into a single read by the cache.
 
         let key = self.base_block_key.clone().block(block_id);
-
-        let mut block_header = BlockMeta::default();
-        let mut do_verify_checksum = false;
+        let mut missed = false;
 
         // support multiple I/O backend
         let block =
             self.block_cache
                 .try_get_with(key, async {
                     // block has not been in cache, so we fetch it from disk
+                    missed = true;
                     let file = self.file.clone();
                     let info = self.index.index(block_id).clone();
                     let block = tokio::task::spawn_blocking(move || {
@@ -181,32 +217,59 @@ impl Column {
                         Ok::<_, TracedStorageError>(data)
                     })
                     .await
-                    .unwrap();
+                    .unwrap()?;
                     // TODO(chi): we should invalidate cache item after a RowSet has been compacted.
                     // self.block_cache.insert(key, block.clone()).await;
 
-                    // need to verify checksum when read from disk
-                    do_verify_checksum = true;
-                    block
+                    // need to verify checksum and decompress when read from disk
+                    verify_and_decompress(block)
                 })
                 .await?;
-
-        if block.len() < BLOCK_META_SIZE {
-            return Err(TracedStorageError::decode(
-                "block is smaller than header size",
-            ));
+        if missed {
+            metrics::BLOCK_CACHE_MISSES.inc();
+        } else {
+            metrics::BLOCK_CACHE_HITS.inc();
         }
+
+        let mut block_header = BlockMeta::default();
         let mut header = &block[block.len() - BLOCK_META_SIZE..];
         block_header.decode(&mut header)?;
 
-        if do_verify_checksum {
-            verify_checksum(
-                block_header.checksum_type,
-                &block[..block.len() - BLOCK_META_CHECKSUM_SIZE],
-                block_header.checksum,
-            )?;
-        }
-
         Ok((block_header, block.slice(..block.len() - BLOCK_META_SIZE)))
     }
 }
+
+/// Verifies the checksum of a block read from disk. If the block is compressed, returns the
+/// decompressed block with a header of its type and no checksum.
+fn verify_and_decompress(block: Block) -> StorageResult<Block> {
+    if block.len() < BLOCK_META_SIZE {
+        return Err(TracedStorageError::decode(
+            "block is smaller than header size",
+        ));
+    }
+    let mut block_header = BlockMeta::default();
+    let mut header = &block[block.len() - BLOCK_META_SIZE..];
+    block_header.decode(&mut header)?;
+
+    verify_checksum(
+        block_header.checksum_type,
+        &block[..block.len() - BLOCK_META_CHECKSUM_SIZE],
+        block_header.checksum,
+    )?;
+
+    if !is_compressed_block(block_header.block_type) {
+        return Ok(block);
+    }
+    let (block_type, mut data) = decompress_block(
+        block_header.block_type,
+        &block[..block.len() - BLOCK_META_SIZE],
+    )?;
+    let header = BlockMeta {
+        block_type,
+        checksum_type: ChecksumType::None,
+        checksum: 0,
+    };
+    header.encode_except_checksum(&mut data);
+    header.encode_checksum(&mut data);
+    Ok(data.into())
+}
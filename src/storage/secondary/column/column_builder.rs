@@ -10,7 +10,7 @@ use super::primitive_column_builder::{
     I64ColumnBuilder,
 };
 use super::{BoolColumnBuilder, ColumnBuilder};
-use crate::array::ArrayImpl;
+use crate::array::{Array, ArrayImpl, BlobArray};
 use crate::storage::secondary::column::{
     IntervalColumnBuilder, TimestampColumnBuilder, TimestampTzColumnBuilder,
 };
@@ -30,6 +30,10 @@ pub enum ColumnBuilderImpl {
     TimestampTz(TimestampTzColumnBuilder),
     Interval(IntervalColumnBuilder),
     Blob(BlobColumnBuilder),
+    /// JSON values are stored as blobs in their binary format.
+    Json(BlobColumnBuilder),
+    /// Lists are serialized into blobs.
+    List(BlobColumnBuilder),
 }
 
 impl ColumnBuilderImpl {
@@ -53,6 +57,8 @@ impl ColumnBuilderImpl {
             TimestampTz => Self::TimestampTz(TimestampTzColumnBuilder::new(nullable, options)),
             Interval => Self::Interval(IntervalColumnBuilder::new(nullable, options)),
             Blob => Self::Blob(BlobColumnBuilder::new(nullable, options)),
+            Json => Self::Json(BlobColumnBuilder::new(nullable, options)),
+            List(_) => Self::List(BlobColumnBuilder::new(nullable, options)),
             Struct(_) => todo!("struct column builder"),
         }
     }
@@ -71,6 +77,19 @@ impl ColumnBuilderImpl {
             (Self::TimestampTz(builder), ArrayImpl::TimestampTz(array)) => builder.append(array),
             (Self::Interval(builder), ArrayImpl::Interval(array)) => builder.append(array),
             (Self::Blob(builder), ArrayImpl::Blob(array)) => builder.append(array),
+            (Self::Json(builder), ArrayImpl::Json(array)) => {
+                let blobs: BlobArray = (array.iter())
+                    .map(|json| json.map(|json| crate::types::Blob::from(json.as_bytes())))
+                    .collect();
+                builder.append(&blobs)
+            }
+            (Self::List(builder), ArrayImpl::List(array)) => {
+                let blobs: BlobArray = (array.iter())
+                    .map(|list| list.map(|list| serde_json::to_vec(&**list).unwrap()))
+                    .map(|list| list.map(crate::types::Blob::from))
+                    .collect();
+                builder.append(&blobs)
+            }
             _ => todo!(),
         }
     }
@@ -88,7 +107,7 @@ impl ColumnBuilderImpl {
             Self::Timestamp(builder) => builder.finish(),
             Self::TimestampTz(builder) => builder.finish(),
             Self::Interval(builder) => builder.finish(),
-            Self::Blob(builder) => builder.finish(),
+            Self::Blob(builder) | Self::Json(builder) | Self::List(builder) => builder.finish(),
         }
     }
 }
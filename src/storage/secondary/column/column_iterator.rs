@@ -1,10 +1,12 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use bitvec::prelude::BitSlice;
+
 use super::*;
-use crate::array::{Array, ArrayImpl};
+use crate::array::{Array, ArrayBuilderImpl, ArrayImpl, JsonArray, ListArray};
 use crate::catalog::ColumnCatalog;
 use crate::storage::secondary::column::{DateColumnIterator, IntervalColumnIterator};
-use crate::types::DataType;
+use crate::types::{DataType, DataValue, JsonbRef};
 
 /// [`ColumnIteratorImpl`] of all types
 pub enum ColumnIteratorImpl {
@@ -20,8 +22,14 @@ pub enum ColumnIteratorImpl {
     TimestampTz(TimestampTzColumnIterator),
     Interval(IntervalColumnIterator),
     Blob(BlobColumnIterator),
+    /// JSON values are stored as blobs in their binary format.
+    Json(BlobColumnIterator),
+    /// Lists are stored as serialized blobs.
+    List(BlobColumnIterator),
     /// Special for row handler and not correspond to any data type
     RowHandler(RowHandlerColumnIterator),
+    /// Special for columns missing in a rowset, which are filled with the default value
+    Default(DefaultColumnIterator),
 }
 
 impl ColumnIteratorImpl {
@@ -97,6 +105,22 @@ impl ColumnIteratorImpl {
                 )
                 .await?,
             ),
+            Json => Self::Json(
+                BlobColumnIterator::new(
+                    column,
+                    start_pos,
+                    super::blob_column_factory::BlobBlockIteratorFactory(),
+                )
+                .await?,
+            ),
+            List(_) => Self::List(
+                BlobColumnIterator::new(
+                    column,
+                    start_pos,
+                    super::blob_column_factory::BlobBlockIteratorFactory(),
+                )
+                .await?,
+            ),
             Struct(_) => todo!("struct column iterator"),
         };
         Ok(iter)
@@ -111,6 +135,15 @@ impl ColumnIteratorImpl {
         Ok(iter)
     }
 
+    pub fn new_default(column_info: &ColumnCatalog, row_count: u32, start_pos: u32) -> Self {
+        Self::Default(DefaultColumnIterator::new(
+            column_info.data_type(),
+            column_info.default_value(),
+            row_count as usize,
+            start_pos as usize,
+        ))
+    }
+
     fn erase_concrete_type(
         ret: Option<(u32, impl Array + Into<ArrayImpl>)>,
     ) -> Option<(u32, ArrayImpl)> {
@@ -134,11 +167,76 @@ impl ColumnIteratorImpl {
             Self::TimestampTz(it) => Self::erase_concrete_type(it.next_batch(expected_size).await?),
             Self::Interval(it) => Self::erase_concrete_type(it.next_batch(expected_size).await?),
             Self::Blob(it) => Self::erase_concrete_type(it.next_batch(expected_size).await?),
+            Self::Json(it) => match it.next_batch(expected_size).await? {
+                Some((row_id, array)) => {
+                    let json: JsonArray = (array.iter())
+                        .map(|blob| blob.map(|blob| JsonbRef::new(blob.as_ref())))
+                        .collect();
+                    Some((row_id, json.into()))
+                }
+                None => None,
+            },
+            Self::List(it) => match it.next_batch(expected_size).await? {
+                Some((row_id, array)) => {
+                    let lists = (array.iter())
+                        .map(|blob| {
+                            blob.map(|blob| {
+                                serde_json::from_slice::<crate::types::List>(blob.as_ref())
+                            })
+                            .transpose()
+                        })
+                        .collect::<Result<ListArray, _>>()?;
+                    Some((row_id, lists.into()))
+                }
+                None => None,
+            },
             Self::RowHandler(it) => Self::erase_concrete_type(it.next_batch(expected_size).await?),
+            Self::Default(it) => it.next_batch(expected_size),
         };
         Ok(result)
     }
 
+    /// Reads the next `selection.len()` rows, but only decodes the selected rows. Unselected rows
+    /// are skipped and filled with nulls, so blocks without any selected row are not read.
+    pub async fn next_batch_selected(
+        &mut self,
+        selection: &BitSlice,
+    ) -> StorageResult<Option<(u32, ArrayImpl)>> {
+        if selection.not_any() {
+            return self.next_batch(Some(selection.len())).await;
+        }
+        let start_row_id = self.fetch_current_row_id();
+        let mut builder: Option<ArrayBuilderImpl> = None;
+        // unselected rows before the first decoded array
+        let mut skipped = 0;
+        let mut pos = 0;
+        while pos < selection.len() {
+            let rest = &selection[pos..];
+            if rest[0] {
+                let cnt = rest.first_zero().unwrap_or(rest.len());
+                let Some((_, array)) = self.next_batch(Some(cnt)).await? else {
+                    return Ok(None);
+                };
+                let builder = builder.get_or_insert_with(|| {
+                    let mut builder = ArrayBuilderImpl::from_type_of_array(&array);
+                    builder.push_n(skipped, &DataValue::Null);
+                    builder
+                });
+                builder.append(&array);
+                pos += array.len();
+            } else {
+                let cnt = rest.first_one().unwrap_or(rest.len());
+                self.skip(cnt);
+                match &mut builder {
+                    Some(builder) => builder.push_n(cnt, &DataValue::Null),
+                    None => skipped += cnt,
+                }
+                pos += cnt;
+            }
+        }
+        Ok(Some((start_row_id, builder.unwrap().finish())))
+    }
+
     pub fn fetch_hint(&self) -> (usize, bool) {
         match self {
             Self::Int16(it) => it.fetch_hint(),
@@ -152,8 +250,9 @@ impl ColumnIteratorImpl {
             Self::Timestamp(it) => it.fetch_hint(),
             Self::TimestampTz(it) => it.fetch_hint(),
             Self::Interval(it) => it.fetch_hint(),
-            Self::Blob(it) => it.fetch_hint(),
+            Self::Blob(it) | Self::Json(it) | Self::List(it) => it.fetch_hint(),
             Self::RowHandler(it) => it.fetch_hint(),
+            Self::Default(it) => it.fetch_hint(),
         }
     }
 
@@ -170,8 +269,9 @@ impl ColumnIteratorImpl {
             Self::Timestamp(it) => it.fetch_current_row_id(),
             Self::TimestampTz(it) => it.fetch_current_row_id(),
             Self::Interval(it) => it.fetch_current_row_id(),
-            Self::Blob(it) => it.fetch_current_row_id(),
+            Self::Blob(it) | Self::Json(it) | Self::List(it) => it.fetch_current_row_id(),
             Self::RowHandler(it) => it.fetch_current_row_id(),
+            Self::Default(it) => it.fetch_current_row_id(),
         }
     }
 
@@ -188,8 +288,9 @@ impl ColumnIteratorImpl {
             Self::Timestamp(it) => it.skip(cnt),
             Self::TimestampTz(it) => it.skip(cnt),
             Self::Interval(it) => it.skip(cnt),
-            Self::Blob(it) => it.skip(cnt),
+            Self::Blob(it) | Self::Json(it) | Self::List(it) => it.skip(cnt),
             Self::RowHandler(it) => it.skip(cnt),
+            Self::Default(it) => it.skip(cnt),
         }
     }
 }
@@ -1,11 +1,13 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::collections::VecDeque;
+
 use futures::Future;
 use risinglight_proto::rowset::block_index::BlockType;
 use risinglight_proto::rowset::BlockIndex;
 
 use super::super::{Block, BlockIterator};
-use super::{Column, ColumnIterator, ColumnSeekPosition};
+use super::{Column, ColumnIterator, ColumnSeekPosition, PrefetchHandle};
 use crate::array::{Array, ArrayBuilder};
 use crate::storage::StorageResult;
 
@@ -63,6 +65,9 @@ pub struct ConcreteColumnIterator<A: Array, F: BlockIteratorFactory<A>> {
 
     /// Statistics which used for reporting.
     statistics: Statistics,
+
+    /// Blocks after the current block being fetched in background, in the order of block ids.
+    prefetching: VecDeque<(u32, PrefetchHandle)>,
 }
 
 impl<A: Array, F: BlockIteratorFactory<A>> ConcreteColumnIterator<A, F> {
@@ -71,7 +76,7 @@ impl<A: Array, F: BlockIteratorFactory<A>> ConcreteColumnIterator<A, F> {
             .index()
             .block_of_seek_position(ColumnSeekPosition::RowId(start_pos));
         let (header, block) = column.get_block(current_block_id).await?;
-        Ok(Self {
+        let mut iter = Self {
             block_iterator: factory.get_iterator_for(
                 header.block_type,
                 block,
@@ -88,7 +93,28 @@ impl<A: Array, F: BlockIteratorFactory<A>> ConcreteColumnIterator<A, F> {
                 next_batch_count: 0,
                 fetched_block_count: 1,
             },
-        })
+            prefetching: VecDeque::new(),
+        };
+        iter.prefetch();
+        Ok(iter)
+    }
+
+    /// Starts fetching the blocks after the current block in background, up to the prefetch
+    /// count of the column.
+    fn prefetch(&mut self) {
+        let n = self.column.prefetch_blocks() as u32;
+        if n == 0 {
+            return;
+        }
+        // cancel fetches of blocks skipped over
+        while (self.prefetching.front()).is_some_and(|(id, _)| *id <= self.current_block_id) {
+            self.prefetching.pop_front();
+        }
+        let start = (self.prefetching.back()).map_or(self.current_block_id + 1, |(id, _)| id + 1);
+        let end = (self.current_block_id + 1 + n).min(self.column.index().len() as u32);
+        for block_id in start..end {
+            (self.prefetching).push_back((block_id, self.column.prefetch(block_id)));
+        }
     }
 
     pub async fn next_batch_inner(
@@ -119,7 +145,8 @@ impl<A: Array, F: BlockIteratorFactory<A>> ConcreteColumnIterator<A, F> {
                 block,
                 self.column.index().index(self.current_block_id),
                 self.current_row_id as usize,
-            )
+            );
+            self.prefetch();
         }
 
         loop {
@@ -153,6 +180,7 @@ impl<A: Array, F: BlockIteratorFactory<A>> ConcreteColumnIterator<A, F> {
                 self.column.index().index(self.current_block_id),
                 self.current_row_id as usize,
             );
+            self.prefetch();
         }
 
         if total_cnt == 0 {
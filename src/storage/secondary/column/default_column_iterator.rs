@@ -0,0 +1,60 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::cmp::min;
+
+use crate::array::{ArrayBuilderImpl, ArrayImpl};
+use crate::types::{DataType, DataValue};
+
+/// Yields the default value of a column for every row of a rowset written before the column
+/// was added to the table.
+pub struct DefaultColumnIterator {
+    data_type: DataType,
+    value: DataValue,
+    row_count: usize,
+    current_row_id: usize,
+}
+
+impl DefaultColumnIterator {
+    pub fn new(data_type: DataType, value: DataValue, row_count: usize, first_row: usize) -> Self {
+        Self {
+            data_type,
+            value,
+            row_count,
+            current_row_id: first_row,
+        }
+    }
+
+    pub fn next_batch(&mut self, expected_size: Option<usize>) -> Option<(u32, ArrayImpl)> {
+        if self.current_row_id >= self.row_count {
+            return None;
+        }
+
+        let mut remaining_cnt = self.row_count - self.current_row_id;
+        if let Some(expected_size) = expected_size {
+            assert!(expected_size > 0);
+            remaining_cnt = min(remaining_cnt, expected_size);
+        }
+
+        let first_row_id = self.current_row_id as u32;
+
+        let mut builder = ArrayBuilderImpl::with_capacity(remaining_cnt, &self.data_type);
+        builder.push_n(remaining_cnt, &self.value);
+        let batch = builder.finish();
+
+        self.current_row_id += remaining_cnt;
+        Some((first_row_id, batch))
+    }
+
+    pub fn fetch_hint(&self) -> (usize, bool) {
+        let cnt = self.row_count - self.current_row_id;
+        (cnt, cnt == 0)
+    }
+
+    pub fn fetch_current_row_id(&self) -> u32 {
+        self.current_row_id as u32
+    }
+
+    pub fn skip(&mut self, cnt: usize) {
+        self.current_row_id += cnt
+    }
+}
@@ -13,8 +13,9 @@ use super::super::{
 use super::ColumnBuilder;
 use crate::array::Array;
 use crate::storage::secondary::block::{DictBlockBuilder, NullableBlockBuilder, RleBlockBuilder};
+use crate::storage::secondary::statistics::MinMaxBuilder;
 use crate::storage::secondary::EncodeType;
-use crate::types::{Date, Interval, Timestamp, TimestampTz, F64};
+use crate::types::{DataValue, Date, Interval, Timestamp, TimestampTz, F64};
 
 /// All supported block builders for primitive types.
 pub(super) enum BlockBuilderImpl<T: PrimitiveFixedWidthEncode> {
@@ -64,17 +65,22 @@ pub struct PrimitiveColumnBuilder<T: PrimitiveFixedWidthEncode> {
 
     /// First key
     first_key: Option<Vec<u8>>,
+
+    /// Minimum and maximum values of the current block
+    min_max: MinMaxBuilder,
 }
 
 impl<T: PrimitiveFixedWidthEncode> PrimitiveColumnBuilder<T> {
     pub fn new(nullable: bool, options: ColumnBuilderOptions) -> Self {
         Self {
             data: vec![],
-            block_index_builder: BlockIndexBuilder::new(options.clone()),
+            block_index_builder: BlockIndexBuilder::new(options.clone())
+                .with_encoding_version(T::VERSION),
             options,
             current_builder: None,
             nullable,
             first_key: None,
+            min_max: MinMaxBuilder::default(),
         }
     }
 
@@ -83,7 +89,7 @@ impl<T: PrimitiveFixedWidthEncode> PrimitiveColumnBuilder<T> {
             return;
         }
 
-        let (block_type, stats, mut block_data) = match self.current_builder.take().unwrap() {
+        let (block_type, mut stats, mut block_data) = match self.current_builder.take().unwrap() {
             BlockBuilderImpl::Plain(builder) => {
                 (BlockType::Plain, builder.get_statistics(), builder.finish())
             }
@@ -113,6 +119,7 @@ impl<T: PrimitiveFixedWidthEncode> PrimitiveColumnBuilder<T> {
                 builder.finish(),
             ),
         };
+        stats.extend(std::mem::take(&mut self.min_max).get_statistics());
 
         self.block_index_builder.finish_block(
             block_type,
@@ -153,9 +160,14 @@ pub fn append_one_by_one<'a, A: Array>(
     (cnt, false)
 }
 
-impl<T: PrimitiveFixedWidthEncode> ColumnBuilder<T::ArrayType> for PrimitiveColumnBuilder<T> {
+impl<T: PrimitiveFixedWidthEncode> ColumnBuilder<T::ArrayType> for PrimitiveColumnBuilder<T>
+where
+    for<'a> DataValue: From<Option<&'a T>>,
+{
     fn append(&mut self, array: &T::ArrayType) {
         let mut iter = array.iter().peekable();
+        // position of the next item to append in the array
+        let mut pos = 0;
         while iter.peek().is_some() {
             if self.current_builder.is_none() {
                 match (self.nullable, self.options.encode_type) {
@@ -244,6 +256,10 @@ impl<T: PrimitiveFixedWidthEncode> ColumnBuilder<T::ArrayType> for PrimitiveColu
             };
 
             self.block_index_builder.add_rows(row_count);
+            for idx in pos..pos + row_count {
+                self.min_max.add_item(DataValue::from(array.get(idx)));
+            }
+            pos += row_count;
 
             // finish the current block
             if should_finish {
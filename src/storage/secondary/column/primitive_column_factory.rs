@@ -121,19 +121,22 @@ impl<T: PrimitiveFixedWidthEncode> BlockIteratorFactory<T::ArrayType>
     ) -> Self::BlockIteratorImpl {
         let mut it = match block_type {
             BlockType::Plain => {
-                let it = PlainPrimitiveBlockIterator::new(block, index.row_count as usize);
+                let it = PlainPrimitiveBlockIterator::new(block, index.row_count as usize)
+                    .with_version(index.encoding_version);
                 PrimitiveBlockIteratorImpl::Plain(it)
             }
             BlockType::PlainNullable => {
                 let (inner_block, bitmap_block) = decode_nullable_block(block);
                 let inner_it =
-                    PlainPrimitiveBlockIterator::new(inner_block, index.row_count as usize);
+                    PlainPrimitiveBlockIterator::new(inner_block, index.row_count as usize)
+                        .with_version(index.encoding_version);
                 let it = NullableBlockIterator::new(inner_it, bitmap_block);
                 PrimitiveBlockIteratorImpl::PlainNullable(it)
             }
             BlockType::RunLength => {
                 let (rle_num, rle_data, block_data) = decode_rle_block(block);
-                let block_iter = PlainPrimitiveBlockIterator::<T>::new(block_data, rle_num);
+                let block_iter = PlainPrimitiveBlockIterator::<T>::new(block_data, rle_num)
+                    .with_version(index.encoding_version);
                 let it = RleBlockIterator::<T::ArrayType, PlainPrimitiveBlockIterator<T>>::new(
                     block_iter, rle_data, rle_num,
                 );
@@ -143,7 +146,8 @@ impl<T: PrimitiveFixedWidthEncode> BlockIteratorFactory<T::ArrayType>
                 let (rle_num, rle_data, block_data) = decode_rle_block(block);
                 let (inner_block, bitmap_block) = decode_nullable_block(block_data);
                 let inner_it =
-                    PlainPrimitiveBlockIterator::<T>::new(inner_block, index.row_count as usize);
+                    PlainPrimitiveBlockIterator::<T>::new(inner_block, index.row_count as usize)
+                        .with_version(index.encoding_version);
                 let block_iter = NullableBlockIterator::new(inner_it, bitmap_block);
                 let it = RleBlockIterator::<
                     T::ArrayType,
@@ -156,7 +160,8 @@ impl<T: PrimitiveFixedWidthEncode> BlockIteratorFactory<T::ArrayType>
 
                 let mut dict_builder = <<T::ArrayType as Array>::Builder as ArrayBuilder>::new();
                 let mut dict_values_iter =
-                    PlainPrimitiveBlockIterator::<T>::new(dict_values, dict_size);
+                    PlainPrimitiveBlockIterator::<T>::new(dict_values, dict_size)
+                        .with_version(index.encoding_version);
 
                 let iter = DictBlockIterator::new(
                     &mut dict_builder,
@@ -173,7 +178,8 @@ impl<T: PrimitiveFixedWidthEncode> BlockIteratorFactory<T::ArrayType>
 
                 let (dict_values_inner, bitmap_block) = decode_nullable_block(dict_values);
                 let dict_values_inner_iter =
-                    PlainPrimitiveBlockIterator::<T>::new(dict_values_inner, dict_size);
+                    PlainPrimitiveBlockIterator::<T>::new(dict_values_inner, dict_size)
+                        .with_version(index.encoding_version);
                 let mut dict_values_iter =
                     NullableBlockIterator::new(dict_values_inner_iter, bitmap_block);
 
@@ -1,25 +1,34 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use itertools::Itertools;
 use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
 use tokio::sync::oneshot::Receiver;
 use tracing::{info, warn};
 
-use super::{SecondaryStorage, SecondaryTable, Snapshot};
-use crate::catalog::find_sort_key_id;
+use super::{CompactionStrategy, SecondaryStorage, SecondaryTable, Snapshot};
+use crate::catalog::{find_sort_key_id, TableRefId};
 use crate::storage::secondary::column::ColumnSeekPosition;
 use crate::storage::secondary::concat_iterator::ConcatIterator;
-use crate::storage::secondary::manifest::{AddRowSetEntry, DeleteRowsetEntry};
+use crate::storage::secondary::manifest::{
+    AddDVEntry, AddRowSetEntry, DeleteDVEntry, DeleteRowsetEntry,
+};
 use crate::storage::secondary::merge_iterator::MergeIterator;
 use crate::storage::secondary::rowset::{DiskRowset, RowsetBuilder, RowsetWriter};
 use crate::storage::secondary::statistics::create_statistics_global_aggregator;
 use crate::storage::secondary::version_manager::EpochOp;
-use crate::storage::secondary::{ColumnBuilderOptions, EncodeType, SecondaryIterator};
+use crate::storage::secondary::{
+    ColumnBuilderOptions, DeleteVector, EncodeType, SecondaryIterator,
+};
 use crate::storage::{StorageColumnRef, StorageResult};
-use crate::types::DataValue;
+use crate::types::{DataType, DataValue};
+use crate::utils::metrics;
+
+/// RowSets with more DVs than this have their DVs merged into one by compactions, so that scans
+/// apply fewer DVs.
+const MAX_DVS_PER_ROWSET: usize = 4;
 
 /// Manages all compactions happening in the storage engine.
 pub struct Compactor {
@@ -27,35 +36,66 @@ pub struct Compactor {
     stop: Receiver<()>,
 }
 
-impl Compactor {
-    pub fn new(storage: Arc<SecondaryStorage>, stop: Receiver<()>) -> Self {
-        Self { storage, stop }
-    }
+/// Statistics of compactions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CompactionStats {
+    /// Number of RowSets merged.
+    pub rowsets_merged: usize,
+    /// Size of the merged RowSets minus the size of the new one.
+    pub bytes_reclaimed: u64,
+}
 
-    async fn compact_table(&self, snapshot: &Snapshot, table: SecondaryTable) -> StorageResult<()> {
+impl SecondaryStorage {
+    /// Merge RowSets of a table and remove deleted rows.
+    ///
+    /// If `full` is false, RowSets are selected by the compaction strategy. Otherwise, all RowSets
+    /// are merged into one, and a single RowSet is rewritten if it has deleted rows.
+    async fn compact_table(
+        &self,
+        snapshot: &Snapshot,
+        table: SecondaryTable,
+        full: bool,
+    ) -> StorageResult<CompactionStats> {
         let rowsets = if let Some(rowsets) = snapshot.get_rowsets_of(table.table_id()) {
             rowsets
         } else {
             // No rowset available for this table
-            return Ok(());
+            return Ok(CompactionStats::default());
         };
         let mut selected_rowsets = vec![];
-        let mut current_size = 0;
         for rowset_id in rowsets {
-            let rowset = self
-                .storage
-                .version
-                .get_rowset(table.table_id(), *rowset_id);
-            let on_disk_size = rowset.on_disk_size();
-            if on_disk_size + current_size <= self.storage.options.target_rowset_size as u64 {
-                current_size += on_disk_size;
-                selected_rowsets.push(rowset);
+            // rowsets in the write-ahead log are written to disk by checkpoints
+            if (self.wal.as_ref())
+                .is_some_and(|wal| wal.is_unpersisted_rowset(table.table_id(), *rowset_id))
+            {
+                continue;
             }
+            selected_rowsets.push(self.version.get_rowset(table.table_id(), *rowset_id));
         }
-        if selected_rowsets.len() <= 1 {
-            return Ok(());
+        if !full {
+            let sizes = (selected_rowsets.iter())
+                .map(|rowset| (rowset.rowset_id(), rowset.on_disk_size()))
+                .collect_vec();
+            let picked = pick_rowsets(
+                self.options.compaction_strategy,
+                &sizes,
+                self.options.target_rowset_size as u64,
+            );
+            selected_rowsets.retain(|rowset| picked.contains(&rowset.rowset_id()));
+        }
+        let current_size: u64 = selected_rowsets.iter().map(|x| x.on_disk_size()).sum();
+        let has_deletes = |rowset: &Arc<DiskRowset>| {
+            (snapshot.get_dvs_of(table.table_id(), rowset.rowset_id()))
+                .is_some_and(|dvs| !dvs.is_empty())
+        };
+        match selected_rowsets.as_slice() {
+            [] => return Ok(CompactionStats::default()),
+            [rowset] if !full || !has_deletes(rowset) => return Ok(CompactionStats::default()),
+            _ => {}
         }
 
+        let start = Instant::now();
+
         // sort RowSets by id so that the output RowSet will have old rows in the front and new rows
         // at the end.
         selected_rowsets.sort_by_key(|x| x.rowset_id());
@@ -70,14 +110,23 @@ impl Compactor {
                 .get_dvs_of(table.table_id(), rowset.rowset_id())
                 .map(|dvs| {
                     dvs.iter()
-                        .map(|dv_id| self.storage.version.get_dv(table.table_id(), *dv_id))
+                        .map(|dv_id| self.version.get_dv(table.table_id(), *dv_id))
                         .collect_vec()
                 })
                 .unwrap_or_default();
 
             iters.push(
                 rowset
-                    .iter(column_refs.clone(), dvs, ColumnSeekPosition::start(), None)
+                    .iter_with_schema(
+                        &table.columns,
+                        column_refs.clone(),
+                        dvs,
+                        ColumnSeekPosition::start(),
+                        None,
+                        &[],
+                        0,
+                        None,
+                    )
                     .await?,
             );
         }
@@ -93,44 +142,39 @@ impl Compactor {
             ConcatIterator::new(iters).into()
         };
 
-        let mut distinct_value = 0;
-        let mut row_count = 0;
-
-        {
-            let mut distinct_value_aggregator =
-                create_statistics_global_aggregator(BlockStatisticsType::DistinctValue);
-            let mut row_count_aggregator =
-                create_statistics_global_aggregator(BlockStatisticsType::RowCount);
-
-            for rowset in &selected_rowsets {
-                let columns = rowset.get_columns();
-                let column_indexs = columns.iter().map(|column| column.index()).collect_vec();
-                for column_index in column_indexs {
-                    row_count_aggregator.apply_batch(column_index);
-                    distinct_value_aggregator.apply_batch(column_index);
-                }
-            }
-
-            if let DataValue::Int64(dv) = distinct_value_aggregator.get_output() {
-                distinct_value = dv;
-            }
-
-            if let DataValue::Int64(rc) = row_count_aggregator.get_output() {
-                row_count = rc;
-            }
-        }
-
-        let mut builder = if distinct_value < row_count / 5 {
-            let mut column_options =
-                ColumnBuilderOptions::from_storage_options(&table.storage_options);
-            column_options.encode_type = EncodeType::Dictionary;
-            RowsetBuilder::new(table.columns.clone(), column_options)
-        } else {
-            RowsetBuilder::new(
-                table.columns.clone(),
-                ColumnBuilderOptions::from_storage_options(&table.storage_options),
-            )
-        };
+        let column_options = ColumnBuilderOptions::from_storage_options(&table.storage_options);
+        let encode_types = (table.columns.iter())
+            .map(|column| {
+                let indexes = (selected_rowsets.iter())
+                    .filter_map(|rowset| {
+                        let idx = rowset.storage_column_id(column.id())?;
+                        Some(rowset.get_columns()[idx].index())
+                    })
+                    .collect_vec();
+                let aggregate = |ty| {
+                    let mut aggregator = create_statistics_global_aggregator(ty);
+                    for index in &indexes {
+                        aggregator.apply_batch(index);
+                    }
+                    match aggregator.get_output() {
+                        DataValue::Int64(v) => v as u64,
+                        _ => 0,
+                    }
+                };
+                choose_encode_type(
+                    &column.data_type(),
+                    aggregate(BlockStatisticsType::RowCount),
+                    aggregate(BlockStatisticsType::DistinctValue),
+                    aggregate(BlockStatisticsType::RunCount),
+                )
+                .unwrap_or(column_options.encode_type)
+            })
+            .collect_vec();
+        let mut builder = RowsetBuilder::new_with_encode_types(
+            table.columns.clone(),
+            column_options,
+            &encode_types,
+        );
 
         while let Some(batch) = iter.next_batch(None).await? {
             builder.append(batch.to_data_chunk());
@@ -139,6 +183,7 @@ impl Compactor {
         let rowset = builder.finish();
 
         let mut changes: Vec<EpochOp> = vec![];
+        let mut new_size = 0;
 
         // If the row sets are not empty, add a new rowset.
         let rowset_id: Option<u32> = if rowset.is_empty() {
@@ -150,24 +195,26 @@ impl Compactor {
             let rowset_id = rowset_id.unwrap();
             let directory = table.get_rowset_path(rowset_id);
 
-            let writer = RowsetWriter::new(&directory, self.storage.options.io_backend.clone());
+            let writer = RowsetWriter::new(&directory, self.options.io_backend.clone());
             writer.create_dir().await?;
             writer.flush(rowset).await?;
 
             let rowset = DiskRowset::open(
                 directory,
                 table.columns.clone(),
-                self.storage.block_cache.clone(),
+                self.block_cache.clone(),
                 rowset_id,
-                self.storage.options.io_backend.clone(),
+                self.options.io_backend.clone(),
             )
             .await?;
+            new_size = rowset.on_disk_size();
 
             // Add RowSets
             let add_rowset_op = EpochOp::AddRowSet((
                 AddRowSetEntry {
                     rowset_id: rowset.rowset_id(),
                     table_id: table.table_ref_id,
+                    columns: rowset.column_infos().to_vec(),
                 },
                 rowset,
             ));
@@ -175,16 +222,29 @@ impl Compactor {
             changes.push(add_rowset_op);
         }
 
-        // Remove old RowSets
-        // and TODO: remove old DVs
-        changes.extend(selected_rowsets.iter().map(|x| {
-            EpochOp::DeleteRowSet(DeleteRowsetEntry {
-                rowset_id: x.rowset_id(),
+        // Remove old RowSets and their DVs, as deleted rows are not in the new RowSet.
+        for rowset in &selected_rowsets {
+            changes.push(EpochOp::DeleteRowSet(DeleteRowsetEntry {
+                rowset_id: rowset.rowset_id(),
                 table_id: table.table_ref_id,
-            })
-        }));
+            }));
+            if let Some(dvs) = snapshot.get_dvs_of(table.table_id(), rowset.rowset_id()) {
+                changes.extend(dvs.iter().map(|dv_id| {
+                    EpochOp::DeleteDV(DeleteDVEntry {
+                        table_id: table.table_ref_id,
+                        dv_id: *dv_id,
+                        rowset_id: rowset.rowset_id(),
+                    })
+                }));
+            }
+        }
 
-        self.storage.version.commit_changes(changes).await?;
+        // rows of the old RowSets are moved to the new one
+        let indexes = table.indexes.read().clone();
+        indexes.iter().for_each(|index| index.begin_move());
+        let result = self.version.commit_changes(changes).await;
+        indexes.iter().for_each(|index| index.end_move());
+        result?;
 
         match rowset_id {
             Some(rowset_id) => {
@@ -202,23 +262,257 @@ impl Compactor {
             }
         }
 
+        metrics::COMPACTIONS.inc();
+        metrics::COMPACTED_ROWSETS.inc_by(selected_rowsets.len() as u64);
+        metrics::COMPACTION_DURATION.observe(start.elapsed());
+        Ok(CompactionStats {
+            rowsets_merged: selected_rowsets.len(),
+            bytes_reclaimed: current_size.saturating_sub(new_size),
+        })
+    }
+
+    /// Merge DVs of RowSets having too many DVs, without rewriting the RowSets.
+    ///
+    /// Returns the number of RowSets whose DVs are merged.
+    async fn merge_dvs(&self, snapshot: &Snapshot, table: &SecondaryTable) -> StorageResult<usize> {
+        let Some(rowsets) = snapshot.get_rowsets_of(table.table_id()) else {
+            return Ok(0);
+        };
+        let mut changes = vec![];
+        let mut merged = 0;
+        for &rowset_id in rowsets {
+            let Some(dv_ids) = snapshot.get_dvs_of(table.table_id(), rowset_id) else {
+                continue;
+            };
+            // DVs in the write-ahead log are merged after written to disk by checkpoints
+            if dv_ids.len() <= MAX_DVS_PER_ROWSET
+                || (self.wal.as_ref()).is_some_and(|wal| {
+                    (dv_ids.iter()).any(|dv_id| wal.is_unpersisted_dv(table.table_id(), *dv_id))
+                })
+            {
+                continue;
+            }
+            let dvs = (dv_ids.iter())
+                .map(|dv_id| self.version.get_dv(table.table_id(), *dv_id))
+                .collect_vec();
+            let dv = DeleteVector::merge(table.generate_dv_id(), &dvs);
+            let path = table.get_dv_path(rowset_id, dv.dv_id());
+            DeleteVector::write_file(path, &self.options.io_backend, &dv.records()).await?;
+
+            let entry = AddDVEntry {
+                table_id: table.table_ref_id,
+                dv_id: dv.dv_id(),
+                rowset_id,
+            };
+            changes.push(EpochOp::AddDV((entry, dv)));
+            changes.extend(dv_ids.iter().map(|dv_id| {
+                EpochOp::DeleteDV(DeleteDVEntry {
+                    table_id: table.table_ref_id,
+                    dv_id: *dv_id,
+                    rowset_id,
+                })
+            }));
+            merged += 1;
+        }
+        if merged > 0 {
+            self.version.commit_changes(changes).await?;
+            info!("DVs of {} RowSets merged", merged);
+        }
+        Ok(merged)
+    }
+
+    /// Write the write-ahead log to disk, and merge all RowSets of a table, or of all tables if
+    /// `table_id` is `None`. Space of deleted rows is reclaimed.
+    pub async fn vacuum(&self, table_id: Option<TableRefId>) -> StorageResult<CompactionStats> {
+        self.checkpoint_inner().await?;
+
+        let tables = self.tables.read().clone();
+        let mut stats = CompactionStats::default();
+        for (id, table) in tables {
+            if table_id.is_some_and(|table_id| table_id != id) {
+                continue;
+            }
+            // wait for ongoing deletions and compactions of the table
+            let _guard = self.txn_mgr.lock_for_deletion(id.table_id).await;
+            let pin_version = self.version.pin();
+            let table_stats = self
+                .compact_table(&pin_version.snapshot, table, true)
+                .await?;
+            stats.rowsets_merged += table_stats.rowsets_merged;
+            stats.bytes_reclaimed += table_stats.bytes_reclaimed;
+        }
+
+        // remove files of merged RowSets if they are not used by others
+        self.version.do_vacuum().await?;
+        Ok(stats)
+    }
+}
+
+/// Choose the encoding with the smallest estimated size, from the statistics of a column: the
+/// number of rows, and the total number of distinct values and runs in blocks.
+///
+/// Returns `None` if the statistics are not available.
+fn choose_encode_type(ty: &DataType, rows: u64, distinct: u64, runs: u64) -> Option<EncodeType> {
+    if runs == 0 || distinct == 0 {
+        return None;
+    }
+    // variable-length values are assumed to be 16 bytes
+    let width = match ty {
+        DataType::Bool => 1,
+        DataType::Int16 => 2,
+        DataType::Int32 | DataType::Date => 4,
+        DataType::Int64 | DataType::Float64 | DataType::Timestamp | DataType::TimestampTz => 8,
+        _ => 16,
+    };
+    // a run length takes about 2 bytes, and a dictionary key takes 4 bytes
+    let plain = rows * width;
+    let run_length = runs * (width + 2);
+    let dictionary = distinct * width + runs * (4 + 2);
+    Some(if run_length <= plain && run_length <= dictionary {
+        EncodeType::RunLength
+    } else if dictionary <= plain {
+        EncodeType::Dictionary
+    } else {
+        EncodeType::Plain
+    })
+}
+
+/// Pick RowSets to merge by the strategy, from the ids and sizes of RowSets of a table.
+///
+/// Returns an empty vector if there is nothing to merge.
+fn pick_rowsets(strategy: CompactionStrategy, rowsets: &[(u32, u64)], max_size: u64) -> Vec<u32> {
+    let mut rowsets = (rowsets.iter())
+        .filter(|(_, size)| *size < max_size)
+        .copied()
+        .collect_vec();
+    rowsets.sort_by_key(|&(id, size)| (size, id));
+
+    let picked = match strategy {
+        CompactionStrategy::SizeTiered {
+            min_rowsets,
+            size_ratio,
+        } => {
+            let mut picked: &[(u32, u64)] = &[];
+            let mut start = 0;
+            for end in 1..=rowsets.len() {
+                let max_tier_size = rowsets[start].1.max(1).saturating_mul(size_ratio);
+                if end < rowsets.len() && rowsets[end].1 <= max_tier_size {
+                    continue;
+                }
+                if end - start >= min_rowsets {
+                    picked = &rowsets[start..end];
+                    break;
+                }
+                start = end;
+            }
+            picked
+        }
+        CompactionStrategy::Leveled {
+            base_size,
+            multiplier,
+            level0_max_rowsets,
+        } => {
+            // capacities of levels must grow, or RowSets larger than the base fit in no level
+            let base_size = base_size.max(1);
+            let multiplier = multiplier.max(2);
+            let level_of = |size: u64| {
+                let mut level = 0;
+                let mut capacity = base_size;
+                while size > capacity {
+                    level += 1;
+                    capacity = capacity.saturating_mul(multiplier);
+                }
+                level
+            };
+            // levels are non-decreasing as RowSets are sorted by size
+            let levels = rowsets
+                .iter()
+                .map(|(_, size)| level_of(*size))
+                .collect_vec();
+            let end_of_level = |level| levels.partition_point(|l| *l <= level);
+
+            let mut picked: &[(u32, u64)] = &[];
+            let mut capacity = base_size;
+            for level in 0..=levels.last().copied().unwrap_or(0) {
+                let end = end_of_level(level);
+                if level == 0 && end >= level0_max_rowsets.max(2) {
+                    picked = &rowsets[..end];
+                    break;
+                }
+                let total_size: u64 = rowsets[..end].iter().map(|(_, size)| size).sum();
+                if total_size > capacity {
+                    picked = &rowsets[..end_of_level(level + 1)];
+                    break;
+                }
+                capacity = capacity.saturating_mul(multiplier);
+            }
+            picked
+        }
+    };
+
+    // merge the smallest RowSets if the output is too large
+    let mut total_size = 0;
+    let picked = (picked.iter())
+        .take_while(|(_, size)| {
+            total_size += size;
+            total_size <= max_size
+        })
+        .map(|(id, _)| *id)
+        .collect_vec();
+    if picked.len() < 2 {
+        return vec![];
+    }
+    picked
+}
+
+impl Compactor {
+    pub fn new(storage: Arc<SecondaryStorage>, stop: Receiver<()>) -> Self {
+        Self { storage, stop }
+    }
+
+    /// Syncs the write-ahead log by its policy, and takes a checkpoint if it is too large.
+    async fn maintain_wal(&self) -> StorageResult<()> {
+        let Some(wal) = &self.storage.wal else {
+            return Ok(());
+        };
+        wal.sync().await?;
+        if wal.needs_checkpoint().await {
+            self.storage.checkpoint_inner().await?;
+        }
         Ok(())
     }
 
     pub async fn run(mut self) -> StorageResult<()> {
         loop {
             {
+                if let Err(err) = self.maintain_wal().await {
+                    warn!("failed to checkpoint: {:?}", err);
+                }
                 let tables = self.storage.tables.read().clone();
-                let pin_version = self.storage.version.pin();
                 for (_, table) in tables {
                     if let Some(_guard) = self
                         .storage
                         .txn_mgr
                         .try_lock_for_compaction(table.table_id())
                     {
-                        if let Err(err) = self.compact_table(&pin_version.snapshot, table).await {
+                        // Pin the version after taking the lock, so that the snapshot contains
+                        // all committed deletions of the table.
+                        let pin_version = self.storage.version.pin();
+                        if let Err(err) = self
+                            .storage
+                            .compact_table(&pin_version.snapshot, table.clone(), false)
+                            .await
+                        {
                             warn!("failed to compact: {:?}", err);
                         }
+                        drop(pin_version);
+                        // RowSets merged above have no DVs in the new version
+                        let pin_version = self.storage.version.pin();
+                        if let Err(err) =
+                            self.storage.merge_dvs(&pin_version.snapshot, &table).await
+                        {
+                            warn!("failed to merge DVs: {:?}", err);
+                        }
                     }
                 }
                 match self.stop.try_recv() {
@@ -233,3 +527,66 @@ impl Compactor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_encode_type() {
+        let int = |rows, distinct, runs| choose_encode_type(&DataType::Int32, rows, distinct, runs);
+        let string =
+            |rows, distinct, runs| choose_encode_type(&DataType::String, rows, distinct, runs);
+        // sorted column with a few values
+        assert!(matches!(int(1000, 10, 10), Some(EncodeType::RunLength)));
+        // unsorted column with a few values
+        assert!(matches!(
+            string(1000, 10, 800),
+            Some(EncodeType::Dictionary)
+        ));
+        // keys of a dictionary are as large as integers
+        assert!(matches!(int(1000, 10, 800), Some(EncodeType::Plain)));
+        // unique values
+        assert!(matches!(int(1000, 1000, 1000), Some(EncodeType::Plain)));
+        // no statistics
+        assert!(int(1000, 0, 0).is_none());
+    }
+
+    #[test]
+    fn test_pick_size_tiered() {
+        let strategy = CompactionStrategy::SizeTiered {
+            min_rowsets: 3,
+            size_ratio: 4,
+        };
+        // tiers: [1, 2, 3, 4], [10, 15]
+        let rowsets = [(1, 10), (2, 1), (3, 15), (4, 2), (5, 3), (6, 4)];
+        assert_eq!(pick_rowsets(strategy, &rowsets, 100), vec![2, 4, 5, 6]);
+        // tiers: [1, 2], [10, 15], [100]
+        let rowsets = [(1, 10), (2, 1), (3, 15), (4, 2), (5, 100)];
+        assert_eq!(pick_rowsets(strategy, &rowsets, 1000), Vec::<u32>::new());
+        // the output is limited by the max size
+        let rowsets = [(1, 10), (2, 11), (3, 12), (4, 13)];
+        assert_eq!(pick_rowsets(strategy, &rowsets, 40), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_pick_leveled() {
+        let strategy = CompactionStrategy::Leveled {
+            base_size: 10,
+            multiplier: 10,
+            level0_max_rowsets: 3,
+        };
+        // level 0: [4, 5], level 1: [50]
+        let rowsets = [(1, 50), (2, 4), (3, 5)];
+        assert_eq!(pick_rowsets(strategy, &rowsets, 1000), Vec::<u32>::new());
+        // level 0 exceeds its capacity, and is merged with level 1
+        let rowsets = [(1, 50), (2, 4), (3, 7)];
+        assert_eq!(pick_rowsets(strategy, &rowsets, 1000), vec![2, 3, 1]);
+        // level 0 has too many RowSets
+        let rowsets = [(1, 50), (2, 1), (3, 1), (4, 1)];
+        assert_eq!(pick_rowsets(strategy, &rowsets, 1000), vec![2, 3, 4]);
+        // levels 0 and 1 exceed the capacity of level 1, and are merged with level 2
+        let rowsets = [(1, 60), (2, 45), (3, 200), (4, 2000)];
+        assert_eq!(pick_rowsets(strategy, &rowsets, 1000), vec![2, 1, 3]);
+    }
+}
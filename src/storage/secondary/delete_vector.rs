@@ -1,6 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use bitvec::prelude::BitVec;
 use futures::pin_mut;
@@ -9,6 +10,7 @@ use prost::Message;
 use risinglight_proto::rowset::DeleteRecord;
 use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
 
+use super::IOBackend;
 use crate::storage::StorageResult;
 
 pub struct DeleteVector {
@@ -25,7 +27,12 @@ impl DeleteVector {
         // TODO: don't read all to memory
         reader.read_to_end(&mut data).await?;
 
-        let mut buf = &data[..];
+        Self::from_bytes(dv_id, rowset_id, &data)
+    }
+
+    /// Decode a DV from the content written by [`DeleteVector::write_all`].
+    pub fn from_bytes(dv_id: u64, rowset_id: u32, data: &[u8]) -> StorageResult<Self> {
+        let mut buf = data;
         let mut deletes = vec![];
 
         while !buf.is_empty() {
@@ -55,6 +62,31 @@ impl DeleteVector {
         Ok(())
     }
 
+    /// Write a DV file to the IO backend.
+    pub async fn write_file(
+        path: PathBuf,
+        io_backend: &IOBackend,
+        deletes: &[DeleteRecord],
+    ) -> StorageResult<()> {
+        match io_backend {
+            IOBackend::InMemory(map) => {
+                let mut buf = vec![];
+                Self::write_all(&mut buf, deletes).await?;
+                map.lock().insert(path, buf.into());
+            }
+            _ => {
+                let mut file = tokio::fs::OpenOptions::default()
+                    .write(true)
+                    .create_new(true)
+                    .open(path)
+                    .await?;
+                Self::write_all(&mut file, deletes).await?;
+                file.sync_data().await?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn new(dv_id: u64, rowset_id: u32, deletes: Vec<DeleteRecord>) -> Self {
         let mut deletes = deletes.into_iter().map(|x| x.row_id).collect_vec();
         deletes.sort_unstable();
@@ -67,6 +99,30 @@ impl DeleteVector {
         }
     }
 
+    /// Merge DVs of a RowSet into one with the given id.
+    pub fn merge(dv_id: u64, dvs: &[Arc<DeleteVector>]) -> Self {
+        let rowset_id = dvs.first().map_or(0, |dv| dv.rowset_id);
+        let mut deletes = dvs
+            .iter()
+            .flat_map(|dv| dv.deletes.iter().copied())
+            .collect_vec();
+        deletes.sort_unstable();
+        deletes.dedup();
+
+        Self {
+            dv_id,
+            rowset_id,
+            deletes,
+        }
+    }
+
+    /// Returns the deleted rows as records to be written.
+    pub fn records(&self) -> Vec<DeleteRecord> {
+        (self.deletes.iter())
+            .map(|row_id| DeleteRecord { row_id: *row_id })
+            .collect()
+    }
+
     pub fn dv_id(&self) -> u64 {
         self.dv_id
     }
@@ -125,4 +181,22 @@ mod tests {
         dv.apply_to(&mut bv, 4);
         assert_eq!(bv, bitvec![1, 0, 1]);
     }
+
+    #[test]
+    fn test_dv_merge() {
+        let dv1 = DeleteVector::new(
+            0,
+            1,
+            vec![DeleteRecord { row_id: 5 }, DeleteRecord { row_id: 1 }],
+        );
+        let dv2 = DeleteVector::new(
+            1,
+            1,
+            vec![DeleteRecord { row_id: 3 }, DeleteRecord { row_id: 5 }],
+        );
+        let dv = DeleteVector::merge(2, &[Arc::new(dv1), Arc::new(dv2)]);
+        assert_eq!((dv.dv_id(), dv.rowset_id()), (2, 1));
+        assert_eq!(dv.deletes, vec![1, 3, 5]);
+        assert_eq!(dv.records()[1], DeleteRecord { row_id: 3 });
+    }
 }
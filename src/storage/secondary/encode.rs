@@ -17,6 +17,10 @@ pub trait PrimitiveFixedWidthEncode:
     const WIDTH: usize;
     const DEFAULT_VALUE: &'static Self;
 
+    /// Version of the encoding, which is recorded in the block index. Bump it when the layout
+    /// changes, and keep decoding the old versions in [`decode_version`](Self::decode_version).
+    const VERSION: u32 = 0;
+
     type ArrayType: Array<Item = Self>;
 
     /// Encode current primitive data to the end of an `Vec<u8>`.
@@ -24,6 +28,16 @@ pub trait PrimitiveFixedWidthEncode:
 
     /// Decode a data from a bytes array.
     fn decode(buffer: &mut impl Buf) -> Self;
+
+    /// Width of each element encoded in the given version.
+    fn width(_version: u32) -> usize {
+        Self::WIDTH
+    }
+
+    /// Decode a data encoded in the given version from a bytes array.
+    fn decode_version(buffer: &mut impl Buf, _version: u32) -> Self {
+        Self::decode(buffer)
+    }
 }
 
 impl PrimitiveFixedWidthEncode for bool {
@@ -160,20 +174,42 @@ impl PrimitiveFixedWidthEncode for TimestampTz {
 }
 
 impl PrimitiveFixedWidthEncode for Interval {
-    const WIDTH: usize = std::mem::size_of::<i32>() + std::mem::size_of::<i32>();
+    const WIDTH: usize = std::mem::size_of::<i32>() * 3;
     const DEFAULT_VALUE: &'static Self = &Interval::from_days(0);
+    /// Version 0 only encodes months and days.
+    const VERSION: u32 = 1;
 
     type ArrayType = IntervalArray;
 
     fn encode(&self, buffer: &mut impl BufMut) {
         buffer.put_i32(self.num_months());
         buffer.put_i32(self.days());
+        buffer.put_i32(self.num_milliseconds());
     }
 
     fn decode(buffer: &mut impl Buf) -> Self {
         let months = buffer.get_i32();
         let days = buffer.get_i32();
-        Interval::from_md(months, days)
+        let ms = buffer.get_i32();
+        Interval::new(months, days, ms)
+    }
+
+    fn width(version: u32) -> usize {
+        match version {
+            0 => std::mem::size_of::<i32>() * 2,
+            _ => Self::WIDTH,
+        }
+    }
+
+    fn decode_version(buffer: &mut impl Buf, version: u32) -> Self {
+        match version {
+            0 => {
+                let months = buffer.get_i32();
+                let days = buffer.get_i32();
+                Interval::from_md(months, days)
+            }
+            _ => Self::decode(buffer),
+        }
     }
 }
 
@@ -7,13 +7,19 @@ use prost::Message;
 use risinglight_proto::rowset::block_checksum::ChecksumType;
 use risinglight_proto::rowset::BlockIndex;
 
+use super::statistics::decode_min_max;
 use super::{ColumnSeekPosition, SECONDARY_INDEX_MAGIC};
 use crate::storage::secondary::{verify_checksum, INDEX_FOOTER_SIZE};
 use crate::storage::{StorageResult, TracedStorageError};
+use crate::types::DataValue;
 
 #[derive(Clone)]
 pub struct ColumnIndex {
     indexes: Arc<[BlockIndex]>,
+    /// Minimum and maximum values of each block, decoded from the statistics.
+    zone_maps: Arc<[Option<(DataValue, DataValue)>]>,
+    /// Minimum and maximum values of the column, if they are recorded for all blocks.
+    min_max: Option<(DataValue, DataValue)>,
 }
 
 impl ColumnIndex {
@@ -29,6 +35,16 @@ impl ColumnIndex {
         self.indexes.len()
     }
 
+    /// Get the minimum and maximum values of a block, if they are recorded.
+    pub fn zone_map(&self, block_id: u32) -> Option<&(DataValue, DataValue)> {
+        self.zone_maps[block_id as usize].as_ref()
+    }
+
+    /// Get the minimum and maximum values of the column, if they are recorded for all blocks.
+    pub fn min_max(&self) -> Option<&(DataValue, DataValue)> {
+        self.min_max.as_ref()
+    }
+
     pub fn from_bytes(data: &[u8]) -> StorageResult<Self> {
         // TODO(chi): error handling
         let mut index_data = &data[..data.len() - INDEX_FOOTER_SIZE];
@@ -50,8 +66,22 @@ impl ColumnIndex {
             indexes.push(index);
         }
 
+        let zone_maps = indexes.iter().map(decode_min_max).collect::<Vec<_>>();
+        let mut min_max: Option<(DataValue, DataValue)> = None;
+        for zone_map in &zone_maps {
+            let Some((min, max)) = zone_map.clone() else {
+                min_max = None;
+                break;
+            };
+            min_max = Some(match min_max {
+                Some((acc_min, acc_max)) => (acc_min.min(min), acc_max.max(max)),
+                None => (min, max),
+            });
+        }
         Ok(Self {
             indexes: indexes.into(),
+            zone_maps: zone_maps.into(),
+            min_max,
         })
     }
 
@@ -9,7 +9,9 @@
 
 use std::io::SeekFrom;
 use std::path::Path;
+use std::sync::Arc;
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_json::Deserializer;
 use tokio::fs::OpenOptions;
@@ -18,7 +20,12 @@ use tracing::warn;
 
 use super::version_manager::EpochOp;
 use super::{SecondaryStorage, SecondaryTable, StorageResult, TracedStorageError};
-use crate::catalog::{ColumnCatalog, ColumnId, SchemaId, TableRefId};
+use crate::binder::Binder;
+use crate::catalog::{
+    ColumnCatalog, ColumnId, Constraints, ForeignKey, IndexId, SchemaId, TableRefId, ViewDefinition,
+};
+use crate::planner::RecExpr;
+use crate::storage::TableIndex;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CreateTableEntry {
@@ -26,6 +33,12 @@ pub struct CreateTableEntry {
     pub table_name: String,
     pub column_descs: Vec<ColumnCatalog>,
     pub ordered_pk_ids: Vec<ColumnId>,
+    #[serde(default)]
+    pub unique_keys: Vec<Vec<ColumnId>>,
+    #[serde(default)]
+    pub checks: Vec<String>,
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKey>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -33,10 +46,64 @@ pub struct DropTableEntry {
     pub table_id: TableRefId,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AddColumnEntry {
+    pub table_id: TableRefId,
+    pub column: ColumnCatalog,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DropColumnEntry {
+    pub table_id: TableRefId,
+    pub column_id: ColumnId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateIndexEntry {
+    pub index_name: String,
+    pub table_id: TableRefId,
+    pub column_ids: Vec<ColumnId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DropIndexEntry {
+    pub schema_id: SchemaId,
+    pub index_id: IndexId,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateSchemaEntry {
+    pub schema_name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DropSchemaEntry {
+    pub schema_id: SchemaId,
+}
+
+/// A view is persisted with its definition, and the query is bound again on replay.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateViewEntry {
+    pub schema_id: SchemaId,
+    pub view_name: String,
+    pub column_descs: Vec<ColumnCatalog>,
+    pub definition: ViewDefinition,
+    pub dependencies: Vec<TableRefId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DropViewEntry {
+    pub table_id: TableRefId,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AddRowSetEntry {
     pub table_id: TableRefId,
     pub rowset_id: u32,
+    /// Columns stored in the rowset. Empty for entries written before columns could be
+    /// altered, in which case the rowset has all columns of the table.
+    #[serde(default)]
+    pub columns: Vec<ColumnCatalog>,
 }
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DeleteRowsetEntry {
@@ -58,14 +125,29 @@ pub struct DeleteDVEntry {
     pub rowset_id: u32,
 }
 
+/// All write-ahead log records up to `lsn` have been written to disk.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckpointEntry {
+    pub lsn: u64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ManifestOperation {
     CreateTable(CreateTableEntry),
     DropTable(DropTableEntry),
+    AddColumn(AddColumnEntry),
+    DropColumn(DropColumnEntry),
+    CreateIndex(CreateIndexEntry),
+    DropIndex(DropIndexEntry),
+    CreateSchema(CreateSchemaEntry),
+    DropSchema(DropSchemaEntry),
+    CreateView(CreateViewEntry),
+    DropView(DropViewEntry),
     AddRowSet(AddRowSetEntry),
     DeleteRowSet(DeleteRowsetEntry),
     AddDV(AddDVEntry),
     DeleteDV(DeleteDVEntry),
+    Checkpoint(CheckpointEntry),
     // begin transaction
     Begin,
     // end transaction
@@ -117,7 +199,12 @@ impl Manifest {
         Ok(())
     }
 
-    pub async fn replay(&mut self) -> StorageResult<Vec<ManifestOperation>> {
+    /// Returns the operations of each committed transaction in the manifest.
+    ///
+    /// The transactions are the versions of the manifest: version `n` is the state after the first
+    /// `n` transactions. Since the manifest is compacted into a single transaction when the
+    /// storage is opened, the versions are those committed since then.
+    pub async fn replay(&mut self) -> StorageResult<Vec<Vec<ManifestOperation>>> {
         let file = if let Some(file) = &mut self.file {
             file
         } else {
@@ -142,7 +229,7 @@ impl Manifest {
             match value {
                 ManifestOperation::Begin => begin = true,
                 ManifestOperation::End => {
-                    ops.append(&mut buffered_ops);
+                    ops.push(std::mem::take(&mut buffered_ops));
                     begin = false;
                 }
                 op => {
@@ -190,6 +277,9 @@ impl SecondaryStorage {
             table_name,
             column_descs,
             ordered_pk_ids,
+            unique_keys,
+            checks,
+            foreign_keys,
         } = entry.clone();
 
         let schema = self
@@ -205,7 +295,12 @@ impl SecondaryStorage {
                 schema_id,
                 table_name.clone(),
                 column_descs.to_vec(),
-                ordered_pk_ids.clone(),
+                Constraints {
+                    primary_key: ordered_pk_ids.clone(),
+                    unique_keys,
+                    checks,
+                    foreign_keys,
+                },
             )
             .map_err(|_| TracedStorageError::duplicated("table", table_name))?;
 
@@ -222,6 +317,7 @@ impl SecondaryStorage {
             self.block_cache.clone(),
             self.txn_mgr.clone(),
             ordered_pk_ids,
+            self.wal.clone(),
         );
         self.tables.write().insert(id, table);
 
@@ -233,13 +329,16 @@ impl SecondaryStorage {
         schema_id: SchemaId,
         table_name: &str,
         column_descs: &[ColumnCatalog],
-        ordered_pk_ids: &[ColumnId],
+        constraints: &Constraints,
     ) -> StorageResult<()> {
         let entry = CreateTableEntry {
             schema_id,
             table_name: table_name.to_string(),
             column_descs: column_descs.to_vec(),
-            ordered_pk_ids: ordered_pk_ids.to_vec(),
+            ordered_pk_ids: constraints.primary_key.clone(),
+            unique_keys: constraints.unique_keys.clone(),
+            checks: constraints.checks.clone(),
+            foreign_keys: constraints.foreign_keys.clone(),
         };
 
         // persist to manifest first
@@ -263,6 +362,313 @@ impl SecondaryStorage {
         Ok(table)
     }
 
+    pub(super) fn apply_add_column(&self, entry: &AddColumnEntry) -> StorageResult<()> {
+        let AddColumnEntry { table_id, column } = entry.clone();
+
+        let mut tables = self.tables.write();
+        let table = tables
+            .get_mut(&table_id)
+            .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
+        self.catalog
+            .add_column(table_id, column.clone())
+            .map_err(|_| TracedStorageError::duplicated("column", column.name()))?;
+        let mut columns = table.columns.to_vec();
+        columns.push(column);
+        table.set_columns(&columns);
+
+        Ok(())
+    }
+
+    pub(super) async fn add_column_inner(
+        &self,
+        table_id: TableRefId,
+        column: &ColumnCatalog,
+    ) -> StorageResult<()> {
+        let entry = AddColumnEntry {
+            table_id,
+            column: column.clone(),
+        };
+
+        // persist to manifest first
+        self.version
+            .commit_changes(vec![EpochOp::AddColumn(entry.clone())])
+            .await?;
+
+        // then apply to catalog
+        self.apply_add_column(&entry)?;
+
+        Ok(())
+    }
+
+    pub(super) fn apply_drop_column(&self, entry: &DropColumnEntry) -> StorageResult<()> {
+        let DropColumnEntry {
+            table_id,
+            column_id,
+        } = entry.clone();
+
+        let mut tables = self.tables.write();
+        let table = tables
+            .get_mut(&table_id)
+            .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
+        self.catalog
+            .drop_column(table_id, column_id)
+            .map_err(|_| TracedStorageError::not_found("column", column_id))?;
+        let columns = (table.columns.iter())
+            .filter(|col| col.id() != column_id)
+            .cloned()
+            .collect_vec();
+        table.set_columns(&columns);
+
+        Ok(())
+    }
+
+    pub(super) async fn drop_column_inner(
+        &self,
+        table_id: TableRefId,
+        column_id: ColumnId,
+    ) -> StorageResult<()> {
+        let entry = DropColumnEntry {
+            table_id,
+            column_id,
+        };
+
+        // the data of the column is left in the rowsets, and will be removed on compaction
+        self.version
+            .commit_changes(vec![EpochOp::DropColumn(entry.clone())])
+            .await?;
+
+        self.apply_drop_column(&entry)?;
+
+        Ok(())
+    }
+
+    pub(super) fn apply_create_index(&self, entry: &CreateIndexEntry) -> StorageResult<()> {
+        let CreateIndexEntry {
+            index_name,
+            table_id,
+            column_ids,
+        } = entry.clone();
+
+        let table = self.get_table_inner(table_id)?;
+        let index_id = self
+            .catalog
+            .add_index(
+                table_id.schema_id,
+                index_name.clone(),
+                table_id.table_id,
+                column_ids.clone(),
+            )
+            .map_err(|_| TracedStorageError::duplicated("index", index_name))?;
+        let index = Arc::new(TableIndex::new(&column_ids));
+        table.indexes.write().push(index.clone());
+        (self.indexes.write()).insert((table_id.schema_id, index_id), index);
+
+        Ok(())
+    }
+
+    pub(super) async fn create_index_inner(
+        &self,
+        index_name: &str,
+        table_id: TableRefId,
+        column_ids: &[ColumnId],
+    ) -> StorageResult<()> {
+        let entry = CreateIndexEntry {
+            index_name: index_name.into(),
+            table_id,
+            column_ids: column_ids.to_vec(),
+        };
+
+        // only the definition is persisted, entries are rebuilt from the table on demand
+        self.version
+            .commit_changes(vec![EpochOp::CreateIndex(entry.clone())])
+            .await?;
+
+        self.apply_create_index(&entry)?;
+
+        Ok(())
+    }
+
+    pub(super) fn apply_drop_index(&self, entry: &DropIndexEntry) -> StorageResult<()> {
+        let DropIndexEntry {
+            schema_id,
+            index_id,
+        } = entry.clone();
+
+        let index = self
+            .indexes
+            .write()
+            .remove(&(schema_id, index_id))
+            .ok_or_else(|| TracedStorageError::not_found("index", index_id))?;
+        if let Some(catalog) = self.catalog.get_index(schema_id, index_id) {
+            let table_id = TableRefId::new(schema_id, catalog.table_id());
+            if let Ok(table) = self.get_table_inner(table_id) {
+                (table.indexes.write()).retain(|i| !Arc::ptr_eq(i, &index));
+            }
+        }
+        self.catalog.drop_index(schema_id, index_id);
+
+        Ok(())
+    }
+
+    pub(super) async fn drop_index_inner(
+        &self,
+        schema_id: SchemaId,
+        index_id: IndexId,
+    ) -> StorageResult<()> {
+        let entry = DropIndexEntry {
+            schema_id,
+            index_id,
+        };
+
+        self.version
+            .commit_changes(vec![EpochOp::DropIndex(entry.clone())])
+            .await?;
+
+        self.apply_drop_index(&entry)?;
+
+        Ok(())
+    }
+
+    pub(super) fn get_index_inner(
+        &self,
+        schema_id: SchemaId,
+        index_id: IndexId,
+    ) -> StorageResult<Arc<TableIndex>> {
+        let index = self
+            .indexes
+            .read()
+            .get(&(schema_id, index_id))
+            .ok_or_else(|| TracedStorageError::not_found("index", index_id))?
+            .clone();
+        Ok(index)
+    }
+
+    pub(super) fn apply_create_schema(&self, entry: &CreateSchemaEntry) -> StorageResult<()> {
+        let CreateSchemaEntry { schema_name } = entry;
+        self.catalog
+            .add_schema(schema_name.clone())
+            .map_err(|_| TracedStorageError::duplicated("schema", schema_name))?;
+        Ok(())
+    }
+
+    pub(super) async fn create_schema_inner(&self, schema_name: &str) -> StorageResult<()> {
+        let entry = CreateSchemaEntry {
+            schema_name: schema_name.into(),
+        };
+
+        self.version
+            .commit_changes(vec![EpochOp::CreateSchema(entry.clone())])
+            .await?;
+
+        self.apply_create_schema(&entry)?;
+
+        Ok(())
+    }
+
+    pub(super) fn apply_drop_schema(&self, entry: &DropSchemaEntry) -> StorageResult<()> {
+        self.catalog
+            .get_schema_by_id(entry.schema_id)
+            .ok_or_else(|| TracedStorageError::not_found("schema", entry.schema_id))?;
+        self.catalog.drop_schema(entry.schema_id);
+        Ok(())
+    }
+
+    pub(super) async fn drop_schema_inner(&self, schema_id: SchemaId) -> StorageResult<()> {
+        let entry = DropSchemaEntry { schema_id };
+
+        self.version
+            .commit_changes(vec![EpochOp::DropSchema(entry.clone())])
+            .await?;
+
+        self.apply_drop_schema(&entry)?;
+
+        Ok(())
+    }
+
+    /// Binds the query of a view from the definition in its entry.
+    pub(super) fn bind_view(&self, entry: &CreateViewEntry) -> StorageResult<RecExpr> {
+        let mut binder = Binder::new(self.catalog.clone());
+        binder.bind_view_definition(&entry.definition).map_err(|e| {
+            TracedStorageError::decode(format!("failed to bind view {}: {e}", entry.view_name))
+        })
+    }
+
+    pub(super) fn apply_create_view(
+        &self,
+        entry: &CreateViewEntry,
+        query: RecExpr,
+    ) -> StorageResult<()> {
+        let CreateViewEntry {
+            schema_id,
+            view_name,
+            column_descs,
+            dependencies,
+            ..
+        } = entry.clone();
+
+        self.catalog
+            .get_schema_by_id(schema_id)
+            .ok_or_else(|| TracedStorageError::not_found("schema", schema_id))?;
+        self.catalog
+            .add_view(
+                schema_id,
+                view_name.clone(),
+                column_descs,
+                query,
+                dependencies,
+            )
+            .map_err(|_| TracedStorageError::duplicated("view", view_name))?;
+
+        Ok(())
+    }
+
+    pub(super) async fn create_view_inner(
+        &self,
+        schema_id: SchemaId,
+        view_name: &str,
+        column_descs: &[ColumnCatalog],
+        definition: &ViewDefinition,
+        query: &RecExpr,
+        dependencies: &[TableRefId],
+    ) -> StorageResult<()> {
+        let entry = CreateViewEntry {
+            schema_id,
+            view_name: view_name.into(),
+            column_descs: column_descs.to_vec(),
+            definition: definition.clone(),
+            dependencies: dependencies.to_vec(),
+        };
+
+        self.version
+            .commit_changes(vec![EpochOp::CreateView(entry.clone())])
+            .await?;
+
+        self.apply_create_view(&entry, query.clone())?;
+
+        Ok(())
+    }
+
+    pub(super) fn apply_drop_view(&self, entry: &DropViewEntry) -> StorageResult<()> {
+        let DropViewEntry { table_id } = entry;
+        (self.catalog.get_table(table_id))
+            .filter(|table| table.is_view())
+            .ok_or_else(|| TracedStorageError::not_found("view", table_id.table_id))?;
+        self.catalog.drop_table(*table_id);
+        Ok(())
+    }
+
+    pub(super) async fn drop_view_inner(&self, table_id: TableRefId) -> StorageResult<()> {
+        let entry = DropViewEntry { table_id };
+
+        self.version
+            .commit_changes(vec![EpochOp::DropView(entry.clone())])
+            .await?;
+
+        self.apply_drop_view(&entry)?;
+
+        Ok(())
+    }
+
     pub(super) fn apply_drop_table(&self, entry: &DropTableEntry) -> StorageResult<()> {
         let DropTableEntry { table_id } = entry.clone();
 
@@ -270,6 +676,10 @@ impl SecondaryStorage {
             .write()
             .remove(&table_id)
             .ok_or_else(|| TracedStorageError::not_found("table", table_id.table_id))?;
+        let mut indexes = self.indexes.write();
+        for index in self.catalog.get_table_indexes(&table_id) {
+            indexes.remove(&(table_id.schema_id, index.id()));
+        }
         self.catalog.drop_table(table_id);
 
         Ok(())
@@ -284,7 +694,17 @@ impl SecondaryStorage {
         self.apply_drop_table(&entry)?;
 
         changeset.push(EpochOp::DropTable(entry));
+        changeset.extend(self.delete_all_rowsets(table_id));
+
+        // and then persist to manifest
+        self.version.commit_changes(changeset).await?;
+
+        Ok(())
+    }
 
+    /// Returns the operations to delete all RowSets and DVs of the table.
+    fn delete_all_rowsets(&self, table_id: TableRefId) -> Vec<EpochOp> {
+        let mut changeset = vec![];
         let pin_version = self.version.pin();
 
         if let Some(rowsets) = pin_version.snapshot.get_rowsets_of(table_id.table_id) {
@@ -308,10 +728,110 @@ impl SecondaryStorage {
                 }
             }
         }
+        changeset
+    }
 
-        // and then persist to manifest
-        self.version.commit_changes(changeset).await?;
-
+    pub(super) async fn truncate_table_inner(&self, table_id: TableRefId) -> StorageResult<()> {
+        let table = self.get_table_inner(table_id)?;
+        let mut table_ids = self
+            .tables
+            .read()
+            .keys()
+            .map(|id| id.table_id)
+            .collect_vec();
+        table_ids.sort();
+
+        // no deletion or compaction can happen on the tables during the truncation
+        let mut guards = Vec::with_capacity(table_ids.len());
+        for table_id in table_ids {
+            guards.push(self.txn_mgr.lock_for_deletion(table_id).await);
+        }
+        // rowsets in the write-ahead log should not be replayed after the truncation
+        self.checkpoint_locked().await?;
+
+        // only the metadata is changed, files of the rowsets are removed once unreferenced
+        let changeset = self.delete_all_rowsets(table_id);
+        let indexes = table.indexes.read().clone();
+        indexes.iter().for_each(|index| index.begin_move());
+        let result = self.version.commit_changes(changeset).await;
+        table.bump_data_version();
+        indexes.iter().for_each(|index| index.end_move());
+        result?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::SecondaryStorageOptions;
+    use crate::Database;
+
+    #[tokio::test]
+    async fn reload_catalog() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let options = SecondaryStorageOptions {
+            path: tempdir.path().into(),
+            disable_all_disk_operation: false,
+            ..SecondaryStorageOptions::default_for_test()
+        };
+        let db = Database::new_on_disk(options.clone()).await;
+        db.run(
+            "create schema s;
+            create table s.t (a int primary key, b int);
+            create view v as select a + b as c from s.t;
+            create view s.w as select c from v;
+            create table t2 (x int);",
+        )
+        .await
+        .unwrap();
+        let t2 = db.catalog().get_table_by_name("t2").unwrap().id();
+        db.shutdown().await.unwrap();
+
+        let db = Database::new_on_disk(options).await;
+        assert!(db.catalog().get_table_by_name("s.w").unwrap().is_view());
+        // the table is given the same id after the view
+        assert_eq!(db.catalog().get_table_by_name("t2").unwrap().id(), t2);
+        db.run("insert into s.t values (1, 2)").await.unwrap();
+        let chunks = db.run("select c from s.w").await.unwrap();
+        let data_chunk = chunks[0].get_first_data_chunk();
+        assert_eq!(data_chunk.array_at(0).get_to_string(0), "3");
+        db.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn open_older_version() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let options = SecondaryStorageOptions {
+            path: tempdir.path().into(),
+            disable_all_disk_operation: false,
+            ..SecondaryStorageOptions::default_for_test()
+        };
+        let count = |db: Database| async move {
+            let chunks = db.run("select count(*) from t").await.unwrap();
+            let count = chunks[0]
+                .get_first_data_chunk()
+                .array_at(0)
+                .get_to_string(0);
+            db.shutdown().await.unwrap();
+            count
+        };
+
+        // version 1 is written on open, and each statement commits a version
+        let db = Database::new_on_disk(options.clone()).await;
+        db.run("create table t (a int)").await.unwrap();
+        db.run("insert into t values (1)").await.unwrap();
+        db.run("insert into t values (2)").await.unwrap();
+        db.shutdown().await.unwrap();
+
+        let older = SecondaryStorageOptions {
+            manifest_version: Some(3),
+            ..options.clone()
+        };
+        let db = Database::new_on_disk(older.clone()).await;
+        // changes are not persisted
+        db.run("insert into t values (3)").await.unwrap();
+        assert_eq!(count(db).await, "2");
+        assert_eq!(count(Database::new_on_disk(older).await).await, "1");
+        assert_eq!(count(Database::new_on_disk(options).await).await, "2");
+    }
+}
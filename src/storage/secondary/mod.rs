@@ -7,6 +7,7 @@ use std::sync::atomic::{AtomicU32, AtomicU64};
 use std::sync::Arc;
 
 use block::*;
+use bloom_filter::*;
 pub use checksum::*;
 use column::*;
 use compactor::*;
@@ -31,9 +32,14 @@ pub use transaction::*;
 use transaction_manager::*;
 pub use txn_iterator::*;
 use version_manager::*;
+use wal::*;
 
-use super::{Storage, StorageResult, TracedStorageError};
-use crate::catalog::{ColumnCatalog, ColumnId, RootCatalogRef, SchemaId, TableRefId};
+use super::{Storage, StorageResult, TableIndex, TracedStorageError};
+use crate::catalog::{
+    ColumnCatalog, ColumnId, Constraints, IndexId, RootCatalogRef, SchemaId, TableRefId,
+    ViewDefinition,
+};
+use crate::planner::RecExpr;
 
 // public modules and structures
 mod options;
@@ -44,6 +50,7 @@ mod txn_iterator;
 
 // internal modules and structures
 mod block;
+mod bloom_filter;
 mod checksum;
 mod column;
 mod compactor;
@@ -59,8 +66,10 @@ mod statistics;
 mod storage;
 mod transaction_manager;
 mod version_manager;
+mod wal;
 
 const MANIFEST_FILE_NAME: &str = "manifest.json";
+const WAL_FILE_NAME: &str = "wal.log";
 
 #[cfg(test)]
 mod tests;
@@ -73,6 +82,9 @@ pub struct SecondaryStorage {
     /// All tables in the storage engine
     tables: RwLock<HashMap<TableRefId, SecondaryTable>>,
 
+    /// All indexes in the storage engine
+    indexes: RwLock<HashMap<(SchemaId, IndexId), Arc<TableIndex>>>,
+
     /// Options of the current engine
     options: Arc<StorageOptions>,
 
@@ -98,6 +110,9 @@ pub struct SecondaryStorage {
 
     /// Manages all ongoing txns
     txn_mgr: Arc<TransactionManager>,
+
+    /// The write-ahead log, if enabled.
+    wal: Option<Arc<Wal>>,
 }
 
 impl SecondaryStorage {
@@ -157,8 +172,21 @@ impl SecondaryStorage {
         handler.0.take().unwrap().send(()).unwrap();
         handler.1.take().unwrap().await.unwrap();
 
+        self.checkpoint_inner().await?;
+
         Ok(())
     }
+
+    /// Write the write-ahead log to disk.
+    pub async fn checkpoint(&self) -> StorageResult<()> {
+        self.checkpoint_inner().await
+    }
+
+    /// Returns the epochs of snapshots that can be read by time travel queries, and their commit
+    /// times in microseconds since the Unix epoch.
+    pub fn snapshots(&self) -> Vec<(u64, i64)> {
+        self.version.snapshots()
+    }
 }
 
 impl Storage for SecondaryStorage {
@@ -170,9 +198,9 @@ impl Storage for SecondaryStorage {
         schema_id: SchemaId,
         table_name: &str,
         column_descs: &[ColumnCatalog],
-        ordered_pk_ids: &[ColumnId],
+        constraints: &Constraints,
     ) -> StorageResult<()> {
-        self.create_table_inner(schema_id, table_name, column_descs, ordered_pk_ids)
+        self.create_table_inner(schema_id, table_name, column_descs, constraints)
             .await
     }
 
@@ -184,6 +212,68 @@ impl Storage for SecondaryStorage {
         self.drop_table_inner(table_id).await
     }
 
+    async fn truncate_table(&self, table_id: TableRefId) -> StorageResult<()> {
+        self.truncate_table_inner(table_id).await
+    }
+
+    async fn add_column(&self, table_id: TableRefId, column: &ColumnCatalog) -> StorageResult<()> {
+        self.add_column_inner(table_id, column).await
+    }
+
+    async fn drop_column(&self, table_id: TableRefId, column_id: ColumnId) -> StorageResult<()> {
+        self.drop_column_inner(table_id, column_id).await
+    }
+
+    async fn create_index(
+        &self,
+        index_name: &str,
+        table_id: TableRefId,
+        column_ids: &[ColumnId],
+    ) -> StorageResult<()> {
+        self.create_index_inner(index_name, table_id, column_ids)
+            .await
+    }
+
+    async fn drop_index(&self, schema_id: SchemaId, index_id: IndexId) -> StorageResult<()> {
+        self.drop_index_inner(schema_id, index_id).await
+    }
+
+    fn get_index(&self, schema_id: SchemaId, index_id: IndexId) -> StorageResult<Arc<TableIndex>> {
+        self.get_index_inner(schema_id, index_id)
+    }
+
+    async fn create_schema(&self, schema_name: &str) -> StorageResult<()> {
+        self.create_schema_inner(schema_name).await
+    }
+
+    async fn drop_schema(&self, schema_id: SchemaId) -> StorageResult<()> {
+        self.drop_schema_inner(schema_id).await
+    }
+
+    async fn create_view(
+        &self,
+        schema_id: SchemaId,
+        view_name: &str,
+        column_descs: &[ColumnCatalog],
+        definition: &ViewDefinition,
+        query: &RecExpr,
+        dependencies: &[TableRefId],
+    ) -> StorageResult<()> {
+        self.create_view_inner(
+            schema_id,
+            view_name,
+            column_descs,
+            definition,
+            query,
+            dependencies,
+        )
+        .await
+    }
+
+    async fn drop_view(&self, table_id: TableRefId) -> StorageResult<()> {
+        self.drop_view_inner(table_id).await
+    }
+
     fn as_disk(&self) -> Option<&SecondaryStorage> {
         Some(self)
     }
@@ -3,12 +3,15 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::Bytes;
 use parking_lot::Mutex;
 use risinglight_proto::rowset::block_checksum::ChecksumType;
 use tracing::warn;
 
+use crate::catalog::Compression;
+
 /// IO Backend of the rowset readers
 #[derive(Clone)]
 pub enum IOBackend {
@@ -37,14 +40,75 @@ pub enum EncodeType {
     Dictionary,
 }
 
+/// When to sync the write-ahead log to disk.
+#[derive(Clone, Copy, Debug)]
+pub enum WalSyncPolicy {
+    /// Sync before each commit returns, so that committed data is never lost.
+    PerCommit,
+    /// Sync when the oldest unsynced commit is older than the interval. This is checked on each
+    /// commit and about every second in background. Recent commits may be lost on crash.
+    Periodic(Duration),
+}
+
+/// Options for the write-ahead log.
+#[derive(Clone, Copy, Debug)]
+pub struct WalOptions {
+    /// When to sync the log to disk.
+    pub sync_policy: WalSyncPolicy,
+
+    /// Size (in bytes) of the log to trigger a checkpoint, which writes all logged RowSets and
+    /// DVs to disk and truncates the log.
+    pub checkpoint_size: usize,
+}
+
+/// Eviction policy of the block cache.
+#[derive(Clone, Copy, Debug)]
+pub enum BlockCachePolicy {
+    /// Evict the least recently used blocks.
+    Lru,
+    /// Admit new blocks only if they are estimated to be accessed more often than the blocks to
+    /// evict, so that a large scan does not flush hot blocks out of the cache.
+    TinyLfu,
+}
+
+/// Strategy to select RowSets of a table for background compaction.
+///
+/// RowSets larger than `target_rowset_size` are not compacted, and a compaction writes at most
+/// `target_rowset_size` bytes.
+#[derive(Clone, Copy, Debug)]
+pub enum CompactionStrategy {
+    /// Merge RowSets of similar sizes, so that each row is rewritten fewer times.
+    ///
+    /// RowSets sorted by size are grouped into tiers, where each RowSet is at most `size_ratio`
+    /// times larger than the smallest one in its tier. The tier of the smallest RowSets that has
+    /// at least `min_rowsets` RowSets is merged.
+    SizeTiered { min_rowsets: usize, size_ratio: u64 },
+
+    /// Keep a few RowSets of growing sizes, so that scans read fewer RowSets.
+    ///
+    /// A RowSet is in the lowest level `i` that it fits in, whose capacity is
+    /// `base_size * multiplier^i` bytes. Once the total size of levels `0..=i` exceeds the
+    /// capacity of level `i`, they are merged into level `i + 1` with its RowSets. Level 0 is
+    /// also merged once it has `level0_max_rowsets` RowSets.
+    Leveled {
+        base_size: u64,
+        multiplier: u64,
+        level0_max_rowsets: usize,
+    },
+}
+
 /// Options for `SecondaryStorage`
 #[derive(Clone)]
 pub struct StorageOptions {
     /// Path of the storage engine
     pub path: PathBuf,
 
-    /// Number of cache entrires
-    pub cache_size: usize,
+    /// Capacity (in bytes) of the block cache, which keeps decompressed blocks shared by all
+    /// scans
+    pub block_cache_size: usize,
+
+    /// Eviction policy of the block cache
+    pub block_cache_policy: BlockCachePolicy,
 
     /// Target size (in bytes) of RowSets
     pub target_rowset_size: usize,
@@ -66,13 +130,34 @@ pub struct StorageOptions {
 
     /// Whether to disable all disk operations, only for test use
     pub disable_all_disk_operation: bool,
+
+    /// Options of the write-ahead log. If not set, each commit writes RowSets and DVs to disk.
+    pub wal: Option<WalOptions>,
+
+    /// Strategy of background compaction
+    pub compaction_strategy: CompactionStrategy,
+
+    /// Whether to write blocks uncompressed, ignoring the compression of columns. Useful in
+    /// benchmarks to measure the cost of compression.
+    pub disable_compression: bool,
+
+    /// Open the storage at an older version of the manifest, i.e. the state after the given
+    /// number of committed manifest transactions. For debugging only: changes are not persisted,
+    /// and no files are removed, so that later versions are kept intact.
+    pub manifest_version: Option<usize>,
+
+    /// How long replaced snapshots are kept for time travel queries (`AS OF`). The vacuum doesn't
+    /// remove files visible to snapshots committed in this period. Zero disables time travel to
+    /// replaced snapshots.
+    pub snapshot_retention: Duration,
 }
 
 impl StorageOptions {
     pub fn default_for_cli() -> Self {
         Self {
             path: PathBuf::new().join("risinglight.db"),
-            cache_size: 262144,                  // 4GB (16KB * 262144)
+            block_cache_size: 4 << 30, // 4GB
+            block_cache_policy: BlockCachePolicy::TinyLfu,
             target_rowset_size: 256 * (1 << 20), // 256MB
             target_block_size: 16 * (1 << 10),   // 16KB
             io_backend: if cfg!(target_os = "windows") {
@@ -86,13 +171,26 @@ impl StorageOptions {
             // required by range-filter scan rule
             record_first_key: true,
             disable_all_disk_operation: false,
+            wal: Some(WalOptions {
+                sync_policy: WalSyncPolicy::PerCommit,
+                checkpoint_size: 64 * (1 << 20), // 64MB
+            }),
+            compaction_strategy: CompactionStrategy::Leveled {
+                base_size: 16 * (1 << 20), // 16MB
+                multiplier: 10,
+                level0_max_rowsets: 4,
+            },
+            disable_compression: false,
+            manifest_version: None,
+            snapshot_retention: Duration::ZERO,
         }
     }
 
     pub fn default_for_test() -> Self {
         Self {
             path: PathBuf::from("_inaccessible_directory"),
-            cache_size: 1024,
+            block_cache_size: 16 << 20, // 16MB
+            block_cache_policy: BlockCachePolicy::Lru,
             target_rowset_size: 1 << 20,       // 1MB
             target_block_size: 16 * (1 << 10), // 16KB
             io_backend: IOBackend::in_memory(),
@@ -101,6 +199,14 @@ impl StorageOptions {
             // required by range-filter scan rule
             record_first_key: true,
             disable_all_disk_operation: true,
+            wal: None,
+            compaction_strategy: CompactionStrategy::SizeTiered {
+                min_rowsets: 2,
+                size_ratio: 4,
+            },
+            disable_compression: false,
+            manifest_version: None,
+            snapshot_retention: Duration::ZERO,
         }
     }
 }
@@ -119,6 +225,12 @@ pub struct ColumnBuilderOptions {
 
     /// Whether record first_key of each block
     pub record_first_key: bool,
+
+    /// Compression algorithm of blocks
+    pub compression: Compression,
+
+    /// Whether to ignore the compression of columns
+    pub disable_compression: bool,
 }
 
 impl ColumnBuilderOptions {
@@ -128,6 +240,8 @@ impl ColumnBuilderOptions {
             checksum_type: options.checksum_type,
             encode_type: EncodeType::Plain,
             record_first_key: options.record_first_key,
+            compression: Compression::None,
+            disable_compression: options.disable_compression,
         }
     }
 
@@ -138,6 +252,8 @@ impl ColumnBuilderOptions {
             checksum_type: ChecksumType::Crc32,
             encode_type: EncodeType::Plain,
             record_first_key: false,
+            compression: Compression::None,
+            disable_compression: false,
         }
     }
 
@@ -148,6 +264,8 @@ impl ColumnBuilderOptions {
             checksum_type: ChecksumType::None,
             encode_type: EncodeType::Plain,
             record_first_key: false,
+            compression: Compression::None,
+            disable_compression: false,
         }
     }
 
@@ -158,6 +276,8 @@ impl ColumnBuilderOptions {
             checksum_type: ChecksumType::None,
             encode_type: EncodeType::RunLength,
             record_first_key: false,
+            compression: Compression::None,
+            disable_compression: false,
         }
     }
     #[cfg(test)]
@@ -167,6 +287,8 @@ impl ColumnBuilderOptions {
             checksum_type: ChecksumType::None,
             encode_type: EncodeType::Dictionary,
             record_first_key: false,
+            compression: Compression::None,
+            disable_compression: false,
         }
     }
 
@@ -177,6 +299,8 @@ impl ColumnBuilderOptions {
             checksum_type: ChecksumType::None,
             encode_type: EncodeType::Plain,
             record_first_key: true,
+            compression: Compression::None,
+            disable_compression: false,
         }
     }
 }
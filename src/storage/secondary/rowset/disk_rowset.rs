@@ -1,5 +1,6 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use std::ops::{Bound, Range};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
@@ -8,9 +9,11 @@ use itertools::Itertools;
 use moka::future::Cache;
 use tokio::fs::{read, OpenOptions};
 
-use super::super::{Block, BlockCacheKey, Column, ColumnIndex, ColumnSeekPosition, IOBackend};
-use super::{path_of_data_column, path_of_index_column, RowSetIterator};
-use crate::catalog::ColumnCatalog;
+use super::super::{
+    Block, BlockCacheKey, BloomFilter, Column, ColumnIndex, ColumnSeekPosition, IOBackend,
+};
+use super::{path_of_bloom_filter, path_of_data_column, path_of_index_column, RowSetIterator};
+use crate::catalog::{ColumnCatalog, ColumnId};
 use crate::storage::secondary::column::ColumnReadableFile;
 use crate::storage::secondary::encode::PrimitiveFixedWidthEncode;
 use crate::storage::secondary::DeleteVector;
@@ -23,6 +26,8 @@ use crate::types::DataValue;
 pub struct DiskRowset {
     column_infos: Arc<[ColumnCatalog]>,
     columns: Vec<Column>,
+    /// Bloom filters of columns with bloom filter enabled.
+    bloom_filters: Vec<Option<BloomFilter>>,
     rowset_id: u32,
 }
 
@@ -35,6 +40,7 @@ impl DiskRowset {
         io_backend: IOBackend,
     ) -> StorageResult<Self> {
         let mut columns = vec![];
+        let mut bloom_filters = vec![];
 
         for (id, column_info) in column_infos.iter().enumerate() {
             let path_of_index_column = path_of_index_column(&directory, column_info);
@@ -86,11 +92,29 @@ impl DiskRowset {
                 BlockCacheKey::default().rowset(rowset_id).column(id as u32),
             );
             columns.push(column);
+
+            let bloom_filter = if column_info.has_bloom_filter() {
+                let path = path_of_bloom_filter(&directory, column_info);
+                let content = match &io_backend {
+                    IOBackend::NormalRead | IOBackend::PositionedRead => {
+                        Bytes::from(read(&path).await?)
+                    }
+                    IOBackend::InMemory(map) => {
+                        let guard = map.lock();
+                        guard.get(&path).expect("not found").clone()
+                    }
+                };
+                Some(BloomFilter::from_bytes(&content)?)
+            } else {
+                None
+            };
+            bloom_filters.push(bloom_filter);
         }
 
         Ok(Self {
             column_infos,
             columns,
+            bloom_filters,
             rowset_id,
         })
     }
@@ -107,6 +131,16 @@ impl DiskRowset {
         &self.column_infos[storage_column_id]
     }
 
+    pub fn column_infos(&self) -> &Arc<[ColumnCatalog]> {
+        &self.column_infos
+    }
+
+    /// Get the storage column id of a column in catalog, or `None` if the column was added to
+    /// the table after this rowset was written.
+    pub fn storage_column_id(&self, column_id: ColumnId) -> Option<usize> {
+        self.column_infos.iter().position(|c| c.id() == column_id)
+    }
+
     pub fn rowset_id(&self) -> u32 {
         self.rowset_id
     }
@@ -118,7 +152,147 @@ impl DiskRowset {
         seek_pos: ColumnSeekPosition,
         filter: Option<KeyRange>,
     ) -> StorageResult<RowSetIterator> {
-        RowSetIterator::new(self.clone(), column_refs, dvs, seek_pos, filter).await
+        let schema = self.column_infos.clone();
+        self.iter_with_schema(&schema, column_refs, dvs, seek_pos, filter, &[], 0, None)
+            .await
+    }
+
+    /// Iterates the rowset with `column_refs` referring to positions in `schema`, which may be
+    /// newer than the columns this rowset was written with.
+    ///
+    /// Rows whose values are out of the range of a scanned column in `zone_filters` are filtered,
+    /// and blocks out of the range are skipped. Each column reads `prefetch_blocks` blocks ahead
+    /// in background. If `rows` is given, only the rows of these sorted ids are read.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn iter_with_schema(
+        self: &Arc<Self>,
+        schema: &[ColumnCatalog],
+        column_refs: Arc<[StorageColumnRef]>,
+        dvs: Vec<Arc<DeleteVector>>,
+        seek_pos: ColumnSeekPosition,
+        filter: Option<KeyRange>,
+        zone_filters: &[(u32, KeyRange)],
+        prefetch_blocks: usize,
+        rows: Option<&[u32]>,
+    ) -> StorageResult<RowSetIterator> {
+        let pruned_rows = self.pruned_rows(schema, zone_filters, rows);
+        let row_filters = (zone_filters.iter())
+            .filter_map(|(idx, range)| {
+                let id = (column_refs.iter())
+                    .position(|c| matches!(c, StorageColumnRef::Idx(i) if i == idx))?;
+                Some((id, range.clone()))
+            })
+            .collect();
+        RowSetIterator::new(
+            self.clone(),
+            schema,
+            column_refs,
+            dvs,
+            seek_pos,
+            filter,
+            pruned_rows,
+            row_filters,
+            prefetch_blocks,
+        )
+        .await
+    }
+
+    /// Returns true if all values of a column in `zone_filters` are out of its range.
+    ///
+    /// Columns are given by their positions in `schema`.
+    pub fn is_pruned(&self, schema: &[ColumnCatalog], zone_filters: &[(u32, KeyRange)]) -> bool {
+        zone_filters.iter().any(|(idx, range)| {
+            let Some(storage_idx) = self.storage_column_id(schema[*idx as usize].id()) else {
+                return false;
+            };
+            match self.columns[storage_idx].index().min_max() {
+                Some((min, max)) => !range.overlaps(min, max),
+                None => false,
+            }
+        })
+    }
+
+    /// Checks the bloom filters of columns with a single value in `zone_filters`.
+    ///
+    /// Returns `Some(true)` if a bloom filter shows that no row has the value, or `None` if no
+    /// bloom filter is checked. Columns are given by their positions in `schema`.
+    pub fn check_bloom_filters(
+        &self,
+        schema: &[ColumnCatalog],
+        zone_filters: &[(u32, KeyRange)],
+    ) -> Option<bool> {
+        let mut checked = None;
+        for (idx, range) in zone_filters {
+            let (Bound::Included(value), Bound::Included(end)) = (&range.start, &range.end) else {
+                continue;
+            };
+            let column = &schema[*idx as usize];
+            // the hash of a value depends on its type
+            if value != end || value.data_type() != column.data_type() {
+                continue;
+            }
+            let Some(storage_idx) = self.storage_column_id(column.id()) else {
+                continue;
+            };
+            let Some(bloom_filter) = &self.bloom_filters[storage_idx] else {
+                continue;
+            };
+            if !bloom_filter.may_contain(value) {
+                return Some(true);
+            }
+            checked = Some(false);
+        }
+        checked
+    }
+
+    /// Returns the sorted and disjoint row ranges of blocks, whose values of a column in
+    /// `zone_filters` are out of its range, and of rows not in `rows` if it is given.
+    fn pruned_rows(
+        &self,
+        schema: &[ColumnCatalog],
+        zone_filters: &[(u32, KeyRange)],
+        rows: Option<&[u32]>,
+    ) -> Vec<Range<u32>> {
+        let mut ranges = vec![];
+        if let Some(rows) = rows {
+            // skip the gaps between the selected rows
+            let row_count = (self.columns[0].index().indexes().iter())
+                .map(|block| block.row_count)
+                .sum::<u32>();
+            let mut start = 0;
+            for &row in rows {
+                if start < row {
+                    ranges.push(start..row);
+                }
+                start = row + 1;
+            }
+            if start < row_count {
+                ranges.push(start..row_count);
+            }
+        }
+        for (idx, range) in zone_filters {
+            let Some(storage_idx) = self.storage_column_id(schema[*idx as usize].id()) else {
+                continue;
+            };
+            let index = self.columns[storage_idx].index();
+            for (block_id, block) in index.indexes().iter().enumerate() {
+                if let Some((min, max)) = index.zone_map(block_id as u32)
+                    && !range.overlaps(min, max)
+                {
+                    ranges.push(block.first_rowid..block.first_rowid + block.row_count);
+                }
+            }
+        }
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<u32>> = vec![];
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
     }
 
     pub fn on_disk_size(&self) -> u64 {
@@ -361,6 +535,68 @@ pub mod tests {
         column.get_block(0).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_bloom_filter() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut columns = vec![
+            ColumnCatalog::new(0, ColumnDesc::new("v1", DataType::Int32, false)),
+            ColumnCatalog::new(1, ColumnDesc::new("v2", DataType::Int32, false)),
+        ];
+        columns[0].set_bloom_filter(true);
+
+        let mut builder = RowsetBuilder::new(
+            columns.clone().into(),
+            ColumnBuilderOptions::default_for_test(),
+        );
+        builder.append(
+            [
+                ArrayImpl::new_int32([1, 2, 3].into_iter().collect()),
+                ArrayImpl::new_int32([4, 5, 6].into_iter().collect()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let backend = IOBackend::in_memory();
+        let writer = RowsetWriter::new(tempdir.path(), backend.clone());
+        writer.flush(builder.finish()).await.unwrap();
+        let rowset = DiskRowset::open(
+            tempdir.path().to_path_buf(),
+            columns.clone().into(),
+            Cache::new(2333),
+            0,
+            backend,
+        )
+        .await
+        .unwrap();
+
+        let point = |idx: u32, v: i32| {
+            let value = DataValue::Int32(v);
+            (
+                idx,
+                KeyRange {
+                    start: Bound::Included(value.clone()),
+                    end: Bound::Included(value),
+                },
+            )
+        };
+        assert_eq!(
+            rowset.check_bloom_filters(&columns, &[point(0, 2)]),
+            Some(false)
+        );
+        assert_eq!(
+            rowset.check_bloom_filters(&columns, &[point(0, 100)]),
+            Some(true)
+        );
+        // no bloom filter on v2
+        assert_eq!(rowset.check_bloom_filters(&columns, &[point(1, 100)]), None);
+        // ranges are not checked
+        let range = KeyRange {
+            start: Bound::Included(DataValue::Int32(100)),
+            end: Bound::Unbounded,
+        };
+        assert_eq!(rowset.check_bloom_filters(&columns, &[(0, range)]), None);
+    }
+
     #[tokio::test]
     async fn test_get_start_id() {
         let tempdir = tempfile::tempdir().unwrap();
@@ -6,6 +6,8 @@ use crate::catalog::ColumnCatalog;
 pub struct EncodedColumn {
     pub index: Vec<u8>,
     pub data: Vec<u8>,
+    /// The bloom filter of the column, if enabled.
+    pub bloom_filter: Option<Vec<u8>>,
 }
 
 /// Encoded rowset.
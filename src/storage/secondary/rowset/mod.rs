@@ -16,7 +16,8 @@
 //! |- 01.col     data for v1
 //! |- 01.sort    sort index for v1, which stores RowId + Key -> Block mapping
 //! |- 02.col     data for v2
-//! \- 02.idx     normal index for v2, which stores RowId -> Block mapping
+//! |- 02.idx     normal index for v2, which stores RowId -> Block mapping
+//! \- 02.bloom   bloom filter for v2, if enabled by `WITH (bloom_filter = 'v2')`
 //! ```
 //!
 //! Data flushed to directory will be immutable, and the directory content will remain
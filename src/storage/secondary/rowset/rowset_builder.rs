@@ -4,11 +4,11 @@ use std::sync::Arc;
 
 use itertools::Itertools;
 
-use super::super::{ColumnBuilderImpl, IndexBuilder};
+use super::super::{BloomFilterBuilder, ColumnBuilderImpl, IndexBuilder};
 use crate::array::DataChunk;
-use crate::catalog::ColumnCatalog;
+use crate::catalog::{ColumnCatalog, Compression};
 use crate::storage::secondary::rowset::{EncodedColumn, EncodedRowset};
-use crate::storage::secondary::ColumnBuilderOptions;
+use crate::storage::secondary::{ColumnBuilderOptions, EncodeType};
 
 /// Builds a Rowset from [`DataChunk`].
 pub struct RowsetBuilder {
@@ -18,6 +18,9 @@ pub struct RowsetBuilder {
     /// Column data builders
     builders: Vec<ColumnBuilderImpl>,
 
+    /// Bloom filter builders of columns with bloom filter enabled
+    bloom_filters: Vec<Option<BloomFilterBuilder>>,
+
     /// Count of rows in this rowset
     row_cnt: u32,
 
@@ -27,17 +30,38 @@ pub struct RowsetBuilder {
 
 impl RowsetBuilder {
     pub fn new(columns: Arc<[ColumnCatalog]>, column_options: ColumnBuilderOptions) -> Self {
+        let encode_types = vec![column_options.encode_type; columns.len()];
+        Self::new_with_encode_types(columns, column_options, &encode_types)
+    }
+
+    /// Create a builder that encodes each column with the type in `encode_types`.
+    pub fn new_with_encode_types(
+        columns: Arc<[ColumnCatalog]>,
+        column_options: ColumnBuilderOptions,
+        encode_types: &[EncodeType],
+    ) -> Self {
         Self {
-            builders: columns
-                .iter()
-                .map(|column| {
+            builders: (columns.iter().zip(encode_types))
+                .map(|(column, &encode_type)| {
                     ColumnBuilderImpl::new_from_datatype(
                         &column.data_type(),
                         column.is_nullable(),
-                        column_options.clone(),
+                        ColumnBuilderOptions {
+                            encode_type,
+                            compression: if column_options.disable_compression {
+                                Compression::None
+                            } else {
+                                column.compression()
+                            },
+                            ..column_options.clone()
+                        },
                     )
                 })
                 .collect_vec(),
+            bloom_filters: columns
+                .iter()
+                .map(|column| column.has_bloom_filter().then(BloomFilterBuilder::default))
+                .collect_vec(),
             columns,
             row_cnt: 0,
             column_options,
@@ -48,7 +72,13 @@ impl RowsetBuilder {
         self.row_cnt += chunk.cardinality() as u32;
 
         for idx in 0..chunk.column_count() {
-            self.builders[idx].append(chunk.array_at(idx));
+            let array = chunk.array_at(idx);
+            if let Some(bloom_filter) = &mut self.bloom_filters[idx] {
+                for i in 0..array.len() {
+                    bloom_filter.add(array.get(i));
+                }
+            }
+            self.builders[idx].append(array);
         }
     }
 
@@ -60,7 +90,8 @@ impl RowsetBuilder {
             columns: self
                 .builders
                 .into_iter()
-                .map(|builder| {
+                .zip(self.bloom_filters)
+                .map(|(builder, bloom_filter)| {
                     let (block_indices, data) = builder.finish();
 
                     let mut index_builder = IndexBuilder::new(checksum_type, block_indices.len());
@@ -71,6 +102,7 @@ impl RowsetBuilder {
                     EncodedColumn {
                         index: index_builder.finish(),
                         data,
+                        bloom_filter: bloom_filter.map(|builder| builder.finish()),
                     }
                 })
                 .collect_vec(),
@@ -1,14 +1,15 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::ops::Bound;
+use std::collections::VecDeque;
+use std::ops::{Bound, Range};
 use std::sync::Arc;
 
 use bitvec::prelude::BitVec;
-use smallvec::smallvec;
 
 use super::super::{ColumnIteratorImpl, ColumnSeekPosition, SecondaryIteratorImpl};
 use super::DiskRowset;
 use crate::array::ArrayImpl;
+use crate::catalog::ColumnCatalog;
 use crate::storage::secondary::DeleteVector;
 use crate::storage::{KeyRange, PackedVec, StorageChunk, StorageColumnRef, StorageResult};
 
@@ -22,17 +23,27 @@ pub struct RowSetIterator {
     column_iterators: Vec<ColumnIteratorImpl>,
     /// An optional filter for the first column.
     filter: Option<KeyRange>,
+    /// Ranges of columns given by their positions in `column_refs`. Rows out of the ranges are
+    /// filtered before decoding other columns.
+    row_filters: Vec<(usize, KeyRange)>,
+    /// Sorted and disjoint ranges of rows to skip.
+    pruned_rows: VecDeque<Range<u32>>,
     /// Indicate whether the iterator has reached the end.
     end: bool,
 }
 
 impl RowSetIterator {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         rowset: Arc<DiskRowset>,
+        schema: &[ColumnCatalog],
         column_refs: Arc<[StorageColumnRef]>,
         dvs: Vec<Arc<DeleteVector>>,
         seek_pos: ColumnSeekPosition,
         filter: Option<KeyRange>,
+        pruned_rows: Vec<Range<u32>>,
+        row_filters: Vec<(usize, KeyRange)>,
+        prefetch_blocks: usize,
     ) -> StorageResult<Self> {
         let start_row_id = match seek_pos {
             ColumnSeekPosition::RowId(row_id) => row_id,
@@ -54,30 +65,44 @@ impl RowSetIterator {
 
         let mut column_iterators: Vec<ColumnIteratorImpl> = vec![];
 
+        let row_count = || {
+            rowset
+                .column(0)
+                .index()
+                .indexes()
+                .iter()
+                .fold(0, |acc, index| acc + index.row_count)
+        };
+
         for column_ref in &*column_refs {
             // TODO: parallel seek
             match column_ref {
                 StorageColumnRef::RowHandler => {
-                    let column = rowset.column(0);
-                    let row_count = column
-                        .index()
-                        .indexes()
-                        .iter()
-                        .fold(0, |acc, index| acc + index.row_count);
                     column_iterators.push(ColumnIteratorImpl::new_row_handler(
                         rowset.rowset_id(),
-                        row_count,
+                        row_count(),
                         start_row_id,
                     )?)
                 }
-                StorageColumnRef::Idx(idx) => column_iterators.push(
-                    ColumnIteratorImpl::new(
-                        rowset.column(*idx as usize),
-                        rowset.column_info(*idx as usize),
-                        start_row_id,
-                    )
-                    .await?,
-                ),
+                StorageColumnRef::Idx(idx) => {
+                    let column_info = &schema[*idx as usize];
+                    match rowset.storage_column_id(column_info.id()) {
+                        Some(storage_idx) => column_iterators.push(
+                            ColumnIteratorImpl::new(
+                                rowset.column(storage_idx).with_prefetch(prefetch_blocks),
+                                rowset.column_info(storage_idx),
+                                start_row_id,
+                            )
+                            .await?,
+                        ),
+                        // the column is added after this rowset is written
+                        None => column_iterators.push(ColumnIteratorImpl::new_default(
+                            column_info,
+                            row_count(),
+                            start_row_id,
+                        )),
+                    }
+                }
             };
         }
 
@@ -86,6 +111,8 @@ impl RowSetIterator {
             dvs,
             column_iterators,
             filter,
+            row_filters,
+            pruned_rows: pruned_rows.into(),
             end: false,
         })
     }
@@ -151,6 +178,25 @@ impl RowSetIterator {
             fetch_size = if x > fetch_size { fetch_size } else { x }
         }
 
+        // Skip pruned rows without reading their blocks, and stop the batch before them
+        let current_row_id = self.column_iterators[0].fetch_current_row_id();
+        while let Some(range) = self.pruned_rows.front()
+            && range.end <= current_row_id
+        {
+            self.pruned_rows.pop_front();
+        }
+        if let Some(range) = self.pruned_rows.front() {
+            if range.start <= current_row_id {
+                let cnt = (range.end - current_row_id) as usize;
+                for it in &mut self.column_iterators {
+                    it.skip(cnt);
+                }
+                self.pruned_rows.pop_front();
+                return Ok(None);
+            }
+            fetch_size = fetch_size.min((range.start - current_row_id) as usize);
+        }
+
         // TODO: parallel fetch
         // TODO: align unmatched rows
 
@@ -188,16 +234,21 @@ impl RowSetIterator {
             visibility_map = Some(visi);
         }
 
-        let mut arrays: PackedVec<ArrayImpl> = smallvec![];
+        // Late materialization: decode the filtered columns first, and then the other columns only
+        // for rows passing the filters.
+        let mut filtered = vec![false; self.column_refs.len()];
+        if self.filter.is_some() {
+            filtered[0] = true;
+        }
+        for (id, _) in &self.row_filters {
+            filtered[*id] = true;
+        }
+
+        let mut arrays: Vec<Option<ArrayImpl>> = vec![None; self.column_refs.len()];
         // to make sure all columns have the same chunk range
         let mut common_chunk_range = None;
 
-        // At this stage, we know that some rows survived from the filter scan if happend, so
-        // just fetch the next batch for every other columns, and we have `visibility_map` to
-        // indicate the visibility of its rows
-        // TODO: Implement the skip interface for column_iterator and call it here.
-        // For those already fetched columns, they also need to delete corrensponding blocks.
-        for (id, _) in self.column_refs.iter().enumerate() {
+        for id in (0..self.column_refs.len()).filter(|id| filtered[*id]) {
             let Some((row_id, array)) = self.column_iterators[id]
                 .next_batch(Some(fetch_size))
                 .await?
@@ -205,19 +256,7 @@ impl RowSetIterator {
                 self.end = true;
                 return Ok(None);
             };
-
-            // check chunk range
-            let current_range = row_id..row_id + array.len() as u32;
-            if let Some(common_range) = &common_chunk_range {
-                if common_range != &current_range {
-                    panic!(
-                        "unmatched row range from column iterator: {:?} of [{:?}], {:?} != {:?}",
-                        self.column_refs[id], self.column_refs, common_range, current_range
-                    );
-                }
-            } else {
-                common_chunk_range = Some(current_range);
-            }
+            self.check_chunk_range(&mut common_chunk_range, id, row_id, &array);
 
             // For now, we only support range-filter scan by first column.
             if let Some(range) = &self.filter
@@ -251,11 +290,75 @@ impl RowSetIterator {
                 }
             }
 
-            arrays.push(array);
+            for (_, range) in self.row_filters.iter().filter(|(i, _)| *i == id) {
+                let bitmap: BitVec = (0..array.len())
+                    .map(|i| range.may_contain(&array.get(i)))
+                    .collect();
+                if bitmap.all() {
+                    continue;
+                }
+                if let Some(ref mut vis) = visibility_map {
+                    *vis &= bitmap;
+                } else {
+                    visibility_map = Some(bitmap);
+                }
+            }
+
+            arrays[id] = Some(array);
+        }
+
+        // No rows survived from the filters, so skip the other columns without reading them
+        if let Some(vis) = &visibility_map
+            && vis.not_any()
+            && let Some(range) = &common_chunk_range
+        {
+            let len = range.len();
+            for id in (0..self.column_refs.len()).filter(|id| !filtered[*id]) {
+                self.column_iterators[id].skip(len);
+            }
+            return Ok(None);
         }
 
+        // At this stage, we know that some rows survived from the filter scan if happend, so
+        // fetch the next batch for every other columns, and only decode rows in `visibility_map`
+        for id in (0..self.column_refs.len()).filter(|id| !filtered[*id]) {
+            let it = &mut self.column_iterators[id];
+            let batch = match &visibility_map {
+                Some(vis) if !vis.all() => it.next_batch_selected(vis).await?,
+                _ => it.next_batch(Some(fetch_size)).await?,
+            };
+            let Some((row_id, array)) = batch else {
+                self.end = true;
+                return Ok(None);
+            };
+            self.check_chunk_range(&mut common_chunk_range, id, row_id, &array);
+            arrays[id] = Some(array);
+        }
+
+        let arrays: PackedVec<ArrayImpl> = arrays.into_iter().map(Option::unwrap).collect();
         Ok(StorageChunk::construct(visibility_map, arrays))
     }
+
+    /// Checks that all columns are read in the same range of rows.
+    fn check_chunk_range(
+        &self,
+        common_chunk_range: &mut Option<Range<u32>>,
+        id: usize,
+        row_id: u32,
+        array: &ArrayImpl,
+    ) {
+        let current_range = row_id..row_id + array.len() as u32;
+        if let Some(common_range) = common_chunk_range {
+            if common_range != &current_range {
+                panic!(
+                    "unmatched row range from column iterator: {:?} of [{:?}], {:?} != {:?}",
+                    self.column_refs[id], self.column_refs, common_range, current_range
+                );
+            }
+        } else {
+            *common_chunk_range = Some(current_range);
+        }
+    }
 }
 
 impl SecondaryIteratorImpl for RowSetIterator {}
@@ -659,6 +762,95 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_rowset_iterator_with_prefetch() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let rowset = Arc::new(helper_build_rowset_with_first_key_recorded(&tempdir).await);
+        let schema = rowset.column_infos().clone();
+        let mut it = rowset
+            .iter_with_schema(
+                &schema,
+                vec![StorageColumnRef::Idx(0), StorageColumnRef::Idx(2)].into(),
+                vec![],
+                ColumnSeekPosition::RowId(0),
+                None,
+                &[],
+                3,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut column0 = vec![];
+        let mut column2 = vec![];
+        while let Some(chunk) = it.next_batch(Some(100)).await.unwrap() {
+            data_from_chunk(&chunk, &mut column0, 0).await;
+            data_from_chunk(&chunk, &mut column2, 1).await;
+        }
+        assert_eq!(column0, (0..=279).collect_vec());
+        assert_eq!(column2, (2..=281).collect_vec());
+    }
+
+    #[tokio::test]
+    async fn test_rowset_iterator_with_rows() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let rowset = Arc::new(helper_build_rowset_with_first_key_recorded(&tempdir).await);
+        let schema = rowset.column_infos().clone();
+        let mut it = rowset
+            .iter_with_schema(
+                &schema,
+                vec![StorageColumnRef::Idx(0)].into(),
+                vec![],
+                ColumnSeekPosition::RowId(0),
+                None,
+                &[],
+                0,
+                Some(&[3, 4, 100, 279]),
+            )
+            .await
+            .unwrap();
+
+        let mut column0 = vec![];
+        while let Some(chunk) = it.next_batch(None).await.unwrap() {
+            data_from_chunk(&chunk, &mut column0, 0).await;
+        }
+        assert_eq!(column0, vec![3, 4, 100, 279]);
+    }
+
+    #[tokio::test]
+    async fn test_rowset_iterator_with_row_filter() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let rowset = Arc::new(helper_build_rowset_with_first_key_recorded(&tempdir).await);
+        let schema = rowset.column_infos().clone();
+        // the filtered column is decoded before other columns
+        let range = KeyRange {
+            start: Bound::Included(DataValue::Int32(100)),
+            end: Bound::Excluded(DataValue::Int32(110)),
+        };
+        let mut it = rowset
+            .iter_with_schema(
+                &schema,
+                vec![StorageColumnRef::Idx(0), StorageColumnRef::Idx(1)].into(),
+                vec![],
+                ColumnSeekPosition::RowId(0),
+                None,
+                &[(1, range)],
+                0,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut column0 = vec![];
+        let mut column1 = vec![];
+        while let Some(chunk) = it.next_batch(None).await.unwrap() {
+            data_from_chunk(&chunk, &mut column0, 0).await;
+            data_from_chunk(&chunk, &mut column1, 1).await;
+        }
+        assert_eq!(column0, (99..109).collect_vec());
+        assert_eq!(column1, (100..110).collect_vec());
+    }
+
     async fn data_from_chunk(chunk: &StorageChunk, column: &mut Vec<i32>, index: usize) {
         if let ArrayImpl::Int32(array) = chunk.array_at(index) {
             let bit_map = match chunk.visibility() {
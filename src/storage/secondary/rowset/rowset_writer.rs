@@ -19,6 +19,10 @@ pub fn path_of_index_column(base: impl AsRef<Path>, column_info: &ColumnCatalog)
     path_of_column(base, column_info, ".idx")
 }
 
+pub fn path_of_bloom_filter(base: impl AsRef<Path>, column_info: &ColumnCatalog) -> PathBuf {
+    path_of_column(base, column_info, ".bloom")
+}
+
 pub fn path_of_column(
     base: impl AsRef<Path>,
     column_info: &ColumnCatalog,
@@ -112,6 +116,14 @@ impl RowsetWriter {
                 column.index,
             )
             .await?;
+            if let Some(bloom_filter) = column.bloom_filter {
+                Self::pipe_to_file(
+                    &self.io_backend,
+                    path_of_bloom_filter(&self.directory, column_info),
+                    bloom_filter,
+                )
+                .await?;
+            }
         }
 
         Self::sync_dir(&self.io_backend, &self.directory).await?;
@@ -10,6 +10,24 @@
 //!
 //! RowCount is NOT a precise statistics. It simply adds up the row counts of all blocks. As there
 //! might be rows deleted in deletion vector, the aggregated RowCount is not always accurate.
+//!
+//! ## DistinctValue
+//!
+//! The number of distinct non-null values in a block. The aggregated DistinctValue adds up the
+//! counts of all blocks, so it is the total size of per-block dictionaries.
+//!
+//! ## RunCount
+//!
+//! The number of runs of consecutive equal non-null values in a block, which is the number of
+//! entries after run-length encoding. Like DistinctValue, the aggregated RunCount adds up the
+//! counts of all blocks. Compaction uses them to choose the encoding of each column.
+//!
+//! ## MinValue and MaxValue
+//!
+//! The minimum and maximum non-null values of a block, encoded as JSON of `DataValue`. They are
+//! only recorded for columns of fixed-width types, and not for blocks of all nulls. Scans use them
+//! to skip blocks and RowSets out of the range of a filter. Like RowCount, deleted rows are not
+//! excluded.
 
 use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
 
@@ -20,6 +38,10 @@ mod row_count;
 use row_count::*;
 mod distinct_value;
 use distinct_value::*;
+mod run_count;
+use run_count::*;
+mod min_max;
+pub use min_max::*;
 mod statistics_builder;
 pub use statistics_builder::*;
 
@@ -35,5 +57,9 @@ pub fn create_statistics_global_aggregator(
     match ty {
         BlockStatisticsType::RowCount => Box::new(RowCountGlobalAgg::create()),
         BlockStatisticsType::DistinctValue => Box::new(DistinctValueGlobalAgg::create()),
+        BlockStatisticsType::RunCount => Box::new(RunCountGlobalAgg::create()),
+        BlockStatisticsType::MinValue | BlockStatisticsType::MaxValue => {
+            Box::new(MinMaxGlobalAgg::create(ty))
+        }
     }
 }
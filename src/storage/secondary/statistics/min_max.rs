@@ -0,0 +1,116 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
+use risinglight_proto::rowset::{BlockIndex, BlockStatistics};
+
+use super::StatisticsGlobalAgg;
+use crate::storage::secondary::index::ColumnIndex;
+use crate::types::DataValue;
+
+/// Builds the minimum and maximum values of a block.
+#[derive(Default)]
+pub struct MinMaxBuilder {
+    min_max: Option<(DataValue, DataValue)>,
+}
+
+impl MinMaxBuilder {
+    pub fn add_item(&mut self, value: DataValue) {
+        if value.is_null() {
+            return;
+        }
+        self.min_max = Some(match self.min_max.take() {
+            Some((min, max)) => (min.min(value.clone()), max.max(value)),
+            None => (value.clone(), value),
+        });
+    }
+
+    pub fn get_statistics(self) -> Vec<BlockStatistics> {
+        let Some((min, max)) = self.min_max else {
+            return vec![];
+        };
+        vec![
+            BlockStatistics {
+                block_stat_type: BlockStatisticsType::MinValue as i32,
+                body: serde_json::to_vec(&min).unwrap(),
+            },
+            BlockStatistics {
+                block_stat_type: BlockStatisticsType::MaxValue as i32,
+                body: serde_json::to_vec(&max).unwrap(),
+            },
+        ]
+    }
+}
+
+/// Get the minimum and maximum values of a block, if they are recorded.
+pub fn decode_min_max(index: &BlockIndex) -> Option<(DataValue, DataValue)> {
+    let decode = |ty: BlockStatisticsType| {
+        let stat = index
+            .stats
+            .iter()
+            .find(|stat| stat.block_stat_type() == ty)?;
+        serde_json::from_slice::<DataValue>(&stat.body).ok()
+    };
+    Some((
+        decode(BlockStatisticsType::MinValue)?,
+        decode(BlockStatisticsType::MaxValue)?,
+    ))
+}
+
+/// Gather the minimum or maximum value from column index.
+pub struct MinMaxGlobalAgg {
+    ty: BlockStatisticsType,
+    value: DataValue,
+}
+
+impl MinMaxGlobalAgg {
+    pub fn create(ty: BlockStatisticsType) -> Self {
+        Self {
+            ty,
+            value: DataValue::Null,
+        }
+    }
+}
+
+impl StatisticsGlobalAgg for MinMaxGlobalAgg {
+    fn apply_batch(&mut self, index: &ColumnIndex) {
+        for (block_id, _) in index.indexes().iter().enumerate() {
+            if let Some((min, max)) = index.zone_map(block_id as u32) {
+                let value = std::mem::replace(&mut self.value, DataValue::Null);
+                self.value = match self.ty {
+                    BlockStatisticsType::MinValue => value.min(min.clone()),
+                    _ => value.max(max.clone()),
+                };
+            }
+        }
+    }
+
+    fn get_output(&self) -> DataValue {
+        self.value.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_max() {
+        let mut builder = MinMaxBuilder::default();
+        builder.add_item(DataValue::Int32(3));
+        builder.add_item(DataValue::Null);
+        builder.add_item(DataValue::Int32(1));
+        builder.add_item(DataValue::Int32(2));
+        let index = BlockIndex {
+            stats: builder.get_statistics(),
+            ..Default::default()
+        };
+        assert_eq!(
+            decode_min_max(&index),
+            Some((DataValue::Int32(1), DataValue::Int32(3)))
+        );
+
+        let mut builder = MinMaxBuilder::default();
+        builder.add_item(DataValue::Null);
+        assert!(builder.get_statistics().is_empty());
+    }
+}
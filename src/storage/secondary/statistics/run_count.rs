@@ -0,0 +1,35 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
+
+use super::StatisticsGlobalAgg;
+use crate::storage::secondary::index::ColumnIndex;
+use crate::types::DataValue;
+
+/// Gather the number of runs from column index.
+pub struct RunCountGlobalAgg {
+    run_cnt: u64,
+}
+
+impl RunCountGlobalAgg {
+    pub fn create() -> Self {
+        Self { run_cnt: 0 }
+    }
+}
+
+impl StatisticsGlobalAgg for RunCountGlobalAgg {
+    fn apply_batch(&mut self, index: &ColumnIndex) {
+        for index in index.indexes() {
+            for stat in &index.stats {
+                if stat.block_stat_type() == BlockStatisticsType::RunCount {
+                    let cnt = u64::from_le_bytes(stat.body.clone().try_into().unwrap());
+                    self.run_cnt += cnt;
+                }
+            }
+        }
+    }
+
+    fn get_output(&self) -> DataValue {
+        DataValue::Int64(self.run_cnt as i64)
+    }
+}
@@ -7,18 +7,27 @@ use risinglight_proto::rowset::BlockStatistics;
 
 pub struct StatisticsBuilder<'a> {
     distinct_values: HashSet<&'a [u8]>,
+    /// Number of runs of consecutive equal values.
+    run_count: u64,
+    last_value: Option<&'a [u8]>,
 }
 
 impl<'a> StatisticsBuilder<'a> {
     pub fn new() -> Self {
         Self {
             distinct_values: HashSet::<&'a [u8]>::new(),
+            run_count: 0,
+            last_value: None,
         }
     }
 
     pub fn add_item(&mut self, data: Option<&'a [u8]>) {
         if let Some(data) = data {
             self.distinct_values.insert(data);
+            if self.last_value != Some(data) {
+                self.run_count += 1;
+                self.last_value = Some(data);
+            }
         }
     }
 
@@ -28,7 +37,11 @@ impl<'a> StatisticsBuilder<'a> {
             block_stat_type: BlockStatisticsType::DistinctValue as i32,
             body: distinct_count.to_le_bytes().to_vec(),
         };
-        vec![distinct_stat]
+        let run_stat = BlockStatistics {
+            block_stat_type: BlockStatisticsType::RunCount as i32,
+            body: self.run_count.to_le_bytes().to_vec(),
+        };
+        vec![distinct_stat, run_stat]
     }
 }
 
@@ -54,4 +67,19 @@ mod tests {
         let mut body = &stats[0].body[..];
         assert_eq!(body.get_u64_le(), 3);
     }
+
+    #[test]
+    fn test_run_count() {
+        let mut builder = StatisticsBuilder::new();
+        for item in [b"1", b"1", b"2", b"2", b"2", b"1", b"3"] {
+            builder.add_item(Some(item));
+        }
+        let stats = builder.get_statistics();
+        assert_eq!(
+            stats[1].block_stat_type,
+            BlockStatisticsType::RunCount as i32
+        );
+        let mut body = &stats[1].body[..];
+        assert_eq!(body.get_u64_le(), 4);
+    }
 }
@@ -1,21 +1,25 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, AtomicU64};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 
-use moka::future::Cache;
+use itertools::Itertools;
 use parking_lot::RwLock;
 use tokio::fs;
 use tokio::sync::Mutex;
 use tracing::info;
 
-use super::{DiskRowset, Manifest, SecondaryStorage, StorageOptions, StorageResult};
+use super::{
+    new_block_cache, DiskRowset, Manifest, SecondaryStorage, StorageOptions, StorageResult,
+    TracedStorageError,
+};
 use crate::catalog::RootCatalog;
 use crate::storage::secondary::manifest::*;
 use crate::storage::secondary::transaction_manager::TransactionManager;
 use crate::storage::secondary::version_manager::{EpochOp, VersionManager};
-use crate::storage::secondary::{DeleteVector, IOBackend, MANIFEST_FILE_NAME};
+use crate::storage::secondary::wal::Wal;
+use crate::storage::secondary::{DeleteVector, IOBackend, MANIFEST_FILE_NAME, WAL_FILE_NAME};
 
 impl SecondaryStorage {
     pub(super) async fn bootstrap(options: StorageOptions) -> StorageResult<Self> {
@@ -44,26 +48,63 @@ impl SecondaryStorage {
             Manifest::open(options.path.join(MANIFEST_FILE_NAME), enable_fsync).await?
         };
 
-        let manifest_ops = manifest.replay().await?;
+        let mut versions = manifest.replay().await?;
+        info!("manifest version {}", versions.len());
+        // the transactions after the version to open
+        let mut later_versions = vec![];
+        if let Some(version) = options.manifest_version {
+            if version > versions.len() {
+                return Err(TracedStorageError::not_found("manifest version", version));
+            }
+            info!("opening at manifest version {version}, changes will not be persisted");
+            later_versions = versions.split_off(version);
+            manifest = Manifest::new_mock();
+        }
+        let manifest_ops = versions.into_iter().flatten().collect_vec();
+
+        let wal = match options.wal {
+            // the write-ahead log continues the latest version
+            Some(_) if options.manifest_version.is_some() => None,
+            Some(wal_options) if !options.disable_all_disk_operation => Some(Arc::new(
+                Wal::open(options.path.join(WAL_FILE_NAME), wal_options).await?,
+            )),
+            _ => None,
+        };
 
         let options = Arc::new(options);
 
         let engine = Self {
             catalog: Arc::new(catalog),
             tables: RwLock::new(tables),
-            block_cache: Cache::new(options.cache_size as u64),
+            indexes: RwLock::new(HashMap::new()),
+            block_cache: new_block_cache(options.block_cache_size, options.block_cache_policy),
             options: options.clone(),
             next_id: Arc::new((AtomicU32::new(0), AtomicU64::new(0))),
             version: Arc::new(VersionManager::new(manifest, options.clone())),
             compactor_handler: Mutex::new((None, None)),
             vacuum_handler: Mutex::new((None, None)),
             txn_mgr: Arc::new(TransactionManager::default()),
+            wal,
         };
 
         info!("applying {} manifest entries", manifest_ops.len());
 
+        // allocate ids after those of later versions, so that their files are not overwritten
+        for op in later_versions.iter().flatten() {
+            match op {
+                ManifestOperation::AddRowSet(entry) => {
+                    (engine.next_id.0).fetch_max(entry.rowset_id + 1, Ordering::SeqCst);
+                }
+                ManifestOperation::AddDV(entry) => {
+                    (engine.next_id.1).fetch_max(entry.dv_id + 1, Ordering::SeqCst);
+                }
+                _ => {}
+            }
+        }
+
         let mut rowsets_to_open = HashMap::new();
         let mut dvs_to_open = HashMap::new();
+        let mut checkpoint_lsn = 0;
 
         let mut table_changeset = vec![];
         for op in manifest_ops {
@@ -79,6 +120,40 @@ impl SecondaryStorage {
                     // to manifest may solve it, and there may be other solutions.
                     table_changeset.push(EpochOp::DropTable(entry));
                 }
+                ManifestOperation::AddColumn(entry) => {
+                    engine.apply_add_column(&entry)?;
+                    table_changeset.push(EpochOp::AddColumn(entry));
+                }
+                ManifestOperation::DropColumn(entry) => {
+                    engine.apply_drop_column(&entry)?;
+                    table_changeset.push(EpochOp::DropColumn(entry));
+                }
+                ManifestOperation::CreateIndex(entry) => {
+                    engine.apply_create_index(&entry)?;
+                    table_changeset.push(EpochOp::CreateIndex(entry));
+                }
+                ManifestOperation::DropIndex(entry) => {
+                    engine.apply_drop_index(&entry)?;
+                    table_changeset.push(EpochOp::DropIndex(entry));
+                }
+                ManifestOperation::CreateSchema(entry) => {
+                    engine.apply_create_schema(&entry)?;
+                    table_changeset.push(EpochOp::CreateSchema(entry));
+                }
+                ManifestOperation::DropSchema(entry) => {
+                    engine.apply_drop_schema(&entry)?;
+                    table_changeset.push(EpochOp::DropSchema(entry));
+                }
+                ManifestOperation::CreateView(entry) => {
+                    // views are bound again, as the tables they depend on have been created
+                    let query = engine.bind_view(&entry)?;
+                    engine.apply_create_view(&entry, query)?;
+                    table_changeset.push(EpochOp::CreateView(entry));
+                }
+                ManifestOperation::DropView(entry) => {
+                    engine.apply_drop_view(&entry)?;
+                    table_changeset.push(EpochOp::DropView(entry));
+                }
                 ManifestOperation::AddRowSet(entry) => {
                     engine
                         .next_id
@@ -104,6 +179,9 @@ impl SecondaryStorage {
                 ManifestOperation::DeleteDV(entry) => {
                     dvs_to_open.remove(&(entry.table_id.table_id, entry.rowset_id, entry.dv_id));
                 }
+                ManifestOperation::Checkpoint(entry) => {
+                    checkpoint_lsn = checkpoint_lsn.max(entry.lsn);
+                }
                 ManifestOperation::Begin | ManifestOperation::End => {}
             }
         }
@@ -115,25 +193,60 @@ impl SecondaryStorage {
             dvs_to_open.len()
         );
 
+        // DVs of deleted RowSets are no longer needed
+        dvs_to_open.retain(|(table_id, rowset_id, _), _| {
+            rowsets_to_open.contains_key(&(*table_id, *rowset_id))
+        });
+
+        // RowSets of bulk loads are written to disk before commit, and only recorded in the
+        // manifest by the next checkpoint
+        let wal_records = match &engine.wal {
+            Some(wal) => wal.replay(checkpoint_lsn).await?,
+            None => vec![],
+        };
+        let logged_rowsets: HashSet<_> = (wal_records.iter())
+            .flat_map(|record| record.persisted_rowset_ids())
+            .collect();
+
         let mut changeset = vec![];
 
-        if !options.disable_all_disk_operation {
+        // files of later versions are not vacuumed
+        if !options.disable_all_disk_operation && options.manifest_version.is_none() {
             // vacuum unused RowSets
             let mut dir = fs::read_dir(&options.path).await?;
             while let Some(entry) = dir.next_entry().await? {
-                if entry.path().is_dir() {
-                    if let Some((table_id, rowset_id)) =
-                        entry.file_name().to_str().unwrap().split_once('_')
+                if !entry.path().is_dir() {
+                    continue;
+                }
+                let file_name = entry.file_name();
+                let Some((table_id, rowset_id)) =
+                    file_name.to_str().and_then(|name| name.split_once('_'))
+                else {
+                    continue;
+                };
+                if let (Ok(table_id), Ok(rowset_id)) =
+                    (table_id.parse::<u32>(), rowset_id.parse::<u32>())
+                {
+                    if !rowsets_to_open.contains_key(&(table_id, rowset_id))
+                        && !logged_rowsets.contains(&(table_id, rowset_id))
                     {
-                        if let (Ok(table_id), Ok(rowset_id)) =
-                            (table_id.parse::<u32>(), rowset_id.parse::<u32>())
-                        {
-                            if !rowsets_to_open.contains_key(&(table_id, rowset_id)) {
-                                fs::remove_dir_all(entry.path())
-                                    .await
-                                    .expect("failed to vacuum unused rowsets");
-                            }
-                        }
+                        fs::remove_dir_all(entry.path()).await?;
+                    }
+                }
+            }
+
+            // vacuum unused DVs
+            let mut dir = fs::read_dir(options.path.join("dv")).await?;
+            while let Some(entry) = dir.next_entry().await? {
+                let file_name = entry.file_name();
+                let Some(name) = file_name.to_str().and_then(|name| name.strip_suffix(".dv"))
+                else {
+                    continue;
+                };
+                let ids = name.split('_').map(|x| x.parse::<u64>().ok()).collect_vec();
+                if let [Some(table_id), Some(rowset_id), Some(dv_id)] = ids[..] {
+                    if !dvs_to_open.contains_key(&(table_id as u32, rowset_id as u32, dv_id)) {
+                        fs::remove_file(entry.path()).await?;
                     }
                 }
             }
@@ -143,11 +256,15 @@ impl SecondaryStorage {
 
         let tables = engine.tables.read().clone();
 
-        for (_, entry) in rowsets_to_open {
+        for (_, mut entry) in rowsets_to_open {
             let table = tables.get(&entry.table_id).unwrap();
+            if entry.columns.is_empty() {
+                // written before the table can be altered
+                entry.columns = table.columns.to_vec();
+            }
             let disk_rowset = DiskRowset::open(
                 table.get_rowset_path(entry.rowset_id),
-                table.columns.clone(),
+                entry.columns.clone().into(),
                 engine.block_cache.clone(),
                 entry.rowset_id,
                 options.io_backend.clone(),
@@ -167,17 +284,76 @@ impl SecondaryStorage {
             changeset.push(EpochOp::AddDV((entry, dv)));
         }
 
-        if options.disable_all_disk_operation {
+        if options.disable_all_disk_operation || options.manifest_version.is_some() {
             engine.version.commit_changes(changeset).await?;
         } else {
             // Add table changeset, so that they can be reflected in compacted manifest.
             changeset.extend(table_changeset);
+            changeset.push(EpochOp::Checkpoint(CheckpointEntry {
+                lsn: checkpoint_lsn,
+            }));
             engine
                 .version
                 .rewrite_changes(changeset, &options.path)
                 .await?;
         }
 
+        engine.recover_from_wal(wal_records).await?;
+
         Ok(engine)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    use super::*;
+    use crate::storage::SecondaryStorageOptions;
+    use crate::Database;
+
+    #[tokio::test]
+    async fn vacuum_unused_files_on_bootstrap() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let options = SecondaryStorageOptions {
+            path: tempdir.path().join("db"),
+            disable_all_disk_operation: false,
+            io_backend: IOBackend::NormalRead,
+            ..SecondaryStorageOptions::default_for_test()
+        };
+        let db = Database::new_on_disk(options.clone()).await;
+        db.run("create table t (a int); insert into t values (1), (2); delete from t where a = 1;")
+            .await
+            .unwrap();
+        db.shutdown().await.unwrap();
+
+        // files of RowSets and DVs not in the manifest, and files not written by the storage
+        let dv_dir = options.path.join("dv");
+        let unused_dv = dv_dir.join("1000_0_0.dv");
+        let unused_rowset = options.path.join("1000_0");
+        let unknown = [
+            dv_dir.join("unknown.dv"),
+            dv_dir.join(OsStr::from_bytes(b"\xff_0_0.dv")),
+            options.path.join(OsStr::from_bytes(b"\xff_0")),
+        ];
+        std::fs::write(&unused_dv, b"").unwrap();
+        std::fs::create_dir(&unused_rowset).unwrap();
+        std::fs::write(&unknown[0], b"").unwrap();
+        std::fs::write(&unknown[1], b"").unwrap();
+        std::fs::create_dir(&unknown[2]).unwrap();
+
+        let db = Database::new_on_disk(options).await;
+        assert!(!unused_dv.exists());
+        assert!(!unused_rowset.exists());
+        assert!(unknown.iter().all(|path| path.exists()));
+        // the DV of the deleted row is kept
+        let chunks = db.run("select count(*) from t").await.unwrap();
+        let count = chunks[0]
+            .get_first_data_chunk()
+            .array_at(0)
+            .get_to_string(0);
+        assert_eq!(count, "1");
+        db.shutdown().await.unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU32, AtomicU64};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use moka::future::Cache;
@@ -9,7 +9,7 @@ use tokio::sync::OwnedMutexGuard;
 
 use super::*;
 use crate::catalog::TableRefId;
-use crate::storage::Table;
+use crate::storage::{AsOf, Table};
 
 /// A table in Secondary engine.
 ///
@@ -46,6 +46,18 @@ pub struct SecondaryTable {
 
     /// Next RowSet Id and DV Id of the current storage engine
     next_id: Arc<(AtomicU32, AtomicU64)>,
+
+    /// Bumped after each write to the table is committed.
+    data_version: Arc<AtomicU64>,
+
+    /// Statistics of bloom filter checks in scans of the table.
+    bloom_filter_stats: Arc<BloomFilterStats>,
+
+    /// The write-ahead log of the storage, if enabled.
+    pub wal: Option<Arc<Wal>>,
+
+    /// Indexes on the table, which are updated after each write is committed.
+    pub(super) indexes: Arc<RwLock<Vec<Arc<TableIndex>>>>,
 }
 
 impl SecondaryTable {
@@ -59,6 +71,7 @@ impl SecondaryTable {
         block_cache: Cache<BlockCacheKey, Block>,
         txn_mgr: Arc<TransactionManager>,
         ordered_pk_ids: Vec<ColumnId>,
+        wal: Option<Arc<Wal>>,
     ) -> Self {
         Self {
             columns: columns.into(),
@@ -74,9 +87,49 @@ impl SecondaryTable {
             block_cache,
             txn_mgr,
             ordered_pk_ids,
+            data_version: Arc::new(AtomicU64::new(0)),
+            bloom_filter_stats: Arc::new(BloomFilterStats::default()),
+            wal,
+            indexes: Arc::new(RwLock::new(vec![])),
         }
     }
 
+    /// Replaces the columns of the table after it is altered.
+    pub(super) fn set_columns(&mut self, columns: &[ColumnCatalog]) {
+        self.columns = columns.into();
+        self.column_map = columns
+            .iter()
+            .enumerate()
+            .map(|(idx, col)| (col.id(), idx))
+            .collect();
+        self.bump_data_version();
+    }
+
+    /// Marks that the data of the table has been modified.
+    pub(super) fn bump_data_version(&self) {
+        self.data_version
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Records the result of checking bloom filters of a rowset.
+    pub(super) fn record_bloom_filter_check(&self, pruned: bool) {
+        let stats = &self.bloom_filter_stats;
+        stats.checked.fetch_add(1, Ordering::Relaxed);
+        if pruned {
+            stats.pruned.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of rowsets whose bloom filters are checked, and the number of rowsets
+    /// skipped by bloom filters.
+    pub fn bloom_filter_stats(&self) -> (u64, u64) {
+        let stats = &self.bloom_filter_stats;
+        (
+            stats.checked.load(Ordering::Relaxed),
+            stats.pruned.load(Ordering::Relaxed),
+        )
+    }
+
     pub fn generate_rowset_id(&self) -> u32 {
         self.next_id
             .0
@@ -129,11 +182,23 @@ impl Table for SecondaryTable {
         SecondaryTransaction::start(self, true, false).await
     }
 
+    async fn read_as_of(&self, as_of: AsOf) -> StorageResult<SecondaryTransaction> {
+        SecondaryTransaction::start_as_of(self, as_of)
+    }
+
     async fn update(&self) -> StorageResult<SecondaryTransaction> {
         SecondaryTransaction::start(self, false, true).await
     }
 
+    async fn read_pinned(&self) -> StorageResult<SecondaryTransaction> {
+        Ok(SecondaryTransaction::start_pinned(self))
+    }
+
     fn ordered_pk_ids(&self) -> Vec<ColumnId> {
         self.ordered_pk_ids.clone()
     }
+
+    fn data_version(&self) -> u64 {
+        self.data_version.load(std::sync::atomic::Ordering::SeqCst)
+    }
 }
@@ -3,7 +3,9 @@
 use std::collections::HashMap;
 use std::ops::Bound;
 use std::sync::Arc;
+use std::time::Instant;
 
+use bytes::Bytes;
 use itertools::Itertools;
 use risinglight_proto::rowset::block_statistics::BlockStatisticsType;
 use risinglight_proto::rowset::DeleteRecord;
@@ -11,16 +13,20 @@ use tokio::sync::OwnedMutexGuard;
 use tracing::{info, warn};
 
 use super::version_manager::{Snapshot, Version, VersionManager};
+use super::wal::{PersistedRowset, WalDv, WalRowset};
 use super::{
-    AddDVEntry, AddRowSetEntry, ColumnBuilderOptions, ConcatIterator, DeleteVector, DiskRowset,
-    EpochOp, MergeIterator, RowSetIterator, SecondaryMemRowsetImpl, SecondaryRowHandler,
-    SecondaryTable, SecondaryTableTxnIterator,
+    AddDVEntry, AddRowSetEntry, ColumnBuilderOptions, ColumnSeekPosition, CompactionPin,
+    ConcatIterator, DeleteVector, DiskRowset, EpochOp, IOBackend, MergeIterator, RowSetIterator,
+    SecondaryMemRowsetImpl, SecondaryRowHandler, SecondaryTable, SecondaryTableTxnIterator,
 };
 use crate::array::DataChunk;
 use crate::catalog::find_sort_key_id;
 use crate::storage::secondary::statistics::create_statistics_global_aggregator;
-use crate::storage::{ScanOptions, StorageColumnRef, StorageResult, Transaction};
+use crate::storage::{
+    AsOf, ScanOptions, StorageColumnRef, StorageResult, TableIndex, TracedStorageError, Transaction,
+};
 use crate::types::DataValue;
+use crate::utils::metrics;
 
 /// A transaction running on `SecondaryStorage`.
 pub struct SecondaryTransaction {
@@ -47,8 +53,18 @@ pub struct SecondaryTransaction {
     /// The rowsets produced in the txn.
     to_be_committed_rowsets: Vec<DiskRowset>,
 
+    /// The rowsets produced in the txn, if they are committed to the write-ahead log.
+    wal_rowsets: Vec<WalRowset>,
+
+    /// The full rowsets written to disk before commit, if the txn commits to the write-ahead log.
+    persisted_rowsets: Vec<PersistedRowset>,
+
     delete_lock: Option<OwnedMutexGuard<()>>,
 
+    /// Keeps the RowSets of the snapshot from being compacted, if rows are to be deleted by a txn
+    /// started later.
+    _compaction_pin: Option<CompactionPin>,
+
     read_only: bool,
 
     /// Total size of written data in the current txn
@@ -60,6 +76,40 @@ pub struct SecondaryTransaction {
     _pin_version: Arc<Version>,
 }
 
+/// The changes of a txn to commit.
+#[derive(Default)]
+struct CommitChanges {
+    changeset: Vec<EpochOp>,
+    wal_rowsets: Vec<WalRowset>,
+    wal_dvs: Vec<WalDv>,
+    persisted_rowsets: Vec<PersistedRowset>,
+}
+
+/// The rows committed by a txn, which are added to or removed from the indexes of the table.
+#[derive(Default)]
+struct IndexChanges {
+    rowset_ids: Vec<u32>,
+    deleted: Vec<i64>,
+}
+
+impl IndexChanges {
+    fn new(changeset: &[EpochOp]) -> Self {
+        let mut changes = Self::default();
+        for op in changeset {
+            match op {
+                EpochOp::AddRowSet((entry, _)) => changes.rowset_ids.push(entry.rowset_id),
+                EpochOp::AddDV((entry, dv)) => changes.deleted.extend(
+                    dv.records()
+                        .iter()
+                        .map(|record| SecondaryRowHandler(entry.rowset_id, record.row_id).as_i64()),
+                ),
+                _ => {}
+            }
+        }
+        changes
+    }
+}
+
 impl SecondaryTransaction {
     /// Start a transaction on Secondary. If `update` is set to true, we will hold the delete lock
     /// of a table.
@@ -68,57 +118,125 @@ impl SecondaryTransaction {
         read_only: bool,
         update: bool,
     ) -> StorageResult<Self> {
-        // pin a snapshot at version manager
+        let delete_lock = if update {
+            Some(table.lock_for_deletion().await)
+        } else {
+            None
+        };
+        // Pin a snapshot at version manager. For updates, this happens after taking the lock, so
+        // that rows to delete are never in RowSets compacted after the snapshot.
         let pin_version = table.version.pin();
-        Ok(Self {
+        Ok(Self::with_version(
+            table,
+            read_only,
+            delete_lock,
+            pin_version,
+        ))
+    }
+
+    /// Start a read-only transaction, whose rows can be deleted by an update transaction started
+    /// later. The table is not compacted in background until the transaction ends.
+    pub(super) fn start_pinned(table: &SecondaryTable) -> Self {
+        let compaction_pin = table.txn_mgr.pin(table.table_id());
+        let mut txn = Self::with_version(table, true, None, table.version.pin());
+        txn._compaction_pin = Some(compaction_pin);
+        txn
+    }
+
+    /// Start a read-only transaction on a historical snapshot of the table.
+    pub(super) fn start_as_of(table: &SecondaryTable, as_of: AsOf) -> StorageResult<Self> {
+        let pin_version = table.version.pin_at(as_of)?;
+        Ok(Self::with_version(table, true, None, pin_version))
+    }
+
+    fn with_version(
+        table: &SecondaryTable,
+        read_only: bool,
+        delete_lock: Option<OwnedMutexGuard<()>>,
+        pin_version: Arc<Version>,
+    ) -> Self {
+        Self {
             finished: false,
             mem: None,
             delete_buffer: vec![],
             table: table.clone(),
             version: table.version.clone(),
             snapshot: pin_version.snapshot.clone(),
-            delete_lock: if update {
-                Some(table.lock_for_deletion().await)
-            } else {
-                None
-            },
+            delete_lock,
+            _compaction_pin: None,
             to_be_committed_rowsets: vec![],
+            wal_rowsets: vec![],
+            persisted_rowsets: vec![],
             read_only,
             total_size: 0,
             _pin_version: pin_version,
-        })
+        }
     }
 
-    async fn flush_rowset(&mut self) -> StorageResult<()> {
+    /// Flushes the memtable to a rowset.
+    ///
+    /// If the rowset is `full`, it is written to disk even with the write-ahead log, so that large
+    /// transactions don't keep all their data in memory until commit.
+    async fn flush_rowset(&mut self, full: bool) -> StorageResult<()> {
         // only flush when we have memtables
         let mem = if let Some(mem) = self.mem.take() {
             mem
         } else {
             return Ok(());
         };
+        let start = Instant::now();
         let rowset_id = mem.get_rowset_id();
         let directory = self.table.get_rowset_path(rowset_id);
 
-        // flush data to disk
-        mem.flush(self.table.storage_options.io_backend.clone(), &directory)
-            .await?;
+        // flush data to disk, or to memory if the rowset is committed to the write-ahead log
+        let in_wal = self.table.wal.is_some() && !full;
+        let io_backend = if in_wal {
+            IOBackend::in_memory()
+        } else {
+            self.table.storage_options.io_backend.clone()
+        };
+        // the directory is only created in `append_inner` without the write-ahead log
+        if self.table.wal.is_some()
+            && !in_wal
+            && !self.table.storage_options.disable_all_disk_operation
+        {
+            tokio::fs::create_dir(&directory).await?;
+        }
+        mem.flush(io_backend.clone(), &directory).await?;
+        if in_wal {
+            self.wal_rowsets.push(WalRowset::new(
+                self.table.table_ref_id,
+                rowset_id,
+                self.table.columns.clone(),
+                &io_backend,
+            ));
+        } else if self.table.wal.is_some() {
+            self.persisted_rowsets.push(PersistedRowset {
+                table_id: self.table.table_ref_id,
+                rowset_id,
+                columns: self.table.columns.clone(),
+            });
+        }
 
         let on_disk = DiskRowset::open(
             directory,
             self.table.columns.clone(),
             self.table.block_cache.clone(),
             rowset_id,
-            self.table.storage_options.io_backend.clone(),
+            io_backend,
         )
         .await?;
 
         self.to_be_committed_rowsets.push(on_disk);
+        metrics::FLUSHES.inc();
+        metrics::FLUSH_DURATION.observe(start.elapsed());
 
         Ok(())
     }
 
-    async fn commit_inner(mut self) -> StorageResult<()> {
-        self.flush_rowset().await?;
+    /// Flushes the writes of the txn, and returns the changes to commit.
+    async fn prepare(&mut self) -> StorageResult<CommitChanges> {
+        self.flush_rowset(false).await?;
 
         // flush deletes to disk
         let mut delete_split_map = HashMap::new();
@@ -134,28 +252,22 @@ impl SecondaryTransaction {
         let rowsets = std::mem::take(&mut self.to_be_committed_rowsets);
 
         let mut dvs = vec![];
+        let mut wal_dvs = vec![];
         for (rowset_id, deletes) in delete_split_map {
             let dv_id = self.table.generate_dv_id();
-            use bytes::Bytes;
-
-            use super::IOBackend;
             let path = self.table.get_dv_path(rowset_id, dv_id);
-            match &self.table.storage_options.io_backend {
-                IOBackend::InMemory(map) => {
-                    let mut buf = vec![];
-                    DeleteVector::write_all(&mut buf, &deletes).await?;
-                    let mut guard = map.lock();
-                    guard.insert(path, Bytes::from(buf));
-                }
-                _ => {
-                    let mut file = tokio::fs::OpenOptions::default()
-                        .write(true)
-                        .create_new(true)
-                        .open(path)
-                        .await?;
-                    DeleteVector::write_all(&mut file, &deletes).await?;
-                    file.sync_data().await?;
-                }
+            if self.table.wal.is_some() {
+                let mut buf = vec![];
+                DeleteVector::write_all(&mut buf, &deletes).await?;
+                wal_dvs.push(WalDv {
+                    table_id: self.table.table_ref_id,
+                    rowset_id,
+                    dv_id,
+                    data: Bytes::from(buf),
+                });
+            } else {
+                let io_backend = &self.table.storage_options.io_backend;
+                DeleteVector::write_file(path, io_backend, &deletes).await?;
             }
             dvs.push(DeleteVector::new(dv_id, rowset_id, deletes));
         }
@@ -191,6 +303,7 @@ impl SecondaryTransaction {
                 AddRowSetEntry {
                     rowset_id: x.rowset_id(),
                     table_id: self.table.table_ref_id,
+                    columns: x.column_infos().to_vec(),
                 },
                 x,
             ))
@@ -208,11 +321,123 @@ impl SecondaryTransaction {
             ))
         }));
 
+        Ok(CommitChanges {
+            changeset,
+            wal_rowsets: std::mem::take(&mut self.wal_rowsets),
+            wal_dvs,
+            persisted_rowsets: std::mem::take(&mut self.persisted_rowsets),
+        })
+    }
+
+    async fn commit_inner(self) -> StorageResult<()> {
+        Self::commit_all_inner(vec![self]).await
+    }
+
+    /// Commits txns on tables of the same storage in one epoch of the version manager, or in one
+    /// record of the write-ahead log.
+    async fn commit_all_inner(mut txns: Vec<Self>) -> StorageResult<()> {
+        let Some(first) = txns.first() else {
+            return Ok(());
+        };
+        let version = first.version.clone();
+        let wal = first.table.wal.clone();
+
+        let mut changes = CommitChanges::default();
+        let mut index_changes = vec![];
+        for txn in &mut txns {
+            let c = txn.prepare().await?;
+            index_changes.push(IndexChanges::new(&c.changeset));
+            changes.changeset.extend(c.changeset);
+            changes.wal_rowsets.extend(c.wal_rowsets);
+            changes.wal_dvs.extend(c.wal_dvs);
+            changes.persisted_rowsets.extend(c.persisted_rowsets);
+        }
+
         // Commit changeset
-        self.version.commit_changes(changeset).await?;
+        match &wal {
+            // read-only txns do not write to the log
+            Some(_) if changes.changeset.is_empty() => {}
+            Some(wal) => {
+                (wal.commit(
+                    changes.wal_rowsets,
+                    changes.wal_dvs,
+                    changes.persisted_rowsets,
+                    &version,
+                    changes.changeset,
+                ))
+                .await?;
+            }
+            None => {
+                version.commit_changes(changes.changeset).await?;
+            }
+        }
+        for txn in &mut txns {
+            txn.table.bump_data_version();
+            txn.finished = true;
+        }
+        for (txn, changes) in txns.iter().zip(index_changes) {
+            txn.update_indexes(changes).await;
+        }
 
-        self.finished = true;
+        Ok(())
+    }
+
+    /// Adds the rows committed by the txn to the indexes of the table, and removes the deleted
+    /// rows from them.
+    async fn update_indexes(&self, changes: IndexChanges) {
+        let indexes = self.table.indexes.read().clone();
+        if indexes.is_empty() {
+            return;
+        }
+        // the new rowsets may have been compacted since the commit, and then the indexes have
+        // been rebuilt
+        let version = self.version.pin();
+        let table_id = self.table.table_id();
+        let rowsets = match version.snapshot.get_rowsets_of(table_id) {
+            Some(ids) => (changes.rowset_ids.iter())
+                .filter(|id| ids.contains(*id))
+                .map(|id| self.version.get_rowset(table_id, *id))
+                .collect_vec(),
+            None => vec![],
+        };
+        for index in indexes {
+            index.delete(changes.deleted.iter().copied());
+            if let Err(e) = self.insert_into_index(&index, &rowsets).await {
+                // the entries are rebuilt by the next lookup
+                warn!("failed to update index: {}", e);
+                index.reset();
+            }
+        }
+    }
 
+    /// Adds the rows of the rowsets to the index.
+    async fn insert_into_index(
+        &self,
+        index: &TableIndex,
+        rowsets: &[Arc<DiskRowset>],
+    ) -> StorageResult<()> {
+        let column_refs: Arc<[StorageColumnRef]> = (index.key_positions(&self.table.columns)?)
+            .into_iter()
+            .map(|idx| StorageColumnRef::Idx(idx as u32))
+            .chain([StorageColumnRef::RowHandler])
+            .collect();
+        for rowset in rowsets {
+            let mut iter = rowset
+                .iter_with_schema(
+                    &self.table.columns,
+                    column_refs.clone(),
+                    vec![],
+                    ColumnSeekPosition::start(),
+                    None,
+                    &[],
+                    0,
+                    None,
+                )
+                .await?;
+            while let Some(chunk) = iter.next_batch(None).await? {
+                index.insert(&chunk.to_data_chunk());
+            }
+        }
         Ok(())
     }
 
@@ -227,7 +452,39 @@ impl SecondaryTransaction {
 
         if let Some(rowsets) = self.snapshot.get_rowsets_of(self.table.table_id()) {
             for rowset_id in rowsets {
+                if let Some((index, count)) = opts.partition
+                    && *rowset_id as usize % count != index
+                {
+                    continue;
+                }
+                // select the rows of the rowset, whose handlers are in the range of the rowset id
+                let row_ids = match &opts.rows {
+                    Some(rows) => {
+                        let handler = |row_id| SecondaryRowHandler(*rowset_id, row_id).as_i64();
+                        let start = rows.partition_point(|h| *h < handler(0));
+                        let end = rows.partition_point(|h| *h <= handler(u32::MAX));
+                        if start == end {
+                            continue;
+                        }
+                        let row_ids = (rows[start..end].iter())
+                            .map(|h| SecondaryRowHandler::from(*h).row_id())
+                            .collect_vec();
+                        Some(row_ids)
+                    }
+                    None => None,
+                };
                 let rowset = self.version.get_rowset(self.table.table_id(), *rowset_id);
+                if rowset.is_pruned(&self.table.columns, &opts.zone_filters) {
+                    continue;
+                }
+                if let Some(pruned) =
+                    rowset.check_bloom_filters(&self.table.columns, &opts.zone_filters)
+                {
+                    self.table.record_bloom_filter_check(pruned);
+                    if pruned {
+                        continue;
+                    }
+                }
 
                 // Get DV id and read DVs
                 let dvs = self
@@ -250,7 +507,16 @@ impl SecondaryTransaction {
                 let start_rowid = rowset.start_rowid(begin_keys).await;
                 iters.push(
                     rowset
-                        .iter(col_idx.into(), dvs, start_rowid, opts.filter.clone())
+                        .iter_with_schema(
+                            &self.table.columns,
+                            col_idx.into(),
+                            dvs,
+                            start_rowid,
+                            opts.filter.clone(),
+                            &opts.zone_filters,
+                            opts.prefetch_blocks,
+                            row_ids.as_deref(),
+                        )
                         .await?,
                 )
             }
@@ -309,8 +575,11 @@ impl SecondaryTransaction {
                         StorageColumnRef::Idx(idx) => idx,
                         _ => panic!("unsupported column ref for block aggregation"),
                     };
-                    let column = rowset.column(*user_col_idx as usize);
-                    agg.apply_batch(column.index());
+                    let column_id = self.table.columns[*user_col_idx as usize].id();
+                    // skip rowsets written before the column is added
+                    if let Some(storage_idx) = rowset.storage_column_id(column_id) {
+                        agg.apply_batch(rowset.column(storage_idx).index());
+                    }
                 }
             }
         }
@@ -326,7 +595,7 @@ impl SecondaryTransaction {
             let rowset_id = self.table.generate_rowset_id();
             let directory = self.table.get_rowset_path(rowset_id);
 
-            if !self.table.storage_options.disable_all_disk_operation {
+            if !self.table.storage_options.disable_all_disk_operation && self.table.wal.is_none() {
                 tokio::fs::create_dir(&directory).await?;
             }
 
@@ -344,7 +613,7 @@ impl SecondaryTransaction {
                 warn!("DataChunk is too big, target_row_size exceed 2x limit.")
             }
             self.total_size = 0;
-            self.flush_rowset().await?;
+            self.flush_rowset(true).await?;
         }
         Ok(())
     }
@@ -372,6 +641,15 @@ impl Transaction for SecondaryTransaction {
             self.delete_lock.is_some(),
             "delete lock is not held for this txn"
         );
+        // rows of RowSets compacted after the snapshot of the row can not be deleted
+        if !(self.snapshot.get_rowsets_of(self.table.table_id()))
+            .is_some_and(|ids| ids.contains(&id.rowset_id()))
+        {
+            return Err(TracedStorageError::conflict(format!(
+                "RowSet {} of the row to delete is compacted",
+                id.rowset_id()
+            )));
+        }
         self.delete_buffer.push(*id);
         Ok(())
     }
@@ -380,6 +658,10 @@ impl Transaction for SecondaryTransaction {
         self.commit_inner().await
     }
 
+    async fn commit_all(txns: Vec<Self>) -> StorageResult<()> {
+        Self::commit_all_inner(txns).await
+    }
+
     async fn abort(mut self) -> StorageResult<()> {
         self.finished = true;
         Ok(())
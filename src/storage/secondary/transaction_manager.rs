@@ -19,6 +19,27 @@ use tokio::sync::{Mutex, OwnedMutexGuard};
 pub struct TransactionManager {
     /// A single big lock for each table
     lock_map: PLMutex<HashMap<u32, Arc<Mutex<()>>>>,
+
+    /// The number of [`CompactionPin`]s of each table.
+    pins: PLMutex<HashMap<u32, usize>>,
+}
+
+/// Keeps a table from being compacted in background until dropped, so that rows read from a
+/// snapshot can be deleted by a txn started later.
+pub struct CompactionPin {
+    txn_mgr: Arc<TransactionManager>,
+    table: u32,
+}
+
+impl Drop for CompactionPin {
+    fn drop(&mut self) {
+        let mut pins = self.txn_mgr.pins.lock();
+        let count = pins.get_mut(&self.table).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            pins.remove(&self.table);
+        }
+    }
 }
 
 impl TransactionManager {
@@ -30,16 +51,29 @@ impl TransactionManager {
             .clone()
     }
 
-    /// Get a lock for compaction, return immediately
+    /// Get a lock for compaction, return immediately. Returns `None` if the table is pinned.
     pub fn try_lock_for_compaction(&self, table: u32) -> Option<OwnedMutexGuard<()>> {
         let mutex = self.get_lock_for_table(table);
         if let Ok(guard) = mutex.try_lock_owned() {
+            // check after taking the lock, so that snapshots pinned before are not compacted
+            if self.pins.lock().contains_key(&table) {
+                return None;
+            }
             Some(guard)
         } else {
             None
         }
     }
 
+    /// Keep the table from being compacted in background until the pin is dropped.
+    pub fn pin(self: &Arc<Self>, table: u32) -> CompactionPin {
+        *self.pins.lock().entry(table).or_default() += 1;
+        CompactionPin {
+            txn_mgr: self.clone(),
+            table,
+        }
+    }
+
     async fn lock(&self, table: u32) -> OwnedMutexGuard<()> {
         let mutex = self.get_lock_for_table(table);
         mutex.lock_owned().await
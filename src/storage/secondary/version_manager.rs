@@ -1,8 +1,9 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use futures::lock::Mutex;
 use parking_lot::Mutex as PLMutex;
@@ -11,17 +12,30 @@ use tokio::select;
 use tracing::{info, warn};
 
 use super::manifest::*;
-use super::{DeleteVector, DiskRowset, StorageOptions, StorageResult, MANIFEST_FILE_NAME};
+use super::{
+    DeleteVector, DiskRowset, IOBackend, StorageOptions, StorageResult, TracedStorageError,
+    MANIFEST_FILE_NAME,
+};
+use crate::storage::AsOf;
 
 /// The operations sent to the version manager. Compared with manifest entries, operations
 /// like `AddRowSet` needs to be associated with a `DiskRowSet` struct.
 pub enum EpochOp {
     CreateTable(CreateTableEntry),
     DropTable(DropTableEntry),
+    AddColumn(AddColumnEntry),
+    DropColumn(DropColumnEntry),
+    CreateIndex(CreateIndexEntry),
+    DropIndex(DropIndexEntry),
+    CreateSchema(CreateSchemaEntry),
+    DropSchema(DropSchemaEntry),
+    CreateView(CreateViewEntry),
+    DropView(DropViewEntry),
     AddRowSet((AddRowSetEntry, DiskRowset)),
     DeleteRowSet(DeleteRowsetEntry),
     AddDV((AddDVEntry, DeleteVector)),
     DeleteDV(DeleteDVEntry),
+    Checkpoint(CheckpointEntry),
 }
 
 impl std::fmt::Debug for EpochOp {
@@ -29,10 +43,19 @@ impl std::fmt::Debug for EpochOp {
         match self {
             Self::CreateTable(e) => f.debug_tuple("EpochOp::CreateTable").field(e).finish(),
             Self::DropTable(e) => f.debug_tuple("EpochOp::DropTable").field(e).finish(),
+            Self::AddColumn(e) => f.debug_tuple("EpochOp::AddColumn").field(e).finish(),
+            Self::DropColumn(e) => f.debug_tuple("EpochOp::DropColumn").field(e).finish(),
+            Self::CreateIndex(e) => f.debug_tuple("EpochOp::CreateIndex").field(e).finish(),
+            Self::DropIndex(e) => f.debug_tuple("EpochOp::DropIndex").field(e).finish(),
+            Self::CreateSchema(e) => f.debug_tuple("EpochOp::CreateSchema").field(e).finish(),
+            Self::DropSchema(e) => f.debug_tuple("EpochOp::DropSchema").field(e).finish(),
+            Self::CreateView(e) => f.debug_tuple("EpochOp::CreateView").field(e).finish(),
+            Self::DropView(e) => f.debug_tuple("EpochOp::DropView").field(e).finish(),
             Self::AddRowSet((e, _)) => f.debug_tuple("EpochOp::AddRowSet").field(e).finish(),
             Self::DeleteRowSet(e) => f.debug_tuple("EpochOp::DeleteRowSet").field(e).finish(),
             Self::AddDV((e, _)) => f.debug_tuple("EpochOp::AddDV").field(e).finish(),
             Self::DeleteDV(e) => f.debug_tuple("EpochOp::DeleteDV").field(e).finish(),
+            Self::Checkpoint(e) => f.debug_tuple("EpochOp::Checkpoint").field(e).finish(),
         }
     }
 }
@@ -106,6 +129,9 @@ pub struct VersionManagerInner {
     /// MVCC structure for this, and only record changes compared with last epoch.
     status: HashMap<u64, Arc<Snapshot>>,
 
+    /// Commit time of each epoch in `status`, in microseconds since the Unix epoch.
+    commit_times: BTreeMap<u64, i64>,
+
     /// (TableId, RowSetId) -> Object mapping
     rowsets: HashMap<(u32, u32), Arc<DiskRowset>>,
 
@@ -118,6 +144,9 @@ pub struct VersionManagerInner {
     /// Deletion to apply in each epoch.
     rowset_deletion_to_apply: HashMap<u64, Vec<(u32, u32)>>,
 
+    /// Deletion of (TableId, RowSetId, DVId) to apply in each epoch.
+    dv_deletion_to_apply: HashMap<u64, Vec<(u32, u32, u64)>>,
+
     /// Current epoch number.
     epoch: u64,
 }
@@ -201,7 +230,7 @@ impl VersionManager {
         // Write to tempfile
         let epoch = {
             let mut temp_manifest = Manifest::open(&temp_manifest_path, true).await?;
-            self.commit_changes_with_custom_manifest(ops, &mut temp_manifest)
+            self.commit_changes_with_custom_manifest(ops, &mut temp_manifest, true)
                 .await?
         };
         // Rename this tempfile to manifest
@@ -216,7 +245,16 @@ impl VersionManager {
         // Hold the manifest lock so that no one else could commit changes.
         let mut manifest = self.manifest.lock().await;
 
-        self.commit_changes_with_custom_manifest(ops, &mut manifest)
+        self.commit_changes_with_custom_manifest(ops, &mut manifest, true)
+            .await
+    }
+
+    /// Apply changes without persisting them to the manifest, and return a new epoch number.
+    /// This is used for changes recorded in the write-ahead log.
+    pub async fn apply_changes(&self, ops: Vec<EpochOp>) -> StorageResult<u64> {
+        let mut manifest = self.manifest.lock().await;
+
+        self.commit_changes_with_custom_manifest(ops, &mut manifest, false)
             .await
     }
 
@@ -224,11 +262,13 @@ impl VersionManager {
         &self,
         ops: Vec<EpochOp>,
         manifest: &mut Manifest,
+        persist: bool,
     ) -> StorageResult<u64> {
         let mut snapshot;
         let mut entries;
         let current_epoch;
         let mut rowset_deletion_to_apply = vec![];
+        let mut dv_deletion_to_apply = vec![];
 
         {
             // Hold the inner lock, so as to apply the changes to the current status, and add new
@@ -258,10 +298,32 @@ impl VersionManager {
                         entries.push(ManifestOperation::CreateTable(entry))
                     }
                     EpochOp::DropTable(entry) => entries.push(ManifestOperation::DropTable(entry)),
+                    EpochOp::AddColumn(entry) => entries.push(ManifestOperation::AddColumn(entry)),
+                    EpochOp::DropColumn(entry) => {
+                        entries.push(ManifestOperation::DropColumn(entry))
+                    }
+                    EpochOp::CreateIndex(entry) => {
+                        entries.push(ManifestOperation::CreateIndex(entry))
+                    }
+                    EpochOp::DropIndex(entry) => entries.push(ManifestOperation::DropIndex(entry)),
+                    EpochOp::CreateSchema(entry) => {
+                        entries.push(ManifestOperation::CreateSchema(entry))
+                    }
+                    EpochOp::DropSchema(entry) => {
+                        entries.push(ManifestOperation::DropSchema(entry))
+                    }
+                    EpochOp::CreateView(entry) => {
+                        entries.push(ManifestOperation::CreateView(entry))
+                    }
+                    EpochOp::DropView(entry) => entries.push(ManifestOperation::DropView(entry)),
+                    EpochOp::Checkpoint(entry) => {
+                        entries.push(ManifestOperation::Checkpoint(entry))
+                    }
 
                     // For other operations, maintain the snapshot in version manager
                     EpochOp::AddRowSet((entry, rowset)) => {
-                        // record the rowset into the pool
+                        // record the rowset into the pool, replacing the in-memory one of the
+                        // same id after a checkpoint
                         inner
                             .rowsets
                             .insert((entry.table_id.table_id, entry.rowset_id), Arc::new(rowset));
@@ -284,7 +346,11 @@ impl VersionManager {
                         entries.push(ManifestOperation::AddDV(entry));
                     }
                     EpochOp::DeleteDV(entry) => {
-                        // TODO: record delete op and apply it later
+                        dv_deletion_to_apply.push((
+                            entry.table_id.table_id,
+                            entry.rowset_id,
+                            entry.dv_id,
+                        ));
                         snapshot.delete_dv(entry.table_id.table_id, entry.rowset_id, entry.dv_id);
                         entries.push(ManifestOperation::DeleteDV(entry));
                     }
@@ -293,7 +359,9 @@ impl VersionManager {
         }
 
         // Persist the change onto the disk.
-        manifest.append(&entries).await?;
+        if persist {
+            manifest.append(&entries).await?;
+        }
 
         // Add epoch number and make the modified snapshot available.
         let mut inner = self.inner.lock();
@@ -301,9 +369,13 @@ impl VersionManager {
         inner.epoch += 1;
         let epoch = inner.epoch;
         inner.status.insert(epoch, Arc::new(snapshot));
+        inner.commit_times.insert(epoch, now_micros());
         inner
             .rowset_deletion_to_apply
             .insert(epoch, rowset_deletion_to_apply);
+        inner
+            .dv_deletion_to_apply
+            .insert(epoch, dv_deletion_to_apply);
 
         Ok(epoch)
     }
@@ -312,15 +384,51 @@ impl VersionManager {
     pub fn pin(&self) -> Arc<Version> {
         let mut inner = self.inner.lock();
         let epoch = inner.epoch;
+        let snapshot = inner.status.get(&epoch).unwrap().clone();
+        self.pin_epoch(&mut inner, epoch, snapshot)
+    }
+
+    /// Pin the snapshot of a historical version for time travel queries. Returns an error if the
+    /// snapshot has been vacuumed or not committed yet.
+    pub fn pin_at(&self, as_of: AsOf) -> StorageResult<Arc<Version>> {
+        let mut inner = self.inner.lock();
+        let epoch = match as_of {
+            AsOf::Epoch(epoch) => Some(epoch),
+            AsOf::Timestamp(time) => (inner.commit_times.iter())
+                .take_while(|(_, commit_time)| **commit_time <= time)
+                .last()
+                .map(|(epoch, _)| *epoch),
+        };
+        let Some((epoch, snapshot)) =
+            epoch.and_then(|epoch| Some((epoch, inner.status.get(&epoch)?.clone())))
+        else {
+            return Err(TracedStorageError::not_found("snapshot", as_of));
+        };
+        Ok(self.pin_epoch(&mut inner, epoch, snapshot))
+    }
+
+    fn pin_epoch(
+        &self,
+        inner: &mut VersionManagerInner,
+        epoch: u64,
+        snapshot: Arc<Snapshot>,
+    ) -> Arc<Version> {
         *inner.ref_cnt.entry(epoch).or_default() += 1;
         Arc::new(Version {
             epoch,
-            snapshot: inner.status.get(&epoch).unwrap().clone(),
+            snapshot,
             inner: self.inner.clone(),
             tx: self.tx.clone(),
         })
     }
 
+    /// Returns the epochs of the snapshots kept in memory and their commit times, in microseconds
+    /// since the Unix epoch.
+    pub fn snapshots(&self) -> Vec<(u64, i64)> {
+        let inner = self.inner.lock();
+        inner.commit_times.iter().map(|(e, t)| (*e, *t)).collect()
+    }
+
     pub fn get_rowset(&self, table_id: u32, rowset_id: u32) -> Arc<DiskRowset> {
         let inner = self.inner.lock();
         inner.rowsets.get(&(table_id, rowset_id)).unwrap().clone()
@@ -331,12 +439,27 @@ impl VersionManager {
         inner.dvs.get(&(table_id, dv_id)).unwrap().clone()
     }
 
-    pub async fn find_vacuum(self: &Arc<Self>) -> StorageResult<Vec<(u32, u32)>> {
+    /// Find the RowSets and DVs that are no longer visible to any pinned epoch or any snapshot in
+    /// the retention period, and remove them from the pool. Snapshots of epochs before them are
+    /// dropped as well.
+    pub async fn find_vacuum(self: &Arc<Self>) -> StorageResult<Vacuum> {
         let mut inner = self.inner.lock();
         let min_pinned_epoch = inner.ref_cnt.keys().min().cloned();
 
         // If there is no pinned epoch, all deletions can be applied.
-        let vacuum_epoch = min_pinned_epoch.unwrap_or(inner.epoch);
+        let mut vacuum_epoch = min_pinned_epoch.unwrap_or(inner.epoch);
+
+        // Keep the snapshots committed in the retention period, and the one visible at its start.
+        let retention = self.storage_options.snapshot_retention;
+        if !retention.is_zero() {
+            let deadline = now_micros() - retention.as_micros() as i64;
+            let retained_epoch = (inner.commit_times.iter())
+                .take_while(|(_, commit_time)| **commit_time <= deadline)
+                .last()
+                .or_else(|| inner.commit_times.first_key_value())
+                .map_or(vacuum_epoch, |(epoch, _)| *epoch);
+            vacuum_epoch = vacuum_epoch.min(retained_epoch);
+        }
 
         let can_apply = |epoch, vacuum_epoch| epoch <= vacuum_epoch;
 
@@ -350,6 +473,18 @@ impl VersionManager {
         inner
             .rowset_deletion_to_apply
             .retain(|k, _| !can_apply(*k, vacuum_epoch));
+        let mut dv_deletions = vec![];
+        for (epoch, deletion) in &inner.dv_deletion_to_apply {
+            if can_apply(*epoch, vacuum_epoch) {
+                dv_deletions.extend(deletion.iter().cloned());
+            }
+        }
+        inner
+            .dv_deletion_to_apply
+            .retain(|k, _| !can_apply(*k, vacuum_epoch));
+        inner.status.retain(|epoch, _| *epoch >= vacuum_epoch);
+        inner.commit_times.retain(|epoch, _| *epoch >= vacuum_epoch);
+
         for deletion in &deletions {
             if let Some(rowset) = inner.rowsets.remove(deletion) {
                 match Arc::try_unwrap(rowset) {
@@ -360,13 +495,41 @@ impl VersionManager {
                 warn!("duplicated deletion dectected, but we can't solve this issue for now -- see https://github.com/risinglightdb/risinglight/issues/566 for more information.");
             }
         }
-        Ok(deletions)
+        for (table_id, _, dv_id) in &dv_deletions {
+            // iterators may still hold the DV, which will be freed when they are dropped
+            inner.dvs.remove(&(*table_id, *dv_id));
+        }
+        Ok(Vacuum {
+            rowsets: deletions,
+            dvs: dv_deletions,
+        })
     }
 
     pub async fn do_vacuum(self: &Arc<Self>) -> StorageResult<()> {
-        let deletions = self.find_vacuum().await?;
+        let Vacuum { rowsets, dvs } = self.find_vacuum().await?;
+        if self.storage_options.manifest_version.is_some() {
+            // the files may be used by later versions of the manifest
+            return Ok(());
+        }
+
+        for (table_id, rowset_id, dv_id) in dvs {
+            let path = self
+                .storage_options
+                .path
+                .join(format!("dv/{}_{}_{}.dv", table_id, rowset_id, dv_id));
+            info!("vacuum DV {}_{}_{}", table_id, rowset_id, dv_id);
+            match &self.storage_options.io_backend {
+                IOBackend::InMemory(map) => {
+                    map.lock().remove(&path);
+                }
+                _ if !self.storage_options.disable_all_disk_operation => {
+                    tokio::fs::remove_file(path).await?;
+                }
+                _ => {}
+            }
+        }
 
-        for (table_id, rowset_id) in deletions {
+        for (table_id, rowset_id) in rowsets {
             let path = self
                 .storage_options
                 .path
@@ -395,6 +558,20 @@ impl VersionManager {
     }
 }
 
+/// Returns the current time in microseconds since the Unix epoch.
+fn now_micros() -> i64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    now.as_micros() as i64
+}
+
+/// RowSets and DVs to be removed from disk by the vacuum.
+pub struct Vacuum {
+    /// (TableId, RowSetId) of RowSets.
+    pub rowsets: Vec<(u32, u32)>,
+    /// (TableId, RowSetId, DVId) of DVs.
+    pub dvs: Vec<(u32, u32, u64)>,
+}
+
 pub struct Version {
     pub epoch: u64,
     pub snapshot: Arc<Snapshot>,
@@ -421,3 +598,139 @@ impl Drop for Version {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::catalog::TableRefId;
+    use crate::Database;
+
+    #[tokio::test]
+    async fn test_vacuum_dv() {
+        let version = Arc::new(VersionManager::new(
+            Manifest::new_mock(),
+            Arc::new(StorageOptions::default_for_test()),
+        ));
+        let table_id = TableRefId::new(0, 1);
+        let dv = DeleteVector::new(0, 0, vec![]);
+        let entry = AddDVEntry {
+            table_id,
+            dv_id: 0,
+            rowset_id: 0,
+        };
+        version
+            .commit_changes(vec![EpochOp::AddDV((entry, dv))])
+            .await
+            .unwrap();
+        let pinned = version.pin();
+        let entry = DeleteDVEntry {
+            table_id,
+            dv_id: 0,
+            rowset_id: 0,
+        };
+        version
+            .commit_changes(vec![EpochOp::DeleteDV(entry)])
+            .await
+            .unwrap();
+        assert!(version.pin().snapshot.get_dvs_of(1, 0).is_none());
+
+        // the DV is still visible to the pinned snapshot
+        let vacuum = version.find_vacuum().await.unwrap();
+        assert!(vacuum.dvs.is_empty());
+        assert!(pinned.snapshot.get_dvs_of(1, 0).is_some());
+        version.get_dv(1, 0);
+
+        drop(pinned);
+        let vacuum = version.find_vacuum().await.unwrap();
+        assert_eq!(vacuum.dvs, vec![(1, 0, 0)]);
+        // only the snapshot of the latest epoch is kept
+        assert_eq!(version.inner.lock().status.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_retention() {
+        let options = StorageOptions {
+            snapshot_retention: Duration::from_secs(3600),
+            ..StorageOptions::default_for_test()
+        };
+        let version = Arc::new(VersionManager::new(Manifest::new_mock(), Arc::new(options)));
+        let table_id = TableRefId::new(0, 1);
+        let dv = DeleteVector::new(0, 0, vec![]);
+        let entry = AddDVEntry {
+            table_id,
+            dv_id: 0,
+            rowset_id: 0,
+        };
+        let added = version
+            .commit_changes(vec![EpochOp::AddDV((entry, dv))])
+            .await
+            .unwrap();
+        let entry = DeleteDVEntry {
+            table_id,
+            dv_id: 0,
+            rowset_id: 0,
+        };
+        version
+            .commit_changes(vec![EpochOp::DeleteDV(entry)])
+            .await
+            .unwrap();
+
+        // the replaced snapshot is kept in the retention period
+        let vacuum = version.find_vacuum().await.unwrap();
+        assert!(vacuum.dvs.is_empty());
+        let older = version.pin_at(AsOf::Epoch(added)).unwrap();
+        assert!(older.snapshot.get_dvs_of(1, 0).is_some());
+        drop(older);
+        let latest = version.pin_at(AsOf::Timestamp(now_micros())).unwrap();
+        assert!(latest.snapshot.get_dvs_of(1, 0).is_none());
+        assert!(version.pin_at(AsOf::Epoch(added + 2)).is_err());
+        assert!(version.pin_at(AsOf::Timestamp(0)).is_err());
+
+        // and vacuumed after the retention period
+        for time in version.inner.lock().commit_times.values_mut() {
+            *time -= 7200 * 1_000_000;
+        }
+        let vacuum = version.find_vacuum().await.unwrap();
+        assert_eq!(vacuum.dvs, vec![(1, 0, 0)]);
+        assert!(version.pin_at(AsOf::Epoch(added)).is_err());
+        drop(latest);
+    }
+
+    async fn query(db: &Database, sql: &str) -> String {
+        let chunks = db.run(sql).await.unwrap();
+        chunks[0]
+            .get_first_data_chunk()
+            .array_at(0)
+            .get_to_string(0)
+    }
+
+    #[tokio::test]
+    async fn time_travel_query() {
+        let options = StorageOptions {
+            snapshot_retention: Duration::from_secs(3600),
+            ..StorageOptions::default_for_test()
+        };
+        let db = Database::new_on_disk(options).await;
+        db.run("create table t (a int); insert into t values (1);")
+            .await
+            .unwrap();
+        let epoch = query(&db, "select max(epoch) from pg_catalog.pg_snapshots").await;
+        db.run("delete from t; insert into t values (2);")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            query(&db, &format!("select a from t as of {epoch}")).await,
+            "1"
+        );
+        assert_eq!(query(&db, "select a from t").await, "2");
+        let sql = format!("select count(*) from t as of {epoch} as old join t on old.a < t.a");
+        assert_eq!(query(&db, &sql).await, "1");
+
+        let error = (db.run("select * from t as of '2000-01-01 00:00:00'").await).unwrap_err();
+        assert!(error.to_string().contains("snapshot"), "{error}");
+        db.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,812 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Write-ahead log (WAL) of the secondary storage.
+//!
+//! With the log enabled, a transaction does not write its RowSets and DVs to disk on commit.
+//! Instead, they are encoded in memory and appended to the log as one record, which is the commit
+//! point of the transaction. Logged RowSets and DVs are served from memory until a checkpoint
+//! writes them to disk, records the last persisted log sequence number (LSN) in the manifest, and
+//! truncates the log. On startup, records after the last checkpoint are replayed.
+//!
+//! DDL operations are recorded in the manifest as before. Each logged RowSet carries its columns,
+//! so that it can be replayed after the table is altered. RowSets of dropped tables are skipped.
+//!
+//! Full RowSets of large transactions, such as bulk loads, are written to disk before commit
+//! instead of being kept in memory. Only their ids and columns are logged, and they are added to
+//! the manifest by the next checkpoint.
+//!
+//! A record is encoded as `len: u64 | crc32: u32 | payload` in little endian. A torn record at the
+//! end of the log is discarded on replay.
+
+use std::collections::{HashMap, HashSet};
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+use bytes::{Buf, BufMut, Bytes};
+use itertools::Itertools;
+use moka::future::Cache;
+use parking_lot::Mutex as PLMutex;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use super::version_manager::{EpochOp, VersionManager};
+use super::{
+    AddDVEntry, AddRowSetEntry, Block, BlockCacheKey, CheckpointEntry, DeleteVector, DiskRowset,
+    IOBackend, SecondaryStorage, StorageResult, WalOptions, WalSyncPolicy,
+};
+use crate::catalog::{ColumnCatalog, TableRefId};
+
+/// A RowSet committed to the write-ahead log.
+#[derive(Clone)]
+pub struct WalRowset {
+    pub table_id: TableRefId,
+    pub rowset_id: u32,
+    pub columns: Arc<[ColumnCatalog]>,
+    /// Names and contents of files in the RowSet directory.
+    pub files: Vec<(String, Bytes)>,
+}
+
+impl WalRowset {
+    /// Collect the files of a RowSet written to an in-memory backend.
+    pub fn new(
+        table_id: TableRefId,
+        rowset_id: u32,
+        columns: Arc<[ColumnCatalog]>,
+        io_backend: &IOBackend,
+    ) -> Self {
+        let IOBackend::InMemory(map) = io_backend else {
+            panic!("RowSet is not in memory");
+        };
+        let files = (map.lock().iter())
+            .map(|(path, data)| {
+                let name = path.file_name().unwrap().to_str().unwrap();
+                (name.to_string(), data.clone())
+            })
+            .collect();
+        Self {
+            table_id,
+            rowset_id,
+            columns,
+            files,
+        }
+    }
+
+    /// Open the RowSet from memory.
+    async fn open(
+        &self,
+        directory: &Path,
+        block_cache: Cache<BlockCacheKey, Block>,
+    ) -> StorageResult<DiskRowset> {
+        let io_backend = IOBackend::in_memory();
+        if let IOBackend::InMemory(map) = &io_backend {
+            let mut map = map.lock();
+            for (name, data) in &self.files {
+                map.insert(directory.join(name), data.clone());
+            }
+        }
+        DiskRowset::open(
+            directory.to_path_buf(),
+            self.columns.clone(),
+            block_cache,
+            self.rowset_id,
+            io_backend,
+        )
+        .await
+    }
+
+    /// Write the RowSet to disk.
+    async fn persist(&self, directory: &Path) -> StorageResult<()> {
+        tokio::fs::create_dir_all(directory).await?;
+        for (name, data) in &self.files {
+            write_file(&directory.join(name), data).await?;
+        }
+        File::open(directory).await?.sync_data().await?;
+        Ok(())
+    }
+}
+
+/// A RowSet written to disk before commit, whose data is not in the write-ahead log.
+#[derive(Clone)]
+pub struct PersistedRowset {
+    pub table_id: TableRefId,
+    pub rowset_id: u32,
+    pub columns: Arc<[ColumnCatalog]>,
+}
+
+/// A DV committed to the write-ahead log.
+#[derive(Clone)]
+pub struct WalDv {
+    pub table_id: TableRefId,
+    pub rowset_id: u32,
+    pub dv_id: u64,
+    /// Content written by [`DeleteVector::write_all`].
+    pub data: Bytes,
+}
+
+/// Write a file and sync it to disk.
+async fn write_file(path: &Path, data: &[u8]) -> StorageResult<()> {
+    let mut file = OpenOptions::default()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await?;
+    file.write_all(data).await?;
+    file.sync_data().await?;
+    Ok(())
+}
+
+/// A transaction committed to the write-ahead log.
+pub struct WalRecord {
+    lsn: u64,
+    rowsets: Vec<WalRowset>,
+    dvs: Vec<WalDv>,
+    persisted_rowsets: Vec<PersistedRowset>,
+}
+
+impl WalRecord {
+    /// Returns the (TableId, RowSetId) of RowSets written to disk before commit.
+    pub fn persisted_rowset_ids(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (self.persisted_rowsets.iter()).map(|rowset| (rowset.table_id.table_id, rowset.rowset_id))
+    }
+
+    fn encode(&self) -> StorageResult<Vec<u8>> {
+        let mut payload = vec![];
+        payload.put_u64_le(self.lsn);
+        payload.put_u32_le(self.rowsets.len() as u32);
+        for rowset in &self.rowsets {
+            put_table_id(&mut payload, rowset.table_id);
+            payload.put_u32_le(rowset.rowset_id);
+            put_bytes(&mut payload, &serde_json::to_vec(&rowset.columns[..])?);
+            payload.put_u32_le(rowset.files.len() as u32);
+            for (name, data) in &rowset.files {
+                put_bytes(&mut payload, name.as_bytes());
+                put_bytes(&mut payload, data);
+            }
+        }
+        payload.put_u32_le(self.dvs.len() as u32);
+        for dv in &self.dvs {
+            put_table_id(&mut payload, dv.table_id);
+            payload.put_u32_le(dv.rowset_id);
+            payload.put_u64_le(dv.dv_id);
+            put_bytes(&mut payload, &dv.data);
+        }
+        payload.put_u32_le(self.persisted_rowsets.len() as u32);
+        for rowset in &self.persisted_rowsets {
+            put_table_id(&mut payload, rowset.table_id);
+            payload.put_u32_le(rowset.rowset_id);
+            put_bytes(&mut payload, &serde_json::to_vec(&rowset.columns[..])?);
+        }
+
+        let mut buf = Vec::with_capacity(payload.len() + 12);
+        buf.put_u64_le(payload.len() as u64);
+        buf.put_u32_le(crc32fast::hash(&payload));
+        buf.extend_from_slice(&payload);
+        Ok(buf)
+    }
+
+    /// Decode a record from the beginning of `data` and advance it. Returns `None` if the record
+    /// is torn.
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let mut reader = Reader(*data);
+        let len = reader.u64()? as usize;
+        let checksum = reader.u32()?;
+        let payload = reader.take(len)?;
+        if crc32fast::hash(payload) != checksum {
+            return None;
+        }
+        let rest = reader.0;
+
+        let mut reader = Reader(payload);
+        let lsn = reader.u64()?;
+        let num_rowsets = reader.u32()?;
+        let mut rowsets = Vec::with_capacity(num_rowsets as usize);
+        for _ in 0..num_rowsets {
+            let table_id = reader.table_id()?;
+            let rowset_id = reader.u32()?;
+            let columns: Vec<ColumnCatalog> = serde_json::from_slice(reader.bytes()?).ok()?;
+            let num_files = reader.u32()?;
+            let mut files = Vec::with_capacity(num_files as usize);
+            for _ in 0..num_files {
+                let name = std::str::from_utf8(reader.bytes()?).ok()?.to_string();
+                files.push((name, Bytes::copy_from_slice(reader.bytes()?)));
+            }
+            rowsets.push(WalRowset {
+                table_id,
+                rowset_id,
+                columns: columns.into(),
+                files,
+            });
+        }
+        let num_dvs = reader.u32()?;
+        let mut dvs = Vec::with_capacity(num_dvs as usize);
+        for _ in 0..num_dvs {
+            dvs.push(WalDv {
+                table_id: reader.table_id()?,
+                rowset_id: reader.u32()?,
+                dv_id: reader.u64()?,
+                data: Bytes::copy_from_slice(reader.bytes()?),
+            });
+        }
+        // records written before persisted RowSets were logged end here
+        let num_persisted = if reader.0.is_empty() {
+            0
+        } else {
+            reader.u32()?
+        };
+        let mut persisted_rowsets = Vec::with_capacity(num_persisted as usize);
+        for _ in 0..num_persisted {
+            let table_id = reader.table_id()?;
+            let rowset_id = reader.u32()?;
+            let columns: Vec<ColumnCatalog> = serde_json::from_slice(reader.bytes()?).ok()?;
+            persisted_rowsets.push(PersistedRowset {
+                table_id,
+                rowset_id,
+                columns: columns.into(),
+            });
+        }
+        *data = rest;
+        Some(Self {
+            lsn,
+            rowsets,
+            dvs,
+            persisted_rowsets,
+        })
+    }
+}
+
+fn put_table_id(buf: &mut Vec<u8>, table_id: TableRefId) {
+    buf.put_u32_le(table_id.schema_id);
+    buf.put_u32_le(table_id.table_id);
+}
+
+fn put_bytes(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.put_u64_le(data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+/// Reads values from a buffer, returning `None` if it is too short.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn u32(&mut self) -> Option<u32> {
+        (self.0.len() >= 4).then(|| self.0.get_u32_le())
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        (self.0.len() >= 8).then(|| self.0.get_u64_le())
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (data, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(data)
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u64()? as usize;
+        self.take(len)
+    }
+
+    fn table_id(&mut self) -> Option<TableRefId> {
+        Some(TableRefId::new(self.u32()?, self.u32()?))
+    }
+}
+
+/// RowSets and DVs in the log which are not written to disk or recorded in the manifest yet.
+#[derive(Clone, Default)]
+struct Unpersisted {
+    /// (TableId, RowSetId) -> RowSet
+    rowsets: HashMap<(u32, u32), WalRowset>,
+    /// (TableId, DVId) -> DV
+    dvs: HashMap<(u32, u64), WalDv>,
+    /// (TableId, RowSetId) -> RowSet on disk but not in the manifest
+    persisted_rowsets: HashMap<(u32, u32), PersistedRowset>,
+}
+
+struct WalInner {
+    file: File,
+    /// Size of all records in the file.
+    size: u64,
+    /// LSN of the next record.
+    next_lsn: u64,
+    /// Time when the first record not synced to disk is written.
+    unsynced_since: Option<Instant>,
+}
+
+/// The write-ahead log of `SecondaryStorage`.
+pub struct Wal {
+    options: WalOptions,
+
+    /// The log file. Commits and checkpoints hold this lock until complete, so that the changes
+    /// of all records in the file are applied to the version manager in order.
+    inner: Mutex<WalInner>,
+
+    unpersisted: PLMutex<Unpersisted>,
+}
+
+impl Wal {
+    pub async fn open(path: impl AsRef<Path>, options: WalOptions) -> StorageResult<Self> {
+        let file = OpenOptions::default()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref())
+            .await?;
+        Ok(Self {
+            options,
+            inner: Mutex::new(WalInner {
+                file,
+                size: 0,
+                next_lsn: 1,
+                unsynced_since: None,
+            }),
+            unpersisted: PLMutex::new(Unpersisted::default()),
+        })
+    }
+
+    /// Read the records after `checkpoint_lsn`, and position the log after the last record.
+    pub async fn replay(&self, checkpoint_lsn: u64) -> StorageResult<Vec<WalRecord>> {
+        let mut inner = self.inner.lock().await;
+        let mut data = vec![];
+        inner.file.seek(SeekFrom::Start(0)).await?;
+        inner.file.read_to_end(&mut data).await?;
+
+        let mut buf = &data[..];
+        let mut records = vec![];
+        let mut last_lsn = checkpoint_lsn;
+        while let Some(record) = WalRecord::decode(&mut buf) {
+            last_lsn = last_lsn.max(record.lsn);
+            if record.lsn > checkpoint_lsn {
+                records.push(record);
+            }
+        }
+        if !buf.is_empty() {
+            warn!("WAL: discard torn record at the end");
+        }
+
+        inner.size = (data.len() - buf.len()) as u64;
+        let size = inner.size;
+        inner.file.set_len(size).await?;
+        inner.file.seek(SeekFrom::End(0)).await?;
+        inner.next_lsn = last_lsn + 1;
+        Ok(records)
+    }
+
+    /// Append a record of the RowSets and DVs written by a transaction, and then apply the
+    /// changes to the version manager.
+    pub async fn commit(
+        &self,
+        rowsets: Vec<WalRowset>,
+        dvs: Vec<WalDv>,
+        persisted_rowsets: Vec<PersistedRowset>,
+        version: &VersionManager,
+        changeset: Vec<EpochOp>,
+    ) -> StorageResult<()> {
+        let mut inner = self.inner.lock().await;
+        let record = WalRecord {
+            lsn: inner.next_lsn,
+            rowsets,
+            dvs,
+            persisted_rowsets,
+        };
+        let data = record.encode()?;
+        if let Err(err) = inner.file.write_all(&data).await {
+            // remove the partially written record
+            let size = inner.size;
+            inner.file.set_len(size).await?;
+            inner.file.seek(SeekFrom::End(0)).await?;
+            return Err(err.into());
+        }
+        inner.file.flush().await?;
+        inner.size += data.len() as u64;
+        inner.next_lsn += 1;
+        inner.unsynced_since.get_or_insert_with(Instant::now);
+        self.sync_inner(&mut inner).await?;
+
+        self.register(record.rowsets, record.dvs, record.persisted_rowsets);
+        version.apply_changes(changeset).await?;
+        Ok(())
+    }
+
+    /// Sync the log if it is due by the sync policy.
+    pub async fn sync(&self) -> StorageResult<()> {
+        let mut inner = self.inner.lock().await;
+        self.sync_inner(&mut inner).await
+    }
+
+    async fn sync_inner(&self, inner: &mut WalInner) -> StorageResult<()> {
+        let due = match self.options.sync_policy {
+            WalSyncPolicy::PerCommit => true,
+            WalSyncPolicy::Periodic(interval) => {
+                (inner.unsynced_since).is_some_and(|since| since.elapsed() >= interval)
+            }
+        };
+        if due && inner.unsynced_since.take().is_some() {
+            inner.file.sync_data().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the log is large enough to take a checkpoint.
+    pub async fn needs_checkpoint(&self) -> bool {
+        self.inner.lock().await.size >= self.options.checkpoint_size as u64
+    }
+
+    /// Returns true if the RowSet is in the log and not written to disk or recorded in the
+    /// manifest yet.
+    pub fn is_unpersisted_rowset(&self, table_id: u32, rowset_id: u32) -> bool {
+        let unpersisted = self.unpersisted.lock();
+        unpersisted.rowsets.contains_key(&(table_id, rowset_id))
+            || (unpersisted.persisted_rowsets).contains_key(&(table_id, rowset_id))
+    }
+
+    /// Returns true if the DV is in the log and not written to disk yet.
+    pub fn is_unpersisted_dv(&self, table_id: u32, dv_id: u64) -> bool {
+        (self.unpersisted.lock().dvs).contains_key(&(table_id, dv_id))
+    }
+
+    fn register(
+        &self,
+        rowsets: Vec<WalRowset>,
+        dvs: Vec<WalDv>,
+        persisted_rowsets: Vec<PersistedRowset>,
+    ) {
+        let mut unpersisted = self.unpersisted.lock();
+        for rowset in rowsets {
+            let key = (rowset.table_id.table_id, rowset.rowset_id);
+            unpersisted.rowsets.insert(key, rowset);
+        }
+        for dv in dvs {
+            unpersisted.dvs.insert((dv.table_id.table_id, dv.dv_id), dv);
+        }
+        for rowset in persisted_rowsets {
+            let key = (rowset.table_id.table_id, rowset.rowset_id);
+            unpersisted.persisted_rowsets.insert(key, rowset);
+        }
+    }
+}
+
+impl SecondaryStorage {
+    /// Write all RowSets and DVs in the write-ahead log to disk, and truncate the log.
+    pub(super) async fn checkpoint_inner(&self) -> StorageResult<()> {
+        if self.wal.is_none() {
+            return Ok(());
+        }
+        let mut table_ids = self
+            .tables
+            .read()
+            .keys()
+            .map(|id| id.table_id)
+            .collect_vec();
+        table_ids.sort();
+
+        // no deletion or compaction can happen on the tables during the checkpoint
+        let mut guards = Vec::with_capacity(table_ids.len());
+        for table_id in table_ids {
+            guards.push(self.txn_mgr.lock_for_deletion(table_id).await);
+        }
+        self.checkpoint_locked().await
+    }
+
+    /// Same as [`checkpoint_inner`](Self::checkpoint_inner), with the locks of all tables held.
+    pub(super) async fn checkpoint_locked(&self) -> StorageResult<()> {
+        let Some(wal) = &self.wal else {
+            return Ok(());
+        };
+        let mut inner = wal.inner.lock().await;
+        let unpersisted = wal.unpersisted.lock().clone();
+        if inner.size == 0
+            && unpersisted.rowsets.is_empty()
+            && unpersisted.dvs.is_empty()
+            && unpersisted.persisted_rowsets.is_empty()
+        {
+            return Ok(());
+        }
+
+        let pin_version = self.version.pin();
+        let snapshot = &pin_version.snapshot;
+        let tables = self.tables.read().clone();
+        let mut changeset = vec![];
+
+        // RowSets and DVs deleted after commit are skipped
+        for rowset in unpersisted.rowsets.values() {
+            let table_id = rowset.table_id;
+            if !(snapshot.get_rowsets_of(table_id.table_id))
+                .is_some_and(|ids| ids.contains(&rowset.rowset_id))
+            {
+                continue;
+            }
+            let directory = tables[&table_id].get_rowset_path(rowset.rowset_id);
+            rowset.persist(&directory).await?;
+            let disk_rowset = DiskRowset::open(
+                directory,
+                rowset.columns.clone(),
+                self.block_cache.clone(),
+                rowset.rowset_id,
+                self.options.io_backend.clone(),
+            )
+            .await?;
+            changeset.push(EpochOp::AddRowSet((
+                AddRowSetEntry {
+                    table_id,
+                    rowset_id: rowset.rowset_id,
+                    columns: rowset.columns.to_vec(),
+                },
+                disk_rowset,
+            )));
+        }
+        for rowset in unpersisted.persisted_rowsets.values() {
+            let table_id = rowset.table_id;
+            if !(snapshot.get_rowsets_of(table_id.table_id))
+                .is_some_and(|ids| ids.contains(&rowset.rowset_id))
+            {
+                continue;
+            }
+            let disk_rowset = DiskRowset::open(
+                tables[&table_id].get_rowset_path(rowset.rowset_id),
+                rowset.columns.clone(),
+                self.block_cache.clone(),
+                rowset.rowset_id,
+                self.options.io_backend.clone(),
+            )
+            .await?;
+            changeset.push(EpochOp::AddRowSet((
+                AddRowSetEntry {
+                    table_id,
+                    rowset_id: rowset.rowset_id,
+                    columns: rowset.columns.to_vec(),
+                },
+                disk_rowset,
+            )));
+        }
+        for dv in unpersisted.dvs.values() {
+            let table_id = dv.table_id;
+            if !(snapshot.get_dvs_of(table_id.table_id, dv.rowset_id))
+                .is_some_and(|ids| ids.contains(&dv.dv_id))
+            {
+                continue;
+            }
+            let path = tables[&table_id].get_dv_path(dv.rowset_id, dv.dv_id);
+            write_file(&path, &dv.data).await?;
+            changeset.push(EpochOp::AddDV((
+                AddDVEntry {
+                    table_id,
+                    dv_id: dv.dv_id,
+                    rowset_id: dv.rowset_id,
+                },
+                DeleteVector::from_bytes(dv.dv_id, dv.rowset_id, &dv.data)?,
+            )));
+        }
+
+        // all records up to this LSN are applied to the version
+        let lsn = inner.next_lsn - 1;
+        changeset.push(EpochOp::Checkpoint(CheckpointEntry { lsn }));
+        self.version.commit_changes(changeset).await?;
+        info!("checkpoint at LSN {}", lsn);
+
+        inner.file.set_len(0).await?;
+        inner.file.seek(SeekFrom::Start(0)).await?;
+        inner.file.sync_data().await?;
+        inner.size = 0;
+        inner.unsynced_since = None;
+        *wal.unpersisted.lock() = Unpersisted::default();
+        Ok(())
+    }
+
+    /// Replay the records after the last checkpoint, which are read by [`Wal::replay`], and then
+    /// take a checkpoint.
+    pub(super) async fn recover_from_wal(&self, records: Vec<WalRecord>) -> StorageResult<()> {
+        let Some(wal) = &self.wal else {
+            return Ok(());
+        };
+        info!("replaying {} WAL records", records.len());
+
+        let tables = self.tables.read().clone();
+        for record in records {
+            let mut changeset = vec![];
+            let mut rowsets = vec![];
+            let mut dvs = vec![];
+            let mut added = HashSet::new();
+            for rowset in record.rowsets {
+                self.next_id
+                    .0
+                    .fetch_max(rowset.rowset_id + 1, Ordering::SeqCst);
+                // skip RowSets of dropped tables
+                let Some(table) = tables.get(&rowset.table_id) else {
+                    continue;
+                };
+                let directory = table.get_rowset_path(rowset.rowset_id);
+                let disk_rowset = rowset.open(&directory, self.block_cache.clone()).await?;
+                changeset.push(EpochOp::AddRowSet((
+                    AddRowSetEntry {
+                        table_id: rowset.table_id,
+                        rowset_id: rowset.rowset_id,
+                        columns: rowset.columns.to_vec(),
+                    },
+                    disk_rowset,
+                )));
+                added.insert((rowset.table_id.table_id, rowset.rowset_id));
+                rowsets.push(rowset);
+            }
+            let mut persisted_rowsets = vec![];
+            for rowset in record.persisted_rowsets {
+                self.next_id
+                    .0
+                    .fetch_max(rowset.rowset_id + 1, Ordering::SeqCst);
+                let Some(table) = tables.get(&rowset.table_id) else {
+                    continue;
+                };
+                let disk_rowset = DiskRowset::open(
+                    table.get_rowset_path(rowset.rowset_id),
+                    rowset.columns.clone(),
+                    self.block_cache.clone(),
+                    rowset.rowset_id,
+                    self.options.io_backend.clone(),
+                )
+                .await?;
+                changeset.push(EpochOp::AddRowSet((
+                    AddRowSetEntry {
+                        table_id: rowset.table_id,
+                        rowset_id: rowset.rowset_id,
+                        columns: rowset.columns.to_vec(),
+                    },
+                    disk_rowset,
+                )));
+                added.insert((rowset.table_id.table_id, rowset.rowset_id));
+                persisted_rowsets.push(rowset);
+            }
+
+            let pin_version = self.version.pin();
+            for dv in record.dvs {
+                self.next_id.1.fetch_max(dv.dv_id + 1, Ordering::SeqCst);
+                // skip DVs of RowSets which are dropped or compacted
+                let key = (dv.table_id.table_id, dv.rowset_id);
+                if !added.contains(&key)
+                    && !(pin_version.snapshot.get_rowsets_of(key.0))
+                        .is_some_and(|ids| ids.contains(&key.1))
+                {
+                    continue;
+                }
+                changeset.push(EpochOp::AddDV((
+                    AddDVEntry {
+                        table_id: dv.table_id,
+                        dv_id: dv.dv_id,
+                        rowset_id: dv.rowset_id,
+                    },
+                    DeleteVector::from_bytes(dv.dv_id, dv.rowset_id, &dv.data)?,
+                )));
+                dvs.push(dv);
+            }
+            drop(pin_version);
+
+            wal.register(rowsets, dvs, persisted_rowsets);
+            self.version.apply_changes(changeset).await?;
+        }
+
+        self.checkpoint_inner().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::storage::SecondaryStorageOptions;
+    use crate::Database;
+
+    #[test]
+    fn test_encode_decode() {
+        let table_id = TableRefId::new(0, 1);
+        let record = WalRecord {
+            lsn: 42,
+            rowsets: vec![WalRowset {
+                table_id,
+                rowset_id: 3,
+                columns: vec![].into(),
+                files: vec![("0.col".into(), Bytes::from_static(b"data"))],
+            }],
+            dvs: vec![WalDv {
+                table_id,
+                rowset_id: 2,
+                dv_id: 5,
+                data: Bytes::from_static(b"dv"),
+            }],
+            persisted_rowsets: vec![PersistedRowset {
+                table_id,
+                rowset_id: 4,
+                columns: vec![].into(),
+            }],
+        };
+        let mut data = record.encode().unwrap();
+        let len = data.len();
+        data.extend(record.encode().unwrap());
+
+        let mut buf = &data[..];
+        let decoded = WalRecord::decode(&mut buf).unwrap();
+        assert_eq!(decoded.lsn, 42);
+        assert_eq!(decoded.rowsets[0].rowset_id, 3);
+        assert_eq!(decoded.rowsets[0].files, record.rowsets[0].files);
+        assert_eq!(decoded.dvs[0].dv_id, 5);
+        assert_eq!(decoded.dvs[0].data, record.dvs[0].data);
+        assert_eq!(decoded.persisted_rowsets[0].rowset_id, 4);
+        assert_eq!(buf.len(), len);
+
+        // a torn record is not decoded
+        let mut buf = &data[len..data.len() - 1];
+        assert!(WalRecord::decode(&mut buf).is_none());
+    }
+
+    /// Copy the files of a database, as if it crashed at this point.
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &to.join(entry.file_name()));
+            } else {
+                std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn recover_persisted_rowsets() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let options = SecondaryStorageOptions {
+            path: tempdir.path().join("db"),
+            disable_all_disk_operation: false,
+            io_backend: IOBackend::NormalRead,
+            // every chunk fills a RowSet
+            target_rowset_size: 1 << 10,
+            wal: Some(WalOptions {
+                sync_policy: WalSyncPolicy::PerCommit,
+                checkpoint_size: 1 << 30,
+            }),
+            ..SecondaryStorageOptions::default_for_test()
+        };
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for i in 0..10000 {
+            writeln!(file, "{i}").unwrap();
+        }
+        file.flush().unwrap();
+
+        let db = Database::new_on_disk(options.clone()).await;
+        db.run("create table t (a int)").await.unwrap();
+        let sql = format!("copy t from '{}'", file.path().display());
+        db.run(&sql).await.unwrap();
+        // full RowSets are written to disk before commit
+        let rowset_dirs = std::fs::read_dir(&options.path)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().is_dir())
+            .count();
+        // besides the DV directory
+        assert!(rowset_dirs > 2, "{rowset_dirs}");
+
+        // reopen without a checkpoint
+        let crashed = SecondaryStorageOptions {
+            path: tempdir.path().join("crashed"),
+            ..options
+        };
+        copy_dir(&tempdir.path().join("db"), &crashed.path);
+        let db2 = Database::new_on_disk(crashed).await;
+        let chunks = db2.run("select count(*) from t").await.unwrap();
+        let count = chunks[0]
+            .get_first_data_chunk()
+            .array_at(0)
+            .get_to_string(0);
+        assert_eq!(count, "10000");
+        db2.shutdown().await.unwrap();
+        db.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,583 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Transactions of sessions, started by `BEGIN` and ended by `COMMIT` or `ROLLBACK`.
+//!
+//! A [`SessionTransaction`] reads each table it accesses from a snapshot, which is taken by a
+//! [`read_pinned`](Table::read_pinned) storage transaction on the first access. Rows written by
+//! statements in the transaction are kept in the session, so that they are visible to later
+//! statements in the transaction but not to other sessions. No table is locked before `COMMIT`.
+//!
+//! On `COMMIT`, a storage transaction is started on each written table, locking the table if rows
+//! are deleted from it. Tables are locked in the order of their ids, so that commits of different
+//! sessions never deadlock. The writes are applied to the storage transactions, which are
+//! committed together by [`Transaction::commit_all`]. The commit fails if a deleted row has been
+//! moved since the snapshot, e.g. by a `VACUUM`. On `ROLLBACK`, the snapshots are released.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use itertools::Itertools;
+use tokio::sync::Mutex;
+
+use super::{
+    AsOf, RowHandler, ScanOptions, SecondaryStorage, Storage, StorageColumnRef, StorageError,
+    StorageResult, Table, TableIndex, Transaction, TxnIterator,
+};
+use crate::array::{Array, ArrayImpl, DataChunk};
+use crate::catalog::{
+    find_sort_key_id, ColumnCatalog, ColumnId, Constraints, IndexId, SchemaId, TableRefId,
+    ViewDefinition,
+};
+use crate::planner::RecExpr;
+
+/// A transaction of a session on the storage `S`.
+///
+/// It implements [`Storage`], so that statements in the transaction are executed on it.
+pub struct SessionTransaction<S: Storage> {
+    storage: Arc<S>,
+    /// The writes on each table accessed in the transaction.
+    tables: Arc<Mutex<HashMap<TableRefId, TableWrites<S::Transaction>>>>,
+}
+
+impl<S: Storage> Clone for SessionTransaction<S> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            tables: self.tables.clone(),
+        }
+    }
+}
+
+impl<S: Storage> SessionTransaction<S> {
+    /// Begins a transaction on the storage.
+    pub fn begin(storage: Arc<S>) -> Self {
+        Self {
+            storage,
+            tables: Default::default(),
+        }
+    }
+
+    /// Applies the writes of the transaction to the storage atomically.
+    pub async fn commit(&self) -> StorageResult<()> {
+        let tables = std::mem::take(&mut *self.tables.lock().await);
+        let mut txns = vec![];
+        for (table_id, writes) in tables.into_iter().sorted_by_key(|(id, _)| id.table_id) {
+            let table = self.storage.get_table(table_id)?;
+            if let Some(txn) = writes.prepare(&table).await? {
+                txns.push(txn);
+            }
+        }
+        <S::Transaction as Transaction>::commit_all(txns).await
+    }
+
+    /// Discards the writes of the transaction.
+    pub async fn rollback(&self) -> StorageResult<()> {
+        let tables = std::mem::take(&mut *self.tables.lock().await);
+        for (_, writes) in tables {
+            writes.snapshot.abort().await?;
+        }
+        Ok(())
+    }
+}
+
+/// The writes of a transaction on a table.
+struct TableWrites<T: Transaction> {
+    /// The read-only storage transaction on the snapshot of the table.
+    snapshot: T,
+    /// Rows appended in the transaction.
+    appended: Vec<DataChunk>,
+    /// Positions of the rows deleted from `appended`.
+    deleted_appended: HashSet<usize>,
+    /// Rows deleted from the snapshot of `txn`, by their values in the row handler column.
+    deleted: HashMap<i64, T::RowHandlerType>,
+}
+
+impl<T: Transaction> TableWrites<T> {
+    fn new(snapshot: T) -> Self {
+        Self {
+            snapshot,
+            appended: vec![],
+            deleted_appended: HashSet::new(),
+            deleted: HashMap::new(),
+        }
+    }
+
+    /// Scans the snapshot of the storage transaction with the writes applied.
+    async fn scan(
+        &self,
+        columns: &[ColumnCatalog],
+        col_idx: &[StorageColumnRef],
+        opts: ScanOptions,
+    ) -> StorageResult<SessionTxnIterator<T::TxnIteratorType>> {
+        let appended = self.scan_appended(columns, col_idx, &opts);
+
+        // the row handler column is needed to filter out deleted rows
+        let mut txn_col_idx = col_idx.to_vec();
+        let handler_idx = if self.deleted.is_empty() {
+            None
+        } else if let Some(idx) = col_idx
+            .iter()
+            .position(|c| *c == StorageColumnRef::RowHandler)
+        {
+            Some(idx)
+        } else {
+            txn_col_idx.push(StorageColumnRef::RowHandler);
+            Some(col_idx.len())
+        };
+        let iter = self.snapshot.scan(&txn_col_idx, opts).await?;
+        Ok(SessionTxnIterator {
+            iter: Some(iter),
+            deleted: self.deleted.keys().copied().collect(),
+            handler_idx,
+            column_count: col_idx.len(),
+            appended: appended.into(),
+        })
+    }
+
+    /// Returns the columns of appended rows that are not deleted.
+    ///
+    /// Handlers of appended rows are negative, so that they are distinguished from the rows in the
+    /// storage. The rows are not sorted, and they are all in the first partition.
+    fn scan_appended(
+        &self,
+        columns: &[ColumnCatalog],
+        col_idx: &[StorageColumnRef],
+        opts: &ScanOptions,
+    ) -> Vec<DataChunk> {
+        if matches!(opts.partition, Some((index, _)) if index != 0) {
+            return vec![];
+        }
+        // the range filter is on the leading sort key
+        let filter = match (&opts.filter, find_sort_key_id(columns).first()) {
+            (Some(range), Some(&key)) => Some((range, key)),
+            _ => None,
+        };
+        let mut chunks = vec![];
+        let mut offset = 0;
+        for chunk in &self.appended {
+            let positions = offset..offset + chunk.cardinality();
+            offset = positions.end;
+            let visibility = (positions.clone().enumerate())
+                .map(|(row, pos)| {
+                    !self.deleted_appended.contains(&pos)
+                        && filter.map_or(true, |(range, key)| {
+                            range.contains(&chunk.array_at(key).get(row))
+                        })
+                        && (opts.rows.as_ref()).map_or(true, |rows| {
+                            rows.binary_search(&(-(pos as i64) - 1)).is_ok()
+                        })
+                })
+                .collect_vec();
+            let columns = if col_idx.is_empty() {
+                DataChunk::no_column(chunk.cardinality())
+            } else {
+                (col_idx.iter())
+                    .map(|c| match c {
+                        StorageColumnRef::Idx(idx) => chunk.array_at(*idx as usize).clone(),
+                        StorageColumnRef::RowHandler => ArrayImpl::new_int64(
+                            positions.clone().map(|pos| -(pos as i64) - 1).collect(),
+                        ),
+                    })
+                    .collect()
+            };
+            chunks.push(columns.filter(&visibility));
+        }
+        chunks
+    }
+
+    /// Releases the snapshot, and applies the writes to a new storage transaction on the table.
+    /// Returns `None` if there is no write.
+    async fn prepare(self, table: &impl Table<Transaction = T>) -> StorageResult<Option<T>> {
+        self.snapshot.abort().await?;
+        let mut offset = 0;
+        let mut appended = vec![];
+        for chunk in self.appended {
+            let visibility = (offset..offset + chunk.cardinality())
+                .map(|pos| !self.deleted_appended.contains(&pos))
+                .collect_vec();
+            offset += chunk.cardinality();
+            let chunk = chunk.filter(&visibility);
+            if chunk.cardinality() != 0 {
+                appended.push(chunk);
+            }
+        }
+        if appended.is_empty() && self.deleted.is_empty() {
+            return Ok(None);
+        }
+
+        // rows are deleted with the table locked
+        let mut txn = if self.deleted.is_empty() {
+            table.write().await?
+        } else {
+            table.update().await?
+        };
+        for chunk in appended {
+            txn.append(chunk).await?;
+        }
+        for handler in self.deleted.values() {
+            txn.delete(handler).await?;
+        }
+        Ok(Some(txn))
+    }
+}
+
+impl<S: Storage> Storage for SessionTransaction<S> {
+    type Transaction = StatementTransaction<S>;
+    type Table = SessionTable<S>;
+
+    async fn create_table(
+        &self,
+        schema_id: SchemaId,
+        table_name: &str,
+        column_descs: &[ColumnCatalog],
+        constraints: &Constraints,
+    ) -> StorageResult<()> {
+        (self.storage)
+            .create_table(schema_id, table_name, column_descs, constraints)
+            .await
+    }
+
+    fn get_table(&self, table_id: TableRefId) -> StorageResult<SessionTable<S>> {
+        Ok(SessionTable {
+            table: self.storage.get_table(table_id)?,
+            txn: self.clone(),
+        })
+    }
+
+    async fn drop_table(&self, table_id: TableRefId) -> StorageResult<()> {
+        self.storage.drop_table(table_id).await
+    }
+
+    async fn truncate_table(&self, table_id: TableRefId) -> StorageResult<()> {
+        self.storage.truncate_table(table_id).await
+    }
+
+    async fn add_column(&self, table_id: TableRefId, column: &ColumnCatalog) -> StorageResult<()> {
+        self.storage.add_column(table_id, column).await
+    }
+
+    async fn drop_column(&self, table_id: TableRefId, column_id: ColumnId) -> StorageResult<()> {
+        self.storage.drop_column(table_id, column_id).await
+    }
+
+    async fn create_index(
+        &self,
+        index_name: &str,
+        table_id: TableRefId,
+        column_ids: &[ColumnId],
+    ) -> StorageResult<()> {
+        (self.storage)
+            .create_index(index_name, table_id, column_ids)
+            .await
+    }
+
+    async fn drop_index(&self, schema_id: SchemaId, index_id: IndexId) -> StorageResult<()> {
+        self.storage.drop_index(schema_id, index_id).await
+    }
+
+    fn get_index(
+        &self,
+        _schema_id: SchemaId,
+        _index_id: IndexId,
+    ) -> StorageResult<Arc<TableIndex>> {
+        // entries of indexes only contain committed rows, so they can't be used in a transaction
+        Err(StorageError::Unsupported("index scan in transaction").into())
+    }
+
+    async fn create_schema(&self, schema_name: &str) -> StorageResult<()> {
+        self.storage.create_schema(schema_name).await
+    }
+
+    async fn drop_schema(&self, schema_id: SchemaId) -> StorageResult<()> {
+        self.storage.drop_schema(schema_id).await
+    }
+
+    async fn create_view(
+        &self,
+        schema_id: SchemaId,
+        view_name: &str,
+        column_descs: &[ColumnCatalog],
+        definition: &ViewDefinition,
+        query: &RecExpr,
+        dependencies: &[TableRefId],
+    ) -> StorageResult<()> {
+        (self.storage)
+            .create_view(
+                schema_id,
+                view_name,
+                column_descs,
+                definition,
+                query,
+                dependencies,
+            )
+            .await
+    }
+
+    async fn drop_view(&self, table_id: TableRefId) -> StorageResult<()> {
+        self.storage.drop_view(table_id).await
+    }
+
+    fn as_disk(&self) -> Option<&SecondaryStorage> {
+        self.storage.as_disk()
+    }
+}
+
+/// A table accessed in a [`SessionTransaction`].
+pub struct SessionTable<S: Storage> {
+    table: S::Table,
+    txn: SessionTransaction<S>,
+}
+
+impl<S: Storage> Clone for SessionTable<S> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            txn: self.txn.clone(),
+        }
+    }
+}
+
+impl<S: Storage> SessionTable<S> {
+    /// Begins a statement transaction, taking the snapshot of the table on the first access.
+    async fn begin(&self) -> StorageResult<StatementTransaction<S>> {
+        let table_id = self.table.table_id();
+        let mut tables = self.txn.tables.lock().await;
+        if !tables.contains_key(&table_id) {
+            let snapshot = self.table.read_pinned().await?;
+            tables.insert(table_id, TableWrites::new(snapshot));
+        }
+        Ok(StatementTransaction::Session {
+            table: self.clone(),
+            appended: vec![],
+            deleted: vec![],
+        })
+    }
+}
+
+impl<S: Storage> Table for SessionTable<S> {
+    type Transaction = StatementTransaction<S>;
+
+    fn columns(&self) -> StorageResult<Arc<[ColumnCatalog]>> {
+        self.table.columns()
+    }
+
+    async fn write(&self) -> StorageResult<StatementTransaction<S>> {
+        self.begin().await
+    }
+
+    async fn read(&self) -> StorageResult<StatementTransaction<S>> {
+        self.begin().await
+    }
+
+    async fn read_as_of(&self, as_of: AsOf) -> StorageResult<StatementTransaction<S>> {
+        Ok(StatementTransaction::AsOf(
+            self.table.read_as_of(as_of).await?,
+        ))
+    }
+
+    async fn update(&self) -> StorageResult<StatementTransaction<S>> {
+        self.begin().await
+    }
+
+    async fn read_pinned(&self) -> StorageResult<StatementTransaction<S>> {
+        self.begin().await
+    }
+
+    fn table_id(&self) -> TableRefId {
+        self.table.table_id()
+    }
+
+    fn ordered_pk_ids(&self) -> Vec<ColumnId> {
+        self.table.ordered_pk_ids()
+    }
+
+    fn data_version(&self) -> u64 {
+        self.table.data_version()
+    }
+}
+
+/// A transaction of a statement in a [`SessionTransaction`].
+pub enum StatementTransaction<S: Storage> {
+    /// Reads the table in the session transaction. Writes are added to the session transaction
+    /// on commit, and discarded on abort.
+    Session {
+        table: SessionTable<S>,
+        appended: Vec<DataChunk>,
+        deleted: Vec<SessionRowHandler<<S::Transaction as Transaction>::RowHandlerType>>,
+    },
+    /// Reads a historical version of the table, which is not affected by the transaction.
+    AsOf(S::Transaction),
+}
+
+impl<S: Storage> Transaction for StatementTransaction<S> {
+    type TxnIteratorType = SessionTxnIterator<<S::Transaction as Transaction>::TxnIteratorType>;
+    type RowHandlerType = SessionRowHandler<<S::Transaction as Transaction>::RowHandlerType>;
+
+    async fn scan(
+        &self,
+        col_idx: &[StorageColumnRef],
+        opts: ScanOptions,
+    ) -> StorageResult<Self::TxnIteratorType> {
+        match self {
+            Self::Session { table, .. } => {
+                let columns = table.columns()?;
+                let tables = table.txn.tables.lock().await;
+                let writes = (tables.get(&table.table_id()))
+                    .expect("table should be accessed in the transaction");
+                writes.scan(&columns, col_idx, opts).await
+            }
+            Self::AsOf(txn) => Ok(SessionTxnIterator {
+                iter: Some(txn.scan(col_idx, opts).await?),
+                deleted: HashSet::new(),
+                handler_idx: None,
+                column_count: col_idx.len(),
+                appended: VecDeque::new(),
+            }),
+        }
+    }
+
+    async fn append(&mut self, columns: DataChunk) -> StorageResult<()> {
+        match self {
+            Self::Session { appended, .. } => appended.push(columns.compact()),
+            Self::AsOf(txn) => txn.append(columns).await?,
+        }
+        Ok(())
+    }
+
+    async fn delete(&mut self, id: &Self::RowHandlerType) -> StorageResult<()> {
+        match (self, id) {
+            (Self::Session { deleted, .. }, id) => deleted.push(id.clone()),
+            (Self::AsOf(txn), SessionRowHandler::Snapshot(_, handler)) => {
+                txn.delete(handler).await?
+            }
+            (Self::AsOf(_), SessionRowHandler::Appended(_)) => {
+                unreachable!("no row is appended in a historical version")
+            }
+        }
+        Ok(())
+    }
+
+    async fn commit(self) -> StorageResult<()> {
+        match self {
+            Self::Session {
+                table,
+                appended,
+                deleted,
+            } => {
+                let mut tables = table.txn.tables.lock().await;
+                let writes = (tables.get_mut(&table.table_id()))
+                    .expect("table should be accessed in the transaction");
+                writes.appended.extend(appended);
+                for handler in deleted {
+                    match handler {
+                        SessionRowHandler::Snapshot(id, handler) => {
+                            writes.deleted.entry(id).or_insert(handler);
+                        }
+                        SessionRowHandler::Appended(pos) => {
+                            writes.deleted_appended.insert(pos);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Self::AsOf(txn) => txn.commit().await,
+        }
+    }
+
+    async fn commit_all(txns: Vec<Self>) -> StorageResult<()> {
+        // writes are only added to the session transaction
+        for txn in txns {
+            txn.commit().await?;
+        }
+        Ok(())
+    }
+
+    async fn abort(self) -> StorageResult<()> {
+        match self {
+            Self::Session { .. } => Ok(()),
+            Self::AsOf(txn) => txn.abort().await,
+        }
+    }
+}
+
+/// A row in a [`SessionTransaction`].
+#[derive(Clone)]
+pub enum SessionRowHandler<H> {
+    /// A row in the snapshot of the storage transaction, with its value in the row handler column.
+    Snapshot(i64, H),
+    /// A row appended in the transaction, with its position in the appended rows.
+    Appended(usize),
+}
+
+impl<H: RowHandler> RowHandler for SessionRowHandler<H> {
+    fn from_column(column: &ArrayImpl, idx: usize) -> Self {
+        let ArrayImpl::Int64(array) = column else {
+            panic!("invalid column type")
+        };
+        let id = *array
+            .get(idx)
+            .expect("RowHandler column should not have null elements");
+        if id < 0 {
+            Self::Appended((-id - 1) as usize)
+        } else {
+            Self::Snapshot(id, H::from_column(column, idx))
+        }
+    }
+}
+
+/// An iterator over a table in a [`SessionTransaction`].
+///
+/// Rows in the storage are returned first, and then the rows appended in the transaction.
+pub struct SessionTxnIterator<I: TxnIterator> {
+    /// The iterator on the storage, or `None` if it is exhausted.
+    iter: Option<I>,
+    /// Values in the row handler column of the deleted rows in the storage.
+    deleted: HashSet<i64>,
+    /// The index of the row handler column, if there are deleted rows to filter out.
+    handler_idx: Option<usize>,
+    /// The number of columns to return. Columns after them are only used to filter rows.
+    column_count: usize,
+    /// Columns of the rows appended in the transaction.
+    appended: VecDeque<DataChunk>,
+}
+
+impl<I: TxnIterator> SessionTxnIterator<I> {
+    /// Removes deleted rows and the columns not requested from a chunk of the storage.
+    fn filter_deleted(&self, chunk: DataChunk) -> DataChunk {
+        let Some(handler_idx) = self.handler_idx else {
+            return chunk;
+        };
+        let ArrayImpl::Int64(handlers) = chunk.array_at(handler_idx) else {
+            panic!("invalid row handler column")
+        };
+        let visibility = (handlers.iter())
+            .map(|handler| !matches!(handler, Some(handler) if self.deleted.contains(handler)))
+            .collect_vec();
+        let chunk = chunk.filter(&visibility);
+        if self.column_count == 0 {
+            DataChunk::no_column(chunk.cardinality())
+        } else if self.column_count == chunk.column_count() {
+            chunk
+        } else {
+            chunk.arrays()[..self.column_count]
+                .iter()
+                .cloned()
+                .collect()
+        }
+    }
+}
+
+impl<I: TxnIterator> TxnIterator for SessionTxnIterator<I> {
+    async fn next_batch(
+        &mut self,
+        expected_size: Option<usize>,
+    ) -> StorageResult<Option<DataChunk>> {
+        if let Some(iter) = &mut self.iter {
+            if let Some(chunk) = iter.next_batch(expected_size).await? {
+                return Ok(Some(self.filter_deleted(chunk)));
+            }
+            self.iter = None;
+        }
+        Ok(self.appended.pop_front())
+    }
+}
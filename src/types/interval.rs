@@ -6,10 +6,12 @@ use std::num::ParseIntError;
 use std::ops::{Add, Neg, Sub};
 use std::str::FromStr;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// Interval type
-#[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize)]
+#[derive(
+    PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize, Deserialize,
+)]
 pub struct Interval {
     months: i32,
     days: i32,
@@ -17,6 +19,10 @@ pub struct Interval {
 }
 
 impl Interval {
+    pub const fn new(months: i32, days: i32, ms: i32) -> Self {
+        Interval { months, days, ms }
+    }
+
     pub const fn from_days(days: i32) -> Self {
         Interval {
             months: 0,
@@ -85,6 +91,10 @@ impl Interval {
         self.months
     }
 
+    pub const fn num_milliseconds(&self) -> i32 {
+        self.ms
+    }
+
     pub const fn is_zero(&self) -> bool {
         matches!(
             self,
@@ -0,0 +1,343 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use ref_cast::RefCast;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+/// A JSON value in binary format.
+///
+/// Values are encoded as a tag byte followed by the payload:
+///
+/// | tag | value  | payload                                                         |
+/// |-----|--------|-----------------------------------------------------------------|
+/// | 0   | null   |                                                                 |
+/// | 1   | false  |                                                                 |
+/// | 2   | true   |                                                                 |
+/// | 3   | number | `i64`                                                           |
+/// | 4   | number | `u64`                                                           |
+/// | 5   | number | `f64`                                                           |
+/// | 6   | string | length: `u32`, UTF-8 bytes                                      |
+/// | 7   | array  | size: `u32`, count: `u32`, values                               |
+/// | 8   | object | size: `u32`, count: `u32`, (key length: `u32`, key, value)...   |
+///
+/// Integers are little-endian. The size of arrays and objects is the number of bytes after the
+/// size field, so that nested values can be skipped without decoding them.
+/// Keys of objects are sorted, so equal values have the same encoding.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct Jsonb(Box<[u8]>);
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INT: u8 = 3;
+const UINT: u8 = 4;
+const FLOAT: u8 = 5;
+const STRING: u8 = 6;
+const ARRAY: u8 = 7;
+const OBJECT: u8 = 8;
+
+impl From<&Value> for Jsonb {
+    fn from(value: &Value) -> Self {
+        let mut buf = vec![];
+        encode(value, &mut buf);
+        Jsonb(buf.into())
+    }
+}
+
+fn encode(value: &Value, buf: &mut Vec<u8>) {
+    match value {
+        Value::Null => buf.push(NULL),
+        Value::Bool(false) => buf.push(FALSE),
+        Value::Bool(true) => buf.push(TRUE),
+        Value::Number(n) => {
+            if let Some(v) = n.as_i64() {
+                buf.push(INT);
+                buf.extend_from_slice(&v.to_le_bytes());
+            } else if let Some(v) = n.as_u64() {
+                buf.push(UINT);
+                buf.extend_from_slice(&v.to_le_bytes());
+            } else {
+                buf.push(FLOAT);
+                buf.extend_from_slice(&n.as_f64().unwrap().to_le_bytes());
+            }
+        }
+        Value::String(s) => {
+            buf.push(STRING);
+            encode_bytes(s.as_bytes(), buf);
+        }
+        Value::Array(array) => encode_container(ARRAY, array.len(), buf, |buf| {
+            for v in array {
+                encode(v, buf);
+            }
+        }),
+        Value::Object(object) => {
+            let mut entries = object.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|(k, _)| *k);
+            encode_container(OBJECT, entries.len(), buf, |buf| {
+                for (k, v) in entries {
+                    encode_bytes(k.as_bytes(), buf);
+                    encode(v, buf);
+                }
+            })
+        }
+    }
+}
+
+fn encode_bytes(bytes: &[u8], buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn encode_container(tag: u8, count: usize, buf: &mut Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) {
+    buf.push(tag);
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    buf.extend_from_slice(&(count as u32).to_le_bytes());
+    f(buf);
+    let size = (buf.len() - start - 4) as u32;
+    buf[start..start + 4].copy_from_slice(&size.to_le_bytes());
+}
+
+impl FromStr for Jsonb {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(&serde_json::from_str::<Value>(s)?))
+    }
+}
+
+impl Borrow<JsonbRef> for Jsonb {
+    fn borrow(&self) -> &JsonbRef {
+        self
+    }
+}
+
+impl AsRef<JsonbRef> for Jsonb {
+    fn as_ref(&self) -> &JsonbRef {
+        self
+    }
+}
+
+impl Deref for Jsonb {
+    type Target = JsonbRef;
+
+    fn deref(&self) -> &Self::Target {
+        JsonbRef::new(&self.0)
+    }
+}
+
+impl fmt::Debug for Jsonb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_ref())
+    }
+}
+
+impl fmt::Display for Jsonb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+/// A slice of a JSON value in binary format. See [`Jsonb`] for the format.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, RefCast, Hash)]
+pub struct JsonbRef([u8]);
+
+impl JsonbRef {
+    /// Creates a value from its encoding.
+    pub fn new(bytes: &[u8]) -> &Self {
+        // SAFETY: `&JsonbRef` and `&[u8]` have the same layout.
+        JsonbRef::ref_cast(bytes)
+    }
+
+    /// Returns the encoding of the value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns true if the value is JSON null.
+    pub fn is_null(&self) -> bool {
+        self.0[0] == NULL
+    }
+
+    /// Returns the string if the value is a JSON string.
+    pub fn as_str(&self) -> Option<&str> {
+        if self.0[0] != STRING {
+            return None;
+        }
+        let (bytes, _) = read_bytes(&self.0[1..]);
+        Some(std::str::from_utf8(bytes).expect("invalid utf-8 in jsonb"))
+    }
+
+    /// Returns the field of an object by key.
+    pub fn get_field(&self, key: &str) -> Option<&JsonbRef> {
+        if self.0[0] != OBJECT {
+            return None;
+        }
+        let (count, mut rest) = read_u32(&self.0[5..]);
+        for _ in 0..count {
+            let (k, r) = read_bytes(rest);
+            let len = value_len(r);
+            if k == key.as_bytes() {
+                return Some(JsonbRef::new(&r[..len]));
+            }
+            rest = &r[len..];
+        }
+        None
+    }
+
+    /// Returns the element of an array by index. Negative indexes count from the end.
+    pub fn get_index(&self, index: i64) -> Option<&JsonbRef> {
+        if self.0[0] != ARRAY {
+            return None;
+        }
+        let (count, mut rest) = read_u32(&self.0[5..]);
+        let index = if index < 0 {
+            count as i64 + index
+        } else {
+            index
+        };
+        if index < 0 || index >= count as i64 {
+            return None;
+        }
+        for _ in 0..index {
+            rest = &rest[value_len(rest)..];
+        }
+        Some(JsonbRef::new(&rest[..value_len(rest)]))
+    }
+
+    /// Decodes the value.
+    pub fn to_value(&self) -> Value {
+        decode(&self.0)
+    }
+}
+
+/// Reads a `u32` and returns the rest of the bytes.
+fn read_u32(bytes: &[u8]) -> (u32, &[u8]) {
+    let (v, rest) = bytes.split_at(4);
+    (u32::from_le_bytes(v.try_into().unwrap()), rest)
+}
+
+/// Reads bytes prefixed by the length and returns the rest of the bytes.
+fn read_bytes(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let (len, rest) = read_u32(bytes);
+    rest.split_at(len as usize)
+}
+
+/// Returns the length of the first value in the bytes.
+fn value_len(bytes: &[u8]) -> usize {
+    match bytes[0] {
+        NULL | FALSE | TRUE => 1,
+        INT | UINT | FLOAT => 9,
+        STRING | ARRAY | OBJECT => 5 + read_u32(&bytes[1..]).0 as usize,
+        tag => panic!("invalid jsonb tag: {tag}"),
+    }
+}
+
+fn decode(bytes: &[u8]) -> Value {
+    let payload = &bytes[1..];
+    let number = || -> [u8; 8] { payload[..8].try_into().unwrap() };
+    match bytes[0] {
+        NULL => Value::Null,
+        FALSE => Value::Bool(false),
+        TRUE => Value::Bool(true),
+        INT => Value::Number(i64::from_le_bytes(number()).into()),
+        UINT => Value::Number(u64::from_le_bytes(number()).into()),
+        FLOAT => Number::from_f64(f64::from_le_bytes(number())).map_or(Value::Null, Value::Number),
+        STRING => Value::String(JsonbRef::new(bytes).as_str().unwrap().into()),
+        ARRAY => {
+            let (count, mut rest) = read_u32(&payload[4..]);
+            let mut array = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let len = value_len(rest);
+                array.push(decode(&rest[..len]));
+                rest = &rest[len..];
+            }
+            Value::Array(array)
+        }
+        OBJECT => {
+            let (count, mut rest) = read_u32(&payload[4..]);
+            let mut object = Map::new();
+            for _ in 0..count {
+                let (key, r) = read_bytes(rest);
+                let len = value_len(r);
+                let key = std::str::from_utf8(key).expect("invalid utf-8 in jsonb");
+                object.insert(key.into(), decode(&r[..len]));
+                rest = &r[len..];
+            }
+            Value::Object(object)
+        }
+        tag => panic!("invalid jsonb tag: {tag}"),
+    }
+}
+
+impl ToOwned for JsonbRef {
+    type Owned = Jsonb;
+
+    fn to_owned(&self) -> Self::Owned {
+        Jsonb(self.0.into())
+    }
+}
+
+impl AsRef<JsonbRef> for JsonbRef {
+    fn as_ref(&self) -> &JsonbRef {
+        self
+    }
+}
+
+impl AsRef<[u8]> for JsonbRef {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for JsonbRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{self}'")
+    }
+}
+
+impl fmt::Display for JsonbRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let s = r#"{"b":[1,-2,3.5,"x",null,true],"a":{"c":18446744073709551615}}"#;
+        let json: Jsonb = s.parse().unwrap();
+        assert_eq!(
+            json.to_string(),
+            r#"{"a":{"c":18446744073709551615},"b":[1,-2,3.5,"x",null,true]}"#
+        );
+        assert_eq!(
+            json,
+            r#"{"a": {"c": 18446744073709551615}, "b": [1, -2, 3.5, "x", null, true]}"#
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn get() {
+        let json: Jsonb = r#"{"a":{"b":[1,"x"]},"c":null}"#.parse().unwrap();
+        let b = json.get_field("a").unwrap().get_field("b").unwrap();
+        assert_eq!(b.to_string(), r#"[1,"x"]"#);
+        assert_eq!(b.get_index(-1).unwrap().as_str(), Some("x"));
+        assert_eq!(b.get_index(0).unwrap().to_string(), "1");
+        assert!(b.get_index(2).is_none());
+        assert!(json.get_field("c").unwrap().is_null());
+        assert!(json.get_field("d").is_none());
+        assert!(json.get_index(0).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+
+use ref_cast::RefCast;
+use serde::{Deserialize, Serialize};
+
+use super::DataValue;
+
+/// A list of values.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize, Default)]
+pub struct List(Box<[DataValue]>);
+
+impl From<Vec<DataValue>> for List {
+    fn from(values: Vec<DataValue>) -> Self {
+        List(values.into())
+    }
+}
+
+impl From<&[DataValue]> for List {
+    fn from(values: &[DataValue]) -> Self {
+        List(values.into())
+    }
+}
+
+impl FromIterator<DataValue> for List {
+    fn from_iter<I: IntoIterator<Item = DataValue>>(iter: I) -> Self {
+        List(iter.into_iter().collect())
+    }
+}
+
+impl Borrow<ListRef> for List {
+    fn borrow(&self) -> &ListRef {
+        self
+    }
+}
+
+impl AsRef<ListRef> for List {
+    fn as_ref(&self) -> &ListRef {
+        self
+    }
+}
+
+impl Deref for List {
+    type Target = ListRef;
+
+    fn deref(&self) -> &Self::Target {
+        ListRef::new(&self.0)
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_ref())
+    }
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+/// A slice of a list.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, RefCast, Hash)]
+pub struct ListRef([DataValue]);
+
+impl ListRef {
+    pub fn new(values: &[DataValue]) -> &Self {
+        // SAFETY: `&ListRef` and `&[DataValue]` have the same layout.
+        ListRef::ref_cast(values)
+    }
+}
+
+impl ToOwned for ListRef {
+    type Owned = List;
+
+    fn to_owned(&self) -> Self::Owned {
+        List::from(&self.0)
+    }
+}
+
+impl AsRef<ListRef> for ListRef {
+    fn as_ref(&self) -> &ListRef {
+        self
+    }
+}
+
+impl Deref for ListRef {
+    type Target = [DataValue];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ListRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl fmt::Display for ListRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, v) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            match v {
+                DataValue::Null => write!(f, "NULL")?,
+                DataValue::String(s) => write!(f, "{s}")?,
+                v => write!(f, "{v}")?,
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_to_string() {
+        let list = List::from(vec![
+            DataValue::Int32(1),
+            DataValue::Null,
+            DataValue::String("a".into()),
+        ]);
+        assert_eq!(list.to_string(), "[1, NULL, a]");
+    }
+}
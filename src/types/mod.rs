@@ -12,6 +12,8 @@ use sqlparser::ast::TimezoneInfo;
 mod blob;
 mod date;
 mod interval;
+mod json;
+mod list;
 mod native;
 mod timestamp;
 mod value;
@@ -19,6 +21,8 @@ mod value;
 pub use self::blob::*;
 pub use self::date::*;
 pub use self::interval::*;
+pub use self::json::*;
+pub use self::list::*;
 pub use self::native::*;
 pub use self::timestamp::*;
 pub use self::value::*;
@@ -42,6 +46,9 @@ pub enum DataType {
     Interval,
     String,
     Blob,
+    // JSON value stored in binary format
+    Json,
+    List(Box<DataType>),
     Struct(Vec<DataType>),
 }
 
@@ -61,6 +68,14 @@ impl DataType {
         matches!(self, Self::Decimal(Some(_), _) | Self::Decimal(_, Some(_)))
     }
 
+    /// Returns the element type of the list.
+    pub fn as_list(&self) -> &DataType {
+        let Self::List(ty) = self else {
+            panic!("not a list: {self}")
+        };
+        ty
+    }
+
     /// Returns the inner types of the struct.
     pub fn as_struct(&self) -> &[DataType] {
         let Self::Struct(types) = self else {
@@ -88,6 +103,8 @@ impl DataType {
             (Interval, Interval | String) => Some(b.clone()),
             (String, String | Blob) => Some(b.clone()),
             (Blob, Blob) => Some(b.clone()),
+            (Json, Json) => Some(b.clone()),
+            (List(a), List(b)) => Some(List(Box::new(a.union(b)?))),
             (Struct(a), Struct(b)) => {
                 if a.len() != b.len() {
                     return None;
@@ -100,11 +117,24 @@ impl DataType {
             _ => None,
         }
     }
+
+    /// Returns true if values of this type can be implicitly cast to `target` on assignment,
+    /// e.g. when inserted into a column. Casts between numbers may still overflow at runtime.
+    pub fn can_assign_to(&self, target: &Self) -> bool {
+        use DataType::*;
+        match (self, target) {
+            (Null | String, _) => true,
+            (a, b) if a.is_number() && b.is_number() => true,
+            (Date, Timestamp) | (Timestamp, Date) | (Timestamp | TimestampTz, String) => true,
+            (List(a), List(b)) => a.can_assign_to(b),
+            _ => self.union(target).is_some(),
+        }
+    }
 }
 
 impl From<&crate::parser::DataType> for DataType {
     fn from(kind: &crate::parser::DataType) -> Self {
-        use sqlparser::ast::ExactNumberInfo;
+        use sqlparser::ast::{ArrayElemTypeDef, ExactNumberInfo};
 
         use crate::parser::DataType::*;
         match kind {
@@ -127,6 +157,10 @@ impl From<&crate::parser::DataType> for DataType {
             Timestamp(_, TimezoneInfo::None) => Self::Timestamp,
             Timestamp(_, TimezoneInfo::Tz) => Self::TimestampTz,
             Interval => Self::Interval,
+            JSON | JSONB => Self::Json,
+            Array(ArrayElemTypeDef::SquareBracket(ty) | ArrayElemTypeDef::AngleBracket(ty)) => {
+                Self::List(Box::new(ty.as_ref().into()))
+            }
             _ => todo!("not supported type: {:?}", kind),
         }
     }
@@ -143,6 +177,8 @@ impl std::fmt::Display for DataType {
             Self::Float64 => write!(f, "DOUBLE"),
             Self::String => write!(f, "STRING"),
             Self::Blob => write!(f, "BLOB"),
+            Self::Json => write!(f, "JSON"),
+            Self::List(ty) => write!(f, "{ty}[]"),
             Self::Bool => write!(f, "BOOLEAN"),
             Self::Decimal(p, s) => match (p, s) {
                 (None, None) => write!(f, "DECIMAL"),
@@ -182,12 +218,14 @@ impl FromStr for DataType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         use DataType::*;
         Ok(match s {
+            _ if s.ends_with("[]") => List(Box::new(s[..s.len() - 2].parse()?)),
             "INT" => Int32,
             "BIGINT" => Int64,
             // "REAL" => Float32,
             "DOUBLE" => Float64,
             "STRING" => String,
             "BLOB" => Blob,
+            "JSON" => Json,
             "BOOLEAN" => Bool,
             "DECIMAL" => Decimal(None, None),
             _ if s.starts_with("DECIMAL") => {
@@ -228,6 +266,8 @@ pub enum ConvertError {
     ParseInterval(String, #[source] ParseIntervalError),
     #[error("failed to convert string {0:?} to blob: {1}")]
     ParseBlob(String, #[source] ParseBlobError),
+    #[error("failed to convert string {0:?} to json: {1}")]
+    ParseJson(String, String),
     #[error("failed to convert {0} to decimal")]
     ToDecimalError(DataValue),
     #[error("failed to convert {0} from decimal {1}")]
@@ -240,6 +280,8 @@ pub enum ConvertError {
     Cast(String, &'static str),
     #[error("constant {0} overflows {1}")]
     Overflow(DataValue, DataType),
+    #[error("timestamp out of range")]
+    TimestampOutOfRange,
     #[error("no function {0}({1})")]
     NoUnaryOp(String, &'static str),
     #[error("no function {0}({1}, {2})")]
@@ -248,6 +290,14 @@ pub enum ConvertError {
     NoTernaryOp(String, &'static str, &'static str, &'static str),
     #[error("no cast {0} -> {1}")]
     NoCast(&'static str, DataType),
+    #[error("invalid argument {1} for function {0}")]
+    InvalidArgument(&'static str, DataValue),
+    #[error("invalid regular expression {0:?}: {1}")]
+    ParseRegex(String, #[source] regex::Error),
+    #[error("failed to convert from arrow: {0}")]
+    FromArrow(String),
+    #[error("failed to convert to arrow: {0}")]
+    ToArrow(String),
 }
 
 /// The physical index to the column from child plan.
@@ -275,3 +325,27 @@ impl FromStr for ColumnIndex {
         Ok(Self(num))
     }
 }
+
+/// The index of a parameter `$n` in a prepared statement, starting from 1.
+#[derive(Debug, Display, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+#[display("?{0}")]
+pub struct ParamIndex(pub u32);
+
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("parse parameter index error: {}")]
+pub enum ParseParamIndexError {
+    #[error("no leading '?'")]
+    NoLeadingSign,
+    #[error("invalid number: {0}")]
+    InvalidNum(#[from] std::num::ParseIntError),
+}
+
+impl FromStr for ParamIndex {
+    type Err = ParseParamIndexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let body = s.strip_prefix('?').ok_or(Self::Err::NoLeadingSign)?;
+        let num = body.parse()?;
+        Ok(Self(num))
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
 use std::fmt::Debug;
+use std::panic::RefUnwindSafe;
 
 use rust_decimal::Decimal;
 
@@ -10,7 +11,7 @@ use super::timestamp::{Timestamp, TimestampTz};
 use super::{F32, F64};
 
 pub trait NativeType:
-    PartialOrd + PartialEq + Debug + Copy + Send + Sync + Sized + Default + 'static
+    PartialOrd + PartialEq + Debug + Copy + Send + Sync + RefUnwindSafe + Sized + Default + 'static
 {
 }
 
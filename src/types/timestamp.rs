@@ -2,10 +2,13 @@
 
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use std::sync::OnceLock;
 
-use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, Timelike};
-use serde::Serialize;
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, NaiveDateTime, Offset, Timelike, Utc,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Date, Interval, UNIX_EPOCH_DAYS};
 
 /// unix timestamp counts from 1970-01-01 00:00:00,
 ///
@@ -14,10 +17,26 @@ use serde::Serialize;
 /// this is the difference between them
 const THIRTY_YEARS_MICROSECONDS: i64 = 946_684_800_000_000;
 
-/// global timezone
-static TIME_ZONE: OnceLock<FixedOffset> = OnceLock::new();
-
-const DEFALUT_TZ: i32 = 0;
+/// Parses a time zone given by `UTC`, hours east of UTC, or an offset like `+08:00`.
+pub fn parse_time_zone(s: &str) -> Option<FixedOffset> {
+    if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("gmt") {
+        return FixedOffset::east_opt(0);
+    }
+    if let Ok(hours) = s.parse::<i32>() {
+        return FixedOffset::east_opt(hours.checked_mul(3600)?);
+    }
+    let (sign, offset) = match (s.strip_prefix('+'), s.strip_prefix('-')) {
+        (Some(offset), _) => (1, offset),
+        (_, Some(offset)) => (-1, offset),
+        _ => return None,
+    };
+    let (hours, minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+    let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+    if hours > 15 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60) as i32)
+}
 
 /// input format without timezone
 const TIMESTAMP_FORMATS: [&str; 3] = [
@@ -34,7 +53,9 @@ const TIMESTAMP_TZ_FORMATS: [&str; 5] = [
     "%Y-%m-%d %H:%M:%S BC %z", // 1991-01-08 04:05:06 BC +08:00
 ];
 
-#[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize)]
+#[derive(
+    PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize, Deserialize,
+)]
 pub struct Timestamp(i64);
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
@@ -50,9 +71,92 @@ impl Timestamp {
         Self(value)
     }
 
+    /// Creates a timestamp from the number of microseconds since 1970-01-01 00:00:00.
+    pub const fn from_unix_micros(micros: i64) -> Self {
+        Self(micros + THIRTY_YEARS_MICROSECONDS)
+    }
+
+    /// Returns the number of microseconds since 1970-01-01 00:00:00.
+    pub const fn to_unix_micros(&self) -> i64 {
+        self.0 - THIRTY_YEARS_MICROSECONDS
+    }
+
     pub fn get_inner(&self) -> i64 {
         self.0
     }
+
+    fn to_naive(self) -> Option<NaiveDateTime> {
+        DateTime::from_timestamp_micros(self.0 - THIRTY_YEARS_MICROSECONDS).map(|dt| dt.naive_utc())
+    }
+
+    fn from_naive(dt: NaiveDateTime) -> Self {
+        Self(dt.and_utc().timestamp_micros() + THIRTY_YEARS_MICROSECONDS)
+    }
+
+    pub fn year(&self) -> Option<i32> {
+        self.to_naive().map(|dt| dt.year())
+    }
+
+    pub fn month(&self) -> Option<i32> {
+        self.to_naive().map(|dt| dt.month() as i32)
+    }
+
+    pub fn day(&self) -> Option<i32> {
+        self.to_naive().map(|dt| dt.day() as i32)
+    }
+
+    pub fn hour(&self) -> Option<i32> {
+        self.to_naive().map(|dt| dt.hour() as i32)
+    }
+
+    pub fn minute(&self) -> Option<i32> {
+        self.to_naive().map(|dt| dt.minute() as i32)
+    }
+
+    pub fn second(&self) -> Option<i32> {
+        self.to_naive().map(|dt| dt.second() as i32)
+    }
+
+    /// Returns the date part of the timestamp.
+    pub fn date(&self) -> Option<Date> {
+        self.to_naive()
+            .map(|dt| Date::new(dt.num_days_from_ce() - UNIX_EPOCH_DAYS))
+    }
+
+    /// Adds an interval to the timestamp. Returns `None` if the result is out of range.
+    pub fn checked_add(self, rhs: Interval) -> Option<Self> {
+        // like postgres, add months first, then days and time.
+        // the day is clamped to the end of month, e.g. 1970-01-31 + 1 month = 1970-02-28
+        let dt = self.to_naive()?;
+        let months = rhs.num_months();
+        let dt = if months >= 0 {
+            dt.checked_add_months(Months::new(months as u32))
+        } else {
+            dt.checked_sub_months(Months::new(months.unsigned_abs()))
+        }?;
+        let days = rhs.days();
+        let dt = if days >= 0 {
+            dt.checked_add_days(Days::new(days as u64))
+        } else {
+            dt.checked_sub_days(Days::new(days.unsigned_abs() as u64))
+        }?;
+        let micros = (rhs.num_milliseconds() as i64).checked_mul(1000)?;
+        let ts = Self(Self::from_naive(dt).0.checked_add(micros)?);
+        ts.to_naive().map(|_| ts)
+    }
+
+    /// Subtracts an interval from the timestamp. Returns `None` if the result is out of range.
+    pub fn checked_sub(self, rhs: Interval) -> Option<Self> {
+        self.checked_add(-rhs)
+    }
+}
+
+impl From<Date> for Timestamp {
+    fn from(date: Date) -> Self {
+        let date =
+            NaiveDate::from_num_days_from_ce_opt(date.get_inner() + UNIX_EPOCH_DAYS).unwrap();
+        Self::from_naive(date.and_hms_opt(0, 0, 0).unwrap())
+    }
 }
 
 impl Display for Timestamp {
@@ -78,7 +182,9 @@ impl FromStr for Timestamp {
     }
 }
 
-#[derive(PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize)]
+#[derive(
+    PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Clone, Default, Hash, Serialize, Deserialize,
+)]
 pub struct TimestampTz(i64);
 
 impl TimestampTz {
@@ -86,31 +192,25 @@ impl TimestampTz {
         Self(value)
     }
 
-    pub fn get_inner(&self) -> i64 {
-        self.0
+    /// Creates a timestamp from the number of microseconds since 1970-01-01 00:00:00 UTC.
+    pub const fn from_unix_micros(micros: i64) -> Self {
+        Self(micros + THIRTY_YEARS_MICROSECONDS)
     }
-}
 
-impl Display for TimestampTz {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let dt = DateTime::from_timestamp_millis((self.0 - THIRTY_YEARS_MICROSECONDS) / 1000)
-            .ok_or(std::fmt::Error)?
-            .naive_utc();
-        let sys_tz = TIME_ZONE.get_or_init(|| FixedOffset::east_opt(DEFALUT_TZ * 3600).unwrap());
-        let dt = dt + *sys_tz;
-        naive_sys_fmt(&dt, f)?;
-        write!(f, " {}", sys_tz)
+    /// Returns the number of microseconds since 1970-01-01 00:00:00 UTC.
+    pub const fn to_unix_micros(&self) -> i64 {
+        self.0 - THIRTY_YEARS_MICROSECONDS
     }
-}
 
-impl FromStr for TimestampTz {
-    type Err = ParseTimestampError;
+    pub fn get_inner(&self) -> i64 {
+        self.0
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let sys_tz = TIME_ZONE.get_or_init(|| FixedOffset::east_opt(DEFALUT_TZ * 3600).unwrap());
+    /// Parses a timestamp in text. Timestamps without an offset are in the time zone `tz`.
+    pub fn parse_in(s: &str, tz: FixedOffset) -> Result<Self, ParseTimestampError> {
         for fmt in TIMESTAMP_FORMATS {
             if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
-                let dt = dt - *sys_tz;
+                let dt = dt - tz;
                 return naive_utc_to_timestamp(&dt, s.contains("BC")).map(Self);
             }
         }
@@ -122,6 +222,41 @@ impl FromStr for TimestampTz {
         }
         Err(ParseTimestampError::InvalidString(s.to_string()))
     }
+
+    /// Returns a wrapper to display the timestamp in the time zone `tz`.
+    pub fn display_in(&self, tz: FixedOffset) -> impl Display {
+        TimestampTzDisplay(*self, tz)
+    }
+}
+
+/// Displays a timestamp in a time zone.
+struct TimestampTzDisplay(TimestampTz, FixedOffset);
+
+impl Display for TimestampTzDisplay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Self(TimestampTz(v), tz) = *self;
+        let dt = DateTime::from_timestamp_millis((v - THIRTY_YEARS_MICROSECONDS) / 1000)
+            .ok_or(std::fmt::Error)?
+            .naive_utc();
+        naive_sys_fmt(&(dt + tz), f)?;
+        write!(f, " {}", tz)
+    }
+}
+
+/// Displays the timestamp in UTC.
+impl Display for TimestampTz {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.display_in(Utc.fix()).fmt(f)
+    }
+}
+
+/// Parses a timestamp, which is in UTC if no offset is given.
+impl FromStr for TimestampTz {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_in(s, Utc.fix())
+    }
 }
 
 fn naive_sys_fmt(dt: &NaiveDateTime, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -152,3 +287,33 @@ fn naive_utc_to_timestamp(dt: &NaiveDateTime, is_bc: bool) -> Result<i64, ParseT
     }
     Ok(dt.and_utc().timestamp_micros() + THIRTY_YEARS_MICROSECONDS)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_zone() {
+        let tz = |secs| FixedOffset::east_opt(secs);
+        assert_eq!(parse_time_zone("UTC"), tz(0));
+        assert_eq!(parse_time_zone("8"), tz(8 * 3600));
+        assert_eq!(parse_time_zone("-5"), tz(-5 * 3600));
+        assert_eq!(parse_time_zone("+08:00"), tz(8 * 3600));
+        assert_eq!(parse_time_zone("-03:30"), tz(-(3 * 3600 + 30 * 60)));
+        assert_eq!(parse_time_zone("+09"), tz(9 * 3600));
+        assert_eq!(parse_time_zone("Asia/Shanghai"), None);
+        assert_eq!(parse_time_zone("+25:00"), None);
+    }
+
+    #[test]
+    fn test_timestamp_tz_in_time_zone() {
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let ts = TimestampTz::parse_in("2024-01-01 08:00:00", tz).unwrap();
+        assert_eq!(ts, "2024-01-01 00:00:00 +00:00".parse().unwrap());
+        assert_eq!(ts.to_string(), "2024-01-01 00:00:00 +00:00");
+        assert_eq!(ts.display_in(tz).to_string(), "2024-01-01 08:00:00 +08:00");
+        // the offset in text takes precedence
+        let ts = TimestampTz::parse_in("2024-01-01 08:00:00 +00:00", tz).unwrap();
+        assert_eq!(ts.display_in(tz).to_string(), "2024-01-01 16:00:00 +08:00");
+    }
+}
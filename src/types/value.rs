@@ -1,17 +1,18 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+use chrono::FixedOffset;
 use num_traits::ToPrimitive;
 use ordered_float::OrderedFloat;
 use parse_display::Display;
 use rust_decimal::Decimal;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use super::*;
 use crate::array::ArrayImpl;
 use crate::for_all_variants_without_null;
 
 /// Primitive SQL value.
-#[derive(Debug, Display, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[derive(Debug, Display, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum DataValue {
     // NOTE: Null comes first.
     // => NULL is less than any non-NULL values
@@ -32,6 +33,8 @@ pub enum DataValue {
     #[display("{0}")]
     Blob(Blob),
     #[display("{0}")]
+    Json(Jsonb),
+    #[display("{0}")]
     Decimal(Decimal),
     #[display("{0}")]
     Date(Date),
@@ -41,6 +44,8 @@ pub enum DataValue {
     TimestampTz(TimestampTz),
     #[display("{0}")]
     Interval(Interval),
+    #[display("{0}")]
+    List(List),
 }
 
 /// memory table row type
@@ -95,6 +100,18 @@ impl DataValue {
         matches!(self, Self::Null)
     }
 
+    /// Returns the estimated number of bytes used by the value, including its heap allocation.
+    pub fn estimated_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match self {
+                Self::String(s) => s.len(),
+                Self::Blob(b) => b.len(),
+                Self::Json(j) => j.as_bytes().len(),
+                Self::List(l) => l.iter().map(|v| v.estimated_size()).sum(),
+                _ => 0,
+            }
+    }
+
     /// Whether the value is divisible by another.
     pub fn is_divisible_by(&self, other: &DataValue) -> bool {
         use DataValue::*;
@@ -118,11 +135,13 @@ impl DataValue {
             Self::Float64(v) => v.0.is_sign_positive(),
             Self::String(_) => false,
             Self::Blob(_) => false,
+            Self::Json(_) => false,
             Self::Decimal(v) => v.is_sign_positive(),
             Self::Date(_) => false,
             Self::Timestamp(_) => false,
             Self::TimestampTz(_) => false,
             Self::Interval(v) => v.is_positive(),
+            Self::List(_) => false,
         }
     }
 
@@ -137,11 +156,13 @@ impl DataValue {
             Self::Float64(v) => v.0 == 0.0,
             Self::String(_) => false,
             Self::Blob(_) => false,
+            Self::Json(_) => false,
             Self::Decimal(v) => v.is_zero(),
             Self::Date(_) => false,
             Self::Timestamp(_) => false,
             Self::TimestampTz(_) => false,
             Self::Interval(v) => v.is_zero(),
+            Self::List(_) => false,
         }
     }
 
@@ -156,11 +177,17 @@ impl DataValue {
             Self::Float64(_) => DataType::Float64,
             Self::String(_) => DataType::String,
             Self::Blob(_) => DataType::Blob,
+            Self::Json(_) => DataType::Json,
             Self::Decimal(_) => DataType::Decimal(None, None),
             Self::Date(_) => DataType::Date,
             Self::Timestamp(_) => DataType::Timestamp,
             Self::TimestampTz(_) => DataType::TimestampTz,
             Self::Interval(_) => DataType::Interval,
+            Self::List(v) => DataType::List(Box::new(
+                (v.iter().map(|v| v.data_type()))
+                    .find(|t| !t.is_null())
+                    .unwrap_or(DataType::Null),
+            )),
         }
     }
 
@@ -183,6 +210,8 @@ impl DataValue {
             &Self::Interval(_) => return Err(cast_err()),
             Self::String(s) => s.parse::<usize>().map_err(|_| cast_err())?,
             Self::Blob(_) => return Err(cast_err()),
+            Self::Json(_) => return Err(cast_err()),
+            Self::List(_) => return Err(cast_err()),
         }))
     }
 
@@ -190,6 +219,12 @@ impl DataValue {
     pub fn cast(&self, ty: &DataType) -> Result<Self, ConvertError> {
         Ok(ArrayImpl::from(self).cast(ty)?.get(0))
     }
+
+    /// Cast the value to another type. Timestamps with time zone are converted from or to strings
+    /// in the time zone `tz`.
+    pub fn cast_in_time_zone(&self, ty: &DataType, tz: FixedOffset) -> Result<Self, ConvertError> {
+        Ok(ArrayImpl::from(self).cast_in_time_zone(ty, tz)?.get(0))
+    }
 }
 
 /// Implement aggregation functions.
@@ -0,0 +1,208 @@
+// Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
+
+//! Runtime metrics of the database, exported in the Prometheus text format.
+//!
+//! Metrics are process-wide counters and histograms updated by executors and storage.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// A monotonically increasing counter.
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Counter {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    fn encode(&self, out: &mut String) {
+        writeln!(out, "# HELP {} {}", self.name, self.help).unwrap();
+        writeln!(out, "# TYPE {} counter", self.name).unwrap();
+        writeln!(out, "{} {}", self.name, self.get()).unwrap();
+    }
+}
+
+/// Upper bounds of buckets of histograms in seconds.
+const BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0, 10.0,
+];
+
+/// A histogram of durations.
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    /// The number of observations in each bucket, not including smaller buckets.
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        Histogram {
+            name,
+            help,
+            buckets: [ZERO; BUCKETS.len()],
+            count: ZERO,
+            sum_micros: ZERO,
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        (self.sum_micros).fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn encode(&self, out: &mut String) {
+        writeln!(out, "# HELP {} {}", self.name, self.help).unwrap();
+        writeln!(out, "# TYPE {} histogram", self.name).unwrap();
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(&self.buckets) {
+            cumulative += count.load(Ordering::Relaxed);
+            writeln!(out, "{}_bucket{{le=\"{bound}\"}} {cumulative}", self.name).unwrap();
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        writeln!(out, "{}_bucket{{le=\"+Inf\"}} {count}", self.name).unwrap();
+        writeln!(out, "{}_sum {sum}", self.name).unwrap();
+        writeln!(out, "{}_count {count}", self.name).unwrap();
+    }
+}
+
+pub static QUERIES: Counter = Counter::new(
+    "risinglight_queries_total",
+    "Number of statements executed.",
+);
+pub static QUERY_ERRORS: Counter = Counter::new(
+    "risinglight_query_errors_total",
+    "Number of statements failed in execution.",
+);
+pub static QUERY_DURATION: Histogram = Histogram::new(
+    "risinglight_query_duration_seconds",
+    "Time to execute a statement.",
+);
+pub static EXECUTORS: Counter = Counter::new(
+    "risinglight_executors_total",
+    "Number of executors started.",
+);
+pub static EXECUTOR_ROWS: Counter = Counter::new(
+    "risinglight_executor_output_rows_total",
+    "Number of rows output by executors.",
+);
+pub static EXECUTOR_CHUNKS: Counter = Counter::new(
+    "risinglight_executor_output_chunks_total",
+    "Number of chunks output by executors.",
+);
+pub static SPILL_FILES: Counter = Counter::new(
+    "risinglight_spill_files_total",
+    "Number of temporary files created by executors spilling to disk.",
+);
+pub static FLUSHES: Counter = Counter::new(
+    "risinglight_storage_flushes_total",
+    "Number of RowSets flushed by transactions.",
+);
+pub static FLUSH_DURATION: Histogram = Histogram::new(
+    "risinglight_storage_flush_duration_seconds",
+    "Time to flush a RowSet.",
+);
+pub static COMPACTIONS: Counter = Counter::new(
+    "risinglight_storage_compactions_total",
+    "Number of compactions.",
+);
+pub static COMPACTED_ROWSETS: Counter = Counter::new(
+    "risinglight_storage_compacted_rowsets_total",
+    "Number of RowSets merged by compactions.",
+);
+pub static COMPACTION_DURATION: Histogram = Histogram::new(
+    "risinglight_storage_compaction_duration_seconds",
+    "Time to compact RowSets of a table.",
+);
+pub static BLOCK_CACHE_HITS: Counter = Counter::new(
+    "risinglight_block_cache_hits_total",
+    "Number of blocks found in the block cache.",
+);
+pub static BLOCK_CACHE_MISSES: Counter = Counter::new(
+    "risinglight_block_cache_misses_total",
+    "Number of blocks read from files on block cache misses.",
+);
+
+/// Returns all metrics in the Prometheus text format.
+pub fn gather() -> String {
+    let mut out = String::new();
+    let counters = [
+        &QUERIES,
+        &QUERY_ERRORS,
+        &EXECUTORS,
+        &EXECUTOR_ROWS,
+        &EXECUTOR_CHUNKS,
+        &SPILL_FILES,
+        &FLUSHES,
+        &COMPACTIONS,
+        &COMPACTED_ROWSETS,
+        &BLOCK_CACHE_HITS,
+        &BLOCK_CACHE_MISSES,
+    ];
+    for counter in counters {
+        counter.encode(&mut out);
+    }
+    for histogram in [&QUERY_DURATION, &FLUSH_DURATION, &COMPACTION_DURATION] {
+        histogram.encode(&mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let counter = Counter::new("c_total", "A counter.");
+        counter.inc_by(3);
+        let mut out = String::new();
+        counter.encode(&mut out);
+        assert_eq!(
+            out,
+            "# HELP c_total A counter.\n# TYPE c_total counter\nc_total 3\n"
+        );
+
+        let histogram = Histogram::new("h_seconds", "A histogram.");
+        histogram.observe(Duration::from_millis(2));
+        histogram.observe(Duration::from_secs(60));
+        let mut out = String::new();
+        histogram.encode(&mut out);
+        assert!(out.contains("h_seconds_bucket{le=\"0.001\"} 0\n"));
+        assert!(out.contains("h_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(out.contains("h_seconds_bucket{le=\"10\"} 1\n"));
+        assert!(out.contains("h_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(out.contains("h_seconds_sum 60.002\n"));
+        assert!(out.contains("h_seconds_count 2\n"));
+    }
+}
@@ -1,4 +1,6 @@
 // Copyright 2024 RisingLight Project Authors. Licensed under Apache-2.0.
 
+pub mod hyperloglog;
+pub mod metrics;
 pub mod time;
 pub mod timed;
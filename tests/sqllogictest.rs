@@ -8,7 +8,7 @@ use std::path::Path;
 use libtest_mimic::{Arguments, Trial};
 use risinglight::array::*;
 use risinglight::storage::SecondaryStorageOptions;
-use risinglight::{Database, Error};
+use risinglight::{Database, Error, Session};
 use sqllogictest::{DBOutput, DefaultColumnType};
 use tokio::runtime::Runtime;
 
@@ -80,8 +80,13 @@ async fn test(filename: impl AsRef<Path>, engine: Engine) -> Result<()> {
         Engine::Mem => Database::new_in_memory(),
     };
 
-    let db = DatabaseWrapper(db);
-    let mut tester = sqllogictest::Runner::new(|| async { Ok(&db) });
+    // each connection has its own session
+    let mut tester = sqllogictest::Runner::new(|| async {
+        Ok(DatabaseWrapper {
+            db: &db,
+            session: Session::default(),
+        })
+    });
 
     // Uncomment the following lines to update the test files.
     // if engine == Engine::Disk {
@@ -91,15 +96,18 @@ async fn test(filename: impl AsRef<Path>, engine: Engine) -> Result<()> {
     // }
 
     tester.run_file_async(filename).await?;
-    db.0.shutdown().await?;
+    db.shutdown().await?;
     Ok(())
 }
 
-/// New type to implement sqllogictest driver trait for risinglight.
-struct DatabaseWrapper(Database);
+/// A connection to implement sqllogictest driver trait for risinglight.
+struct DatabaseWrapper<'a> {
+    db: &'a Database,
+    session: Session,
+}
 
 #[async_trait::async_trait]
-impl sqllogictest::AsyncDB for &DatabaseWrapper {
+impl sqllogictest::AsyncDB for DatabaseWrapper<'_> {
     type ColumnType = DefaultColumnType;
 
     type Error = Error;
@@ -116,7 +124,7 @@ impl sqllogictest::AsyncDB for &DatabaseWrapper {
                 || lower_sql.starts_with("describe")
         };
 
-        let chunks = self.0.run(sql).await?;
+        let chunks = self.db.run_in_session(&self.session, sql).await?;
         if chunks.is_empty() || chunks.iter().all(|c| c.data_chunks().is_empty()) {
             if is_query_sql {
                 return Ok(DBOutput::Rows {